@@ -0,0 +1,161 @@
+//! Hot-path benchmarks: header parsing, payload parsers, frame analysis and
+//! reassembly. Run with `cargo bench --features test-utils`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rtpar::{
+    codecs::{
+        av1::parse_av1_obu_elements, avc::parse_avc_payload_header,
+        hevc::parse_hevc_payload_header, split_annex_b, vp9::Vp9PayloadDesc, Codec,
+    },
+    test_utils::{
+        av1, avc, drop_packets, hevc, reorder_within, vp9, RtpPacketBuilder, StreamBuilder,
+    },
+    FrameAnalyzer, FrameReassembler, RtpPacket,
+};
+
+const FRAME_LEN: usize = 30_000;
+const FRAMES: usize = 10;
+const PACKET_SIZES: [usize; 3] = [200, 500, 1200];
+
+fn frame_bytes(first: u8) -> Vec<u8> {
+    let mut frame = vec![first];
+    frame.extend((1..FRAME_LEN).map(|i| (i % 251) as u8 | 0x01));
+    frame
+}
+
+// One stream of FRAMES frames for `codec`, fragmented to `packet_size`.
+fn stream(codec: Codec, packet_size: usize) -> Vec<Vec<u8>> {
+    let mut builder = StreamBuilder::new(0x1234);
+    for i in 0..FRAMES {
+        let payloads = match codec {
+            Codec::Avc => avc::fu_a(&frame_bytes(0x65), packet_size),
+            Codec::Hevc => {
+                let mut nal = vec![0x26, 0x01];
+                nal.extend_from_slice(&frame_bytes(0xAF)[1..]);
+                hevc::fu(&nal, packet_size)
+            }
+            Codec::Vp9 => vp9::fragments(&frame_bytes(0x82), i as u16, packet_size),
+            _ => av1::fragments(&frame_bytes(0x32), packet_size, i == 0),
+        };
+        builder = builder.frame(&payloads);
+    }
+    builder.build()
+}
+
+fn bench_rtp_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rtp_parse");
+    for size in PACKET_SIZES {
+        let payload = vec![0xAB; size];
+        let plain = RtpPacketBuilder::new().payload(&payload).build();
+        let extended = RtpPacketBuilder::new()
+            .csrc(1)
+            .csrc(2)
+            .extension(0xBEDE, &[0x10, 0xFF, 0, 0])
+            .payload(&payload)
+            .build();
+        group.throughput(Throughput::Bytes(plain.len() as u64));
+        group.bench_with_input(BenchmarkId::new("plain", size), &plain, |b, p| {
+            b.iter(|| RtpPacket::parse(black_box(p)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("csrc_ext", size), &extended, |b, p| {
+            b.iter(|| RtpPacket::parse(black_box(p)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_payload_parsers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("payload_parse");
+    let fu_a = avc::fu_a(&frame_bytes(0x65), 1200).remove(0);
+    group.bench_function("avc_fu_a", |b| {
+        b.iter(|| parse_avc_payload_header(black_box(&fu_a)))
+    });
+    let stap_a = avc::stap_a(&[&[0x67, 0x42, 0x00], &[0x68, 0xCE], &[0x65, 0x88]]);
+    group.bench_function("avc_stap_a", |b| {
+        b.iter(|| parse_avc_payload_header(black_box(&stap_a)))
+    });
+    let hevc_fu = hevc::fu(&[0x26, 0x01, 0xAA, 0xBB, 0xCC], 2).remove(0);
+    group.bench_function("hevc_fu", |b| {
+        b.iter(|| parse_hevc_payload_header(black_box(&hevc_fu)))
+    });
+    let vp9_desc = vp9::fragments(&frame_bytes(0x82), 300, 1200).remove(0);
+    group.bench_function("vp9_descriptor", |b| {
+        b.iter(|| Vp9PayloadDesc::parse(black_box(&vp9_desc)))
+    });
+    let av1_agg = av1::fragments(&frame_bytes(0x32), 1200, true).remove(0);
+    group.bench_function("av1_elements", |b| {
+        b.iter(|| parse_av1_obu_elements(black_box(&av1_agg)))
+    });
+    let mut annex_b = Vec::new();
+    for _ in 0..8 {
+        annex_b.extend_from_slice(&[0, 0, 0, 1]);
+        annex_b.extend_from_slice(&frame_bytes(0x65)[..FRAME_LEN / 8]);
+    }
+    group.bench_function("split_annex_b", |b| {
+        b.iter(|| split_annex_b(black_box(&annex_b)))
+    });
+    group.finish();
+}
+
+fn bench_analyze(c: &mut Criterion) {
+    let mut group = c.benchmark_group("analyze");
+    for codec in [Codec::Avc, Codec::Hevc, Codec::Vp9, Codec::Av1] {
+        let packets = stream(codec, 1200);
+        group.throughput(Throughput::Elements(packets.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{:?}", codec)),
+            &packets,
+            |b, packets| {
+                b.iter(|| {
+                    let mut a = FrameAnalyzer::new();
+                    a.set_codec(codec);
+                    for p in packets {
+                        black_box(a.analyze(&RtpPacket::parse(p).unwrap()));
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn reassemble(codec: Codec, packets: &[Vec<u8>]) -> usize {
+    let mut r = FrameReassembler::builder().codec(codec).build().unwrap();
+    packets
+        .iter()
+        .filter_map(|p| r.push_packet(&RtpPacket::parse(p).unwrap()))
+        .count()
+}
+
+fn bench_reassembly(c: &mut Criterion) {
+    let mut group = c.benchmark_group("reassemble");
+    for codec in [Codec::Avc, Codec::Hevc, Codec::Vp9, Codec::Av1] {
+        for size in PACKET_SIZES {
+            let in_order = stream(codec, size);
+            let per_frame = in_order.len() / FRAMES;
+            let lossy_idx: Vec<usize> = (0..FRAMES).map(|f| f * per_frame + 1).collect();
+            let patterns = [
+                ("in_order", in_order.clone()),
+                ("reorder4", reorder_within(&in_order, 4)),
+                ("loss", drop_packets(&in_order, &lossy_idx)),
+            ];
+            group.throughput(Throughput::Bytes((FRAMES * FRAME_LEN) as u64));
+            for (pattern, packets) in &patterns {
+                let id = BenchmarkId::new(format!("{:?}/{}", codec, pattern), size);
+                group.bench_with_input(id, packets, |b, packets| {
+                    b.iter(|| reassemble(codec, black_box(packets)))
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_rtp_parse,
+    bench_payload_parsers,
+    bench_analyze,
+    bench_reassembly
+);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::extensions::AbsCaptureTime;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(time) = AbsCaptureTime::parse(data) {
+        assert_eq!(
+            time.estimated_capture_clock_offset.is_some(),
+            data.len() >= 16
+        );
+        let _ = time.sender_capture_nanos();
+    }
+});
@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::{
+    frame::{AncDepacketizer, AncPayload},
+    RtpPacket,
+};
+
+fuzz_target!(|payloads: Vec<(bool, Vec<u8>)>| {
+    let mut depacketizer = AncDepacketizer::new();
+    for (seq, (marker, payload)) in payloads.iter().enumerate() {
+        if let Ok(anc) = AncPayload::parse(payload) {
+            assert!(anc.packets.len() <= 255);
+            for packet in &anc.packets {
+                let _ = packet.timecode();
+            }
+        }
+        let mut bytes = vec![0x80, if *marker { 0x80 | 96 } else { 96 }];
+        bytes.extend_from_slice(&(seq as u16).to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        bytes.extend_from_slice(payload);
+        let pkt = RtpPacket::parse(&bytes).expect("well-formed header");
+        let _ = depacketizer.push_packet(&pkt);
+    }
+});
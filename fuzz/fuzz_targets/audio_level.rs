@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::extensions::{AudioLevel, CsrcAudioLevels};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(level) = AudioLevel::parse(data) {
+        assert_eq!(AudioLevel::parse(&[level.to_byte()]), Ok(level));
+    }
+    if let Ok(levels) = CsrcAudioLevels::parse(data) {
+        assert_eq!(levels.levels().count(), levels.len());
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::codecs::av1::{parse_av1_obu_elements, parse_av1_payload_header, split_obus};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((_hdr, off)) = parse_av1_payload_header(data) {
+        assert!(off <= data.len());
+    }
+    if let Ok((_hdr, elements)) = parse_av1_obu_elements(data) {
+        assert!(elements.iter().map(|e| e.len()).sum::<usize>() < data.len());
+    }
+    let _ = split_obus(data);
+});
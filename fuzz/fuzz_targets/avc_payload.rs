@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::codecs::avc::parse_avc_payload_header;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((_kind, off)) = parse_avc_payload_header(data) {
+        assert!(off <= data.len());
+    }
+});
@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::{
+    codec::{H263Depacketizer, H263PayloadHeader, H263PictureHeader},
+    RtpPacket,
+};
+
+fuzz_target!(|payloads: Vec<(bool, Vec<u8>)>| {
+    let mut depacketizer = H263Depacketizer::new();
+    for (seq, (marker, payload)) in payloads.iter().enumerate() {
+        if let Ok((header, offset)) = H263PayloadHeader::parse(payload) {
+            assert!(offset <= payload.len());
+            let _ = H263PictureHeader::parse(header.extra_picture_header);
+        }
+        let _ = H263PictureHeader::parse(payload);
+        let mut bytes = vec![0x80, if *marker { 0x80 | 96 } else { 96 }];
+        bytes.extend_from_slice(&(seq as u16).to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        bytes.extend_from_slice(payload);
+        let pkt = RtpPacket::parse(&bytes).expect("well-formed header");
+        let _ = depacketizer.push_packet(&pkt);
+    }
+});
@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::{
+    codec::{JpegDepacketizer, JpegHeader},
+    RtpPacket,
+};
+
+fuzz_target!(|payloads: Vec<(bool, Vec<u8>)>| {
+    let mut depacketizer = JpegDepacketizer::new();
+    for (seq, (marker, payload)) in payloads.iter().enumerate() {
+        if let Ok((_header, offset)) = JpegHeader::parse(payload) {
+            assert!(offset <= payload.len());
+        }
+        let mut bytes = vec![0x80, if *marker { 0x80 | 26 } else { 26 }];
+        bytes.extend_from_slice(&(seq as u16).to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        bytes.extend_from_slice(payload);
+        let pkt = RtpPacket::parse(&bytes).expect("well-formed header");
+        let _ = depacketizer.push_packet(&pkt);
+    }
+});
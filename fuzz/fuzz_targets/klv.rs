@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::klv::parse_klv_items;
+
+fuzz_target!(|data: &[u8]| {
+    match parse_klv_items(data) {
+        // Keys and BER lengths take at least 17 bytes per item.
+        Ok(items) => {
+            let values: usize = items.iter().map(|item| item.value.len()).sum();
+            assert!(values + 17 * items.len() <= data.len());
+        }
+        Err(offset) => assert!(offset < data.len()),
+    }
+});
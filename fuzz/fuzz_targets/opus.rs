@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::{
+    codec::{OpusDepacketizer, OpusPacketInfo},
+    RtpPacket,
+};
+
+fuzz_target!(|packets: Vec<(u16, u32, Vec<u8>)>| {
+    let mut depacketizer = OpusDepacketizer::new();
+    for (seq, timestamp, payload) in &packets {
+        if let Ok(info) = OpusPacketInfo::parse(payload) {
+            assert!((1..=63).contains(&info.frames));
+        }
+        let mut bytes = vec![0x80, 111];
+        bytes.extend_from_slice(&seq.to_be_bytes());
+        bytes.extend_from_slice(&timestamp.to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 1]);
+        bytes.extend_from_slice(payload);
+        let pkt = RtpPacket::parse(&bytes).expect("well-formed header");
+        let _ = depacketizer.push_packet(&pkt);
+        while depacketizer.pop_event().is_some() {}
+    }
+});
@@ -0,0 +1,29 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::{
+    codec::{RawVideoDepacketizer, RawVideoFormat},
+    raw::parse_rows,
+    RtpPacket,
+};
+
+fuzz_target!(|input: (bool, Vec<(bool, Vec<u8>)>)| {
+    let (interlaced, payloads) = input;
+    // A small picture keeps frame buffers cheap.
+    let mut format =
+        RawVideoFormat::from_fmtp("sampling=YCbCr-4:2:2; depth=10; width=64; height=32").unwrap();
+    format.interlaced = interlaced;
+    let mut depacketizer = RawVideoDepacketizer::new(format).unwrap();
+    for (seq, (marker, payload)) in payloads.iter().enumerate() {
+        if let Ok((_esn, rows, offset)) = parse_rows(payload) {
+            assert_eq!(offset, 2 + 6 * rows.len());
+            assert!(offset <= payload.len());
+        }
+        let mut bytes = vec![0x80, if *marker { 0x80 | 96 } else { 96 }];
+        bytes.extend_from_slice(&(seq as u16).to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        bytes.extend_from_slice(payload);
+        let pkt = RtpPacket::parse(&bytes).expect("well-formed header");
+        let _ = depacketizer.push_packet(&pkt);
+    }
+});
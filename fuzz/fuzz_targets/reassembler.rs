@@ -0,0 +1,57 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use rtpar::{
+    reassemble::{ReassemblerConfig, ReorderConfig},
+    Codec, FrameReassembler, RtpPacket,
+};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    codec: Option<Codec>,
+    config: Option<ReorderConfig>,
+    packets: Vec<FuzzPacket>,
+}
+
+#[derive(Debug, Arbitrary)]
+struct FuzzPacket {
+    marker: bool,
+    sequence_number: u16,
+    timestamp: u32,
+    ssrc: u8,
+    payload: Vec<u8>,
+}
+
+impl FuzzPacket {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0x80, if self.marker { 0x80 | 96 } else { 96 }];
+        buf.extend_from_slice(&self.sequence_number.to_be_bytes());
+        buf.extend_from_slice(&self.timestamp.to_be_bytes());
+        buf.extend_from_slice(&u32::from(self.ssrc).to_be_bytes());
+        buf.extend_from_slice(&self.payload);
+        buf
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mut config = ReassemblerConfig {
+        codec: input.codec,
+        ..ReassemblerConfig::default()
+    };
+    if let Some(mut reorder) = input.config {
+        // Bound buffering so the fuzzer explores logic rather than allocation.
+        reorder.max_buffered_packets_per_frame = reorder.max_buffered_packets_per_frame.min(4096);
+        reorder.max_pending_frames = reorder.max_pending_frames.min(256);
+        reorder.expected_frame_size = reorder.expected_frame_size.min(1 << 20);
+        config.reorder = reorder;
+    }
+    let Ok(mut r) = FrameReassembler::from_config(config) else {
+        return;
+    };
+    for p in &input.packets {
+        let bytes = p.to_bytes();
+        let pkt = RtpPacket::parse(&bytes).expect("well-formed header");
+        let _ = r.push_packet(&pkt);
+    }
+});
@@ -0,0 +1,27 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::packet::{RoqError, RoqStreamDecoder};
+
+// Packets of a stream read in `chunks`, up to the first error.
+fn decode(chunks: &[Vec<u8>]) -> (Vec<Vec<u8>>, Option<RoqError>) {
+    let mut decoder = RoqStreamDecoder::new();
+    let mut packets = Vec::new();
+    for chunk in chunks {
+        decoder.push(chunk);
+        loop {
+            match decoder.next_packet() {
+                Ok(Some(packet)) => packets.push(packet.to_vec()),
+                Ok(None) => break,
+                Err(e) => return (packets, Some(e)),
+            }
+        }
+    }
+    (packets, None)
+}
+
+fuzz_target!(|chunks: Vec<Vec<u8>>| {
+    // However reads split the stream, it decodes to the same packets.
+    let whole = decode(&[chunks.concat()]);
+    assert_eq!(decode(&chunks), whole);
+});
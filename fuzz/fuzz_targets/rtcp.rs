@@ -0,0 +1,66 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::packet::{CompoundRtcp, RtcpPacket, XrBlock};
+
+fuzz_target!(|data: &[u8]| {
+    // A valid compound packet is a run of packets filling the datagram.
+    for parse in [CompoundRtcp::parse, CompoundRtcp::parse_reduced_size] {
+        if let Ok(compound) = parse(data) {
+            assert_eq!(compound.as_bytes(), data);
+            let packets: Result<Vec<_>, _> = RtcpPacket::parse_compound(data).collect();
+            assert_eq!(compound.packets().collect::<Vec<_>>(), packets.unwrap());
+        }
+    }
+    for packet in RtcpPacket::parse_compound(data) {
+        let packet = match packet {
+            Ok(packet) => packet,
+            Err(e) => {
+                assert!(e.offset() <= data.len());
+                break;
+            }
+        };
+        match packet {
+            RtcpPacket::SenderReport(sr) => assert_eq!(sr.reports.len(), sr.reports.count()),
+            RtcpPacket::ReceiverReport(rr) => assert_eq!(rr.reports.len(), rr.reports.count()),
+            RtcpPacket::Sdes(sdes) => {
+                for chunk in sdes.chunks() {
+                    for item in chunk.items() {
+                        let _ = item.text();
+                    }
+                }
+            }
+            RtcpPacket::Bye(bye) => {
+                let _ = (bye.sources().count(), bye.reason());
+            }
+            RtcpPacket::Nack(nack) => {
+                assert!(nack.sequence_numbers().count() <= 17 * nack.entries().count());
+            }
+            RtcpPacket::Fir(fir) => {
+                let _ = fir.requests().count();
+            }
+            RtcpPacket::Remb(remb) => {
+                let _ = remb.ssrcs().count();
+            }
+            RtcpPacket::TransportFeedback(twcc) => {
+                assert!(twcc.packets().count() <= twcc.status_count as usize);
+            }
+            RtcpPacket::ExtendedReport(xr) => {
+                for block in xr.blocks() {
+                    match block {
+                        XrBlock::LossRle(rle) => {
+                            let _ = rle.packets().count();
+                        }
+                        XrBlock::Dlrr(dlrr) => {
+                            for item in dlrr.items() {
+                                let _ = item.round_trip_time(u64::MAX);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+});
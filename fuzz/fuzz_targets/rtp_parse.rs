@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::RtpPacket;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(pkt) = RtpPacket::parse(data) {
+        // The payload must always be a sub-slice that ends inside the buffer.
+        assert!(pkt.payload_offset + pkt.payload.len() <= data.len());
+        assert_eq!(pkt.header.csrcs.len(), pkt.header.csrc_count as usize);
+    }
+});
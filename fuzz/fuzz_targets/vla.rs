@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::extensions::VideoLayersAllocation;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(vla) = VideoLayersAllocation::parse(data) {
+        // At most four streams, and the index points into them when any is active.
+        assert!(vla.rtp_streams.len() <= 4);
+        assert!(
+            vla.rtp_streams.is_empty() || (vla.rtp_stream_index as usize) < vla.rtp_streams.len()
+        );
+    }
+});
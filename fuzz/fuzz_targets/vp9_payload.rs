@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtpar::codecs::vp9::Vp9PayloadDesc;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((_desc, off)) = Vp9PayloadDesc::parse(data) {
+        assert!(off <= data.len());
+    }
+});
@@ -0,0 +1,83 @@
+/*
+ * C interface to the rtpar frame reassembler.
+ *
+ * Build the library with:
+ *   cargo rustc --release --lib --features capi --crate-type cdylib
+ * (or --crate-type staticlib) and link against the resulting artifact.
+ *
+ * Regenerate with: cbindgen --config cbindgen.toml --output include/rtpar.h
+ */
+
+#ifndef RTPAR_H
+#define RTPAR_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define RTPAR_CODEC_UNKNOWN 0
+#define RTPAR_CODEC_AVC 1
+#define RTPAR_CODEC_HEVC 2
+#define RTPAR_CODEC_VP9 3
+#define RTPAR_CODEC_AV1 4
+
+/* A frame has been completed and can be taken with rtpar_reassembler_take_frame. */
+#define RTPAR_FRAME_READY 1
+/* The call succeeded. */
+#define RTPAR_OK 0
+/* A required pointer argument was null. */
+#define RTPAR_ERR_NULL -1
+/* The input bytes are not a valid RTP packet. */
+#define RTPAR_ERR_PARSE -2
+/* An argument was out of range (e.g. unknown codec id). */
+#define RTPAR_ERR_INVALID_ARG -3
+/* No completed frame is available. */
+#define RTPAR_ERR_NO_FRAME -4
+
+/* Opaque reassembler handle. */
+typedef struct RtparReassembler RtparReassembler;
+
+/*
+ * A completed frame handed out to C. `data` is owned by the library and must
+ * be released with rtpar_frame_free.
+ */
+typedef struct RtparFrame {
+  uint8_t *data;
+  size_t len;
+  uint32_t timestamp;
+  uint32_t ssrc;
+  uint32_t codec;
+} RtparFrame;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Creates a reassembler. Never returns null. */
+RtparReassembler *rtpar_reassembler_new(void);
+
+/* Destroys a reassembler created by rtpar_reassembler_new. Accepts null. */
+void rtpar_reassembler_free(RtparReassembler *handle);
+
+/* Forces the payload codec (RTPAR_CODEC_*) instead of guessing it. Discards
+   buffered packets, so call it before the first push. */
+int32_t rtpar_reassembler_set_codec(RtparReassembler *handle, uint32_t codec);
+
+/*
+ * Pushes one RTP packet (the full datagram, header included).
+ * Returns RTPAR_FRAME_READY when a frame was completed, RTPAR_OK when the
+ * packet was buffered, or a negative error code. A completed frame that is
+ * not taken before the next ready frame is replaced.
+ */
+int32_t rtpar_reassembler_push(RtparReassembler *handle, const uint8_t *data, size_t len);
+
+/* Moves the most recently completed frame into `out`. */
+int32_t rtpar_reassembler_take_frame(RtparReassembler *handle, RtparFrame *out);
+
+/* Releases the bytes of a taken frame and resets it to an empty frame. */
+void rtpar_frame_free(RtparFrame *frame);
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif /* RTPAR_H */
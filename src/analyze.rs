@@ -1,16 +1,26 @@
 use crate::{
+    cn::is_comfort_noise,
     codecs::{
         av1::parse_av1_payload_header,
-        avc::{avc_vcl_type, parse_avc_payload_header, AvcNalKind},
+        avc::{
+            avc_vcl_type, parse_avc_payload_header, AvcNalKind, AvcPps, AvcSliceHeader,
+            AvcSliceSummary, AvcSps,
+        },
+        custom::CodecRegistry,
         hevc::{hevc_vcl_type, parse_hevc_payload_header, HevcNalKind},
         vp9::Vp9PayloadDesc,
         Codec,
     },
+    extensions::{StreamTag, StreamTagger, VideoLayersAllocation},
     guess::guess_codec,
-    rtp::RtpPacket,
+    metrics::{Metrics, MetricsSink},
+    rtp::{RtpError, RtpPacket},
 };
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum FrameBoundary {
     None,
     Start,
@@ -22,6 +32,108 @@ pub enum FrameBoundary {
 pub struct FrameAnalyzer {
     codec: Option<Codec>,
     in_frame: bool,
+    vla_extension: Option<u8>,
+    layers_allocation: Option<VideoLayersAllocation>,
+    tagger: StreamTagger,
+    comfort_noise_pt: Option<u8>,
+    avc: AvcAccessUnits,
+    registry: Option<Arc<CodecRegistry>>,
+    pub(crate) metrics: Metrics,
+}
+
+// Bytes of a fragmented slice read for its header, which ends well before.
+const SLICE_HEADER_PREFIX: usize = 64;
+
+// H.264 parameter sets and the last slice header, for finding access unit
+// starts (H.264 7.4.1.2.3) when the marker bit is missing or one timestamp
+// carries several pictures.
+#[derive(Debug, Default)]
+struct AvcAccessUnits {
+    sps: BTreeMap<u8, AvcSps>,
+    pps: BTreeMap<u8, AvcPps>,
+    last_slice: Option<AvcSliceHeader>,
+    // A slice of the current access unit was seen.
+    vcl_seen: bool,
+}
+
+impl AvcAccessUnits {
+    // Whether a NAL unit starting in this packet begins a new access unit;
+    // `None` when a slice header could not be parsed.
+    fn starts_access_unit(&mut self, payload: &[u8], kind: &AvcNalKind) -> Option<bool> {
+        match *kind {
+            AvcNalKind::Single(_) => self.observe(payload),
+            AvcNalKind::StapA => {
+                let mut new = Some(false);
+                let mut rest = payload.get(1..)?;
+                while let Some((len, tail)) = rest.split_first_chunk::<2>() {
+                    let len = u16::from_be_bytes(*len) as usize;
+                    let nal = tail.get(..len)?;
+                    match self.observe(nal) {
+                        Some(true) => new = Some(true),
+                        None if new == Some(false) => new = None,
+                        _ => {}
+                    }
+                    rest = &tail[len..];
+                }
+                new
+            }
+            AvcNalKind::FuA {
+                start: true,
+                nal_type,
+                ..
+            } => {
+                // Parameter sets are short and kept whole; of a slice only its
+                // header is needed.
+                let body = payload.get(2..)?;
+                let len = match nal_type {
+                    7 | 8 => body.len(),
+                    _ => body.len().min(SLICE_HEADER_PREFIX),
+                };
+                let mut nal = Vec::with_capacity(1 + len);
+                nal.push((payload[0] & 0xE0) | nal_type);
+                nal.extend_from_slice(&body[..len]);
+                self.observe(&nal)
+            }
+            _ => Some(false),
+        }
+    }
+
+    fn observe(&mut self, nal: &[u8]) -> Option<bool> {
+        let nal_type = nal.first()? & 0x1F;
+        match nal_type {
+            1..=5 => {
+                let slice = AvcSliceHeader::parse(nal, |id| {
+                    let pps = self.pps.get(&id)?;
+                    Some((*self.sps.get(&pps.sps_id)?, *pps))
+                });
+                let vcl_seen = core::mem::replace(&mut self.vcl_seen, true);
+                let prev = core::mem::replace(&mut self.last_slice, slice.ok().flatten());
+                match (&self.last_slice, prev) {
+                    (Some(slice), Some(prev)) if vcl_seen => Some(slice.starts_new_picture(&prev)),
+                    (Some(_), _) => Some(false),
+                    (None, _) => None,
+                }
+            }
+            7 => {
+                if let Ok(sps) = AvcSps::parse(nal) {
+                    self.sps.insert(sps.sps_id, sps);
+                }
+                Some(core::mem::take(&mut self.vcl_seen))
+            }
+            8 => {
+                if let Ok(pps) = AvcPps::parse(nal) {
+                    self.pps.insert(pps.pps_id, pps);
+                }
+                Some(core::mem::take(&mut self.vcl_seen))
+            }
+            // SEI, access unit delimiter, subset SPS and reserved types
+            // 16..=18 can only precede the first slice of an access unit. MVC
+            // prefix NAL units (14) precede every base view slice, so the
+            // slice itself decides.
+            6 | 9 | 15..=18 => Some(core::mem::take(&mut self.vcl_seen)),
+            _ => Some(false),
+        }
+    }
 }
 
 impl FrameAnalyzer {
@@ -29,9 +141,89 @@ impl FrameAnalyzer {
         Self {
             codec: None,
             in_frame: false,
+            vla_extension: None,
+            layers_allocation: None,
+            tagger: StreamTagger::default(),
+            comfort_noise_pt: None,
+            avc: AvcAccessUnits::default(),
+            registry: None,
+            metrics: Metrics::default(),
         }
     }
 
+    /// Reports analyzed packets, frame starts and codec guesses to `sink`.
+    pub fn set_metrics(&mut self, sink: Arc<dyn MetricsSink>) {
+        self.metrics = Metrics::new(sink);
+    }
+
+    /// Slice accounting of an access unit, with the parameter sets seen so far.
+    pub(crate) fn avc_slices<'a>(
+        &self,
+        nals: impl IntoIterator<Item = &'a [u8]>,
+    ) -> AvcSliceSummary {
+        AvcSliceSummary::new(nals, |id| {
+            let pps = self.avc.pps.get(&id)?;
+            Some((*self.avc.sps.get(&pps.sps_id)?, *pps))
+        })
+    }
+
+    /// Extmap id of the video layers allocation extension, decoded by
+    /// [`analyze_bytes`](Self::analyze_bytes).
+    pub fn set_layers_allocation_extension(&mut self, id: u8) {
+        self.vla_extension = Some(id);
+    }
+
+    /// Extmap id of the `sdes:mid` extension, decoded by
+    /// [`analyze_bytes`](Self::analyze_bytes) into the
+    /// [`stream_tag`](Self::stream_tag).
+    pub fn set_mid_extension(&mut self, id: u8) {
+        self.tagger.mid_extension = Some(id);
+    }
+
+    /// Extmap id of the `sdes:rtp-stream-id` extension, naming the simulcast
+    /// encoding in the [`stream_tag`](Self::stream_tag).
+    pub fn set_rid_extension(&mut self, id: u8) {
+        self.tagger.rid_extension = Some(id);
+    }
+
+    /// MID and RID of the current stream.
+    pub fn stream_tag(&self) -> &StreamTag {
+        &self.tagger.tag
+    }
+
+    /// Custom codecs to guess, before the built-in ones, and analyze.
+    pub fn set_codec_registry(&mut self, registry: Arc<CodecRegistry>) {
+        self.registry = Some(registry);
+    }
+
+    /// Dynamic payload type of comfort noise, besides the static 13;
+    /// comfort noise packets are counted but otherwise skipped.
+    pub fn set_comfort_noise_payload_type(&mut self, payload_type: u8) {
+        self.comfort_noise_pt = Some(payload_type);
+    }
+
+    /// Most recent layers allocation received, with the per-layer target
+    /// bitrates and resolutions of the sender.
+    pub fn layers_allocation(&self) -> Option<&VideoLayersAllocation> {
+        self.layers_allocation.as_ref()
+    }
+
+    /// Like [`analyze`](Self::analyze) for a serialized packet, also
+    /// decoding the configured header extensions.
+    pub fn analyze_bytes(&mut self, packet: &[u8]) -> Result<FrameBoundary, RtpError> {
+        let pkt = RtpPacket::parse(packet)?;
+        self.tagger.observe(&pkt, packet);
+        if let (Some(id), Some(ext)) = (self.vla_extension, &pkt.header.extension_header) {
+            if let Some(element) = ext.elements(packet).find(|e| e.id == id) {
+                match VideoLayersAllocation::parse(element.data) {
+                    Ok(vla) => self.layers_allocation = Some(vla),
+                    Err(_e) => trace_event!(DEBUG, error = %_e, "bad layers allocation"),
+                }
+            }
+        }
+        Ok(self.analyze(&pkt))
+    }
+
     pub fn set_codec(&mut self, codec: Codec) {
         self.codec = Some(codec);
     }
@@ -41,17 +233,49 @@ impl FrameAnalyzer {
     }
 
     pub fn analyze<'a>(&mut self, packet: &RtpPacket<'a>) -> FrameBoundary {
+        if is_comfort_noise(packet.header.payload_type, self.comfort_noise_pt) {
+            self.metrics
+                .counter("rtpar_analyzer_comfort_noise_total", &[], 1);
+            return FrameBoundary::None;
+        }
         // Guess codec if unknown
-        let codec = self.codec.unwrap_or_else(|| guess_codec(packet.payload));
+        let codec = self.codec.unwrap_or_else(|| {
+            let guessed = self
+                .registry
+                .as_ref()
+                .and_then(|registry| registry.guess(packet.payload))
+                .unwrap_or_else(|| guess_codec(packet.payload));
+            let name = match guessed {
+                Codec::Custom(_) => "custom",
+                _ => guessed.mime_type().unwrap_or("unknown"),
+            };
+            let labels = [("codec", name)];
+            self.metrics
+                .counter("rtpar_analyzer_codec_guesses_total", &labels, 1);
+            trace_event!(
+                DEBUG,
+                ssrc = packet.header.ssrc,
+                codec = ?guessed,
+                "guessed codec from payload"
+            );
+            guessed
+        });
         self.codec = Some(codec);
 
-        match codec {
+        let boundary = match codec {
             Codec::Avc => self.analyze_avc(packet),
             Codec::Hevc => self.analyze_hevc(packet),
             Codec::Vp9 => self.analyze_vp9(packet),
             Codec::Av1 => self.analyze_av1(packet),
+            Codec::Custom(_) => self.analyze_custom(codec, packet),
             Codec::Unknown => self.analyze_generic(packet),
+        };
+        self.metrics.counter("rtpar_analyzer_packets_total", &[], 1);
+        if matches!(boundary, FrameBoundary::Start | FrameBoundary::StartEnd) {
+            self.metrics
+                .counter("rtpar_analyzer_frame_starts_total", &[], 1);
         }
+        boundary
     }
 
     fn analyze_generic(&mut self, packet: &RtpPacket<'_>) -> FrameBoundary {
@@ -72,65 +296,40 @@ impl FrameAnalyzer {
             Ok(v) => v,
             Err(_) => return self.analyze_generic(packet),
         };
-        match kind {
+        let new_au = self.avc.starts_access_unit(packet.payload, &kind);
+        let start = match kind {
             AvcNalKind::FuA {
-                start: s,
-                end: _e,
-                nal_type,
+                start: s, nal_type, ..
             }
             | AvcNalKind::FuB {
-                start: s,
-                end: _e,
-                nal_type,
-            } => {
-                let start = s && avc_vcl_type(nal_type);
-                let end = packet.header.marker;
-                let fb = match (start, end) {
-                    (true, true) => FrameBoundary::StartEnd,
-                    (true, false) => FrameBoundary::Start,
-                    (false, true) => FrameBoundary::End,
-                    _ => FrameBoundary::None,
-                };
-                self.in_frame = !matches!(fb, FrameBoundary::End | FrameBoundary::StartEnd);
-                fb
-            }
+                start: s, nal_type, ..
+            } => match new_au {
+                // A later slice of the current picture does not start a frame.
+                Some(new) => new || (s && avc_vcl_type(nal_type) && !self.in_frame),
+                None => s && avc_vcl_type(nal_type),
+            },
+            // Parameter sets, SEI and delimiters lead their access unit.
             AvcNalKind::Single(t) => {
-                let start = avc_vcl_type(t) && !self.in_frame;
-                let end = packet.header.marker;
-                let fb = match (start, end) {
-                    (true, true) => FrameBoundary::StartEnd,
-                    (true, false) => FrameBoundary::Start,
-                    (false, true) => FrameBoundary::End,
-                    _ => FrameBoundary::None,
-                };
-                self.in_frame = !matches!(fb, FrameBoundary::End | FrameBoundary::StartEnd);
-                fb
+                (!self.in_frame && (avc_vcl_type(t) || (6..=9).contains(&t)))
+                    || new_au == Some(true)
             }
             AvcNalKind::StapA | AvcNalKind::StapB | AvcNalKind::Mtap16 | AvcNalKind::Mtap24 => {
-                let start = !self.in_frame; // conservative
-                let end = packet.header.marker;
-                let fb = match (start, end) {
-                    (true, true) => FrameBoundary::StartEnd,
-                    (true, false) => FrameBoundary::Start,
-                    (false, true) => FrameBoundary::End,
-                    _ => FrameBoundary::None,
-                };
-                self.in_frame = !matches!(fb, FrameBoundary::End | FrameBoundary::StartEnd);
-                fb
-            }
-            AvcNalKind::Unknown(_) => {
-                let start = !self.in_frame;
-                let end = packet.header.marker;
-                let fb = match (start, end) {
-                    (true, true) => FrameBoundary::StartEnd,
-                    (true, false) => FrameBoundary::Start,
-                    (false, true) => FrameBoundary::End,
-                    _ => FrameBoundary::None,
-                };
-                self.in_frame = !matches!(fb, FrameBoundary::End | FrameBoundary::StartEnd);
-                fb
+                !self.in_frame || new_au == Some(true) // conservative
             }
+            AvcNalKind::Unknown(_) => !self.in_frame,
+        };
+        let end = packet.header.marker;
+        let fb = match (start, end) {
+            (true, true) => FrameBoundary::StartEnd,
+            (true, false) => FrameBoundary::Start,
+            (false, true) => FrameBoundary::End,
+            _ => FrameBoundary::None,
+        };
+        self.in_frame = !end;
+        if end {
+            self.avc.vcl_seen = false;
         }
+        fb
     }
 
     fn analyze_hevc(&mut self, packet: &RtpPacket<'_>) -> FrameBoundary {
@@ -182,6 +381,22 @@ impl FrameAnalyzer {
         }
     }
 
+    fn analyze_custom(&mut self, codec: Codec, packet: &RtpPacket<'_>) -> FrameBoundary {
+        let Some(custom) = self.registry.as_ref().and_then(|r| r.get(codec)) else {
+            return self.analyze_generic(packet);
+        };
+        let start = custom.starts_frame(packet.payload) || !self.in_frame;
+        let end = packet.header.marker;
+        let fb = match (start, end) {
+            (true, true) => FrameBoundary::StartEnd,
+            (true, false) => FrameBoundary::Start,
+            (false, true) => FrameBoundary::End,
+            _ => FrameBoundary::None,
+        };
+        self.in_frame = !matches!(fb, FrameBoundary::End | FrameBoundary::StartEnd);
+        fb
+    }
+
     fn analyze_vp9(&mut self, packet: &RtpPacket<'_>) -> FrameBoundary {
         let (desc, _off) = match Vp9PayloadDesc::parse(packet.payload) {
             Ok(v) => v,
@@ -219,24 +434,15 @@ impl FrameAnalyzer {
 }
 
 #[cfg(test)]
+// FU headers spell out their cleared S/E bits.
+#[allow(clippy::identity_op)]
 mod tests {
     use super::*;
     use crate::rtp::RtpPacket;
+    use crate::test_utils::rtp_packet;
 
     fn build_rtp(payload: &[u8], marker: bool) -> Vec<u8> {
-        let mut v = Vec::new();
-        let b0 = (2u8 << 6) | 0;
-        v.push(b0);
-        let mut b1 = 96u8;
-        if marker {
-            b1 |= 0x80;
-        }
-        v.push(b1);
-        v.extend_from_slice(&1u16.to_be_bytes());
-        v.extend_from_slice(&2u32.to_be_bytes());
-        v.extend_from_slice(&3u32.to_be_bytes());
-        v.extend_from_slice(payload);
-        v
+        rtp_packet(payload, marker, 1)
     }
 
     #[test]
@@ -302,4 +508,74 @@ mod tests {
         let pkt3 = RtpPacket::parse(&p3).unwrap();
         assert_eq!(a.analyze(&pkt3), FrameBoundary::End);
     }
+
+    #[test]
+    fn h264_slice_headers_split_access_units_without_marker() {
+        use crate::test_utils::{
+            fixtures::{AVC_PPS, AVC_SPS},
+            rbsp,
+        };
+        // P slices (first_mb, frame_num): a picture in two slices, then the
+        // next picture, none carrying the marker bit.
+        let slice = |first_mb: &str, frame_num: &str| {
+            [
+                &[0x41][..],
+                &rbsp(&[first_mb, "00110 1", frame_num].join(" ")),
+            ]
+            .concat()
+        };
+        let mut a = FrameAnalyzer::new();
+        a.set_codec(Codec::Avc);
+        let boundaries: Vec<_> = [
+            AVC_SPS.to_vec(),
+            AVC_PPS.to_vec(),
+            slice("1", "0000"),
+            slice("00000101001", "0000"),
+            slice("1", "0001"),
+        ]
+        .iter()
+        .map(|nal| a.analyze(&RtpPacket::parse(&build_rtp(nal, false)).unwrap()))
+        .collect();
+        use FrameBoundary::{None, Start};
+        assert_eq!(boundaries, [Start, None, None, None, Start]);
+
+        // A parameter set after a slice starts the next access unit too.
+        let sps = build_rtp(AVC_SPS, false);
+        assert_eq!(a.analyze(&RtpPacket::parse(&sps).unwrap()), Start);
+
+        // Long FU-A fragmented slices are told apart by their headers alone.
+        let fu_start = |nal: Vec<u8>| {
+            let mut p = [
+                &[(nal[0] & 0xE0) | 28, 0x80 | (nal[0] & 0x1F)][..],
+                &nal[1..],
+            ]
+            .concat();
+            p.resize(p.len() + 4000, 0xAA);
+            build_rtp(&p, false)
+        };
+        let boundaries: Vec<_> = [
+            slice("1", "0001"),
+            slice("00000101001", "0001"),
+            slice("1", "0010"),
+        ]
+        .into_iter()
+        .map(|nal| a.analyze(&RtpPacket::parse(&fu_start(nal)).unwrap()))
+        .collect();
+        assert_eq!(boundaries, [None, None, Start]);
+    }
+
+    #[test]
+    fn keeps_latest_layers_allocation() {
+        let mut a = FrameAnalyzer::new();
+        a.set_codec(Codec::Vp9);
+        a.set_layers_allocation_extension(3);
+        // One stream, spatial layer 0 with a single 250 kbps temporal layer.
+        let p = crate::test_utils::RtpPacketBuilder::new()
+            .extension(0xBEDE, &[0x33, 0x01, 0x00, 0xFA, 0x01])
+            .payload(&[0x88])
+            .build();
+        assert_eq!(a.analyze_bytes(&p).unwrap(), FrameBoundary::Start);
+        let layers = &a.layers_allocation().unwrap().rtp_streams[0].spatial_layers;
+        assert_eq!(layers[0].target_bitrates_kbps, [250]);
+    }
 }
@@ -0,0 +1,416 @@
+//! SMPTE ST 291-1 ancillary data over RTP (RFC 8331, SMPTE ST 2110-40).
+//!
+//! A payload carries the ANC packets of one frame or field, or part of
+//! them; the marker bit flags the last payload of the frame or field. Each
+//! ANC packet is placed at a line and horizontal offset of the video and
+//! identified by its DID/SDID; its data words are 10 bits wide, the low 8
+//! carrying the value. [`AncPacket::captions`] and [`AncPacket::timecode`]
+//! decode the two most common kinds.
+
+use crate::{
+    bitstream::{BitError, BitReader},
+    captions::FrameCaptions,
+    rtp::RtpPacket,
+};
+use alloc::vec::Vec;
+
+/// DID/SDID of a SMPTE ST 334 CEA-708 caption distribution packet.
+pub const DID_CEA708: (u8, u8) = (0x61, 0x01);
+/// DID/SDID of a SMPTE ST 12-2 ancillary time code.
+pub const DID_TIMECODE: (u8, u8) = (0x60, 0x60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AncError {
+    /// The payload ends inside `header`.
+    Truncated { header: &'static str },
+    /// An ANC packet runs past the payload's length field.
+    Bits(BitError),
+    /// F is 0b01, which is not a valid field.
+    InvalidField,
+    /// Packets of the frame or field at `timestamp` were lost.
+    MissingPacket { timestamp: u32 },
+}
+
+impl core::fmt::Display for AncError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AncError::Truncated { header } => write!(f, "ANC payload ends inside {}", header),
+            AncError::Bits(e) => write!(f, "ANC packet: {}", e),
+            AncError::InvalidField => f.write_str("invalid ANC field bits"),
+            AncError::MissingPacket { timestamp } => {
+                write!(f, "ANC data at {} lost packets", timestamp)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AncError {}
+
+impl From<BitError> for AncError {
+    fn from(e: BitError) -> Self {
+        AncError::Bits(e)
+    }
+}
+
+/// Picture the ANC packets belong to (F bits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AncField {
+    Progressive,
+    Field1,
+    Field2,
+}
+
+/// One ANC packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AncPacket {
+    /// Carried in the color difference channel (C bit).
+    pub color_difference: bool,
+    /// Line number; 0x7FF is unspecified and 0x7FE any line after the
+    /// vertical blanking interval.
+    pub line: u16,
+    /// Horizontal offset; 0xFFF is unspecified.
+    pub horizontal_offset: u16,
+    /// Data stream of the link, when the S bit is set.
+    pub stream: Option<u8>,
+    pub did: u8,
+    pub sdid: u8,
+    /// User data words, 10 bits each.
+    pub user_data: Vec<u16>,
+    /// The checksum word matches the packet.
+    pub checksum_valid: bool,
+}
+
+impl AncPacket {
+    /// The 8-bit values of the user data words.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.user_data.iter().map(|&w| w as u8).collect()
+    }
+
+    /// Captions of a CEA-708 caption distribution packet.
+    pub fn captions(&self) -> Option<FrameCaptions> {
+        if (self.did, self.sdid) != DID_CEA708 {
+            return None;
+        }
+        let mut captions = FrameCaptions::default();
+        captions.push_cdp(&self.bytes());
+        Some(captions)
+    }
+
+    /// Time code of an ancillary time code packet.
+    pub fn timecode(&self) -> Option<Timecode> {
+        if (self.did, self.sdid) != DID_TIMECODE || self.user_data.len() < 16 {
+            return None;
+        }
+        // The time bits are bits 4-7 of every other word.
+        let nibble = |i: usize| ((self.user_data[i] >> 4) & 0x0F) as u8;
+        Some(Timecode {
+            hours: (nibble(14) & 0x03) * 10 + nibble(12),
+            minutes: (nibble(10) & 0x07) * 10 + nibble(8),
+            seconds: (nibble(6) & 0x07) * 10 + nibble(4),
+            frames: (nibble(2) & 0x03) * 10 + nibble(0),
+            drop_frame: nibble(2) & 0x04 != 0,
+        })
+    }
+}
+
+/// SMPTE ST 12-1 time code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timecode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub drop_frame: bool,
+}
+
+/// Contents of one RFC 8331 payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AncPayload {
+    /// High half of the extended sequence number.
+    pub extended_sequence: u16,
+    pub field: AncField,
+    pub packets: Vec<AncPacket>,
+}
+
+impl AncPayload {
+    pub fn parse(payload: &[u8]) -> Result<Self, AncError> {
+        let header = payload.get(..8).ok_or(AncError::Truncated {
+            header: "payload header",
+        })?;
+        let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+        let field = match header[5] >> 6 {
+            0 => AncField::Progressive,
+            2 => AncField::Field1,
+            3 => AncField::Field2,
+            _ => return Err(AncError::InvalidField),
+        };
+        let data = payload
+            .get(8..8 + len)
+            .ok_or(AncError::Truncated { header: "ANC data" })?;
+        let mut r = BitReader::new(data);
+        let mut packets = Vec::with_capacity(header[4] as usize);
+        for _ in 0..header[4] {
+            packets.push(read_anc_packet(&mut r)?);
+        }
+        Ok(Self {
+            extended_sequence: u16::from_be_bytes([header[0], header[1]]),
+            field,
+            packets,
+        })
+    }
+}
+
+fn read_anc_packet(r: &mut BitReader<'_>) -> Result<AncPacket, BitError> {
+    let color_difference = r.read_bit()?;
+    let line = r.read_bits(11)? as u16;
+    let horizontal_offset = r.read_bits(12)? as u16;
+    let has_stream = r.read_bit()?;
+    let stream = r.read_bits(7)? as u8;
+    let did = r.read_bits(10)? as u16;
+    let sdid = r.read_bits(10)? as u16;
+    let data_count = r.read_bits(10)? as u16;
+    let mut sum = (did + sdid + data_count) as u32;
+    let mut user_data = Vec::with_capacity((data_count & 0xFF) as usize);
+    for _ in 0..data_count & 0xFF {
+        let word = r.read_bits(10)? as u16;
+        sum += word as u32;
+        user_data.push(word);
+    }
+    let checksum = r.read_bits(10)? as u16;
+    // The checksum is the 9-bit sum with bit 9 the inverse of bit 8.
+    let sum = sum & 0x1FF;
+    let expected = (sum | ((!sum << 1) & 0x200)) as u16;
+    // word_align to the next 32-bit boundary.
+    r.skip((32 - r.position() % 32) % 32)?;
+    Ok(AncPacket {
+        color_difference,
+        line,
+        horizontal_offset,
+        stream: has_stream.then_some(stream),
+        did: did as u8,
+        sdid: sdid as u8,
+        user_data,
+        checksum_valid: checksum == expected,
+    })
+}
+
+/// ANC packets of one frame or field, completed by an [`AncDepacketizer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AncFrame {
+    pub timestamp: u32,
+    pub field: AncField,
+    pub packets: Vec<AncPacket>,
+}
+
+// Frame or field being received.
+#[derive(Debug)]
+struct PendingAnc {
+    timestamp: u32,
+    field: AncField,
+    packets: Vec<AncPacket>,
+    error: Option<AncError>,
+}
+
+/// Collects the ANC packets of one ST 2110-40 stream per frame or field.
+///
+/// Data whose last payload is lost is dropped when the next frame or
+/// field starts; like other dropped data it is counted by
+/// [`dropped_frames`](Self::dropped_frames).
+#[derive(Debug, Default)]
+pub struct AncDepacketizer {
+    pending: Option<PendingAnc>,
+    // Extended sequence number expected next.
+    next_seq: Option<u32>,
+    dropped: u64,
+}
+
+impl AncDepacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a packet; returns the frame or field it completes, or why that
+    /// was dropped.
+    pub fn push_packet(&mut self, pkt: &RtpPacket<'_>) -> Option<Result<AncFrame, AncError>> {
+        let timestamp = pkt.header.timestamp;
+        let parsed = AncPayload::parse(pkt.payload);
+        let field = parsed.as_ref().map_or(AncField::Progressive, |p| p.field);
+        let mut frame = match self.pending.take() {
+            Some(frame) if frame.timestamp == timestamp && frame.field == field => frame,
+            previous => {
+                if let Some(_previous) = previous {
+                    trace_event!(
+                        DEBUG,
+                        timestamp = _previous.timestamp,
+                        "ANC data lost its last packet"
+                    );
+                    self.dropped += 1;
+                }
+                PendingAnc {
+                    timestamp,
+                    field,
+                    packets: Vec::new(),
+                    error: None,
+                }
+            }
+        };
+        match parsed {
+            Ok(payload) => {
+                let seq =
+                    (payload.extended_sequence as u32) << 16 | pkt.header.sequence_number as u32;
+                if self.next_seq.is_some_and(|next| next != seq) {
+                    frame
+                        .error
+                        .get_or_insert(AncError::MissingPacket { timestamp });
+                }
+                self.next_seq = Some(seq.wrapping_add(1));
+                frame.packets.extend(payload.packets);
+            }
+            Err(e) => {
+                frame.error.get_or_insert(e);
+            }
+        }
+        if !pkt.header.marker {
+            self.pending = Some(frame);
+            return None;
+        }
+        let result = match frame.error {
+            Some(error) => Err(error),
+            None => Ok(AncFrame {
+                timestamp,
+                field: frame.field,
+                packets: frame.packets,
+            }),
+        };
+        if let Err(_e) = &result {
+            trace_event!(DEBUG, timestamp, error = %_e, "ANC data dropped");
+            self.dropped += 1;
+        }
+        Some(result)
+    }
+
+    /// Frames or fields dropped so far, including those returned as errors.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{captions::CcType, test_utils::RtpPacketBuilder};
+
+    // MSB-first writer of the 10-bit ANC words.
+    #[derive(Default)]
+    struct Bits {
+        out: Vec<u8>,
+        len: usize,
+    }
+
+    impl Bits {
+        fn put(&mut self, value: u32, n: usize) {
+            for i in (0..n).rev() {
+                if self.len.is_multiple_of(8) {
+                    self.out.push(0);
+                }
+                if value >> i & 1 != 0 {
+                    *self.out.last_mut().unwrap() |= 0x80 >> (self.len % 8);
+                }
+                self.len += 1;
+            }
+        }
+    }
+
+    // 8-bit value with even parity in bit 8 and its inverse in bit 9.
+    fn word(value: u8) -> u32 {
+        let parity = value.count_ones() & 1;
+        value as u32 | parity << 8 | (parity ^ 1) << 9
+    }
+
+    fn anc_packet(bits: &mut Bits, line: u32, (did, sdid): (u8, u8), data: &[u8]) {
+        bits.put(0, 1);
+        bits.put(line, 11);
+        bits.put(0xFFF, 12);
+        bits.put(0, 8);
+        let words: Vec<u32> = [did, sdid, data.len() as u8]
+            .iter()
+            .chain(data)
+            .map(|&b| word(b))
+            .collect();
+        for &w in &words {
+            bits.put(w, 10);
+        }
+        let sum = words.iter().sum::<u32>() & 0x1FF;
+        bits.put(sum | (!sum << 1 & 0x200), 10);
+        while !bits.len.is_multiple_of(32) {
+            bits.put(0, 1);
+        }
+    }
+
+    fn payload(bits: &Bits, count: u8, field: u8) -> Vec<u8> {
+        let mut payload = vec![0, 0];
+        payload.extend_from_slice(&(bits.out.len() as u16).to_be_bytes());
+        payload.extend_from_slice(&[count, field << 6, 0, 0]);
+        payload.extend_from_slice(&bits.out);
+        payload
+    }
+
+    #[test]
+    fn decodes_captions_and_timecode() {
+        let mut bits = Bits::default();
+        // CDP with one CEA-608 field 1 pair.
+        let cdp = [
+            0x96, 0x69, 13, 0x4F, 0x43, 0x00, 0x01, 0x72, 0xE1, 0xFC, 0x94, 0x20, 0x74,
+        ];
+        anc_packet(&mut bits, 9, DID_CEA708, &cdp);
+        // 01:02:03:04, drop frame.
+        let mut atc = [0u8; 16];
+        for (i, nibble) in [4, 0, 3, 0, 2, 0, 1, 0].into_iter().enumerate() {
+            atc[i * 2] = nibble << 4;
+        }
+        atc[2] |= 0x40;
+        anc_packet(&mut bits, 10, DID_TIMECODE, &atc);
+        let bytes = RtpPacketBuilder::new()
+            .marker(true)
+            .timestamp(3000)
+            .payload(&payload(&bits, 2, 2))
+            .build();
+        let mut anc = AncDepacketizer::new();
+        let frame = anc
+            .push_packet(&RtpPacket::parse(&bytes).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(frame.field, AncField::Field1);
+        assert!(frame.packets.iter().all(|p| p.checksum_valid));
+        let captions = frame.packets[0].captions().unwrap();
+        assert_eq!(captions.cc_data[0].cc_type, CcType::Cea608Field1);
+        assert_eq!(captions.cea608(1), [0x94, 0x20]);
+        assert_eq!(
+            frame.packets[1].timecode(),
+            Some(Timecode {
+                hours: 1,
+                minutes: 2,
+                seconds: 3,
+                frames: 4,
+                drop_frame: true
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_anc_data() {
+        let mut bits = Bits::default();
+        anc_packet(&mut bits, 9, (0x41, 0x05), &[1, 2, 3]);
+        let mut short = payload(&bits, 2, 0);
+        assert!(matches!(
+            AncPayload::parse(&short),
+            Err(AncError::Bits(BitError::EndOfData { .. }))
+        ));
+        short[5] = 0x40;
+        assert_eq!(AncPayload::parse(&short), Err(AncError::InvalidField));
+    }
+}
@@ -0,0 +1,289 @@
+//! Shared bitstream utilities: start-code and emulation-prevention scanning
+//! for Annex-B byte streams, and an exp-Golomb bit reader for headers.
+//!
+//! Scanning uses `memchr::memmem`, which searches with SIMD where the
+//! target supports it instead of testing every byte.
+
+use alloc::{borrow::Cow, vec::Vec};
+use memchr::memmem;
+
+const START_CODE: [u8; 3] = [0, 0, 1];
+const EMULATION_PREVENTION: [u8; 3] = [0, 0, 3];
+
+/// Position of an Annex-B start code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartCode {
+    /// Offset of the first start-code byte (a leading zero of a 4-byte code included).
+    pub offset: usize,
+    /// 3 or 4.
+    pub len: usize,
+}
+
+impl StartCode {
+    /// Offset of the first NAL unit byte following the start code.
+    pub fn payload_offset(&self) -> usize {
+        self.offset + self.len
+    }
+}
+
+/// Finds the first start code at or after `from`.
+pub fn find_start_code(data: &[u8], from: usize) -> Option<StartCode> {
+    let pos = from + memmem::find(data.get(from..)?, &START_CODE)?;
+    Some(if pos > from && data[pos - 1] == 0 {
+        StartCode {
+            offset: pos - 1,
+            len: 4,
+        }
+    } else {
+        StartCode {
+            offset: pos,
+            len: 3,
+        }
+    })
+}
+
+/// Iterates over the start codes of `data` in order.
+pub fn start_codes(data: &[u8]) -> impl Iterator<Item = StartCode> + '_ {
+    let finder = memmem::find_iter(data, &START_CODE);
+    finder.map(move |pos| {
+        if pos > 0 && data[pos - 1] == 0 {
+            StartCode {
+                offset: pos - 1,
+                len: 4,
+            }
+        } else {
+            StartCode {
+                offset: pos,
+                len: 3,
+            }
+        }
+    })
+}
+
+/// Splits an Annex-B byte stream into NAL units (start codes removed).
+///
+/// Both 3- and 4-byte start codes are accepted; zero bytes trailing a NAL
+/// unit are treated as part of the next start code.
+pub fn split_annex_b(data: &[u8]) -> Vec<&[u8]> {
+    let mut nals = Vec::new();
+    let mut nal_start: Option<usize> = None;
+    for pos in memmem::find_iter(data, &START_CODE) {
+        if let Some(start) = nal_start {
+            // Overlapping matches (e.g. 00 00 00 01) start inside the code.
+            if pos < start {
+                continue;
+            }
+            let mut end = pos;
+            while end > start && data[end - 1] == 0 {
+                end -= 1;
+            }
+            if end > start {
+                nals.push(&data[start..end]);
+            }
+        }
+        nal_start = Some(pos + 3);
+    }
+    if let Some(start) = nal_start {
+        if start < data.len() {
+            nals.push(&data[start..]);
+        }
+    }
+    nals
+}
+
+/// Rewrites the 4-byte start codes of an Annex-B buffer in place as 4-byte
+/// big-endian NAL unit lengths (avcC/hvcC layout). 3-byte start codes
+/// cannot be rewritten in place and are left as they are.
+pub fn annex_b_to_length_prefixed(data: &mut [u8]) {
+    let codes: Vec<usize> = start_codes(data)
+        .filter(|code| code.len == 4)
+        .map(|code| code.offset)
+        .collect();
+    for (i, &offset) in codes.iter().enumerate() {
+        let end = codes.get(i + 1).copied().unwrap_or(data.len());
+        let len = (end - offset - 4) as u32;
+        data[offset..offset + 4].copy_from_slice(&len.to_be_bytes());
+    }
+}
+
+/// Removes emulation prevention bytes (`00 00 03` -> `00 00`) from a NAL
+/// unit, borrowing the input when there are none.
+pub fn remove_emulation_prevention(nal: &[u8]) -> Cow<'_, [u8]> {
+    let mut positions = memmem::find_iter(nal, &EMULATION_PREVENTION).peekable();
+    if positions.peek().is_none() {
+        return Cow::Borrowed(nal);
+    }
+    let mut out = Vec::with_capacity(nal.len());
+    let mut copied = 0usize;
+    for pos in positions {
+        // `00 00 03 00 00 03`: the second match overlaps a removed byte.
+        if pos + 2 < copied {
+            continue;
+        }
+        out.extend_from_slice(&nal[copied..pos + 2]);
+        copied = pos + 3;
+    }
+    out.extend_from_slice(&nal[copied..]);
+    Cow::Owned(out)
+}
+
+/// Appends `rbsp` to `out`, inserting emulation prevention bytes where two
+/// zero bytes would be followed by a byte <= 3.
+pub fn write_emulation_prevented(rbsp: &[u8], out: &mut Vec<u8>) {
+    let mut zeros = 0usize;
+    for &b in rbsp {
+        if zeros >= 2 && b <= 3 {
+            out.push(3);
+            zeros = 0;
+        }
+        out.push(b);
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+    }
+}
+
+/// Bit reader failure, with the bit position the read started at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BitError {
+    EndOfData { bit_offset: usize },
+    // Exp-Golomb code longer than 32 bits.
+    InvalidGolomb { bit_offset: usize },
+}
+
+impl core::fmt::Display for BitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BitError::EndOfData { bit_offset } => {
+                write!(f, "unexpected end of data at bit {}", bit_offset)
+            }
+            BitError::InvalidGolomb { bit_offset } => {
+                write!(f, "exp-Golomb code too long at bit {}", bit_offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BitError {}
+
+/// MSB-first bit reader over an RBSP, with exp-Golomb decoding.
+#[derive(Debug, Clone)]
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Bits consumed so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn bits_left(&self) -> usize {
+        self.data.len() * 8 - self.pos
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool, BitError> {
+        let byte = self.data.get(self.pos / 8).ok_or(BitError::EndOfData {
+            bit_offset: self.pos,
+        })?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(bit == 1)
+    }
+
+    /// Reads `n` (at most 32) bits as an unsigned value.
+    pub fn read_bits(&mut self, n: u32) -> Result<u32, BitError> {
+        debug_assert!(n <= 32);
+        if n as usize > self.bits_left() {
+            return Err(BitError::EndOfData {
+                bit_offset: self.pos,
+            });
+        }
+        let mut value = 0u32;
+        for _ in 0..n {
+            value = (value << 1) | self.read_bit()? as u32;
+        }
+        Ok(value)
+    }
+
+    pub fn skip(&mut self, n: usize) -> Result<(), BitError> {
+        if n > self.bits_left() {
+            return Err(BitError::EndOfData {
+                bit_offset: self.pos,
+            });
+        }
+        self.pos += n;
+        Ok(())
+    }
+
+    /// Reads an unsigned exp-Golomb code (`ue(v)`).
+    pub fn read_ue(&mut self) -> Result<u32, BitError> {
+        let start = self.pos;
+        let mut leading_zeros = 0u32;
+        while !self.read_bit()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return Err(BitError::InvalidGolomb { bit_offset: start });
+            }
+        }
+        let suffix = self.read_bits(leading_zeros)?;
+        Ok(((1u64 << leading_zeros) - 1 + suffix as u64) as u32)
+    }
+
+    /// Reads a signed exp-Golomb code (`se(v)`).
+    pub fn read_se(&mut self) -> Result<i32, BitError> {
+        let k = self.read_ue()? as i64;
+        Ok(if k % 2 == 1 { (k + 1) / 2 } else { -(k / 2) } as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_three_and_four_byte_start_codes() {
+        let data = [0xAA, 0, 0, 0, 1, 0x67, 0, 0, 1, 0x68];
+        let codes: Vec<_> = start_codes(&data).collect();
+        assert_eq!(
+            codes,
+            [
+                StartCode { offset: 1, len: 4 },
+                StartCode { offset: 6, len: 3 }
+            ]
+        );
+        assert_eq!(find_start_code(&data, 5), Some(codes[1]));
+        assert_eq!(find_start_code(&data, 9), None);
+        assert_eq!(split_annex_b(&data), [&[0x67][..], &[0x68]]);
+    }
+
+    #[test]
+    fn emulation_prevention_round_trip() {
+        let rbsp = [0x65, 0, 0, 0, 0, 0, 1, 0, 0, 4];
+        let mut escaped = Vec::new();
+        write_emulation_prevented(&rbsp, &mut escaped);
+        assert_eq!(escaped, [0x65, 0, 0, 3, 0, 0, 3, 0, 1, 0, 0, 4]);
+        assert_eq!(&*remove_emulation_prevention(&escaped), &rbsp);
+        assert!(matches!(
+            remove_emulation_prevention(&[1, 2, 3]),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn reads_exp_golomb_codes() {
+        // ue: 1 -> 0, 010 -> 1, 011 -> 2, 00100 -> 3; se: 00101 -> -2
+        let mut r = BitReader::new(&[0b1010_0110, 0b0100_0010, 0b1000_0000]);
+        assert_eq!(r.read_ue(), Ok(0));
+        assert_eq!(r.read_ue(), Ok(1));
+        assert_eq!(r.read_ue(), Ok(2));
+        assert_eq!(r.read_ue(), Ok(3));
+        assert_eq!(r.read_se(), Ok(-2));
+        assert_eq!(r.position(), 17);
+        assert_eq!(r.read_bits(8), Err(BitError::EndOfData { bit_offset: 17 }));
+    }
+}
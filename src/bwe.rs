@@ -0,0 +1,415 @@
+//! Receive-side bandwidth estimation.
+//!
+//! [`ReceiveBandwidthEstimator`] is the delay-based part of Google
+//! Congestion Control (draft-ietf-rmcat-gcc). Packets are grouped into
+//! bursts sent within 5 ms; the growth of one-way delay between groups is
+//! taken from their abs-send-time and arrival deltas. A trendline filter
+//! fits a slope to the accumulated delay, an adaptive threshold turns it
+//! into overuse or underuse, and AIMD rate control moves the estimate: it
+//! grows while the path keeps up and drops to 85 % of the received bitrate
+//! on overuse. The estimate is meant for RTCP REMB feedback ([`remb`]).
+
+use crate::{
+    extensions::AbsSendTime,
+    rtcp::RTCP_PSFB,
+    rtp::{RtpError, RtpPacket},
+};
+use alloc::{collections::VecDeque, vec::Vec};
+use core::time::Duration;
+
+// Packets sent within this span form one group.
+const BURST: Duration = Duration::from_millis(5);
+// Window of the received bitrate.
+const RATE_WINDOW: Duration = Duration::from_millis(500);
+const TRENDLINE_WINDOW: usize = 20;
+const SMOOTHING: f64 = 0.9;
+const THRESHOLD_GAIN: f64 = 4.0;
+const OVERUSE_TIME_MS: f64 = 10.0;
+const K_UP: f64 = 0.0087;
+const K_DOWN: f64 = 0.039;
+const BACKOFF: f64 = 0.85;
+// Multiplicative increase per second.
+const INCREASE: f64 = 0.08;
+const MIN_BITRATE: u64 = 30_000;
+
+/// Congestion signal of the overuse detector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BandwidthUsage {
+    Normal,
+    Underusing,
+    Overusing,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PacketGroup {
+    first_send: i64,
+    last_send: i64,
+    last_arrival: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct Trendline {
+    first_arrival: Option<Duration>,
+    accumulated: f64,
+    smoothed: f64,
+    // (arrival ms since the first group, smoothed accumulated delay ms)
+    samples: VecDeque<(f64, f64)>,
+    deltas: usize,
+}
+
+impl Trendline {
+    fn new() -> Self {
+        Self {
+            first_arrival: None,
+            accumulated: 0.0,
+            smoothed: 0.0,
+            samples: VecDeque::with_capacity(TRENDLINE_WINDOW),
+            deltas: 0,
+        }
+    }
+
+    // Adds one inter-group delay variation; returns the slope once the
+    // window is full.
+    fn update(&mut self, delay_ms: f64, arrival: Duration) -> Option<f64> {
+        let first = *self.first_arrival.get_or_insert(arrival);
+        self.deltas += 1;
+        self.accumulated += delay_ms;
+        self.smoothed = SMOOTHING * self.smoothed + (1.0 - SMOOTHING) * self.accumulated;
+        let x = arrival.saturating_sub(first).as_secs_f64() * 1000.0;
+        if self.samples.len() == TRENDLINE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((x, self.smoothed));
+        if self.samples.len() < TRENDLINE_WINDOW {
+            return None;
+        }
+        let n = self.samples.len() as f64;
+        let mean_x = self.samples.iter().map(|s| s.0).sum::<f64>() / n;
+        let mean_y = self.samples.iter().map(|s| s.1).sum::<f64>() / n;
+        let (mut num, mut den) = (0.0, 0.0);
+        for &(x, y) in &self.samples {
+            num += (x - mean_x) * (y - mean_y);
+            den += (x - mean_x) * (x - mean_x);
+        }
+        (den != 0.0).then(|| num / den)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OveruseDetector {
+    threshold: f64,
+    last_update: Option<Duration>,
+    time_over: f64,
+    overuse_count: u32,
+    prev_trend: f64,
+    usage: BandwidthUsage,
+}
+
+impl OveruseDetector {
+    fn new() -> Self {
+        Self {
+            threshold: 12.5,
+            last_update: None,
+            time_over: -1.0,
+            overuse_count: 0,
+            prev_trend: 0.0,
+            usage: BandwidthUsage::Normal,
+        }
+    }
+
+    fn detect(&mut self, trend: f64, deltas: usize, send_delta_ms: f64, now: Duration) {
+        let modified = deltas.min(60) as f64 * trend * THRESHOLD_GAIN;
+        if modified > self.threshold {
+            self.time_over = if self.time_over < 0.0 {
+                send_delta_ms / 2.0
+            } else {
+                self.time_over + send_delta_ms
+            };
+            self.overuse_count += 1;
+            if self.time_over > OVERUSE_TIME_MS
+                && self.overuse_count > 1
+                && trend >= self.prev_trend
+            {
+                self.time_over = 0.0;
+                self.overuse_count = 0;
+                self.usage = BandwidthUsage::Overusing;
+            }
+        } else {
+            self.time_over = -1.0;
+            self.overuse_count = 0;
+            self.usage = if modified < -self.threshold {
+                BandwidthUsage::Underusing
+            } else {
+                BandwidthUsage::Normal
+            };
+        }
+        self.prev_trend = trend;
+        self.adapt_threshold(modified.abs(), now);
+    }
+
+    fn adapt_threshold(&mut self, modified: f64, now: Duration) {
+        let last = self.last_update.replace(now).unwrap_or(now);
+        // Spikes far above the threshold are not let to drag it up.
+        if modified > self.threshold + 15.0 {
+            return;
+        }
+        let k = if modified < self.threshold {
+            K_DOWN
+        } else {
+            K_UP
+        };
+        let dt_ms = (now.saturating_sub(last).as_secs_f64() * 1000.0).min(100.0);
+        self.threshold =
+            (self.threshold + k * (modified - self.threshold) * dt_ms).clamp(6.0, 600.0);
+    }
+}
+
+/// Delay-based receive-side bandwidth estimator for one transport.
+#[derive(Debug, Clone)]
+pub struct ReceiveBandwidthEstimator {
+    extension: Option<u8>,
+    // Unwrapped abs-send-time of the last packet, in 1/2^18 s.
+    send_time: Option<(AbsSendTime, i64)>,
+    group: Option<PacketGroup>,
+    prev_group: Option<PacketGroup>,
+    trendline: Trendline,
+    detector: OveruseDetector,
+    received: VecDeque<(Duration, usize)>,
+    received_bytes: usize,
+    estimate: Option<u64>,
+    last_change: Option<Duration>,
+}
+
+impl Default for ReceiveBandwidthEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReceiveBandwidthEstimator {
+    pub fn new() -> Self {
+        Self {
+            extension: None,
+            send_time: None,
+            group: None,
+            prev_group: None,
+            trendline: Trendline::new(),
+            detector: OveruseDetector::new(),
+            received: VecDeque::new(),
+            received_bytes: 0,
+            estimate: None,
+            last_change: None,
+        }
+    }
+
+    /// Extmap id of the abs-send-time extension, read by
+    /// [`push_bytes`](Self::push_bytes).
+    pub fn set_abs_send_time_extension(&mut self, id: u8) {
+        self.extension = Some(id);
+    }
+
+    /// Records a serialized RTP packet, as fed to the reassembler; returns
+    /// whether it carried an abs-send-time to estimate from.
+    pub fn push_bytes(&mut self, packet: &[u8], arrival: Duration) -> Result<bool, RtpError> {
+        let pkt = RtpPacket::parse(packet)?;
+        let send_time = match (self.extension, &pkt.header.extension_header) {
+            (Some(id), Some(ext)) => ext
+                .elements(packet)
+                .find(|e| e.id == id)
+                .and_then(|e| AbsSendTime::parse(e.data).ok()),
+            _ => None,
+        };
+        if let Some(send_time) = send_time {
+            self.on_packet(arrival, send_time, packet.len());
+        }
+        Ok(send_time.is_some())
+    }
+
+    /// Records a packet of `size` bytes (e.g. the RTP packet length) that
+    /// arrived at `arrival` (any monotonic clock) with the abs-send-time
+    /// `send_time`.
+    pub fn on_packet(&mut self, arrival: Duration, send_time: AbsSendTime, size: usize) {
+        self.count_received(arrival, size);
+        let send = match self.send_time {
+            Some((last, unwrapped)) => unwrapped + send_time.delta(last) as i64,
+            None => 0,
+        };
+        self.send_time = Some((send_time, send));
+        let burst = (BURST.as_nanos() as u64 * AbsSendTime::RATE / 1_000_000_000) as i64;
+        match &mut self.group {
+            Some(group) if send - group.first_send <= burst => {
+                group.last_send = group.last_send.max(send);
+                group.last_arrival = group.last_arrival.max(arrival);
+                return;
+            }
+            _ => {}
+        }
+        let finished = self.group.replace(PacketGroup {
+            first_send: send,
+            last_send: send,
+            last_arrival: arrival,
+        });
+        // A late packet from an earlier burst does not start a group.
+        if let (Some(finished), Some(prev)) = (finished, self.prev_group) {
+            if finished.last_send > prev.last_send {
+                self.on_group(&prev, &finished);
+            }
+        }
+        self.prev_group = finished.or(self.prev_group);
+    }
+
+    fn on_group(&mut self, prev: &PacketGroup, group: &PacketGroup) {
+        let send_delta_ms =
+            (group.last_send - prev.last_send) as f64 * 1000.0 / AbsSendTime::RATE as f64;
+        let arrival_delta_ms =
+            (group.last_arrival.as_secs_f64() - prev.last_arrival.as_secs_f64()) * 1000.0;
+        let now = group.last_arrival;
+        if let Some(trend) = self.trendline.update(arrival_delta_ms - send_delta_ms, now) {
+            self.detector
+                .detect(trend, self.trendline.deltas, send_delta_ms, now);
+        }
+        self.update_estimate(now);
+    }
+
+    fn update_estimate(&mut self, now: Duration) {
+        let Some(incoming) = self.incoming_bitrate() else {
+            return;
+        };
+        let last = self.last_change.replace(now).unwrap_or(now);
+        let estimate = self.estimate.unwrap_or(incoming) as f64;
+        let estimate = match self.detector.usage {
+            BandwidthUsage::Overusing => {
+                // Back off once per overuse, then wait for a new signal.
+                self.detector.usage = BandwidthUsage::Underusing;
+                BACKOFF * incoming as f64
+            }
+            BandwidthUsage::Underusing => estimate,
+            BandwidthUsage::Normal => {
+                let dt = now.saturating_sub(last).as_secs_f64().min(1.0);
+                let grown = estimate * (1.0 + INCREASE * dt);
+                grown.min(1.5 * incoming as f64 + 10_000.0)
+            }
+        };
+        self.estimate = Some((estimate as u64).max(MIN_BITRATE));
+    }
+
+    fn count_received(&mut self, arrival: Duration, size: usize) {
+        self.received.push_back((arrival, size));
+        self.received_bytes += size;
+        while let Some(&(time, size)) = self.received.front() {
+            if arrival.saturating_sub(time) <= RATE_WINDOW {
+                break;
+            }
+            self.received.pop_front();
+            self.received_bytes -= size;
+        }
+    }
+
+    /// Bitrate received over the last 500 ms, once that much has passed.
+    pub fn incoming_bitrate(&self) -> Option<u64> {
+        let (first, _) = self.received.front()?;
+        let (last, _) = self.received.back()?;
+        let span = last.saturating_sub(*first);
+        if span < RATE_WINDOW / 2 {
+            return None;
+        }
+        Some(self.received_bytes as u64 * 8 * 1_000_000 / span.as_micros().max(1) as u64)
+    }
+
+    /// Estimated available bandwidth in bits per second.
+    pub fn estimate(&self) -> Option<u64> {
+        self.estimate
+    }
+
+    pub fn usage(&self) -> BandwidthUsage {
+        self.detector.usage
+    }
+}
+
+/// Serializes an RTCP REMB (draft-alvestrand-rmcat-remb) from
+/// `sender_ssrc` carrying `bitrate` for the streams `ssrcs`; at most 255
+/// are listed.
+pub fn remb(sender_ssrc: u32, bitrate: u64, ssrcs: &[u32]) -> Vec<u8> {
+    let ssrcs = &ssrcs[..ssrcs.len().min(255)];
+    let (mut mantissa, mut exponent) = (bitrate, 0u32);
+    while mantissa > 0x3_FFFF {
+        mantissa >>= 1;
+        exponent += 1;
+    }
+    let len = 20 + 4 * ssrcs.len();
+    let mut out = Vec::with_capacity(len);
+    // FMT 15: application layer feedback.
+    out.extend_from_slice(&[0x8F, RTCP_PSFB]);
+    out.extend_from_slice(&((len / 4 - 1) as u16).to_be_bytes());
+    out.extend_from_slice(&sender_ssrc.to_be_bytes());
+    out.extend_from_slice(&0u32.to_be_bytes());
+    out.extend_from_slice(b"REMB");
+    let brexp = (ssrcs.len() as u32) << 24 | exponent << 18 | mantissa as u32;
+    out.extend_from_slice(&brexp.to_be_bytes());
+    for ssrc in ssrcs {
+        out.extend_from_slice(&ssrc.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1200-byte packets every 10 ms (960 kbit/s), arriving `queue(i)` late.
+    fn feed(
+        bwe: &mut ReceiveBandwidthEstimator,
+        packets: core::ops::Range<u64>,
+        queue: impl Fn(u64) -> u64,
+    ) {
+        for i in packets {
+            let sent = Duration::from_millis(10 * i);
+            let send_time = (sent.as_nanos() as u64 * AbsSendTime::RATE / 1_000_000_000) as u32;
+            let arrival = sent + Duration::from_millis(20 + queue(i));
+            bwe.on_packet(arrival, AbsSendTime(send_time % AbsSendTime::WRAP), 1200);
+        }
+    }
+
+    #[test]
+    fn backs_off_when_delay_grows() {
+        let mut bwe = ReceiveBandwidthEstimator::new();
+        feed(&mut bwe, 0..300, |_| 0);
+        let steady = bwe.estimate().unwrap();
+        assert_eq!(bwe.usage(), BandwidthUsage::Normal);
+        assert!(steady > 960_000, "{}", steady);
+
+        // A 480 kbit/s bottleneck: every packet queues 10 ms more.
+        feed(&mut bwe, 300..360, |i| 10 * (i - 299));
+        let congested = bwe.estimate().unwrap();
+        assert!(congested < 900_000, "{}", congested);
+    }
+
+    #[test]
+    fn tolerates_arrival_clock_stepping_back() {
+        let mut bwe = ReceiveBandwidthEstimator::new();
+        for i in 0..80u64 {
+            let sent = Duration::from_millis(10 * i);
+            let send_time = (sent.as_nanos() as u64 * AbsSendTime::RATE / 1_000_000_000) as u32;
+            // The receive clock is set back by a second after 40 packets.
+            let step = if i < 40 { 0 } else { 1000 };
+            let arrival = Duration::from_millis(2000 + 10 * i - step);
+            bwe.on_packet(arrival, AbsSendTime(send_time), 1200);
+        }
+        assert!(bwe.estimate().is_some());
+    }
+
+    #[test]
+    fn serializes_remb() {
+        let packet = remb(1, 1_000_000, &[0x1234_5678]);
+        assert_eq!(packet.len(), 24);
+        assert_eq!(packet[..4], [0x8F, 206, 0, 5]);
+        assert_eq!(&packet[12..16], b"REMB");
+        // 1_000_000 = 250_000 << 2.
+        let brexp = u32::from_be_bytes(packet[16..20].try_into().unwrap());
+        assert_eq!(
+            (brexp >> 24, (brexp >> 18) & 0x3F, brexp & 0x3_FFFF),
+            (1, 2, 250_000)
+        );
+    }
+}
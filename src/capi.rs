@@ -0,0 +1,292 @@
+//! C ABI for embedding the reassembler in non-Rust hosts.
+//!
+//! Enabled with the `capi` feature. Build a shared or static library with
+//! `cargo rustc --release --lib --features capi --crate-type cdylib` (or
+//! `staticlib`) and include `include/rtpar.h`.
+//!
+//! All handles are opaque and must be released with their matching `*_free`
+//! function. A panic inside the library aborts instead of unwinding into C.
+
+use crate::{codecs::Codec, reassemble::FrameReassembler, rtp::RtpPacket};
+use core::ptr;
+use core::slice;
+
+pub const RTPAR_CODEC_UNKNOWN: u32 = 0;
+pub const RTPAR_CODEC_AVC: u32 = 1;
+pub const RTPAR_CODEC_HEVC: u32 = 2;
+pub const RTPAR_CODEC_VP9: u32 = 3;
+pub const RTPAR_CODEC_AV1: u32 = 4;
+
+/// A frame has been completed and can be taken with `rtpar_reassembler_take_frame`.
+pub const RTPAR_FRAME_READY: i32 = 1;
+/// The call succeeded.
+pub const RTPAR_OK: i32 = 0;
+/// A required pointer argument was null.
+pub const RTPAR_ERR_NULL: i32 = -1;
+/// The input bytes are not a valid RTP packet.
+pub const RTPAR_ERR_PARSE: i32 = -2;
+/// An argument was out of range (e.g. unknown codec id).
+pub const RTPAR_ERR_INVALID_ARG: i32 = -3;
+/// No completed frame is available.
+pub const RTPAR_ERR_NO_FRAME: i32 = -4;
+
+/// Opaque reassembler handle.
+pub struct RtparReassembler {
+    inner: FrameReassembler,
+    ready: Option<ReadyFrame>,
+}
+
+struct ReadyFrame {
+    data: Vec<u8>,
+    timestamp: u32,
+    ssrc: u32,
+    codec: Codec,
+}
+
+/// A completed frame handed out to C. `data` is owned by the library and must
+/// be released with `rtpar_frame_free`.
+#[repr(C)]
+pub struct RtparFrame {
+    pub data: *mut u8,
+    pub len: usize,
+    pub timestamp: u32,
+    pub ssrc: u32,
+    pub codec: u32,
+}
+
+fn codec_from_id(id: u32) -> Option<Codec> {
+    match id {
+        RTPAR_CODEC_UNKNOWN => Some(Codec::Unknown),
+        RTPAR_CODEC_AVC => Some(Codec::Avc),
+        RTPAR_CODEC_HEVC => Some(Codec::Hevc),
+        RTPAR_CODEC_VP9 => Some(Codec::Vp9),
+        RTPAR_CODEC_AV1 => Some(Codec::Av1),
+        _ => None,
+    }
+}
+
+fn codec_to_id(codec: Codec) -> u32 {
+    match codec {
+        // Custom codecs are only registered from Rust.
+        Codec::Unknown | Codec::Custom(_) => RTPAR_CODEC_UNKNOWN,
+        Codec::Avc => RTPAR_CODEC_AVC,
+        Codec::Hevc => RTPAR_CODEC_HEVC,
+        Codec::Vp9 => RTPAR_CODEC_VP9,
+        Codec::Av1 => RTPAR_CODEC_AV1,
+    }
+}
+
+/// Creates a reassembler. Never returns null.
+#[no_mangle]
+pub extern "C" fn rtpar_reassembler_new() -> *mut RtparReassembler {
+    Box::into_raw(Box::new(RtparReassembler {
+        inner: FrameReassembler::new(),
+        ready: None,
+    }))
+}
+
+/// Destroys a reassembler created by `rtpar_reassembler_new`.
+///
+/// # Safety
+/// `handle` must be null or a pointer returned by `rtpar_reassembler_new`
+/// that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rtpar_reassembler_free(handle: *mut RtparReassembler) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Forces the payload codec instead of guessing it from the first packets.
+/// Packets buffered so far are discarded, so call it before the first push.
+///
+/// # Safety
+/// `handle` must be null or a live reassembler handle.
+#[no_mangle]
+pub unsafe extern "C" fn rtpar_reassembler_set_codec(
+    handle: *mut RtparReassembler,
+    codec: u32,
+) -> i32 {
+    let Some(handle) = handle.as_mut() else {
+        return RTPAR_ERR_NULL;
+    };
+    match codec_from_id(codec) {
+        Some(c) => match FrameReassembler::builder().codec(c).build() {
+            Ok(inner) => {
+                handle.inner = inner;
+                RTPAR_OK
+            }
+            Err(_) => RTPAR_ERR_INVALID_ARG,
+        },
+        None => RTPAR_ERR_INVALID_ARG,
+    }
+}
+
+/// Pushes one RTP packet (the full datagram, header included).
+///
+/// Returns `RTPAR_FRAME_READY` when a frame was completed, `RTPAR_OK` when
+/// the packet was buffered, or a negative error code. A completed frame that
+/// is not taken before the next ready frame is replaced.
+///
+/// # Safety
+/// `handle` must be null or a live reassembler handle, and `data` must be
+/// null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rtpar_reassembler_push(
+    handle: *mut RtparReassembler,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    let Some(handle) = handle.as_mut() else {
+        return RTPAR_ERR_NULL;
+    };
+    if data.is_null() {
+        return RTPAR_ERR_NULL;
+    }
+    let bytes = slice::from_raw_parts(data, len);
+    let pkt = match RtpPacket::parse(bytes) {
+        Ok(pkt) => pkt,
+        Err(_) => return RTPAR_ERR_PARSE,
+    };
+    match handle.inner.push_packet(&pkt) {
+        Some(frame) => {
+            handle.ready = Some(ReadyFrame {
+                data: frame,
+                timestamp: pkt.header.timestamp,
+                ssrc: pkt.header.ssrc,
+                codec: handle.inner.codec().unwrap_or(Codec::Unknown),
+            });
+            RTPAR_FRAME_READY
+        }
+        None => RTPAR_OK,
+    }
+}
+
+/// Moves the most recently completed frame into `out`.
+///
+/// # Safety
+/// `handle` must be null or a live reassembler handle and `out` must be null
+/// or point to writable memory for one `RtparFrame`.
+#[no_mangle]
+pub unsafe extern "C" fn rtpar_reassembler_take_frame(
+    handle: *mut RtparReassembler,
+    out: *mut RtparFrame,
+) -> i32 {
+    let Some(handle) = handle.as_mut() else {
+        return RTPAR_ERR_NULL;
+    };
+    if out.is_null() {
+        return RTPAR_ERR_NULL;
+    }
+    let Some(frame) = handle.ready.take() else {
+        return RTPAR_ERR_NO_FRAME;
+    };
+    let data = frame.data.into_boxed_slice();
+    let len = data.len();
+    out.write(RtparFrame {
+        data: Box::into_raw(data) as *mut u8,
+        len,
+        timestamp: frame.timestamp,
+        ssrc: frame.ssrc,
+        codec: codec_to_id(frame.codec),
+    });
+    RTPAR_OK
+}
+
+/// Releases the bytes of a frame obtained from `rtpar_reassembler_take_frame`
+/// and resets the struct to an empty frame.
+///
+/// # Safety
+/// `frame` must be null or point to a frame filled by
+/// `rtpar_reassembler_take_frame` whose data has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rtpar_frame_free(frame: *mut RtparFrame) {
+    let Some(frame) = frame.as_mut() else {
+        return;
+    };
+    if !frame.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            frame.data, frame.len,
+        )));
+    }
+    frame.data = ptr::null_mut();
+    frame.len = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::StreamBuilder;
+
+    #[test]
+    fn push_and_take_frame_through_c_api() {
+        unsafe {
+            let handle = rtpar_reassembler_new();
+            assert_eq!(
+                rtpar_reassembler_set_codec(handle, RTPAR_CODEC_AVC),
+                RTPAR_OK
+            );
+            let packets = StreamBuilder::new(0x1234)
+                .start_sequence(1)
+                .start_timestamp(90)
+                .frame(&[vec![0x7C, 0x85, 0xAA], vec![0x7C, 0x45, 0xBB]])
+                .build();
+            let [p1, p2] = &packets[..] else {
+                panic!("{} packets", packets.len());
+            };
+            assert_eq!(
+                rtpar_reassembler_push(handle, p1.as_ptr(), p1.len()),
+                RTPAR_OK
+            );
+            assert_eq!(
+                rtpar_reassembler_push(handle, p2.as_ptr(), p2.len()),
+                RTPAR_FRAME_READY
+            );
+
+            let mut frame = RtparFrame {
+                data: ptr::null_mut(),
+                len: 0,
+                timestamp: 0,
+                ssrc: 0,
+                codec: 0,
+            };
+            assert_eq!(rtpar_reassembler_take_frame(handle, &mut frame), RTPAR_OK);
+            assert_eq!(
+                slice::from_raw_parts(frame.data, frame.len),
+                &[0, 0, 0, 1, 0x65, 0xAA, 0xBB]
+            );
+            assert_eq!(frame.timestamp, 90);
+            assert_eq!(frame.ssrc, 0x1234);
+            assert_eq!(frame.codec, RTPAR_CODEC_AVC);
+            rtpar_frame_free(&mut frame);
+            assert!(frame.data.is_null());
+
+            assert_eq!(
+                rtpar_reassembler_take_frame(handle, &mut frame),
+                RTPAR_ERR_NO_FRAME
+            );
+            rtpar_reassembler_free(handle);
+        }
+    }
+
+    #[test]
+    fn rejects_null_and_invalid_input() {
+        unsafe {
+            assert_eq!(
+                rtpar_reassembler_push(ptr::null_mut(), ptr::null(), 0),
+                RTPAR_ERR_NULL
+            );
+            let handle = rtpar_reassembler_new();
+            let short = [0x80u8, 96];
+            assert_eq!(
+                rtpar_reassembler_push(handle, short.as_ptr(), short.len()),
+                RTPAR_ERR_PARSE
+            );
+            assert_eq!(
+                rtpar_reassembler_set_codec(handle, 99),
+                RTPAR_ERR_INVALID_ARG
+            );
+            rtpar_reassembler_free(handle);
+        }
+    }
+}
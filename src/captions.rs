@@ -0,0 +1,193 @@
+//! CEA-608/708 closed captions carried in H.264/H.265 SEI.
+//!
+//! Broadcast encoders put caption data in `user_data_registered_itu_t_t35`
+//! SEI messages as ATSC A/53 `cc_data()` (SCTE 128). [`FrameCaptions`]
+//! collects the valid caption byte pairs of one frame in transmission
+//! order; [`FrameCaptions::cea608`] and [`FrameCaptions::dtvcc`] split them
+//! into the 608 field streams and the 708 DTVCC channel.
+
+use crate::codecs::{
+    sei::{sei_messages, sei_rbsp, SEI_USER_DATA_REGISTERED},
+    Codec,
+};
+use alloc::vec::Vec;
+
+// itu_t_t35_country_code (United States) and provider code (ATSC).
+const T35_USA: u8 = 0xB5;
+const T35_PROVIDER_ATSC: u16 = 0x0031;
+const ATSC_USER_ID: &[u8; 4] = b"GA94";
+const A53_CC_DATA: u8 = 0x03;
+
+/// `cc_type` of a caption byte pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CcType {
+    /// CEA-608 line 21 field 1.
+    Cea608Field1,
+    /// CEA-608 line 21 field 2.
+    Cea608Field2,
+    /// Continuation of a CEA-708 DTVCC packet.
+    DtvccData,
+    /// Start of a CEA-708 DTVCC packet.
+    DtvccStart,
+}
+
+/// One valid `cc_data_1`/`cc_data_2` pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CcData {
+    pub cc_type: CcType,
+    pub data: [u8; 2],
+}
+
+/// The `cc_data()` triplets of an SEI `user_data_registered_itu_t_t35`
+/// payload, or `None` if it does not carry ATSC A/53 captions.
+pub fn a53_cc_data(payload: &[u8]) -> Option<&[u8]> {
+    let (&country, rest) = payload.split_first()?;
+    if country != T35_USA {
+        return None;
+    }
+    let (provider, rest) = rest.split_first_chunk::<2>()?;
+    let (user_id, rest) = rest.split_first_chunk::<4>()?;
+    if u16::from_be_bytes(*provider) != T35_PROVIDER_ATSC || user_id != ATSC_USER_ID {
+        return None;
+    }
+    let (&[data_type, flags, _em_data], rest) = rest.split_first_chunk::<3>()?;
+    // process_cc_data_flag must be set for the triplets to be meaningful.
+    if data_type != A53_CC_DATA || flags & 0x40 == 0 {
+        return None;
+    }
+    let cc_count = (flags & 0x1F) as usize;
+    rest.get(..cc_count * 3)
+}
+
+/// Caption data found in one frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameCaptions {
+    /// Valid pairs in transmission order; invalid (padding) ones are left out.
+    pub cc_data: Vec<CcData>,
+}
+
+impl FrameCaptions {
+    /// Collects the captions of a frame's NAL units.
+    pub fn from_nals<'a>(codec: Codec, nals: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut captions = Self::default();
+        for nal in nals {
+            let Some(rbsp) = sei_rbsp(codec, nal) else {
+                continue;
+            };
+            for message in sei_messages(&rbsp) {
+                if message.payload_type == SEI_USER_DATA_REGISTERED {
+                    captions.push_t35(message.payload);
+                }
+            }
+        }
+        captions
+    }
+
+    /// Appends the captions of a `user_data_registered_itu_t_t35` payload.
+    pub fn push_t35(&mut self, payload: &[u8]) {
+        if let Some(triplets) = a53_cc_data(payload) {
+            self.push_triplets(triplets);
+        }
+    }
+
+    /// Appends the captions of a SMPTE ST 334-2 caption distribution
+    /// packet, as carried in ancillary data.
+    pub fn push_cdp(&mut self, cdp: &[u8]) {
+        let Some((&[0x96, 0x69, len, _rate, flags, _, _], mut rest)) = cdp.split_first_chunk::<7>()
+        else {
+            return;
+        };
+        rest = rest.get(..(len as usize).saturating_sub(7)).unwrap_or(rest);
+        // time_code_section comes first when present.
+        if flags & 0x80 != 0 {
+            match rest.split_first_chunk::<5>() {
+                Some(([0x71, ..], tail)) => rest = tail,
+                _ => return,
+            }
+        }
+        if let Some((&[0x72, count], tail)) = rest.split_first_chunk::<2>() {
+            if flags & 0x40 != 0 {
+                let len = (count & 0x1F) as usize * 3;
+                self.push_triplets(&tail[..len.min(tail.len())]);
+            }
+        }
+    }
+
+    fn push_triplets(&mut self, triplets: &[u8]) {
+        for t in triplets.chunks_exact(3) {
+            // marker_bits (5), cc_valid, cc_type (2).
+            if t[0] & 0x04 == 0 {
+                continue;
+            }
+            let cc_type = match t[0] & 0x03 {
+                0 => CcType::Cea608Field1,
+                1 => CcType::Cea608Field2,
+                2 => CcType::DtvccData,
+                _ => CcType::DtvccStart,
+            };
+            self.cc_data.push(CcData {
+                cc_type,
+                data: [t[1], t[2]],
+            });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cc_data.is_empty()
+    }
+
+    /// CEA-608 byte pairs of line 21 field 1 or 2, odd parity bits included.
+    pub fn cea608(&self, field: u8) -> Vec<u8> {
+        let want = if field == 2 {
+            CcType::Cea608Field2
+        } else {
+            CcType::Cea608Field1
+        };
+        self.bytes(|t| t == want)
+    }
+
+    /// CEA-708 caption channel bytes; a new DTVCC packet begins at each
+    /// [`CcType::DtvccStart`] pair.
+    pub fn dtvcc(&self) -> Vec<u8> {
+        self.bytes(|t| matches!(t, CcType::DtvccData | CcType::DtvccStart))
+    }
+
+    fn bytes(&self, keep: impl Fn(CcType) -> bool) -> Vec<u8> {
+        self.cc_data
+            .iter()
+            .filter(|cc| keep(cc.cc_type))
+            .flat_map(|cc| cc.data)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_cc_data_from_sei() {
+        let mut nal = alloc::vec![0x06, 0x04, 26];
+        nal.extend_from_slice(&[0xB5, 0x00, 0x31, b'G', b'A', b'9', b'4', 0x03]);
+        // process_cc_data_flag, cc_count 5, em_data.
+        nal.extend_from_slice(&[0x45, 0xFF]);
+        nal.extend_from_slice(&[0xFC, 0x94, 0x2C]); // 608 field 1
+        nal.extend_from_slice(&[0xFD, 0x80, 0x80]); // 608 field 2
+        nal.extend_from_slice(&[0xFF, 0x02, 0x21]); // DTVCC start
+        nal.extend_from_slice(&[0xFE, 0x8C, 0x41]); // DTVCC data
+        nal.extend_from_slice(&[0xFA, 0x00, 0x00]); // cc_valid 0
+        nal.extend_from_slice(&[0xFF, 0x80]);
+        let captions = FrameCaptions::from_nals(Codec::Avc, [nal.as_slice()]);
+        assert_eq!(captions.cc_data.len(), 4);
+        assert_eq!(captions.cea608(1), [0x94, 0x2C]);
+        assert_eq!(captions.cea608(2), [0x80, 0x80]);
+        assert_eq!(captions.dtvcc(), [0x02, 0x21, 0x8C, 0x41]);
+
+        // Other T.35 user data is ignored.
+        let afd = [0xB5, 0x00, 0x31, b'D', b'T', b'G', b'1', 0x41, 0x08];
+        assert_eq!(a53_cc_data(&afd), None);
+    }
+}
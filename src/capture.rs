@@ -0,0 +1,246 @@
+//! Reading packet captures taken with other tools.
+//!
+//! [`read_capture`] recognizes libpcap files (RTP over UDP on Ethernet,
+//! Linux cooked or raw IP links), rtpdump files as written by `rtpdump -F
+//! dump` and this crate's own [recordings](crate::replay), and returns their
+//! RTP packets with arrival times relative to the first packet. RTCP and
+//! non-UDP traffic is skipped, as are IP fragments.
+
+use crate::{
+    replay::{RecordedPacket, Recording, ReplayError},
+    rtcp::is_rtcp,
+};
+use std::{time::Duration, vec::Vec};
+
+const PCAP_MICROS: [u8; 4] = [0xA1, 0xB2, 0xC3, 0xD4];
+const PCAP_NANOS: [u8; 4] = [0xA1, 0xB2, 0x3C, 0x4D];
+const RTPDUMP_MAGIC: &[u8] = b"#!rtpplay1.0 ";
+
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// Not a pcap, rtpdump or recording file.
+    UnknownFormat,
+    /// A pcap link layer other than Ethernet, Linux cooked or raw IP.
+    UnsupportedLinkType(u32),
+    /// The record starting at `offset` is cut off.
+    Truncated {
+        offset: usize,
+    },
+    Recording(ReplayError),
+}
+
+impl core::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CaptureError::UnknownFormat => write!(f, "not a pcap, rtpdump or recording file"),
+            CaptureError::UnsupportedLinkType(t) => {
+                write!(f, "unsupported pcap link type {}", t)
+            }
+            CaptureError::Truncated { offset } => {
+                write!(f, "capture truncated in the record at offset {}", offset)
+            }
+            CaptureError::Recording(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<ReplayError> for CaptureError {
+    fn from(e: ReplayError) -> Self {
+        CaptureError::Recording(e)
+    }
+}
+
+/// The RTP packets of a capture in any supported format.
+pub fn read_capture(bytes: &[u8]) -> Result<Vec<RecordedPacket>, CaptureError> {
+    match bytes {
+        [a, b, c, d, ..] if is_pcap_magic([*a, *b, *c, *d]) => read_pcap(bytes),
+        _ if bytes.starts_with(RTPDUMP_MAGIC) => read_rtpdump(bytes),
+        _ if bytes.starts_with(b"RTPR") => Ok(Recording::parse(bytes)?.packets),
+        _ => Err(CaptureError::UnknownFormat),
+    }
+}
+
+fn is_pcap_magic(magic: [u8; 4]) -> bool {
+    let swapped = u32::from_le_bytes(magic).to_be_bytes();
+    [PCAP_MICROS, PCAP_NANOS].contains(&magic) || [PCAP_MICROS, PCAP_NANOS].contains(&swapped)
+}
+
+/// The RTP packets of a libpcap file.
+pub fn read_pcap(bytes: &[u8]) -> Result<Vec<RecordedPacket>, CaptureError> {
+    let header = bytes.get(..24).ok_or(CaptureError::UnknownFormat)?;
+    let magic = [header[0], header[1], header[2], header[3]];
+    let (big_endian, nanos) = match magic {
+        PCAP_MICROS => (true, false),
+        PCAP_NANOS => (true, true),
+        _ if u32::from_le_bytes(magic) == u32::from_be_bytes(PCAP_MICROS) => (false, false),
+        _ if u32::from_le_bytes(magic) == u32::from_be_bytes(PCAP_NANOS) => (false, true),
+        _ => return Err(CaptureError::UnknownFormat),
+    };
+    let word = |b: &[u8], at: usize| {
+        let w = [b[at], b[at + 1], b[at + 2], b[at + 3]];
+        if big_endian {
+            u32::from_be_bytes(w)
+        } else {
+            u32::from_le_bytes(w)
+        }
+    };
+    let link_type = word(header, 20);
+    if ![
+        LINKTYPE_ETHERNET,
+        LINKTYPE_RAW,
+        LINKTYPE_LINUX_SLL,
+        LINKTYPE_IPV4,
+        LINKTYPE_IPV6,
+    ]
+    .contains(&link_type)
+    {
+        return Err(CaptureError::UnsupportedLinkType(link_type));
+    }
+    let mut packets = Vec::new();
+    let mut first = None;
+    let mut offset = 24;
+    while offset < bytes.len() {
+        let truncated = CaptureError::Truncated { offset };
+        let record = bytes.get(offset..offset + 16).ok_or(truncated.clone())?;
+        let (secs, fraction, len) = (word(record, 0), word(record, 4), word(record, 8));
+        let frame = bytes
+            .get(offset + 16..offset + 16 + len as usize)
+            .ok_or(truncated)?;
+        offset += 16 + frame.len();
+        let time = Duration::new(
+            secs as u64,
+            if nanos {
+                fraction
+            } else {
+                fraction.saturating_mul(1000)
+            },
+        );
+        let first = *first.get_or_insert(time);
+        let payload = link_payload(link_type, frame).and_then(udp_payload);
+        if let Some(data) = payload.filter(|d| !is_rtcp(d)) {
+            packets.push(RecordedPacket {
+                arrival: time.saturating_sub(first),
+                data: data.to_vec(),
+            });
+        }
+    }
+    Ok(packets)
+}
+
+// The IP packet inside a link layer frame.
+fn link_payload(link_type: u32, frame: &[u8]) -> Option<&[u8]> {
+    let (ethertype, mut rest) = match link_type {
+        LINKTYPE_ETHERNET => (frame.get(12..14)?, frame.get(14..)?),
+        LINKTYPE_LINUX_SLL => (frame.get(14..16)?, frame.get(16..)?),
+        _ => return Some(frame),
+    };
+    let mut ethertype = u16::from_be_bytes([ethertype[0], ethertype[1]]);
+    // 802.1Q VLAN tags.
+    while ethertype == 0x8100 {
+        ethertype = u16::from_be_bytes([*rest.get(2)?, *rest.get(3)?]);
+        rest = rest.get(4..)?;
+    }
+    matches!(ethertype, 0x0800 | 0x86DD).then_some(rest)
+}
+
+// The UDP payload of an unfragmented IPv4 or IPv6 packet.
+fn udp_payload(ip: &[u8]) -> Option<&[u8]> {
+    let udp = match ip.first()? >> 4 {
+        4 => {
+            let header_len = (ip[0] & 0x0F) as usize * 4;
+            let fragment = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]);
+            if *ip.get(9)? != 17 || fragment & 0x3FFF != 0 {
+                return None;
+            }
+            let total = u16::from_be_bytes([ip[2], ip[3]]) as usize;
+            ip.get(header_len..total.min(ip.len()))?
+        }
+        6 => {
+            // Extension headers are not followed.
+            if *ip.get(6)? != 17 {
+                return None;
+            }
+            ip.get(40..)?
+        }
+        _ => return None,
+    };
+    let len = u16::from_be_bytes([*udp.get(4)?, *udp.get(5)?]) as usize;
+    udp.get(8..len.clamp(8, udp.len()))
+}
+
+/// The RTP packets of an rtpdump file.
+pub fn read_rtpdump(bytes: &[u8]) -> Result<Vec<RecordedPacket>, CaptureError> {
+    let text_end = bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .filter(|_| bytes.starts_with(RTPDUMP_MAGIC))
+        .ok_or(CaptureError::UnknownFormat)?;
+    // The text line is followed by the start time, source address and port.
+    let mut offset = text_end + 1 + 16;
+    if offset > bytes.len() {
+        return Err(CaptureError::Truncated {
+            offset: text_end + 1,
+        });
+    }
+    let mut packets = Vec::new();
+    let mut first = None;
+    while offset < bytes.len() {
+        let truncated = CaptureError::Truncated { offset };
+        let header = bytes.get(offset..offset + 8).ok_or(truncated.clone())?;
+        let len = u16::from_be_bytes([header[0], header[1]]) as usize;
+        let rtp_len = u16::from_be_bytes([header[2], header[3]]);
+        let millis = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let data = bytes
+            .get(offset + 8..offset + len.max(8))
+            .ok_or(truncated)?;
+        offset += len.max(8);
+        // RTCP packets are stored with an RTP length of 0.
+        if rtp_len != 0 {
+            let first = *first.get_or_insert(millis);
+            packets.push(RecordedPacket {
+                arrival: Duration::from_millis(millis.wrapping_sub(first) as u64),
+                data: data.to_vec(),
+            });
+        }
+    }
+    Ok(packets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{capture, rtp_packet};
+
+    #[test]
+    fn reads_rtp_from_pcap_and_rtpdump() {
+        let packets = [
+            (Duration::ZERO, rtp_packet(&[0x65, 1], false, 1)),
+            // An RTCP receiver report on the same port.
+            (
+                Duration::from_millis(5),
+                [0x80, 201, 0, 1, 0, 0, 0, 1].to_vec(),
+            ),
+            (Duration::from_millis(20), rtp_packet(&[0x65, 2], true, 2)),
+        ];
+        for bytes in [capture::pcap(&packets), capture::rtpdump(&packets)] {
+            let read = read_capture(&bytes).unwrap();
+            assert_eq!(read.len(), 2);
+            assert_eq!(read[0].data, packets[0].1);
+            assert_eq!(read[1].arrival, Duration::from_millis(20));
+            let cut = &bytes[..bytes.len() - 1];
+            assert!(matches!(
+                read_capture(cut),
+                Err(CaptureError::Truncated { .. })
+            ));
+        }
+        assert_eq!(read_capture(b"RIFF"), Err(CaptureError::UnknownFormat));
+    }
+}
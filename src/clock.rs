@@ -0,0 +1,255 @@
+//! Mapping RTP timestamps to wall-clock time.
+//!
+//! Wall-clock times are [`Duration`]s since the NTP epoch (1900-01-01), the
+//! time base of RTCP sender reports and the abs-capture-time extension.
+//! [`MediaTime`] does the RTP timestamp arithmetic at a stream's clock rate.
+
+use crate::extensions::AbsCaptureTime;
+use core::time::Duration;
+
+/// Converts a 32.32 fixed point NTP timestamp to nanoseconds.
+pub fn ntp_to_nanos(ntp: u64) -> u64 {
+    (ntp >> 32) * 1_000_000_000 + (((ntp & 0xFFFF_FFFF) * 1_000_000_000) >> 32)
+}
+
+/// Converts an NTP timestamp to a `Duration` since the NTP epoch.
+pub fn ntp_to_duration(ntp: u64) -> Duration {
+    Duration::from_nanos(ntp_to_nanos(ntp))
+}
+
+/// Converts a `Duration` since the NTP epoch to a 32.32 NTP timestamp.
+pub fn duration_to_ntp(time: Duration) -> u64 {
+    (time.as_secs() << 32) | (((time.subsec_nanos() as u64) << 32) / 1_000_000_000)
+}
+
+/// RTP timestamp arithmetic at one clock rate.
+///
+/// Timestamps wrap modulo 2^32, so two of them are compared through their
+/// signed difference, which is right while they are less than 2^31 ticks
+/// apart (6.6 hours at 90 kHz).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MediaTime {
+    clock_rate: u32,
+}
+
+impl MediaTime {
+    /// The 90 kHz clock of every video payload format.
+    pub const VIDEO: MediaTime = MediaTime::new(90_000);
+
+    /// A zero rate is treated as 1 Hz.
+    pub const fn new(clock_rate: u32) -> Self {
+        Self {
+            clock_rate: if clock_rate == 0 { 1 } else { clock_rate },
+        }
+    }
+
+    pub fn clock_rate(self) -> u32 {
+        self.clock_rate
+    }
+
+    /// Ticks from `earlier` to `later`, negative if `later` is older.
+    pub fn ticks_between(later: u32, earlier: u32) -> i32 {
+        later.wrapping_sub(earlier) as i32
+    }
+
+    /// Nanoseconds spanned by `ticks`, truncated toward zero.
+    pub fn ticks_to_nanos(self, ticks: i64) -> i64 {
+        (ticks as i128 * 1_000_000_000 / self.clock_rate as i128) as i64
+    }
+
+    pub fn ticks_to_duration(self, ticks: u64) -> Duration {
+        let nanos = ticks as u128 * 1_000_000_000 / self.clock_rate as u128;
+        Duration::from_nanos(nanos.min(u64::MAX as u128) as u64)
+    }
+
+    /// Whole ticks in `duration`; truncate to `u32` for a wrapping timestamp.
+    pub fn duration_to_ticks(self, duration: Duration) -> u64 {
+        let ticks = duration.as_nanos() * self.clock_rate as u128 / 1_000_000_000;
+        ticks.min(u64::MAX as u128) as u64
+    }
+
+    /// Nanoseconds from `earlier` to `later`, as [`ticks_between`](Self::ticks_between).
+    pub fn nanos_between(self, later: u32, earlier: u32) -> i64 {
+        self.ticks_to_nanos(Self::ticks_between(later, earlier) as i64)
+    }
+
+    /// RTP timestamp of `ptp`, a time since the PTP epoch, for senders
+    /// whose media clock counts from that epoch (SMPTE ST 2110-10).
+    pub fn ptp_to_timestamp(self, ptp: Duration) -> u32 {
+        self.duration_to_ticks(ptp) as u32
+    }
+
+    /// PTP time of a ST 2110-10 `timestamp`, taking the wrap closest to
+    /// `near`, e.g. the receiver's own PTP time; exact to a tick.
+    pub fn timestamp_to_ptp(self, timestamp: u32, near: Duration) -> Duration {
+        let ticks = self.duration_to_ticks(near);
+        let delta = Self::ticks_between(timestamp, ticks as u32) as i64;
+        self.ticks_to_duration(ticks.saturating_add_signed(delta))
+    }
+
+    /// `ticks` of this clock in ticks of `to`, e.g. a 90 kHz duration on a
+    /// 48 kHz audio clock; truncated toward zero.
+    pub fn rescale(self, ticks: i64, to: MediaTime) -> i64 {
+        (ticks as i128 * to.clock_rate as i128 / self.clock_rate as i128) as i64
+    }
+}
+
+/// Extends wrapping RTP timestamps to a monotonic 64-bit timeline, for
+/// presentation timestamps that do not wrap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimestampUnwrapper {
+    last: Option<(u32, i64)>,
+}
+
+impl TimestampUnwrapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `timestamp` on the extended timeline; the first one maps to itself
+    /// and later ones move by their signed distance from the previous.
+    pub fn unwrap(&mut self, timestamp: u32) -> i64 {
+        let extended = match self.last {
+            Some((last, extended)) => extended + MediaTime::ticks_between(timestamp, last) as i64,
+            None => timestamp as i64,
+        };
+        self.last = Some((timestamp, extended));
+        extended
+    }
+}
+
+/// Capture time of one stream's RTP timestamps on the sender's clock, and
+/// the offset between that clock and the receiver's.
+///
+/// The clock is anchored by an abs-capture-time extension (or an RTCP SR);
+/// later timestamps are extrapolated at the stream's clock rate. Until an
+/// offset is known the two clocks are assumed to agree.
+#[derive(Debug, Clone)]
+pub struct MediaClock {
+    time: MediaTime,
+    // RTP timestamp and its sender capture time in nanoseconds.
+    anchor: Option<(u32, i128)>,
+    // Receiver clock minus sender clock, in nanoseconds.
+    offset: i128,
+}
+
+impl MediaClock {
+    pub fn new(clock_rate: u32) -> Self {
+        Self {
+            time: MediaTime::new(clock_rate),
+            anchor: None,
+            offset: 0,
+        }
+    }
+
+    /// Anchors the clock with the capture time of a packet carrying
+    /// `rtp_timestamp`.
+    pub fn on_capture_time(&mut self, rtp_timestamp: u32, capture: &AbsCaptureTime) {
+        self.anchor = Some((rtp_timestamp, capture.sender_capture_nanos()));
+    }
+
+    /// Anchors the clock with an RTCP SR (`ntp` and `rtp_timestamp` of the
+    /// report) received at `arrival` on the receiver's clock, and estimates
+    /// the clock offset assuming the report took half of `rtt` to arrive.
+    pub fn on_sender_report(
+        &mut self,
+        rtp_timestamp: u32,
+        ntp: u64,
+        arrival: Duration,
+        rtt: Option<Duration>,
+    ) {
+        let sent = ntp_to_nanos(ntp) as i128;
+        self.anchor = Some((rtp_timestamp, sent));
+        let one_way = rtt.unwrap_or_default().as_nanos() as i128 / 2;
+        self.offset = arrival.as_nanos() as i128 - one_way - sent;
+    }
+
+    /// Sets the receiver clock minus the sender clock, in nanoseconds.
+    pub fn set_clock_offset(&mut self, nanos: i64) {
+        self.offset = nanos as i128;
+    }
+
+    pub fn clock_offset(&self) -> i64 {
+        self.offset as i64
+    }
+
+    /// Sender wall-clock time at which `rtp_timestamp` was captured.
+    pub fn capture_time(&self, rtp_timestamp: u32) -> Option<Duration> {
+        let nanos = self.capture_nanos(rtp_timestamp)?;
+        u64::try_from(nanos).ok().map(Duration::from_nanos)
+    }
+
+    /// Time from the capture of `rtp_timestamp` to `now` on the receiver's
+    /// clock, i.e. the end-to-end latency of a frame completed at `now`.
+    pub fn latency(&self, rtp_timestamp: u32, now: Duration) -> Option<Duration> {
+        let captured = self.capture_nanos(rtp_timestamp)? + self.offset;
+        let latency = now.as_nanos() as i128 - captured;
+        Some(Duration::from_nanos(
+            latency.clamp(0, u64::MAX as i128) as u64
+        ))
+    }
+
+    fn capture_nanos(&self, rtp_timestamp: u32) -> Option<i128> {
+        let (anchor_ts, anchor_nanos) = self.anchor?;
+        Some(anchor_nanos + self.time.nanos_between(rtp_timestamp, anchor_ts) as i128)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latency_from_capture_time_and_offset() {
+        let ms = Duration::from_millis;
+        let mut clock = MediaClock::new(90_000);
+        assert_eq!(clock.latency(0, ms(0)), None);
+        clock.on_capture_time(
+            1000,
+            &AbsCaptureTime {
+                capture_timestamp: duration_to_ntp(Duration::from_secs(100)),
+                estimated_capture_clock_offset: None,
+            },
+        );
+        // 3000 ticks later is 33.3 ms; 90 ticks earlier is 1 ms.
+        assert_eq!(
+            clock.capture_time(4000),
+            Some(Duration::new(100, 33_333_333))
+        );
+        assert_eq!(clock.capture_time(910), Some(ms(99_999)));
+        assert_eq!(clock.latency(1000, ms(100_080)), Some(ms(80)));
+
+        // The receiver clock runs 2 s ahead: an SR sent at 200 s arrives at
+        // 202.05 s with a 100 ms round trip.
+        clock.on_sender_report(0, duration_to_ntp(ms(200_000)), ms(202_050), Some(ms(100)));
+        assert_eq!(clock.clock_offset(), 2_000_000_000);
+        assert_eq!(clock.latency(9000, ms(202_140)), Some(ms(40)));
+        assert_eq!(ntp_to_duration(duration_to_ntp(ms(1500))), ms(1500));
+    }
+
+    #[test]
+    fn media_time_wraps_and_rescales() {
+        let video = MediaTime::VIDEO;
+        assert_eq!(MediaTime::ticks_between(5, u32::MAX - 4), 10);
+        assert_eq!(MediaTime::ticks_between(u32::MAX - 4, 5), -10);
+        assert_eq!(video.nanos_between(90, 180), -1_000_000);
+        assert_eq!(video.ticks_to_duration(3000), Duration::new(0, 33_333_333));
+        assert_eq!(video.duration_to_ticks(Duration::from_millis(20)), 1800);
+        // One 30 fps frame at 48 kHz.
+        assert_eq!(video.rescale(3000, MediaTime::new(48_000)), 1600);
+
+        // ST 2110 media clocks count from the PTP epoch.
+        let ptp = Duration::new(1_700_000_000, 500_000_000);
+        let timestamp = video.ptp_to_timestamp(ptp);
+        assert_eq!(timestamp, (153_000_000_045_000u64 % (1 << 32)) as u32);
+        let near = ptp + Duration::from_secs(5);
+        assert_eq!(video.timestamp_to_ptp(timestamp, near), ptp);
+
+        let mut unwrapper = TimestampUnwrapper::new();
+        assert_eq!(unwrapper.unwrap(u32::MAX - 1499), u32::MAX as i64 - 1499);
+        let pts = unwrapper.unwrap(1500);
+        assert_eq!(pts, u32::MAX as i64 + 1501);
+        assert_eq!(unwrapper.unwrap(u32::MAX - 1499), u32::MAX as i64 - 1499);
+    }
+}
@@ -0,0 +1,88 @@
+//! Comfort noise payloads (RFC 3389).
+//!
+//! Audio senders using silence suppression send a comfort noise packet at
+//! the start of a silence period, and from time to time during it, instead
+//! of audio. It uses static payload type 13 or a dynamic one negotiated as
+//! `CN` in SDP. Such packets appear in the same capture, and often on the
+//! same SSRC, as audio; [`FrameAnalyzer`](crate::analyze::FrameAnalyzer) and
+//! [`FrameReassembler`](crate::reassemble::FrameReassembler) set them apart
+//! rather than guessing a video codec from them.
+
+use alloc::vec::Vec;
+
+/// Static payload type of comfort noise at 8 kHz.
+pub const CN_PAYLOAD_TYPE: u8 = 13;
+
+/// Whether `payload_type` is comfort noise: the static type or the
+/// dynamic one negotiated for the session, if any.
+pub fn is_comfort_noise(payload_type: u8, dynamic: Option<u8>) -> bool {
+    payload_type == CN_PAYLOAD_TYPE || dynamic == Some(payload_type)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CnError {
+    /// The payload has no noise level byte.
+    Empty,
+    /// The noise level's top bit is reserved and must be zero.
+    InvalidLevel(u8),
+}
+
+impl core::fmt::Display for CnError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CnError::Empty => f.write_str("empty comfort noise payload"),
+            CnError::InvalidLevel(level) => {
+                write!(f, "invalid comfort noise level {:#04x}", level)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CnError {}
+
+/// A comfort noise payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComfortNoise {
+    /// Noise level in -dBov, 0 to 127.
+    pub level: u8,
+    /// Quantized reflection coefficients of the noise spectrum; empty when
+    /// only the level is sent.
+    pub reflection_coefficients: Vec<u8>,
+}
+
+impl ComfortNoise {
+    pub fn parse(payload: &[u8]) -> Result<Self, CnError> {
+        let (&level, coefficients) = payload.split_first().ok_or(CnError::Empty)?;
+        if level & 0x80 != 0 {
+            return Err(CnError::InvalidLevel(level));
+        }
+        Ok(Self {
+            level,
+            reflection_coefficients: coefficients.to_vec(),
+        })
+    }
+
+    /// Noise level in dBov, 0 to -127.
+    pub fn dbov(&self) -> i8 {
+        -(self.level as i8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_level_and_spectrum() {
+        let cn = ComfortNoise::parse(&[0x40, 0x80, 0x7F]).unwrap();
+        assert_eq!((cn.dbov(), cn.reflection_coefficients.len()), (-64, 2));
+        assert_eq!(
+            ComfortNoise::parse(&[0x80]),
+            Err(CnError::InvalidLevel(0x80))
+        );
+        assert!(is_comfort_noise(13, None) && is_comfort_noise(118, Some(118)));
+        assert!(!is_comfort_noise(96, Some(118)));
+    }
+}
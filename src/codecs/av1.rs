@@ -1,45 +1,390 @@
+use crate::bitstream::{BitError, BitReader};
+use alloc::vec::Vec;
+
+pub const OBU_SEQUENCE_HEADER: u8 = 1;
+pub const OBU_TEMPORAL_DELIMITER: u8 = 2;
+pub const OBU_TILE_LIST: u8 = 8;
+pub const OBU_PADDING: u8 = 15;
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Av1PayloadHdr {
+    // First OBU element continues an OBU from the previous packet.
     pub z_bit: bool,
+    // Last OBU element continues in the next packet.
     pub y_bit: bool,
+    // Number of OBU elements (0 means every element is length-prefixed).
+    pub w: u8,
+    // First packet of a coded video sequence.
     pub n_bit: bool,
-    pub w_bit: bool,
-    pub t_bit: bool,
-    pub k_bit: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Av1Error {
-    BufferTooShort,
+    /// The data ends before `field`, which starts at `offset`.
+    BufferTooShort { field: &'static str, offset: usize },
+    /// The length or size field at `offset` is malformed or runs past the end.
+    InvalidLength { field: &'static str, offset: usize },
+}
+
+impl core::fmt::Display for Av1Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Av1Error::BufferTooShort { field, offset } => {
+                write!(f, "payload too short for {} at offset {}", field, offset)
+            }
+            Av1Error::InvalidLength { field, offset } => {
+                write!(f, "invalid {} at offset {}", field, offset)
+            }
+        }
+    }
 }
 
-// Parse the minimal AV1 RTP payload header as per RFC 9364 first octet
+#[cfg(feature = "std")]
+impl std::error::Error for Av1Error {}
+
+// Parse the AV1 aggregation header as per RFC 9364 (|Z|Y| W |N|-|-|-|)
 pub fn parse_av1_payload_header(payload: &[u8]) -> Result<(Av1PayloadHdr, usize), Av1Error> {
     if payload.is_empty() {
-        return Err(Av1Error::BufferTooShort);
+        return Err(Av1Error::BufferTooShort {
+            field: "aggregation header",
+            offset: 0,
+        });
     }
     let b0 = payload[0];
     let hdr = Av1PayloadHdr {
         z_bit: (b0 & 0x80) != 0,
         y_bit: (b0 & 0x40) != 0,
-        n_bit: (b0 & 0x20) != 0,
-        w_bit: (b0 & 0x10) != 0,
-        t_bit: (b0 & 0x08) != 0,
-        k_bit: (b0 & 0x04) != 0,
+        w: (b0 >> 4) & 0x03,
+        n_bit: (b0 & 0x08) != 0,
     };
     Ok((hdr, 1))
 }
 
+impl Av1PayloadHdr {
+    pub fn to_byte(&self) -> u8 {
+        ((self.z_bit as u8) << 7)
+            | ((self.y_bit as u8) << 6)
+            | ((self.w & 0x03) << 4)
+            | ((self.n_bit as u8) << 3)
+    }
+}
+
+/// Splits an RTP payload into its OBU elements.
+pub fn parse_av1_obu_elements(payload: &[u8]) -> Result<(Av1PayloadHdr, Vec<&[u8]>), Av1Error> {
+    let (hdr, mut i) = parse_av1_payload_header(payload)?;
+    let mut elements = Vec::new();
+    while i < payload.len() {
+        // With W > 0 the last element carries no length field.
+        if hdr.w != 0 && elements.len() + 1 == hdr.w as usize {
+            elements.push(&payload[i..]);
+            break;
+        }
+        let at = i;
+        let invalid = || Av1Error::InvalidLength {
+            field: "OBU element length",
+            offset: at,
+        };
+        let (len, n) = read_leb128(&payload[i..]).ok_or_else(invalid)?;
+        i += n;
+        let end = i
+            .checked_add(len as usize)
+            .filter(|&end| end <= payload.len())
+            .ok_or_else(invalid)?;
+        elements.push(&payload[i..end]);
+        i = end;
+    }
+    if hdr.w != 0 && elements.len() != hdr.w as usize {
+        return Err(Av1Error::InvalidLength {
+            field: "OBU element count",
+            offset: 0,
+        });
+    }
+    Ok((hdr, elements))
+}
+
+/// Reads an unsigned LEB128 value, returning it with its encoded length.
+pub fn read_leb128(buf: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &b) in buf.iter().take(8).enumerate() {
+        value |= ((b & 0x7F) as u64) << (i * 7);
+        if b & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+pub fn write_leb128(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let b = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(b);
+            return;
+        }
+        out.push(b | 0x80);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObuHeader {
+    pub obu_type: u8,
+    pub has_extension: bool,
+    pub has_size_field: bool,
+}
+
+impl ObuHeader {
+    pub fn parse(b: u8) -> Self {
+        Self {
+            obu_type: (b >> 3) & 0x0F,
+            has_extension: (b & 0x04) != 0,
+            has_size_field: (b & 0x02) != 0,
+        }
+    }
+
+    /// Length of the header including the extension byte.
+    pub fn header_len(&self) -> usize {
+        1 + self.has_extension as usize
+    }
+}
+
+/// Splits a low-overhead bitstream temporal unit into OBUs (headers kept).
+/// An OBU without a size field extends to the end of `data`.
+pub fn split_obus(data: &[u8]) -> Result<Vec<&[u8]>, Av1Error> {
+    let mut obus = Vec::new();
+    let mut i = 0usize;
+    while i < data.len() {
+        let hdr = ObuHeader::parse(data[i]);
+        if !hdr.has_size_field {
+            obus.push(&data[i..]);
+            break;
+        }
+        let size_at = i + hdr.header_len();
+        let (size, n) =
+            data.get(size_at..)
+                .and_then(read_leb128)
+                .ok_or(Av1Error::BufferTooShort {
+                    field: "obu_size",
+                    offset: size_at,
+                })?;
+        let end = (size_at + n)
+            .checked_add(size as usize)
+            .filter(|&end| end <= data.len())
+            .ok_or(Av1Error::InvalidLength {
+                field: "obu_size",
+                offset: size_at,
+            })?;
+        obus.push(&data[i..end]);
+        i = end;
+    }
+    Ok(obus)
+}
+
+/// Appends `obu` to `out` with `obu_has_size_field` set, as decoders expect
+/// in a low-overhead bitstream. OBUs already carrying a size are copied.
+pub fn write_obu_with_size(obu: &[u8], out: &mut Vec<u8>) {
+    let Some(&first) = obu.first() else {
+        return;
+    };
+    let hdr = ObuHeader::parse(first);
+    if hdr.has_size_field || obu.len() < hdr.header_len() {
+        out.extend_from_slice(obu);
+        return;
+    }
+    out.push(first | 0x02);
+    out.extend_from_slice(&obu[1..hdr.header_len()]);
+    write_leb128((obu.len() - hdr.header_len()) as u64, out);
+    out.extend_from_slice(&obu[hdr.header_len()..]);
+}
+
+/// Fields of a sequence header OBU needed to describe the stream (AV1
+/// 5.5), from the first operating point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Av1SequenceHeader {
+    pub seq_profile: u8,
+    pub seq_level_idx: u8,
+    pub seq_tier: bool,
+    pub max_frame_width: u32,
+    pub max_frame_height: u32,
+    /// 8, 10 or 12.
+    pub bit_depth: u8,
+    pub monochrome: bool,
+    pub subsampling_x: bool,
+    pub subsampling_y: bool,
+    pub chroma_sample_position: u8,
+}
+
+impl Av1SequenceHeader {
+    /// Parses the payload of a sequence header OBU (header and size
+    /// field excluded).
+    pub fn parse(payload: &[u8]) -> Result<Self, BitError> {
+        let mut r = BitReader::new(payload);
+        let seq_profile = r.read_bits(3)? as u8;
+        r.skip(1)?; // still_picture
+        let reduced_still_picture_header = r.read_bit()?;
+        let (seq_level_idx, seq_tier) = if reduced_still_picture_header {
+            (r.read_bits(5)? as u8, false)
+        } else {
+            let mut buffer_delay_bits = None;
+            if r.read_bit()? {
+                r.skip(64)?; // num_units_in_display_tick, time_scale
+                if r.read_bit()? {
+                    read_uvlc(&mut r)?; // num_ticks_per_picture_minus_1
+                }
+                if r.read_bit()? {
+                    buffer_delay_bits = Some(r.read_bits(5)? as usize + 1);
+                    r.skip(32 + 5 + 5)?;
+                }
+            }
+            let initial_display_delay_present = r.read_bit()?;
+            let operating_points = r.read_bits(5)? + 1;
+            let mut first = None;
+            for _ in 0..operating_points {
+                r.skip(12)?; // operating_point_idc
+                let level = r.read_bits(5)? as u8;
+                let tier = level > 7 && r.read_bit()?;
+                first.get_or_insert((level, tier));
+                if let Some(n) = buffer_delay_bits {
+                    if r.read_bit()? {
+                        r.skip(2 * n + 1)?;
+                    }
+                }
+                if initial_display_delay_present && r.read_bit()? {
+                    r.skip(4)?;
+                }
+            }
+            first.unwrap_or_default()
+        };
+        let width_bits = r.read_bits(4)? + 1;
+        let height_bits = r.read_bits(4)? + 1;
+        let max_frame_width = r.read_bits(width_bits)? + 1;
+        let max_frame_height = r.read_bits(height_bits)? + 1;
+        if !reduced_still_picture_header && r.read_bit()? {
+            r.skip(4 + 3)?; // frame id lengths
+        }
+        r.skip(3)?; // use_128x128_superblock, enable_filter_intra, enable_intra_edge_filter
+        if !reduced_still_picture_header {
+            r.skip(4)?; // interintra, masked compound, warped motion, dual filter
+            let enable_order_hint = r.read_bit()?;
+            if enable_order_hint {
+                r.skip(2)?; // enable_jnt_comp, enable_ref_frame_mvs
+            }
+            let force_screen_content_tools = r.read_bit()? || r.read_bit()?;
+            if force_screen_content_tools && !r.read_bit()? {
+                r.skip(1)?; // seq_force_integer_mv
+            }
+            if enable_order_hint {
+                r.skip(3)?; // order_hint_bits_minus_1
+            }
+        }
+        r.skip(3)?; // enable_superres, enable_cdef, enable_restoration
+
+        // color_config()
+        let high_bitdepth = r.read_bit()?;
+        let bit_depth = match (seq_profile, high_bitdepth) {
+            (2, true) if r.read_bit()? => 12,
+            (_, true) => 10,
+            _ => 8,
+        };
+        let monochrome = seq_profile != 1 && r.read_bit()?;
+        let (mut primaries, mut transfer, mut matrix) = (2, 2, 2);
+        if r.read_bit()? {
+            primaries = r.read_bits(8)?;
+            transfer = r.read_bits(8)?;
+            matrix = r.read_bits(8)?;
+        }
+        let (subsampling_x, subsampling_y) = if monochrome {
+            (true, true)
+        } else if (primaries, transfer, matrix) == (1, 13, 0) {
+            // sRGB
+            (false, false)
+        } else {
+            r.skip(1)?; // color_range
+            match seq_profile {
+                0 => (true, true),
+                1 => (false, false),
+                _ if bit_depth == 12 => {
+                    let x = r.read_bit()?;
+                    (x, x && r.read_bit()?)
+                }
+                _ => (true, false),
+            }
+        };
+        let chroma_sample_position = if !monochrome && subsampling_x && subsampling_y {
+            r.read_bits(2)? as u8
+        } else {
+            0
+        };
+        Ok(Self {
+            seq_profile,
+            seq_level_idx,
+            seq_tier,
+            max_frame_width,
+            max_frame_height,
+            bit_depth,
+            monochrome,
+            subsampling_x,
+            subsampling_y,
+            chroma_sample_position,
+        })
+    }
+}
+
+fn read_uvlc(r: &mut BitReader<'_>) -> Result<u32, BitError> {
+    let mut leading_zeros = 0;
+    while !r.read_bit()? {
+        leading_zeros += 1;
+    }
+    if leading_zeros >= 32 {
+        return Ok(u32::MAX);
+    }
+    Ok(r.read_bits(leading_zeros)? + ((1u64 << leading_zeros) - 1) as u32)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn parse_av1_header_basic() {
-        let b0 = 0x04; // K=1
+        let b0 = 0x08 | 0x20; // N=1, W=2
         let (h, off) = parse_av1_payload_header(&[b0, 0xAA]).unwrap();
-        assert!(h.k_bit);
-        assert!(!h.t_bit);
+        assert!(h.n_bit);
+        assert_eq!(h.w, 2);
+        assert!(!h.z_bit && !h.y_bit);
+        assert_eq!(h.to_byte(), b0);
         assert_eq!(off, 1);
     }
+
+    #[test]
+    fn obu_elements_and_size_fields() {
+        // W=2: first element length-prefixed, last one runs to the end.
+        let payload = [0x20, 0x02, 0x08, 0x01, 0x30, 0xAA, 0xBB];
+        let (_, elements) = parse_av1_obu_elements(&payload).unwrap();
+        assert_eq!(elements, [&[0x08, 0x01][..], &[0x30, 0xAA, 0xBB]]);
+        assert_eq!(
+            parse_av1_obu_elements(&[0x00, 0x05, 0x01]),
+            Err(Av1Error::InvalidLength {
+                field: "OBU element length",
+                offset: 1
+            })
+        );
+
+        let mut tu = Vec::new();
+        write_obu_with_size(elements[0], &mut tu);
+        write_obu_with_size(elements[1], &mut tu);
+        assert_eq!(tu, [0x0A, 0x01, 0x01, 0x32, 0x02, 0xAA, 0xBB]);
+        assert_eq!(
+            split_obus(&tu).unwrap(),
+            [&[0x0A, 0x01, 0x01][..], &[0x32, 0x02, 0xAA, 0xBB]]
+        );
+
+        let mut leb = Vec::new();
+        write_leb128(300, &mut leb);
+        assert_eq!(read_leb128(&leb), Some((300, 2)));
+    }
 }
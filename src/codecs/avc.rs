@@ -1,4 +1,9 @@
+use crate::bitstream::{remove_emulation_prevention, BitError, BitReader};
+use alloc::vec::Vec;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum AvcNalKind {
     // Single NAL unit (types 1..23)
     Single(u8),
@@ -24,9 +29,23 @@ pub enum AvcNalKind {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AvcError {
-    BufferTooShort,
+    /// The payload ends before `field`, which starts at `offset`.
+    BufferTooShort { field: &'static str, offset: usize },
+}
+
+impl core::fmt::Display for AvcError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AvcError::BufferTooShort { field, offset } => {
+                write!(f, "payload too short for {} at offset {}", field, offset)
+            }
+        }
+    }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for AvcError {}
+
 #[inline]
 pub fn avc_vcl_type(nal_type: u8) -> bool {
     (1..=5).contains(&nal_type)
@@ -34,7 +53,10 @@ pub fn avc_vcl_type(nal_type: u8) -> bool {
 
 pub fn parse_avc_payload_header(payload: &[u8]) -> Result<(AvcNalKind, usize), AvcError> {
     if payload.is_empty() {
-        return Err(AvcError::BufferTooShort);
+        return Err(AvcError::BufferTooShort {
+            field: "NAL unit header",
+            offset: 0,
+        });
     }
     let indicator = payload[0];
     let nal_type = indicator & 0x1F;
@@ -46,7 +68,10 @@ pub fn parse_avc_payload_header(payload: &[u8]) -> Result<(AvcNalKind, usize), A
         27 => Ok((AvcNalKind::Mtap24, 1)),
         28 | 29 => {
             if payload.len() < 2 {
-                return Err(AvcError::BufferTooShort);
+                return Err(AvcError::BufferTooShort {
+                    field: "FU header",
+                    offset: 1,
+                });
             }
             let fu_header = payload[1];
             let start = (fu_header & 0x80) != 0;
@@ -71,6 +96,406 @@ pub fn parse_avc_payload_header(payload: &[u8]) -> Result<(AvcNalKind, usize), A
     }
 }
 
+/// Fields of a sequence parameter set needed to parse slice headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvcSps {
+    pub profile_idc: u8,
+    pub level_idc: u8,
+    pub sps_id: u8,
+    pub chroma_format_idc: u8,
+    pub separate_colour_plane: bool,
+    pub log2_max_frame_num: u8,
+    pub pic_order_cnt_type: u8,
+    /// Only meaningful for `pic_order_cnt_type` 0.
+    pub log2_max_poc_lsb: u8,
+    /// Only meaningful for `pic_order_cnt_type` 1.
+    pub delta_pic_order_always_zero: bool,
+    pub width_in_mbs: u32,
+    pub height_in_map_units: u32,
+    pub frame_mbs_only: bool,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    /// `frame_crop_{left,right,top,bottom}_offset`, in crop units.
+    pub frame_crop: [u32; 4],
+}
+
+/// Fields of a picture parameter set needed to parse slice headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvcPps {
+    pub pps_id: u8,
+    pub sps_id: u8,
+    pub bottom_field_pic_order_in_frame_present: bool,
+}
+
+/// Leading slice header fields that tell pictures apart (H.264 7.4.1.2.4).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvcSliceHeader {
+    pub nal_ref_idc: u8,
+    pub nal_unit_type: u8,
+    pub first_mb_in_slice: u32,
+    pub slice_type: u8,
+    pub pps_id: u8,
+    pub frame_num: u32,
+    pub field_pic: bool,
+    pub bottom_field: bool,
+    pub idr_pic_id: u32,
+    pub pic_order_cnt_lsb: u32,
+    pub delta_pic_order_cnt_bottom: i32,
+    pub delta_pic_order_cnt: [i32; 2],
+}
+
+fn read_id(r: &mut BitReader<'_>, max: u32) -> Result<u8, BitError> {
+    let bit_offset = r.position();
+    match r.read_ue()? {
+        id if id <= max => Ok(id as u8),
+        _ => Err(BitError::InvalidGolomb { bit_offset }),
+    }
+}
+
+// Skips a scaling_list() of `size` coefficients.
+fn skip_scaling_list(r: &mut BitReader<'_>, size: usize) -> Result<(), BitError> {
+    let (mut last, mut next) = (8i32, 8i32);
+    for _ in 0..size {
+        if next != 0 {
+            next = (last + r.read_se()? + 256) % 256;
+        }
+        if next != 0 {
+            last = next;
+        }
+    }
+    Ok(())
+}
+
+impl AvcSps {
+    /// Parses an SPS NAL unit, header byte included.
+    pub fn parse(nal: &[u8]) -> Result<Self, BitError> {
+        let rbsp = remove_emulation_prevention(nal.get(1..).unwrap_or_default());
+        let mut r = BitReader::new(&rbsp);
+        let profile_idc = r.read_bits(8)? as u8;
+        r.skip(8)?;
+        let level_idc = r.read_bits(8)? as u8;
+        let sps_id = read_id(&mut r, 31)?;
+        let (mut chroma_format_idc, mut separate_colour_plane) = (1, false);
+        let (mut bit_depth_luma, mut bit_depth_chroma) = (8, 8);
+        if matches!(
+            profile_idc,
+            100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+        ) {
+            chroma_format_idc = read_id(&mut r, 3)?;
+            if chroma_format_idc == 3 {
+                separate_colour_plane = r.read_bit()?;
+            }
+            bit_depth_luma = r.read_ue()?.min(6) as u8 + 8;
+            bit_depth_chroma = r.read_ue()?.min(6) as u8 + 8;
+            r.skip(1)?; // qpprime_y_zero_transform_bypass_flag
+            if r.read_bit()? {
+                let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+                for i in 0..lists {
+                    if r.read_bit()? {
+                        skip_scaling_list(&mut r, if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+        let log2_max_frame_num = read_id(&mut r, 12)? + 4;
+        let pic_order_cnt_type = read_id(&mut r, 2)?;
+        let (mut log2_max_poc_lsb, mut delta_pic_order_always_zero) = (0, false);
+        match pic_order_cnt_type {
+            0 => log2_max_poc_lsb = read_id(&mut r, 12)? + 4,
+            1 => {
+                delta_pic_order_always_zero = r.read_bit()?;
+                r.read_se()?; // offset_for_non_ref_pic
+                r.read_se()?; // offset_for_top_to_bottom_field
+                for _ in 0..read_id(&mut r, 255)? {
+                    r.read_se()?;
+                }
+            }
+            _ => {}
+        }
+        r.read_ue()?; // max_num_ref_frames
+        r.skip(1)?; // gaps_in_frame_num_value_allowed_flag
+        let width_in_mbs = r.read_ue()? + 1;
+        let height_in_map_units = r.read_ue()? + 1;
+        let frame_mbs_only = r.read_bit()?;
+        // Cropping is optional for the slice parsing this serves, so a
+        // truncated SPS still parses.
+        let mut frame_crop = [0; 4];
+        let mut read_crop = || -> Result<(), BitError> {
+            r.skip(if frame_mbs_only { 1 } else { 2 })?; // mb_adaptive_frame_field, direct_8x8_inference
+            if r.read_bit()? {
+                for offset in &mut frame_crop {
+                    *offset = r.read_ue()?;
+                }
+            }
+            Ok(())
+        };
+        if read_crop().is_err() {
+            frame_crop = [0; 4];
+        }
+        Ok(Self {
+            profile_idc,
+            level_idc,
+            sps_id,
+            chroma_format_idc,
+            separate_colour_plane,
+            log2_max_frame_num,
+            pic_order_cnt_type,
+            log2_max_poc_lsb,
+            delta_pic_order_always_zero,
+            width_in_mbs,
+            height_in_map_units,
+            frame_mbs_only,
+            bit_depth_luma,
+            bit_depth_chroma,
+            frame_crop,
+        })
+    }
+
+    /// Width and height of the cropped frame in luma samples.
+    pub fn dimensions(&self) -> (u32, u32) {
+        let fields = 2 - self.frame_mbs_only as u32;
+        let height_in_mbs = self.height_in_map_units * fields;
+        // CropUnitX/Y (H.264 7.4.2.1.1) from SubWidthC/SubHeightC.
+        let (unit_x, unit_y) = match (self.chroma_format_idc, self.separate_colour_plane) {
+            (1, false) => (2, 2 * fields),
+            (2, false) => (2, fields),
+            _ => (1, fields),
+        };
+        let [left, right, top, bottom] = self.frame_crop;
+        let width = (self.width_in_mbs * 16).saturating_sub(unit_x * (left + right));
+        let height = (height_in_mbs * 16).saturating_sub(unit_y * (top + bottom));
+        (width, height)
+    }
+}
+
+impl AvcPps {
+    /// Parses a PPS NAL unit, header byte included.
+    pub fn parse(nal: &[u8]) -> Result<Self, BitError> {
+        let rbsp = remove_emulation_prevention(nal.get(1..).unwrap_or_default());
+        let mut r = BitReader::new(&rbsp);
+        let pps_id = read_id(&mut r, 255)?;
+        let sps_id = read_id(&mut r, 31)?;
+        r.skip(1)?; // entropy_coding_mode_flag
+        Ok(Self {
+            pps_id,
+            sps_id,
+            bottom_field_pic_order_in_frame_present: r.read_bit()?,
+        })
+    }
+}
+
+impl AvcSliceHeader {
+    /// Parses the start of a slice NAL unit (header byte included); only
+    /// the beginning of the slice is needed. `sps_for` looks up the SPS
+    /// and PPS referenced by `pps_id`.
+    pub fn parse(
+        nal: &[u8],
+        sps_for: impl FnOnce(u8) -> Option<(AvcSps, AvcPps)>,
+    ) -> Result<Option<Self>, BitError> {
+        let Some(&header) = nal.first() else {
+            return Ok(None);
+        };
+        let nal_unit_type = header & 0x1F;
+        if !avc_vcl_type(nal_unit_type) {
+            return Ok(None);
+        }
+        // Enough for the fields below in any realistic stream.
+        let head = &nal[1..nal.len().min(64)];
+        let rbsp = remove_emulation_prevention(head);
+        let mut r = BitReader::new(&rbsp);
+        let first_mb_in_slice = r.read_ue()?;
+        let slice_type = (r.read_ue()? % 5) as u8;
+        let pps_id = read_id(&mut r, 255)?;
+        let Some((sps, pps)) = sps_for(pps_id) else {
+            return Ok(None);
+        };
+        if sps.separate_colour_plane {
+            r.skip(2)?; // colour_plane_id
+        }
+        let frame_num = r.read_bits(sps.log2_max_frame_num as u32)?;
+        let (mut field_pic, mut bottom_field) = (false, false);
+        if !sps.frame_mbs_only {
+            field_pic = r.read_bit()?;
+            if field_pic {
+                bottom_field = r.read_bit()?;
+            }
+        }
+        let idr_pic_id = if nal_unit_type == 5 { r.read_ue()? } else { 0 };
+        let (mut pic_order_cnt_lsb, mut delta_pic_order_cnt_bottom) = (0, 0);
+        let mut delta_pic_order_cnt = [0; 2];
+        let bottom_present = pps.bottom_field_pic_order_in_frame_present && !field_pic;
+        if sps.pic_order_cnt_type == 0 {
+            pic_order_cnt_lsb = r.read_bits(sps.log2_max_poc_lsb as u32)?;
+            if bottom_present {
+                delta_pic_order_cnt_bottom = r.read_se()?;
+            }
+        } else if sps.pic_order_cnt_type == 1 && !sps.delta_pic_order_always_zero {
+            delta_pic_order_cnt[0] = r.read_se()?;
+            if bottom_present {
+                delta_pic_order_cnt[1] = r.read_se()?;
+            }
+        }
+        Ok(Some(Self {
+            nal_ref_idc: (header >> 5) & 0x03,
+            nal_unit_type,
+            first_mb_in_slice,
+            slice_type,
+            pps_id,
+            frame_num,
+            field_pic,
+            bottom_field,
+            idr_pic_id,
+            pic_order_cnt_lsb,
+            delta_pic_order_cnt_bottom,
+            delta_pic_order_cnt,
+        }))
+    }
+
+    /// Whether this slice starts a new primary coded picture after `prev`,
+    /// per the rules of H.264 7.4.1.2.4 plus `first_mb_in_slice` 0 (which
+    /// assumes no arbitrary slice order).
+    pub fn starts_new_picture(&self, prev: &Self) -> bool {
+        let idr = self.nal_unit_type == 5;
+        self.first_mb_in_slice == 0
+            || self.frame_num != prev.frame_num
+            || self.pps_id != prev.pps_id
+            || self.field_pic != prev.field_pic
+            || self.bottom_field != prev.bottom_field
+            || (self.nal_ref_idc == 0) != (prev.nal_ref_idc == 0)
+            || self.pic_order_cnt_lsb != prev.pic_order_cnt_lsb
+            || self.delta_pic_order_cnt_bottom != prev.delta_pic_order_cnt_bottom
+            || self.delta_pic_order_cnt != prev.delta_pic_order_cnt
+            || idr != (prev.nal_unit_type == 5)
+            || (idr && self.idr_pic_id != prev.idr_pic_id)
+    }
+}
+
+/// Slice accounting of one access unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvcSliceSummary {
+    /// Coded slice NAL units (types 1 to 5).
+    pub slice_count: usize,
+    /// The first slice starts at macroblock 0 and each later one further
+    /// on, so no leading slice is missing and no other picture is mixed
+    /// in. Slices whose parameter sets are unknown are not checked.
+    pub complete: bool,
+}
+
+impl AvcSliceSummary {
+    /// Checks the slices among `nals`; `sps_for` is as for
+    /// [`AvcSliceHeader::parse`].
+    pub fn new<'a>(
+        nals: impl IntoIterator<Item = &'a [u8]>,
+        sps_for: impl Fn(u8) -> Option<(AvcSps, AvcPps)>,
+    ) -> Self {
+        let mut summary = Self {
+            slice_count: 0,
+            complete: true,
+        };
+        let mut next_mb = None;
+        for nal in nals {
+            if !nal.first().is_some_and(|h| avc_vcl_type(h & 0x1F)) {
+                continue;
+            }
+            summary.slice_count += 1;
+            let Ok(Some(slice)) = AvcSliceHeader::parse(nal, &sps_for) else {
+                continue;
+            };
+            let first_mb = slice.first_mb_in_slice;
+            summary.complete &= match next_mb {
+                None => first_mb == 0,
+                Some(min) => first_mb >= min,
+            };
+            next_mb = Some(first_mb.saturating_add(1));
+        }
+        summary
+    }
+}
+
+/// Prefix NAL unit (Annex H).
+pub const AVC_NAL_PREFIX: u8 = 14;
+/// Subset sequence parameter set of the non-base views.
+pub const AVC_NAL_SUBSET_SPS: u8 = 15;
+/// Coded slice extension, carrying a non-base view.
+pub const AVC_NAL_SLICE_EXTENSION: u8 = 20;
+
+/// `nal_unit_header_mvc_extension()` of a prefix or slice extension NAL
+/// unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvcMvcHeader {
+    pub non_idr: bool,
+    pub priority_id: u8,
+    pub view_id: u16,
+    pub temporal_id: u8,
+    pub anchor_pic: bool,
+    pub inter_view: bool,
+}
+
+impl AvcMvcHeader {
+    /// `None` unless `nal` is a type 14 or 20 NAL unit with an MVC (not
+    /// SVC) header extension.
+    pub fn parse(nal: &[u8]) -> Option<Self> {
+        let &[header, b1, b2, b3, ..] = nal else {
+            return None;
+        };
+        let nal_type = header & 0x1F;
+        // svc_extension_flag selects the SVC header layout instead.
+        if !matches!(nal_type, AVC_NAL_PREFIX | AVC_NAL_SLICE_EXTENSION) || b1 & 0x80 != 0 {
+            return None;
+        }
+        Some(Self {
+            non_idr: b1 & 0x40 != 0,
+            priority_id: b1 & 0x3F,
+            view_id: ((b2 as u16) << 2) | (b3 >> 6) as u16,
+            temporal_id: (b3 >> 3) & 0x07,
+            anchor_pic: b3 & 0x04 != 0,
+            inter_view: b3 & 0x02 != 0,
+        })
+    }
+}
+
+/// View ids of the coded slices of an MVC access unit, base view first and
+/// each listed once. Empty when there are no MVC NAL units; base view
+/// slices without a prefix NAL unit count as view 0.
+pub fn mvc_views<'a>(nals: impl IntoIterator<Item = &'a [u8]>) -> Vec<u16> {
+    let mut views = Vec::new();
+    let mut mvc = false;
+    let mut prefix = None;
+    for nal in nals {
+        let Some(&header) = nal.first() else {
+            continue;
+        };
+        let view = match header & 0x1F {
+            AVC_NAL_PREFIX => {
+                mvc = true;
+                prefix = AvcMvcHeader::parse(nal).map(|h| h.view_id);
+                continue;
+            }
+            AVC_NAL_SLICE_EXTENSION => {
+                mvc = true;
+                match AvcMvcHeader::parse(nal) {
+                    Some(h) => h.view_id,
+                    None => continue,
+                }
+            }
+            t if avc_vcl_type(t) => prefix.take().unwrap_or(0),
+            _ => continue,
+        };
+        if !views.contains(&view) {
+            views.push(view);
+        }
+    }
+    if !mvc {
+        views.clear();
+    }
+    views
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,4 +532,74 @@ mod tests {
         }
         assert_eq!(off, 0);
     }
+
+    #[test]
+    fn lists_mvc_views_from_nal_header_extensions() {
+        use crate::test_utils::fixtures::AVC_IDR;
+        // Prefix NAL unit for view 0, anchor picture, inter-view reference.
+        let prefix = [0x6E, 0x00, 0x00, 0x07];
+        // Slice extension of view 1 at temporal_id 2.
+        let extension = [0x74, 0x40, 0x00, 0x53, 0x88];
+        let header = AvcMvcHeader::parse(&extension).unwrap();
+        assert_eq!((header.view_id, header.temporal_id), (1, 2));
+        assert!(header.non_idr && !header.anchor_pic && header.inter_view);
+        assert_eq!(mvc_views([&prefix[..], AVC_IDR, &extension]), [0, 1]);
+        assert!(mvc_views([AVC_IDR]).is_empty());
+        assert_eq!(AvcMvcHeader::parse(AVC_IDR), None);
+    }
+
+    #[test]
+    fn parses_parameter_sets_and_slice_headers() {
+        use crate::test_utils::fixtures::{AVC_PPS, AVC_SPS};
+        let sps = AvcSps::parse(AVC_SPS).unwrap();
+        assert_eq!((sps.profile_idc, sps.level_idc), (66, 30));
+        assert_eq!((sps.width_in_mbs, sps.height_in_map_units), (20, 15));
+        assert_eq!(sps.dimensions(), (320, 240));
+        assert_eq!((sps.log2_max_frame_num, sps.pic_order_cnt_type), (4, 2));
+        let pps = AvcPps::parse(AVC_PPS).unwrap();
+        assert_eq!((pps.pps_id, pps.sps_id), (0, 0));
+
+        // first_mb 0, slice_type P (5), pps 0, frame_num 3.
+        let nal = [&[0x41][..], &crate::test_utils::rbsp("1 00110 1 0011")].concat();
+        let slice = AvcSliceHeader::parse(&nal, |_| Some((sps, pps)))
+            .unwrap()
+            .unwrap();
+        assert_eq!((slice.slice_type, slice.frame_num), (0, 3));
+        // Same picture from macroblock 40 on, then the next picture.
+        let same = AvcSliceHeader {
+            first_mb_in_slice: 40,
+            ..slice
+        };
+        assert!(!same.starts_new_picture(&slice));
+        let next = AvcSliceHeader {
+            frame_num: 4,
+            ..same
+        };
+        assert!(next.starts_new_picture(&same));
+        assert_eq!(AvcSliceHeader::parse(&nal, |_| None), Ok(None));
+    }
+
+    #[test]
+    fn slice_summary_flags_missing_leading_slice() {
+        use crate::test_utils::fixtures::{AVC_PPS, AVC_SPS};
+        let sps = AvcSps::parse(AVC_SPS).unwrap();
+        let pps = AvcPps::parse(AVC_PPS).unwrap();
+        let slice = |first_mb: &str| {
+            [
+                &[0x41][..],
+                &crate::test_utils::rbsp(&[first_mb, "00110 1 0011"].concat()),
+            ]
+            .concat()
+        };
+        let (mb0, mb40) = (slice("1"), slice("00000101001"));
+        let summary =
+            |nals: &[&[u8]]| AvcSliceSummary::new(nals.iter().copied(), |_| Some((sps, pps)));
+        let whole = summary(&[AVC_SPS, &mb0, &mb40]);
+        assert_eq!((whole.slice_count, whole.complete), (2, true));
+        assert!(!summary(&[&mb40]).complete);
+        assert!(!summary(&[&mb40, &mb0]).complete);
+        // Without parameter sets nothing can be checked.
+        let unknown = AvcSliceSummary::new([mb40.as_slice()], |_| None);
+        assert_eq!((unknown.slice_count, unknown.complete), (1, true));
+    }
 }
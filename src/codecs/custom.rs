@@ -0,0 +1,152 @@
+//! Payload formats defined by the application.
+//!
+//! A [`Depacketizer`] describes a proprietary or experimental payload
+//! format; registered in a [`CodecRegistry`] under an id, it is known as
+//! [`Codec::Custom`] with that id. Hand the registry to
+//! [`FrameAnalyzer::set_codec_registry`](crate::analyze::FrameAnalyzer::set_codec_registry),
+//! [`FrameReassembler::set_codec_registry`](crate::reassemble::FrameReassembler::set_codec_registry)
+//! or [`StreamSet::set_codec_registry`](crate::shard::StreamSet::set_codec_registry)
+//! and custom codecs are guessed, analyzed and reassembled like the built-in
+//! ones. Custom formats use the 90 kHz video clock.
+
+use super::Codec;
+use crate::reassemble::MalformedReason;
+use alloc::{sync::Arc, vec::Vec};
+
+/// A custom payload format. Shared by every stream using it, so it takes
+/// `&self` and keeps no per-stream state.
+pub trait Depacketizer: Send + Sync {
+    /// SDP encoding name, e.g. `X-ACME-CAM`.
+    fn name(&self) -> &str;
+
+    /// Whether `payload` looks like this format, when guessing the codec of
+    /// a stream. Formats that cannot be told from their payload keep the
+    /// default and must be configured.
+    fn probe(&self, _payload: &[u8]) -> bool {
+        false
+    }
+
+    /// Whether `payload` is the first of a frame; the marker bit ends it.
+    fn starts_frame(&self, payload: &[u8]) -> bool;
+
+    /// Appends the frame data `payload` carries to `out`. A malformed
+    /// payload appends nothing and marks its frame as damaged.
+    fn depacketize(&self, payload: &[u8], out: &mut Vec<u8>) -> Result<(), MalformedReason>;
+
+    /// Whether an assembled frame can be decoded on its own, ending a wait
+    /// for a keyframe after loss.
+    fn is_keyframe(&self, _frame: &[u8]) -> bool {
+        false
+    }
+}
+
+impl core::fmt::Debug for dyn Depacketizer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Depacketizer({})", self.name())
+    }
+}
+
+/// Custom payload formats by id.
+#[derive(Debug, Clone, Default)]
+pub struct CodecRegistry {
+    codecs: Vec<(u32, Arc<dyn Depacketizer>)>,
+}
+
+impl CodecRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `depacketizer` as `Codec::Custom(id)`, replacing any format
+    /// registered under `id` before.
+    pub fn register(&mut self, id: u32, depacketizer: Arc<dyn Depacketizer>) -> Codec {
+        match self.codecs.iter_mut().find(|(i, _)| *i == id) {
+            Some(entry) => entry.1 = depacketizer,
+            None => self.codecs.push((id, depacketizer)),
+        }
+        Codec::Custom(id)
+    }
+
+    /// The depacketizer of a custom codec.
+    pub fn get(&self, codec: Codec) -> Option<&Arc<dyn Depacketizer>> {
+        let Codec::Custom(id) = codec else {
+            return None;
+        };
+        self.codecs.iter().find(|(i, _)| *i == id).map(|(_, d)| d)
+    }
+
+    /// The custom codec registered as `name`, case-insensitively.
+    pub fn by_name(&self, name: &str) -> Option<Codec> {
+        self.codecs
+            .iter()
+            .find(|(_, d)| d.name().eq_ignore_ascii_case(name))
+            .map(|(id, _)| Codec::Custom(*id))
+    }
+
+    /// The first custom codec, in registration order, whose
+    /// [`probe`](Depacketizer::probe) accepts `payload`.
+    pub fn guess(&self, payload: &[u8]) -> Option<Codec> {
+        self.codecs
+            .iter()
+            .find(|(_, d)| d.probe(payload))
+            .map(|(id, _)| Codec::Custom(*id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{reassemble::FrameReassembler, rtp::RtpPacket, test_utils::StreamBuilder};
+    use alloc::vec;
+
+    // One header byte: start flag, keyframe flag and a 6-bit magic.
+    struct Toy;
+
+    impl Depacketizer for Toy {
+        fn name(&self) -> &str {
+            "X-TOY"
+        }
+
+        fn probe(&self, payload: &[u8]) -> bool {
+            payload.first().is_some_and(|h| h & 0x3F == 0x2A)
+        }
+
+        fn starts_frame(&self, payload: &[u8]) -> bool {
+            payload.first().is_some_and(|h| h & 0x80 != 0)
+        }
+
+        fn depacketize(&self, payload: &[u8], out: &mut Vec<u8>) -> Result<(), MalformedReason> {
+            match payload.split_first() {
+                Some((h, data)) if h & 0x3F == 0x2A => {
+                    out.extend_from_slice(data);
+                    Ok(())
+                }
+                _ => Err(MalformedReason::InvalidHeader),
+            }
+        }
+
+        fn is_keyframe(&self, frame: &[u8]) -> bool {
+            frame.first() == Some(&0xEE)
+        }
+    }
+
+    #[test]
+    fn custom_codecs_are_guessed_and_reassembled() {
+        let mut registry = CodecRegistry::new();
+        let toy = registry.register(9, Arc::new(Toy));
+        assert_eq!(registry.by_name("x-toy"), Some(toy));
+        let packets = StreamBuilder::new(1)
+            .frame(&[vec![0xEA, 0xEE, 1], vec![0x2A, 2, 3]])
+            .frame(&[vec![0xAA, 4]])
+            .build();
+        let mut r = FrameReassembler::default();
+        r.set_codec_registry(Arc::new(registry));
+        let mut frames = Vec::new();
+        for bytes in &packets {
+            frames.extend(r.push_packet(&RtpPacket::parse(bytes).unwrap()));
+        }
+        assert_eq!(r.codec(), Some(Codec::Custom(9)));
+        assert_eq!(frames, [vec![0xEE, 1, 2, 3], vec![4]]);
+        assert!(r.frame_is_keyframe(&frames[0]) && !r.frame_is_keyframe(&frames[1]));
+    }
+}
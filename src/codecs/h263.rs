@@ -0,0 +1,384 @@
+//! H.263 over RTP (RFC 4629).
+//!
+//! Every payload starts with a two-byte header. P marks a packet starting
+//! with a picture, GOB or slice start code, whose two leading zero bytes
+//! the sender removed; V adds a video redundancy coding byte; PLEN bytes
+//! of redundant picture header follow. [`H263Depacketizer`] restores the
+//! start codes and rebuilds pictures, which end at the marker bit.
+
+use crate::{bitstream::BitReader, limits::Limits, rtp::RtpPacket};
+use alloc::vec::Vec;
+
+/// Pictures growing past this many bytes are dropped, unless configured
+/// otherwise with [`H263Depacketizer::with_limits`].
+pub const MAX_PICTURE_BYTES: usize = 1 << 20;
+
+// Picture start code: 16 zero bits, then 1000 00.
+const PSC: u32 = 0x20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum H263Error {
+    /// The payload ends inside `field`.
+    Truncated { field: &'static str },
+    /// The picture at `timestamp` does not start with a picture start code.
+    MissingPictureStart { timestamp: u32 },
+    /// Packets of the picture at `timestamp` were lost.
+    MissingPacket { timestamp: u32 },
+    /// The picture at `timestamp` exceeded its size limit.
+    TooLarge { timestamp: u32 },
+}
+
+impl core::fmt::Display for H263Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            H263Error::Truncated { field } => write!(f, "H.263 payload ends inside {}", field),
+            H263Error::MissingPictureStart { timestamp } => {
+                write!(
+                    f,
+                    "H.263 picture at {} has no picture start code",
+                    timestamp
+                )
+            }
+            H263Error::MissingPacket { timestamp } => {
+                write!(f, "H.263 picture at {} lost packets", timestamp)
+            }
+            H263Error::TooLarge { timestamp } => {
+                write!(f, "H.263 picture at {} exceeds the size limit", timestamp)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for H263Error {}
+
+/// Video redundancy coding byte, present when V is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct H263Vrc {
+    /// Thread ID.
+    pub tid: u8,
+    /// Packets of the thread since its last sync frame.
+    pub trun: u8,
+    /// Sync frame.
+    pub sync: bool,
+}
+
+/// The RFC 4629 payload header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct H263PayloadHeader<'a> {
+    /// P: the packet starts with a start code whose zero bytes were removed.
+    pub start: bool,
+    pub vrc: Option<H263Vrc>,
+    /// Redundant copy of the picture header, PLEN bytes.
+    pub extra_picture_header: &'a [u8],
+    /// PEBIT: trailing bits of the last byte of `extra_picture_header` to
+    /// ignore.
+    pub pebit: u8,
+}
+
+impl<'a> H263PayloadHeader<'a> {
+    /// Parses the header; returns it and the offset of the payload data.
+    pub fn parse(payload: &'a [u8]) -> Result<(Self, usize), H263Error> {
+        let truncated = |field| H263Error::Truncated { field };
+        let header = payload.get(..2).ok_or(truncated("payload header"))?;
+        let start = header[0] & 0x04 != 0;
+        let plen = ((header[0] & 0x01) << 5 | header[1] >> 3) as usize;
+        let pebit = header[1] & 0x07;
+        let mut at = 2;
+        let vrc = if header[0] & 0x02 != 0 {
+            let byte = *payload.get(at).ok_or(truncated("VRC"))?;
+            at += 1;
+            Some(H263Vrc {
+                tid: byte >> 5,
+                trun: (byte >> 1) & 0x0F,
+                sync: byte & 0x01 != 0,
+            })
+        } else {
+            None
+        };
+        let extra_picture_header = payload
+            .get(at..at + plen)
+            .ok_or(truncated("extra picture header"))?;
+        Ok((
+            Self {
+                start,
+                vrc,
+                extra_picture_header,
+                pebit,
+            },
+            at + plen,
+        ))
+    }
+}
+
+/// Fields of an H.263 picture header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct H263PictureHeader {
+    pub temporal_reference: u8,
+    /// Width and height of the standard source formats, from sub-QCIF to
+    /// 16CIF; `None` for custom formats or when PLUSPTYPE omits it.
+    pub size: Option<(u16, u16)>,
+    /// INTRA (I) picture.
+    pub intra: bool,
+}
+
+impl H263PictureHeader {
+    /// Parses the header at the start of `data`, from its picture start
+    /// code on.
+    pub fn parse(data: &[u8]) -> Result<Self, H263Error> {
+        let truncated = H263Error::Truncated {
+            field: "picture header",
+        };
+        let mut r = BitReader::new(data);
+        let mut read = |n| r.read_bits(n).map_err(|_| truncated);
+        if read(22)? != PSC {
+            return Err(truncated);
+        }
+        let temporal_reference = read(8)? as u8;
+        // PTYPE: marker bits, split screen, document camera, freeze release.
+        read(5)?;
+        let format = read(3)?;
+        let (format, intra) = if format == 7 {
+            // PLUSPTYPE: OPPTYPE is only sent when UFEP is 001.
+            let format = match read(3)? {
+                1 => Some(read(18)? >> 15),
+                _ => None,
+            };
+            let picture_type = read(3)?;
+            (format, picture_type == 0)
+        } else {
+            (Some(format), read(1)? == 0)
+        };
+        let size = match format {
+            Some(1) => Some((128, 96)),
+            Some(2) => Some((176, 144)),
+            Some(3) => Some((352, 288)),
+            Some(4) => Some((704, 576)),
+            Some(5) => Some((1408, 1152)),
+            _ => None,
+        };
+        Ok(Self {
+            temporal_reference,
+            size,
+            intra,
+        })
+    }
+}
+
+/// H.263 picture completed by an [`H263Depacketizer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H263Frame {
+    pub timestamp: u32,
+    pub header: H263PictureHeader,
+    /// The picture's bitstream, from its picture start code on.
+    pub data: Vec<u8>,
+}
+
+// Picture being received.
+#[derive(Debug)]
+struct PendingPicture {
+    timestamp: u32,
+    next_seq: u16,
+    data: Vec<u8>,
+    error: Option<H263Error>,
+}
+
+/// Rebuilds H.263 pictures from the packets of one RTP stream.
+///
+/// A picture whose last packet is lost is dropped when the next picture
+/// starts; like other dropped pictures it is counted by
+/// [`dropped_pictures`](Self::dropped_pictures).
+#[derive(Debug)]
+pub struct H263Depacketizer {
+    pending: Option<PendingPicture>,
+    max_picture_bytes: usize,
+    dropped: u64,
+}
+
+impl Default for H263Depacketizer {
+    fn default() -> Self {
+        Self {
+            pending: None,
+            max_picture_bytes: MAX_PICTURE_BYTES,
+            dropped: 0,
+        }
+    }
+}
+
+impl H263Depacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bounds pictures by `limits.max_frame_bytes`.
+    pub fn with_limits(limits: &Limits) -> Self {
+        Self {
+            max_picture_bytes: limits.max_frame_bytes,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a packet; returns the picture it completes, or why that picture
+    /// was dropped.
+    pub fn push_packet(&mut self, pkt: &RtpPacket<'_>) -> Option<Result<H263Frame, H263Error>> {
+        let timestamp = pkt.header.timestamp;
+        let seq = pkt.header.sequence_number;
+        let mut picture = match self.pending.take() {
+            Some(mut picture) if picture.timestamp == timestamp => {
+                if seq != picture.next_seq {
+                    picture
+                        .error
+                        .get_or_insert(H263Error::MissingPacket { timestamp });
+                }
+                picture
+            }
+            previous => {
+                if let Some(_previous) = previous {
+                    trace_event!(
+                        DEBUG,
+                        timestamp = _previous.timestamp,
+                        "H.263 picture lost its last packet"
+                    );
+                    self.dropped += 1;
+                }
+                PendingPicture {
+                    timestamp,
+                    next_seq: seq,
+                    data: Vec::new(),
+                    error: None,
+                }
+            }
+        };
+        picture.next_seq = seq.wrapping_add(1);
+        if picture.error.is_none() {
+            picture.error = self.add(&mut picture, pkt.payload).err();
+        }
+        if !pkt.header.marker {
+            self.pending = Some(picture);
+            return None;
+        }
+        let result = match picture.error {
+            Some(error) => Err(error),
+            None => H263PictureHeader::parse(&picture.data).map(|header| H263Frame {
+                timestamp,
+                header,
+                data: picture.data,
+            }),
+        };
+        if let Err(_e) = &result {
+            trace_event!(DEBUG, timestamp, error = %_e, "H.263 picture dropped");
+            self.dropped += 1;
+        }
+        Some(result)
+    }
+
+    /// Pictures dropped so far, including those returned as errors.
+    pub fn dropped_pictures(&self) -> u64 {
+        self.dropped
+    }
+
+    fn add(&self, picture: &mut PendingPicture, payload: &[u8]) -> Result<(), H263Error> {
+        let timestamp = picture.timestamp;
+        let (header, at) = H263PayloadHeader::parse(payload)?;
+        let data = &payload[at..];
+        if picture.data.is_empty()
+            && !(header.start && data.first().is_some_and(|b| b >> 2 == 0x20))
+        {
+            return Err(H263Error::MissingPictureStart { timestamp });
+        }
+        let start_code = if header.start { 2 } else { 0 };
+        if picture.data.len() + start_code + data.len() > self.max_picture_bytes {
+            picture.data = Vec::new();
+            return Err(H263Error::TooLarge { timestamp });
+        }
+        picture.data.resize(picture.data.len() + start_code, 0);
+        picture.data.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::RtpPacketBuilder;
+
+    // CIF INTRA picture header with TR 5, then GOB 1 in a second packet.
+    const PICTURE: [u8; 6] = [0x80, 0x16, 0x0C, 0x00, 0xAA, 0xBB];
+    const GOB: [u8; 3] = [0x88, 0x00, 0xCC];
+
+    fn packet(seq: u16, timestamp: u32, marker: bool, payload: &[u8]) -> Vec<u8> {
+        RtpPacketBuilder::new()
+            .sequence_number(seq)
+            .timestamp(timestamp)
+            .marker(marker)
+            .payload(payload)
+            .build()
+    }
+
+    fn with_header(header: &[u8], data: &[u8]) -> Vec<u8> {
+        [header, data].concat()
+    }
+
+    #[test]
+    fn restores_start_codes_and_parses_the_picture_header() {
+        let mut h263 = H263Depacketizer::new();
+        // Second packet with V set and a two-byte redundant picture header.
+        let packets = [
+            packet(1, 3003, false, &with_header(&[0x04, 0x00], &PICTURE)),
+            packet(2, 3003, false, &with_header(&[0x00, 0x00], &[0xDD])),
+            packet(
+                3,
+                3003,
+                true,
+                &with_header(&[0x06, 0x10, 0x21, 0x80, 0x16], &GOB),
+            ),
+        ];
+        let mut frames = Vec::new();
+        for bytes in &packets {
+            frames.extend(h263.push_packet(&RtpPacket::parse(bytes).unwrap()));
+        }
+        let [Ok(frame)] = &frames[..] else {
+            panic!("expected one picture, got {:?}", frames);
+        };
+        assert_eq!(
+            frame.data,
+            [&[0, 0][..], &PICTURE, &[0xDD], &[0, 0], &GOB].concat()
+        );
+        assert_eq!(
+            frame.header,
+            H263PictureHeader {
+                temporal_reference: 5,
+                size: Some((352, 288)),
+                intra: true
+            }
+        );
+        let (header, at) = H263PayloadHeader::parse(&packets[2][12..]).unwrap();
+        assert_eq!((header.extra_picture_header, at), (&[0x80, 0x16][..], 5));
+        let vrc = header.vrc.unwrap();
+        assert_eq!((vrc.tid, vrc.trun, vrc.sync), (1, 0, true));
+    }
+
+    #[test]
+    fn drops_pictures_missing_packets_or_their_start() {
+        let mut h263 = H263Depacketizer::new();
+        let mut results = Vec::new();
+        for bytes in [
+            packet(1, 0, false, &with_header(&[0x04, 0x00], &PICTURE)),
+            packet(3, 0, true, &with_header(&[0x04, 0x00], &GOB)),
+            // The next picture starts with a GOB: its first packet is lost.
+            packet(5, 3003, true, &with_header(&[0x04, 0x00], &GOB)),
+        ] {
+            results.extend(h263.push_packet(&RtpPacket::parse(&bytes).unwrap()));
+        }
+        assert_eq!(
+            results,
+            [
+                Err(H263Error::MissingPacket { timestamp: 0 }),
+                Err(H263Error::MissingPictureStart { timestamp: 3003 })
+            ]
+        );
+        assert_eq!(h263.dropped_pictures(), 2);
+    }
+}
@@ -1,4 +1,8 @@
+use crate::bitstream::{remove_emulation_prevention, BitError, BitReader};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum HevcNalKind {
     Single {
         nal_type: u8,
@@ -15,9 +19,23 @@ pub enum HevcNalKind {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum HevcError {
-    BufferTooShort,
+    /// The payload ends before `field`, which starts at `offset`.
+    BufferTooShort { field: &'static str, offset: usize },
 }
 
+impl core::fmt::Display for HevcError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HevcError::BufferTooShort { field, offset } => {
+                write!(f, "payload too short for {} at offset {}", field, offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HevcError {}
+
 #[inline]
 pub fn hevc_vcl_type(nal_type: u8) -> bool {
     nal_type <= 31
@@ -25,7 +43,10 @@ pub fn hevc_vcl_type(nal_type: u8) -> bool {
 
 pub fn parse_hevc_payload_header(payload: &[u8]) -> Result<(HevcNalKind, usize), HevcError> {
     if payload.len() < 2 {
-        return Err(HevcError::BufferTooShort);
+        return Err(HevcError::BufferTooShort {
+            field: "payload header",
+            offset: 0,
+        });
     }
     // HEVC NALU header is 2 bytes
     let b0 = payload[0];
@@ -34,7 +55,10 @@ pub fn parse_hevc_payload_header(payload: &[u8]) -> Result<(HevcNalKind, usize),
         48 => Ok((HevcNalKind::Ap, 2)),
         49 => {
             if payload.len() < 3 {
-                return Err(HevcError::BufferTooShort);
+                return Err(HevcError::BufferTooShort {
+                    field: "FU header",
+                    offset: 2,
+                });
             }
             let fu_header = payload[2];
             let start = (fu_header & 0x80) != 0;
@@ -55,6 +79,278 @@ pub fn parse_hevc_payload_header(payload: &[u8]) -> Result<(HevcNalKind, usize),
     }
 }
 
+/// HRD field lengths from the SPS VUI, needed to parse picture timing SEI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HevcHrd {
+    pub sub_pic_hrd_params_present: bool,
+    pub au_cpb_removal_delay_length: u8,
+    pub dpb_output_delay_length: u8,
+    /// Only meaningful with `sub_pic_hrd_params_present`.
+    pub dpb_output_delay_du_length: u8,
+}
+
+/// Sequence parameter set fields up to the VUI timing and HRD information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HevcSps {
+    pub sps_id: u8,
+    pub max_sub_layers: u8,
+    pub general_profile_idc: u8,
+    pub general_level_idc: u8,
+    pub chroma_format_idc: u8,
+    /// Luma samples, before the conformance window.
+    pub width: u32,
+    pub height: u32,
+    /// `conf_win_{left,right,top,bottom}_offset`, in chroma samples.
+    pub conformance_window: [u32; 4],
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub log2_max_poc_lsb: u8,
+    /// VUI `field_seq_flag`: every picture is a field.
+    pub field_seq: bool,
+    /// VUI `frame_field_info_present_flag`: picture timing SEI carries
+    /// `pic_struct`.
+    pub frame_field_info_present: bool,
+    /// `(num_units_in_tick, time_scale)`.
+    pub timing: Option<(u32, u32)>,
+    /// NAL or VCL HRD parameters are present (`CpbDpbDelaysPresentFlag`).
+    pub hrd: Option<HevcHrd>,
+}
+
+// profile_tier_level(1, max_sub_layers_minus1); returns
+// (general_profile_idc, general_level_idc).
+fn read_profile_tier_level(
+    r: &mut BitReader<'_>,
+    max_sub_layers_minus1: usize,
+) -> Result<(u8, u8), BitError> {
+    r.skip(3)?; // general_profile_space, general_tier_flag
+    let profile_idc = r.read_bits(5)? as u8;
+    r.skip(32 + 4 + 43 + 1)?;
+    let level_idc = r.read_bits(8)? as u8;
+    let mut present = [(false, false); 7];
+    for p in &mut present[..max_sub_layers_minus1] {
+        *p = (r.read_bit()?, r.read_bit()?);
+    }
+    if max_sub_layers_minus1 > 0 {
+        r.skip(2 * (8 - max_sub_layers_minus1))?;
+    }
+    for &(profile, level) in &present[..max_sub_layers_minus1] {
+        r.skip(if profile { 88 } else { 0 } + if level { 8 } else { 0 })?;
+    }
+    Ok((profile_idc, level_idc))
+}
+
+fn skip_scaling_list_data(r: &mut BitReader<'_>) -> Result<(), BitError> {
+    for size_id in 0..4 {
+        let step = if size_id == 3 { 3 } else { 1 };
+        for _ in (0..6).step_by(step) {
+            if !r.read_bit()? {
+                r.read_ue()?; // scaling_list_pred_matrix_id_delta
+                continue;
+            }
+            if size_id > 1 {
+                r.read_se()?; // scaling_list_dc_coef_minus8
+            }
+            for _ in 0..64.min(1 << (4 + (size_id << 1))) {
+                r.read_se()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// st_ref_pic_set(idx) as coded in the SPS; returns NumDeltaPocs[idx].
+fn skip_st_ref_pic_set(
+    r: &mut BitReader<'_>,
+    idx: usize,
+    num_delta_pocs: &[u32],
+) -> Result<u32, BitError> {
+    if idx != 0 && r.read_bit()? {
+        // Predicted from the previous set.
+        r.skip(1)?; // delta_rps_sign
+        r.read_ue()?; // abs_delta_rps_minus1
+        let mut count = 0;
+        for _ in 0..=num_delta_pocs[idx - 1] {
+            let used_by_curr_pic = r.read_bit()?;
+            if used_by_curr_pic || r.read_bit()? {
+                count += 1;
+            }
+        }
+        return Ok(count);
+    }
+    let negative = r.read_ue()?;
+    let positive = r.read_ue()?;
+    for _ in 0..negative.saturating_add(positive) {
+        r.read_ue()?; // delta_poc_s*_minus1
+        r.skip(1)?; // used_by_curr_pic_s*_flag
+    }
+    Ok(negative.saturating_add(positive))
+}
+
+// hrd_parameters(1, _) up to the delay lengths; `None` without NAL or VCL
+// HRD parameters.
+fn read_hrd_lengths(r: &mut BitReader<'_>) -> Result<Option<HevcHrd>, BitError> {
+    let nal_hrd = r.read_bit()?;
+    let vcl_hrd = r.read_bit()?;
+    if !nal_hrd && !vcl_hrd {
+        return Ok(None);
+    }
+    let sub_pic_hrd_params_present = r.read_bit()?;
+    let mut dpb_output_delay_du_length = 0;
+    if sub_pic_hrd_params_present {
+        r.skip(8 + 5 + 1)?; // tick_divisor, du_cpb_removal_delay_increment_length, flag
+        dpb_output_delay_du_length = r.read_bits(5)? as u8 + 1;
+    }
+    r.skip(8)?; // bit_rate_scale, cpb_size_scale
+    if sub_pic_hrd_params_present {
+        r.skip(4)?; // cpb_size_du_scale
+    }
+    r.skip(5)?; // initial_cpb_removal_delay_length_minus1
+    Ok(Some(HevcHrd {
+        sub_pic_hrd_params_present,
+        au_cpb_removal_delay_length: r.read_bits(5)? as u8 + 1,
+        dpb_output_delay_length: r.read_bits(5)? as u8 + 1,
+        dpb_output_delay_du_length,
+    }))
+}
+
+impl HevcSps {
+    /// Parses an SPS NAL unit, 2-byte header included.
+    pub fn parse(nal: &[u8]) -> Result<Self, BitError> {
+        let rbsp = remove_emulation_prevention(nal.get(2..).unwrap_or_default());
+        let mut r = BitReader::new(&rbsp);
+        r.skip(4)?; // sps_video_parameter_set_id
+        let max_sub_layers_minus1 = (r.read_bits(3)? as usize).min(6);
+        r.skip(1)?; // sps_temporal_id_nesting_flag
+        let (general_profile_idc, general_level_idc) =
+            read_profile_tier_level(&mut r, max_sub_layers_minus1)?;
+        let sps_id = r.read_ue()?.min(15) as u8;
+        let chroma_format_idc = r.read_ue()?.min(3) as u8;
+        if chroma_format_idc == 3 {
+            r.skip(1)?; // separate_colour_plane_flag
+        }
+        let width = r.read_ue()?;
+        let height = r.read_ue()?;
+        let mut conformance_window = [0; 4];
+        if r.read_bit()? {
+            for offset in &mut conformance_window {
+                *offset = r.read_ue()?;
+            }
+        }
+        let bit_depth_luma = r.read_ue()?.min(8) as u8 + 8;
+        let bit_depth_chroma = r.read_ue()?.min(8) as u8 + 8;
+        let log2_max_poc_lsb = r.read_ue()?.min(12) as u8 + 4;
+        let ordering_info_for_all = r.read_bit()?;
+        let first = if ordering_info_for_all {
+            0
+        } else {
+            max_sub_layers_minus1
+        };
+        for _ in first..=max_sub_layers_minus1 {
+            for _ in 0..3 {
+                r.read_ue()?; // max_dec_pic_buffering, num_reorder_pics, max_latency_increase
+            }
+        }
+        for _ in 0..6 {
+            r.read_ue()?; // coding block, transform block and hierarchy sizes
+        }
+        if r.read_bit()? && r.read_bit()? {
+            skip_scaling_list_data(&mut r)?;
+        }
+        r.skip(2)?; // amp_enabled_flag, sample_adaptive_offset_enabled_flag
+        if r.read_bit()? {
+            r.skip(8)?; // pcm sample bit depths
+            r.read_ue()?;
+            r.read_ue()?;
+            r.skip(1)?; // pcm_loop_filter_disabled_flag
+        }
+        let num_st_rps = r.read_ue()?.min(64) as usize;
+        let mut num_delta_pocs = [0u32; 64];
+        for idx in 0..num_st_rps {
+            num_delta_pocs[idx] = skip_st_ref_pic_set(&mut r, idx, &num_delta_pocs)?;
+        }
+        if r.read_bit()? {
+            for _ in 0..r.read_ue()?.min(32) {
+                r.skip(log2_max_poc_lsb as usize + 1)?;
+            }
+        }
+        r.skip(2)?; // temporal_mvp, strong_intra_smoothing
+        let mut sps = Self {
+            sps_id,
+            max_sub_layers: max_sub_layers_minus1 as u8 + 1,
+            general_profile_idc,
+            general_level_idc,
+            chroma_format_idc,
+            width,
+            height,
+            conformance_window,
+            bit_depth_luma,
+            bit_depth_chroma,
+            log2_max_poc_lsb,
+            field_seq: false,
+            frame_field_info_present: false,
+            timing: None,
+            hrd: None,
+        };
+        if r.read_bit()? {
+            sps.read_vui(&mut r)?;
+        }
+        Ok(sps)
+    }
+
+    /// Width and height after the conformance window, in luma samples.
+    pub fn dimensions(&self) -> (u32, u32) {
+        let (sub_width, sub_height) = match self.chroma_format_idc {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        };
+        let [left, right, top, bottom] = self.conformance_window;
+        (
+            self.width.saturating_sub(sub_width * (left + right)),
+            self.height.saturating_sub(sub_height * (top + bottom)),
+        )
+    }
+
+    fn read_vui(&mut self, r: &mut BitReader<'_>) -> Result<(), BitError> {
+        if r.read_bit()? && r.read_bits(8)? == 255 {
+            r.skip(32)?; // sar_width, sar_height
+        }
+        if r.read_bit()? {
+            r.skip(1)?; // overscan_appropriate_flag
+        }
+        if r.read_bit()? {
+            r.skip(4)?; // video_format, video_full_range_flag
+            if r.read_bit()? {
+                r.skip(24)?; // colour_primaries, transfer, matrix_coeffs
+            }
+        }
+        if r.read_bit()? {
+            r.read_ue()?; // chroma_sample_loc_type_top_field
+            r.read_ue()?;
+        }
+        r.skip(1)?; // neutral_chroma_indication_flag
+        self.field_seq = r.read_bit()?;
+        self.frame_field_info_present = r.read_bit()?;
+        if r.read_bit()? {
+            for _ in 0..4 {
+                r.read_ue()?; // def_disp_win_*_offset
+            }
+        }
+        if r.read_bit()? {
+            self.timing = Some((r.read_bits(32)?, r.read_bits(32)?));
+            if r.read_bit()? {
+                r.read_ue()?; // num_ticks_poc_diff_one_minus1
+            }
+            if r.read_bit()? {
+                self.hrd = read_hrd_lengths(r)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +379,46 @@ mod tests {
         }
         assert_eq!(off, 3);
     }
+
+    #[test]
+    fn parses_sps_through_vui_hrd() {
+        let bits = concat!(
+            "0000 000 1",
+            // Main profile, level 3.1.
+            "00 0 00001 01100000000000000000000000000000 1001",
+            "00000000000000000000000000000000000000000000 01011101",
+            // sps_id 0, 4:2:0, 1920x1080 with a conformance window.
+            "1 010 000000000011110000001 000000000010000111001 1 1 1 1 00101",
+            // 8-bit, log2_max_poc_lsb 8, one ordering entry, block sizes.
+            "1 1 00101 1 00100 1 1 1 011 1 00100 1 1",
+            // No scaling lists, SAO, no PCM.
+            "0 01 0",
+            // Two short-term sets, the second predicted from the first.
+            "011 010 1 1 1 1 1 1 1 00",
+            "0 11 1",
+            // VUI: SAR 1:1, BT.709 colour, frame_field_info_present.
+            "1 00000001 0 1 101 0 1 00001001 00010000 00001001 0 0 0 1 0",
+            // 1001/60000 timing, NAL HRD with 24/24/5-bit delay lengths.
+            "1 00000000000000000000001111101001 00000000000000001110101001100000 0 1",
+            "1 0 0 0000 0000 10111 10111 00100",
+        );
+        let nal = [&[0x42, 0x01][..], &crate::test_utils::rbsp(bits)].concat();
+        let sps = HevcSps::parse(&nal).unwrap();
+        assert_eq!((sps.general_profile_idc, sps.general_level_idc), (1, 93));
+        assert_eq!(
+            (sps.width, sps.height, sps.log2_max_poc_lsb),
+            (1920, 1080, 8)
+        );
+        assert!(sps.frame_field_info_present && !sps.field_seq);
+        assert_eq!(sps.timing, Some((1001, 60000)));
+        assert_eq!(
+            sps.hrd,
+            Some(HevcHrd {
+                sub_pic_hrd_params_present: false,
+                au_cpb_removal_delay_length: 24,
+                dpb_output_delay_length: 5,
+                dpb_output_delay_du_length: 0,
+            })
+        );
+    }
 }
@@ -1,13 +1,139 @@
 pub mod av1;
 pub mod avc;
+pub mod custom;
+pub mod h263;
 pub mod hevc;
+pub mod sei;
 pub mod vp9;
 
+pub use crate::bitstream::split_annex_b;
+
+use alloc::string::String;
+use core::{fmt, str::FromStr};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
 pub enum Codec {
     Vp9,
     Avc,
     Hevc,
     Av1,
     Unknown,
+    /// A payload format registered in a
+    /// [`CodecRegistry`](custom::CodecRegistry) under this id.
+    Custom(u32),
+}
+
+impl Codec {
+    /// RTP timestamp clock rate in Hz.
+    pub fn clock_rate(self) -> u32 {
+        // Every supported payload format mandates the 90 kHz video clock,
+        // and custom ones are expected to follow.
+        90_000
+    }
+
+    /// Media type as used in SDP and WebRTC codec capabilities, e.g.
+    /// `video/H264`. `None` for [`Codec::Unknown`] and custom codecs.
+    pub fn mime_type(self) -> Option<&'static str> {
+        match self {
+            Codec::Vp9 => Some("video/VP9"),
+            Codec::Avc => Some("video/H264"),
+            Codec::Hevc => Some("video/H265"),
+            Codec::Av1 => Some("video/AV1"),
+            Codec::Unknown | Codec::Custom(_) => None,
+        }
+    }
+}
+
+/// Prints the SDP encoding name (`H264`, `H265`, `VP9`, `AV1`), or
+/// `custom-<id>`.
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self, self.mime_type()) {
+            (_, Some(mime)) => f.write_str(&mime["video/".len()..]),
+            (Codec::Custom(id), None) => write!(f, "custom-{}", id),
+            _ => f.write_str("unknown"),
+        }
+    }
+}
+
+/// Returned by [`Codec::from_str`] for names it does not recognise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCodecError {
+    pub name: String,
+}
+
+impl fmt::Display for ParseCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown codec '{}'", self.name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseCodecError {}
+
+/// Parses an SDP encoding name or media type, case-insensitively: `H264`,
+/// `avc`, `video/H265`, `hevc`, `VP9`, `AV1`, ... and `custom-<id>`.
+/// Custom codecs are looked up by name with
+/// [`CodecRegistry::by_name`](custom::CodecRegistry::by_name).
+impl FromStr for Codec {
+    type Err = ParseCodecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = match s.split_once('/') {
+            Some((kind, name)) if kind.eq_ignore_ascii_case("video") => name,
+            _ => s,
+        };
+        let codec = [
+            ("h264", Codec::Avc),
+            ("avc", Codec::Avc),
+            ("h265", Codec::Hevc),
+            ("hevc", Codec::Hevc),
+            ("vp9", Codec::Vp9),
+            ("av1", Codec::Av1),
+        ]
+        .into_iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, codec)| codec)
+        .or_else(|| {
+            let (prefix, id) = name.split_at_checked(7)?;
+            prefix.eq_ignore_ascii_case("custom-").then_some(())?;
+            id.parse().ok().map(Codec::Custom)
+        });
+        codec.ok_or_else(|| ParseCodecError { name: s.into() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_annex_b_mixed_start_codes() {
+        let data = [
+            0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x68, 0xCE, 0, 0, 0, 1, 0x65, 0x00,
+        ];
+        let nals = split_annex_b(&data);
+        assert_eq!(nals, [&[0x67, 0x42][..], &[0x68, 0xCE], &[0x65, 0x00]]);
+        assert!(split_annex_b(&[0x65, 0x01]).is_empty());
+    }
+
+    #[test]
+    fn codec_names_round_trip() {
+        assert_eq!("custom-7".parse::<Codec>(), Ok(Codec::Custom(7)));
+        assert_eq!(Codec::Custom(7).to_string(), "custom-7");
+        for codec in [Codec::Vp9, Codec::Avc, Codec::Hevc, Codec::Av1] {
+            assert_eq!(codec.to_string().parse::<Codec>(), Ok(codec));
+            assert_eq!(codec.mime_type().unwrap().parse::<Codec>(), Ok(codec));
+            assert_eq!(codec.clock_rate(), 90_000);
+        }
+        assert_eq!("video/h265".parse::<Codec>(), Ok(Codec::Hevc));
+        assert_eq!("avc".parse::<Codec>(), Ok(Codec::Avc));
+        assert_eq!(Codec::Avc.to_string(), "H264");
+        let err = "audio/opus".parse::<Codec>().unwrap_err();
+        assert_eq!(err.to_string(), "unknown codec 'audio/opus'");
+        assert_eq!(Codec::Unknown.mime_type(), None);
+    }
 }
@@ -0,0 +1,311 @@
+//! Supplemental enhancement information (H.264 7.3.2.3, H.265 7.3.5).
+
+use super::{hevc::HevcSps, Codec};
+use crate::bitstream::{remove_emulation_prevention, BitError, BitReader};
+use alloc::{borrow::Cow, vec::Vec};
+
+/// `payloadType` of a picture timing SEI message.
+pub const SEI_PIC_TIMING: u32 = 1;
+/// `payloadType` of `user_data_registered_itu_t_t35` (e.g. captions).
+pub const SEI_USER_DATA_REGISTERED: u32 = 4;
+/// `payloadType` of a recovery point SEI message, the same in both codecs.
+pub const SEI_RECOVERY_POINT: u32 = 6;
+/// `payloadType` of an H.265 time code SEI message.
+pub const SEI_TIME_CODE: u32 = 136;
+
+/// One `sei_message()` of an SEI RBSP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeiMessage<'a> {
+    pub payload_type: u32,
+    pub payload: &'a [u8],
+}
+
+/// RBSP of an SEI NAL unit (H.264 type 6, H.265 prefix or suffix SEI),
+/// `None` for other NAL units.
+pub fn sei_rbsp(codec: Codec, nal: &[u8]) -> Option<Cow<'_, [u8]>> {
+    let header_len = match (codec, nal.first()?) {
+        (Codec::Avc, h) if h & 0x1F == 6 => 1,
+        (Codec::Hevc, h) if matches!((h >> 1) & 0x3F, 39 | 40) => 2,
+        _ => return None,
+    };
+    Some(remove_emulation_prevention(nal.get(header_len..)?))
+}
+
+/// Messages of an SEI RBSP, stopping at the trailing bits or the first
+/// truncated message.
+pub fn sei_messages(rbsp: &[u8]) -> impl Iterator<Item = SeiMessage<'_>> + '_ {
+    let mut rest = rbsp;
+    core::iter::from_fn(move || {
+        // rbsp_trailing_bits: the stop bit, then zero padding.
+        if rest.first().is_none_or(|&b| b == 0x80) {
+            return None;
+        }
+        let payload_type = read_ff_coded(&mut rest)?;
+        let size = read_ff_coded(&mut rest)? as usize;
+        let Some(payload) = rest.get(..size) else {
+            rest = &[];
+            return None;
+        };
+        rest = &rest[size..];
+        Some(SeiMessage {
+            payload_type,
+            payload,
+        })
+    })
+}
+
+// A payloadType or payloadSize: 0xFF bytes each adding 255, then the last byte.
+fn read_ff_coded(data: &mut &[u8]) -> Option<u32> {
+    let mut value = 0u32;
+    loop {
+        let (&b, rest) = data.split_first()?;
+        *data = rest;
+        value = value.saturating_add(b as u32);
+        if b != 0xFF {
+            return Some(value);
+        }
+    }
+}
+
+/// Recovery point SEI: decoding from this picture is correct (or close to
+/// it) after `recovery_frames` more pictures, without an IDR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecoveryPoint {
+    /// `recovery_frame_cnt` (H.264) or `recovery_poc_cnt` (H.265, negative
+    /// values clamped to 0).
+    pub recovery_frames: u32,
+    pub exact_match: bool,
+    pub broken_link: bool,
+}
+
+impl RecoveryPoint {
+    /// Parses the payload of a [`SEI_RECOVERY_POINT`] message.
+    pub fn parse(codec: Codec, payload: &[u8]) -> Result<Self, BitError> {
+        let mut r = BitReader::new(payload);
+        let recovery_frames = match codec {
+            Codec::Hevc => r.read_se()?.max(0) as u32,
+            _ => r.read_ue()?,
+        };
+        Ok(Self {
+            recovery_frames,
+            exact_match: r.read_bit()?,
+            broken_link: r.read_bit()?,
+        })
+    }
+
+    /// First recovery point SEI in a NAL unit, if it is an SEI carrying one.
+    pub fn find(codec: Codec, nal: &[u8]) -> Option<Self> {
+        let rbsp = sei_rbsp(codec, nal)?;
+        let found = sei_messages(&rbsp)
+            .filter(|m| m.payload_type == SEI_RECOVERY_POINT)
+            .find_map(|m| Self::parse(codec, m.payload).ok());
+        found
+    }
+}
+
+/// `pic_struct`, `source_scan_type` and `duplicate_flag` of an H.265
+/// picture timing SEI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameFieldInfo {
+    /// Table D.2, e.g. 0 frame, 1/2 top/bottom field.
+    pub pic_struct: u8,
+    /// 0 interlaced, 1 progressive, 2 unknown.
+    pub source_scan_type: u8,
+    pub duplicate: bool,
+}
+
+impl FrameFieldInfo {
+    /// The picture is a single field rather than a frame.
+    pub fn is_field(&self) -> bool {
+        matches!(self.pic_struct, 1 | 2 | 9..=12)
+    }
+}
+
+/// H.265 picture timing SEI (D.2.3), as far as the SPS describes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HevcPicTiming {
+    /// Present with the VUI `frame_field_info_present_flag`.
+    pub frame_field: Option<FrameFieldInfo>,
+    /// `au_cpb_removal_delay_minus1 + 1`, with HRD parameters.
+    pub cpb_removal_delay: Option<u32>,
+    /// `pic_dpb_output_delay`, with HRD parameters.
+    pub dpb_output_delay: Option<u32>,
+}
+
+impl HevcPicTiming {
+    /// Parses the payload of a [`SEI_PIC_TIMING`] message of a stream
+    /// using `sps`.
+    pub fn parse(payload: &[u8], sps: &HevcSps) -> Result<Self, BitError> {
+        let mut r = BitReader::new(payload);
+        let mut timing = Self {
+            frame_field: None,
+            cpb_removal_delay: None,
+            dpb_output_delay: None,
+        };
+        if sps.frame_field_info_present {
+            timing.frame_field = Some(FrameFieldInfo {
+                pic_struct: r.read_bits(4)? as u8,
+                source_scan_type: r.read_bits(2)? as u8,
+                duplicate: r.read_bit()?,
+            });
+        }
+        if let Some(hrd) = sps.hrd {
+            let cpb = r.read_bits(hrd.au_cpb_removal_delay_length as u32)?;
+            timing.cpb_removal_delay = Some(cpb.saturating_add(1));
+            timing.dpb_output_delay = Some(r.read_bits(hrd.dpb_output_delay_length as u32)?);
+        }
+        Ok(timing)
+    }
+}
+
+/// One `clock_timestamp` of an H.265 time code SEI (D.2.27). Fields left
+/// out of a partial timestamp are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClockTimestamp {
+    pub units_field_based: bool,
+    /// Table D.3, e.g. 4 for drop-frame counting.
+    pub counting_type: u8,
+    pub discontinuity: bool,
+    pub cnt_dropped: bool,
+    pub n_frames: u16,
+    pub seconds: Option<u8>,
+    pub minutes: Option<u8>,
+    pub hours: Option<u8>,
+    /// Signed offset in clock ticks.
+    pub time_offset: i32,
+}
+
+/// Parses the clock timestamps of a [`SEI_TIME_CODE`] payload.
+pub fn parse_time_code(payload: &[u8]) -> Result<Vec<ClockTimestamp>, BitError> {
+    let mut r = BitReader::new(payload);
+    let mut timestamps = Vec::new();
+    for _ in 0..r.read_bits(2)? {
+        if !r.read_bit()? {
+            continue;
+        }
+        let units_field_based = r.read_bit()?;
+        let counting_type = r.read_bits(5)? as u8;
+        let full_timestamp = r.read_bit()?;
+        let discontinuity = r.read_bit()?;
+        let cnt_dropped = r.read_bit()?;
+        let n_frames = r.read_bits(9)? as u16;
+        let (mut seconds, mut minutes, mut hours) = (None, None, None);
+        if full_timestamp {
+            seconds = Some(r.read_bits(6)? as u8);
+            minutes = Some(r.read_bits(6)? as u8);
+            hours = Some(r.read_bits(5)? as u8);
+        } else if r.read_bit()? {
+            seconds = Some(r.read_bits(6)? as u8);
+            if r.read_bit()? {
+                minutes = Some(r.read_bits(6)? as u8);
+                if r.read_bit()? {
+                    hours = Some(r.read_bits(5)? as u8);
+                }
+            }
+        }
+        let offset_len = r.read_bits(5)?;
+        let mut time_offset = 0;
+        if offset_len > 0 {
+            // i(v): two's complement of offset_len bits.
+            let raw = r.read_bits(offset_len)?;
+            time_offset = ((raw << (32 - offset_len)) as i32) >> (32 - offset_len);
+        }
+        timestamps.push(ClockTimestamp {
+            units_field_based,
+            counting_type,
+            discontinuity,
+            cnt_dropped,
+            n_frames,
+            seconds,
+            minutes,
+            hours,
+            time_offset,
+        });
+    }
+    Ok(timestamps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_recovery_point_among_messages() {
+        // User data (type 5) of six emulation-prevented zero bytes, then a
+        // recovery point with recovery_frame_cnt 3 and exact_match set.
+        let nal = [
+            0x06, 0x05, 0x06, 0, 0, 3, 0, 0, 3, 0, 0, 0x06, 0x02, 0x24, 0x40, 0x80,
+        ];
+        let rbsp = sei_rbsp(Codec::Avc, &nal).unwrap();
+        let types: alloc::vec::Vec<_> = sei_messages(&rbsp).map(|m| m.payload_type).collect();
+        assert_eq!(types, [5, 6]);
+        assert_eq!(
+            RecoveryPoint::find(Codec::Avc, &nal),
+            Some(RecoveryPoint {
+                recovery_frames: 3,
+                exact_match: true,
+                broken_link: false,
+            })
+        );
+
+        // H.265 prefix SEI, recovery_poc_cnt -1 (se "011").
+        let hevc = [0x4E, 0x01, 0x06, 0x01, 0x78, 0x80];
+        assert_eq!(
+            RecoveryPoint::find(Codec::Hevc, &hevc).map(|rp| rp.recovery_frames),
+            Some(0)
+        );
+        assert_eq!(RecoveryPoint::find(Codec::Avc, &[0x65, 0x88]), None);
+    }
+
+    #[test]
+    fn parses_hevc_picture_timing_and_time_code() {
+        use crate::{codecs::hevc::HevcHrd, test_utils::rbsp};
+        let sps = HevcSps {
+            sps_id: 0,
+            max_sub_layers: 1,
+            general_profile_idc: 1,
+            general_level_idc: 93,
+            chroma_format_idc: 1,
+            width: 1920,
+            height: 540,
+            conformance_window: [0; 4],
+            bit_depth_luma: 8,
+            bit_depth_chroma: 8,
+            log2_max_poc_lsb: 8,
+            field_seq: true,
+            frame_field_info_present: true,
+            timing: None,
+            hrd: Some(HevcHrd {
+                sub_pic_hrd_params_present: false,
+                au_cpb_removal_delay_length: 24,
+                dpb_output_delay_length: 5,
+                dpb_output_delay_du_length: 0,
+            }),
+        };
+        // Top field, interlaced source; cpb delay 1 + 1, dpb delay 3.
+        let payload = rbsp("0001 00 0 000000000000000000000001 00011");
+        let timing = HevcPicTiming::parse(&payload, &sps).unwrap();
+        let field = timing.frame_field.unwrap();
+        assert!(field.is_field() && field.source_scan_type == 0);
+        assert_eq!(timing.cpb_removal_delay, Some(2));
+        assert_eq!(timing.dpb_output_delay, Some(3));
+
+        // One full drop-frame timestamp 01:10:05;29, time offset -2.
+        let payload = rbsp("01 1 0 00100 1 0 1 000011101 000101 001010 00001 00100 1110");
+        let ts = parse_time_code(&payload).unwrap();
+        assert_eq!(ts.len(), 1);
+        assert_eq!(
+            (ts[0].hours, ts[0].minutes, ts[0].seconds),
+            (Some(1), Some(10), Some(5))
+        );
+        assert_eq!(
+            (ts[0].n_frames, ts[0].counting_type, ts[0].time_offset),
+            (29, 4, -2)
+        );
+        assert!(ts[0].cnt_dropped && !ts[0].discontinuity);
+    }
+}
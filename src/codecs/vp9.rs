@@ -1,4 +1,43 @@
+use crate::bitstream::{BitError, BitReader};
+use alloc::vec::Vec;
+
+/// Layer indices carried when the L bit is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Vp9LayerIndices {
+    pub temporal_id: u8,
+    pub switching_up_point: bool,
+    pub spatial_id: u8,
+    pub inter_layer_dependency: bool,
+}
+
+/// Picture group entry of the scalability structure.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Vp9PictureGroupEntry {
+    pub temporal_id: u8,
+    pub switching_up_point: bool,
+    pub p_diffs: Vec<u8>,
+}
+
+/// Scalability structure (SS) carried when the V bit is set.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Vp9ScalabilityStructure {
+    /// Number of spatial layers, 1 to 8.
+    pub spatial_layers: u8,
+    /// Width and height of each spatial layer, if signalled.
+    pub resolutions: Option<Vec<(u16, u16)>>,
+    /// Picture group description, if signalled.
+    pub picture_group: Option<Vec<Vp9PictureGroupEntry>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Vp9PayloadDesc {
     pub i_bit: bool,
     pub p_bit: bool,
@@ -9,20 +48,43 @@ pub struct Vp9PayloadDesc {
     pub v_bit: bool,
     pub z_bit: bool,
     pub picture_id: Option<u16>,
+    pub layer: Option<Vp9LayerIndices>,
+    // Only present in non-flexible mode with layer indices.
+    pub tl0_pic_idx: Option<u8>,
+    // Reference differences, flexible mode with P set only (at most 3).
+    pub p_diffs: Vec<u8>,
+    pub scalability: Option<Vp9ScalabilityStructure>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Vp9Error {
-    BufferTooShort,
+    /// The descriptor ends before `field`, which starts at `offset`.
+    BufferTooShort { field: &'static str, offset: usize },
+}
+
+impl core::fmt::Display for Vp9Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Vp9Error::BufferTooShort { field, offset } => {
+                write!(f, "payload too short for {} at offset {}", field, offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Vp9Error {}
+
+fn byte_at(buf: &[u8], offset: usize, field: &'static str) -> Result<u8, Vp9Error> {
+    buf.get(offset)
+        .copied()
+        .ok_or(Vp9Error::BufferTooShort { field, offset })
 }
 
 impl Vp9PayloadDesc {
-    // Parses the VP9 payload descriptor as per RFC 8585.
+    // Parses the VP9 payload descriptor as per RFC 9628.
     pub fn parse(buf: &[u8]) -> Result<(Self, usize), Vp9Error> {
-        if buf.is_empty() {
-            return Err(Vp9Error::BufferTooShort);
-        }
-        let b0 = buf[0];
+        let b0 = byte_at(buf, 0, "descriptor")?;
         let i_bit = (b0 & 0x80) != 0;
         let p_bit = (b0 & 0x40) != 0;
         let l_bit = (b0 & 0x20) != 0;
@@ -34,22 +96,48 @@ impl Vp9PayloadDesc {
         let mut offset = 1usize;
         let mut picture_id = None;
         if i_bit {
-            if buf.len() < offset + 1 {
-                return Err(Vp9Error::BufferTooShort);
-            }
-            let b = buf[offset];
+            let b = byte_at(buf, offset, "picture ID")?;
             let m = (b & 0x80) != 0;
             let mut pid = (b & 0x7F) as u16;
             offset += 1;
             if m {
-                if buf.len() < offset + 1 {
-                    return Err(Vp9Error::BufferTooShort);
-                }
-                pid = (pid << 8) | buf[offset] as u16;
+                pid = (pid << 8) | byte_at(buf, offset, "extended picture ID")? as u16;
                 offset += 1;
             }
             picture_id = Some(pid);
         }
+        let mut layer = None;
+        let mut tl0_pic_idx = None;
+        if l_bit {
+            let b = byte_at(buf, offset, "layer indices")?;
+            offset += 1;
+            layer = Some(Vp9LayerIndices {
+                temporal_id: b >> 5,
+                switching_up_point: (b & 0x10) != 0,
+                spatial_id: (b >> 1) & 0x07,
+                inter_layer_dependency: (b & 0x01) != 0,
+            });
+            if !f_bit {
+                tl0_pic_idx = Some(byte_at(buf, offset, "TL0PICIDX")?);
+                offset += 1;
+            }
+        }
+        let mut p_diffs = Vec::new();
+        if f_bit && p_bit {
+            loop {
+                let b = byte_at(buf, offset, "P_DIFF")?;
+                offset += 1;
+                p_diffs.push(b >> 1);
+                if b & 0x01 == 0 || p_diffs.len() == 3 {
+                    break;
+                }
+            }
+        }
+        let scalability = if v_bit {
+            Some(parse_scalability_structure(buf, &mut offset)?)
+        } else {
+            None
+        };
         Ok((
             Self {
                 i_bit,
@@ -61,16 +149,305 @@ impl Vp9PayloadDesc {
                 v_bit,
                 z_bit,
                 picture_id,
+                layer,
+                tl0_pic_idx,
+                p_diffs,
+                scalability,
             },
             offset,
         ))
     }
+
+    /// Serializes the descriptor. The I, L and V bits follow `picture_id`,
+    /// `layer` and `scalability`; picture ids always use the 15-bit form.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        let flags = [
+            (self.picture_id.is_some(), 0x80),
+            (self.p_bit, 0x40),
+            (self.layer.is_some(), 0x20),
+            (self.f_bit, 0x10),
+            (self.b_bit, 0x08),
+            (self.e_bit, 0x04),
+            (self.scalability.is_some(), 0x02),
+            (self.z_bit, 0x01),
+        ];
+        out.push(
+            flags
+                .iter()
+                .filter(|(set, _)| *set)
+                .fold(0, |acc, (_, bit)| acc | bit),
+        );
+        if let Some(pid) = self.picture_id {
+            out.push(0x80 | ((pid >> 8) as u8 & 0x7F));
+            out.push(pid as u8);
+        }
+        if let Some(layer) = self.layer {
+            out.push(
+                (layer.temporal_id << 5)
+                    | ((layer.switching_up_point as u8) << 4)
+                    | ((layer.spatial_id & 0x07) << 1)
+                    | layer.inter_layer_dependency as u8,
+            );
+            if !self.f_bit {
+                out.push(self.tl0_pic_idx.unwrap_or(0));
+            }
+        }
+        if self.f_bit && self.p_bit {
+            let diffs = &self.p_diffs[..self.p_diffs.len().min(3)];
+            for (i, diff) in diffs.iter().enumerate() {
+                out.push((diff << 1) | (i + 1 < diffs.len()) as u8);
+            }
+        }
+        if let Some(ss) = &self.scalability {
+            write_scalability_structure(ss, out);
+        }
+    }
+}
+
+fn parse_scalability_structure(
+    buf: &[u8],
+    offset: &mut usize,
+) -> Result<Vp9ScalabilityStructure, Vp9Error> {
+    let mut next = || {
+        let b = byte_at(buf, *offset, "scalability structure")?;
+        *offset += 1;
+        Ok(b)
+    };
+    let b = next()?;
+    let spatial_layers = (b >> 5) + 1;
+    let resolutions = if b & 0x10 != 0 {
+        let mut res = Vec::with_capacity(spatial_layers as usize);
+        for _ in 0..spatial_layers {
+            let w = u16::from_be_bytes([next()?, next()?]);
+            let h = u16::from_be_bytes([next()?, next()?]);
+            res.push((w, h));
+        }
+        Some(res)
+    } else {
+        None
+    };
+    let picture_group = if b & 0x08 != 0 {
+        let count = next()?;
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let g = next()?;
+            let mut p_diffs = Vec::new();
+            for _ in 0..(g >> 2) & 0x03 {
+                p_diffs.push(next()?);
+            }
+            entries.push(Vp9PictureGroupEntry {
+                temporal_id: g >> 5,
+                switching_up_point: (g & 0x10) != 0,
+                p_diffs,
+            });
+        }
+        Some(entries)
+    } else {
+        None
+    };
+    Ok(Vp9ScalabilityStructure {
+        spatial_layers,
+        resolutions,
+        picture_group,
+    })
+}
+
+fn write_scalability_structure(ss: &Vp9ScalabilityStructure, out: &mut Vec<u8>) {
+    let n_s = ss.spatial_layers.clamp(1, 8) - 1;
+    out.push(
+        (n_s << 5)
+            | ((ss.resolutions.is_some() as u8) << 4)
+            | ((ss.picture_group.is_some() as u8) << 3),
+    );
+    if let Some(res) = &ss.resolutions {
+        for i in 0..=n_s as usize {
+            let (w, h) = res.get(i).copied().unwrap_or_default();
+            out.extend_from_slice(&w.to_be_bytes());
+            out.extend_from_slice(&h.to_be_bytes());
+        }
+    }
+    if let Some(group) = &ss.picture_group {
+        out.push(group.len().min(255) as u8);
+        for entry in group.iter().take(255) {
+            let diffs = &entry.p_diffs[..entry.p_diffs.len().min(3)];
+            out.push(
+                (entry.temporal_id << 5)
+                    | ((entry.switching_up_point as u8) << 4)
+                    | ((diffs.len() as u8) << 2),
+            );
+            out.extend_from_slice(diffs);
+        }
+    }
+}
+
+const CS_RGB: u8 = 7;
+
+/// `color_config()` of a VP9 uncompressed header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vp9ColorConfig {
+    pub bit_depth: u8,
+    /// `color_space`, e.g. 1 BT.601, 2 BT.709, 5 BT.2020, 7 sRGB.
+    pub color_space: u8,
+    /// `color_range`: full (0-255) rather than studio swing.
+    pub full_range: bool,
+    pub subsampling_x: bool,
+    pub subsampling_y: bool,
+}
+
+impl Vp9ColorConfig {
+    // Profile 0 intra-only frames leave the color config implicit.
+    const PROFILE_0: Self = Self {
+        bit_depth: 8,
+        color_space: 1,
+        full_range: false,
+        subsampling_x: true,
+        subsampling_y: true,
+    };
+
+    fn read(r: &mut BitReader<'_>, profile: u8) -> Result<Self, BitError> {
+        let bit_depth = match profile {
+            0 | 1 => 8,
+            _ if r.read_bit()? => 12,
+            _ => 10,
+        };
+        let color_space = r.read_bits(3)? as u8;
+        let chroma_bits = profile == 1 || profile == 3;
+        let (full_range, subsampling_x, subsampling_y) = if color_space != CS_RGB {
+            let full_range = r.read_bit()?;
+            if chroma_bits {
+                let ss = (r.read_bit()?, r.read_bit()?);
+                r.skip(1)?; // reserved_zero
+                (full_range, ss.0, ss.1)
+            } else {
+                (full_range, true, true)
+            }
+        } else {
+            if chroma_bits {
+                r.skip(1)?; // reserved_zero
+            }
+            (true, false, false)
+        };
+        Ok(Self {
+            bit_depth,
+            color_space,
+            full_range,
+            subsampling_x,
+            subsampling_y,
+        })
+    }
+
+    /// ISO/IEC 23091-4 `MatrixCoefficients` for vpcC and WebM `Colour`;
+    /// 2 (unspecified) for unknown or reserved color spaces.
+    pub fn matrix_coefficients(&self) -> u8 {
+        match self.color_space {
+            1 => 5, // BT.601 (BT.470 System B, G)
+            2 => 1, // BT.709
+            3 => 6, // SMPTE 170M
+            4 => 7, // SMPTE 240M
+            5 => 9, // BT.2020 non-constant luminance
+            CS_RGB => 0,
+            _ => 2,
+        }
+    }
+}
+
+/// Stream parameters from the uncompressed header of a key frame or an
+/// intra-only frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vp9StreamParams {
+    pub profile: u8,
+    pub color: Vp9ColorConfig,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Vp9StreamParams {
+    /// Parses the start of a VP9 frame; `None` for inter frames and frames
+    /// showing an existing one.
+    pub fn parse(frame: &[u8]) -> Result<Option<Self>, BitError> {
+        let mut r = BitReader::new(frame);
+        if r.read_bits(2)? != 2 {
+            return Ok(None);
+        }
+        let profile = (r.read_bits(1)? | (r.read_bits(1)? << 1)) as u8;
+        if profile == 3 {
+            r.skip(1)?;
+        }
+        if r.read_bit()? {
+            return Ok(None); // show_existing_frame
+        }
+        let key_frame = !r.read_bit()?;
+        let show_frame = r.read_bit()?;
+        let error_resilient = r.read_bit()?;
+        let color = if key_frame {
+            r.skip(24)?; // frame_sync_code
+            Vp9ColorConfig::read(&mut r, profile)?
+        } else {
+            let intra_only = !show_frame && r.read_bit()?;
+            if !intra_only {
+                return Ok(None);
+            }
+            if !error_resilient {
+                r.skip(2)?; // reset_frame_context
+            }
+            r.skip(24)?; // frame_sync_code
+            let color = if profile > 0 {
+                Vp9ColorConfig::read(&mut r, profile)?
+            } else {
+                Vp9ColorConfig::PROFILE_0
+            };
+            r.skip(8)?; // refresh_frame_flags
+            color
+        };
+        Ok(Some(Self {
+            profile,
+            color,
+            width: r.read_bits(16)? + 1,
+            height: r.read_bits(16)? + 1,
+        }))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn parses_key_frame_color_config() {
+        use crate::test_utils::rbsp;
+        // Profile 0 key frame, BT.709 full range, 1280x720.
+        let frame = rbsp(concat!(
+            "10 0 0 0 0 1 0 010010011000001101000010",
+            "010 1 0000010011111111 0000001011001111"
+        ));
+        let params = Vp9StreamParams::parse(&frame).unwrap().unwrap();
+        assert_eq!(
+            (params.profile, params.width, params.height),
+            (0, 1280, 720)
+        );
+        assert_eq!(params.color.bit_depth, 8);
+        assert!(params.color.full_range && params.color.subsampling_y);
+        assert_eq!(params.color.matrix_coefficients(), 1);
+
+        // Profile 2 (10-bit) key frame, BT.2020 studio range.
+        let frame = rbsp(
+            "10 0 1 0 0 1 0 010010011000001101000010 0 101 0 0000000000000000 0000000000000000",
+        );
+        let params = Vp9StreamParams::parse(&frame).unwrap().unwrap();
+        assert_eq!(
+            (
+                params.profile,
+                params.color.bit_depth,
+                params.color.color_space
+            ),
+            (2, 10, 5)
+        );
+        // Inter frame.
+        assert_eq!(Vp9StreamParams::parse(&[0x84, 0x00]), Ok(None));
+    }
+
     #[test]
     fn parse_vp9_payload_descriptor_basic() {
         // I=1 P=0 L=0 F=0 B=1 E=0 V=0 Z=0, PictureID=13 (7-bit)
@@ -84,4 +461,73 @@ mod tests {
         assert_eq!(desc.picture_id, Some(13));
         assert_eq!(off, 2);
     }
+
+    #[test]
+    fn parse_layer_indices_and_p_diffs() {
+        // Non-flexible: L=1 carries the layer byte and TL0PICIDX.
+        let buf = [0xA8, 0x05, 0b0101_0011, 0x07, 0xAA];
+        let (desc, off) = Vp9PayloadDesc::parse(&buf).unwrap();
+        assert_eq!(
+            desc.layer,
+            Some(Vp9LayerIndices {
+                temporal_id: 2,
+                switching_up_point: true,
+                spatial_id: 1,
+                inter_layer_dependency: true,
+            })
+        );
+        assert_eq!(desc.tl0_pic_idx, Some(7));
+        assert_eq!(off, 4);
+
+        // Flexible with P: P_DIFFs chained by the N bit, no TL0PICIDX.
+        let buf = [0x78, 0x00, (3 << 1) | 1, 5 << 1, 0xAA];
+        let (desc, off) = Vp9PayloadDesc::parse(&buf).unwrap();
+        assert_eq!(desc.tl0_pic_idx, None);
+        assert_eq!(desc.p_diffs, [3, 5]);
+        assert_eq!(off, 4);
+        assert_eq!(
+            Vp9PayloadDesc::parse(&buf[..3]),
+            Err(Vp9Error::BufferTooShort {
+                field: "P_DIFF",
+                offset: 3
+            })
+        );
+    }
+
+    #[test]
+    fn write_round_trips_with_scalability_structure() {
+        let desc = Vp9PayloadDesc {
+            b_bit: true,
+            z_bit: true,
+            picture_id: Some(0x1234),
+            layer: Some(Vp9LayerIndices {
+                spatial_id: 1,
+                ..Default::default()
+            }),
+            tl0_pic_idx: Some(9),
+            scalability: Some(Vp9ScalabilityStructure {
+                spatial_layers: 2,
+                resolutions: Some(vec![(320, 180), (640, 360)]),
+                picture_group: Some(vec![Vp9PictureGroupEntry {
+                    temporal_id: 0,
+                    switching_up_point: false,
+                    p_diffs: vec![1],
+                }]),
+            }),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        desc.write(&mut buf);
+        let (parsed, off) = Vp9PayloadDesc::parse(&buf).unwrap();
+        assert_eq!(off, buf.len());
+        assert_eq!(
+            parsed,
+            Vp9PayloadDesc {
+                i_bit: true,
+                l_bit: true,
+                v_bit: true,
+                ..desc
+            }
+        );
+    }
 }
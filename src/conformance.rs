@@ -0,0 +1,381 @@
+//! Running a corpus of reference captures against golden output.
+//!
+//! A corpus is a directory of captures (`.pcap`, `.rtpdump` or `.rtpr`
+//! recordings, see [`crate::capture`]) holding one video stream each. Next
+//! to every capture sits the golden elementary stream it must reassemble
+//! to, with the same stem and an extension naming the codec: `.h264`,
+//! `.h265`, `.vp9` (frames back to back) or `.obu` (low overhead bitstream).
+//! Large streams can be kept as `<stem>.<ext>.fnv64` instead, holding the
+//! stream's 64-bit FNV-1a hash in hex. [`Corpus::run`] checks every case;
+//! [`Corpus::bless`] rewrites the golden files after an intended change.
+
+use crate::{
+    capture::{read_capture, CaptureError},
+    codecs::Codec,
+    reassemble::{fnv1a, ConfigError, FrameReassembler, ReassemblerConfig},
+    rtp::RtpPacket,
+};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    string::String,
+    vec::Vec,
+};
+
+const CAPTURE_EXTENSIONS: [&str; 3] = ["pcap", "rtpdump", "rtpr"];
+const GOLDEN_EXTENSIONS: [(&str, Codec); 4] = [
+    ("h264", Codec::Avc),
+    ("h265", Codec::Hevc),
+    ("vp9", Codec::Vp9),
+    ("obu", Codec::Av1),
+];
+
+#[derive(Debug)]
+pub enum ConformanceError {
+    Io(PathBuf, io::Error),
+    Capture(PathBuf, CaptureError),
+    /// A capture has no golden stream or hash next to it.
+    MissingGolden(PathBuf),
+    /// A hash file does not hold a hexadecimal 64-bit value.
+    InvalidHash(PathBuf),
+    /// The reassembler configuration is invalid.
+    Config(ConfigError),
+}
+
+impl fmt::Display for ConformanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConformanceError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConformanceError::Capture(path, e) => write!(f, "{}: {}", path.display(), e),
+            ConformanceError::MissingGolden(path) => {
+                write!(f, "{}: no golden stream next to it", path.display())
+            }
+            ConformanceError::InvalidHash(path) => {
+                write!(f, "{}: not a hexadecimal FNV-1a hash", path.display())
+            }
+            ConformanceError::Config(e) => write!(f, "invalid reassembler config: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConformanceError {}
+
+/// What a capture's output is compared against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Golden {
+    /// The expected elementary stream, byte for byte.
+    Stream(PathBuf),
+    /// A file holding the expected stream's FNV-1a hash.
+    Hash(PathBuf),
+}
+
+/// One capture and its golden output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    /// File name of the capture without its extension.
+    pub name: String,
+    pub capture: PathBuf,
+    pub codec: Codec,
+    pub golden: Golden,
+}
+
+/// How a case's output compared with its golden output.
+#[derive(Debug)]
+pub enum Outcome {
+    Pass,
+    /// The streams first differ at byte `offset`.
+    Differs {
+        offset: usize,
+        expected_len: usize,
+        actual_len: usize,
+    },
+    HashDiffers {
+        expected: u64,
+        actual: u64,
+    },
+    Error(ConformanceError),
+}
+
+#[derive(Debug)]
+pub struct CaseReport {
+    pub name: String,
+    /// Frames the capture reassembled to.
+    pub frames: usize,
+    pub outcome: Outcome,
+}
+
+/// Result of [`Corpus::run`]; its `Display` lists one line per case.
+#[derive(Debug)]
+pub struct CorpusReport {
+    pub cases: Vec<CaseReport>,
+}
+
+impl CorpusReport {
+    pub fn passed(&self) -> bool {
+        self.cases
+            .iter()
+            .all(|c| matches!(c.outcome, Outcome::Pass))
+    }
+}
+
+impl fmt::Display for CorpusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for case in &self.cases {
+            match &case.outcome {
+                Outcome::Pass => writeln!(f, "ok   {} ({} frames)", case.name, case.frames)?,
+                Outcome::Differs {
+                    offset,
+                    expected_len,
+                    actual_len,
+                } => writeln!(
+                    f,
+                    "FAIL {}: differs at byte {} ({} bytes, expected {})",
+                    case.name, offset, actual_len, expected_len
+                )?,
+                Outcome::HashDiffers { expected, actual } => writeln!(
+                    f,
+                    "FAIL {}: hash {:016x}, expected {:016x}",
+                    case.name, actual, expected
+                )?,
+                Outcome::Error(e) => writeln!(f, "FAIL {}: {}", case.name, e)?,
+            }
+        }
+        let passed = self
+            .cases
+            .iter()
+            .filter(|c| matches!(c.outcome, Outcome::Pass))
+            .count();
+        write!(f, "{} of {} cases passed", passed, self.cases.len())
+    }
+}
+
+/// The cases of a corpus directory, in file name order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Corpus {
+    pub cases: Vec<Case>,
+}
+
+impl Corpus {
+    /// Finds every capture in `dir` (not recursively) and its golden file.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, ConformanceError> {
+        let dir = dir.as_ref();
+        let io_error = |e| ConformanceError::Io(dir.to_path_buf(), e);
+        let mut captures = Vec::new();
+        for entry in fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let is_capture = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| CAPTURE_EXTENSIONS.contains(&e));
+            if is_capture && path.is_file() {
+                captures.push(path);
+            }
+        }
+        captures.sort();
+        let cases = captures
+            .into_iter()
+            .map(Case::for_capture)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { cases })
+    }
+
+    pub fn run(&self) -> CorpusReport {
+        self.run_with(ReassemblerConfig::default())
+    }
+
+    /// Runs every case through a reassembler configured with `config`, its
+    /// codec replaced by the case's; e.g. for HEVC with DONL.
+    pub fn run_with(&self, config: ReassemblerConfig) -> CorpusReport {
+        CorpusReport {
+            cases: self.cases.iter().map(|c| c.run_with(config)).collect(),
+        }
+    }
+
+    /// Overwrites every golden file with the current output.
+    pub fn bless(&self) -> Result<(), ConformanceError> {
+        self.bless_with(ReassemblerConfig::default())
+    }
+
+    pub fn bless_with(&self, config: ReassemblerConfig) -> Result<(), ConformanceError> {
+        self.cases.iter().try_for_each(|c| c.bless_with(config))
+    }
+}
+
+impl Case {
+    fn for_capture(capture: PathBuf) -> Result<Self, ConformanceError> {
+        let name = capture
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        for (extension, codec) in GOLDEN_EXTENSIONS {
+            let stream = capture.with_extension(extension);
+            let hash = capture.with_extension(std::format!("{}.fnv64", extension));
+            let golden = if stream.is_file() {
+                Golden::Stream(stream)
+            } else if hash.is_file() {
+                Golden::Hash(hash)
+            } else {
+                continue;
+            };
+            return Ok(Self {
+                name,
+                capture,
+                codec,
+                golden,
+            });
+        }
+        Err(ConformanceError::MissingGolden(capture))
+    }
+
+    /// Reassembles the capture; returns the elementary stream and its
+    /// number of frames.
+    pub fn assemble(&self) -> Result<(Vec<u8>, usize), ConformanceError> {
+        self.assemble_with(ReassemblerConfig::default())
+    }
+
+    pub fn assemble_with(
+        &self,
+        config: ReassemblerConfig,
+    ) -> Result<(Vec<u8>, usize), ConformanceError> {
+        let bytes =
+            fs::read(&self.capture).map_err(|e| ConformanceError::Io(self.capture.clone(), e))?;
+        let packets =
+            read_capture(&bytes).map_err(|e| ConformanceError::Capture(self.capture.clone(), e))?;
+        let config = ReassemblerConfig {
+            codec: Some(self.codec),
+            ..config
+        };
+        let mut reassembler =
+            FrameReassembler::from_config(config).map_err(ConformanceError::Config)?;
+        let (mut stream, mut frames) = (Vec::new(), 0);
+        for packet in &packets {
+            let Ok(pkt) = RtpPacket::parse(&packet.data) else {
+                continue;
+            };
+            if let Some(frame) = reassembler.push_packet(&pkt) {
+                stream.extend_from_slice(&frame);
+                frames += 1;
+            }
+        }
+        Ok((stream, frames))
+    }
+
+    pub fn run(&self) -> CaseReport {
+        self.run_with(ReassemblerConfig::default())
+    }
+
+    pub fn run_with(&self, config: ReassemblerConfig) -> CaseReport {
+        let (outcome, frames) = match self.assemble_with(config) {
+            Ok((stream, frames)) => (self.compare(&stream), frames),
+            Err(e) => (Outcome::Error(e), 0),
+        };
+        CaseReport {
+            name: self.name.clone(),
+            frames,
+            outcome,
+        }
+    }
+
+    fn compare(&self, actual: &[u8]) -> Outcome {
+        match &self.golden {
+            Golden::Stream(path) => match fs::read(path) {
+                Ok(expected) if expected == actual => Outcome::Pass,
+                Ok(expected) => Outcome::Differs {
+                    offset: expected
+                        .iter()
+                        .zip(actual)
+                        .take_while(|(a, b)| a == b)
+                        .count(),
+                    expected_len: expected.len(),
+                    actual_len: actual.len(),
+                },
+                Err(e) => Outcome::Error(ConformanceError::Io(path.clone(), e)),
+            },
+            Golden::Hash(path) => match read_hash(path) {
+                Ok(expected) if expected == fnv1a(actual) => Outcome::Pass,
+                Ok(expected) => Outcome::HashDiffers {
+                    expected,
+                    actual: fnv1a(actual),
+                },
+                Err(e) => Outcome::Error(e),
+            },
+        }
+    }
+
+    /// Overwrites the golden file with the current output.
+    pub fn bless(&self) -> Result<(), ConformanceError> {
+        self.bless_with(ReassemblerConfig::default())
+    }
+
+    pub fn bless_with(&self, config: ReassemblerConfig) -> Result<(), ConformanceError> {
+        let (stream, _) = self.assemble_with(config)?;
+        let (path, contents) = match &self.golden {
+            Golden::Stream(path) => (path, stream),
+            Golden::Hash(path) => (path, std::format!("{:016x}\n", fnv1a(&stream)).into()),
+        };
+        fs::write(path, contents).map_err(|e| ConformanceError::Io(path.clone(), e))
+    }
+}
+
+fn read_hash(path: &Path) -> Result<u64, ConformanceError> {
+    let text = fs::read_to_string(path).map_err(|e| ConformanceError::Io(path.to_path_buf(), e))?;
+    u64::from_str_radix(text.trim(), 16)
+        .map_err(|_| ConformanceError::InvalidHash(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        avc, capture,
+        fixtures::{annex_b, AVC_IDR, AVC_NON_IDR},
+        StreamBuilder,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn compares_captures_with_golden_streams_and_blesses_them() {
+        let dir = std::env::temp_dir().join(std::format!("rtpar-corpus-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let packets = StreamBuilder::new(5)
+            .frame(&avc::fu_a(AVC_IDR, 4))
+            .frame(&[avc::single(AVC_NON_IDR)])
+            .build();
+        let timed: Vec<_> = packets
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| (Duration::from_millis(i as u64), p))
+            .collect();
+        let expected = [annex_b(&[AVC_IDR]), annex_b(&[AVC_NON_IDR])].concat();
+        fs::write(dir.join("a.pcap"), capture::pcap(&timed)).unwrap();
+        fs::write(dir.join("a.h264"), &expected).unwrap();
+        fs::write(dir.join("b.rtpdump"), capture::rtpdump(&timed)).unwrap();
+        fs::write(dir.join("b.h264.fnv64"), "0123456789abcdef\n").unwrap();
+
+        let corpus = Corpus::load(&dir).unwrap();
+        assert_eq!(corpus.cases.len(), 2);
+        assert_eq!(corpus.cases[0].codec, Codec::Avc);
+        let report = corpus.run();
+        assert!(!report.passed());
+        assert!(matches!(report.cases[0].outcome, Outcome::Pass));
+        assert_eq!(report.cases[0].frames, 2);
+        assert!(matches!(
+            report.cases[1].outcome,
+            Outcome::HashDiffers {
+                expected: 0x0123_4567_89AB_CDEF,
+                ..
+            }
+        ));
+        corpus.cases[1].bless().unwrap();
+        let report = corpus.run();
+        assert!(report.passed(), "{}", report);
+        assert!(report.to_string().ends_with("2 of 2 cases passed"));
+
+        fs::write(dir.join("c.rtpr"), b"").unwrap();
+        assert!(matches!(
+            Corpus::load(&dir),
+            Err(ConformanceError::MissingGolden(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+//! RTP/RTCP demultiplexing for sessions that share one port (rtcp-mux,
+//! RFC 5761).
+//!
+//! [`demux`] tells RTCP from RTP by the second byte, where RTCP packet
+//! types 192 to 223 take the place of RTP payload types 64 to 95 with the
+//! marker bit set (see [`is_rtcp`]), and parses the datagram accordingly.
+//! RTCP is accepted in reduced size (RFC 5506), as WebRTC sends it.
+
+use crate::{
+    rtcp::{is_rtcp, CompoundRtcp, RtcpError},
+    rtp::{RtpError, RtpPacket},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Demuxed<'a> {
+    Rtp(RtpPacket<'a>),
+    Rtcp(CompoundRtcp<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DemuxError {
+    Rtp(RtpError),
+    Rtcp(RtcpError),
+}
+
+impl core::fmt::Display for DemuxError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DemuxError::Rtp(e) => write!(f, "invalid RTP packet: {}", e),
+            DemuxError::Rtcp(e) => write!(f, "invalid RTCP packet: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DemuxError {}
+
+impl From<RtpError> for DemuxError {
+    fn from(e: RtpError) -> Self {
+        DemuxError::Rtp(e)
+    }
+}
+
+impl From<RtcpError> for DemuxError {
+    fn from(e: RtcpError) -> Self {
+        DemuxError::Rtcp(e)
+    }
+}
+
+/// Classifies a datagram received on a multiplexed port and parses it as
+/// RTP or compound RTCP.
+pub fn demux(datagram: &[u8]) -> Result<Demuxed<'_>, DemuxError> {
+    if is_rtcp(datagram) {
+        Ok(Demuxed::Rtcp(CompoundRtcp::parse_reduced_size(datagram)?))
+    } else {
+        Ok(Demuxed::Rtp(RtpPacket::parse(datagram)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rtcp::{pli, RtcpPacket},
+        test_utils::RtpPacketBuilder,
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn dispatches_rtp_and_rtcp() {
+        // Payload type 96 with the marker set is 224, above the RTCP range.
+        let rtp = RtpPacketBuilder::new()
+            .payload_type(96)
+            .marker(true)
+            .payload(&[1, 2])
+            .build();
+        assert!(matches!(demux(&rtp), Ok(Demuxed::Rtp(p)) if p.payload == [1, 2]));
+
+        let feedback = pli(1, 2);
+        let Ok(Demuxed::Rtcp(compound)) = demux(&feedback) else {
+            panic!();
+        };
+        let packets: Vec<_> = compound.packets().collect();
+        assert!(matches!(packets[..], [RtcpPacket::Pli(_)]));
+
+        assert!(matches!(
+            demux(&feedback[..8]),
+            Err(DemuxError::Rtcp(RtcpError::InvalidLength { .. }))
+        ));
+        assert!(matches!(
+            demux(&[0x80, 96]),
+            Err(DemuxError::Rtp(RtpError::BufferTooShort { .. }))
+        ));
+    }
+}
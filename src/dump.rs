@@ -0,0 +1,473 @@
+//! Human-readable packet and stream dumps for bug reports.
+//!
+//! [`dump_packet`] prints the parsed RTP header, the header extension
+//! elements and the codec payload header, followed by a hex dump split into
+//! the same regions. [`dump_stream`] prints one line per packet with
+//! arrival time, sequence gaps, timestamp steps and the payload header, and
+//! a rule between frames.
+
+use crate::{
+    clock::MediaTime,
+    cn::{is_comfort_noise, ComfortNoise},
+    codecs::{
+        av1::{parse_av1_obu_elements, ObuHeader},
+        avc::{parse_avc_payload_header, AvcNalKind},
+        hevc::{parse_hevc_payload_header, HevcNalKind},
+        vp9::Vp9PayloadDesc,
+        Codec,
+    },
+    guess::guess_codec,
+    rtp::RtpPacket,
+};
+use alloc::{format, string::String, vec::Vec};
+use core::{fmt::Write, time::Duration};
+
+// Payload bytes shown in a packet's hex dump.
+const MAX_PAYLOAD_HEX: usize = 64;
+const HEX_ROW: usize = 16;
+
+/// Annotated dump of one serialized RTP packet. `codec` picks the payload
+/// format; `None` guesses it from the payload, except for comfort noise
+/// on payload type 13.
+pub fn dump_packet(packet: &[u8], codec: Option<Codec>) -> String {
+    let mut out = String::new();
+    let pkt = match RtpPacket::parse(packet) {
+        Ok(pkt) => pkt,
+        Err(e) => {
+            let _ = writeln!(out, "invalid RTP packet ({} bytes): {}", packet.len(), e);
+            hex_region(&mut out, "data", packet, 0, packet.len());
+            return out;
+        }
+    };
+    let h = &pkt.header;
+    let _ = writeln!(
+        out,
+        "RTP v{} pt={} seq={} ts={} ssrc={:#010x}{}{} ({} bytes)",
+        h.version,
+        h.payload_type,
+        h.sequence_number,
+        h.timestamp,
+        h.ssrc,
+        if h.marker { " marker" } else { "" },
+        if h.padding { " padding" } else { "" },
+        packet.len()
+    );
+    if !h.csrcs.is_empty() {
+        let csrcs: Vec<_> = h.csrcs.iter().map(|c| format!("{:#010x}", c)).collect();
+        let _ = writeln!(out, "  csrcs: {}", csrcs.join(" "));
+    }
+    let csrc_end = 12 + 4 * h.csrcs.len();
+    let mut payload_start = csrc_end;
+    if let Some(ext) = &h.extension_header {
+        let _ = writeln!(
+            out,
+            "  extension profile={:#06x} {} bytes",
+            ext.profile, ext.data_len
+        );
+        for element in ext.elements(packet) {
+            let _ = writeln!(out, "    id={} {}", element.id, hex(element.data));
+        }
+        payload_start = ext.data_offset + ext.data_len;
+    }
+    let (codec, guessed) = match codec {
+        Some(codec) => (codec, ""),
+        None => (guess_codec(pkt.payload), " (guessed)"),
+    };
+    if !guessed.is_empty() && is_comfort_noise(h.payload_type, None) {
+        let _ = writeln!(
+            out,
+            "  payload {} bytes, {}",
+            pkt.payload.len(),
+            describe_comfort_noise(pkt.payload)
+        );
+    } else {
+        let _ = writeln!(
+            out,
+            "  payload {} bytes, {}{}: {}",
+            pkt.payload.len(),
+            codec,
+            guessed,
+            describe_payload(codec, pkt.payload)
+        );
+        if codec == Codec::Avc || codec == Codec::Hevc {
+            for line in aggregated_units(codec, pkt.payload) {
+                let _ = writeln!(out, "    {}", line);
+            }
+        }
+    }
+
+    hex_region(&mut out, "header", packet, 0, 12);
+    hex_region(&mut out, "csrcs", packet, 12, csrc_end);
+    hex_region(&mut out, "extension", packet, csrc_end, payload_start);
+    let payload_end = pkt.payload_offset + pkt.payload.len();
+    let shown = pkt.payload.len().min(MAX_PAYLOAD_HEX);
+    hex_region(
+        &mut out,
+        "payload",
+        packet,
+        pkt.payload_offset,
+        pkt.payload_offset + shown,
+    );
+    if shown < pkt.payload.len() {
+        let _ = writeln!(
+            out,
+            "  {:<10} ... {} more bytes",
+            "",
+            pkt.payload.len() - shown
+        );
+    }
+    hex_region(&mut out, "padding", packet, payload_end, packet.len());
+    out
+}
+
+/// One line per packet, in the order given, with the arrival time, the
+/// sequence number step (gaps and late packets flagged), the timestamp
+/// step in milliseconds and the payload header. Packets that fail to parse
+/// are listed as such.
+pub fn dump_stream<'a>(
+    packets: impl IntoIterator<Item = (Duration, &'a [u8])>,
+    codec: Option<Codec>,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:>12}  {:<10}  {:>5}  {:<8}  {:>10}  {:>9}  M  {:>5}  payload",
+        "arrival", "ssrc", "seq", "", "timestamp", "step ms", "bytes"
+    );
+    let mut previous: Option<(u32, u16, u32)> = None;
+    for (arrival, bytes) in packets {
+        let _ = write!(out, "{:>12.6}  ", arrival.as_secs_f64());
+        let pkt = match RtpPacket::parse(bytes) {
+            Ok(pkt) => pkt,
+            Err(e) => {
+                let _ = writeln!(out, "invalid packet ({} bytes): {}", bytes.len(), e);
+                continue;
+            }
+        };
+        let h = &pkt.header;
+        let comfort_noise = codec.is_none() && is_comfort_noise(h.payload_type, None);
+        let codec = codec.unwrap_or_else(|| guess_codec(pkt.payload));
+        let (seq_note, step) = match previous {
+            Some((ssrc, seq, ts)) if ssrc == h.ssrc => {
+                let delta = h.sequence_number.wrapping_sub(seq) as i16;
+                let note = match delta {
+                    1 => String::new(),
+                    0 => "dup".into(),
+                    d if d < 0 => format!("late {}", -d),
+                    d => format!("gap {}", d - 1),
+                };
+                let time = MediaTime::new(codec.clock_rate());
+                let nanos = time.nanos_between(h.timestamp, ts);
+                if nanos != 0 {
+                    let _ = writeln!(out, "{:-<12}", "");
+                    let _ = write!(out, "{:>12.6}  ", arrival.as_secs_f64());
+                }
+                (note, Some(nanos as f64 / 1e6))
+            }
+            _ => (String::new(), None),
+        };
+        let step = step.map_or(String::new(), |ms| format!("{:+.3}", ms));
+        let _ = writeln!(
+            out,
+            "{:#010x}  {:>5}  {:<8}  {:>10}  {:>9}  {}  {:>5}  {}",
+            h.ssrc,
+            h.sequence_number,
+            seq_note,
+            h.timestamp,
+            step,
+            if h.marker { "M" } else { " " },
+            pkt.payload.len(),
+            if comfort_noise {
+                describe_comfort_noise(pkt.payload)
+            } else {
+                describe_payload(codec, pkt.payload)
+            }
+        );
+        let newest = previous.is_none_or(|(ssrc, seq, _)| {
+            ssrc != h.ssrc || h.sequence_number.wrapping_sub(seq) as i16 > 0
+        });
+        if newest {
+            previous = Some((h.ssrc, h.sequence_number, h.timestamp));
+        }
+    }
+    out
+}
+
+fn describe_comfort_noise(payload: &[u8]) -> String {
+    match ComfortNoise::parse(payload) {
+        Ok(cn) => format!("comfort noise {} dBov", cn.dbov()),
+        Err(e) => format!("comfort noise, {}", e),
+    }
+}
+
+// One-line breakdown of the codec payload header.
+fn describe_payload(codec: Codec, payload: &[u8]) -> String {
+    match codec {
+        Codec::Avc => match parse_avc_payload_header(payload) {
+            Ok((kind, _)) => match kind {
+                AvcNalKind::Single(t) => format!("NAL {} ({})", t, avc_nal_name(t)),
+                AvcNalKind::StapA => "STAP-A".into(),
+                AvcNalKind::StapB => "STAP-B".into(),
+                AvcNalKind::Mtap16 => "MTAP16".into(),
+                AvcNalKind::Mtap24 => "MTAP24".into(),
+                AvcNalKind::FuA {
+                    start,
+                    end,
+                    nal_type,
+                }
+                | AvcNalKind::FuB {
+                    start,
+                    end,
+                    nal_type,
+                } => format!(
+                    "FU{}{} NAL {} ({})",
+                    if start { " start" } else { "" },
+                    if end { " end" } else { "" },
+                    nal_type,
+                    avc_nal_name(nal_type)
+                ),
+                AvcNalKind::Unknown(t) => format!("unknown NAL type {}", t),
+            },
+            Err(e) => format!("invalid: {}", e),
+        },
+        Codec::Hevc => match parse_hevc_payload_header(payload) {
+            Ok((kind, _)) => match kind {
+                HevcNalKind::Single { nal_type } => {
+                    format!("NAL {} ({})", nal_type, hevc_nal_name(nal_type))
+                }
+                HevcNalKind::Ap => "AP".into(),
+                HevcNalKind::Fu {
+                    start,
+                    end,
+                    nal_type,
+                } => format!(
+                    "FU{}{} NAL {} ({})",
+                    if start { " start" } else { "" },
+                    if end { " end" } else { "" },
+                    nal_type,
+                    hevc_nal_name(nal_type)
+                ),
+                HevcNalKind::Pacsi => "PACSI".into(),
+                HevcNalKind::Unknown(t) => format!("unknown NAL type {}", t),
+            },
+            Err(e) => format!("invalid: {}", e),
+        },
+        Codec::Vp9 => match Vp9PayloadDesc::parse(payload) {
+            Ok((desc, len)) => {
+                let mut s = String::new();
+                for (set, flag) in [
+                    (desc.b_bit, "start"),
+                    (desc.e_bit, "end"),
+                    (!desc.p_bit, "keyframe"),
+                    (desc.f_bit, "flexible"),
+                    (desc.z_bit, "not-reference"),
+                    (desc.v_bit, "ss"),
+                ] {
+                    if set {
+                        let _ = write!(s, "{} ", flag);
+                    }
+                }
+                if let Some(id) = desc.picture_id {
+                    let _ = write!(s, "picture_id={} ", id);
+                }
+                if let Some(l) = &desc.layer {
+                    let _ = write!(s, "sid={} tid={} ", l.spatial_id, l.temporal_id);
+                }
+                if let Some(idx) = desc.tl0_pic_idx {
+                    let _ = write!(s, "tl0picidx={} ", idx);
+                }
+                if !desc.p_diffs.is_empty() {
+                    let _ = write!(s, "p_diff={:?} ", desc.p_diffs);
+                }
+                let _ = write!(s, "({} byte descriptor)", len);
+                s
+            }
+            Err(e) => format!("invalid: {}", e),
+        },
+        Codec::Av1 => match parse_av1_obu_elements(payload) {
+            Ok((hdr, elements)) => {
+                let mut s = String::new();
+                for (set, flag) in [
+                    (hdr.z_bit, "continues"),
+                    (hdr.y_bit, "continued"),
+                    (hdr.n_bit, "new-sequence"),
+                ] {
+                    if set {
+                        let _ = write!(s, "{} ", flag);
+                    }
+                }
+                let types: Vec<_> = elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| match e.first() {
+                        // A continued fragment has no OBU header.
+                        Some(_) if i == 0 && hdr.z_bit => String::from("(fragment)"),
+                        Some(&b) => av1_obu_name(ObuHeader::parse(b).obu_type).into(),
+                        None => String::from("(empty)"),
+                    })
+                    .collect();
+                let _ = write!(s, "OBUs [{}]", types.join(", "));
+                s
+            }
+            Err(e) => format!("invalid: {}", e),
+        },
+        Codec::Unknown | Codec::Custom(_) => String::from("opaque"),
+    }
+}
+
+// The NAL units of an STAP-A or AP, one line each.
+fn aggregated_units(codec: Codec, payload: &[u8]) -> Vec<String> {
+    let (skip, name): (usize, fn(u8) -> &'static str) = match codec {
+        Codec::Avc if payload.first().is_some_and(|b| b & 0x1F == 24) => (1, avc_nal_name),
+        Codec::Hevc if payload.first().is_some_and(|b| (b >> 1) & 0x3F == 48) => (2, hevc_nal_name),
+        _ => return Vec::new(),
+    };
+    let mut lines = Vec::new();
+    let mut rest = &payload[skip.min(payload.len())..];
+    while let [hi, lo, tail @ ..] = rest {
+        let len = u16::from_be_bytes([*hi, *lo]) as usize;
+        let Some(nal) = tail.get(..len).filter(|n| !n.is_empty()) else {
+            lines.push(format!("truncated unit of {} bytes", len));
+            break;
+        };
+        let t = if codec == Codec::Avc {
+            nal[0] & 0x1F
+        } else {
+            (nal[0] >> 1) & 0x3F
+        };
+        lines.push(format!("unit NAL {} ({}) {} bytes", t, name(t), len));
+        rest = &tail[len..];
+    }
+    lines
+}
+
+fn avc_nal_name(t: u8) -> &'static str {
+    match t {
+        1 => "non-IDR slice",
+        2..=4 => "slice data partition",
+        5 => "IDR slice",
+        6 => "SEI",
+        7 => "SPS",
+        8 => "PPS",
+        9 => "access unit delimiter",
+        10 => "end of sequence",
+        11 => "end of stream",
+        12 => "filler",
+        14 => "prefix",
+        15 => "subset SPS",
+        20 => "slice extension",
+        _ => "other",
+    }
+}
+
+fn hevc_nal_name(t: u8) -> &'static str {
+    match t {
+        0..=9 => "slice",
+        16..=21 => "IRAP slice",
+        32 => "VPS",
+        33 => "SPS",
+        34 => "PPS",
+        35 => "access unit delimiter",
+        39 | 40 => "SEI",
+        _ => "other",
+    }
+}
+
+fn av1_obu_name(t: u8) -> &'static str {
+    match t {
+        1 => "sequence header",
+        2 => "temporal delimiter",
+        3 => "frame header",
+        4 => "tile group",
+        5 => "metadata",
+        6 => "frame",
+        7 => "redundant frame header",
+        8 => "tile list",
+        15 => "padding",
+        _ => "reserved",
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    let bytes: Vec<_> = data.iter().map(|b| format!("{:02x}", b)).collect();
+    bytes.join(" ")
+}
+
+// Hex rows of `packet[start..end]`, labelled and with their offsets.
+fn hex_region(out: &mut String, label: &str, packet: &[u8], start: usize, end: usize) {
+    let end = end.min(packet.len());
+    if start >= end {
+        return;
+    }
+    for (i, row) in packet[start..end].chunks(HEX_ROW).enumerate() {
+        let label = if i == 0 { label } else { "" };
+        let _ = writeln!(
+            out,
+            "  {:<10} {:04x}  {}",
+            label,
+            start + i * HEX_ROW,
+            hex(row)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{avc, fixtures::AVC_IDR, RtpPacketBuilder, StreamBuilder};
+
+    #[test]
+    fn dumps_header_extensions_and_payload_header() {
+        let packet = RtpPacketBuilder::new()
+            .ssrc(7)
+            .sequence_number(1000)
+            .timestamp(90_000)
+            .marker(true)
+            .extension(0xBEDE, &[0x10, b'0', 0, 0])
+            .payload(&avc::fu_a(AVC_IDR, 4)[0])
+            .build();
+        let text = dump_packet(&packet, Some(Codec::Avc));
+        assert!(
+            text.starts_with("RTP v2 pt=96 seq=1000 ts=90000 ssrc=0x00000007 marker"),
+            "{}",
+            text
+        );
+        assert!(text.contains("    id=1 30\n"), "{}", text);
+        let cn = RtpPacketBuilder::new()
+            .payload_type(13)
+            .payload(&[40])
+            .build();
+        assert!(dump_packet(&cn, None).contains("payload 1 bytes, comfort noise -40 dBov\n"));
+        assert!(
+            text.contains("H264: FU start NAL 5 (IDR slice)\n"),
+            "{}",
+            text
+        );
+        assert!(text.contains("  header     0000  90 e0 03 e8"), "{}", text);
+        assert!(
+            text.contains("  extension  000c  be de 00 01 10 30 00 00\n"),
+            "{}",
+            text
+        );
+        assert!(text.contains("  payload    0014  7c 85"), "{}", text);
+        assert!(dump_packet(&[0x80], None).starts_with("invalid RTP packet (1 bytes)"));
+    }
+
+    #[test]
+    fn stream_dump_flags_gaps_and_frames() {
+        let packets = StreamBuilder::new(5)
+            .frame(&avc::fu_a(AVC_IDR, 3))
+            .frame(&avc::fu_a(AVC_IDR, 3))
+            .build();
+        let arrivals = packets.iter().enumerate().filter(|&(i, _)| i != 1);
+        let text = dump_stream(
+            arrivals.map(|(i, p)| (Duration::from_millis(i as u64), p.as_slice())),
+            Some(Codec::Avc),
+        );
+        let lines: Vec<_> = text.lines().collect();
+        assert!(lines[0].contains("arrival"));
+        assert!(lines[2].contains("gap 1"), "{}", text);
+        assert!(text.contains("------------\n"), "{}", text);
+        assert!(text.contains("+33.333"), "{}", text);
+    }
+}
@@ -0,0 +1,255 @@
+//! JSON export of analysis results (`json` feature).
+//!
+//! [`JsonExporter`] runs packets through a per-SSRC analyzer, reassembler
+//! and receiver statistics and writes JSON Lines: one [`Record`] per line,
+//! tagged by `"type"` (`packet`, `frame` or `stream`). Stream summaries
+//! are written by [`JsonExporter::finish`]. Fields are only ever added to
+//! the schema; [`SCHEMA_VERSION`] changes if one is renamed or removed.
+
+use crate::{
+    analyze::{FrameAnalyzer, FrameBoundary},
+    codecs::Codec,
+    reassemble::{DropReason, FrameReassembler, PushResult, ReassemblerConfig},
+    rtp::RtpPacket,
+    stats::ReceiverStats,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    time::Duration,
+    vec::Vec,
+};
+
+/// Version of the record schema, in every stream summary.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// One line of the export.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Packet(PacketRecord),
+    Frame(FrameRecord),
+    Stream(StreamSummary),
+}
+
+/// Analysis of one RTP packet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PacketRecord {
+    /// Arrival time in microseconds, on the clock the caller supplied.
+    pub arrival_us: u64,
+    pub ssrc: u32,
+    pub sequence_number: u16,
+    pub timestamp: u32,
+    pub payload_type: u8,
+    pub marker: bool,
+    pub payload_len: usize,
+    pub boundary: FrameBoundary,
+}
+
+/// A frame the reassembler completed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FrameRecord {
+    /// Arrival time of the packet that completed it, in microseconds.
+    pub arrival_us: u64,
+    pub ssrc: u32,
+    pub timestamp: u32,
+    pub codec: Option<Codec>,
+    pub size: usize,
+    pub keyframe: bool,
+}
+
+/// Totals of one SSRC at the end of the export.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StreamSummary {
+    pub schema_version: u32,
+    pub ssrc: u32,
+    pub codec: Option<Codec>,
+    pub packets: u64,
+    pub packets_lost: i64,
+    /// Interarrival jitter in RTP timestamp units.
+    pub jitter: u32,
+    pub clock_drift_ppm: Option<f64>,
+    pub frames: u64,
+    pub keyframes: u64,
+    pub frames_dropped: BTreeMap<DropReason, u64>,
+    pub bytes: u64,
+}
+
+#[derive(Debug)]
+struct StreamState {
+    analyzer: FrameAnalyzer,
+    reassembler: FrameReassembler,
+    stats: ReceiverStats,
+    frames: u64,
+    keyframes: u64,
+    frames_dropped: BTreeMap<DropReason, u64>,
+    bytes: u64,
+}
+
+/// Writes the analysis of a packet sequence as JSON Lines.
+#[derive(Debug)]
+pub struct JsonExporter<W> {
+    out: W,
+    codec: Option<Codec>,
+    packets: bool,
+    streams: BTreeMap<u32, StreamState>,
+}
+
+impl<W: Write> JsonExporter<W> {
+    /// `codec` is the payload format of every stream; `None` guesses it per
+    /// stream.
+    pub fn new(out: W, codec: Option<Codec>) -> Self {
+        Self {
+            out,
+            codec,
+            packets: true,
+            streams: BTreeMap::new(),
+        }
+    }
+
+    /// Whether packet records are written (the default); frames and stream
+    /// summaries always are.
+    pub fn with_packets(mut self, packets: bool) -> Self {
+        self.packets = packets;
+        self
+    }
+
+    /// Analyzes a serialized RTP packet that arrived at `arrival`; packets
+    /// that do not parse are skipped.
+    pub fn push(&mut self, packet: &[u8], arrival: Duration) -> io::Result<()> {
+        let Ok(pkt) = RtpPacket::parse(packet) else {
+            return Ok(());
+        };
+        let codec = self.codec;
+        let stream = self.streams.entry(pkt.header.ssrc).or_insert_with(|| {
+            let mut analyzer = FrameAnalyzer::new();
+            if let Some(codec) = codec {
+                analyzer.set_codec(codec);
+            }
+            let reassembler = FrameReassembler::from_config(ReassemblerConfig {
+                codec,
+                ..ReassemblerConfig::default()
+            })
+            .expect("default config is valid");
+            let clock_rate = codec.unwrap_or(Codec::Unknown).clock_rate();
+            StreamState {
+                analyzer,
+                reassembler,
+                stats: ReceiverStats::new(clock_rate),
+                frames: 0,
+                keyframes: 0,
+                frames_dropped: BTreeMap::new(),
+                bytes: 0,
+            }
+        });
+        let arrival_us = arrival.as_micros() as u64;
+        let boundary = stream.analyzer.analyze(&pkt);
+        stream.stats.on_packet(&pkt.header, arrival);
+        stream.bytes += pkt.payload.len() as u64;
+        let mut records = Vec::new();
+        if self.packets {
+            records.push(Record::Packet(PacketRecord {
+                arrival_us,
+                ssrc: pkt.header.ssrc,
+                sequence_number: pkt.header.sequence_number,
+                timestamp: pkt.header.timestamp,
+                payload_type: pkt.header.payload_type,
+                marker: pkt.header.marker,
+                payload_len: pkt.payload.len(),
+                boundary,
+            }));
+        }
+        match stream.reassembler.push_packet_result(&pkt) {
+            PushResult::FrameCompleted(frame) => {
+                let codec = stream.reassembler.codec();
+                let keyframe = stream.reassembler.frame_is_keyframe(&frame);
+                stream.frames += 1;
+                stream.keyframes += keyframe as u64;
+                records.push(Record::Frame(FrameRecord {
+                    arrival_us,
+                    ssrc: pkt.header.ssrc,
+                    timestamp: pkt.header.timestamp,
+                    codec,
+                    size: frame.len(),
+                    keyframe,
+                }));
+            }
+            PushResult::FrameDropped(reason) => {
+                *stream.frames_dropped.entry(reason).or_default() += 1;
+            }
+            _ => {}
+        }
+        records.iter().try_for_each(|r| self.write(r))
+    }
+
+    /// Writes a summary per stream and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let streams = core::mem::take(&mut self.streams);
+        for (ssrc, stream) in streams {
+            let summary = StreamSummary {
+                schema_version: SCHEMA_VERSION,
+                ssrc,
+                codec: stream.reassembler.codec(),
+                packets: stream.stats.received(),
+                packets_lost: stream.stats.cumulative_lost(),
+                jitter: stream.stats.jitter(),
+                clock_drift_ppm: stream.stats.clock_drift_ppm(),
+                frames: stream.frames,
+                keyframes: stream.keyframes,
+                frames_dropped: stream.frames_dropped,
+                bytes: stream.bytes,
+            };
+            self.write(&Record::Stream(summary))?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, record)?;
+        self.out.write_all(b"\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{avc, fixtures::AVC_IDR, StreamBuilder};
+
+    #[test]
+    fn writes_packet_frame_and_stream_records() {
+        let packets = StreamBuilder::new(9)
+            .frame(&avc::fu_a(AVC_IDR, 3))
+            .frame(&avc::fu_a(AVC_IDR, 3))
+            .build();
+        let mut exporter = JsonExporter::new(Vec::new(), Some(Codec::Avc));
+        for (i, p) in packets.iter().enumerate() {
+            // The second frame loses its middle packet.
+            if i != packets.len() - 2 {
+                exporter.push(p, Duration::from_millis(i as u64)).unwrap();
+            }
+        }
+        let out = String::from_utf8(exporter.finish().unwrap()).unwrap();
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), packets.len() - 1 + 2);
+        assert_eq!(lines[0]["type"], "packet");
+        assert_eq!(lines[0]["boundary"], "Start");
+        let frame = lines.iter().find(|l| l["type"] == "frame").unwrap();
+        assert_eq!(
+            (&frame["ssrc"], &frame["keyframe"]),
+            (&9.into(), &true.into())
+        );
+        let stream = lines.last().unwrap();
+        assert_eq!(stream["type"], "stream");
+        assert_eq!(stream["schema_version"], SCHEMA_VERSION);
+        assert_eq!(
+            (&stream["frames"], &stream["packets_lost"]),
+            (&1.into(), &1.into())
+        );
+        assert_eq!(stream["frames_dropped"]["SequenceGap"], 1);
+    }
+}
@@ -0,0 +1,55 @@
+//! Absolute capture time extension
+//! (`http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time`).
+
+use super::{ensure_len, ExtensionError};
+use crate::clock::ntp_to_nanos;
+
+/// When the first sample of a frame was captured, in NTP format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AbsCaptureTime {
+    /// Capture time on the capture system's clock, 32.32 fixed point NTP.
+    pub capture_timestamp: u64,
+    /// Sender clock minus capture system clock as estimated by the sender
+    /// (e.g. a mixer), signed 32.32 fixed point seconds.
+    pub estimated_capture_clock_offset: Option<i64>,
+}
+
+impl AbsCaptureTime {
+    pub fn parse(data: &[u8]) -> Result<Self, ExtensionError> {
+        ensure_len(data, "absolute capture time", 8)?;
+        let capture_timestamp = u64::from_be_bytes(data[..8].try_into().unwrap());
+        let estimated_capture_clock_offset = data
+            .get(8..16)
+            .map(|b| i64::from_be_bytes(b.try_into().unwrap()));
+        Ok(Self {
+            capture_timestamp,
+            estimated_capture_clock_offset,
+        })
+    }
+
+    /// Capture time on the sender's clock, in nanoseconds since the NTP
+    /// epoch.
+    pub fn sender_capture_nanos(&self) -> i128 {
+        let offset = self.estimated_capture_clock_offset.unwrap_or(0) as i128;
+        ntp_to_nanos(self.capture_timestamp) as i128 + ((offset * 1_000_000_000) >> 32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_with_and_without_offset() {
+        // 10.5 s, then an offset of -0.25 s.
+        let ts = (10u64 << 32 | 1 << 31).to_be_bytes();
+        let offset = (-(1i64 << 30)).to_be_bytes();
+        let short = AbsCaptureTime::parse(&ts).unwrap();
+        assert_eq!(short.estimated_capture_clock_offset, None);
+        assert_eq!(short.sender_capture_nanos(), 10_500_000_000);
+        let long = AbsCaptureTime::parse(&[ts, offset].concat()).unwrap();
+        assert_eq!(long.sender_capture_nanos(), 10_250_000_000);
+        assert!(AbsCaptureTime::parse(&ts[..4]).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+//! Absolute send time extension
+//! (`http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time`).
+
+use super::{ensure_len, ExtensionError};
+
+/// When the sender sent the packet: 24-bit 6.18 fixed point seconds, wrapping
+/// every 64 s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AbsSendTime(pub u32);
+
+impl AbsSendTime {
+    /// Units per second.
+    pub const RATE: u64 = 1 << 18;
+    /// Range of the 24-bit value.
+    pub const WRAP: u32 = 1 << 24;
+
+    pub fn parse(data: &[u8]) -> Result<Self, ExtensionError> {
+        ensure_len(data, "absolute send time", 3)?;
+        Ok(Self(u32::from_be_bytes([0, data[0], data[1], data[2]])))
+    }
+
+    /// Units from `earlier` to `self`, assuming they are less than 32 s
+    /// apart.
+    pub fn delta(self, earlier: AbsSendTime) -> i32 {
+        // Shift the 24-bit difference to the top so it sign-extends.
+        ((self.0.wrapping_sub(earlier.0) << 8) as i32) >> 8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_wraps() {
+        // 1.5 s.
+        let t = AbsSendTime::parse(&[0x06, 0x00, 0x00]).unwrap();
+        assert_eq!(t, AbsSendTime(3 << 17));
+        let wrapped = AbsSendTime(10);
+        assert_eq!(wrapped.delta(AbsSendTime(AbsSendTime::WRAP - 6)), 16);
+        assert_eq!(AbsSendTime(AbsSendTime::WRAP - 6).delta(wrapped), -16);
+        assert!(AbsSendTime::parse(&[1, 2]).is_err());
+    }
+}
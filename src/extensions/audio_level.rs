@@ -0,0 +1,118 @@
+//! Audio level extensions: client-to-mixer (RFC 6464) and mixer-to-client
+//! (RFC 6465).
+
+use super::{ensure_len, ExtensionError};
+
+/// Level of the audio in one packet, as sent by a client
+/// (`urn:ietf:params:rtp-hdrext:ssrc-audio-level`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AudioLevel {
+    /// V flag: the sender's voice activity detector found speech. Only
+    /// meaningful when negotiated with `vad=on`.
+    pub voice_activity: bool,
+    /// Level in -dBov, from 0 (loudest) to 127 (silence).
+    pub level: u8,
+}
+
+impl AudioLevel {
+    pub fn parse(data: &[u8]) -> Result<Self, ExtensionError> {
+        ensure_len(data, "audio level", 1)?;
+        Ok(Self {
+            voice_activity: data[0] & 0x80 != 0,
+            level: data[0] & 0x7F,
+        })
+    }
+
+    /// Level in dBov, from -127 to 0.
+    pub fn dbov(self) -> i8 {
+        -(self.level as i8)
+    }
+
+    pub fn to_byte(self) -> u8 {
+        (self.voice_activity as u8) << 7 | self.level.min(127)
+    }
+}
+
+/// Levels of the contributing sources of a mixed packet
+/// (`urn:ietf:params:rtp-hdrext:csrc-audio-level`), one per CSRC in header
+/// order. Each is in -dBov, 127 being silence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsrcAudioLevels<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> CsrcAudioLevels<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ExtensionError> {
+        ensure_len(data, "CSRC audio level", 1)?;
+        Ok(Self { data })
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn levels(&self) -> impl Iterator<Item = u8> + 'a {
+        self.data.iter().map(|b| b & 0x7F)
+    }
+
+    /// Pairs each level with its CSRC; levels or CSRCs without a
+    /// counterpart are left out.
+    pub fn with_csrcs<'c>(&self, csrcs: &'c [u32]) -> impl Iterator<Item = (u32, u8)> + 'c
+    where
+        'a: 'c,
+    {
+        csrcs.iter().copied().zip(self.levels())
+    }
+
+    /// Contributing source with the highest level, e.g. the active talker.
+    pub fn loudest(&self, csrcs: &[u32]) -> Option<(u32, u8)> {
+        self.with_csrcs(csrcs).min_by_key(|&(_, level)| level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rtp::RtpPacket, test_utils::RtpPacketBuilder};
+
+    #[test]
+    fn parses_client_to_mixer_level() {
+        // One-byte form, id 1, V set, level 42.
+        let buf = RtpPacketBuilder::new()
+            .extension(0xBEDE, &[0x10, 0x80 | 42])
+            .build();
+        let ext = RtpPacket::parse(&buf)
+            .unwrap()
+            .header
+            .extension_header
+            .unwrap();
+        let element = ext.elements(&buf).next().unwrap();
+        let level = AudioLevel::parse(element.data).unwrap();
+        assert_eq!(
+            level,
+            AudioLevel {
+                voice_activity: true,
+                level: 42
+            }
+        );
+        assert_eq!((level.dbov(), level.to_byte()), (-42, 0xAA));
+        assert!(AudioLevel::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn pairs_mixer_levels_with_csrcs() {
+        let levels = CsrcAudioLevels::parse(&[127, 30, 0x80 | 10]).unwrap();
+        assert_eq!(
+            levels.levels().collect::<alloc::vec::Vec<_>>(),
+            [127, 30, 10]
+        );
+        // The third level has no CSRC.
+        assert_eq!(levels.loudest(&[7, 8]), Some((8, 30)));
+        assert_eq!(levels.with_csrcs(&[7, 8, 9, 10]).count(), 3);
+    }
+}
@@ -0,0 +1,223 @@
+use super::{
+    AbsCaptureTime, AbsSendTime, AudioLevel, CsrcAudioLevels, ExtensionError, StreamId,
+    TransportSequenceNumber, VideoLayersAllocation, VideoOrientation,
+};
+use crate::rtp::{ExtensionElement, RtpExtension};
+use alloc::collections::BTreeMap;
+
+/// Header extensions with a typed decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExtensionKind {
+    AbsSendTime,
+    AbsCaptureTime,
+    TransportSequenceNumber,
+    AudioLevel,
+    CsrcAudioLevel,
+    VideoOrientation,
+    VideoLayersAllocation,
+    Mid,
+    RtpStreamId,
+    RepairedRtpStreamId,
+}
+
+impl ExtensionKind {
+    pub const ALL: [ExtensionKind; 10] = [
+        ExtensionKind::AbsSendTime,
+        ExtensionKind::AbsCaptureTime,
+        ExtensionKind::TransportSequenceNumber,
+        ExtensionKind::AudioLevel,
+        ExtensionKind::CsrcAudioLevel,
+        ExtensionKind::VideoOrientation,
+        ExtensionKind::VideoLayersAllocation,
+        ExtensionKind::Mid,
+        ExtensionKind::RtpStreamId,
+        ExtensionKind::RepairedRtpStreamId,
+    ];
+
+    /// URI identifying the extension in `a=extmap`.
+    pub fn uri(self) -> &'static str {
+        match self {
+            ExtensionKind::AbsSendTime => {
+                "http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time"
+            }
+            ExtensionKind::AbsCaptureTime => {
+                "http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time"
+            }
+            ExtensionKind::TransportSequenceNumber => {
+                "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01"
+            }
+            ExtensionKind::AudioLevel => "urn:ietf:params:rtp-hdrext:ssrc-audio-level",
+            ExtensionKind::CsrcAudioLevel => "urn:ietf:params:rtp-hdrext:csrc-audio-level",
+            ExtensionKind::VideoOrientation => "urn:3gpp:video-orientation",
+            ExtensionKind::VideoLayersAllocation => {
+                "http://www.webrtc.org/experiments/rtp-hdrext/video-layers-allocation00"
+            }
+            ExtensionKind::Mid => "urn:ietf:params:rtp-hdrext:sdes:mid",
+            ExtensionKind::RtpStreamId => "urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id",
+            ExtensionKind::RepairedRtpStreamId => {
+                "urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id"
+            }
+        }
+    }
+
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.uri() == uri)
+    }
+
+    pub fn decode(self, data: &[u8]) -> Result<ExtensionValue<'_>, ExtensionError> {
+        Ok(match self {
+            ExtensionKind::AbsSendTime => ExtensionValue::AbsSendTime(AbsSendTime::parse(data)?),
+            ExtensionKind::AbsCaptureTime => {
+                ExtensionValue::AbsCaptureTime(AbsCaptureTime::parse(data)?)
+            }
+            ExtensionKind::TransportSequenceNumber => {
+                ExtensionValue::TransportSequenceNumber(TransportSequenceNumber::parse(data)?)
+            }
+            ExtensionKind::AudioLevel => ExtensionValue::AudioLevel(AudioLevel::parse(data)?),
+            ExtensionKind::CsrcAudioLevel => {
+                ExtensionValue::CsrcAudioLevels(CsrcAudioLevels::parse(data)?)
+            }
+            ExtensionKind::VideoOrientation => {
+                ExtensionValue::VideoOrientation(VideoOrientation::parse(data)?)
+            }
+            ExtensionKind::VideoLayersAllocation => {
+                ExtensionValue::VideoLayersAllocation(VideoLayersAllocation::parse(data)?)
+            }
+            ExtensionKind::Mid => ExtensionValue::Mid(StreamId::parse(data)?),
+            ExtensionKind::RtpStreamId => ExtensionValue::RtpStreamId(StreamId::parse(data)?),
+            ExtensionKind::RepairedRtpStreamId => {
+                ExtensionValue::RepairedRtpStreamId(StreamId::parse(data)?)
+            }
+        })
+    }
+}
+
+/// Decoded header extension element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionValue<'a> {
+    AbsSendTime(AbsSendTime),
+    AbsCaptureTime(AbsCaptureTime),
+    TransportSequenceNumber(TransportSequenceNumber),
+    AudioLevel(AudioLevel),
+    CsrcAudioLevels(CsrcAudioLevels<'a>),
+    VideoOrientation(VideoOrientation),
+    VideoLayersAllocation(VideoLayersAllocation),
+    Mid(StreamId<'a>),
+    RtpStreamId(StreamId<'a>),
+    RepairedRtpStreamId(StreamId<'a>),
+}
+
+/// The extmap ids of a session, mapped to the extensions they carry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionMap {
+    ids: BTreeMap<u8, ExtensionKind>,
+}
+
+impl ExtensionMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps `id` to the extension named by `uri`, as in
+    /// `a=extmap:<id> <uri>`. Returns the extension, or `None` and leaves
+    /// `id` unmapped if there is no decoder for `uri`.
+    pub fn insert(&mut self, id: u8, uri: &str) -> Option<ExtensionKind> {
+        let kind = ExtensionKind::from_uri(uri);
+        match kind {
+            Some(kind) => self.ids.insert(id, kind),
+            None => self.ids.remove(&id),
+        };
+        kind
+    }
+
+    pub fn kind(&self, id: u8) -> Option<ExtensionKind> {
+        self.ids.get(&id).copied()
+    }
+
+    pub fn id(&self, kind: ExtensionKind) -> Option<u8> {
+        self.ids.iter().find(|(_, &k)| k == kind).map(|(&id, _)| id)
+    }
+
+    /// Decodes an element; `None` if its id is not mapped.
+    pub fn decode<'a>(
+        &self,
+        element: ExtensionElement<'a>,
+    ) -> Option<Result<ExtensionValue<'a>, ExtensionError>> {
+        Some(self.kind(element.id)?.decode(element.data))
+    }
+
+    /// Decodes the elements of a packet's extension block whose ids are
+    /// mapped; `packet` is the buffer `ext` was parsed from.
+    pub fn decode_all<'a>(
+        &'a self,
+        ext: &RtpExtension,
+        packet: &'a [u8],
+    ) -> impl Iterator<Item = Result<ExtensionValue<'a>, ExtensionError>> + 'a {
+        ext.elements(packet)
+            .filter_map(|element| self.decode(element))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{extensions::Camera, rtp::RtpPacket, test_utils::RtpPacketBuilder};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn decodes_mapped_elements_by_uri() {
+        let mut map = ExtensionMap::new();
+        for (id, kind) in [
+            (1, ExtensionKind::AudioLevel),
+            (3, ExtensionKind::TransportSequenceNumber),
+        ] {
+            assert_eq!(map.insert(id, kind.uri()), Some(kind));
+        }
+        assert_eq!(
+            map.insert(4, "urn:3gpp:video-orientation"),
+            Some(ExtensionKind::VideoOrientation)
+        );
+        assert_eq!(map.insert(5, "urn:example:unknown"), None);
+        assert_eq!(map.id(ExtensionKind::TransportSequenceNumber), Some(3));
+
+        // Audio level 30, sequence number 0x1234, back camera rotated by
+        // 270 degrees, an unmapped id 5 and a truncated id 3.
+        let buf = RtpPacketBuilder::new()
+            .extension(
+                0xBEDE,
+                &[0x10, 30, 0x31, 0x12, 0x34, 0x40, 0x0B, 0x50, 9, 0x30, 7],
+            )
+            .build();
+        let ext = RtpPacket::parse(&buf)
+            .unwrap()
+            .header
+            .extension_header
+            .unwrap();
+        let values: Vec<_> = map.decode_all(&ext, &buf).collect();
+        let orientation = VideoOrientation {
+            camera: Camera::Back,
+            flip: false,
+            rotation: 270,
+        };
+        assert_eq!(
+            values,
+            [
+                Ok(ExtensionValue::AudioLevel(AudioLevel {
+                    voice_activity: false,
+                    level: 30
+                })),
+                Ok(ExtensionValue::TransportSequenceNumber(
+                    TransportSequenceNumber(0x1234)
+                )),
+                Ok(ExtensionValue::VideoOrientation(orientation)),
+                Err(ExtensionError::TooShort {
+                    extension: "transport-wide sequence number",
+                    len: 1,
+                    needed: 2
+                }),
+            ]
+        );
+        assert_eq!(orientation.to_byte(), 0x0B);
+    }
+}
@@ -0,0 +1,74 @@
+//! Typed values of RTP header extensions, decoded from the data of an
+//! [`ExtensionElement`](crate::rtp::ExtensionElement). Which extension an
+//! element carries is negotiated per session (`a=extmap`), so callers pick
+//! the decoder by element id, or register the session's ids in an
+//! [`ExtensionMap`].
+
+pub mod abs_capture_time;
+pub mod abs_send_time;
+pub mod audio_level;
+mod map;
+pub mod sdes;
+pub mod transport_cc;
+pub mod video_orientation;
+pub mod vla;
+
+pub use abs_capture_time::AbsCaptureTime;
+pub use abs_send_time::AbsSendTime;
+pub use audio_level::{AudioLevel, CsrcAudioLevels};
+pub use map::{ExtensionKind, ExtensionMap, ExtensionValue};
+pub(crate) use sdes::StreamTagger;
+pub use sdes::{StreamId, StreamTag};
+pub use transport_cc::TransportSequenceNumber;
+pub use video_orientation::{Camera, VideoOrientation};
+pub use vla::{
+    LayerResolution, RtpStreamAllocation, SpatialLayerAllocation, VideoLayersAllocation,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionError {
+    /// The element of `extension` is `len` bytes; at least `needed` are required.
+    TooShort {
+        extension: &'static str,
+        len: usize,
+        needed: usize,
+    },
+    /// The element of `extension` holds an impossible value at `offset`.
+    Invalid {
+        extension: &'static str,
+        offset: usize,
+    },
+}
+
+impl core::fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ExtensionError::TooShort {
+                extension,
+                len,
+                needed,
+            } => write!(
+                f,
+                "{} extension is {} bytes, needs {}",
+                extension, len, needed
+            ),
+            ExtensionError::Invalid { extension, offset } => {
+                write!(f, "invalid {} extension at offset {}", extension, offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExtensionError {}
+
+fn ensure_len(data: &[u8], extension: &'static str, needed: usize) -> Result<(), ExtensionError> {
+    if data.len() < needed {
+        return Err(ExtensionError::TooShort {
+            extension,
+            len: data.len(),
+            needed,
+        });
+    }
+    Ok(())
+}
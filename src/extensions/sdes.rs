@@ -0,0 +1,132 @@
+//! SDES item extensions identifying streams: the media id
+//! (`urn:ietf:params:rtp-hdrext:sdes:mid`, RFC 8843), and the RTP stream id
+//! of a simulcast encoding and of its repair stream
+//! (`urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id` and
+//! `urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id`, RFC 8852).
+
+use super::{ensure_len, ExtensionError};
+use crate::rtp::RtpPacket;
+use alloc::string::{String, ToString};
+
+/// MID or RID text of an SDES item extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamId<'a>(pub &'a str);
+
+impl<'a> StreamId<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ExtensionError> {
+        ensure_len(data, "SDES item", 1)?;
+        core::str::from_utf8(data)
+            .map(Self)
+            .map_err(|e| ExtensionError::Invalid {
+                extension: "SDES item",
+                offset: e.valid_up_to(),
+            })
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+}
+
+/// MID and RID a stream was last seen with. Senders usually stop including
+/// them once the receiver is known to have them, so they stick until the
+/// SSRC changes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamTag {
+    pub mid: Option<String>,
+    pub rid: Option<String>,
+}
+
+// Fills a `StreamTag` from the packets of a stream, given the extmap ids.
+#[derive(Debug, Default)]
+pub(crate) struct StreamTagger {
+    pub(crate) mid_extension: Option<u8>,
+    pub(crate) rid_extension: Option<u8>,
+    ssrc: Option<u32>,
+    pub(crate) tag: StreamTag,
+}
+
+impl StreamTagger {
+    // `packet` is the buffer `pkt` was parsed from.
+    pub(crate) fn observe(&mut self, pkt: &RtpPacket<'_>, packet: &[u8]) {
+        if self.ssrc != Some(pkt.header.ssrc) {
+            self.ssrc = Some(pkt.header.ssrc);
+            self.tag = StreamTag::default();
+        }
+        let Some(ext) = &pkt.header.extension_header else {
+            return;
+        };
+        for element in ext.elements(packet) {
+            let id = Some(element.id);
+            let slot = if id == self.mid_extension {
+                &mut self.tag.mid
+            } else if id == self.rid_extension {
+                &mut self.tag.rid
+            } else {
+                continue;
+            };
+            match StreamId::parse(element.data) {
+                Ok(value) if slot.as_deref() != Some(value.0) => *slot = Some(value.0.to_string()),
+                Ok(_) => {}
+                Err(_e) => trace_event!(DEBUG, error = %_e, "bad SDES item extension"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze::FrameAnalyzer,
+        codecs::Codec,
+        reassemble::{FrameReassembler, SsrcPolicy},
+        test_utils::RtpPacketBuilder,
+    };
+
+    #[test]
+    fn tags_streams_with_their_mid_and_rid() {
+        assert_eq!(StreamId::parse(b"hi").unwrap().as_str(), "hi");
+        assert!(StreamId::parse(&[b'a', 0xFF]).is_err());
+
+        // MID "0" (id 1) and RID "h" (id 2) on the first packet only.
+        let packet = |ssrc: u32, seq: u16, ids: &[u8]| {
+            let mut builder = RtpPacketBuilder::new()
+                .ssrc(ssrc)
+                .sequence_number(seq)
+                .timestamp(seq as u32 * 3000)
+                .marker(true)
+                .payload(&[0x65, 0x88, 0x84]);
+            if !ids.is_empty() {
+                builder = builder.extension(0xBEDE, ids);
+            }
+            builder.build()
+        };
+        let high = [0x10, b'0', 0x20, b'h'];
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .ssrc_policy(SsrcPolicy::LockFirst)
+            .build()
+            .unwrap();
+        r.set_mid_extension(1);
+        r.set_rid_extension(2);
+        assert!(r.push_bytes(&packet(7, 0, &high)).unwrap().is_some());
+        assert!(r.push_bytes(&packet(7, 1, &[])).unwrap().is_some());
+        // Ignored under `LockFirst`.
+        r.push_bytes(&packet(8, 0, &[0x10, b'0', 0x20, b'l']))
+            .unwrap();
+        let tag = StreamTag {
+            mid: Some("0".into()),
+            rid: Some("h".into()),
+        };
+        assert_eq!(r.stream_tag(), &tag);
+
+        let mut analyzer = FrameAnalyzer::new();
+        analyzer.set_rid_extension(2);
+        analyzer.analyze_bytes(&packet(7, 0, &high)).unwrap();
+        assert_eq!(analyzer.stream_tag().rid.as_deref(), Some("h"));
+        analyzer.analyze_bytes(&packet(8, 0, &[])).unwrap();
+        assert_eq!(analyzer.stream_tag(), &StreamTag::default());
+    }
+}
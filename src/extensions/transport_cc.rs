@@ -0,0 +1,23 @@
+//! Transport-wide sequence number extension
+//! (`http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01`),
+//! numbering the packets of all streams sent over one transport for
+//! transport-wide congestion control feedback.
+
+use super::{ensure_len, ExtensionError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransportSequenceNumber(pub u16);
+
+impl TransportSequenceNumber {
+    pub fn parse(data: &[u8]) -> Result<Self, ExtensionError> {
+        ensure_len(data, "transport-wide sequence number", 2)?;
+        Ok(Self(u16::from_be_bytes([data[0], data[1]])))
+    }
+
+    /// Packets from `earlier` to `self`, assuming they are less than half
+    /// the sequence space apart.
+    pub fn delta(self, earlier: TransportSequenceNumber) -> i16 {
+        self.0.wrapping_sub(earlier.0) as i16
+    }
+}
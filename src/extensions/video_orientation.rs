@@ -0,0 +1,44 @@
+//! Coordination of video orientation extension (`urn:3gpp:video-orientation`,
+//! 3GPP TS 26.114), telling the receiver how to rotate frames for display.
+
+use super::{ensure_len, ExtensionError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Camera {
+    /// Facing the user.
+    Front,
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VideoOrientation {
+    pub camera: Camera,
+    /// The frames are mirrored horizontally, to be undone before rotating.
+    pub flip: bool,
+    /// Clockwise rotation to apply for display: 0, 90, 180 or 270 degrees.
+    pub rotation: u16,
+}
+
+impl VideoOrientation {
+    pub fn parse(data: &[u8]) -> Result<Self, ExtensionError> {
+        ensure_len(data, "video orientation", 1)?;
+        let b = data[0];
+        Ok(Self {
+            camera: if b & 0x08 != 0 {
+                Camera::Back
+            } else {
+                Camera::Front
+            },
+            flip: b & 0x04 != 0,
+            rotation: (b & 0x03) as u16 * 90,
+        })
+    }
+
+    pub fn to_byte(self) -> u8 {
+        (matches!(self.camera, Camera::Back) as u8) << 3
+            | (self.flip as u8) << 2
+            | (self.rotation / 90 % 4) as u8
+    }
+}
@@ -0,0 +1,199 @@
+//! Video layers allocation extension
+//! (`http://www.webrtc.org/experiments/rtp-hdrext/video-layers-allocation00`),
+//! sent by libwebrtc simulcast and SVC senders on keyframes and whenever the
+//! allocation changes.
+
+use super::{ensure_len, ExtensionError};
+use crate::codecs::av1::read_leb128;
+use alloc::vec::Vec;
+
+const NAME: &str = "video layers allocation";
+
+/// Layers a sender currently produces, across all of its simulcast streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VideoLayersAllocation {
+    /// Index into `rtp_streams` of the stream this extension was sent on.
+    pub rtp_stream_index: u8,
+    /// Simulcast streams in RID order; empty when no layer is active.
+    pub rtp_streams: Vec<RtpStreamAllocation>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RtpStreamAllocation {
+    /// Active spatial layers in increasing `spatial_id` order.
+    pub spatial_layers: Vec<SpatialLayerAllocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpatialLayerAllocation {
+    pub spatial_id: u8,
+    /// Target bitrate of each temporal layer in kbps, including the layers
+    /// below it.
+    pub target_bitrates_kbps: Vec<u32>,
+    pub resolution: Option<LayerResolution>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerResolution {
+    pub width: u16,
+    pub height: u16,
+    pub max_framerate: u8,
+}
+
+impl VideoLayersAllocation {
+    pub fn parse(data: &[u8]) -> Result<Self, ExtensionError> {
+        ensure_len(data, NAME, 1)?;
+        // A single zero byte means no active layers.
+        if data == [0] {
+            return Ok(Self {
+                rtp_stream_index: 0,
+                rtp_streams: Vec::new(),
+            });
+        }
+        let rtp_stream_index = data[0] >> 6;
+        let num_streams = ((data[0] >> 4) & 0x03) as usize + 1;
+        let mut offset = 1;
+
+        // Spatial layer bitmask per stream, shared when nonzero.
+        let mut masks = [data[0] & 0x0F; 4];
+        if masks[0] == 0 {
+            let bytes = num_streams.div_ceil(2);
+            ensure_len(data, NAME, offset + bytes)?;
+            for (i, mask) in masks.iter_mut().take(num_streams).enumerate() {
+                let b = data[offset + i / 2];
+                *mask = if i % 2 == 0 { b >> 4 } else { b & 0x0F };
+            }
+            offset += bytes;
+        }
+        let mut rtp_streams: Vec<RtpStreamAllocation> = masks[..num_streams]
+            .iter()
+            .map(|&mask| RtpStreamAllocation {
+                spatial_layers: (0..4)
+                    .filter(|sid| mask & (1 << sid) != 0)
+                    .map(|spatial_id| SpatialLayerAllocation {
+                        spatial_id,
+                        target_bitrates_kbps: Vec::new(),
+                        resolution: None,
+                    })
+                    .collect(),
+            })
+            .collect();
+        let layers = rtp_streams
+            .iter()
+            .map(|s| s.spatial_layers.len())
+            .sum::<usize>();
+        if layers == 0 || (rtp_stream_index as usize) >= num_streams {
+            return Err(ExtensionError::Invalid {
+                extension: NAME,
+                offset: 0,
+            });
+        }
+
+        // Temporal layer counts, 2 bits per spatial layer.
+        let bytes = layers.div_ceil(4);
+        ensure_len(data, NAME, offset + bytes)?;
+        let counts: Vec<usize> = (0..layers)
+            .map(|i| ((data[offset + i / 4] >> (6 - 2 * (i % 4))) & 0x03) as usize + 1)
+            .collect();
+        offset += bytes;
+
+        let all_layers = rtp_streams.iter_mut().flat_map(|s| &mut s.spatial_layers);
+        for (layer, &count) in all_layers.zip(&counts) {
+            for _ in 0..count {
+                let (kbps, n) = read_leb128(&data[offset..]).ok_or(ExtensionError::TooShort {
+                    extension: NAME,
+                    len: data.len(),
+                    needed: offset + 1,
+                })?;
+                layer
+                    .target_bitrates_kbps
+                    .push(kbps.min(u32::MAX as u64) as u32);
+                offset += n;
+            }
+        }
+
+        // Resolutions are optional, but all or none.
+        if offset < data.len() {
+            ensure_len(data, NAME, offset + 5 * layers)?;
+            for layer in rtp_streams.iter_mut().flat_map(|s| &mut s.spatial_layers) {
+                let r = &data[offset..offset + 5];
+                layer.resolution = Some(LayerResolution {
+                    width: u16::from_be_bytes([r[0], r[1]]).wrapping_add(1),
+                    height: u16::from_be_bytes([r[2], r[3]]).wrapping_add(1),
+                    max_framerate: r[4],
+                });
+                offset += 5;
+            }
+        }
+        Ok(Self {
+            rtp_stream_index,
+            rtp_streams,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_simulcast_allocation() {
+        let data = [
+            // Sent on stream 1 of 2, spatial layer 0 in both.
+            0x51, // Two temporal layers, then one.
+            0x40, // 100, 200 and 500 kbps.
+            0x64, 0xC8, 0x01, 0xF4, 0x03, // 320x180 at 15 fps, 640x360 at 30 fps.
+            0x01, 0x3F, 0x00, 0xB3, 15, 0x02, 0x7F, 0x01, 0x67, 30,
+        ];
+        let vla = VideoLayersAllocation::parse(&data).unwrap();
+        assert_eq!(vla.rtp_stream_index, 1);
+        let layers: Vec<_> = vla
+            .rtp_streams
+            .iter()
+            .flat_map(|s| &s.spatial_layers)
+            .map(|l| (l.target_bitrates_kbps.clone(), l.resolution.unwrap()))
+            .collect();
+        assert_eq!(layers[0].0, [100, 200]);
+        assert_eq!(layers[1].0, [500]);
+        assert_eq!(
+            layers[1].1,
+            LayerResolution {
+                width: 640,
+                height: 360,
+                max_framerate: 30
+            }
+        );
+        // Cut inside the resolutions.
+        assert!(matches!(
+            VideoLayersAllocation::parse(&data[..10]),
+            Err(ExtensionError::TooShort { needed: 17, .. })
+        ));
+    }
+
+    #[test]
+    fn parses_per_stream_layer_masks() {
+        // Three streams with spatial layers {0}, {0, 1} and none; each with
+        // one temporal layer; no resolutions.
+        let data = [0x20, 0x13, 0x00, 0x00, 10, 20, 30];
+        let vla = VideoLayersAllocation::parse(&data).unwrap();
+        let ids: Vec<Vec<u8>> = vla
+            .rtp_streams
+            .iter()
+            .map(|s| s.spatial_layers.iter().map(|l| l.spatial_id).collect())
+            .collect();
+        assert_eq!(ids, [alloc::vec![0], alloc::vec![0, 1], alloc::vec![]]);
+        assert_eq!(
+            vla.rtp_streams[1].spatial_layers[1].target_bitrates_kbps,
+            [30]
+        );
+        assert!(VideoLayersAllocation::parse(&[0])
+            .unwrap()
+            .rtp_streams
+            .is_empty());
+        assert!(VideoLayersAllocation::parse(&[0x10, 0x00]).is_err());
+    }
+}
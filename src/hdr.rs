@@ -0,0 +1,192 @@
+//! HDR dynamic metadata attached to frames.
+//!
+//! SMPTE ST 2094 dynamic metadata travels as ITU-T T.35 user data: in
+//! `user_data_registered_itu_t_t35` SEI messages for H.265 and in
+//! `METADATA_TYPE_ITUT_T35` metadata OBUs for AV1. [`HdrMetadata`] keeps
+//! the T.35 bytes untouched, tagged with the standard they follow, so
+//! remuxers can write them back without understanding them.
+//!
+//! Dolby Vision instead sends its reference processing unit (RPU) in H.265
+//! NAL units of the unspecified type 62, next to the slices of each
+//! picture, and a dual layer stream's enhancement layer in type 63.
+//! [`DolbyVisionRpu`] picks the RPU out of a frame; the NAL units stay in
+//! the frame data. H.264 based profiles are not detected.
+
+use crate::{
+    bitstream::remove_emulation_prevention,
+    codecs::{
+        av1::{read_leb128, split_obus, ObuHeader},
+        sei::{sei_messages, sei_rbsp, SEI_USER_DATA_REGISTERED},
+        Codec,
+    },
+};
+use alloc::vec::Vec;
+
+const OBU_METADATA: u8 = 5;
+const METADATA_TYPE_ITUT_T35: u64 = 4;
+
+/// H.265 `nal_unit_type` of Dolby Vision RPUs.
+pub const HEVC_NAL_DOLBY_VISION_RPU: u8 = 62;
+/// H.265 `nal_unit_type` of Dolby Vision enhancement layer NAL units.
+pub const HEVC_NAL_DOLBY_VISION_EL: u8 = 63;
+
+// First RBSP byte of every RPU.
+const RPU_NAL_PREFIX: u8 = 0x19;
+
+/// Whether `nal` is a Dolby Vision RPU or enhancement layer NAL unit.
+pub fn is_dolby_vision_nal(codec: Codec, nal: &[u8]) -> bool {
+    codec == Codec::Hevc
+        && nal.first().is_some_and(|h| {
+            matches!(
+                (h >> 1) & 0x3F,
+                HEVC_NAL_DOLBY_VISION_RPU | HEVC_NAL_DOLBY_VISION_EL
+            )
+        })
+}
+
+/// Which ST 2094 application a [`HdrMetadata`] carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HdrMetadataKind {
+    /// SMPTE ST 2094-40 (HDR10+).
+    St2094_40,
+    /// SMPTE ST 2094-10, as carried by ATSC A/341.
+    St2094_10,
+}
+
+/// One dynamic metadata message of a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HdrMetadata {
+    pub kind: HdrMetadataKind,
+    /// T.35 payload from `itu_t_t35_country_code` on.
+    pub t35: Vec<u8>,
+}
+
+impl HdrMetadata {
+    /// Tags a T.35 payload, `None` if it is not ST 2094 metadata.
+    pub fn from_t35(t35: &[u8]) -> Option<Self> {
+        let kind = match t35 {
+            // Samsung provider code, provider-oriented code 1, application 4.
+            [0xB5, 0x00, 0x3C, 0x00, 0x01, 0x04, ..] => HdrMetadataKind::St2094_40,
+            // ATSC "GA94" with user_data_type_code 0x09.
+            [0xB5, 0x00, 0x31, b'G', b'A', b'9', b'4', 0x09, ..] => HdrMetadataKind::St2094_10,
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            t35: t35.to_vec(),
+        })
+    }
+
+    /// Metadata in the SEI NAL units of an H.265 (or H.264) frame.
+    pub fn from_nals<'a>(codec: Codec, nals: impl IntoIterator<Item = &'a [u8]>) -> Vec<Self> {
+        let mut found = Vec::new();
+        for nal in nals {
+            let Some(rbsp) = sei_rbsp(codec, nal) else {
+                continue;
+            };
+            found.extend(
+                sei_messages(&rbsp)
+                    .filter(|m| m.payload_type == SEI_USER_DATA_REGISTERED)
+                    .filter_map(|m| Self::from_t35(m.payload)),
+            );
+        }
+        found
+    }
+
+    /// Metadata in the metadata OBUs of an AV1 temporal unit.
+    pub fn from_obus(temporal_unit: &[u8]) -> Vec<Self> {
+        let obus = split_obus(temporal_unit).unwrap_or_default();
+        obus.iter().filter_map(|obu| Self::from_obu(obu)).collect()
+    }
+
+    fn from_obu(obu: &[u8]) -> Option<Self> {
+        let header = ObuHeader::parse(*obu.first()?);
+        if header.obu_type != OBU_METADATA {
+            return None;
+        }
+        let mut payload = obu.get(header.header_len()..)?;
+        if header.has_size_field {
+            let (_, n) = read_leb128(payload)?;
+            payload = &payload[n..];
+        }
+        let (metadata_type, n) = read_leb128(payload)?;
+        if metadata_type != METADATA_TYPE_ITUT_T35 {
+            return None;
+        }
+        // trailing_bits(): a byte-aligned payload ends with one 0x80 byte.
+        let payload = &payload[n..];
+        let end = payload.iter().rposition(|&b| b != 0)?;
+        Self::from_t35(&payload[..end])
+    }
+}
+
+/// The Dolby Vision RPU of a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DolbyVisionRpu {
+    /// `rpu_type`; 2 for the RPUs of all current profiles.
+    pub rpu_type: u8,
+    pub rpu_format: u16,
+    /// The whole NAL unit, header and emulation prevention included.
+    pub nal: Vec<u8>,
+}
+
+impl DolbyVisionRpu {
+    /// Parses an RPU NAL unit, `None` for other NAL units.
+    pub fn parse(nal: &[u8]) -> Option<Self> {
+        if nal.first().map(|h| (h >> 1) & 0x3F) != Some(HEVC_NAL_DOLBY_VISION_RPU) {
+            return None;
+        }
+        let rbsp = remove_emulation_prevention(nal.get(2..)?);
+        let [RPU_NAL_PREFIX, a, b, c, ..] = rbsp[..] else {
+            return None;
+        };
+        Some(Self {
+            rpu_type: a >> 2,
+            rpu_format: ((a as u16 & 0x03) << 9) | ((b as u16) << 1) | (c as u16 >> 7),
+            nal: nal.to_vec(),
+        })
+    }
+
+    /// The first RPU among the NAL units of an H.265 frame.
+    pub fn from_nals<'a>(codec: Codec, nals: impl IntoIterator<Item = &'a [u8]>) -> Option<Self> {
+        if codec != Codec::Hevc {
+            return None;
+        }
+        nals.into_iter().find_map(Self::parse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HDR10_PLUS: &[u8] = &[0xB5, 0x00, 0x3C, 0x00, 0x01, 0x04, 0x01, 0x40, 0x00, 0x0C];
+
+    #[test]
+    fn collects_t35_metadata_from_sei_and_obus() {
+        let mut sei = alloc::vec![0x4E, 0x01, 0x04, HDR10_PLUS.len() as u8];
+        sei.extend_from_slice(HDR10_PLUS);
+        sei.push(0x80);
+        let found = HdrMetadata::from_nals(Codec::Hevc, [sei.as_slice()]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, HdrMetadataKind::St2094_40);
+        assert_eq!(found[0].t35, HDR10_PLUS);
+
+        // Temporal delimiter, then a metadata OBU with obu_size.
+        let mut tu = alloc::vec![0x12, 0x00, 0x2A, HDR10_PLUS.len() as u8 + 2, 0x04];
+        tu.extend_from_slice(HDR10_PLUS);
+        tu.push(0x80);
+        assert_eq!(HdrMetadata::from_obus(&tu), found);
+
+        // Captions share the T.35 prefix but are not HDR metadata.
+        assert_eq!(HdrMetadata::from_t35(b"\xB5\x00\x31GA94\x03\x40"), None);
+    }
+
+    #[test]
+    fn metadata_obu_without_payload_is_ignored() {
+        assert!(HdrMetadata::from_obus(&[0x2A, 0x01, 0x04]).is_empty());
+    }
+}
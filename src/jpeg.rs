@@ -0,0 +1,601 @@
+//! Motion JPEG over RTP (RFC 2435).
+//!
+//! RTP/JPEG strips the JPEG headers and sends only the scan data, each
+//! packet led by a main header naming the image type, size and quality
+//! factor `Q`. Types 64–127 add a restart marker header carrying the
+//! restart interval, and `Q` values 128–255 send the quantization tables
+//! in-band in the first packet of a frame; for `Q` below 255 the tables may
+//! be omitted in later frames, which then reuse the last ones sent.
+//! [`JpegDepacketizer`] rebuilds complete JPEG images, headers included.
+
+use crate::{limits::Limits, rtp::RtpPacket};
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// Images growing past this many bytes are dropped, unless configured
+/// otherwise with [`JpegDepacketizer::with_limits`].
+pub const MAX_IMAGE_BYTES: usize = 8 << 20;
+
+// RFC 2435, Appendix A: the JPEG Annex K tables in zigzag order.
+const LUMA_QUANTIZER: [u8; 64] = [
+    16, 11, 12, 14, 12, 10, 16, 14, 13, 14, 18, 17, 16, 19, 24, 40, 26, 24, 22, 22, 24, 49, 35, 37,
+    29, 40, 58, 51, 61, 60, 57, 51, 56, 55, 64, 72, 92, 78, 64, 68, 87, 69, 55, 56, 80, 109, 81,
+    87, 95, 98, 103, 104, 103, 62, 77, 113, 121, 112, 100, 120, 92, 101, 103, 99,
+];
+const CHROMA_QUANTIZER: [u8; 64] = [
+    17, 18, 18, 24, 21, 24, 47, 26, 26, 47, 99, 66, 56, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+// RFC 2435, Appendix B: the JPEG Annex K Huffman tables.
+const DC_SYMBOLS: [u8; 12] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+const LUMA_DC_CODELENS: [u8; 16] = [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0];
+const LUMA_AC_CODELENS: [u8; 16] = [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7D];
+const LUMA_AC_SYMBOLS: [u8; 162] = [
+    0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07,
+    0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xA1, 0x08, 0x23, 0x42, 0xB1, 0xC1, 0x15, 0x52, 0xD1, 0xF0,
+    0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0A, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x25, 0x26, 0x27, 0x28,
+    0x29, 0x2A, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49,
+    0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69,
+    0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+    0x8A, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7,
+    0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA, 0xC2, 0xC3, 0xC4, 0xC5,
+    0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA, 0xE1, 0xE2,
+    0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xEA, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8,
+    0xF9, 0xFA,
+];
+const CHROMA_DC_CODELENS: [u8; 16] = [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0];
+const CHROMA_AC_CODELENS: [u8; 16] = [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77];
+const CHROMA_AC_SYMBOLS: [u8; 162] = [
+    0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61, 0x71,
+    0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xA1, 0xB1, 0xC1, 0x09, 0x23, 0x33, 0x52, 0xF0,
+    0x15, 0x62, 0x72, 0xD1, 0x0A, 0x16, 0x24, 0x34, 0xE1, 0x25, 0xF1, 0x17, 0x18, 0x19, 0x1A, 0x26,
+    0x27, 0x28, 0x29, 0x2A, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48,
+    0x49, 0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68,
+    0x69, 0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87,
+    0x88, 0x89, 0x8A, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5,
+    0xA6, 0xA7, 0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA, 0xC2, 0xC3,
+    0xC4, 0xC5, 0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA,
+    0xE2, 0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xEA, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8,
+    0xF9, 0xFA,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JpegError {
+    /// The payload ends inside `header`.
+    Truncated { header: &'static str },
+    /// Only types 0, 1, 64 and 65 (YUV 4:2:2 and 4:2:0) are defined.
+    UnsupportedType(u8),
+    /// `Q` values 100–127 are reserved.
+    ReservedQ(u8),
+    /// A frame with this `Q` omitted its quantization tables and none were
+    /// received before.
+    MissingQuantizationTables(u8),
+    /// Packets of the frame at `timestamp` were lost.
+    MissingPacket { timestamp: u32 },
+    /// Packets of the frame at `timestamp` disagree on its type, size,
+    /// `Q` or restart interval.
+    InconsistentHeaders { timestamp: u32 },
+    /// The frame at `timestamp` exceeded its size limit.
+    TooLarge { timestamp: u32 },
+}
+
+impl core::fmt::Display for JpegError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            JpegError::Truncated { header } => write!(f, "JPEG payload ends inside {}", header),
+            JpegError::UnsupportedType(t) => write!(f, "unsupported JPEG type {}", t),
+            JpegError::ReservedQ(q) => write!(f, "reserved JPEG Q value {}", q),
+            JpegError::MissingQuantizationTables(q) => {
+                write!(f, "no quantization tables received for Q {}", q)
+            }
+            JpegError::MissingPacket { timestamp } => {
+                write!(f, "JPEG frame at {} lost packets", timestamp)
+            }
+            JpegError::InconsistentHeaders { timestamp } => {
+                write!(f, "JPEG frame at {} has inconsistent headers", timestamp)
+            }
+            JpegError::TooLarge { timestamp } => {
+                write!(f, "JPEG frame at {} exceeds the size limit", timestamp)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for JpegError {}
+
+/// Restart marker header of types 64–127.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartHeader {
+    /// MCUs between restart markers, as in a DRI segment.
+    pub interval: u16,
+    /// The packet starts the restart intervals it carries (F bit).
+    pub first: bool,
+    /// The packet ends them (L bit).
+    pub last: bool,
+    /// Restart interval the packet starts with, or 0x3FFF.
+    pub count: u16,
+}
+
+/// In-band quantization tables, in zigzag order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuantizationTables {
+    /// Bit `i` set when table `i` has 16-bit entries.
+    pub precision: u8,
+    pub data: Vec<u8>,
+}
+
+impl QuantizationTables {
+    /// Tables computed from the standard ones for `Q` 1–99 (RFC 2435,
+    /// Appendix A).
+    pub fn from_q(q: u8) -> Self {
+        let factor = q.clamp(1, 99) as u32;
+        let scale = if factor < 50 {
+            5000 / factor
+        } else {
+            200 - factor * 2
+        };
+        let data = LUMA_QUANTIZER
+            .iter()
+            .chain(&CHROMA_QUANTIZER)
+            .map(|&v| ((v as u32 * scale + 50) / 100).clamp(1, 255) as u8)
+            .collect();
+        Self { precision: 0, data }
+    }
+
+    // Each table's precision bit and entries.
+    fn tables(&self) -> impl Iterator<Item = (bool, &[u8])> {
+        let mut rest = self.data.as_slice();
+        (0..8).map_while(move |i| {
+            let wide = self.precision & (1 << i) != 0;
+            let len = if wide { 128 } else { 64 };
+            let table = rest.get(..len)?;
+            rest = &rest[len..];
+            Some((wide, table))
+        })
+    }
+}
+
+/// Headers leading one RTP/JPEG payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JpegHeader {
+    pub type_specific: u8,
+    /// Position of the packet's scan data in the frame.
+    pub fragment_offset: u32,
+    pub jpeg_type: u8,
+    pub q: u8,
+    /// Image size in pixels.
+    pub width: u16,
+    pub height: u16,
+    pub restart: Option<RestartHeader>,
+    /// Present in the first packet of frames with `Q` 128–255; `None` when
+    /// its length is 0, i.e. earlier tables apply.
+    pub tables: Option<QuantizationTables>,
+}
+
+impl JpegHeader {
+    /// Parses the headers of a payload; returns them and where the scan
+    /// data starts.
+    pub fn parse(payload: &[u8]) -> Result<(Self, usize), JpegError> {
+        let truncated = |header| JpegError::Truncated { header };
+        let main = payload.get(..8).ok_or(truncated("main header"))?;
+        let mut header = JpegHeader {
+            type_specific: main[0],
+            fragment_offset: u32::from_be_bytes([0, main[1], main[2], main[3]]),
+            jpeg_type: main[4],
+            q: main[5],
+            width: main[6] as u16 * 8,
+            height: main[7] as u16 * 8,
+            restart: None,
+            tables: None,
+        };
+        if !matches!(header.jpeg_type & 0x3F, 0 | 1) || header.jpeg_type >= 128 {
+            return Err(JpegError::UnsupportedType(header.jpeg_type));
+        }
+        if (100..128).contains(&header.q) || header.q == 0 {
+            return Err(JpegError::ReservedQ(header.q));
+        }
+        let mut at = 8;
+        if header.jpeg_type >= 64 {
+            let restart = payload.get(at..at + 4).ok_or(truncated("restart header"))?;
+            let count = u16::from_be_bytes([restart[2], restart[3]]);
+            header.restart = Some(RestartHeader {
+                interval: u16::from_be_bytes([restart[0], restart[1]]),
+                first: count & 0x8000 != 0,
+                last: count & 0x4000 != 0,
+                count: count & 0x3FFF,
+            });
+            at += 4;
+        }
+        if header.q >= 128 && header.fragment_offset == 0 {
+            let qt = payload
+                .get(at..at + 4)
+                .ok_or(truncated("quantization table header"))?;
+            let len = u16::from_be_bytes([qt[2], qt[3]]) as usize;
+            let data = payload
+                .get(at + 4..at + 4 + len)
+                .ok_or(truncated("quantization tables"))?;
+            if len > 0 {
+                header.tables = Some(QuantizationTables {
+                    precision: qt[1],
+                    data: data.to_vec(),
+                });
+            } else if header.q == 255 {
+                return Err(JpegError::MissingQuantizationTables(255));
+            }
+            at += 4 + len;
+        }
+        Ok((header, at))
+    }
+}
+
+/// JPEG image completed by a [`JpegDepacketizer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JpegFrame {
+    pub timestamp: u32,
+    pub width: u16,
+    pub height: u16,
+    /// Complete baseline JPEG, from SOI to EOI.
+    pub data: Vec<u8>,
+}
+
+// Frame being received.
+#[derive(Debug)]
+struct PendingFrame {
+    timestamp: u32,
+    next_seq: u16,
+    // Type, Q, width, height and restart interval of the first packet.
+    params: (u8, u8, u16, u16, Option<u16>),
+    tables: Option<QuantizationTables>,
+    scan: Vec<u8>,
+    error: Option<JpegError>,
+}
+
+/// Rebuilds JPEG images from the packets of one RTP/JPEG stream.
+///
+/// A frame whose last packet is lost is dropped when the next frame
+/// starts; like other dropped frames it is counted by
+/// [`dropped_frames`](Self::dropped_frames).
+#[derive(Debug)]
+pub struct JpegDepacketizer {
+    pending: Option<PendingFrame>,
+    // Last in-band tables of each `Q` from 128 to 254.
+    tables: BTreeMap<u8, QuantizationTables>,
+    max_image_bytes: usize,
+    dropped: u64,
+}
+
+impl Default for JpegDepacketizer {
+    fn default() -> Self {
+        Self {
+            pending: None,
+            tables: BTreeMap::new(),
+            max_image_bytes: MAX_IMAGE_BYTES,
+            dropped: 0,
+        }
+    }
+}
+
+impl JpegDepacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bounds images by `limits.max_frame_bytes`.
+    pub fn with_limits(limits: &Limits) -> Self {
+        Self {
+            max_image_bytes: limits.max_frame_bytes,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a packet; returns the image it completes, or why that frame was
+    /// dropped.
+    pub fn push_packet(&mut self, pkt: &RtpPacket<'_>) -> Option<Result<JpegFrame, JpegError>> {
+        let timestamp = pkt.header.timestamp;
+        let seq = pkt.header.sequence_number;
+        let parsed = JpegHeader::parse(pkt.payload);
+        let mut frame = match self.pending.take() {
+            Some(frame) if frame.timestamp == timestamp => frame,
+            previous => {
+                if let Some(_previous) = previous {
+                    trace_event!(
+                        DEBUG,
+                        timestamp = _previous.timestamp,
+                        "JPEG frame lost its last packet"
+                    );
+                    self.dropped += 1;
+                }
+                let params = parsed.as_ref().map_or((0, 0, 0, 0, None), |(h, _)| {
+                    (
+                        h.jpeg_type,
+                        h.q,
+                        h.width,
+                        h.height,
+                        h.restart.map(|r| r.interval),
+                    )
+                });
+                PendingFrame {
+                    timestamp,
+                    next_seq: seq,
+                    params,
+                    tables: None,
+                    scan: Vec::new(),
+                    error: None,
+                }
+            }
+        };
+        if frame.error.is_none() {
+            frame.error = self.add(&mut frame, seq, parsed, pkt.payload).err();
+        }
+        frame.next_seq = seq.wrapping_add(1);
+        if !pkt.header.marker {
+            self.pending = Some(frame);
+            return None;
+        }
+        let result = match frame.error {
+            Some(error) => Err(error),
+            None => self.finish(frame),
+        };
+        if let Err(_e) = &result {
+            trace_event!(DEBUG, timestamp, error = %_e, "JPEG frame dropped");
+            self.dropped += 1;
+        }
+        Some(result)
+    }
+
+    /// Frames dropped so far, including those returned as errors.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped
+    }
+
+    fn add(
+        &mut self,
+        frame: &mut PendingFrame,
+        seq: u16,
+        parsed: Result<(JpegHeader, usize), JpegError>,
+        payload: &[u8],
+    ) -> Result<(), JpegError> {
+        let timestamp = frame.timestamp;
+        let (header, at) = parsed?;
+        let params = (
+            header.jpeg_type,
+            header.q,
+            header.width,
+            header.height,
+            header.restart.map(|r| r.interval),
+        );
+        if params != frame.params {
+            return Err(JpegError::InconsistentHeaders { timestamp });
+        }
+        if seq != frame.next_seq || header.fragment_offset as usize != frame.scan.len() {
+            return Err(JpegError::MissingPacket { timestamp });
+        }
+        if frame.scan.len() + payload.len() > self.max_image_bytes {
+            return Err(JpegError::TooLarge { timestamp });
+        }
+        if header.fragment_offset == 0 && header.q >= 128 {
+            frame.tables = match header.tables {
+                Some(tables) => {
+                    if header.q < 255 {
+                        self.tables.insert(header.q, tables.clone());
+                    }
+                    Some(tables)
+                }
+                None => Some(
+                    self.tables
+                        .get(&header.q)
+                        .cloned()
+                        .ok_or(JpegError::MissingQuantizationTables(header.q))?,
+                ),
+            };
+        }
+        frame.scan.extend_from_slice(&payload[at..]);
+        Ok(())
+    }
+
+    fn finish(&self, frame: PendingFrame) -> Result<JpegFrame, JpegError> {
+        let (jpeg_type, q, width, height, interval) = frame.params;
+        let tables = match frame.tables {
+            Some(tables) => tables,
+            None if q < 128 => QuantizationTables::from_q(q),
+            // The first packet was lost.
+            None => {
+                return Err(JpegError::MissingPacket {
+                    timestamp: frame.timestamp,
+                })
+            }
+        };
+        let mut data = Vec::with_capacity(frame.scan.len() + 1024);
+        write_headers(&mut data, jpeg_type, width, height, interval, &tables);
+        data.extend_from_slice(&frame.scan);
+        if !data.ends_with(&[0xFF, 0xD9]) {
+            data.extend_from_slice(&[0xFF, 0xD9]);
+        }
+        Ok(JpegFrame {
+            timestamp: frame.timestamp,
+            width,
+            height,
+            data,
+        })
+    }
+}
+
+// The JPEG headers RTP/JPEG leaves out (RFC 2435, Appendix B).
+fn write_headers(
+    out: &mut Vec<u8>,
+    jpeg_type: u8,
+    width: u16,
+    height: u16,
+    interval: Option<u16>,
+    tables: &QuantizationTables,
+) {
+    out.extend_from_slice(&[0xFF, 0xD8]);
+    let mut count = 0;
+    for (id, (wide, table)) in tables.tables().enumerate() {
+        segment(out, 0xDB, &[&[(wide as u8) << 4 | id as u8], table]);
+        count += 1;
+    }
+    if let Some(interval) = interval.filter(|&i| i > 0) {
+        segment(out, 0xDD, &[&interval.to_be_bytes()]);
+    }
+    // 4:2:2 for type 0, 4:2:0 for type 1; chroma uses the second table
+    // when there is one.
+    let luma_sampling = if jpeg_type & 0x3F == 0 { 0x21 } else { 0x22 };
+    let chroma_table = (count > 1) as u8;
+    let [h0, h1] = height.to_be_bytes();
+    let [w0, w1] = width.to_be_bytes();
+    segment(
+        out,
+        0xC0,
+        &[&[
+            8,
+            h0,
+            h1,
+            w0,
+            w1,
+            3,
+            1,
+            luma_sampling,
+            0,
+            2,
+            0x11,
+            chroma_table,
+            3,
+            0x11,
+            chroma_table,
+        ]],
+    );
+    segment(out, 0xC4, &[&[0x00], &LUMA_DC_CODELENS, &DC_SYMBOLS]);
+    segment(out, 0xC4, &[&[0x10], &LUMA_AC_CODELENS, &LUMA_AC_SYMBOLS]);
+    segment(out, 0xC4, &[&[0x01], &CHROMA_DC_CODELENS, &DC_SYMBOLS]);
+    segment(
+        out,
+        0xC4,
+        &[&[0x11], &CHROMA_AC_CODELENS, &CHROMA_AC_SYMBOLS],
+    );
+    segment(out, 0xDA, &[&[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]]);
+}
+
+fn segment(out: &mut Vec<u8>, marker: u8, parts: &[&[u8]]) {
+    let len = 2 + parts.iter().map(|p| p.len()).sum::<usize>();
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&(len as u16).to_be_bytes());
+    for part in parts {
+        out.extend_from_slice(part);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::RtpPacketBuilder;
+
+    // Payloads of one frame: scan data split in `size` byte fragments,
+    // `extra` placed after the main header of each and `tables` after
+    // that in the first.
+    fn payloads(
+        main: [u8; 8],
+        extra: &[u8],
+        tables: &[u8],
+        scan: &[u8],
+        size: usize,
+    ) -> Vec<Vec<u8>> {
+        scan.chunks(size)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let offset = ((i * size) as u32).to_be_bytes();
+                let mut payload = main.to_vec();
+                payload[1..4].copy_from_slice(&offset[1..]);
+                payload.extend_from_slice(extra);
+                if i == 0 {
+                    payload.extend_from_slice(tables);
+                }
+                payload.extend_from_slice(chunk);
+                payload
+            })
+            .collect()
+    }
+
+    fn push_frame(
+        jpeg: &mut JpegDepacketizer,
+        payloads: &[Vec<u8>],
+        timestamp: u32,
+        first_seq: u16,
+    ) -> Vec<Result<JpegFrame, JpegError>> {
+        let mut results = Vec::new();
+        for (i, payload) in payloads.iter().enumerate() {
+            let bytes = RtpPacketBuilder::new()
+                .marker(i + 1 == payloads.len())
+                .sequence_number(first_seq + i as u16)
+                .timestamp(timestamp)
+                .payload(payload)
+                .build();
+            results.extend(jpeg.push_packet(&RtpPacket::parse(&bytes).unwrap()));
+        }
+        results
+    }
+
+    #[test]
+    fn rebuilds_headers_for_standard_tables() {
+        let scan: Vec<u8> = (0..100).collect();
+        let mut jpeg = JpegDepacketizer::new();
+        let frame = payloads([0, 0, 0, 0, 1, 50, 2, 2], &[], &[], &scan, 40);
+        let [Ok(image)] = &push_frame(&mut jpeg, &frame, 0, 1)[..] else {
+            panic!("expected one image");
+        };
+        assert_eq!((image.width, image.height), (16, 16));
+        assert!(image
+            .data
+            .starts_with(&[0xFF, 0xD8, 0xFF, 0xDB, 0, 67, 0x00]));
+        // Q 50 keeps the standard tables.
+        assert_eq!(image.data[7..71], LUMA_QUANTIZER);
+        let sof = image
+            .data
+            .windows(2)
+            .position(|w| w == [0xFF, 0xC0])
+            .unwrap();
+        assert_eq!(image.data[sof + 5..sof + 9], [0, 16, 0, 16]);
+        assert_eq!(image.data[sof + 11], 0x22);
+        assert!(image
+            .data
+            .ends_with(&[&scan[60..], &[0xFF, 0xD9][..]].concat()));
+    }
+
+    #[test]
+    fn restart_intervals_and_in_band_tables_carry_over() {
+        let scan = [0x55; 30];
+        let restart = [0x00, 0x04, 0xC0 | 0x3F, 0xFF];
+        let mut qt = vec![0, 0, 0, 128];
+        qt.extend((0..128).map(|i| i as u8 + 1));
+        let main = [0, 0, 0, 0, 65, 128, 4, 4];
+        let mut jpeg = JpegDepacketizer::new();
+        let first = push_frame(&mut jpeg, &payloads(main, &restart, &qt, &scan, 10), 0, 1);
+        // Later frames may leave the tables out.
+        let second = push_frame(
+            &mut jpeg,
+            &payloads(main, &restart, &[0, 0, 0, 0], &scan, 10),
+            3000,
+            4,
+        );
+        assert_eq!(
+            first[0].as_ref().map(|f| &f.data),
+            second[0].as_ref().map(|f| &f.data)
+        );
+        let image = &first[0].as_ref().unwrap().data;
+        assert_eq!(image[7..71], qt[4..68]);
+        let dri = image.windows(2).position(|w| w == [0xFF, 0xDD]).unwrap();
+        assert_eq!(image[dri + 2..dri + 6], [0, 4, 0, 4]);
+
+        let other_q = [0, 0, 0, 0, 65, 129, 4, 4];
+        let third = push_frame(
+            &mut jpeg,
+            &payloads(other_q, &restart, &[0, 0, 0, 0], &scan, 10),
+            6000,
+            7,
+        );
+        assert_eq!(third, [Err(JpegError::MissingQuantizationTables(129))]);
+        assert_eq!(jpeg.dropped_frames(), 1);
+    }
+}
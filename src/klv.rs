@@ -0,0 +1,297 @@
+//! SMPTE 336M KLV metadata over RTP (RFC 6597).
+//!
+//! A KLV unit holds the KLV items to present at one instant, e.g. a MISB
+//! ST 0601 UAS datalink local set. It may be split across packets, which
+//! then share one timestamp, and the marker bit flags the last of them.
+//! [`KlvDepacketizer`] rebuilds the units and checks that their items are
+//! complete. Metadata is synchronized with the video it describes through
+//! the RTP timestamps (and RTCP sender reports) of both streams.
+
+use crate::{limits::Limits, rtp::RtpPacket};
+use alloc::vec::Vec;
+
+/// First bytes of every SMPTE Universal Label key.
+pub const UL_PREFIX: [u8; 4] = [0x06, 0x0E, 0x2B, 0x34];
+
+/// Units growing past this many bytes are dropped, unless configured
+/// otherwise with [`KlvDepacketizer::with_limits`].
+pub const MAX_UNIT_BYTES: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KlvError {
+    /// Packets of the unit at `timestamp` were lost.
+    MissingPacket { timestamp: u32 },
+    /// The unit at `timestamp` is not a sequence of complete KLV items;
+    /// the first bad one starts at `offset`.
+    Malformed { timestamp: u32, offset: usize },
+    /// The unit at `timestamp` exceeded its size limit.
+    TooLarge { timestamp: u32 },
+}
+
+impl core::fmt::Display for KlvError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            KlvError::MissingPacket { timestamp } => {
+                write!(f, "KLV unit at {} lost packets", timestamp)
+            }
+            KlvError::Malformed { timestamp, offset } => write!(
+                f,
+                "KLV unit at {} has a malformed item at offset {}",
+                timestamp, offset
+            ),
+            KlvError::TooLarge { timestamp } => {
+                write!(f, "KLV unit at {} exceeds the size limit", timestamp)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KlvError {}
+
+/// Reads a BER length (SMPTE 336M short or long form); returns it and the
+/// bytes it took.
+pub fn read_ber_length(buf: &[u8]) -> Option<(usize, usize)> {
+    let first = *buf.first()?;
+    if first < 0x80 {
+        return Some((first as usize, 1));
+    }
+    // The indefinite form (0x80) is not allowed in KLV.
+    let len = (first & 0x7F) as usize;
+    let bytes = buf.get(1..1 + len).filter(|_| (1..=8).contains(&len))?;
+    let value = bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    Some((usize::try_from(value).ok()?, 1 + len))
+}
+
+/// Key and value of one KLV item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KlvItem<'a> {
+    /// 16-byte SMPTE Universal Label.
+    pub key: &'a [u8; 16],
+    pub value: &'a [u8],
+}
+
+/// Splits `data` into KLV items; fails with the offset of the first item
+/// that is truncated or lacks a Universal Label key.
+pub fn parse_klv_items(data: &[u8]) -> Result<Vec<KlvItem<'_>>, usize> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+        let item = rest
+            .split_first_chunk::<16>()
+            .filter(|(key, _)| key.starts_with(&UL_PREFIX))
+            .and_then(|(key, tail)| {
+                let (len, n) = read_ber_length(tail)?;
+                let value = tail.get(n..n.checked_add(len)?)?;
+                Some((KlvItem { key, value }, 16 + n + len))
+            });
+        let Some((item, size)) = item else {
+            return Err(offset);
+        };
+        items.push(item);
+        offset += size;
+    }
+    Ok(items)
+}
+
+/// KLV unit completed by a [`KlvDepacketizer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KlvUnit {
+    pub timestamp: u32,
+    /// The unit's KLV items, back to back.
+    pub data: Vec<u8>,
+}
+
+impl KlvUnit {
+    pub fn items(&self) -> Vec<KlvItem<'_>> {
+        // Checked when the unit was completed.
+        parse_klv_items(&self.data).unwrap_or_default()
+    }
+}
+
+// Unit being received.
+#[derive(Debug)]
+struct PendingUnit {
+    timestamp: u32,
+    next_seq: u16,
+    data: Vec<u8>,
+    error: Option<KlvError>,
+}
+
+/// Rebuilds KLV units from the packets of one RTP stream.
+///
+/// A unit whose last packet is lost is dropped when the next unit starts;
+/// like other dropped units it is counted by
+/// [`dropped_units`](Self::dropped_units).
+#[derive(Debug)]
+pub struct KlvDepacketizer {
+    pending: Option<PendingUnit>,
+    max_unit_bytes: usize,
+    dropped: u64,
+}
+
+impl Default for KlvDepacketizer {
+    fn default() -> Self {
+        Self {
+            pending: None,
+            max_unit_bytes: MAX_UNIT_BYTES,
+            dropped: 0,
+        }
+    }
+}
+
+impl KlvDepacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bounds units by `limits.max_frame_bytes`.
+    pub fn with_limits(limits: &Limits) -> Self {
+        Self {
+            max_unit_bytes: limits.max_frame_bytes,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a packet; returns the unit it completes, or why that unit was
+    /// dropped.
+    pub fn push_packet(&mut self, pkt: &RtpPacket<'_>) -> Option<Result<KlvUnit, KlvError>> {
+        let timestamp = pkt.header.timestamp;
+        let seq = pkt.header.sequence_number;
+        let mut unit = match self.pending.take() {
+            Some(mut unit) if unit.timestamp == timestamp => {
+                if seq != unit.next_seq {
+                    unit.error
+                        .get_or_insert(KlvError::MissingPacket { timestamp });
+                }
+                unit
+            }
+            previous => {
+                if let Some(_previous) = previous {
+                    trace_event!(
+                        DEBUG,
+                        timestamp = _previous.timestamp,
+                        "KLV unit lost its last packet"
+                    );
+                    self.dropped += 1;
+                }
+                PendingUnit {
+                    timestamp,
+                    next_seq: seq,
+                    data: Vec::new(),
+                    error: None,
+                }
+            }
+        };
+        unit.next_seq = seq.wrapping_add(1);
+        if unit.error.is_none() {
+            if unit.data.len() + pkt.payload.len() > self.max_unit_bytes {
+                unit.error = Some(KlvError::TooLarge { timestamp });
+                unit.data = Vec::new();
+            } else {
+                unit.data.extend_from_slice(pkt.payload);
+            }
+        }
+        if !pkt.header.marker {
+            self.pending = Some(unit);
+            return None;
+        }
+        let result = match unit.error {
+            Some(error) => Err(error),
+            None => match parse_klv_items(&unit.data) {
+                Ok(_) => Ok(KlvUnit {
+                    timestamp,
+                    data: unit.data,
+                }),
+                Err(offset) => Err(KlvError::Malformed { timestamp, offset }),
+            },
+        };
+        if let Err(_e) = &result {
+            trace_event!(DEBUG, timestamp, error = %_e, "KLV unit dropped");
+            self.dropped += 1;
+        }
+        Some(result)
+    }
+
+    /// Units dropped so far, including those returned as errors.
+    pub fn dropped_units(&self) -> u64 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::RtpPacketBuilder;
+
+    fn item(tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut item = UL_PREFIX.to_vec();
+        item.extend_from_slice(&[0x02, 0x0B, 0x01, 0x01, 0x0E, 0x01, 0x03, 0x01, 0x01, 0x00]);
+        item.extend_from_slice(&[0x00, tag]);
+        // Long form length, as MISB encoders commonly write.
+        item.extend_from_slice(&[0x81, value.len() as u8]);
+        item.extend_from_slice(value);
+        item
+    }
+
+    fn packets(unit: &[u8], timestamp: u32, first_seq: u16, size: usize) -> Vec<Vec<u8>> {
+        let chunks: Vec<_> = unit.chunks(size).collect();
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                RtpPacketBuilder::new()
+                    .marker(i + 1 == chunks.len())
+                    .sequence_number(first_seq + i as u16)
+                    .timestamp(timestamp)
+                    .payload(chunk)
+                    .build()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reassembles_units_split_across_packets() {
+        let unit = [item(1, &[0xAA; 200]), item(2, b"ISR")].concat();
+        let mut klv = KlvDepacketizer::new();
+        let mut units = Vec::new();
+        for bytes in packets(&unit, 9000, 1, 64) {
+            units.extend(klv.push_packet(&RtpPacket::parse(&bytes).unwrap()));
+        }
+        let [Ok(completed)] = &units[..] else {
+            panic!("expected one unit, got {:?}", units);
+        };
+        assert_eq!(completed.data, unit);
+        let items = completed.items();
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].key[15], items[1].value), (1, &b"ISR"[..]));
+    }
+
+    #[test]
+    fn drops_units_with_lost_or_truncated_packets() {
+        let unit = item(1, &[0x55; 100]);
+        let mut klv = KlvDepacketizer::new();
+        let lossy = packets(&unit, 0, 1, 40);
+        let mut results = Vec::new();
+        for bytes in [&lossy[0], &lossy[2]] {
+            results.extend(klv.push_packet(&RtpPacket::parse(bytes).unwrap()));
+        }
+        // The first packet of the next unit is lost.
+        for bytes in &packets(&unit, 3000, 4, 40)[1..] {
+            results.extend(klv.push_packet(&RtpPacket::parse(bytes).unwrap()));
+        }
+        assert_eq!(
+            results,
+            [
+                Err(KlvError::MissingPacket { timestamp: 0 }),
+                Err(KlvError::Malformed {
+                    timestamp: 3000,
+                    offset: 0
+                })
+            ]
+        );
+        assert_eq!(klv.dropped_units(), 2);
+        assert_eq!(read_ber_length(&[0x82, 0x01, 0x00]), Some((256, 3)));
+    }
+}
@@ -1,10 +1,168 @@
+//! RTP depacketization and frame reassembly for video streams.
+//!
+//! The public API is layered; each layer re-exports the types it is made of
+//! so that downstream code does not depend on internal module paths:
+//!
+//! - [`packet`]: RTP header, header extension and RTCP parsing.
+//! - [`codec`]: codec identification and payload format parsing.
+//! - [`frame`]: frame boundary detection, reassembly and packetization.
+//! - [`session`]: handling many streams at once.
+//!
+//! [`prelude`] gathers the handful of types most applications need.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[macro_use]
+mod macros;
+
 pub mod analyze;
+pub mod anc;
+pub mod bitstream;
+pub mod bwe;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod captions;
+#[cfg(feature = "std")]
+pub mod capture;
+pub mod clock;
+pub mod cn;
 pub mod codecs;
+#[cfg(feature = "std")]
+pub mod conformance;
+pub mod demux;
+pub mod dump;
+#[cfg(feature = "json")]
+pub mod export;
+pub mod extensions;
 pub mod guess;
+pub mod hdr;
+pub mod jpeg;
+pub mod klv;
+pub mod limits;
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod net;
+pub mod opus;
+pub mod packetize;
+#[cfg(feature = "std")]
+pub mod pipeline;
+pub mod prelude;
+pub mod quality;
+pub mod raw;
 pub mod reassemble;
+#[cfg(feature = "std")]
+pub mod replay;
+pub mod roq;
+pub mod rtcp;
 pub mod rtp;
+pub mod session;
+pub mod sframe;
+pub mod shard;
+#[cfg(feature = "std")]
+pub mod sim;
+pub mod stats;
+pub mod sync;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod timeline;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "webrtc")]
+pub mod webrtc;
 
 pub use analyze::{FrameAnalyzer, FrameBoundary};
-pub use codecs::Codec;
-pub use reassemble::FrameReassembler;
-pub use rtp::{RtpError, RtpHeader, RtpPacket};
+pub use codecs::{Codec, ParseCodecError};
+pub use limits::{LimitExceeded, Limits};
+pub use packetize::{FramePacketizer, Packetizer, RtpSequencer};
+pub use reassemble::{
+    DropReason, FrameReassembler, FrameReassemblerBuilder, FrameStatus, PushResult,
+    ReassemblerConfig,
+};
+pub use rtp::{CsrcList, RtpError, RtpHeader, RtpPacket};
+
+/// RTP packet, header extension and RTCP parsing.
+pub mod packet {
+    pub use crate::demux::{demux, DemuxError, Demuxed};
+    pub use crate::extensions::{
+        AbsCaptureTime, AbsSendTime, AudioLevel, Camera, CsrcAudioLevels, ExtensionError,
+        ExtensionKind, ExtensionMap, ExtensionValue, LayerResolution, RtpStreamAllocation,
+        SpatialLayerAllocation, StreamId, StreamTag, TransportSequenceNumber,
+        VideoLayersAllocation, VideoOrientation,
+    };
+    pub use crate::limits::{Limit, LimitExceeded, Limits};
+    pub use crate::roq::{RoqError, RoqStreamDecoder};
+    pub use crate::rtcp::{
+        fir, generic_nack, pli, receiver_reference_time, AppPacket, ByePacket, CompoundRtcp, Dlrr,
+        DlrrItem, FirPacket, FirRequest, GenericNack, LossRle, PacketReport, PacketStatus, Pli,
+        Remb, ReportBlocks, RrPacket, RtcpError, RtcpPacket, RtcpPackets, SdesChunk, SdesChunks,
+        SdesItem, SdesPacket, SrPacket, TransportFeedback, VoipMetrics, XrBlock, XrPacket,
+    };
+    pub use crate::rtp::{
+        CsrcList, ExtensionElement, ExtensionElements, RtpError, RtpExtension, RtpHeader,
+        RtpPacket, TooManyCsrcs,
+    };
+}
+
+/// Codec identification and payload format parsing.
+pub mod codec {
+    pub use crate::cn::{is_comfort_noise, CnError, ComfortNoise, CN_PAYLOAD_TYPE};
+    pub use crate::codecs::{
+        av1::{Av1Error, Av1PayloadHdr, ObuHeader},
+        avc::{
+            mvc_views, AvcError, AvcMvcHeader, AvcNalKind, AvcPps, AvcSliceHeader, AvcSliceSummary,
+            AvcSps,
+        },
+        custom::{CodecRegistry, Depacketizer},
+        h263::{
+            H263Depacketizer, H263Error, H263Frame, H263PayloadHeader, H263PictureHeader, H263Vrc,
+        },
+        hevc::{HevcError, HevcHrd, HevcNalKind, HevcSps},
+        sei::{
+            parse_time_code, sei_messages, sei_rbsp, ClockTimestamp, FrameFieldInfo, HevcPicTiming,
+            RecoveryPoint, SeiMessage, SEI_PIC_TIMING, SEI_RECOVERY_POINT, SEI_TIME_CODE,
+            SEI_USER_DATA_REGISTERED,
+        },
+        split_annex_b,
+        vp9::{Vp9ColorConfig, Vp9Error, Vp9PayloadDesc, Vp9ScalabilityStructure, Vp9StreamParams},
+        Codec, ParseCodecError,
+    };
+    pub use crate::guess::guess_codec;
+    pub use crate::jpeg::{
+        JpegDepacketizer, JpegError, JpegFrame, JpegHeader, QuantizationTables, RestartHeader,
+    };
+    pub use crate::klv::{KlvDepacketizer, KlvError, KlvItem, KlvUnit};
+    pub use crate::opus::{
+        OpusDepacketizer, OpusError, OpusEvent, OpusMode, OpusPacketInfo, OpusToc,
+    };
+    pub use crate::raw::{
+        PixelGroup, RawVideoDepacketizer, RawVideoError, RawVideoFormat, RawVideoFrame, SampleRow,
+        Sampling,
+    };
+}
+
+/// Frame boundary detection, reassembly and packetization.
+pub mod frame {
+    pub use crate::analyze::{FrameAnalyzer, FrameBoundary};
+    pub use crate::anc::{
+        AncDepacketizer, AncError, AncField, AncFrame, AncPacket, AncPayload, Timecode,
+    };
+    pub use crate::captions::{CcData, CcType, FrameCaptions};
+    pub use crate::hdr::{
+        is_dolby_vision_nal, DolbyVisionRpu, HdrMetadata, HdrMetadataKind,
+        HEVC_NAL_DOLBY_VISION_EL, HEVC_NAL_DOLBY_VISION_RPU,
+    };
+    pub use crate::packetize::{FramePacketizer, Packetizer, PacketizerConfig, RtpSequencer};
+    pub use crate::reassemble::{
+        AssembledFrame, Av1Frame, Av1Reassembler, AvcConfig, AvcFrame, AvcReassembler, CodecConfig,
+        ConfigError, Decimation, Delivery, DropReason, EventKind, EvictionCause, FrameDefect,
+        FrameReassembler, FrameReassemblerBuilder, FrameStatus, HevcConfig, HevcFrame,
+        HevcReassembler, MalformedReason, NalUnit, OutputFormat, OverflowPolicy, ParameterSetMode,
+        PushResult, QuarantinedPayload, ReassemblerConfig, ReassemblerEvent, ReorderConfig, Resync,
+        SsrcPolicy, StartCodes, Trace, TraceError, TraceReplay, Vp9Frame, Vp9Reassembler,
+    };
+    pub use crate::sframe::{
+        FrameDecryptor, SframeDetector, SframeError, SframeHeader, SframeMode,
+    };
+}
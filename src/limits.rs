@@ -0,0 +1,123 @@
+//! Resource limits for untrusted input.
+//!
+//! Every limit of [`Limits`] bounds memory or work an attacker controls
+//! with crafted packets. They are opt-in:
+//! [`RtpPacket::parse_with_limits`](crate::rtp::RtpPacket::parse_with_limits)
+//! checks header fields, [`FrameReassemblerBuilder::limits`] the buffered
+//! frames and aggregation packets, and [`StreamSet::set_limits`] the number
+//! of streams tracked.
+//!
+//! [`FrameReassemblerBuilder::limits`]: crate::reassemble::FrameReassemblerBuilder::limits
+//! [`StreamSet::set_limits`]: crate::shard::StreamSet::set_limits
+
+use crate::rtp::MAX_CSRCS;
+
+/// Upper bounds on what a packet or stream may make this crate hold.
+///
+/// The defaults suit video from untrusted peers; raise them for larger
+/// frames or many concurrent streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Limits {
+    /// SSRCs tracked at once by a `StreamSet`.
+    pub max_ssrcs: usize,
+    /// Bytes of an RTP header extension block.
+    pub max_extension_bytes: usize,
+    /// CSRCs in an RTP header.
+    pub max_csrcs: usize,
+    /// Bytes of an assembled frame.
+    pub max_frame_bytes: usize,
+    /// Frames buffered at once per stream.
+    pub max_pending_frames: usize,
+    /// NAL units of an STAP-A/AP or OBU elements of an AV1 packet.
+    pub max_aggregation_items: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_ssrcs: 1024,
+            max_extension_bytes: 1024,
+            max_csrcs: MAX_CSRCS,
+            max_frame_bytes: 8 << 20,
+            max_pending_frames: 64,
+            max_aggregation_items: 64,
+        }
+    }
+}
+
+impl Limits {
+    pub fn max(&self, limit: Limit) -> usize {
+        match limit {
+            Limit::Ssrcs => self.max_ssrcs,
+            Limit::ExtensionBytes => self.max_extension_bytes,
+            Limit::Csrcs => self.max_csrcs,
+            Limit::FrameBytes => self.max_frame_bytes,
+            Limit::PendingFrames => self.max_pending_frames,
+            Limit::AggregationItems => self.max_aggregation_items,
+        }
+    }
+
+    /// Fails when `value` is above the `limit`.
+    pub fn check(&self, limit: Limit, value: usize) -> Result<(), LimitExceeded> {
+        let max = self.max(limit);
+        if value > max {
+            return Err(LimitExceeded { limit, value, max });
+        }
+        Ok(())
+    }
+}
+
+/// One of the bounds of [`Limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Limit {
+    Ssrcs,
+    ExtensionBytes,
+    Csrcs,
+    FrameBytes,
+    PendingFrames,
+    AggregationItems,
+}
+
+impl Limit {
+    /// Short description, also the `limit` label of metrics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Limit::Ssrcs => "ssrcs",
+            Limit::ExtensionBytes => "extension bytes",
+            Limit::Csrcs => "csrcs",
+            Limit::FrameBytes => "frame bytes",
+            Limit::PendingFrames => "pending frames",
+            Limit::AggregationItems => "aggregation items",
+        }
+    }
+}
+
+impl core::fmt::Display for Limit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Input went past one of the [`Limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub value: usize,
+    pub max: usize,
+}
+
+impl core::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} {} exceed the limit of {}",
+            self.value, self.limit, self.max
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LimitExceeded {}
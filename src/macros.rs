@@ -0,0 +1,9 @@
+// Emits a `tracing` event at the given level when the `tracing` feature is
+// enabled, and compiles to nothing otherwise:
+// `trace_event!(DEBUG, timestamp = ts, "frame dropped")`.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($arg)+);
+    }};
+}
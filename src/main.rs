@@ -1,3 +1,317 @@
-fn main() {
-    println!("Hello, world!");
+use rtpar::conformance::Corpus;
+use rtpar::prelude::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::UdpSocket;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const USAGE: &str = "\
+usage: rtpar listen <addr:port> [options]
+       rtpar conformance <dir> [--bless]
+
+Binds a UDP socket, reassembles incoming RTP packets into frames through a
+jitter buffer and writes them to stdout or to a series of rolling output files.
+
+options:
+  --codec <avc|hevc|vp9|av1>  skip codec guessing and force a codec
+  --output <prefix>           write frames to <prefix>-NNNNN.<ext> instead of stdout
+  --frames-per-file <n>       frames written before rolling to a new file (default 300)
+  --latency <ms>              how long to wait for reordered packets (default 100)
+
+conformance reassembles every capture in <dir> and compares the result with
+the golden elementary stream next to it; --bless rewrites the golden files.";
+
+const DEFAULT_FRAMES_PER_FILE: u64 = 300;
+const DEFAULT_LATENCY_MS: u64 = 100;
+const MAX_DATAGRAM: usize = 65_536;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ListenArgs {
+    addr: String,
+    codec: Option<Codec>,
+    output: Option<String>,
+    frames_per_file: u64,
+    latency: Duration,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("listen") => match parse_listen_args(&args[1..]) {
+            Ok(listen) => match run_listen(&listen) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("rtpar: {}", e);
+                    ExitCode::FAILURE
+                }
+            },
+            Err(msg) => {
+                eprintln!("rtpar: {}\n\n{}", msg, USAGE);
+                ExitCode::from(2)
+            }
+        },
+        Some("conformance") => match parse_conformance_args(&args[1..]) {
+            Ok((dir, bless)) => run_conformance(dir, bless),
+            Err(msg) => {
+                eprintln!("rtpar: {}\n\n{}", msg, USAGE);
+                ExitCode::from(2)
+            }
+        },
+        Some("-h") | Some("--help") | Some("help") => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_listen_args(args: &[String]) -> Result<ListenArgs, String> {
+    let mut addr = None;
+    let mut codec = None;
+    let mut output = None;
+    let mut frames_per_file = DEFAULT_FRAMES_PER_FILE;
+    let mut latency = Duration::from_millis(DEFAULT_LATENCY_MS);
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--codec" => {
+                let value = iter.next().ok_or("--codec requires a value")?;
+                codec = Some(value.parse::<Codec>().map_err(|e| e.to_string())?);
+            }
+            "--output" => {
+                output = Some(iter.next().ok_or("--output requires a value")?.clone());
+            }
+            "--frames-per-file" => {
+                let value = iter.next().ok_or("--frames-per-file requires a value")?;
+                frames_per_file = value
+                    .parse()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or(format!("invalid frame count '{}'", value))?;
+            }
+            "--latency" => {
+                let value = iter.next().ok_or("--latency requires a value")?;
+                latency = value
+                    .parse()
+                    .ok()
+                    .filter(|ms| *ms > 0)
+                    .map(Duration::from_millis)
+                    .ok_or(format!("invalid latency '{}'", value))?;
+            }
+            other if other.starts_with("--") => {
+                return Err(format!("unknown option '{}'", other));
+            }
+            other => {
+                if addr.is_some() {
+                    return Err(format!("unexpected argument '{}'", other));
+                }
+                addr = Some(other.to_string());
+            }
+        }
+    }
+
+    Ok(ListenArgs {
+        addr: addr.ok_or("missing <addr:port>")?,
+        codec,
+        output,
+        frames_per_file,
+        latency,
+    })
+}
+
+fn parse_conformance_args(args: &[String]) -> Result<(&str, bool), String> {
+    match args {
+        [dir] => Ok((dir, false)),
+        [dir, flag] | [flag, dir] if flag == "--bless" => Ok((dir, true)),
+        [] => Err("missing <dir>".to_string()),
+        _ => Err(format!("unexpected arguments '{}'", args.join(" "))),
+    }
+}
+
+fn run_conformance(dir: &str, bless: bool) -> ExitCode {
+    let corpus = match Corpus::load(dir) {
+        Ok(corpus) => corpus,
+        Err(e) => {
+            eprintln!("rtpar: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if bless {
+        if let Err(e) = corpus.bless() {
+            eprintln!("rtpar: {}", e);
+            return ExitCode::FAILURE;
+        }
+        eprintln!("rtpar: blessed {} cases", corpus.cases.len());
+        return ExitCode::SUCCESS;
+    }
+    let report = corpus.run();
+    println!("{}", report);
+    if report.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn file_extension(codec: Option<Codec>) -> &'static str {
+    match codec {
+        Some(Codec::Avc) => "h264",
+        Some(Codec::Hevc) => "h265",
+        Some(Codec::Vp9) => "vp9",
+        Some(Codec::Av1) => "obu",
+        _ => "bin",
+    }
+}
+
+// Writes frames to numbered files, starting a new file every `frames_per_file` frames.
+struct RollingWriter {
+    prefix: String,
+    frames_per_file: u64,
+    file_index: u64,
+    frames_in_file: u64,
+    current: Option<BufWriter<File>>,
+}
+
+impl RollingWriter {
+    fn new(prefix: String, frames_per_file: u64) -> Self {
+        Self {
+            prefix,
+            frames_per_file,
+            file_index: 0,
+            frames_in_file: 0,
+            current: None,
+        }
+    }
+
+    fn write_frame(&mut self, frame: &[u8], codec: Option<Codec>) -> io::Result<()> {
+        if self.frames_in_file >= self.frames_per_file {
+            if let Some(mut file) = self.current.take() {
+                file.flush()?;
+            }
+            self.frames_in_file = 0;
+        }
+        let file = match self.current {
+            Some(ref mut file) => file,
+            None => {
+                let path = format!(
+                    "{}-{:05}.{}",
+                    self.prefix,
+                    self.file_index,
+                    file_extension(codec)
+                );
+                self.file_index += 1;
+                eprintln!("rtpar: writing {}", path);
+                self.current.insert(BufWriter::new(File::create(path)?))
+            }
+        };
+        file.write_all(frame)?;
+        self.frames_in_file += 1;
+        Ok(())
+    }
+}
+
+fn run_listen(args: &ListenArgs) -> io::Result<()> {
+    let socket = UdpSocket::bind(&args.addr)?;
+    eprintln!("rtpar: listening on {}", socket.local_addr()?);
+
+    // Resilient mode hands out frames once complete or once `latency` has
+    // passed, flagging what is missing, so a decoder can conceal the loss.
+    let mut builder = FrameReassembler::builder()
+        .latency(args.latency)
+        .resilient(true);
+    if let Some(codec) = args.codec {
+        builder = builder.codec(codec);
+    }
+    let mut reassembler = builder
+        .build()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+
+    let mut rolling = args
+        .output
+        .as_ref()
+        .map(|prefix| RollingWriter::new(prefix.clone(), args.frames_per_file));
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    let mut buf = vec![0u8; MAX_DATAGRAM];
+    loop {
+        let (len, _from) = socket.recv_from(&mut buf)?;
+        let pkt = match RtpPacket::parse(&buf[..len]) {
+            Ok(pkt) => pkt,
+            Err(e) => {
+                eprintln!("rtpar: dropping datagram: {}", e);
+                continue;
+            }
+        };
+        reassembler.push_packet(&pkt);
+        while let Some(frame) = reassembler.pop_frame() {
+            if frame.corrupted {
+                eprintln!(
+                    "rtpar: frame {} is damaged, {} packets lost",
+                    frame.timestamp, frame.lost_packets
+                );
+            }
+            match rolling.as_mut() {
+                Some(writer) => writer.write_frame(&frame.data, reassembler.codec())?,
+                None => {
+                    stdout.write_all(&frame.data)?;
+                    stdout.flush()?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_listen_with_options() {
+        let parsed = parse_listen_args(&args(&[
+            "127.0.0.1:5004",
+            "--codec",
+            "h264",
+            "--output",
+            "cam",
+            "--frames-per-file",
+            "10",
+            "--latency",
+            "250",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.addr, "127.0.0.1:5004");
+        assert_eq!(parsed.codec, Some(Codec::Avc));
+        assert_eq!(parsed.output.as_deref(), Some("cam"));
+        assert_eq!(parsed.frames_per_file, 10);
+        assert_eq!(parsed.latency, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn parse_listen_rejects_bad_input() {
+        assert!(parse_listen_args(&args(&[])).is_err());
+        assert!(parse_listen_args(&args(&["0.0.0.0:5004", "--codec", "mpeg2"])).is_err());
+        assert!(parse_listen_args(&args(&["0.0.0.0:5004", "--frames-per-file", "0"])).is_err());
+        assert!(parse_listen_args(&args(&["0.0.0.0:5004", "--latency", "0"])).is_err());
+        assert!(parse_listen_args(&args(&["0.0.0.0:5004", "--bogus"])).is_err());
+    }
+
+    #[test]
+    fn parse_conformance_args_accepts_bless_either_side() {
+        let list = args(&["corpus", "--bless"]);
+        assert_eq!(parse_conformance_args(&list), Ok(("corpus", true)));
+        let list = args(&["--bless", "corpus"]);
+        assert_eq!(parse_conformance_args(&list), Ok(("corpus", true)));
+        assert!(parse_conformance_args(&args(&[])).is_err());
+        assert!(parse_conformance_args(&args(&["a", "b"])).is_err());
+    }
 }
@@ -0,0 +1,268 @@
+//! Reporting counters, gauges and histograms to a monitoring system.
+//!
+//! Components given a [`MetricsSink`] (through `set_metrics` on
+//! [`FrameReassembler`](crate::FrameReassembler),
+//! [`FrameAnalyzer`](crate::FrameAnalyzer),
+//! [`StreamSet`](crate::session::StreamSet) and
+//! [`Session`](crate::session::Session)) call it as packets go through;
+//! without one nothing is reported. Names follow Prometheus conventions and
+//! start with `rtpar_`. Label values are static, so reporting never
+//! allocates. [`PrometheusText`] (`std`) collects everything into the
+//! Prometheus text exposition format.
+
+use alloc::sync::Arc;
+
+/// Label pairs of one report, e.g. `&[("reason", "sequence gap")]`.
+pub type Labels<'a> = &'a [(&'static str, &'static str)];
+
+/// Receiver of metrics. Shared between components, so it takes `&self`;
+/// every method defaults to doing nothing.
+pub trait MetricsSink: Send + Sync {
+    /// Adds `value` to a monotonically increasing counter.
+    fn counter(&self, _name: &'static str, _labels: Labels<'_>, _value: u64) {}
+    /// Sets the current value of a gauge.
+    fn gauge(&self, _name: &'static str, _labels: Labels<'_>, _value: i64) {}
+    /// Records one observation, e.g. a frame size.
+    fn histogram(&self, _name: &'static str, _labels: Labels<'_>, _value: u64) {}
+}
+
+/// Sink that discards everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl MetricsSink for NoopMetrics {}
+
+// Optional sink held by an instrumented component.
+#[derive(Clone, Default)]
+pub(crate) struct Metrics(Option<Arc<dyn MetricsSink>>);
+
+impl core::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(if self.0.is_some() {
+            "Metrics(Some(..))"
+        } else {
+            "Metrics(None)"
+        })
+    }
+}
+
+impl Metrics {
+    pub(crate) fn new(sink: Arc<dyn MetricsSink>) -> Self {
+        Self(Some(sink))
+    }
+
+    pub(crate) fn sink(&self) -> Option<&Arc<dyn MetricsSink>> {
+        self.0.as_ref()
+    }
+
+    pub(crate) fn counter(&self, name: &'static str, labels: Labels<'_>, value: u64) {
+        if let Some(sink) = &self.0 {
+            sink.counter(name, labels, value);
+        }
+    }
+
+    pub(crate) fn gauge(&self, name: &'static str, labels: Labels<'_>, value: i64) {
+        if let Some(sink) = &self.0 {
+            sink.gauge(name, labels, value);
+        }
+    }
+
+    pub(crate) fn histogram(&self, name: &'static str, labels: Labels<'_>, value: u64) {
+        if let Some(sink) = &self.0 {
+            sink.histogram(name, labels, value);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub use prometheus::PrometheusText;
+
+#[cfg(feature = "std")]
+mod prometheus {
+    use super::{Labels, MetricsSink};
+    use crate::stats::Histogram;
+    use std::{collections::BTreeMap, fmt::Write, string::String, sync::Mutex, vec::Vec};
+
+    type Key = (&'static str, Vec<(&'static str, &'static str)>);
+
+    #[derive(Debug, Default)]
+    struct Series {
+        counters: BTreeMap<Key, u64>,
+        gauges: BTreeMap<Key, i64>,
+        // Histograms with the sum of their values.
+        histograms: BTreeMap<Key, (Histogram, u64)>,
+    }
+
+    /// Sink keeping the latest values in memory and rendering them in the
+    /// Prometheus text format, for a `/metrics` endpoint. Histograms are
+    /// exposed as summaries with their 0.5, 0.95 and 0.99 quantiles.
+    #[derive(Debug, Default)]
+    pub struct PrometheusText {
+        series: Mutex<Series>,
+    }
+
+    impl PrometheusText {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Every series, grouped by metric name.
+        pub fn render(&self) -> String {
+            let series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+            let mut out = String::new();
+            let mut last = "";
+            for ((name, labels), value) in &series.counters {
+                type_line(&mut out, &mut last, name, "counter");
+                let _ = writeln!(out, "{}{} {}", name, label_set(labels, None), value);
+            }
+            for ((name, labels), value) in &series.gauges {
+                type_line(&mut out, &mut last, name, "gauge");
+                let _ = writeln!(out, "{}{} {}", name, label_set(labels, None), value);
+            }
+            for ((name, labels), (histogram, sum)) in &series.histograms {
+                type_line(&mut out, &mut last, name, "summary");
+                for (quantile, q) in [("0.5", 0.5), ("0.95", 0.95), ("0.99", 0.99)] {
+                    let value = histogram.quantile(q).unwrap_or(0);
+                    let labels = label_set(labels, Some(quantile));
+                    let _ = writeln!(out, "{}{} {}", name, labels, value);
+                }
+                let labels = label_set(labels, None);
+                let _ = writeln!(out, "{}_sum{} {}", name, labels, sum);
+                let _ = writeln!(out, "{}_count{} {}", name, labels, histogram.count());
+            }
+            out
+        }
+    }
+
+    impl MetricsSink for PrometheusText {
+        fn counter(&self, name: &'static str, labels: Labels<'_>, value: u64) {
+            let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+            *series.counters.entry((name, labels.to_vec())).or_default() += value;
+        }
+
+        fn gauge(&self, name: &'static str, labels: Labels<'_>, value: i64) {
+            let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+            series.gauges.insert((name, labels.to_vec()), value);
+        }
+
+        fn histogram(&self, name: &'static str, labels: Labels<'_>, value: u64) {
+            let mut series = self.series.lock().unwrap_or_else(|e| e.into_inner());
+            let (histogram, sum) = series
+                .histograms
+                .entry((name, labels.to_vec()))
+                .or_default();
+            histogram.record(value);
+            *sum = sum.saturating_add(value);
+        }
+    }
+
+    fn type_line(out: &mut String, last: &mut &'static str, name: &'static str, kind: &str) {
+        if *last != name {
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            *last = name;
+        }
+    }
+
+    fn label_set(labels: &[(&str, &str)], quantile: Option<&str>) -> String {
+        let pairs = labels
+            .iter()
+            .copied()
+            .chain(quantile.map(|q| ("quantile", q)));
+        let pairs: Vec<_> = pairs
+            .map(|(key, value)| {
+                let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+                std::format!("{}=\"{}\"", key, value)
+            })
+            .collect();
+        if pairs.is_empty() {
+            String::new()
+        } else {
+            std::format!("{{{}}}", pairs.join(","))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::Codec,
+        rtp::RtpPacket,
+        session::{Session, StreamSet},
+        test_utils::RtpPacketBuilder,
+        test_utils::{avc, fixtures::AVC_NON_IDR, StreamBuilder},
+        FrameReassembler,
+    };
+
+    #[test]
+    fn reassembler_reports_into_prometheus_text() {
+        let sink = Arc::new(PrometheusText::new());
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        r.set_metrics(sink.clone());
+        let packets = StreamBuilder::new(1)
+            .frame(&avc::fu_a(AVC_NON_IDR, 3))
+            .frame(&avc::fu_a(AVC_NON_IDR, 3))
+            .build();
+        for (i, bytes) in packets.iter().enumerate() {
+            // The second frame loses its middle packet.
+            if i != packets.len() - 2 {
+                r.push_packet(&RtpPacket::parse(bytes).unwrap());
+            }
+        }
+        let text = sink.render();
+        assert!(
+            text.contains(
+                "# TYPE rtpar_frames_completed_total counter\nrtpar_frames_completed_total 1\n"
+            ),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("rtpar_frames_dropped_total{reason=\"sequence gap\"} 1\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("# TYPE rtpar_frame_bytes summary\n"),
+            "{}",
+            text
+        );
+        assert!(text.contains("rtpar_frame_bytes_count 1\n"), "{}", text);
+        assert!(
+            text.contains("rtpar_analyzer_frame_starts_total 2\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn session_hands_its_sink_to_new_streams() {
+        let sink = Arc::new(PrometheusText::new());
+        let mut session = Session::new(1);
+        session.set_metrics(sink.clone());
+        session.add_media("0", StreamSet::new());
+        let routed = RtpPacketBuilder::new()
+            .ssrc(7)
+            .extension(0xBEDE, &[0x10, b'0', 0, 0])
+            .payload(&[0x65, 0])
+            .build();
+        let unrouted = RtpPacketBuilder::new().ssrc(8).payload(&[0x65, 0]).build();
+        session.push(&routed).unwrap();
+        session.push(&unrouted).unwrap();
+        let text = sink.render();
+        assert!(
+            text.contains("rtpar_session_unrouted_packets_total 1\n"),
+            "{}",
+            text
+        );
+        assert!(text.contains("rtpar_packets_total 1\n"), "{}", text);
+        assert!(
+            text.contains("# TYPE rtpar_streams gauge\nrtpar_streams 1\n"),
+            "{}",
+            text
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! Socket to frames.
+//!
+//! [`UdpReceiver`] owns a `std::net::UdpSocket` and [`TokioUdpReceiver`]
+//! (`tokio` feature) a `tokio::net::UdpSocket`. Both read each datagram
+//! into one reused receive buffer and hand it to a [`PacketSink`] — a
+//! [`FrameReassembler`], a per-SSRC [`StreamSet`] or a BUNDLE [`Session`] —
+//! until a frame completes:
+//!
+//! ```no_run
+//! # use rtpar::{net::UdpReceiver, reassemble::FrameReassembler};
+//! let mut rx = UdpReceiver::bind("0.0.0.0:5004", FrameReassembler::new())?;
+//! loop {
+//!     let frame = rx.recv_frame()?;
+//!     # drop(frame);
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use crate::{
+    reassemble::FrameReassembler,
+    rtp::{RtpError, RtpPacket},
+    session::{MediaFrame, Session},
+    shard::StreamSet,
+};
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    vec::Vec,
+};
+
+/// Largest UDP payload.
+const MAX_DATAGRAM: usize = 65_536;
+
+/// Consumer of serialized RTP packets.
+pub trait PacketSink {
+    type Frame;
+
+    /// Parses and routes one datagram; returns the frame it completes.
+    fn push_datagram(&mut self, datagram: &[u8]) -> Result<Option<Self::Frame>, RtpError>;
+}
+
+impl PacketSink for FrameReassembler {
+    type Frame = Vec<u8>;
+
+    fn push_datagram(&mut self, datagram: &[u8]) -> Result<Option<Vec<u8>>, RtpError> {
+        self.push_bytes(datagram)
+    }
+}
+
+impl PacketSink for StreamSet {
+    type Frame = Vec<u8>;
+
+    fn push_datagram(&mut self, datagram: &[u8]) -> Result<Option<Vec<u8>>, RtpError> {
+        Ok(self.push_packet(&RtpPacket::parse(datagram)?))
+    }
+}
+
+impl PacketSink for Session {
+    type Frame = MediaFrame;
+
+    fn push_datagram(&mut self, datagram: &[u8]) -> Result<Option<MediaFrame>, RtpError> {
+        self.push(datagram)
+    }
+}
+
+// Receive buffer and the datagrams that did not parse as RTP.
+#[derive(Debug)]
+struct Intake {
+    buf: Vec<u8>,
+    malformed: u64,
+}
+
+impl Intake {
+    fn new() -> Self {
+        Self {
+            buf: vec![0; MAX_DATAGRAM],
+            malformed: 0,
+        }
+    }
+
+    fn feed<S: PacketSink>(&mut self, sink: &mut S, len: usize) -> Option<S::Frame> {
+        match sink.push_datagram(&self.buf[..len]) {
+            Ok(frame) => frame,
+            Err(_e) => {
+                trace_event!(DEBUG, error = %_e, "malformed datagram skipped");
+                self.malformed += 1;
+                None
+            }
+        }
+    }
+}
+
+/// Blocking receiver over a `std::net::UdpSocket`.
+#[derive(Debug)]
+pub struct UdpReceiver<S> {
+    socket: UdpSocket,
+    sink: S,
+    intake: Intake,
+}
+
+impl<S: PacketSink> UdpReceiver<S> {
+    pub fn bind(addr: impl ToSocketAddrs, sink: S) -> io::Result<Self> {
+        Ok(Self::new(UdpSocket::bind(addr)?, sink))
+    }
+
+    /// Wraps an already configured socket (multicast membership, buffer
+    /// sizes, read timeout).
+    pub fn new(socket: UdpSocket, sink: S) -> Self {
+        Self {
+            socket,
+            sink,
+            intake: Intake::new(),
+        }
+    }
+
+    /// Blocks until a datagram completes a frame. Datagrams that are not
+    /// RTP are skipped and counted in [`malformed`](Self::malformed).
+    pub fn recv_frame(&mut self) -> io::Result<S::Frame> {
+        loop {
+            let len = self.socket.recv(&mut self.intake.buf)?;
+            if let Some(frame) = self.intake.feed(&mut self.sink, len) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// Datagrams skipped because they did not parse.
+    pub fn malformed(&self) -> u64 {
+        self.intake.malformed
+    }
+
+    pub fn into_parts(self) -> (UdpSocket, S) {
+        (self.socket, self.sink)
+    }
+}
+
+/// Async receiver over a `tokio::net::UdpSocket`.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TokioUdpReceiver<S> {
+    socket: tokio::net::UdpSocket,
+    sink: S,
+    intake: Intake,
+}
+
+#[cfg(feature = "tokio")]
+impl<S: PacketSink> TokioUdpReceiver<S> {
+    pub async fn bind(addr: impl tokio::net::ToSocketAddrs, sink: S) -> io::Result<Self> {
+        Ok(Self::new(tokio::net::UdpSocket::bind(addr).await?, sink))
+    }
+
+    pub fn new(socket: tokio::net::UdpSocket, sink: S) -> Self {
+        Self {
+            socket,
+            sink,
+            intake: Intake::new(),
+        }
+    }
+
+    /// Waits until a datagram completes a frame. Cancel safe: a datagram
+    /// is only consumed together with its processing.
+    pub async fn recv_frame(&mut self) -> io::Result<S::Frame> {
+        loop {
+            let len = self.socket.recv(&mut self.intake.buf).await?;
+            if let Some(frame) = self.intake.feed(&mut self.sink, len) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// Datagrams skipped because they did not parse.
+    pub fn malformed(&self) -> u64 {
+        self.intake.malformed
+    }
+
+    pub fn into_parts(self) -> (tokio::net::UdpSocket, S) {
+        (self.socket, self.sink)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::Codec,
+        test_utils::{avc, fixtures::AVC_NON_IDR, StreamBuilder},
+    };
+
+    fn stream() -> Vec<Vec<u8>> {
+        StreamBuilder::new(7)
+            .frame(&avc::fu_a(AVC_NON_IDR, 4))
+            .build()
+    }
+
+    #[test]
+    fn std_receiver_reassembles_datagrams() {
+        let reassembler = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let mut rx = UdpReceiver::bind("127.0.0.1:0", reassembler).unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        tx.send_to(&[0x00, 0x01], rx.local_addr().unwrap()).unwrap();
+        for packet in stream() {
+            tx.send_to(&packet, rx.local_addr().unwrap()).unwrap();
+        }
+        let frame = rx.recv_frame().unwrap();
+        assert!(frame.ends_with(AVC_NON_IDR));
+        assert_eq!(rx.malformed(), 1);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio_receiver_reassembles_datagrams() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut sets = StreamSet::new();
+            sets.set_codec(Some(Codec::Avc));
+            let mut rx = TokioUdpReceiver::bind("127.0.0.1:0", sets).await.unwrap();
+            let tx = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            for packet in stream() {
+                tx.send_to(&packet, rx.local_addr().unwrap()).await.unwrap();
+            }
+            let frame = rx.recv_frame().await.unwrap();
+            assert!(frame.ends_with(AVC_NON_IDR));
+            assert_eq!(rx.sink().len(), 1);
+        });
+    }
+}
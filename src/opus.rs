@@ -0,0 +1,340 @@
+//! Opus over RTP (RFC 7587).
+//!
+//! Each payload is one Opus packet (RFC 6716) and timestamps always run at
+//! 48 kHz. Two features need the receiver's help: SILK and hybrid packets
+//! may carry a low bitrate copy of the previous frame (LBRR, in-band FEC),
+//! which recovers that frame if it was lost, and with DTX the sender stops
+//! sending during silence, leaving timestamp jumps without sequence gaps.
+//! [`OpusDepacketizer`] tells the two apart and queues an [`OpusEvent`] for
+//! every stretch of missing audio.
+
+use crate::rtp::RtpPacket;
+use alloc::collections::VecDeque;
+
+/// Opus packets of at most this many bytes hold no audio; DTX senders
+/// send them to keep the stream alive.
+pub const DTX_PACKET_BYTES: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpusError {
+    /// The payload ends inside `field`.
+    Truncated { field: &'static str },
+    /// Code 3 packets need at least one frame.
+    NoFrames,
+}
+
+impl core::fmt::Display for OpusError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            OpusError::Truncated { field } => write!(f, "Opus packet ends inside {}", field),
+            OpusError::NoFrames => f.write_str("Opus packet has no frames"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OpusError {}
+
+/// Coding mode of an Opus packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OpusMode {
+    Silk,
+    Hybrid,
+    Celt,
+}
+
+/// The table-of-contents byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpusToc {
+    /// Mode, bandwidth and frame size (RFC 6716, section 3.1).
+    pub config: u8,
+    pub stereo: bool,
+    /// Frame count code: 0 one frame, 1 and 2 two, 3 signaled.
+    pub code: u8,
+}
+
+impl OpusToc {
+    pub fn parse(byte: u8) -> Self {
+        Self {
+            config: byte >> 3,
+            stereo: byte & 0x04 != 0,
+            code: byte & 0x03,
+        }
+    }
+
+    pub fn mode(&self) -> OpusMode {
+        match self.config {
+            0..=11 => OpusMode::Silk,
+            12..=15 => OpusMode::Hybrid,
+            _ => OpusMode::Celt,
+        }
+    }
+
+    /// Samples per frame at 48 kHz.
+    pub fn frame_samples(&self) -> u32 {
+        match self.config {
+            // SILK: 10, 20, 40 and 60 ms.
+            0..=11 => [480, 960, 1920, 2880][self.config as usize % 4],
+            // Hybrid: 10 and 20 ms.
+            12..=15 => [480, 960][self.config as usize % 2],
+            // CELT: 2.5, 5, 10 and 20 ms.
+            _ => [120, 240, 480, 960][self.config as usize % 4],
+        }
+    }
+}
+
+/// What an Opus packet holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpusPacketInfo {
+    pub toc: OpusToc,
+    pub frames: u8,
+    /// Duration at 48 kHz.
+    pub samples: u32,
+    /// The packet carries in-band FEC for the frame before it, which a
+    /// decoder recovers by decoding this packet with FEC enabled.
+    pub fec: bool,
+    /// A DTX packet without audio.
+    pub dtx: bool,
+}
+
+impl OpusPacketInfo {
+    pub fn parse(packet: &[u8]) -> Result<Self, OpusError> {
+        let truncated = |field| OpusError::Truncated { field };
+        let (&toc_byte, rest) = packet.split_first().ok_or(truncated("TOC"))?;
+        let toc = OpusToc::parse(toc_byte);
+        let (frames, first) = match toc.code {
+            0 | 1 => (toc.code + 1, rest),
+            2 => {
+                let (len, n) = frame_length(rest).ok_or(truncated("frame length"))?;
+                (2, rest.get(n..n + len).ok_or(truncated("frame"))?)
+            }
+            _ => {
+                let (&count, mut rest) = rest.split_first().ok_or(truncated("frame count"))?;
+                let frames = count & 0x3F;
+                if frames == 0 {
+                    return Err(OpusError::NoFrames);
+                }
+                if count & 0x40 != 0 {
+                    // Padding lengths: 255 adds 254 and continues.
+                    loop {
+                        let (&pad, tail) = rest.split_first().ok_or(truncated("padding"))?;
+                        rest = tail;
+                        if pad != 255 {
+                            break;
+                        }
+                    }
+                }
+                if count & 0x80 != 0 {
+                    // VBR: lengths of all frames but the last.
+                    let mut at = 0;
+                    for _ in 1..frames {
+                        at += frame_length(&rest[at..])
+                            .ok_or(truncated("frame length"))?
+                            .1;
+                    }
+                    rest = &rest[at..];
+                }
+                (frames, rest)
+            }
+        };
+        Ok(Self {
+            toc,
+            frames,
+            samples: toc.frame_samples() * frames as u32,
+            fec: has_lbrr(&toc, first),
+            dtx: packet.len() <= DTX_PACKET_BYTES,
+        })
+    }
+}
+
+// A frame length of one or two bytes; returns it and the bytes it took.
+fn frame_length(buf: &[u8]) -> Option<(usize, usize)> {
+    match *buf.first()? {
+        len @ 0..=251 => Some((len as usize, 1)),
+        len => Some((len as usize + 4 * *buf.get(1)? as usize, 2)),
+    }
+}
+
+// The LBRR flag of each channel follows its VAD flags, one per 20 ms SILK
+// frame, at the very start of the first frame's range coded data, where
+// each of these equiprobable flags is one bit.
+fn has_lbrr(toc: &OpusToc, first_frame: &[u8]) -> bool {
+    if toc.mode() == OpusMode::Celt {
+        return false;
+    }
+    let Some(&byte) = first_frame.first() else {
+        return false;
+    };
+    let silk_frames = (toc.frame_samples() / 960).max(1);
+    let channels = 1 + toc.stereo as u32;
+    (1..=channels).any(|n| byte & (0x80 >> (n * (silk_frames + 1) - 1)) != 0)
+}
+
+/// Audio missing before a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OpusEvent {
+    /// Packets from `timestamp` on were lost. When `recoverable`, the
+    /// packet that follows carries FEC for these samples: decode it with
+    /// FEC enabled and a frame size of `samples`, then decode it normally.
+    /// Otherwise conceal them.
+    Lost {
+        timestamp: u32,
+        samples: u32,
+        recoverable: bool,
+    },
+    /// The sender paused for DTX: nothing was lost, but `samples` of comfort
+    /// noise or concealment are due from `timestamp` on.
+    Dtx { timestamp: u32, samples: u32 },
+}
+
+/// Parses the packets of one Opus stream and reports missing audio.
+#[derive(Debug, Default)]
+pub struct OpusDepacketizer {
+    // Sequence number, timestamp and duration of the last packet.
+    last: Option<(u16, u32, u32)>,
+    events: VecDeque<OpusEvent>,
+}
+
+impl OpusDepacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a packet, queueing events for the audio missing before it.
+    /// Packets older than the last one are rejected as `Ok(None)`; the
+    /// decoder has concealed them already.
+    pub fn push_packet(
+        &mut self,
+        pkt: &RtpPacket<'_>,
+    ) -> Result<Option<OpusPacketInfo>, OpusError> {
+        let info = OpusPacketInfo::parse(pkt.payload)?;
+        let seq = pkt.header.sequence_number;
+        let timestamp = pkt.header.timestamp;
+        if let Some((last_seq, last_ts, last_samples)) = self.last {
+            let seq_gap = seq.wrapping_sub(last_seq) as i16;
+            if seq_gap <= 0 {
+                return Ok(None);
+            }
+            let expected = last_ts.wrapping_add(last_samples);
+            let missing = timestamp.wrapping_sub(expected) as i32;
+            if missing > 0 {
+                let missing = missing as u32;
+                if seq_gap == 1 {
+                    self.events.push_back(OpusEvent::Dtx {
+                        timestamp: expected,
+                        samples: missing,
+                    });
+                } else {
+                    // FEC only covers the frame right before this packet,
+                    // of the same duration.
+                    let recoverable = if info.fec {
+                        info.samples.min(missing)
+                    } else {
+                        0
+                    };
+                    if missing > recoverable {
+                        self.events.push_back(OpusEvent::Lost {
+                            timestamp: expected,
+                            samples: missing - recoverable,
+                            recoverable: false,
+                        });
+                    }
+                    if recoverable > 0 {
+                        self.events.push_back(OpusEvent::Lost {
+                            timestamp: timestamp.wrapping_sub(recoverable),
+                            samples: recoverable,
+                            recoverable: true,
+                        });
+                    }
+                }
+            }
+        }
+        self.last = Some((seq, timestamp, info.samples));
+        Ok(Some(info))
+    }
+
+    pub fn pop_event(&mut self) -> Option<OpusEvent> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::RtpPacketBuilder;
+
+    fn push(
+        opus: &mut OpusDepacketizer,
+        seq: u16,
+        timestamp: u32,
+        payload: &[u8],
+    ) -> OpusPacketInfo {
+        let bytes = RtpPacketBuilder::new()
+            .sequence_number(seq)
+            .timestamp(timestamp)
+            .payload(payload)
+            .build();
+        opus.push_packet(&RtpPacket::parse(&bytes).unwrap())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn detects_lbrr_in_silk_and_hybrid_packets() {
+        // SILK wideband 20 ms mono: VAD then LBRR in the top bits.
+        let info = OpusPacketInfo::parse(&[9 << 3, 0b0100_0000, 0x12]).unwrap();
+        assert_eq!(
+            (info.toc.mode(), info.samples, info.fec),
+            (OpusMode::Silk, 960, true)
+        );
+        assert!(
+            !OpusPacketInfo::parse(&[9 << 3, 0b1000_0000, 0x12])
+                .unwrap()
+                .fec
+        );
+        // Hybrid 20 ms stereo, LBRR on the second channel only.
+        assert!(
+            OpusPacketInfo::parse(&[15 << 3 | 0x04, 0b0001_0000, 0])
+                .unwrap()
+                .fec
+        );
+        // CELT has no LBRR whatever the bits; code 3 with three frames.
+        let celt = OpusPacketInfo::parse(&[31 << 3 | 3, 3, 0xFF, 0xFF]).unwrap();
+        assert_eq!((celt.fec, celt.frames, celt.samples), (false, 3, 2880));
+    }
+
+    #[test]
+    fn tells_losses_from_dtx() {
+        let mut opus = OpusDepacketizer::new();
+        let fec = [9 << 3, 0b0100_0000, 0x12];
+        let plain = [9 << 3, 0b1000_0000, 0x12];
+        push(&mut opus, 1, 0, &plain);
+        // DTX: no sequence gap across a 400 ms jump.
+        push(&mut opus, 2, 960, &[9 << 3]);
+        push(&mut opus, 3, 20160, &plain);
+        // Two packets lost, the next one has FEC for the second.
+        push(&mut opus, 6, 23040, &fec);
+        let events: Vec<_> = core::iter::from_fn(|| opus.pop_event()).collect();
+        assert_eq!(
+            events,
+            [
+                OpusEvent::Dtx {
+                    timestamp: 1920,
+                    samples: 18240
+                },
+                OpusEvent::Lost {
+                    timestamp: 21120,
+                    samples: 960,
+                    recoverable: false
+                },
+                OpusEvent::Lost {
+                    timestamp: 22080,
+                    samples: 960,
+                    recoverable: true
+                },
+            ]
+        );
+    }
+}
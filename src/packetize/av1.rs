@@ -0,0 +1,215 @@
+use super::Packetizer;
+use crate::codecs::av1::{
+    read_leb128, split_obus, write_leb128, Av1PayloadHdr, ObuHeader, OBU_SEQUENCE_HEADER,
+    OBU_TEMPORAL_DELIMITER, OBU_TILE_LIST,
+};
+use alloc::vec::Vec;
+
+/// AV1 payload format (RFC 9364).
+///
+/// Takes temporal units in low-overhead bitstream format (OBUs with size
+/// fields). Temporal delimiters and tile lists are dropped and OBUs are sent
+/// without size fields, aggregated while they fit and fragmented across
+/// packets (Z/Y) when they do not. N is set on the first packet of a
+/// temporal unit carrying a sequence header.
+#[derive(Debug, Clone, Default)]
+pub struct Av1Packetizer;
+
+impl Av1Packetizer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+// One packet being filled: OBU elements plus the Z/Y continuation flags.
+#[derive(Default)]
+struct PendingPacket {
+    elements: Vec<Vec<u8>>,
+    z_bit: bool,
+    size: usize,
+}
+
+impl PendingPacket {
+    fn push(&mut self, element: &[u8]) {
+        self.size += leb128_len(element.len()) + element.len();
+        self.elements.push(element.to_vec());
+    }
+
+    fn finish(&mut self, y_bit: bool, n_bit: bool, out: &mut Vec<Vec<u8>>) {
+        let elements = core::mem::take(&mut self.elements);
+        let count = elements.len();
+        // W counts up to 3 elements and lets the last one skip its length.
+        let w = if count <= 3 { count as u8 } else { 0 };
+        let hdr = Av1PayloadHdr {
+            z_bit: self.z_bit,
+            y_bit,
+            w,
+            n_bit,
+        };
+        let mut payload = Vec::with_capacity(1 + self.size);
+        payload.push(hdr.to_byte());
+        for (i, element) in elements.iter().enumerate() {
+            if w == 0 || i + 1 < count {
+                write_leb128(element.len() as u64, &mut payload);
+            }
+            payload.extend_from_slice(element);
+        }
+        out.push(payload);
+        self.z_bit = y_bit;
+        self.size = 0;
+    }
+}
+
+fn leb128_len(value: usize) -> usize {
+    let mut len = 1;
+    let mut v = value >> 7;
+    while v != 0 {
+        len += 1;
+        v >>= 7;
+    }
+    len
+}
+
+// Returns the OBU with its size field removed.
+fn strip_size_field(obu: &[u8]) -> Vec<u8> {
+    let hdr = ObuHeader::parse(obu[0]);
+    if !hdr.has_size_field {
+        return obu.to_vec();
+    }
+    let mut out = Vec::with_capacity(obu.len());
+    out.push(obu[0] & !0x02);
+    out.extend_from_slice(&obu[1..hdr.header_len()]);
+    let (_, n) = read_leb128(&obu[hdr.header_len()..]).unwrap_or((0, 0));
+    out.extend_from_slice(&obu[hdr.header_len() + n..]);
+    out
+}
+
+impl Packetizer for Av1Packetizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Vec<Vec<u8>> {
+        // Malformed input is sent as one OBU rather than dropped.
+        let obus = split_obus(frame).unwrap_or_else(|_| alloc::vec![frame]);
+        let obus: Vec<Vec<u8>> = obus
+            .into_iter()
+            .filter(|obu| {
+                let t = ObuHeader::parse(obu[0]).obu_type;
+                t != OBU_TEMPORAL_DELIMITER && t != OBU_TILE_LIST
+            })
+            .map(strip_size_field)
+            .collect();
+        let new_sequence = obus
+            .iter()
+            .any(|obu| ObuHeader::parse(obu[0]).obu_type == OBU_SEQUENCE_HEADER);
+
+        let budget = max_payload_size.max(3);
+        let mut out = Vec::new();
+        let mut packet = PendingPacket::default();
+        for obu in &obus {
+            let mut rest = obu.as_slice();
+            while !rest.is_empty() {
+                let avail = budget - 1 - packet.size;
+                if leb128_len(rest.len()) + rest.len() <= avail {
+                    packet.push(rest);
+                    break;
+                }
+                let take = avail.saturating_sub(leb128_len(avail)).min(rest.len());
+                if take == 0 {
+                    let n_bit = new_sequence && out.is_empty();
+                    packet.finish(false, n_bit, &mut out);
+                    continue;
+                }
+                packet.push(&rest[..take]);
+                rest = &rest[take..];
+                let n_bit = new_sequence && out.is_empty();
+                packet.finish(true, n_bit, &mut out);
+            }
+        }
+        if !packet.elements.is_empty() {
+            let n_bit = new_sequence && out.is_empty();
+            packet.finish(false, n_bit, &mut out);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::{
+            av1::{parse_av1_obu_elements, write_obu_with_size},
+            Codec,
+        },
+        packetize::{FramePacketizer, PacketizerConfig},
+        reassemble::FrameReassembler,
+        rtp::RtpPacket,
+    };
+
+    // Temporal unit: TD, sequence header and a frame OBU of `frame_len` bytes.
+    fn temporal_unit(frame_len: usize) -> (Vec<u8>, Vec<u8>) {
+        let seq_hdr = [OBU_SEQUENCE_HEADER << 3, 0x00, 0x00, 0x00, 0x0A, 0x0B];
+        let mut frame = alloc::vec![6 << 3];
+        frame.extend((0..frame_len).map(|i| i as u8));
+        let mut expected = Vec::new();
+        write_obu_with_size(&seq_hdr, &mut expected);
+        write_obu_with_size(&frame, &mut expected);
+        let mut tu = alloc::vec![OBU_TEMPORAL_DELIMITER << 3 | 0x02, 0x00];
+        tu.extend_from_slice(&expected);
+        (tu, expected)
+    }
+
+    #[test]
+    fn aggregates_small_obus_and_drops_temporal_delimiter() {
+        let (tu, _) = temporal_unit(10);
+        let payloads = Av1Packetizer::new().packetize(&tu, 1200);
+        assert_eq!(payloads.len(), 1);
+        let (hdr, elements) = parse_av1_obu_elements(&payloads[0]).unwrap();
+        assert_eq!(
+            hdr,
+            Av1PayloadHdr {
+                z_bit: false,
+                y_bit: false,
+                w: 2,
+                n_bit: true,
+            }
+        );
+        assert_eq!(elements[0][0], OBU_SEQUENCE_HEADER << 3);
+        assert_eq!(elements[1].len(), 11);
+    }
+
+    #[test]
+    fn fragments_large_obu_with_z_and_y() {
+        let (tu, _) = temporal_unit(300);
+        let payloads = Av1Packetizer::new().packetize(&tu, 100);
+        assert!(payloads.len() > 3);
+        assert!(payloads.iter().all(|p| p.len() <= 100));
+        let hdrs: Vec<_> = payloads
+            .iter()
+            .map(|p| parse_av1_obu_elements(p).unwrap().0)
+            .collect();
+        assert!(hdrs[0].n_bit && !hdrs[0].z_bit && hdrs[0].y_bit);
+        assert!(hdrs[1..].iter().all(|h| h.z_bit && !h.n_bit));
+        assert!(!hdrs[hdrs.len() - 1].y_bit);
+    }
+
+    #[test]
+    fn round_trip_through_reassembler() {
+        let (tu, expected) = temporal_unit(1000);
+        let mut p = FramePacketizer::new(
+            Codec::Av1,
+            PacketizerConfig {
+                mtu: 120,
+                ..Default::default()
+            },
+        );
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Av1)
+            .build()
+            .unwrap();
+        let frames: Vec<_> = p
+            .packetize(&tu, 3000)
+            .iter()
+            .filter_map(|b| r.push_packet(&RtpPacket::parse(b).unwrap()))
+            .collect();
+        assert_eq!(frames, [expected]);
+    }
+}
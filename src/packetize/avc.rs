@@ -0,0 +1,211 @@
+use super::Packetizer;
+use crate::codecs::split_annex_b;
+use alloc::vec::Vec;
+
+const STAP_A: u8 = 24;
+const FU_A: u8 = 28;
+
+/// How access units handed to [`AvcPacketizer`] are framed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AvcInputFormat {
+    /// NAL units separated by 3- or 4-byte start codes.
+    AnnexB,
+    /// NAL units prefixed by a big-endian length of 1, 2 or 4 bytes (avcC style).
+    LengthPrefixed(u8),
+}
+
+/// H.264 payload format (RFC 6184, packetization-mode 1).
+///
+/// NAL units that fit the payload budget are aggregated into STAP-A packets
+/// when more than one fits, sent as single NAL unit packets otherwise, and
+/// larger NAL units are fragmented into FU-A packets.
+#[derive(Debug, Clone)]
+pub struct AvcPacketizer {
+    input: AvcInputFormat,
+}
+
+impl Default for AvcPacketizer {
+    fn default() -> Self {
+        Self::new(AvcInputFormat::AnnexB)
+    }
+}
+
+impl AvcPacketizer {
+    pub fn new(input: AvcInputFormat) -> Self {
+        Self { input }
+    }
+
+    pub fn input_format(&self) -> AvcInputFormat {
+        self.input
+    }
+}
+
+/// Splits a length-prefixed access unit; truncated trailing data is ignored.
+pub(crate) fn split_length_prefixed(data: &[u8], length_size: u8) -> Vec<&[u8]> {
+    let size = length_size as usize;
+    let mut nals = Vec::new();
+    if !matches!(size, 1 | 2 | 4) {
+        return nals;
+    }
+    let mut i = 0usize;
+    while i + size <= data.len() {
+        let len = data[i..i + size]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        i += size;
+        if len == 0 || i + len > data.len() {
+            break;
+        }
+        nals.push(&data[i..i + len]);
+        i += len;
+    }
+    nals
+}
+
+impl Packetizer for AvcPacketizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Vec<Vec<u8>> {
+        let nals = match self.input {
+            AvcInputFormat::AnnexB => split_annex_b(frame),
+            AvcInputFormat::LengthPrefixed(size) => split_length_prefixed(frame, size),
+        };
+        let mut out = Vec::new();
+        let mut i = 0usize;
+        while i < nals.len() {
+            let nal = nals[i];
+            if nal.len() > max_payload_size {
+                fragment_fu_a(nal, max_payload_size, &mut out);
+                i += 1;
+                continue;
+            }
+
+            // Greedily collect following NAL units into one STAP-A.
+            let mut stap_len = 1 + 2 + nal.len();
+            let mut end = i + 1;
+            while end < nals.len() && stap_len + 2 + nals[end].len() <= max_payload_size {
+                stap_len += 2 + nals[end].len();
+                end += 1;
+            }
+            if end - i >= 2 {
+                out.push(build_stap_a(&nals[i..end], stap_len));
+            } else {
+                out.push(nal.to_vec());
+            }
+            i = end;
+        }
+        out
+    }
+}
+
+fn build_stap_a(nals: &[&[u8]], len: usize) -> Vec<u8> {
+    let forbidden = nals.iter().fold(0u8, |acc, n| acc | (n[0] & 0x80));
+    let nri = nals.iter().map(|n| n[0] & 0x60).max().unwrap_or(0);
+    let mut payload = Vec::with_capacity(len);
+    payload.push(forbidden | nri | STAP_A);
+    for nal in nals {
+        payload.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+        payload.extend_from_slice(nal);
+    }
+    payload
+}
+
+fn fragment_fu_a(nal: &[u8], max_payload_size: usize, out: &mut Vec<Vec<u8>>) {
+    let header = nal[0];
+    let indicator = (header & 0xE0) | FU_A;
+    let chunk_size = max_payload_size.saturating_sub(2).max(1);
+    let chunks: Vec<&[u8]> = nal[1..].chunks(chunk_size).collect();
+    let last = chunks.len().saturating_sub(1);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut fu_header = header & 0x1F;
+        if i == 0 {
+            fu_header |= 0x80;
+        }
+        if i == last {
+            fu_header |= 0x40;
+        }
+        let mut payload = Vec::with_capacity(chunk.len() + 2);
+        payload.push(indicator);
+        payload.push(fu_header);
+        payload.extend_from_slice(chunk);
+        out.push(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::{
+            avc::{parse_avc_payload_header, AvcNalKind},
+            Codec,
+        },
+        packetize::{FramePacketizer, PacketizerConfig},
+        reassemble::FrameReassembler,
+        rtp::RtpPacket,
+        test_utils::fixtures::{annex_b, AVC_IDR, AVC_PPS, AVC_SPS},
+    };
+
+    fn large_idr() -> Vec<u8> {
+        let mut nal = AVC_IDR.to_vec();
+        nal.extend((0..300u16).map(|i| (i % 251) as u8 | 0x01));
+        nal
+    }
+
+    #[test]
+    fn aggregates_parameter_sets_and_fragments_slices() {
+        let idr = large_idr();
+        let frame = annex_b(&[AVC_SPS, AVC_PPS, &idr]);
+        let payloads = AvcPacketizer::default().packetize(&frame, 100);
+
+        let (kind, _) = parse_avc_payload_header(&payloads[0]).unwrap();
+        assert_eq!(kind, AvcNalKind::StapA);
+        assert_eq!(payloads[0][0] & 0x60, 0x60, "STAP-A keeps the highest NRI");
+        let fus = &payloads[1..];
+        assert!(fus.len() > 1);
+        assert!(payloads.iter().all(|p| p.len() <= 100));
+        assert_eq!(fus[0][0], (idr[0] & 0xE0) | 28);
+        assert_eq!(fus[0][1], 0x80 | 5);
+        assert_eq!(fus[fus.len() - 1][1], 0x40 | 5);
+    }
+
+    #[test]
+    fn round_trip_annex_b_and_length_prefixed() {
+        let idr = large_idr();
+        let expected = annex_b(&[AVC_SPS, AVC_PPS, &idr]);
+        let mut length_prefixed = Vec::new();
+        for nal in [AVC_SPS, AVC_PPS, &idr] {
+            length_prefixed.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+            length_prefixed.extend_from_slice(nal);
+        }
+
+        for (input, format) in [
+            (expected.clone(), AvcInputFormat::AnnexB),
+            (length_prefixed, AvcInputFormat::LengthPrefixed(4)),
+        ] {
+            let mut p = FramePacketizer::with_packetizer(
+                Codec::Avc,
+                alloc::boxed::Box::new(AvcPacketizer::new(format)),
+                PacketizerConfig {
+                    mtu: 120,
+                    ..Default::default()
+                },
+            );
+            let mut r = FrameReassembler::builder()
+                .codec(Codec::Avc)
+                .build()
+                .unwrap();
+            let frames: Vec<_> = p
+                .packetize(&input, 3000)
+                .iter()
+                .filter_map(|b| r.push_packet(&RtpPacket::parse(b).unwrap()))
+                .collect();
+            assert_eq!(frames, [expected.as_slice()]);
+        }
+    }
+
+    #[test]
+    fn small_lone_nal_is_sent_as_single_nal_unit() {
+        let payloads = AvcPacketizer::default().packetize(&annex_b(&[AVC_IDR]), 1200);
+        assert_eq!(payloads, [AVC_IDR.to_vec()]);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::rtp::{RtpError, RtpPacket};
+use alloc::vec::Vec;
+
+/// Largest group a single ULPFEC packet can protect (48-bit mask).
+pub const ULPFEC_MAX_GROUP: usize = 48;
+
+/// ULPFEC encoder (RFC 5109) with one protection level.
+///
+/// Every `group_size` consecutive media packets of a stream yield one FEC
+/// payload protecting all of them (FEC rate `1 / group_size`). The payload
+/// is sent in its own packet with the ULPFEC payload type, or as a RED
+/// block as WebRTC does.
+#[derive(Debug, Clone)]
+pub struct UlpfecEncoder {
+    group_size: usize,
+    group: Vec<Vec<u8>>,
+}
+
+impl UlpfecEncoder {
+    /// `group_size` is clamped to `1..=ULPFEC_MAX_GROUP`.
+    pub fn new(group_size: usize) -> Self {
+        let group_size = group_size.clamp(1, ULPFEC_MAX_GROUP);
+        Self {
+            group_size,
+            group: Vec::with_capacity(group_size),
+        }
+    }
+
+    pub fn group_size(&self) -> usize {
+        self.group_size
+    }
+
+    /// Adds a sent media packet; returns the FEC payload once the group is full.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<Vec<u8>>, RtpError> {
+        RtpPacket::parse(packet)?;
+        self.group.push(packet.to_vec());
+        if self.group.len() < self.group_size {
+            return Ok(None);
+        }
+        Ok(self.flush())
+    }
+
+    /// Emits FEC for the packets collected so far, if any.
+    pub fn flush(&mut self) -> Option<Vec<u8>> {
+        let group = core::mem::take(&mut self.group);
+        ulpfec_payload(&group)
+    }
+}
+
+/// Builds a ULPFEC payload protecting `packets` (serialized RTP packets of
+/// one SSRC, in sequence order, spanning at most 48 sequence numbers).
+pub fn ulpfec_payload(packets: &[Vec<u8>]) -> Option<Vec<u8>> {
+    let base = RtpPacket::parse(packets.first()?)
+        .ok()?
+        .header
+        .sequence_number;
+    let mut mask = 0u64;
+    let mut header_xor = [0u8; 8];
+    let mut length_xor = 0u16;
+    let mut body: Vec<u8> = Vec::new();
+    for packet in packets {
+        if packet.len() < 12 {
+            return None;
+        }
+        let seq = u16::from_be_bytes([packet[2], packet[3]]);
+        let index = seq.wrapping_sub(base) as usize;
+        if index >= ULPFEC_MAX_GROUP {
+            return None;
+        }
+        mask |= 1 << (47 - index);
+        // Version bits are not recovered; sequence number and SSRC are known.
+        for (x, b) in header_xor.iter_mut().zip(&packet[..8]) {
+            *x ^= b;
+        }
+        let rest = &packet[12..];
+        length_xor ^= rest.len() as u16;
+        if body.len() < rest.len() {
+            body.resize(rest.len(), 0);
+        }
+        for (x, b) in body.iter_mut().zip(rest) {
+            *x ^= b;
+        }
+    }
+
+    let long_mask = mask & 0xFFFF_FFFF != 0;
+    let mut out = Vec::with_capacity(10 + 8 + body.len());
+    // E=0, L, then the P/X/CC recovery bits.
+    out.push(((long_mask as u8) << 6) | (header_xor[0] & 0x3F));
+    out.push(header_xor[1]);
+    out.extend_from_slice(&base.to_be_bytes());
+    out.extend_from_slice(&header_xor[4..8]);
+    out.extend_from_slice(&length_xor.to_be_bytes());
+    out.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    let mask_bytes = mask.to_be_bytes();
+    if long_mask {
+        out.extend_from_slice(&mask_bytes[2..8]);
+    } else {
+        out.extend_from_slice(&mask_bytes[2..4]);
+    }
+    out.extend_from_slice(&body);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::RtpPacketBuilder;
+
+    fn media(seq: u16, payload: &[u8]) -> Vec<u8> {
+        RtpPacketBuilder::new()
+            .sequence_number(seq)
+            .timestamp(3000 + seq as u32)
+            .ssrc(9)
+            .marker(seq & 1 == 0)
+            .payload(payload)
+            .build()
+    }
+
+    // Rebuilds the single missing packet of a group from FEC and the others.
+    fn recover(fec: &[u8], received: &[&[u8]], ssrc: u32) -> Vec<u8> {
+        let long_mask = fec[0] & 0x40 != 0;
+        let base = u16::from_be_bytes([fec[2], fec[3]]);
+        let mask_len = if long_mask { 6 } else { 2 };
+        let mut mask = [0u8; 8];
+        mask[2..2 + mask_len].copy_from_slice(&fec[12..12 + mask_len]);
+        let mask = u64::from_be_bytes(mask);
+        let mut header = [fec[0] & 0x3F, fec[1], 0, 0, fec[4], fec[5], fec[6], fec[7]];
+        let mut length = u16::from_be_bytes([fec[8], fec[9]]);
+        let mut body = fec[12 + mask_len..].to_vec();
+        let mut missing = None;
+        for i in 0..ULPFEC_MAX_GROUP {
+            if mask & (1 << (47 - i)) == 0 {
+                continue;
+            }
+            let seq = base.wrapping_add(i as u16);
+            match received
+                .iter()
+                .find(|p| u16::from_be_bytes([p[2], p[3]]) == seq)
+            {
+                Some(p) => {
+                    for (x, b) in header.iter_mut().zip(&p[..8]) {
+                        *x ^= b;
+                    }
+                    length ^= (p.len() - 12) as u16;
+                    for (x, b) in body.iter_mut().zip(&p[12..]) {
+                        *x ^= b;
+                    }
+                }
+                None => missing = Some(seq),
+            }
+        }
+        let seq = missing.unwrap();
+        let mut out = alloc::vec![0x80 | (header[0] & 0x3F), header[1]];
+        out.extend_from_slice(&seq.to_be_bytes());
+        out.extend_from_slice(&header[4..8]);
+        out.extend_from_slice(&ssrc.to_be_bytes());
+        out.extend_from_slice(&body[..length as usize]);
+        out
+    }
+
+    #[test]
+    fn fec_recovers_any_single_loss_in_group() {
+        let packets = [
+            media(100, &[1, 2, 3]),
+            media(101, &[4, 5, 6, 7, 8]),
+            media(102, &[9]),
+        ];
+        let mut enc = UlpfecEncoder::new(3);
+        assert_eq!(enc.push(&packets[0]).unwrap(), None);
+        assert_eq!(enc.push(&packets[1]).unwrap(), None);
+        let fec = enc.push(&packets[2]).unwrap().expect("fec");
+        assert_eq!(fec[0] & 0x40, 0, "short mask");
+        assert_eq!(&fec[12..14], &[0xE0, 0x00]);
+
+        for lost in 0..packets.len() {
+            let received: Vec<&[u8]> = packets
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != lost)
+                .map(|(_, p)| p.as_slice())
+                .collect();
+            assert_eq!(recover(&fec, &received, 9), packets[lost]);
+        }
+    }
+
+    #[test]
+    fn long_mask_for_wide_groups() {
+        let packets: Vec<Vec<u8>> = (0..20)
+            .map(|i| media(65530u16.wrapping_add(i), &[i as u8]))
+            .collect();
+        let fec = ulpfec_payload(&packets).unwrap();
+        assert_eq!(fec[0] & 0x40, 0x40);
+        assert_eq!(&fec[12..18], &[0xFF, 0xFF, 0xF0, 0, 0, 0]);
+        let received: Vec<&[u8]> = packets[1..].iter().map(|p| p.as_slice()).collect();
+        assert_eq!(recover(&fec, &received, 9), packets[0]);
+        assert_eq!(UlpfecEncoder::new(100).group_size(), ULPFEC_MAX_GROUP);
+    }
+}
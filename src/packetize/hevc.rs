@@ -0,0 +1,242 @@
+use super::Packetizer;
+use crate::codecs::split_annex_b;
+use alloc::vec::Vec;
+
+const AP: u8 = 48;
+const FU: u8 = 49;
+
+/// H.265 payload format (RFC 7798).
+///
+/// Takes Annex-B access units. NAL units that fit the payload budget are
+/// aggregated into AP packets when more than one fits, sent as single NAL
+/// unit packets otherwise, and larger NAL units are fragmented into FU
+/// packets. With DONL enabled (`sprop-max-don-diff` > 0 in SDP) every NAL
+/// unit carries its decoding order number.
+#[derive(Debug, Clone, Default)]
+pub struct HevcPacketizer {
+    donl: bool,
+    next_don: u16,
+}
+
+impl HevcPacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables DONL/DOND fields, starting decoding order numbers at `initial_don`.
+    pub fn with_donl(initial_don: u16) -> Self {
+        Self {
+            donl: true,
+            next_don: initial_don,
+        }
+    }
+
+    pub fn donl_enabled(&self) -> bool {
+        self.donl
+    }
+
+    fn take_don(&mut self) -> u16 {
+        let don = self.next_don;
+        self.next_don = self.next_don.wrapping_add(1);
+        don
+    }
+}
+
+impl Packetizer for HevcPacketizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Vec<Vec<u8>> {
+        // NAL units shorter than the 2-byte header cannot be carried.
+        let nals: Vec<&[u8]> = split_annex_b(frame)
+            .into_iter()
+            .filter(|n| n.len() >= 2)
+            .collect();
+        let don_len = if self.donl { 2 } else { 0 };
+        let mut out = Vec::new();
+        let mut i = 0usize;
+        while i < nals.len() {
+            let nal = nals[i];
+            if nal.len() + don_len > max_payload_size {
+                let don = self.take_don();
+                fragment_fu(nal, max_payload_size, self.donl.then_some(don), &mut out);
+                i += 1;
+                continue;
+            }
+
+            // Greedily collect following NAL units into one AP. After the
+            // first unit, DONL shrinks to a one-byte DOND.
+            let mut ap_len = 2 + don_len + 2 + nal.len();
+            let mut end = i + 1;
+            while end < nals.len() && ap_len + don_len / 2 + 2 + nals[end].len() <= max_payload_size
+            {
+                ap_len += don_len / 2 + 2 + nals[end].len();
+                end += 1;
+            }
+            if end - i >= 2 {
+                let first_don = self.take_don();
+                for _ in i + 1..end {
+                    self.take_don();
+                }
+                out.push(build_ap(
+                    &nals[i..end],
+                    ap_len,
+                    self.donl.then_some(first_don),
+                ));
+            } else if self.donl {
+                let don = self.take_don();
+                let mut payload = Vec::with_capacity(nal.len() + 2);
+                payload.extend_from_slice(&nal[..2]);
+                payload.extend_from_slice(&don.to_be_bytes());
+                payload.extend_from_slice(&nal[2..]);
+                out.push(payload);
+            } else {
+                out.push(nal.to_vec());
+            }
+            i = end;
+        }
+        out
+    }
+}
+
+fn build_ap(nals: &[&[u8]], len: usize, first_don: Option<u16>) -> Vec<u8> {
+    // F is the OR of all units; LayerId and TID are the lowest values.
+    let forbidden = nals.iter().fold(0u8, |acc, n| acc | (n[0] & 0x80));
+    let layer_id = nals
+        .iter()
+        .map(|n| (((n[0] & 0x01) as u16) << 5) | (n[1] >> 3) as u16)
+        .min()
+        .unwrap_or(0);
+    let tid = nals.iter().map(|n| n[1] & 0x07).min().unwrap_or(1);
+    let mut payload = Vec::with_capacity(len);
+    payload.push(forbidden | (AP << 1) | (layer_id >> 5) as u8);
+    payload.push(((layer_id as u8 & 0x1F) << 3) | tid);
+    for (i, nal) in nals.iter().enumerate() {
+        match (first_don, i) {
+            (Some(don), 0) => payload.extend_from_slice(&don.to_be_bytes()),
+            // Consecutive decoding order: DOND = difference - 1 = 0.
+            (Some(_), _) => payload.push(0),
+            (None, _) => {}
+        }
+        payload.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+        payload.extend_from_slice(nal);
+    }
+    payload
+}
+
+fn fragment_fu(nal: &[u8], max_payload_size: usize, don: Option<u16>, out: &mut Vec<Vec<u8>>) {
+    let nal_type = (nal[0] & 0x7E) >> 1;
+    let payload_hdr = [(nal[0] & 0x81) | (FU << 1), nal[1]];
+    let overhead = 3 + if don.is_some() { 2 } else { 0 };
+    let chunk_size = max_payload_size.saturating_sub(overhead).max(1);
+    let chunks: Vec<&[u8]> = nal[2..].chunks(chunk_size).collect();
+    let last = chunks.len().saturating_sub(1);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut fu_header = nal_type;
+        if i == 0 {
+            fu_header |= 0x80;
+        }
+        if i == last {
+            fu_header |= 0x40;
+        }
+        let mut payload = Vec::with_capacity(chunk.len() + overhead);
+        payload.extend_from_slice(&payload_hdr);
+        payload.push(fu_header);
+        // DONL is only present in the first fragment.
+        if let (Some(don), 0) = (don, i) {
+            payload.extend_from_slice(&don.to_be_bytes());
+        }
+        payload.extend_from_slice(chunk);
+        out.push(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::{
+            hevc::{parse_hevc_payload_header, HevcNalKind},
+            Codec,
+        },
+        packetize::{FramePacketizer, PacketizerConfig},
+        reassemble::FrameReassembler,
+        rtp::RtpPacket,
+        test_utils::fixtures::{annex_b, HEVC_IDR, HEVC_PPS, HEVC_SPS, HEVC_VPS},
+    };
+
+    fn large_idr() -> Vec<u8> {
+        let mut nal = HEVC_IDR.to_vec();
+        nal.extend((0..300u16).map(|i| (i % 251) as u8 | 0x01));
+        nal
+    }
+
+    #[test]
+    fn aggregates_parameter_sets_and_fragments_slices() {
+        let idr = large_idr();
+        let frame = annex_b(&[HEVC_VPS, HEVC_SPS, HEVC_PPS, &idr]);
+        let payloads = HevcPacketizer::new().packetize(&frame, 100);
+
+        let (kind, _) = parse_hevc_payload_header(&payloads[0]).unwrap();
+        assert_eq!(kind, HevcNalKind::Ap);
+        assert_eq!(&payloads[0][..2], &[AP << 1, 0x01]);
+        let fus = &payloads[1..];
+        assert!(fus.len() > 1);
+        assert!(payloads.iter().all(|p| p.len() <= 100));
+        let idr_type = (idr[0] >> 1) & 0x3F;
+        assert_eq!(
+            parse_hevc_payload_header(&fus[0]).unwrap().0,
+            HevcNalKind::Fu {
+                start: true,
+                end: false,
+                nal_type: idr_type
+            }
+        );
+        assert_eq!(fus[fus.len() - 1][2], 0x40 | idr_type);
+    }
+
+    #[test]
+    fn round_trip_through_reassembler() {
+        let idr = large_idr();
+        let expected = annex_b(&[HEVC_VPS, HEVC_SPS, HEVC_PPS, &idr]);
+        let mut p = FramePacketizer::new(
+            Codec::Hevc,
+            PacketizerConfig {
+                mtu: 120,
+                ..Default::default()
+            },
+        );
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Hevc)
+            .build()
+            .unwrap();
+        let frames: Vec<_> = p
+            .packetize(&expected, 3000)
+            .iter()
+            .filter_map(|b| r.push_packet(&RtpPacket::parse(b).unwrap()))
+            .collect();
+        assert_eq!(frames, [expected.as_slice()]);
+    }
+
+    #[test]
+    fn donl_fields_carry_decoding_order() {
+        let idr = large_idr();
+        let frame = annex_b(&[HEVC_VPS, HEVC_SPS, &idr, HEVC_PPS]);
+        let payloads = HevcPacketizer::with_donl(65534).packetize(&frame, 100);
+
+        // AP: DONL of the first unit, then DOND before each following unit.
+        let ap = &payloads[0];
+        assert_eq!(&ap[2..4], &65534u16.to_be_bytes());
+        let first_len = u16::from_be_bytes([ap[4], ap[5]]) as usize;
+        assert_eq!(&ap[6..6 + first_len], HEVC_VPS);
+        assert_eq!(ap[6 + first_len], 0);
+
+        // FU: DONL follows the FU header in the first fragment only.
+        assert_eq!(&payloads[1][3..5], &0u16.to_be_bytes());
+        assert_eq!(&payloads[1][5..8], &idr[2..5]);
+        assert_eq!(&payloads[2][3..5], &idr[2 + 95..2 + 97]);
+
+        // Trailing single NAL unit packet: header, DONL, rest of the NAL.
+        let single = payloads.last().unwrap();
+        assert_eq!(&single[..2], &HEVC_PPS[..2]);
+        assert_eq!(&single[2..4], &1u16.to_be_bytes());
+        assert_eq!(&single[4..], &HEVC_PPS[2..]);
+    }
+}
@@ -0,0 +1,230 @@
+//! Send path: splitting encoded frames into RTP packets.
+//!
+//! Codec-specific [`Packetizer`]s turn one frame into payloads that fit a
+//! size budget; [`FramePacketizer`] wraps one of them, mirroring
+//! [`FrameReassembler`](crate::reassemble::FrameReassembler) in the other
+//! direction by adding RTP headers with sequence numbers, timestamp and the
+//! marker bit on the last packet of every frame.
+
+pub mod av1;
+pub mod avc;
+pub mod fec;
+pub mod hevc;
+pub mod red;
+pub mod repacketizer;
+pub mod rtx;
+pub mod sequencer;
+pub mod vp9;
+
+pub use av1::Av1Packetizer;
+pub use avc::{AvcInputFormat, AvcPacketizer};
+pub use fec::UlpfecEncoder;
+pub use hevc::HevcPacketizer;
+pub use red::RedEncoder;
+pub use repacketizer::Repacketizer;
+pub use rtx::RtxCache;
+pub use sequencer::RtpSequencer;
+pub use vp9::Vp9Packetizer;
+
+use crate::codecs::Codec;
+use alloc::{boxed::Box, vec::Vec};
+use core::time::Duration;
+
+/// Size of the fixed RTP header written by [`FramePacketizer`].
+pub const RTP_HEADER_LEN: usize = 12;
+
+/// Splits encoded frames into RTP payloads.
+pub trait Packetizer {
+    /// Returns the payloads carrying `frame`, in send order, each at most
+    /// `max_payload_size` bytes long (when the format allows it).
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Vec<Vec<u8>>;
+}
+
+/// Payload format without codec framing: the frame is cut into equal-size
+/// chunks. Used for [`Codec::Unknown`] and custom codecs, unless given
+/// their own with [`FramePacketizer::with_packetizer`].
+#[derive(Debug, Default, Clone)]
+pub struct RawPacketizer;
+
+impl Packetizer for RawPacketizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Vec<Vec<u8>> {
+        frame
+            .chunks(max_payload_size.max(1))
+            .map(|chunk| chunk.to_vec())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PacketizerConfig {
+    // Maximum size of a serialized RTP packet, header included.
+    pub mtu: usize,
+    pub payload_type: u8,
+    pub ssrc: u32,
+    pub initial_sequence_number: u16,
+    // Media clock used by `FramePacketizer::packetize_at`.
+    pub clock_rate: u32,
+}
+
+impl Default for PacketizerConfig {
+    fn default() -> Self {
+        Self {
+            mtu: 1200,
+            payload_type: 96,
+            ssrc: 0,
+            initial_sequence_number: 0,
+            clock_rate: 90_000,
+        }
+    }
+}
+
+/// Turns encoded frames into serialized RTP packets.
+pub struct FramePacketizer {
+    config: PacketizerConfig,
+    packetizer: Box<dyn Packetizer + Send>,
+    codec: Codec,
+    sequencer: RtpSequencer,
+}
+
+impl core::fmt::Debug for FramePacketizer {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FramePacketizer")
+            .field("config", &self.config)
+            .field("codec", &self.codec)
+            .field("sequencer", &self.sequencer)
+            .finish()
+    }
+}
+
+impl FramePacketizer {
+    /// Creates a packetizer using the payload format of `codec`.
+    ///
+    /// H.264 and H.265 input is expected in Annex-B form, AV1 input as
+    /// temporal units in low-overhead bitstream format.
+    pub fn new(codec: Codec, config: PacketizerConfig) -> Self {
+        let packetizer: Box<dyn Packetizer + Send> = match codec {
+            Codec::Avc => Box::new(AvcPacketizer::default()),
+            Codec::Hevc => Box::new(HevcPacketizer::new()),
+            Codec::Vp9 => Box::new(Vp9Packetizer::new()),
+            Codec::Av1 => Box::new(Av1Packetizer::new()),
+            Codec::Unknown | Codec::Custom(_) => Box::new(RawPacketizer),
+        };
+        Self::with_packetizer(codec, packetizer, config)
+    }
+
+    /// Creates a packetizer around a caller-provided payload format.
+    pub fn with_packetizer(
+        codec: Codec,
+        packetizer: Box<dyn Packetizer + Send>,
+        config: PacketizerConfig,
+    ) -> Self {
+        Self {
+            sequencer: RtpSequencer::with_initial_state(
+                config.payload_type,
+                config.clock_rate,
+                config.ssrc,
+                config.initial_sequence_number,
+                0,
+            ),
+            config,
+            packetizer,
+            codec,
+        }
+    }
+
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    pub fn config(&self) -> &PacketizerConfig {
+        &self.config
+    }
+
+    /// Sequence number the next emitted packet will carry.
+    pub fn next_sequence_number(&self) -> u16 {
+        self.sequencer.next_sequence_number()
+    }
+
+    pub fn sequencer(&self) -> &RtpSequencer {
+        &self.sequencer
+    }
+
+    /// Packetizes one frame captured at `timestamp` into serialized RTP packets.
+    pub fn packetize(&mut self, frame: &[u8], timestamp: u32) -> Vec<Vec<u8>> {
+        let payloads = self.packetizer.packetize(frame, self.max_payload_size());
+        self.sequencer.stamp(payloads, timestamp)
+    }
+
+    /// Packetizes one frame captured `elapsed` after the stream started,
+    /// deriving the timestamp from `clock_rate`.
+    pub fn packetize_at(&mut self, frame: &[u8], elapsed: Duration) -> Vec<Vec<u8>> {
+        let payloads = self.packetizer.packetize(frame, self.max_payload_size());
+        self.sequencer.stamp_at(payloads, elapsed)
+    }
+
+    fn max_payload_size(&self) -> usize {
+        self.config.mtu.saturating_sub(RTP_HEADER_LEN).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{reassemble::FrameReassembler, rtp::RtpPacket};
+
+    #[test]
+    fn raw_packetizer_respects_budget() {
+        let frame: Vec<u8> = (0..25).collect();
+        let payloads = RawPacketizer.packetize(&frame, 10);
+        assert_eq!(payloads.len(), 3);
+        assert!(payloads.iter().all(|p| p.len() <= 10));
+        assert_eq!(payloads.concat(), frame);
+    }
+
+    #[test]
+    fn frame_packetizer_sets_headers() {
+        let mut p = FramePacketizer::new(
+            Codec::Unknown,
+            PacketizerConfig {
+                mtu: 22,
+                payload_type: 100,
+                ssrc: 0xABCD,
+                initial_sequence_number: 65534,
+                ..Default::default()
+            },
+        );
+        let packets = p.packetize(&[7u8; 25], 1234);
+        assert_eq!(packets.len(), 3);
+        let parsed: Vec<_> = packets
+            .iter()
+            .map(|b| RtpPacket::parse(b).unwrap())
+            .collect();
+        let seqs: Vec<u16> = parsed.iter().map(|p| p.header.sequence_number).collect();
+        assert_eq!(seqs, [65534, 65535, 0]);
+        assert!(packets.iter().all(|b| b.len() <= 22));
+        assert!(parsed.iter().all(|p| p.header.timestamp == 1234));
+        assert!(parsed.iter().all(|p| p.header.payload_type == 100));
+        assert_eq!(
+            parsed.iter().map(|p| p.header.marker).collect::<Vec<_>>(),
+            [false, false, true]
+        );
+        assert_eq!(p.next_sequence_number(), 1);
+    }
+
+    #[test]
+    fn round_trip_through_reassembler() {
+        let frame: Vec<u8> = (0..100).collect();
+        let mut p = FramePacketizer::new(Codec::Unknown, PacketizerConfig::default());
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Unknown)
+            .build()
+            .unwrap();
+        let out: Vec<_> = p
+            .packetize(&frame, 90)
+            .iter()
+            .filter_map(|b| r.push_packet(&RtpPacket::parse(b).unwrap()))
+            .collect();
+        assert_eq!(out, [frame]);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::rtp::{RtpError, RtpPacket};
+use alloc::{collections::VecDeque, vec::Vec};
+
+// Block offsets and lengths must fit the 14- and 10-bit header fields.
+const MAX_TIMESTAMP_OFFSET: u32 = (1 << 14) - 1;
+const MAX_BLOCK_LEN: usize = (1 << 10) - 1;
+
+/// RED encoder (RFC 2198).
+///
+/// Re-sends each packet as a RED packet carrying the new payload as the
+/// primary block, preceded by the payloads of up to `distance` earlier
+/// packets as redundant blocks. The RTP header is kept apart from the
+/// payload type.
+#[derive(Debug, Clone)]
+pub struct RedEncoder {
+    payload_type: u8,
+    distance: usize,
+    // (payload type, timestamp, payload) of earlier packets, oldest first.
+    history: VecDeque<(u8, u32, Vec<u8>)>,
+}
+
+impl RedEncoder {
+    pub fn new(payload_type: u8, distance: usize) -> Self {
+        Self {
+            payload_type,
+            distance,
+            history: VecDeque::with_capacity(distance),
+        }
+    }
+
+    pub fn payload_type(&self) -> u8 {
+        self.payload_type
+    }
+
+    /// Number of earlier payloads repeated in every packet.
+    pub fn distance(&self) -> usize {
+        self.distance
+    }
+
+    /// Wraps the serialized RTP `packet` into a RED packet.
+    pub fn encode(&mut self, packet: &[u8]) -> Result<Vec<u8>, RtpError> {
+        let pkt = RtpPacket::parse(packet)?;
+        let ts = pkt.header.timestamp;
+        let blocks: Vec<&(u8, u32, Vec<u8>)> = self
+            .history
+            .iter()
+            .filter(|(_, block_ts, data)| {
+                ts.wrapping_sub(*block_ts) <= MAX_TIMESTAMP_OFFSET && data.len() <= MAX_BLOCK_LEN
+            })
+            .collect();
+
+        let mut header = pkt.header.clone();
+        header.payload_type = self.payload_type;
+        header.padding = false;
+        header.extension = false;
+        header.extension_header = None;
+        let redundant_len: usize = blocks.iter().map(|(_, _, d)| 4 + d.len()).sum();
+        let mut out =
+            Vec::with_capacity(header.serialized_len() + redundant_len + 1 + pkt.payload.len());
+        header.write(&mut out);
+        for (pt, block_ts, data) in &blocks {
+            let offset = ts.wrapping_sub(*block_ts);
+            out.push(0x80 | (pt & 0x7F));
+            out.extend_from_slice(&((offset << 10) | data.len() as u32).to_be_bytes()[1..]);
+        }
+        out.push(pkt.header.payload_type & 0x7F);
+        for (_, _, data) in &blocks {
+            out.extend_from_slice(data);
+        }
+        out.extend_from_slice(pkt.payload);
+
+        if self.distance > 0 {
+            if self.history.len() == self.distance {
+                self.history.pop_front();
+            }
+            self.history
+                .push_back((pkt.header.payload_type, ts, pkt.payload.to_vec()));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::RtpPacketBuilder;
+
+    fn opus(seq: u16, ts: u32, payload: &[u8]) -> Vec<u8> {
+        RtpPacketBuilder::new()
+            .payload_type(111)
+            .sequence_number(seq)
+            .timestamp(ts)
+            .payload(payload)
+            .build()
+    }
+
+    #[test]
+    fn carries_earlier_payloads_as_redundant_blocks() {
+        let mut red = RedEncoder::new(63, 1);
+        let first = red.encode(&opus(1, 960, &[0xAA])).unwrap();
+        let first = RtpPacket::parse(&first).unwrap();
+        assert_eq!(first.header.payload_type, 63);
+        assert_eq!(first.payload, &[111, 0xAA]);
+
+        let second = red.encode(&opus(2, 1920, &[0xBB, 0xCC])).unwrap();
+        let second = RtpPacket::parse(&second).unwrap();
+        assert_eq!(second.header.sequence_number, 2);
+        // F=1|PT, 14-bit offset 960, 10-bit length 1, then F=0|PT.
+        let offset_len = (960u32 << 10) | 1;
+        assert_eq!(
+            second.payload,
+            &[
+                0x80 | 111,
+                (offset_len >> 16) as u8,
+                (offset_len >> 8) as u8,
+                offset_len as u8,
+                111,
+                0xAA,
+                0xBB,
+                0xCC
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_blocks_beyond_offset_range() {
+        let mut red = RedEncoder::new(63, 2);
+        red.encode(&opus(1, 0, &[1])).unwrap();
+        let out = red.encode(&opus(2, 1 << 14, &[2])).unwrap();
+        assert_eq!(RtpPacket::parse(&out).unwrap().payload, &[111, 2]);
+    }
+}
@@ -0,0 +1,146 @@
+use super::{FramePacketizer, Packetizer, PacketizerConfig};
+use crate::{codecs::Codec, reassemble::FrameReassembler, rtp::RtpPacket};
+use alloc::{boxed::Box, vec::Vec};
+
+/// Depacketizes a stream into frames and packetizes them again under a new
+/// SSRC, payload type and MTU, without touching the encoded data.
+///
+/// Output packets keep the RTP timestamp of their frame; sequence numbers
+/// are renumbered, so frames lost or dropped on input leave no gap.
+#[derive(Debug)]
+pub struct Repacketizer {
+    reassembler: FrameReassembler,
+    packetizer: FramePacketizer,
+}
+
+impl Repacketizer {
+    pub fn new(codec: Codec, config: PacketizerConfig) -> Self {
+        Self::from_parts(codec, FramePacketizer::new(codec, config))
+    }
+
+    /// Uses a caller-provided payload format on the output side.
+    pub fn with_packetizer(
+        codec: Codec,
+        packetizer: Box<dyn Packetizer + Send>,
+        config: PacketizerConfig,
+    ) -> Self {
+        Self::from_parts(
+            codec,
+            FramePacketizer::with_packetizer(codec, packetizer, config),
+        )
+    }
+
+    fn from_parts(codec: Codec, packetizer: FramePacketizer) -> Self {
+        let reassembler = FrameReassembler::builder()
+            .codec(codec)
+            .build()
+            .expect("default config is valid");
+        Self {
+            reassembler,
+            packetizer,
+        }
+    }
+
+    pub fn reassembler(&self) -> &FrameReassembler {
+        &self.reassembler
+    }
+
+    /// Input side, e.g. to replace it with one built from another
+    /// configuration.
+    pub fn reassembler_mut(&mut self) -> &mut FrameReassembler {
+        &mut self.reassembler
+    }
+
+    pub fn packetizer(&self) -> &FramePacketizer {
+        &self.packetizer
+    }
+
+    /// Pushes one input packet; returns the output packets of the frames it
+    /// completed, or nothing while they are still incomplete.
+    pub fn push_packet(&mut self, pkt: &RtpPacket<'_>) -> Vec<Vec<u8>> {
+        let mut out = match self.reassembler.push_packet(pkt) {
+            Some(frame) => self.packetizer.packetize(&frame, pkt.header.timestamp),
+            None => Vec::new(),
+        };
+        // A resilient reassembler queues its frames instead, salvaged ones
+        // included.
+        while let Some(frame) = self.reassembler.pop_frame() {
+            out.extend(self.packetizer.packetize(&frame.data, frame.timestamp));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        avc,
+        fixtures::{annex_b, AVC_IDR, AVC_PPS, AVC_SPS},
+        StreamBuilder,
+    };
+
+    #[test]
+    fn bridges_fu_a_stream_to_new_ssrc_and_mtu() {
+        let mut idr = AVC_IDR.to_vec();
+        idr.extend((0..200u16).map(|i| (i % 200) as u8 | 0x01));
+        let mut payloads = alloc::vec![avc::single(AVC_SPS), avc::single(AVC_PPS)];
+        payloads.extend(avc::fu_a(&idr, 30));
+        let input = StreamBuilder::new(1)
+            .start_timestamp(123_456)
+            .frame(&payloads)
+            .build();
+
+        let mut r = Repacketizer::new(
+            Codec::Avc,
+            PacketizerConfig {
+                mtu: 100,
+                payload_type: 102,
+                ssrc: 0xBEEF,
+                ..Default::default()
+            },
+        );
+        let mut output = Vec::new();
+        for (i, b) in input.iter().enumerate() {
+            let out = r.push_packet(&RtpPacket::parse(b).unwrap());
+            assert_eq!(out.is_empty(), i + 1 < input.len());
+            output.extend(out);
+        }
+        assert!(output.len() < input.len());
+        let parsed: Vec<_> = output
+            .iter()
+            .map(|b| RtpPacket::parse(b).unwrap())
+            .collect();
+        assert!(parsed.iter().all(|p| p.header.ssrc == 0xBEEF
+            && p.header.payload_type == 102
+            && p.header.timestamp == 123_456
+            && p.payload.len() <= 100 - 12));
+
+        let mut check = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let frames: Vec<_> = parsed.iter().filter_map(|p| check.push_packet(p)).collect();
+        assert_eq!(frames, [annex_b(&[AVC_SPS, AVC_PPS, &idr])]);
+    }
+
+    #[test]
+    fn forwards_frames_of_a_resilient_reassembler() {
+        let input = StreamBuilder::new(1)
+            .frame(&[avc::single(AVC_IDR)])
+            .frame(&avc::fu_a(AVC_IDR, 8))
+            .build();
+        let mut r = Repacketizer::new(Codec::Avc, PacketizerConfig::default());
+        *r.reassembler_mut() = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .resilient(true)
+            .build()
+            .unwrap();
+        let timestamps: Vec<_> = input
+            .iter()
+            .flat_map(|b| r.push_packet(&RtpPacket::parse(b).unwrap()))
+            .map(|b| RtpPacket::parse(&b).unwrap().header.timestamp)
+            .collect();
+        assert_eq!(timestamps, [0, 3000]);
+    }
+}
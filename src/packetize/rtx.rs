@@ -0,0 +1,165 @@
+use crate::rtp::{RtpError, RtpPacket};
+use alloc::{collections::BTreeMap, collections::VecDeque, vec::Vec};
+
+/// Send-side retransmission cache (RFC 4588, SSRC multiplexing).
+///
+/// Keeps the last `capacity` packets sent on each SSRC and answers lost
+/// sequence numbers (e.g. from a generic NACK) with RTX packets: the
+/// original payload prefixed by its sequence number (OSN), sent on the
+/// stream's RTX SSRC with the RTX payload type associated to the original
+/// one. Header extensions and padding are not carried over.
+#[derive(Debug, Clone)]
+pub struct RtxCache {
+    capacity: usize,
+    // Original payload type -> RTX payload type (SDP `apt`).
+    payload_types: BTreeMap<u8, u8>,
+    streams: BTreeMap<u32, RtxStream>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RtxStream {
+    // RTX SSRC and its next sequence number, once configured.
+    rtx: Option<(u32, u16)>,
+    packets: VecDeque<(u16, Vec<u8>)>,
+}
+
+impl RtxCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            payload_types: BTreeMap::new(),
+            streams: BTreeMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Associates the RTX payload type used for packets of `payload_type`.
+    pub fn set_rtx_payload_type(&mut self, payload_type: u8, rtx_payload_type: u8) {
+        self.payload_types.insert(payload_type, rtx_payload_type);
+    }
+
+    /// Sets the RTX SSRC for `media_ssrc` and the first RTX sequence number.
+    pub fn set_rtx_ssrc(&mut self, media_ssrc: u32, rtx_ssrc: u32, initial_sequence_number: u16) {
+        self.streams.entry(media_ssrc).or_default().rtx = Some((rtx_ssrc, initial_sequence_number));
+    }
+
+    /// Remembers a sent packet, evicting the oldest one of its SSRC when full.
+    pub fn store(&mut self, packet: &[u8]) -> Result<(), RtpError> {
+        let pkt = RtpPacket::parse(packet)?;
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let stream = self.streams.entry(pkt.header.ssrc).or_default();
+        if stream.packets.len() == self.capacity {
+            stream.packets.pop_front();
+        }
+        stream
+            .packets
+            .push_back((pkt.header.sequence_number, packet.to_vec()));
+        Ok(())
+    }
+
+    /// Number of packets cached for `ssrc`.
+    pub fn cached(&self, ssrc: u32) -> usize {
+        self.streams.get(&ssrc).map_or(0, |s| s.packets.len())
+    }
+
+    /// Builds RTX packets for the `lost` sequence numbers of `media_ssrc`.
+    ///
+    /// Sequence numbers no longer cached, or streams without an RTX SSRC or
+    /// RTX payload type, are skipped.
+    pub fn retransmit(&mut self, media_ssrc: u32, lost: &[u16]) -> Vec<Vec<u8>> {
+        let mut out = Vec::new();
+        let Some(stream) = self.streams.get_mut(&media_ssrc) else {
+            return out;
+        };
+        let Some((rtx_ssrc, rtx_seq)) = stream.rtx.as_mut() else {
+            return out;
+        };
+        for &seq in lost {
+            let Some((_, original)) = stream.packets.iter().find(|(s, _)| *s == seq) else {
+                continue;
+            };
+            let Ok(pkt) = RtpPacket::parse(original) else {
+                continue;
+            };
+            let Some(&rtx_pt) = self.payload_types.get(&pkt.header.payload_type) else {
+                continue;
+            };
+            let mut header = pkt.header.clone();
+            header.payload_type = rtx_pt;
+            header.ssrc = *rtx_ssrc;
+            header.sequence_number = *rtx_seq;
+            header.padding = false;
+            header.extension = false;
+            header.extension_header = None;
+            let mut rtx = Vec::with_capacity(header.serialized_len() + 2 + pkt.payload.len());
+            header.write(&mut rtx);
+            rtx.extend_from_slice(&seq.to_be_bytes());
+            rtx.extend_from_slice(pkt.payload);
+            out.push(rtx);
+            *rtx_seq = rtx_seq.wrapping_add(1);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::RtpPacketBuilder;
+
+    fn media(seq: u16) -> Vec<u8> {
+        RtpPacketBuilder::new()
+            .payload_type(96)
+            .sequence_number(seq)
+            .timestamp(9000)
+            .ssrc(0x1111)
+            .marker(true)
+            .payload(&[seq as u8, 0xEE])
+            .build()
+    }
+
+    #[test]
+    fn retransmits_cached_packets_as_rtx() {
+        let mut cache = RtxCache::new(4);
+        cache.set_rtx_payload_type(96, 97);
+        cache.set_rtx_ssrc(0x1111, 0x2222, 500);
+        for seq in 10..15 {
+            cache.store(&media(seq)).unwrap();
+        }
+        assert_eq!(cache.cached(0x1111), 4);
+
+        // 10 was evicted, 99 was never sent.
+        let rtx = cache.retransmit(0x1111, &[10, 12, 99, 14]);
+        assert_eq!(rtx.len(), 2);
+        let first = RtpPacket::parse(&rtx[0]).unwrap();
+        assert_eq!(first.header.ssrc, 0x2222);
+        assert_eq!(first.header.payload_type, 97);
+        assert_eq!(first.header.sequence_number, 500);
+        assert_eq!(first.header.timestamp, 9000);
+        assert!(first.header.marker);
+        assert_eq!(first.payload, &[0, 12, 12, 0xEE]);
+        assert_eq!(
+            RtpPacket::parse(&rtx[1]).unwrap().header.sequence_number,
+            501
+        );
+    }
+
+    #[test]
+    fn skips_streams_without_rtx_configuration() {
+        let mut cache = RtxCache::new(4);
+        cache.store(&media(1)).unwrap();
+        assert!(cache.retransmit(0x1111, &[1]).is_empty());
+        cache.set_rtx_ssrc(0x1111, 0x2222, 0);
+        // Still no RTX payload type for PT 96.
+        assert!(cache.retransmit(0x1111, &[1]).is_empty());
+        assert!(matches!(
+            cache.store(&[0x80]),
+            Err(RtpError::BufferTooShort { offset: 0, .. })
+        ));
+    }
+}
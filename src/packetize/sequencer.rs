@@ -0,0 +1,172 @@
+use crate::{
+    clock::MediaTime,
+    rtp::{CsrcList, RtpHeader},
+};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// Send-side RTP header state for one stream: SSRC, sequence numbers and
+/// the media clock mapping used to derive timestamps.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RtpSequencer {
+    ssrc: u32,
+    payload_type: u8,
+    clock_rate: u32,
+    next_sequence_number: u16,
+    initial_timestamp: u32,
+}
+
+impl RtpSequencer {
+    /// Creates a sequencer with random SSRC, initial sequence number and
+    /// initial timestamp, as RFC 3550 recommends.
+    ///
+    /// The values come from the std hasher seed and are not suitable where
+    /// unpredictability matters for security.
+    #[cfg(feature = "std")]
+    pub fn new(payload_type: u8, clock_rate: u32) -> Self {
+        let r = random_u64();
+        let r2 = random_u64();
+        Self::with_initial_state(
+            payload_type,
+            clock_rate,
+            r as u32,
+            (r >> 32) as u16,
+            r2 as u32,
+        )
+    }
+
+    /// Creates a sequencer with explicit initial values.
+    pub fn with_initial_state(
+        payload_type: u8,
+        clock_rate: u32,
+        ssrc: u32,
+        sequence_number: u16,
+        initial_timestamp: u32,
+    ) -> Self {
+        Self {
+            ssrc,
+            payload_type,
+            clock_rate,
+            next_sequence_number: sequence_number,
+            initial_timestamp,
+        }
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn payload_type(&self) -> u8 {
+        self.payload_type
+    }
+
+    pub fn clock_rate(&self) -> u32 {
+        self.clock_rate
+    }
+
+    /// Sequence number the next stamped packet will carry.
+    pub fn next_sequence_number(&self) -> u16 {
+        self.next_sequence_number
+    }
+
+    pub fn initial_timestamp(&self) -> u32 {
+        self.initial_timestamp
+    }
+
+    /// RTP timestamp of media captured `elapsed` after the stream started.
+    pub fn timestamp_at(&self, elapsed: Duration) -> u32 {
+        let ticks = MediaTime::new(self.clock_rate).duration_to_ticks(elapsed);
+        // Truncation is the intended modulo-2^32 wrap.
+        self.initial_timestamp.wrapping_add(ticks as u32)
+    }
+
+    /// Serializes `payloads` as one frame's packets at `timestamp`, with
+    /// consecutive sequence numbers and the marker bit on the last packet.
+    pub fn stamp(&mut self, payloads: Vec<Vec<u8>>, timestamp: u32) -> Vec<Vec<u8>> {
+        let count = payloads.len();
+        let mut packets = Vec::with_capacity(count);
+        for (i, payload) in payloads.into_iter().enumerate() {
+            let header = RtpHeader {
+                version: 2,
+                padding: false,
+                extension: false,
+                csrc_count: 0,
+                marker: i + 1 == count,
+                payload_type: self.payload_type,
+                sequence_number: self.next_sequence_number,
+                timestamp,
+                ssrc: self.ssrc,
+                csrcs: CsrcList::new(),
+                extension_header: None,
+            };
+            let mut pkt = Vec::with_capacity(header.serialized_len() + payload.len());
+            header.write(&mut pkt);
+            pkt.extend_from_slice(&payload);
+            packets.push(pkt);
+            self.next_sequence_number = self.next_sequence_number.wrapping_add(1);
+        }
+        packets
+    }
+
+    /// Like [`stamp`](Self::stamp), deriving the timestamp from `elapsed`.
+    pub fn stamp_at(&mut self, payloads: Vec<Vec<u8>>, elapsed: Duration) -> Vec<Vec<u8>> {
+        let timestamp = self.timestamp_at(elapsed);
+        self.stamp(payloads, timestamp)
+    }
+}
+
+#[cfg(feature = "std")]
+fn random_u64() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    // Every RandomState gets fresh keys, so hashing nothing yields a new value.
+    std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtp::RtpPacket;
+
+    #[test]
+    fn timestamps_follow_clock_rate_and_wrap() {
+        let s = RtpSequencer::with_initial_state(96, 90_000, 1, 0, u32::MAX - 10);
+        assert_eq!(s.timestamp_at(Duration::ZERO), u32::MAX - 10);
+        assert_eq!(s.timestamp_at(Duration::from_millis(1)), 79);
+        let audio = RtpSequencer::with_initial_state(111, 48_000, 1, 0, 0);
+        assert_eq!(audio.timestamp_at(Duration::from_millis(20)), 960);
+    }
+
+    #[test]
+    fn stamps_marker_and_sequence_numbers() {
+        let mut s = RtpSequencer::with_initial_state(100, 90_000, 0xCAFE, 65535, 1000);
+        let packets = s.stamp_at(
+            alloc::vec![alloc::vec![1], alloc::vec![2]],
+            Duration::from_secs(1),
+        );
+        let parsed: Vec<_> = packets
+            .iter()
+            .map(|b| RtpPacket::parse(b).unwrap())
+            .collect();
+        assert_eq!(parsed[0].header.sequence_number, 65535);
+        assert_eq!(parsed[1].header.sequence_number, 0);
+        assert!(!parsed[0].header.marker && parsed[1].header.marker);
+        assert!(parsed.iter().all(|p| p.header.timestamp == 91_000
+            && p.header.ssrc == 0xCAFE
+            && p.header.payload_type == 100));
+        assert_eq!(s.next_sequence_number(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn random_initial_state_differs() {
+        let a = RtpSequencer::new(96, 90_000);
+        let b = RtpSequencer::new(96, 90_000);
+        assert_ne!(
+            (a.ssrc(), a.next_sequence_number(), a.initial_timestamp()),
+            (b.ssrc(), b.next_sequence_number(), b.initial_timestamp())
+        );
+    }
+}
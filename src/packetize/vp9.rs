@@ -0,0 +1,226 @@
+use super::Packetizer;
+use crate::codecs::vp9::{Vp9LayerIndices, Vp9PayloadDesc, Vp9ScalabilityStructure};
+use alloc::vec::Vec;
+
+/// VP9 payload format (RFC 9628) in non-flexible mode.
+///
+/// Every packet carries a 15-bit picture id that increments once per
+/// picture and wraps at 2^15. Frames passed through [`Packetizer::packetize`]
+/// are sent without layer indices; spatial/temporal layer frames of one
+/// picture go through [`Vp9Packetizer::packetize_layers`].
+#[derive(Debug, Clone, Default)]
+pub struct Vp9Packetizer {
+    picture_id: u16,
+    tl0_pic_idx: u8,
+    scalability: Option<Vp9ScalabilityStructure>,
+}
+
+impl Vp9Packetizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts picture ids at `picture_id` (masked to 15 bits).
+    pub fn with_picture_id(picture_id: u16) -> Self {
+        Self {
+            picture_id: picture_id & 0x7FFF,
+            ..Self::default()
+        }
+    }
+
+    /// Picture id the next picture will carry.
+    pub fn picture_id(&self) -> u16 {
+        self.picture_id
+    }
+
+    /// Scalability structure sent in the first packet of every key picture.
+    pub fn set_scalability_structure(&mut self, ss: Option<Vp9ScalabilityStructure>) {
+        self.scalability = ss;
+    }
+
+    /// Packetizes the layer frames of one picture, lowest spatial layer first.
+    ///
+    /// All layer frames share the picture id; the TL0PICIDX advances on
+    /// pictures whose temporal id is 0.
+    pub fn packetize_layers(
+        &mut self,
+        layers: &[(&[u8], Vp9LayerIndices)],
+        max_payload_size: usize,
+    ) -> Vec<Vec<u8>> {
+        let temporal_id = layers.first().map_or(0, |(_, l)| l.temporal_id);
+        if temporal_id == 0 {
+            self.tl0_pic_idx = self.tl0_pic_idx.wrapping_add(1);
+        }
+        let frames: Vec<(&[u8], Option<Vp9LayerIndices>)> =
+            layers.iter().map(|(data, l)| (*data, Some(*l))).collect();
+        self.packetize_picture(&frames, max_payload_size)
+    }
+
+    fn packetize_picture(
+        &mut self,
+        frames: &[(&[u8], Option<Vp9LayerIndices>)],
+        max_payload_size: usize,
+    ) -> Vec<Vec<u8>> {
+        let key_picture = frames.first().is_some_and(|(data, _)| is_key_frame(data));
+        let mut out = Vec::new();
+        for (n, (data, layer)) in frames.iter().enumerate() {
+            let mut desc = Vp9PayloadDesc {
+                p_bit: !key_picture,
+                // Nothing above the last layer frame predicts from it.
+                z_bit: layer.is_some() && n + 1 == frames.len(),
+                picture_id: Some(self.picture_id),
+                layer: *layer,
+                tl0_pic_idx: layer.map(|_| self.tl0_pic_idx),
+                ..Default::default()
+            };
+            let mut rest = *data;
+            let mut first = true;
+            loop {
+                desc.b_bit = first;
+                desc.scalability = if first && n == 0 && key_picture {
+                    self.scalability.clone()
+                } else {
+                    None
+                };
+                let mut payload = Vec::new();
+                desc.write(&mut payload);
+                let room = max_payload_size.saturating_sub(payload.len()).max(1);
+                let take = room.min(rest.len());
+                if take == rest.len() {
+                    payload[0] |= 0x04; // E
+                }
+                payload.extend_from_slice(&rest[..take]);
+                out.push(payload);
+                rest = &rest[take..];
+                first = false;
+                if rest.is_empty() {
+                    break;
+                }
+            }
+        }
+        self.picture_id = (self.picture_id + 1) & 0x7FFF;
+        out
+    }
+}
+
+impl Packetizer for Vp9Packetizer {
+    fn packetize(&mut self, frame: &[u8], max_payload_size: usize) -> Vec<Vec<u8>> {
+        self.packetize_picture(&[(frame, None)], max_payload_size)
+    }
+}
+
+// Reads frame_type from the start of the uncompressed header.
+fn is_key_frame(frame: &[u8]) -> bool {
+    let Some(&b) = frame.first() else {
+        return false;
+    };
+    if b >> 6 != 2 {
+        return false;
+    }
+    let profile = ((b >> 5) & 1) | (((b >> 4) & 1) << 1);
+    // Profile 3 has a reserved zero bit before show_existing_frame.
+    let show_existing_bit = if profile == 3 { 2 } else { 3 };
+    let show_existing = (b >> show_existing_bit) & 1 == 1;
+    !show_existing && (b >> (show_existing_bit - 1)) & 1 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::Codec,
+        packetize::{FramePacketizer, PacketizerConfig},
+        reassemble::FrameReassembler,
+        rtp::RtpPacket,
+    };
+
+    // Uncompressed header prefixes for profile 0: key frame and inter frame.
+    const KEY: u8 = 0b1000_0010;
+    const INTER: u8 = 0b1000_0110;
+
+    fn frame(first: u8, len: usize) -> Vec<u8> {
+        let mut f = vec![first];
+        f.extend((1..len).map(|i| i as u8));
+        f
+    }
+
+    #[test]
+    fn descriptors_mark_fragments_and_wrap_picture_id() {
+        let mut p = Vp9Packetizer::with_picture_id(0x7FFF);
+        let key = p.packetize(&frame(KEY, 25), 10);
+        let descs: Vec<_> = key
+            .iter()
+            .map(|b| Vp9PayloadDesc::parse(b).unwrap().0)
+            .collect();
+        assert!(key.iter().all(|b| b.len() <= 10));
+        assert!(descs
+            .iter()
+            .all(|d| d.picture_id == Some(0x7FFF) && !d.p_bit));
+        assert_eq!(
+            descs.iter().map(|d| (d.b_bit, d.e_bit)).collect::<Vec<_>>(),
+            [(true, false), (false, false), (false, false), (false, true)]
+        );
+
+        let inter = p.packetize(&frame(INTER, 5), 10);
+        let (desc, _) = Vp9PayloadDesc::parse(&inter[0]).unwrap();
+        assert_eq!(desc.picture_id, Some(0));
+        assert!(desc.p_bit && desc.b_bit && desc.e_bit);
+        assert_eq!(desc.layer, None);
+    }
+
+    #[test]
+    fn svc_layers_share_picture_id_and_carry_indices() {
+        let mut p = Vp9Packetizer::new();
+        p.set_scalability_structure(Some(Vp9ScalabilityStructure {
+            spatial_layers: 2,
+            resolutions: Some(vec![(320, 180), (640, 360)]),
+            picture_group: None,
+        }));
+        let base = frame(KEY, 8);
+        let upper = frame(INTER, 8);
+        let upper_layer = Vp9LayerIndices {
+            spatial_id: 1,
+            inter_layer_dependency: true,
+            ..Default::default()
+        };
+        let payloads = p.packetize_layers(
+            &[(&base, Vp9LayerIndices::default()), (&upper, upper_layer)],
+            1200,
+        );
+        let descs: Vec<_> = payloads
+            .iter()
+            .map(|b| Vp9PayloadDesc::parse(b).unwrap())
+            .collect();
+        assert_eq!(descs.len(), 2);
+        assert!(descs[0].0.scalability.is_some() && descs[1].0.scalability.is_none());
+        assert_eq!(descs[1].0.layer, Some(upper_layer));
+        assert!(!descs[0].0.z_bit && descs[1].0.z_bit);
+        assert!(descs.iter().all(|(d, _)| d.picture_id == Some(0)
+            && d.tl0_pic_idx == Some(1)
+            && d.b_bit
+            && d.e_bit));
+        assert_eq!(&payloads[1][descs[1].1..], &upper[..]);
+    }
+
+    #[test]
+    fn round_trip_through_reassembler() {
+        let expected = frame(KEY, 500);
+        let mut p = FramePacketizer::new(
+            Codec::Vp9,
+            PacketizerConfig {
+                mtu: 120,
+                ..Default::default()
+            },
+        );
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Vp9)
+            .build()
+            .unwrap();
+        let frames: Vec<_> = p
+            .packetize(&expected, 3000)
+            .iter()
+            .filter_map(|b| r.push_packet(&RtpPacket::parse(b).unwrap()))
+            .collect();
+        assert_eq!(frames, [expected]);
+    }
+}
@@ -0,0 +1,366 @@
+//! Worker-pool pipeline for recording thousands of streams.
+//!
+//! [`Pipeline`] takes the datagrams of RTP/RTCP-multiplexed ports, demuxes
+//! RTP from RTCP and hands each packet to the worker owning its SSRC (see
+//! [`shard_index`]), which reassembles it in a [`StreamSet`] and passes the
+//! frame to a handler on the worker thread, so frames can be written out
+//! without funneling them through one consumer. RTCP BYEs end their
+//! streams on the owning worker.
+//!
+//! Handoff goes through one bounded channel per worker, lock-free while it
+//! neither runs full nor empty. A full queue drops the packet instead of
+//! stalling the receive loop, and reports it. Workers count what they do
+//! in atomics that [`Pipeline::stats`] reads without pausing them.
+//! [`ShardedReassembler`](crate::shard::ShardedReassembler) is the simpler
+//! variant, with unbounded queues and frames returned over a channel.
+
+use crate::{
+    reassemble::ConfigError,
+    rtcp::{is_rtcp, RtcpPacket},
+    rtp::{RtpError, RtpPacket},
+    shard::{shard_index, StreamFrame, StreamSet},
+};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    vec::Vec,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineConfig {
+    pub workers: usize,
+    /// Packets queued per worker before new ones are dropped.
+    pub queue_capacity: usize,
+}
+
+impl Default for PipelineConfig {
+    /// One worker per available core, with room for 4096 packets each.
+    fn default() -> Self {
+        Self {
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            queue_capacity: 4096,
+        }
+    }
+}
+
+impl PipelineConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.workers == 0 {
+            return Err(ConfigError::ZeroLimit("workers"));
+        }
+        if self.queue_capacity == 0 {
+            return Err(ConfigError::ZeroLimit("queue_capacity"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineError {
+    Rtp(RtpError),
+    /// The queue of the worker owning the packet's SSRC is full; the packet
+    /// was dropped.
+    QueueFull {
+        shard: usize,
+    },
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Rtp(e) => write!(f, "invalid RTP packet: {}", e),
+            PipelineError::QueueFull { shard } => write!(f, "queue of worker {} is full", shard),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+impl From<RtpError> for PipelineError {
+    fn from(e: RtpError) -> Self {
+        PipelineError::Rtp(e)
+    }
+}
+
+/// What one worker did so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShardStats {
+    /// RTP packets reassembled.
+    pub packets: u64,
+    /// RTP packets dropped because the worker's queue was full.
+    pub queue_full: u64,
+    pub frames: u64,
+    pub frame_bytes: u64,
+    /// Streams ended by an RTCP BYE.
+    pub byes: u64,
+    /// Streams the worker currently reassembles.
+    pub streams: u64,
+}
+
+impl ShardStats {
+    fn add(mut self, other: &ShardStats) -> Self {
+        self.packets += other.packets;
+        self.queue_full += other.queue_full;
+        self.frames += other.frames;
+        self.frame_bytes += other.frame_bytes;
+        self.byes += other.byes;
+        self.streams += other.streams;
+        self
+    }
+}
+
+/// Per-worker [`ShardStats`], in shard order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    pub shards: Vec<ShardStats>,
+}
+
+impl PipelineStats {
+    /// The sum over all workers.
+    pub fn total(&self) -> ShardStats {
+        self.shards
+            .iter()
+            .fold(ShardStats::default(), ShardStats::add)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    packets: AtomicU64,
+    queue_full: AtomicU64,
+    frames: AtomicU64,
+    frame_bytes: AtomicU64,
+    byes: AtomicU64,
+    streams: AtomicU64,
+}
+
+impl Counters {
+    fn bump(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> ShardStats {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ShardStats {
+            packets: get(&self.packets),
+            queue_full: get(&self.queue_full),
+            frames: get(&self.frames),
+            frame_bytes: get(&self.frame_bytes),
+            byes: get(&self.byes),
+            streams: get(&self.streams),
+        }
+    }
+}
+
+enum Job {
+    Packet(Vec<u8>),
+    Bye(u32),
+}
+
+/// Reassembles many streams on a pool of worker threads; see the
+/// [module documentation](self). Dropping it processes the queued packets,
+/// then stops and joins the workers.
+pub struct Pipeline {
+    inputs: Vec<SyncSender<Job>>,
+    counters: Vec<Arc<Counters>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("workers", &self.counters.len())
+            .finish()
+    }
+}
+
+impl Pipeline {
+    /// Starts `config.workers` workers. `make` builds the `StreamSet` of
+    /// each, given its shard index; `on_frame` is called on the worker
+    /// threads with every completed frame.
+    pub fn spawn(
+        config: PipelineConfig,
+        make: impl Fn(usize) -> StreamSet,
+        on_frame: impl Fn(StreamFrame) + Send + Sync + 'static,
+    ) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let on_frame = Arc::new(on_frame);
+        let mut pipeline = Self {
+            inputs: Vec::new(),
+            counters: Vec::new(),
+            handles: Vec::new(),
+        };
+        for shard in 0..config.workers {
+            let (input, jobs) = mpsc::sync_channel(config.queue_capacity);
+            let stats = Arc::new(Counters::default());
+            let mut set = make(shard);
+            let on_frame = on_frame.clone();
+            pipeline.counters.push(stats.clone());
+            pipeline.inputs.push(input);
+            pipeline.handles.push(thread::spawn(move || {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::debug_span!("pipeline", shard).entered();
+                for job in jobs {
+                    match job {
+                        Job::Packet(bytes) => {
+                            let Ok(pkt) = RtpPacket::parse(&bytes) else {
+                                continue;
+                            };
+                            Counters::bump(&stats.packets, 1);
+                            if let Some(data) = set.push_packet(&pkt) {
+                                Counters::bump(&stats.frames, 1);
+                                Counters::bump(&stats.frame_bytes, data.len() as u64);
+                                on_frame(StreamFrame {
+                                    ssrc: pkt.header.ssrc,
+                                    timestamp: pkt.header.timestamp,
+                                    data,
+                                });
+                            }
+                        }
+                        Job::Bye(ssrc) => {
+                            if set.remove(ssrc).is_some() {
+                                Counters::bump(&stats.byes, 1);
+                            }
+                        }
+                    }
+                    stats.streams.store(set.len() as u64, Ordering::Relaxed);
+                }
+            }));
+        }
+        Ok(pipeline)
+    }
+
+    pub fn workers(&self) -> usize {
+        self.counters.len()
+    }
+
+    /// Routes a datagram: an RTP packet to the worker owning its SSRC, the
+    /// BYEs of an RTCP compound packet to the workers of the streams they
+    /// end. Other RTCP is ignored.
+    pub fn push_datagram(&self, datagram: Vec<u8>) -> Result<(), PipelineError> {
+        if is_rtcp(&datagram) {
+            let byes = RtcpPacket::parse_compound(&datagram).map_while(|packet| match packet {
+                Ok(RtcpPacket::Bye(bye)) => Some(Some(bye)),
+                Ok(_) => Some(None),
+                Err(_e) => {
+                    trace_event!(DEBUG, error = %_e, "malformed rtcp packet");
+                    None
+                }
+            });
+            for ssrc in byes.flatten().flat_map(|bye| bye.sources()) {
+                let shard = shard_index(ssrc, self.inputs.len());
+                // Rare and needed to free the stream, so worth waiting for.
+                let _ = self.inputs[shard].send(Job::Bye(ssrc));
+            }
+            return Ok(());
+        }
+        let ssrc = RtpPacket::parse(&datagram)?.header.ssrc;
+        let shard = shard_index(ssrc, self.inputs.len());
+        match self.inputs[shard].try_send(Job::Packet(datagram)) {
+            Ok(()) | Err(TrySendError::Disconnected(_)) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                trace_event!(DEBUG, ssrc, shard, "pipeline queue full, dropping packet");
+                Counters::bump(&self.counters[shard].queue_full, 1);
+                Err(PipelineError::QueueFull { shard })
+            }
+        }
+    }
+
+    /// Current per-worker counters.
+    pub fn stats(&self) -> PipelineStats {
+        PipelineStats {
+            shards: self.counters.iter().map(|c| c.snapshot()).collect(),
+        }
+    }
+
+    /// Processes all queued packets, stops the workers and returns their
+    /// final counters.
+    pub fn finish(mut self) -> PipelineStats {
+        self.join();
+        self.stats()
+    }
+
+    fn join(&mut self) {
+        // Closing the inputs ends the workers' loops.
+        self.inputs.clear();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Pipeline {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::Codec,
+        test_utils::{avc, StreamBuilder},
+    };
+    use std::sync::Mutex;
+
+    #[test]
+    fn workers_reassemble_every_stream_and_end_them_on_bye() {
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let sink = frames.clone();
+        let config = PipelineConfig {
+            workers: 4,
+            queue_capacity: 1024,
+        };
+        let make = |_| {
+            let mut set = StreamSet::new();
+            set.set_codec(Some(Codec::Avc));
+            set
+        };
+        let on_frame = move |frame: StreamFrame| sink.lock().unwrap().push(frame);
+        let pipeline = Pipeline::spawn(config, make, on_frame).unwrap();
+        for ssrc in 0..64u32 {
+            let mut builder = StreamBuilder::new(ssrc);
+            for i in 0..3u8 {
+                builder = builder.frame(&avc::fu_a(&[0x65, ssrc as u8, i, 0xAA], 2));
+            }
+            for p in builder.build() {
+                pipeline.push_datagram(p).unwrap();
+            }
+        }
+        // BYE from SSRCs 5 and 6.
+        let bye = [0x82, 203, 0, 2, 0, 0, 0, 5, 0, 0, 0, 6];
+        pipeline.push_datagram(bye.to_vec()).unwrap();
+        assert!(matches!(
+            pipeline.push_datagram(std::vec![0x80]),
+            Err(PipelineError::Rtp(RtpError::BufferTooShort { .. }))
+        ));
+
+        let stats = pipeline.finish();
+        assert_eq!(stats.shards.len(), 4);
+        let total = stats.total();
+        assert_eq!((total.packets, total.frames), (64 * 3 * 2, 64 * 3));
+        assert_eq!((total.byes, total.streams, total.queue_full), (2, 62, 0));
+        assert!(stats.shards.iter().all(|s| s.frames > 0));
+        let mut frames = frames.lock().unwrap();
+        frames.sort_by_key(|f| (f.ssrc, f.timestamp));
+        assert_eq!(frames[3 * 7 + 2].data, [0, 0, 0, 1, 0x65, 7, 2, 0xAA]);
+        assert_eq!(
+            Pipeline::spawn(
+                PipelineConfig {
+                    workers: 0,
+                    ..config
+                },
+                make,
+                |_| {}
+            )
+            .err(),
+            Some(ConfigError::ZeroLimit("workers"))
+        );
+    }
+}
@@ -0,0 +1,16 @@
+//! The types most applications need, for glob import:
+//!
+//! ```
+//! use rtpar::prelude::*;
+//!
+//! let mut reassembler = FrameReassembler::builder().codec(Codec::Avc).build().unwrap();
+//! # let _ = &mut reassembler;
+//! ```
+
+pub use crate::codec::Codec;
+pub use crate::frame::{
+    FrameAnalyzer, FramePacketizer, FrameReassembler, FrameStatus, Packetizer, PushResult,
+    ReassemblerConfig,
+};
+pub use crate::packet::{RtpError, RtpPacket};
+pub use crate::session::StreamSet;
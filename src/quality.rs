@@ -0,0 +1,397 @@
+//! Per-SSRC quality reports over fixed intervals, after the RTCP XR
+//! statistics summary and burst/gap metrics (RFC 3611 sections 4.6, 4.7).
+//!
+//! [`QualityMonitor`] is fed every received packet header and every frame
+//! outcome. Each SSRC's interval starts with its first packet; once a
+//! packet or [`QualityMonitor::poll`] finds it over, a [`QualityReport`]
+//! with loss, duplicates, burst/gap loss density, jitter and dropped frames
+//! is queued. Reports derive `serde` traits for upload.
+//!
+//! Losses are classified as in RFC 3611: a burst runs from one loss to
+//! another with fewer than [`GMIN`] packets received in between; other
+//! losses fall into gaps. Packets are classified in arrival order, so a
+//! reordered packet counts as lost there, though not in the loss totals.
+
+use crate::{reassemble::DropReason, rtp::RtpHeader, stats::ReceiverStats};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec::Vec,
+};
+use core::time::Duration;
+
+/// Packets received in a row that end a burst, the RFC 3611 default.
+pub const GMIN: u64 = 16;
+
+/// Quality of one SSRC over one interval.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QualityReport {
+    pub ssrc: u32,
+    /// Arrival of the interval's first packet, in microseconds.
+    pub start_us: u64,
+    /// End of the interval, in microseconds.
+    pub end_us: u64,
+    /// First and last sequence number the interval covers.
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    pub expected: u64,
+    /// Distinct packets received.
+    pub received: u64,
+    pub lost: u64,
+    pub duplicates: u64,
+    /// Packets that arrived after their interval was reported.
+    pub late: u64,
+    /// `lost / expected`.
+    pub loss_rate: f64,
+    pub bursts: u64,
+    /// Fraction of the packets within bursts that were lost.
+    pub burst_density: f64,
+    /// Fraction of the packets outside bursts that were lost.
+    pub gap_density: f64,
+    /// RFC 3550 interarrival jitter after each packet, in timestamp units.
+    pub jitter_min: u32,
+    pub jitter_max: u32,
+    pub jitter_mean: f64,
+    pub jitter_dev: f64,
+    pub frames: u64,
+    pub frames_dropped: BTreeMap<DropReason, u64>,
+}
+
+// Burst/gap classification of a sequence of received and lost packets.
+#[derive(Debug, Clone, Default)]
+struct BurstGap {
+    // Received since the last loss.
+    run: u64,
+    seen_loss: bool,
+    // Losses and packets of the cluster the last loss belongs to.
+    cluster: (u64, u64),
+    bursts: u64,
+    burst: (u64, u64),
+    gap: (u64, u64),
+}
+
+impl BurstGap {
+    fn received(&mut self) {
+        self.run += 1;
+    }
+
+    fn lost(&mut self) {
+        if self.seen_loss && self.run < GMIN {
+            self.cluster.0 += 1;
+            self.cluster.1 += self.run + 1;
+        } else {
+            self.close_cluster();
+            self.gap.1 += self.run;
+            self.cluster = (1, 1);
+        }
+        self.seen_loss = true;
+        self.run = 0;
+    }
+
+    fn close_cluster(&mut self) {
+        let (losses, packets) = core::mem::take(&mut self.cluster);
+        // A lone loss lies in a gap.
+        let totals = if losses > 1 {
+            self.bursts += 1;
+            &mut self.burst
+        } else {
+            &mut self.gap
+        };
+        totals.0 += losses;
+        totals.1 += packets;
+    }
+
+    fn finish(mut self) -> (u64, f64, f64) {
+        self.close_cluster();
+        self.gap.1 += self.run;
+        let density = |(lost, packets): (u64, u64)| {
+            if packets == 0 {
+                0.0
+            } else {
+                lost as f64 / packets as f64
+            }
+        };
+        (self.bursts, density(self.burst), density(self.gap))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Interval {
+    start: Duration,
+    // Lowest extended sequence number the interval accounts for.
+    first_seq: i64,
+    highest_seq: i64,
+    seen: BTreeSet<i64>,
+    duplicates: u64,
+    late: u64,
+    burst_gap: BurstGap,
+    jitter: (u32, u32, f64, f64),
+    jitter_samples: u64,
+    frames: u64,
+    frames_dropped: BTreeMap<DropReason, u64>,
+}
+
+impl Interval {
+    fn new(start: Duration, first_seq: i64) -> Self {
+        Self {
+            start,
+            first_seq,
+            highest_seq: first_seq - 1,
+            seen: BTreeSet::new(),
+            duplicates: 0,
+            late: 0,
+            burst_gap: BurstGap::default(),
+            jitter: (u32::MAX, 0, 0.0, 0.0),
+            jitter_samples: 0,
+            frames: 0,
+            frames_dropped: BTreeMap::new(),
+        }
+    }
+
+    fn on_packet(&mut self, seq: i64, jitter: u32) {
+        if seq < self.first_seq {
+            self.late += 1;
+        } else if !self.seen.insert(seq) {
+            self.duplicates += 1;
+        } else if seq > self.highest_seq {
+            for _ in self.highest_seq + 1..seq {
+                self.burst_gap.lost();
+            }
+            self.burst_gap.received();
+            self.highest_seq = seq;
+        }
+        // Running mean and sum of squared deviations (Welford).
+        let (min, max, mean, m2) = &mut self.jitter;
+        self.jitter_samples += 1;
+        let delta = jitter as f64 - *mean;
+        *mean += delta / self.jitter_samples as f64;
+        *m2 += delta * (jitter as f64 - *mean);
+        *min = (*min).min(jitter);
+        *max = (*max).max(jitter);
+    }
+
+    fn report(self, ssrc: u32, end: Duration) -> QualityReport {
+        let expected = (self.highest_seq - self.first_seq + 1).max(0) as u64;
+        let received = self.seen.len() as u64;
+        let lost = expected.saturating_sub(received);
+        let (bursts, burst_density, gap_density) = self.burst_gap.finish();
+        let (min, max, mean, m2) = self.jitter;
+        let samples = self.jitter_samples.max(1) as f64;
+        QualityReport {
+            ssrc,
+            start_us: self.start.as_micros() as u64,
+            end_us: end.as_micros() as u64,
+            begin_seq: self.first_seq as u16,
+            end_seq: self.highest_seq as u16,
+            expected,
+            received,
+            lost,
+            duplicates: self.duplicates,
+            late: self.late,
+            loss_rate: if expected == 0 {
+                0.0
+            } else {
+                lost as f64 / expected as f64
+            },
+            bursts,
+            burst_density,
+            gap_density,
+            jitter_min: min.min(max),
+            jitter_max: max,
+            jitter_mean: mean,
+            jitter_dev: sqrt(m2 / samples),
+            frames: self.frames,
+            frames_dropped: self.frames_dropped,
+        }
+    }
+}
+
+// `f64::sqrt` needs `std`; Newton's method is plenty for a deviation.
+fn sqrt(x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let mut guess = x.max(1.0);
+    for _ in 0..64 {
+        let next = 0.5 * (guess + x / guess);
+        if (next - guess).abs() <= f64::EPSILON * guess {
+            break;
+        }
+        guess = next;
+    }
+    guess
+}
+
+#[derive(Debug, Clone)]
+struct StreamQuality {
+    stats: ReceiverStats,
+    // Highest extended sequence number so far.
+    highest_seq: i64,
+    // First sequence number of the next interval, after the last reported.
+    next_seq: Option<i64>,
+    interval: Option<Interval>,
+}
+
+impl StreamQuality {
+    fn extend(&self, seq: u16) -> i64 {
+        self.highest_seq + seq.wrapping_sub(self.highest_seq as u16) as i16 as i64
+    }
+
+    fn close(&mut self, ssrc: u32, end: Duration) -> Option<QualityReport> {
+        let interval = self.interval.take()?;
+        self.next_seq = Some(interval.highest_seq + 1);
+        Some(interval.report(ssrc, end))
+    }
+}
+
+/// Builds [`QualityReport`]s for every SSRC it is fed.
+#[derive(Debug, Clone)]
+pub struct QualityMonitor {
+    clock_rate: u32,
+    interval: Duration,
+    streams: BTreeMap<u32, StreamQuality>,
+    ready: Vec<QualityReport>,
+}
+
+impl QualityMonitor {
+    /// `clock_rate` is the RTP timestamp rate of the streams; `interval`
+    /// the length of each report.
+    pub fn new(clock_rate: u32, interval: Duration) -> Self {
+        Self {
+            clock_rate,
+            interval,
+            streams: BTreeMap::new(),
+            ready: Vec::new(),
+        }
+    }
+
+    /// Records a packet that arrived at `arrival` (any monotonic clock).
+    pub fn on_packet(&mut self, header: &RtpHeader, arrival: Duration) {
+        self.close_expired(arrival);
+        let clock_rate = self.clock_rate;
+        let stream = self
+            .streams
+            .entry(header.ssrc)
+            .or_insert_with(|| StreamQuality {
+                stats: ReceiverStats::new(clock_rate),
+                highest_seq: header.sequence_number as i64,
+                next_seq: None,
+                interval: None,
+            });
+        stream.stats.on_packet(header, arrival);
+        let seq = stream.extend(header.sequence_number);
+        let first_seq = stream.next_seq.unwrap_or(seq);
+        let interval = stream
+            .interval
+            .get_or_insert_with(|| Interval::new(arrival, first_seq));
+        interval.on_packet(seq, stream.stats.jitter());
+        stream.highest_seq = stream.highest_seq.max(seq);
+    }
+
+    /// Records a frame of `ssrc` that was completed.
+    pub fn on_frame(&mut self, ssrc: u32) {
+        if let Some(interval) = self.interval_mut(ssrc) {
+            interval.frames += 1;
+        }
+    }
+
+    /// Records a frame of `ssrc` that was dropped.
+    pub fn on_frame_dropped(&mut self, ssrc: u32, reason: DropReason) {
+        if let Some(interval) = self.interval_mut(ssrc) {
+            *interval.frames_dropped.entry(reason).or_default() += 1;
+        }
+    }
+
+    fn interval_mut(&mut self, ssrc: u32) -> Option<&mut Interval> {
+        self.streams.get_mut(&ssrc)?.interval.as_mut()
+    }
+
+    /// Closes the intervals over by `now` and returns every queued report.
+    pub fn poll(&mut self, now: Duration) -> Vec<QualityReport> {
+        self.close_expired(now);
+        core::mem::take(&mut self.ready)
+    }
+
+    /// Closes every open interval at `now` and returns every queued
+    /// report, e.g. when a session ends.
+    pub fn finish(&mut self, now: Duration) -> Vec<QualityReport> {
+        for (&ssrc, stream) in &mut self.streams {
+            self.ready.extend(stream.close(ssrc, now));
+        }
+        core::mem::take(&mut self.ready)
+    }
+
+    /// Stops following `ssrc`, e.g. after an RTCP BYE, reporting its open
+    /// interval at `now`.
+    pub fn remove(&mut self, ssrc: u32, now: Duration) {
+        if let Some(mut stream) = self.streams.remove(&ssrc) {
+            self.ready.extend(stream.close(ssrc, now));
+        }
+    }
+
+    fn close_expired(&mut self, now: Duration) {
+        for (&ssrc, stream) in &mut self.streams {
+            let Some(start) = stream.interval.as_ref().map(|i| i.start) else {
+                continue;
+            };
+            if now.saturating_sub(start) >= self.interval {
+                self.ready.extend(stream.close(ssrc, start + self.interval));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rtp::RtpPacket, test_utils::RtpPacketBuilder};
+
+    fn push(monitor: &mut QualityMonitor, seq: u16, arrival_ms: u64) {
+        let bytes = RtpPacketBuilder::new()
+            .ssrc(4)
+            .sequence_number(seq)
+            .timestamp(seq as u32 * 3000)
+            .payload(&[0x65])
+            .build();
+        let header = RtpPacket::parse(&bytes).unwrap().header;
+        monitor.on_packet(&header, Duration::from_millis(arrival_ms));
+    }
+
+    #[test]
+    fn reports_loss_bursts_and_gaps_per_interval() {
+        let mut monitor = QualityMonitor::new(90_000, Duration::from_secs(1));
+        // 100 packets over the first second: a burst losing 3 of 5 around
+        // 20, a lone loss at 60 and a duplicate.
+        for seq in 0..100u16 {
+            if [20, 21, 23, 60].contains(&seq) {
+                continue;
+            }
+            push(&mut monitor, seq, seq as u64 * 10);
+            if seq == 50 {
+                push(&mut monitor, seq, seq as u64 * 10);
+            }
+        }
+        monitor.on_frame(4);
+        monitor.on_frame_dropped(4, DropReason::SequenceGap);
+        assert!(monitor.poll(Duration::from_millis(999)).is_empty());
+        // Closes the first interval and starts the second.
+        push(&mut monitor, 100, 1_000);
+
+        let reports = monitor.poll(Duration::from_millis(1_000));
+        assert_eq!(reports.len(), 1);
+        let r = &reports[0];
+        assert_eq!((r.ssrc, r.start_us, r.end_us), (4, 0, 1_000_000));
+        assert_eq!((r.begin_seq, r.end_seq), (0, 99));
+        assert_eq!((r.expected, r.received, r.lost), (100, 96, 4));
+        assert_eq!(r.duplicates, 1);
+        assert_eq!(r.bursts, 1);
+        assert_eq!(r.burst_density, 3.0 / 4.0);
+        assert_eq!(r.gap_density, 1.0 / 96.0);
+        assert_eq!(r.frames, 1);
+        assert_eq!(r.frames_dropped[&DropReason::SequenceGap], 1);
+
+        let last = monitor.finish(Duration::from_millis(1_500));
+        assert_eq!((last[0].begin_seq, last[0].received), (100, 1));
+        assert_eq!(last[0].loss_rate, 0.0);
+    }
+}
@@ -0,0 +1,441 @@
+//! Uncompressed video over RTP (RFC 4175) with the SMPTE ST 2110-20
+//! conventions.
+//!
+//! Each payload starts with the high half of a 32-bit extended sequence
+//! number and one or more sample row data headers, each placing a run of
+//! pixel groups (pgroups) at a line and pixel offset; the sample data
+//! follows the headers. The marker bit ends a frame or, for interlaced
+//! video, a field. ST 2110-20 fixes the 90 kHz clock and, through ST
+//! 2110-10, derives timestamps from PTP time; see
+//! [`MediaTime::ptp_to_timestamp`](crate::clock::MediaTime::ptp_to_timestamp).
+
+use crate::rtp::RtpPacket;
+use alloc::{vec, vec::Vec};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawVideoError {
+    /// The payload ends inside `header`.
+    Truncated { header: &'static str },
+    /// The sampling and depth have no pgroup here, or the size does not
+    /// fit whole pgroups (or fields).
+    UnsupportedFormat,
+    /// A fmtp parameter is missing or invalid.
+    InvalidFmtp(&'static str),
+    /// A sample row of the frame at `timestamp` lies outside the picture or
+    /// is not made of whole pgroups.
+    RowOutOfBounds { timestamp: u32 },
+    /// Packets of the frame at `timestamp` were lost.
+    MissingPacket { timestamp: u32 },
+}
+
+impl core::fmt::Display for RawVideoError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RawVideoError::Truncated { header } => {
+                write!(f, "raw video payload ends inside {}", header)
+            }
+            RawVideoError::UnsupportedFormat => f.write_str("unsupported raw video format"),
+            RawVideoError::InvalidFmtp(param) => {
+                write!(f, "missing or invalid fmtp parameter {}", param)
+            }
+            RawVideoError::RowOutOfBounds { timestamp } => {
+                write!(
+                    f,
+                    "raw video frame at {} has a row out of bounds",
+                    timestamp
+                )
+            }
+            RawVideoError::MissingPacket { timestamp } => {
+                write!(f, "raw video frame at {} lost packets", timestamp)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RawVideoError {}
+
+/// Color sampling of the `sampling` fmtp parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sampling {
+    /// `YCbCr-4:2:2`.
+    Ycbcr422,
+    /// `YCbCr-4:4:4`.
+    Ycbcr444,
+    /// `RGB`.
+    Rgb,
+}
+
+/// Smallest whole number of bytes holding whole pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelGroup {
+    pub bytes: usize,
+    pub pixels: usize,
+}
+
+/// Picture format of a raw video stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawVideoFormat {
+    pub sampling: Sampling,
+    /// Bits per sample: 8, 10, 12 or 16.
+    pub depth: u8,
+    pub width: u32,
+    /// Lines per frame, both fields together when interlaced.
+    pub height: u32,
+    pub interlaced: bool,
+}
+
+impl RawVideoFormat {
+    /// Reads the `sampling`, `depth`, `width`, `height` and `interlace`
+    /// parameters of an SDP fmtp line; others are ignored.
+    pub fn from_fmtp(fmtp: &str) -> Result<Self, RawVideoError> {
+        let (mut sampling, mut depth, mut width, mut height) = (None, None, None, None);
+        let mut interlaced = false;
+        for param in fmtp.split(';').map(str::trim) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            match key.trim() {
+                "sampling" => {
+                    sampling = Some(match value.trim() {
+                        "YCbCr-4:2:2" => Sampling::Ycbcr422,
+                        "YCbCr-4:4:4" => Sampling::Ycbcr444,
+                        "RGB" => Sampling::Rgb,
+                        _ => return Err(RawVideoError::InvalidFmtp("sampling")),
+                    })
+                }
+                "depth" => depth = value.trim().parse().ok(),
+                "width" => width = value.trim().parse().ok(),
+                "height" => height = value.trim().parse().ok(),
+                "interlace" => interlaced = true,
+                _ => {}
+            }
+        }
+        let format = RawVideoFormat {
+            sampling: sampling.ok_or(RawVideoError::InvalidFmtp("sampling"))?,
+            depth: depth.ok_or(RawVideoError::InvalidFmtp("depth"))?,
+            width: width.ok_or(RawVideoError::InvalidFmtp("width"))?,
+            height: height.ok_or(RawVideoError::InvalidFmtp("height"))?,
+            interlaced,
+        };
+        format.line_bytes()?;
+        Ok(format)
+    }
+
+    /// Pixel group of the sampling and depth (RFC 4175, section 4.3).
+    pub fn pgroup(&self) -> Option<PixelGroup> {
+        let (bytes, pixels) = match (self.sampling, self.depth) {
+            (Sampling::Ycbcr422, 8) => (4, 2),
+            (Sampling::Ycbcr422, 10) => (5, 2),
+            (Sampling::Ycbcr422, 12) => (6, 2),
+            (Sampling::Ycbcr422, 16) => (8, 2),
+            (_, 8) => (3, 1),
+            (_, 10) => (15, 4),
+            (_, 12) => (9, 2),
+            (_, 16) => (6, 1),
+            _ => return None,
+        };
+        Some(PixelGroup { bytes, pixels })
+    }
+
+    /// Bytes of one line.
+    pub fn line_bytes(&self) -> Result<usize, RawVideoError> {
+        let pgroup = self.pgroup().ok_or(RawVideoError::UnsupportedFormat)?;
+        let width = self.width as usize;
+        if width == 0
+            || self.height == 0
+            || !width.is_multiple_of(pgroup.pixels)
+            || (self.interlaced && !self.height.is_multiple_of(2))
+        {
+            return Err(RawVideoError::UnsupportedFormat);
+        }
+        Ok(width / pgroup.pixels * pgroup.bytes)
+    }
+}
+
+/// Sample row data header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleRow {
+    /// Bytes of sample data.
+    pub length: u16,
+    /// Second field of an interlaced frame (F bit).
+    pub second_field: bool,
+    /// Line number, counted within the field when interlaced.
+    pub line: u16,
+    /// Pixel offset of the first pgroup.
+    pub offset: u16,
+}
+
+/// Parses a payload's headers; returns the high half of the extended
+/// sequence number, the rows and where their sample data starts.
+pub fn parse_rows(payload: &[u8]) -> Result<(u16, Vec<SampleRow>, usize), RawVideoError> {
+    let esn = payload.get(..2).ok_or(RawVideoError::Truncated {
+        header: "extended sequence number",
+    })?;
+    let mut rows = Vec::new();
+    let mut at = 2;
+    loop {
+        let row = payload.get(at..at + 6).ok_or(RawVideoError::Truncated {
+            header: "sample row header",
+        })?;
+        let line = u16::from_be_bytes([row[2], row[3]]);
+        let offset = u16::from_be_bytes([row[4], row[5]]);
+        rows.push(SampleRow {
+            length: u16::from_be_bytes([row[0], row[1]]),
+            second_field: line & 0x8000 != 0,
+            line: line & 0x7FFF,
+            offset: offset & 0x7FFF,
+        });
+        at += 6;
+        // Continuation bit.
+        if offset & 0x8000 == 0 {
+            break;
+        }
+    }
+    Ok((u16::from_be_bytes([esn[0], esn[1]]), rows, at))
+}
+
+/// Frame completed by a [`RawVideoDepacketizer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawVideoFrame {
+    /// Timestamp of the frame, or of its first field.
+    pub timestamp: u32,
+    /// Lines top to bottom, fields woven, packed in pgroups.
+    pub data: Vec<u8>,
+}
+
+// Frame being received.
+#[derive(Debug)]
+struct PendingFrame {
+    timestamp: u32,
+    // Timestamp of the field being received.
+    field_timestamp: u32,
+    data: Vec<u8>,
+    received: usize,
+    error: Option<RawVideoError>,
+}
+
+/// Rebuilds frames of one RFC 4175 / ST 2110-20 stream.
+///
+/// Interlaced frames are delivered once their second field ends, with the
+/// fields woven. A frame whose last packet is lost is dropped when the
+/// next one starts; like other dropped frames it is counted by
+/// [`dropped_frames`](Self::dropped_frames).
+#[derive(Debug)]
+pub struct RawVideoDepacketizer {
+    format: RawVideoFormat,
+    pgroup: PixelGroup,
+    line_bytes: usize,
+    pending: Option<PendingFrame>,
+    // Extended sequence number expected next.
+    next_seq: Option<u32>,
+    dropped: u64,
+}
+
+impl RawVideoDepacketizer {
+    pub fn new(format: RawVideoFormat) -> Result<Self, RawVideoError> {
+        let line_bytes = format.line_bytes()?;
+        Ok(Self {
+            format,
+            pgroup: format.pgroup().ok_or(RawVideoError::UnsupportedFormat)?,
+            line_bytes,
+            pending: None,
+            next_seq: None,
+            dropped: 0,
+        })
+    }
+
+    pub fn format(&self) -> &RawVideoFormat {
+        &self.format
+    }
+
+    /// Adds a packet; returns the frame it completes, or why that frame was
+    /// dropped.
+    pub fn push_packet(
+        &mut self,
+        pkt: &RtpPacket<'_>,
+    ) -> Option<Result<RawVideoFrame, RawVideoError>> {
+        let timestamp = pkt.header.timestamp;
+        let parsed = parse_rows(pkt.payload);
+        let second_field =
+            self.format.interlaced && matches!(&parsed, Ok((_, rows, _)) if rows[0].second_field);
+        let mut frame = match self.pending.take() {
+            Some(frame) if frame.field_timestamp == timestamp || second_field => frame,
+            previous => {
+                if let Some(_previous) = previous {
+                    trace_event!(
+                        DEBUG,
+                        timestamp = _previous.timestamp,
+                        "raw video frame lost its last packet"
+                    );
+                    self.dropped += 1;
+                }
+                PendingFrame {
+                    timestamp,
+                    field_timestamp: timestamp,
+                    data: vec![0; self.line_bytes * self.format.height as usize],
+                    received: 0,
+                    error: None,
+                }
+            }
+        };
+        frame.field_timestamp = timestamp;
+        if let Ok((esn, _, _)) = &parsed {
+            let seq = (*esn as u32) << 16 | pkt.header.sequence_number as u32;
+            if self.next_seq.is_some_and(|next| next != seq) {
+                frame.error.get_or_insert(RawVideoError::MissingPacket {
+                    timestamp: frame.timestamp,
+                });
+            }
+            self.next_seq = Some(seq.wrapping_add(1));
+        }
+        if frame.error.is_none() {
+            frame.error = self.place(&mut frame, parsed, pkt.payload).err();
+        }
+        let ends_frame = !self.format.interlaced || second_field;
+        if !pkt.header.marker || !ends_frame {
+            self.pending = Some(frame);
+            return None;
+        }
+        let result = match frame.error {
+            Some(error) => Err(error),
+            None if frame.received < frame.data.len() => Err(RawVideoError::MissingPacket {
+                timestamp: frame.timestamp,
+            }),
+            None => Ok(RawVideoFrame {
+                timestamp: frame.timestamp,
+                data: frame.data,
+            }),
+        };
+        if let Err(_e) = &result {
+            trace_event!(DEBUG, timestamp, error = %_e, "raw video frame dropped");
+            self.dropped += 1;
+        }
+        Some(result)
+    }
+
+    /// Frames dropped so far, including those returned as errors.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped
+    }
+
+    fn place(
+        &self,
+        frame: &mut PendingFrame,
+        parsed: Result<(u16, Vec<SampleRow>, usize), RawVideoError>,
+        payload: &[u8],
+    ) -> Result<(), RawVideoError> {
+        let (_, rows, mut at) = parsed?;
+        let out_of_bounds = RawVideoError::RowOutOfBounds {
+            timestamp: frame.timestamp,
+        };
+        for row in rows {
+            let (offset, length) = (row.offset as usize, row.length as usize);
+            let line = if self.format.interlaced {
+                row.line as usize * 2 + row.second_field as usize
+            } else {
+                row.line as usize
+            };
+            if !offset.is_multiple_of(self.pgroup.pixels)
+                || !length.is_multiple_of(self.pgroup.bytes)
+                || line >= self.format.height as usize
+            {
+                return Err(out_of_bounds);
+            }
+            let start = offset / self.pgroup.pixels * self.pgroup.bytes;
+            if start + length > self.line_bytes {
+                return Err(out_of_bounds);
+            }
+            let data = payload
+                .get(at..at + length)
+                .ok_or(RawVideoError::Truncated {
+                    header: "sample data",
+                })?;
+            let pos = line * self.line_bytes + start;
+            frame.data[pos..pos + length].copy_from_slice(data);
+            frame.received += length;
+            at += length;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::RtpPacketBuilder;
+
+    // One packet per line, carrying `fill` bytes.
+    fn line_packet(
+        seq: u32,
+        timestamp: u32,
+        field: bool,
+        line: u16,
+        fill: u8,
+        marker: bool,
+    ) -> Vec<u8> {
+        let mut payload = ((seq >> 16) as u16).to_be_bytes().to_vec();
+        payload.extend_from_slice(&10u16.to_be_bytes());
+        payload.extend_from_slice(&((field as u16) << 15 | line).to_be_bytes());
+        payload.extend_from_slice(&0u16.to_be_bytes());
+        payload.extend_from_slice(&[fill; 10]);
+        RtpPacketBuilder::new()
+            .marker(marker)
+            .sequence_number(seq as u16)
+            .timestamp(timestamp)
+            .payload(&payload)
+            .build()
+    }
+
+    #[test]
+    fn weaves_interlaced_fields() {
+        let format = RawVideoFormat::from_fmtp(
+            "sampling=YCbCr-4:2:2; width=4; height=4; exactframerate=30000/1001; depth=10; interlace",
+        )
+        .unwrap();
+        assert_eq!(format.line_bytes(), Ok(10));
+        let mut raw = RawVideoDepacketizer::new(format).unwrap();
+        // Field 2 has its own timestamp, half a frame later; the extended
+        // sequence number wraps the RTP one.
+        let packets = [
+            line_packet(0xFFFE, 0, false, 0, 1, false),
+            line_packet(0xFFFF, 0, false, 1, 3, true),
+            line_packet(0x1_0000, 1501, true, 0, 2, false),
+            line_packet(0x1_0001, 1501, true, 1, 4, true),
+        ];
+        let mut frames = Vec::new();
+        for bytes in &packets {
+            frames.extend(raw.push_packet(&RtpPacket::parse(bytes).unwrap()));
+        }
+        let [Ok(frame)] = &frames[..] else {
+            panic!("expected one frame, got {:?}", frames);
+        };
+        assert_eq!(frame.timestamp, 0);
+        let lines: Vec<u8> = frame.data.chunks(10).map(|line| line[0]).collect();
+        assert_eq!(lines, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn reports_lost_lines() {
+        let format = RawVideoFormat {
+            sampling: Sampling::Ycbcr422,
+            depth: 10,
+            width: 4,
+            height: 2,
+            interlaced: false,
+        };
+        let mut raw = RawVideoDepacketizer::new(format).unwrap();
+        let first = line_packet(7, 3000, false, 0, 1, false);
+        let last = line_packet(9, 3000, false, 1, 1, true);
+        assert_eq!(raw.push_packet(&RtpPacket::parse(&first).unwrap()), None);
+        assert_eq!(
+            raw.push_packet(&RtpPacket::parse(&last).unwrap()),
+            Some(Err(RawVideoError::MissingPacket { timestamp: 3000 }))
+        );
+        assert_eq!(raw.dropped_frames(), 1);
+        assert_eq!(
+            RawVideoFormat::from_fmtp("sampling=YCbCr-4:2:0; width=4; height=2; depth=8"),
+            Err(RawVideoError::InvalidFmtp("sampling"))
+        );
+    }
+}
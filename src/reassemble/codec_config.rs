@@ -0,0 +1,319 @@
+use super::param_sets::{nal_role, NalRole};
+use crate::{
+    bitstream::{remove_emulation_prevention, split_annex_b},
+    codecs::{
+        av1::{read_leb128, split_obus, Av1SequenceHeader, ObuHeader, OBU_SEQUENCE_HEADER},
+        avc::AvcSps,
+        hevc::HevcSps,
+        vp9::{Vp9ColorConfig, Vp9StreamParams},
+        Codec,
+    },
+    hdr::is_dolby_vision_nal,
+};
+use alloc::{vec, vec::Vec};
+
+// Largest luma picture size of each VP9 level (VP9 Annex A).
+const VP9_LEVELS: [(u32, u8); 9] = [
+    (36_864, 10),
+    (73_728, 11),
+    (122_880, 20),
+    (245_760, 21),
+    (552_960, 30),
+    (983_040, 31),
+    (2_228_224, 40),
+    (8_912_896, 50),
+    (35_651_584, 60),
+];
+
+/// What a decoder or muxer needs before the first frame: the stream's
+/// parameters and its ISO BMFF decoder configuration record; see
+/// [`FrameReassembler::take_codec_config`](super::FrameReassembler::take_codec_config).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodecConfig {
+    pub codec: Codec,
+    /// Displayed size in luma samples (after cropping).
+    pub width: u32,
+    pub height: u32,
+    /// AVC/HEVC VPS, SPS and PPS NAL units, or the AV1 sequence header
+    /// OBU; empty for VP9.
+    pub parameter_sets: Vec<Vec<u8>>,
+    /// Contents of the `avcC`, `hvcC`, `av1C` or `vpcC` box (the latter
+    /// without its version and flags), with 4-byte NAL unit lengths.
+    pub record: Vec<u8>,
+    /// The stream carries Dolby Vision RPUs; muxers should describe it with
+    /// a `dvcC`/`dvvC` box next to `record` and keep its type 62 and 63 NAL
+    /// units.
+    pub dolby_vision: bool,
+}
+
+// Collects parameters from assembled frames until a configuration can be
+// built.
+#[derive(Debug, Default)]
+pub(super) struct CodecConfigTracker {
+    // Latest VPS, SPS and PPS.
+    param_sets: [Option<Vec<u8>>; 3],
+    dolby_vision: bool,
+    config: Option<CodecConfig>,
+    taken: bool,
+}
+
+impl CodecConfigTracker {
+    /// Looks for parameters in a frame (Annex-B for AVC/HEVC); true when it
+    /// completed the configuration.
+    pub(super) fn observe(&mut self, codec: Codec, frame: &[u8]) -> bool {
+        if self.config.is_some() {
+            return false;
+        }
+        self.config = match codec {
+            Codec::Avc | Codec::Hevc => self.nal_config(codec, frame),
+            Codec::Vp9 => Vp9StreamParams::parse(frame)
+                .ok()
+                .flatten()
+                .map(|params| vp9_config(&params)),
+            Codec::Av1 => av1_config(frame),
+            Codec::Unknown | Codec::Custom(_) => None,
+        };
+        self.config.is_some()
+    }
+
+    pub(super) fn get(&self) -> Option<&CodecConfig> {
+        self.config.as_ref()
+    }
+
+    pub(super) fn take(&mut self) -> Option<CodecConfig> {
+        if self.taken {
+            return None;
+        }
+        self.taken = self.config.is_some();
+        self.config.clone()
+    }
+
+    fn nal_config(&mut self, codec: Codec, frame: &[u8]) -> Option<CodecConfig> {
+        for nal in split_annex_b(frame) {
+            self.dolby_vision |= is_dolby_vision_nal(codec, nal);
+            let slot = match nal.first().map(|&h| nal_role(codec, h)) {
+                Some(NalRole::Vps) => 0,
+                Some(NalRole::Sps) => 1,
+                Some(NalRole::Pps) => 2,
+                _ => continue,
+            };
+            self.param_sets[slot] = Some(nal.to_vec());
+        }
+        let [vps, Some(sps), Some(pps)] = &self.param_sets else {
+            return None;
+        };
+        let mut config = match (codec, vps) {
+            (Codec::Avc, _) => avc_config(sps, pps),
+            (Codec::Hevc, Some(vps)) => hevc_config(vps, sps, pps),
+            _ => None,
+        }?;
+        config.dolby_vision = self.dolby_vision;
+        Some(config)
+    }
+}
+
+fn avc_config(sps_nal: &[u8], pps: &[u8]) -> Option<CodecConfig> {
+    let sps = AvcSps::parse(sps_nal).ok()?;
+    let (width, height) = sps.dimensions();
+    let mut record = vec![
+        1,
+        sps_nal[1],
+        *sps_nal.get(2)?,
+        *sps_nal.get(3)?,
+        0xFF,
+        0xE1,
+    ];
+    push_nal(&mut record, sps_nal);
+    record.push(1);
+    push_nal(&mut record, pps);
+    if matches!(sps.profile_idc, 100 | 110 | 122 | 144) {
+        record.extend_from_slice(&[
+            0xFC | sps.chroma_format_idc,
+            0xF8 | (sps.bit_depth_luma - 8),
+            0xF8 | (sps.bit_depth_chroma - 8),
+            0, // numOfSequenceParameterSetExt
+        ]);
+    }
+    Some(CodecConfig {
+        codec: Codec::Avc,
+        width,
+        height,
+        parameter_sets: vec![sps_nal.to_vec(), pps.to_vec()],
+        record,
+        dolby_vision: false,
+    })
+}
+
+fn hevc_config(vps: &[u8], sps_nal: &[u8], pps: &[u8]) -> Option<CodecConfig> {
+    let sps = HevcSps::parse(sps_nal).ok()?;
+    let (width, height) = sps.dimensions();
+    let rbsp = remove_emulation_prevention(sps_nal.get(2..)?);
+    // The general part of profile_tier_level(), byte aligned after the
+    // first byte of the SPS.
+    let general = rbsp.get(1..13)?;
+    let temporal_id_nested = rbsp[0] & 0x01;
+    let mut record = vec![1];
+    record.extend_from_slice(general);
+    record.extend_from_slice(&[
+        0xF0, // min_spatial_segmentation_idc 0
+        0x00,
+        0xFC, // parallelismType 0
+        0xFC | sps.chroma_format_idc,
+        0xF8 | (sps.bit_depth_luma - 8),
+        0xF8 | (sps.bit_depth_chroma - 8),
+        0, // avgFrameRate
+        0,
+        (sps.max_sub_layers << 3) | (temporal_id_nested << 2) | 3,
+        3, // numOfArrays
+    ]);
+    for nal in [vps, sps_nal, pps] {
+        // array_completeness, NAL_unit_type, numNalus 1.
+        record.extend_from_slice(&[0x80 | ((nal[0] >> 1) & 0x3F), 0, 1]);
+        push_nal(&mut record, nal);
+    }
+    Some(CodecConfig {
+        codec: Codec::Hevc,
+        width,
+        height,
+        parameter_sets: vec![vps.to_vec(), sps_nal.to_vec(), pps.to_vec()],
+        record,
+        dolby_vision: false,
+    })
+}
+
+fn vp9_config(params: &Vp9StreamParams) -> CodecConfig {
+    let color = &params.color;
+    let level = VP9_LEVELS
+        .iter()
+        .find(|(samples, _)| params.width * params.height <= *samples)
+        .map_or(62, |&(_, level)| level);
+    let chroma_subsampling = match (color.subsampling_x, color.subsampling_y) {
+        (true, true) => 0, // 4:2:0, chroma vertically between luma samples
+        (true, false) => 2,
+        _ => 3,
+    };
+    let (primaries, transfer) = vp9_primaries_and_transfer(color);
+    CodecConfig {
+        codec: Codec::Vp9,
+        width: params.width,
+        height: params.height,
+        parameter_sets: Vec::new(),
+        record: vec![
+            params.profile,
+            level,
+            (color.bit_depth << 4) | (chroma_subsampling << 1) | color.full_range as u8,
+            primaries,
+            transfer,
+            color.matrix_coefficients(),
+            0, // codecInitializationDataSize
+            0,
+        ],
+        dolby_vision: false,
+    }
+}
+
+// ISO/IEC 23091-4 ColourPrimaries and TransferCharacteristics; VP9 only
+// signals them for BT.709 and sRGB.
+fn vp9_primaries_and_transfer(color: &Vp9ColorConfig) -> (u8, u8) {
+    match color.color_space {
+        2 => (1, 1),
+        7 => (1, 13),
+        _ => (2, 2),
+    }
+}
+
+fn av1_config(frame: &[u8]) -> Option<CodecConfig> {
+    let obu = split_obus(frame)
+        .ok()?
+        .into_iter()
+        .find(|obu| ObuHeader::parse(obu[0]).obu_type == OBU_SEQUENCE_HEADER)?;
+    let header = ObuHeader::parse(obu[0]);
+    let (_, size_len) = read_leb128(obu.get(header.header_len()..)?)?;
+    let seq = Av1SequenceHeader::parse(obu.get(header.header_len() + size_len..)?).ok()?;
+    let record = vec![
+        0x81, // marker, version 1
+        (seq.seq_profile << 5) | seq.seq_level_idx,
+        (seq.seq_tier as u8) << 7
+            | ((seq.bit_depth > 8) as u8) << 6
+            | ((seq.bit_depth == 12) as u8) << 5
+            | (seq.monochrome as u8) << 4
+            | (seq.subsampling_x as u8) << 3
+            | (seq.subsampling_y as u8) << 2
+            | seq.chroma_sample_position,
+        0, // no initial_presentation_delay
+    ];
+    Some(CodecConfig {
+        codec: Codec::Av1,
+        width: seq.max_frame_width,
+        height: seq.max_frame_height,
+        parameter_sets: vec![obu.to_vec()],
+        record: [record.as_slice(), obu].concat(),
+        dolby_vision: false,
+    })
+}
+
+fn push_nal(record: &mut Vec<u8>, nal: &[u8]) {
+    record.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+    record.extend_from_slice(nal);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        reassemble::{EventKind, FrameReassembler},
+        rtp::RtpPacket,
+        test_utils::{
+            avc,
+            fixtures::{AVC_IDR, AVC_PPS, AVC_SPS},
+            rbsp, StreamBuilder,
+        },
+    };
+
+    #[test]
+    fn avc_config_is_emitted_once_with_the_first_keyframe() {
+        let packets = StreamBuilder::new(1)
+            .frame(&[avc::single(AVC_IDR)])
+            .frame(&[avc::stap_a(&[AVC_SPS, AVC_PPS]), avc::single(AVC_IDR)])
+            .build();
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .event_log(8)
+            .build()
+            .unwrap();
+        let mut configs = Vec::new();
+        for bytes in &packets {
+            r.push_packet(&RtpPacket::parse(bytes).unwrap());
+            configs.extend(r.take_codec_config());
+        }
+        assert_eq!(configs.len(), 1);
+        let config = &configs[0];
+        assert_eq!((config.width, config.height), (320, 240));
+        assert_eq!(config.parameter_sets, [AVC_SPS, AVC_PPS]);
+        let header = [1, AVC_SPS[1], AVC_SPS[2], AVC_SPS[3], 0xFF, 0xE1];
+        assert!(config.record.starts_with(&header));
+        assert_eq!(r.codec_config(), Some(config));
+        assert!(r
+            .recent_events()
+            .any(|e| e.kind == EventKind::CodecConfigured { timestamp: 3000 }));
+    }
+
+    #[test]
+    fn av1_config_comes_from_the_sequence_header() {
+        // Reduced still picture header, level 4.0, 640x480, 8-bit 4:2:0.
+        let payload = rbsp(
+            "000 1 1 01000 1001 1001 1001111111 0111011111 000 000 \
+             0 0 0 0 00 0 0",
+        );
+        let mut obu = vec![0x0A, payload.len() as u8];
+        obu.extend_from_slice(&payload);
+        let mut tracker = CodecConfigTracker::default();
+        assert!(tracker.observe(Codec::Av1, &obu));
+        let config = tracker.take().unwrap();
+        assert_eq!((config.width, config.height), (640, 480));
+        assert_eq!(config.record[..4], [0x81, 0x08, 0x0C, 0]);
+        assert_eq!(config.record[4..], obu);
+        assert_eq!(tracker.take(), None);
+    }
+}
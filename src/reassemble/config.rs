@@ -0,0 +1,680 @@
+use super::{Decimation, Delivery, FrameReassembler};
+use crate::{clock::MediaTime, codecs::Codec, limits::Limits, sframe::SframeMode};
+use core::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ReorderConfig {
+    pub enable_reordering: bool,
+    pub drop_incomplete_frames: bool,
+    pub max_buffered_packets_per_frame: usize,
+    // Upper bound on frames buffered at once; the oldest frame is evicted
+    // when a packet for a new timestamp would exceed it.
+    pub max_pending_frames: usize,
+    // Typical assembled frame size in bytes; output buffers reserve at
+    // least this much up front. 0 sizes buffers from the packets alone.
+    pub expected_frame_size: usize,
+    // Largest frame accepted, in bytes; a frame growing past it is dropped
+    // along with its remaining packets. `None` is unlimited.
+    pub max_frame_bytes: Option<usize>,
+    // Packets more than this many sequence numbers behind the newest one
+    // are ignored as too late. `None` accepts any delay.
+    pub max_reorder_window: Option<u16>,
+    // What gives way when a frame reaches `max_buffered_packets_per_frame`.
+    pub overflow: OverflowPolicy,
+    // Missing sequence numbers are reported by `missing_sequences` until
+    // this many newer ones arrived (or `max_reorder_window`, if smaller).
+    // 0 tracks none.
+    pub nack_window: u16,
+}
+
+impl Default for ReorderConfig {
+    fn default() -> Self {
+        Self {
+            enable_reordering: true,
+            drop_incomplete_frames: true,
+            max_buffered_packets_per_frame: 2048,
+            max_pending_frames: 64,
+            expected_frame_size: 0,
+            max_frame_bytes: None,
+            max_reorder_window: None,
+            overflow: OverflowPolicy::DropFrame,
+            nack_window: 0,
+        }
+    }
+}
+
+impl ReorderConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_buffered_packets_per_frame == 0 {
+            return Err(ConfigError::ZeroLimit("max_buffered_packets_per_frame"));
+        }
+        if self.max_pending_frames == 0 {
+            return Err(ConfigError::ZeroLimit("max_pending_frames"));
+        }
+        if self.max_frame_bytes == Some(0) {
+            return Err(ConfigError::ZeroLimit("max_frame_bytes"));
+        }
+        if self.max_reorder_window == Some(0) {
+            return Err(ConfigError::ZeroLimit("max_reorder_window"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn exceeds_max_frame_bytes(&self, bytes: usize) -> bool {
+        self.max_frame_bytes.is_some_and(|max| bytes > max)
+    }
+}
+
+/// Reaction to a frame reaching `max_buffered_packets_per_frame`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum OverflowPolicy {
+    /// Discard all packets buffered for the frame and start over.
+    #[default]
+    DropFrame,
+    /// Discard the frame's packet with the lowest sequence number.
+    DropOldestPacket,
+}
+
+/// Bitstream layout of assembled AVC/HEVC frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputFormat {
+    /// NAL units preceded by `00 00 00 01` start codes.
+    #[default]
+    AnnexB,
+    /// NAL units preceded by 4-byte big-endian lengths (avcC/hvcC style, as
+    /// MP4 muxers and most hardware decoder APIs expect).
+    LengthPrefixed,
+}
+
+/// Start codes written before the NAL units of Annex-B frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StartCodes {
+    /// `00 00 00 01` before every NAL unit.
+    #[default]
+    FourByte,
+    /// `00 00 00 01` before the first NAL unit of each frame and
+    /// `00 00 01` before the others, the usual Annex-B convention.
+    FourByteFirst,
+    /// `00 00 01` before every NAL unit.
+    ThreeByte,
+}
+
+/// Handling of AVC/HEVC parameter sets (VPS/SPS/PPS).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParameterSetMode {
+    /// Frames are emitted exactly as received.
+    #[default]
+    PassThrough,
+    /// The latest parameter sets seen are cached and prepended to keyframes
+    /// that arrive without them, so every keyframe is decodable on its own.
+    InjectOnKeyframe,
+}
+
+/// Reaction to a packet whose SSRC differs from the current stream's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SsrcPolicy {
+    /// Discard all pending state and follow the new SSRC.
+    #[default]
+    ResetOnChange,
+    /// Stay on the first SSRC seen and ignore packets from any other.
+    LockFirst,
+    /// Follow the new SSRC like `ResetOnChange`, but when it carries the
+    /// same payload type keep the cached parameter sets and codec, so the
+    /// first keyframes after a sender restart stay decodable with
+    /// [`ParameterSetMode::InjectOnKeyframe`]. A different payload type
+    /// also forgets a guessed codec.
+    KeepParameterSets,
+}
+
+/// Recovery from loss bursts, after which inter frames stay undecodable
+/// until the next keyframe (or AVC/HEVC recovery point).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Resync {
+    /// Frames are returned whatever was lost before them.
+    #[default]
+    Off,
+    /// Once this many frames were lost since the last keyframe, drop the
+    /// frames completed until the next one with
+    /// [`DropReason::AwaitingKeyframe`](super::DropReason::AwaitingKeyframe).
+    SuppressAfter(u32),
+    /// Like `SuppressAfter`, but the frames are returned and flagged; see
+    /// [`FrameReassembler::last_frame_unsynced`].
+    FlagAfter(u32),
+}
+
+impl Resync {
+    pub(crate) fn threshold(self) -> Option<u32> {
+        match self {
+            Resync::Off => None,
+            Resync::SuppressAfter(n) | Resync::FlagAfter(n) => Some(n),
+        }
+    }
+}
+
+/// Complete reassembler configuration; see [`FrameReassembler::builder`].
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReassemblerConfig {
+    /// Payload format; `None` guesses it from the first packets.
+    pub codec: Option<Codec>,
+    pub output: OutputFormat,
+    /// Only applies to [`OutputFormat::AnnexB`].
+    pub start_codes: StartCodes,
+    pub reorder: ReorderConfig,
+    /// Jitter buffer depth: pending frames further than this behind the
+    /// newest timestamp (on the codec's RTP clock) are dropped.
+    pub latency: Option<Duration>,
+    /// Upper bound on payload bytes buffered across all pending frames; the
+    /// oldest frames are dropped to stay below it.
+    pub max_buffered_bytes: Option<usize>,
+    pub parameter_sets: ParameterSetMode,
+    /// HEVC payloads carry decoding order numbers (`sprop-max-don-diff` > 0
+    /// in SDP); they are stripped from the output.
+    pub hevc_donl: bool,
+    /// Prepend an access unit delimiter NAL unit to each AVC/HEVC frame
+    /// that lacks one, as some hardware decoders and TS muxers require.
+    pub access_unit_delimiters: bool,
+    /// Drop the non-base views of H.264 MVC (stereo) streams, leaving a
+    /// plain 2D bitstream.
+    pub avc_base_view_only: bool,
+    pub ssrc_policy: SsrcPolicy,
+    /// Number of recent events (drops, gaps, resets) kept for
+    /// [`FrameReassembler::recent_events`]; 0 keeps none.
+    pub event_log: usize,
+    /// Number of recent malformed payloads kept for
+    /// [`FrameReassembler::quarantine`]; 0 keeps none.
+    pub quarantine: usize,
+    pub decimation: Decimation,
+    /// Check the structure of each AVC/HEVC/AV1 frame output and flag
+    /// frames likely to crash a decoder; see
+    /// [`FrameReassembler::last_frame_defect`].
+    pub validate_output: bool,
+    /// Access units or single NAL units (AVC/HEVC only).
+    pub delivery: Delivery,
+    pub resync: Resync,
+    /// Payloads aggregating more NAL units or OBU elements are
+    /// quarantined as [`MalformedReason::TooManyItems`](super::MalformedReason::TooManyItems).
+    pub max_aggregation_items: Option<usize>,
+    /// Dynamic payload type of comfort noise; packets of it or of static
+    /// type 13 are reported as [`PushResult::ComfortNoise`](super::PushResult::ComfortNoise)
+    /// instead of being depacketized.
+    pub comfort_noise_payload_type: Option<u8>,
+    /// Whether frames are SFrame encrypted; encrypted frames are
+    /// reassembled without parsing their payloads and delivered whole.
+    /// See [`FrameReassembler::set_frame_decryptor`].
+    pub sframe: SframeMode,
+    /// Emit every frame, flagging what it is missing, for decoders that
+    /// conceal errors: frames with gaps on their marker, whatever
+    /// `drop_incomplete_frames` says, and frames whose first or last packet
+    /// never arrived when they fall behind `latency` or are pushed out by
+    /// `max_pending_frames`. Frames are queued for
+    /// [`FrameReassembler::pop_frame`] instead of being returned. With
+    /// `latency` set, a frame with gaps waits for them to fill until it
+    /// falls behind.
+    pub resilient: bool,
+    /// Record a [`Trace`](super::Trace) of the inputs from the start; see
+    /// [`FrameReassembler::take_trace`].
+    pub trace: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A limit that must be positive was zero.
+    ZeroLimit(&'static str),
+    /// The output format does not apply to the configured codec.
+    UnsupportedOutput(Codec),
+    /// Parameter set handling does not apply to the configured codec.
+    UnsupportedParameterSets(Codec),
+    /// DONL fields were enabled for a codec other than HEVC.
+    UnsupportedDonl(Codec),
+    /// Access unit delimiters were enabled for a codec without them.
+    UnsupportedAud(Codec),
+    /// MVC view stripping was enabled for a codec other than H.264.
+    UnsupportedBaseView(Codec),
+    /// A start code style was set for a codec without start codes.
+    UnsupportedStartCodes(Codec),
+    /// NAL unit delivery was chosen for a codec without NAL units.
+    UnsupportedDelivery(Codec),
+    /// An output shaping option was combined with NAL unit delivery.
+    ShapedNalUnits(&'static str),
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::ZeroLimit(name) => write!(f, "{} must be greater than zero", name),
+            ConfigError::UnsupportedOutput(codec) => {
+                write!(f, "length-prefixed output is not supported for {:?}", codec)
+            }
+            ConfigError::UnsupportedParameterSets(codec) => {
+                write!(f, "{:?} has no parameter sets to inject", codec)
+            }
+            ConfigError::UnsupportedDonl(codec) => {
+                write!(f, "DONL fields are specific to HEVC, not {:?}", codec)
+            }
+            ConfigError::UnsupportedAud(codec) => {
+                write!(
+                    f,
+                    "access unit delimiters are not supported for {:?}",
+                    codec
+                )
+            }
+            ConfigError::UnsupportedBaseView(codec) => {
+                write!(f, "MVC views are specific to H.264, not {:?}", codec)
+            }
+            ConfigError::UnsupportedStartCodes(codec) => {
+                write!(f, "{:?} frames have no start codes", codec)
+            }
+            ConfigError::UnsupportedDelivery(codec) => {
+                write!(f, "{:?} frames have no NAL units to deliver", codec)
+            }
+            ConfigError::ShapedNalUnits(name) => {
+                write!(f, "{} does not apply to NAL unit delivery", name)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConfigError {}
+
+// Codecs whose frames are NAL unit streams.
+fn is_nal_codec(codec: Option<Codec>) -> bool {
+    matches!(codec, None | Some(Codec::Avc) | Some(Codec::Hevc))
+}
+
+impl ReassemblerConfig {
+    /// Tightens the frame size, pending frame and aggregation settings to
+    /// `limits`; looser limits leave stricter settings as they are.
+    pub fn apply_limits(&mut self, limits: &Limits) {
+        let reorder = &mut self.reorder;
+        reorder.max_frame_bytes = Some(
+            reorder
+                .max_frame_bytes
+                .map_or(limits.max_frame_bytes, |max| {
+                    max.min(limits.max_frame_bytes)
+                }),
+        );
+        reorder.max_pending_frames = reorder.max_pending_frames.min(limits.max_pending_frames);
+        self.max_aggregation_items = Some(
+            self.max_aggregation_items
+                .map_or(limits.max_aggregation_items, |max| {
+                    max.min(limits.max_aggregation_items)
+                }),
+        );
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.reorder.validate()?;
+        if self.latency == Some(Duration::ZERO) {
+            return Err(ConfigError::ZeroLimit("latency"));
+        }
+        if self.max_buffered_bytes == Some(0) {
+            return Err(ConfigError::ZeroLimit("max_buffered_bytes"));
+        }
+        if matches!(
+            self.decimation,
+            Decimation::EveryNth(0) | Decimation::MaxFps(0)
+        ) {
+            return Err(ConfigError::ZeroLimit("decimation"));
+        }
+        if self.resync.threshold() == Some(0) {
+            return Err(ConfigError::ZeroLimit("resync"));
+        }
+        if self.max_aggregation_items == Some(0) {
+            return Err(ConfigError::ZeroLimit("max_aggregation_items"));
+        }
+        if let Some(codec) = self.codec.filter(|_| !is_nal_codec(self.codec)) {
+            if self.output != OutputFormat::AnnexB {
+                return Err(ConfigError::UnsupportedOutput(codec));
+            }
+            if self.parameter_sets != ParameterSetMode::PassThrough {
+                return Err(ConfigError::UnsupportedParameterSets(codec));
+            }
+            if self.access_unit_delimiters {
+                return Err(ConfigError::UnsupportedAud(codec));
+            }
+            if self.start_codes != StartCodes::FourByte {
+                return Err(ConfigError::UnsupportedStartCodes(codec));
+            }
+            if self.delivery != Delivery::AccessUnit {
+                return Err(ConfigError::UnsupportedDelivery(codec));
+            }
+        }
+        if self.delivery == Delivery::NalUnit {
+            let shaping = [
+                ("output", self.output != OutputFormat::AnnexB),
+                ("start_codes", self.start_codes != StartCodes::FourByte),
+                (
+                    "parameter_sets",
+                    self.parameter_sets != ParameterSetMode::PassThrough,
+                ),
+                ("access_unit_delimiters", self.access_unit_delimiters),
+                ("avc_base_view_only", self.avc_base_view_only),
+                ("resilient", self.resilient),
+            ];
+            if let Some((name, _)) = shaping.into_iter().find(|&(_, set)| set) {
+                return Err(ConfigError::ShapedNalUnits(name));
+            }
+        }
+        match self.codec {
+            Some(codec) if self.hevc_donl && codec != Codec::Hevc => {
+                Err(ConfigError::UnsupportedDonl(codec))
+            }
+            Some(codec) if self.avc_base_view_only && codec != Codec::Avc => {
+                Err(ConfigError::UnsupportedBaseView(codec))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Latency in RTP clock ticks, saturating.
+    pub(crate) fn latency_ticks(&self) -> Option<u32> {
+        let time = MediaTime::new(self.codec.unwrap_or(Codec::Unknown).clock_rate());
+        self.latency
+            .map(|l| time.duration_to_ticks(l).min(i32::MAX as u64) as u32)
+    }
+}
+
+/// Builds a [`FrameReassembler`] from a validated [`ReassemblerConfig`].
+#[derive(Debug, Clone, Default)]
+pub struct FrameReassemblerBuilder {
+    config: ReassemblerConfig,
+}
+
+impl FrameReassemblerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn codec(mut self, codec: Codec) -> Self {
+        self.config.codec = Some(codec);
+        self
+    }
+
+    pub fn output(mut self, output: OutputFormat) -> Self {
+        self.config.output = output;
+        self
+    }
+
+    pub fn start_codes(mut self, start_codes: StartCodes) -> Self {
+        self.config.start_codes = start_codes;
+        self
+    }
+
+    pub fn reorder(mut self, reorder: ReorderConfig) -> Self {
+        self.config.reorder = reorder;
+        self
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.config.latency = Some(latency);
+        self
+    }
+
+    pub fn max_buffered_bytes(mut self, bytes: usize) -> Self {
+        self.config.max_buffered_bytes = Some(bytes);
+        self
+    }
+
+    pub fn parameter_sets(mut self, mode: ParameterSetMode) -> Self {
+        self.config.parameter_sets = mode;
+        self
+    }
+
+    pub fn hevc_donl(mut self, donl: bool) -> Self {
+        self.config.hevc_donl = donl;
+        self
+    }
+
+    pub fn access_unit_delimiters(mut self, enabled: bool) -> Self {
+        self.config.access_unit_delimiters = enabled;
+        self
+    }
+
+    pub fn avc_base_view_only(mut self, enabled: bool) -> Self {
+        self.config.avc_base_view_only = enabled;
+        self
+    }
+
+    pub fn ssrc_policy(mut self, policy: SsrcPolicy) -> Self {
+        self.config.ssrc_policy = policy;
+        self
+    }
+
+    pub fn event_log(mut self, capacity: usize) -> Self {
+        self.config.event_log = capacity;
+        self
+    }
+
+    pub fn quarantine(mut self, capacity: usize) -> Self {
+        self.config.quarantine = capacity;
+        self
+    }
+
+    pub fn decimation(mut self, decimation: Decimation) -> Self {
+        self.config.decimation = decimation;
+        self
+    }
+
+    pub fn validate_output(mut self, enabled: bool) -> Self {
+        self.config.validate_output = enabled;
+        self
+    }
+
+    pub fn delivery(mut self, delivery: Delivery) -> Self {
+        self.config.delivery = delivery;
+        self
+    }
+
+    pub fn resync(mut self, resync: Resync) -> Self {
+        self.config.resync = resync;
+        self
+    }
+
+    pub fn comfort_noise_payload_type(mut self, payload_type: u8) -> Self {
+        self.config.comfort_noise_payload_type = Some(payload_type);
+        self
+    }
+
+    pub fn sframe(mut self, mode: SframeMode) -> Self {
+        self.config.sframe = mode;
+        self
+    }
+
+    pub fn resilient(mut self, enabled: bool) -> Self {
+        self.config.resilient = enabled;
+        self
+    }
+
+    pub fn trace(mut self, enabled: bool) -> Self {
+        self.config.trace = enabled;
+        self
+    }
+
+    /// Applies the reassembler's share of `limits`; see
+    /// [`ReassemblerConfig::apply_limits`]. A later call to `reorder`
+    /// replaces the frame limits again.
+    pub fn limits(mut self, limits: &Limits) -> Self {
+        self.config.apply_limits(limits);
+        self
+    }
+
+    pub fn build(self) -> Result<FrameReassembler, ConfigError> {
+        FrameReassembler::from_config(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rtp::RtpPacket, test_utils::RtpPacketBuilder};
+
+    fn packet(payload: &[u8], seq: u16, timestamp: u32, ssrc: u32) -> alloc::vec::Vec<u8> {
+        RtpPacketBuilder::new()
+            .marker(true)
+            .sequence_number(seq)
+            .timestamp(timestamp)
+            .ssrc(ssrc)
+            .payload(payload)
+            .build()
+    }
+
+    #[test]
+    fn builder_validates_configuration() {
+        let err = FrameReassembler::builder()
+            .codec(Codec::Vp9)
+            .output(OutputFormat::LengthPrefixed)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::UnsupportedOutput(Codec::Vp9));
+        let err = FrameReassembler::builder()
+            .reorder(ReorderConfig {
+                max_pending_frames: 0,
+                ..Default::default()
+            })
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "max_pending_frames must be greater than zero"
+        );
+
+        let r = FrameReassembler::builder()
+            .codec(Codec::Hevc)
+            .latency(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        assert_eq!(r.codec(), Some(Codec::Hevc));
+        assert_eq!(r.config().latency_ticks(), Some(18_000));
+    }
+
+    #[test]
+    fn length_prefixed_output_and_ssrc_lock() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .output(OutputFormat::LengthPrefixed)
+            .ssrc_policy(SsrcPolicy::LockFirst)
+            .build()
+            .unwrap();
+        let stap_a = [0x18, 0x00, 0x02, 0x67, 0x42, 0x00, 0x03, 0x65, 0x01, 0x02];
+        let p = packet(&stap_a, 1, 3000, 7);
+        let out = r.push_packet(&RtpPacket::parse(&p).unwrap()).unwrap();
+        assert_eq!(out, [0, 0, 0, 2, 0x67, 0x42, 0, 0, 0, 3, 0x65, 0x01, 0x02]);
+
+        let other = packet(&[0x65, 0x09], 2, 6000, 8);
+        assert!(r.push_packet(&RtpPacket::parse(&other).unwrap()).is_none());
+        let next = packet(&[0x41, 0x09], 2, 6000, 7);
+        assert!(r.push_packet(&RtpPacket::parse(&next).unwrap()).is_some());
+    }
+
+    #[test]
+    fn parameter_sets_survive_ssrc_change_on_same_payload_type() {
+        use crate::test_utils::fixtures::{annex_b, AVC_IDR, AVC_PPS, AVC_SPS};
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .parameter_sets(ParameterSetMode::InjectOnKeyframe)
+            .ssrc_policy(SsrcPolicy::KeepParameterSets)
+            .build()
+            .unwrap();
+        let stap_a = [
+            &[0x18, 0x00, AVC_SPS.len() as u8][..],
+            AVC_SPS,
+            &[0x00, AVC_PPS.len() as u8],
+            AVC_PPS,
+        ]
+        .concat();
+        let push = |r: &mut FrameReassembler, bytes: &[u8]| {
+            r.push_packet(&RtpPacket::parse(bytes).unwrap())
+        };
+        assert!(push(&mut r, &packet(&stap_a, 1, 0, 7)).is_some());
+
+        // The sender reconnects with a new SSRC and a bare keyframe.
+        let out = push(&mut r, &packet(AVC_IDR, 100, 9000, 8)).unwrap();
+        assert_eq!(out, annex_b(&[AVC_SPS, AVC_PPS, AVC_IDR]));
+
+        // A new payload type starts from scratch.
+        let other = RtpPacketBuilder::new()
+            .marker(true)
+            .payload_type(97)
+            .sequence_number(200)
+            .timestamp(18000)
+            .ssrc(9)
+            .payload(AVC_IDR)
+            .build();
+        assert_eq!(push(&mut r, &other).unwrap(), annex_b(&[AVC_IDR]));
+    }
+
+    #[test]
+    fn latency_and_memory_limits_drop_stale_frames() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .latency(Duration::from_millis(100))
+            .max_buffered_bytes(8)
+            .build()
+            .unwrap();
+        // Non-start FU fragments stay pending.
+        for (seq, ts) in [(1u16, 0u32), (2, 3000), (3, 6000)] {
+            let p = packet(&[0x7C, 0x05, 0xAA], seq, ts, 1);
+            assert!(r.push_packet(&RtpPacket::parse(&p).unwrap()).is_none());
+        }
+        // 3 bytes each: the 8-byte budget keeps the two newest frames.
+        assert_eq!(r.frames.len(), 2);
+        assert!(!r.frames.contains_key(&0));
+
+        // 100 ms later on the 90 kHz clock, both are too old.
+        let p = packet(&[0x7C, 0x05, 0xAA], 4, 6000 + 9_001, 1);
+        assert!(r.push_packet(&RtpPacket::parse(&p).unwrap()).is_none());
+        assert_eq!(
+            r.frames.keys().copied().collect::<alloc::vec::Vec<_>>(),
+            [15_001]
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn reorder_limits_drop_late_packets_and_oversized_frames() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let zero_window = ReorderConfig {
+            max_reorder_window: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            r.set_reorder_config(zero_window),
+            Err(ConfigError::ZeroLimit("max_reorder_window"))
+        );
+        r.set_reorder_config(ReorderConfig {
+            max_frame_bytes: Some(8),
+            max_reorder_window: Some(10),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // The FU start alone assembles to 9 bytes; the frame's later packets
+        // are ignored rather than buffered.
+        let start = packet(&[0x7C, 0x85, 1, 2, 3, 4], 100, 3000, 1);
+        let end = packet(&[0x7C, 0x45, 5], 101, 3000, 1);
+        assert!(r.push_packet(&RtpPacket::parse(&start).unwrap()).is_none());
+        assert!(r.push_packet(&RtpPacket::parse(&end).unwrap()).is_none());
+        assert!(r.frames.is_empty() && r.in_order.is_none());
+
+        // Sequence 90 is 11 behind 101: too late even though its frame is new.
+        let late = packet(&[0x65, 1], 90, 6000, 1);
+        assert!(r.push_packet(&RtpPacket::parse(&late).unwrap()).is_none());
+        let in_window = packet(&[0x65, 2], 91, 9000, 1);
+        let out = r.push_packet(&RtpPacket::parse(&in_window).unwrap());
+        assert_eq!(out.unwrap(), [0, 0, 0, 1, 0x65, 2]);
+    }
+}
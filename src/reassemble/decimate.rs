@@ -0,0 +1,162 @@
+use super::keyframe::{packet_frame_role, FrameRole};
+use crate::codecs::Codec;
+
+/// Which frames to emit when full-rate output is not needed.
+///
+/// Frames are picked from their first packet and the others are skipped
+/// without being assembled. Keyframes are always emitted, and a frame is
+/// never emitted when a frame it may predict from was skipped: skipping a
+/// reference frame suppresses everything up to the next keyframe. Only
+/// non-reference AVC/HEVC frames (`nal_ref_idc` 0, sub-layer non-reference
+/// pictures) can be skipped without that, so streams made of reference
+/// frames only decimate to their keyframes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Decimation {
+    /// Every frame is emitted.
+    #[default]
+    Off,
+    /// One frame out of every `n`.
+    EveryNth(u32),
+    /// At most this many frames per second of RTP time.
+    MaxFps(u32),
+}
+
+pub(super) enum Sample {
+    Keep,
+    /// The first packet of a skipped frame.
+    SkipFrame,
+    /// Another packet of a skipped frame.
+    SkipPacket,
+}
+
+// Decimation state of one stream.
+#[derive(Debug, Default)]
+pub(super) struct Decimator {
+    // Timestamp of the frame decided last; `None` while its packets so far
+    // did not tell its role.
+    decided: Option<(u32, Option<bool>)>,
+    // Timestamp of the last frame kept.
+    kept: Option<u32>,
+    skipped_since_kept: u32,
+    // A reference frame was skipped since the last keyframe.
+    broken: bool,
+}
+
+impl Decimator {
+    /// Whether the packet of frame `timestamp` carrying `payload` is kept;
+    /// `pending` tells the frame already has packets buffered.
+    pub(super) fn sample(
+        &mut self,
+        decimation: Decimation,
+        codec: Codec,
+        timestamp: u32,
+        payload: &[u8],
+        pending: bool,
+    ) -> Sample {
+        match self.decided {
+            Some((ts, Some(true))) if ts == timestamp => return Sample::Keep,
+            Some((ts, Some(false))) if ts == timestamp => return Sample::SkipPacket,
+            Some((ts, None)) if ts == timestamp => {}
+            _ if pending => return Sample::Keep,
+            _ => {}
+        }
+        let Some(role) = packet_frame_role(codec, payload) else {
+            self.decided = Some((timestamp, None));
+            return Sample::Keep;
+        };
+        let keep = match role {
+            FrameRole::Keyframe => true,
+            _ if self.broken => false,
+            _ => self.due(decimation, codec, timestamp),
+        };
+        self.decided = Some((timestamp, Some(keep)));
+        if keep {
+            self.kept = Some(timestamp);
+            self.skipped_since_kept = 0;
+            self.broken &= role != FrameRole::Keyframe;
+            Sample::Keep
+        } else {
+            self.skipped_since_kept = self.skipped_since_kept.saturating_add(1);
+            self.broken |= role == FrameRole::Reference;
+            Sample::SkipFrame
+        }
+    }
+
+    fn due(&self, decimation: Decimation, codec: Codec, timestamp: u32) -> bool {
+        let Some(kept) = self.kept else {
+            return true;
+        };
+        match decimation {
+            Decimation::Off => true,
+            Decimation::EveryNth(n) => self.skipped_since_kept + 1 >= n,
+            Decimation::MaxFps(fps) => {
+                let interval = codec.clock_rate() / fps.max(1);
+                // Earlier timestamps (B-frames) are never due.
+                timestamp.wrapping_sub(kept) as i32 >= interval as i32
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        reassemble::{FrameReassembler, PushResult},
+        rtp::RtpPacket,
+        test_utils::{
+            avc,
+            fixtures::{annex_b, AVC_IDR, AVC_NON_IDR},
+            StreamBuilder,
+        },
+    };
+    use alloc::vec::Vec;
+
+    fn run(decimation: Decimation, frames: &[Vec<Vec<u8>>]) -> Vec<Vec<u8>> {
+        let mut builder = StreamBuilder::new(1);
+        for frame in frames {
+            builder = builder.frame(frame);
+        }
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .decimation(decimation)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        for bytes in builder.build() {
+            match r.push_packet_result(&RtpPacket::parse(&bytes).unwrap()) {
+                PushResult::FrameCompleted(frame) => out.push(frame),
+                PushResult::FrameDropped(reason) => panic!("dropped: {}", reason),
+                _ => {}
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn skips_disposable_frames_and_keeps_dependencies_intact() {
+        let disposable: &[u8] = &[0x01, 0x88, 0x84];
+        let gop = [
+            avc::fu_a(AVC_IDR, 3),
+            [avc::single(disposable)].to_vec(),
+            [avc::single(AVC_NON_IDR)].to_vec(),
+            avc::fu_a(disposable, 2),
+            [avc::single(AVC_NON_IDR)].to_vec(),
+        ];
+        let out = run(Decimation::EveryNth(2), &gop);
+        let expected = [AVC_IDR, AVC_NON_IDR, AVC_NON_IDR];
+        assert_eq!(out, expected.map(|nal| annex_b(&[nal])));
+
+        // Skipping a reference frame skips everything up to the keyframe.
+        let gop = [
+            [avc::single(AVC_IDR)].to_vec(),
+            [avc::single(AVC_NON_IDR)].to_vec(),
+            [avc::single(AVC_NON_IDR)].to_vec(),
+            [avc::single(AVC_NON_IDR)].to_vec(),
+            [avc::single(AVC_IDR)].to_vec(),
+        ];
+        let out = run(Decimation::MaxFps(10), &gop);
+        assert_eq!(out, [annex_b(&[AVC_IDR]), annex_b(&[AVC_IDR])]);
+    }
+}
@@ -0,0 +1,304 @@
+use crate::bitstream::split_annex_b;
+use alloc::{collections::VecDeque, vec::Vec};
+use core::ops::RangeInclusive;
+
+/// What the AVC/HEVC reassembler hands out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Delivery {
+    /// Complete access units, returned by the `push_packet` methods.
+    #[default]
+    AccessUnit,
+    /// Single NAL units, queued for [`FrameReassembler::pop_nal_unit`]
+    /// as soon as they are complete: while an access unit arrives in order,
+    /// each NAL unit is available once its last packet is, without waiting
+    /// for the marker. The `push_packet` methods return no frames; dropped
+    /// access units are still reported, but NAL units already queued from
+    /// them stay queued. Output shaping options do not apply.
+    ///
+    /// [`FrameReassembler::pop_nal_unit`]: super::FrameReassembler::pop_nal_unit
+    NalUnit,
+}
+
+/// NAL unit queued under [`Delivery::NalUnit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NalUnit {
+    /// RTP timestamp of its access unit.
+    pub timestamp: u32,
+    /// Position in the access unit's delivery order, from 0.
+    pub index: u32,
+    /// The NAL unit without start code or length prefix.
+    pub data: Vec<u8>,
+    /// Final NAL unit of a completed access unit. Missing when the packet
+    /// completing an access unit added no NAL unit of its own.
+    pub last: bool,
+}
+
+/// Frame queued under [`ReassemblerConfig::resilient`](super::ReassemblerConfig::resilient),
+/// with what it is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AssembledFrame {
+    pub timestamp: u32,
+    pub data: Vec<u8>,
+    /// Packets lost from the frame. Of a salvaged frame missing its first or
+    /// last packet, this counts everything lost since the previous frame or
+    /// until the next one.
+    pub lost_packets: u32,
+    /// Their sequence numbers, in runs; a run across wraparound is split.
+    pub missing_ranges: Vec<RangeInclusive<u16>>,
+    /// Data is missing or could not be depacketized: packets were lost,
+    /// including the first or last ones, or payloads were malformed.
+    pub corrupted: bool,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct NalQueue {
+    queue: VecDeque<NalUnit>,
+    // Access unit being delivered early: its timestamp, the bytes of its
+    // in-order run already queued and the number of NAL units queued.
+    current: Option<(u32, usize, u32)>,
+}
+
+impl NalQueue {
+    pub(super) fn pop(&mut self) -> Option<NalUnit> {
+        self.queue.pop_front()
+    }
+
+    /// Forgets the access unit being delivered early.
+    pub(super) fn reset(&mut self) {
+        self.current = None;
+    }
+
+    /// Queues the NAL units of the in-order run `out` of access unit
+    /// `timestamp` that became complete when `out[appended..]` was added;
+    /// `fu_open` tells its last NAL unit still misses fragments.
+    pub(super) fn deliver_complete(
+        &mut self,
+        timestamp: u32,
+        out: &[u8],
+        appended: usize,
+        fu_open: bool,
+    ) {
+        let (delivered, mut count) = match self.current {
+            Some((ts, delivered, count)) if ts == timestamp => (delivered, count),
+            _ => (0, 0),
+        };
+        let end = if fu_open {
+            // An FU started in this packet ends the complete part; one
+            // started earlier completed nothing new.
+            out[appended..]
+                .windows(4)
+                .rposition(|w| w == [0, 0, 0, 1])
+                .map_or(0, |at| appended + at)
+        } else {
+            out.len()
+        };
+        if end <= delivered {
+            return;
+        }
+        for nal in split_annex_b(&out[delivered..end]) {
+            self.push(timestamp, count, nal);
+            count += 1;
+        }
+        self.current = Some((timestamp, end, count));
+    }
+
+    /// Queues the NAL units of completed access unit `frame` (Annex-B) not
+    /// queued yet, the last one marked; its in-order run, if any, starts at
+    /// `run_at`.
+    pub(super) fn deliver_rest(&mut self, timestamp: u32, frame: &[u8], run_at: Option<usize>) {
+        let (skip, mut count) = match (self.current.take(), run_at) {
+            (Some((ts, delivered, count)), Some(at)) if ts == timestamp => {
+                (at..(at + delivered).min(frame.len()), count)
+            }
+            _ => (0..0, 0),
+        };
+        let queued = self.queue.len();
+        for nal in [&frame[..skip.start], &frame[skip.end..]]
+            .into_iter()
+            .flat_map(split_annex_b)
+        {
+            self.push(timestamp, count, nal);
+            count += 1;
+        }
+        if self.queue.len() > queued {
+            if let Some(last) = self.queue.back_mut() {
+                last.last = true;
+            }
+        }
+    }
+
+    fn push(&mut self, timestamp: u32, index: u32, nal: &[u8]) {
+        self.queue.push_back(NalUnit {
+            timestamp,
+            index,
+            data: nal.to_vec(),
+            last: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::Codec,
+        reassemble::{ConfigError, FrameReassembler, OutputFormat},
+        rtp::RtpPacket,
+        test_utils::{
+            avc, drop_packets,
+            fixtures::{AVC_IDR, AVC_PPS, AVC_SPS},
+            StreamBuilder,
+        },
+    };
+
+    #[test]
+    fn nal_units_are_queued_before_the_access_unit_completes() {
+        let mut packets = vec![avc::stap_a(&[AVC_SPS, AVC_PPS])];
+        packets.extend(avc::fu_a(AVC_IDR, 8));
+        let packets = StreamBuilder::new(1).frame(&packets).build();
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .delivery(Delivery::NalUnit)
+            .build()
+            .unwrap();
+        let mut popped = Vec::new();
+        for bytes in &packets {
+            assert_eq!(r.push_packet(&RtpPacket::parse(bytes).unwrap()), None);
+            popped.push(core::iter::from_fn(|| r.pop_nal_unit()).collect::<Vec<_>>());
+        }
+        let nal = |index, data: &[u8], last| NalUnit {
+            timestamp: 0,
+            index,
+            data: data.to_vec(),
+            last,
+        };
+        // The IDR slice waits for its last fragment.
+        let (last, first) = (popped.pop().unwrap(), popped.remove(0));
+        assert_eq!(first, [nal(0, AVC_SPS, false), nal(1, AVC_PPS, false)]);
+        assert!(popped.iter().all(Vec::is_empty));
+        assert_eq!(last, [nal(2, AVC_IDR, true)]);
+    }
+
+    #[test]
+    fn resilient_output_flags_damaged_and_salvaged_frames() {
+        let mut stream = StreamBuilder::new(1).start_sequence(65532);
+        for i in 0..5u8 {
+            stream = stream.frame(&avc::fu_a(&[0x41, 0x9A, i, 0xAB, 0xCD, i], 2));
+        }
+        // Frame 1 loses its middle packet, frame 2 its marker.
+        let packets = drop_packets(&stream.build(), &[4, 8]);
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .latency(core::time::Duration::from_millis(50))
+            .resilient(true)
+            .build()
+            .unwrap();
+        for bytes in &packets {
+            assert_eq!(r.push_packet(&RtpPacket::parse(bytes).unwrap()), None);
+        }
+        let frames: Vec<_> = core::iter::from_fn(|| r.pop_frame()).collect();
+        let summary: Vec<_> = frames
+            .iter()
+            .map(|f| {
+                (
+                    f.timestamp,
+                    f.lost_packets,
+                    f.missing_ranges.clone(),
+                    f.corrupted,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (0, 0, vec![], false),
+                (3000, 1, vec![0..=0], true),
+                (9000, 0, vec![], false),
+                (6000, 1, vec![4..=4], true),
+                (12000, 0, vec![], false),
+            ]
+        );
+        // What arrived of frame 1, the FU-A fragments on both sides of the
+        // gap.
+        assert_eq!(frames[1].data, [0, 0, 0, 1, 0x41, 0x9A, 1, 1]);
+        assert_eq!(
+            FrameReassembler::builder()
+                .codec(Codec::Avc)
+                .delivery(Delivery::NalUnit)
+                .resilient(true)
+                .build()
+                .map(drop),
+            Err(ConfigError::ShapedNalUnits("resilient"))
+        );
+    }
+
+    #[test]
+    fn resilient_output_counts_losses_at_frame_edges() {
+        let mut stream = StreamBuilder::new(1).start_sequence(65533);
+        for i in 0..4u8 {
+            stream = stream.frame(&avc::fu_a(&[0x41, 0x9A, i, 0xAB, 0xCD, i], 2));
+        }
+        // Frame 1 keeps only its middle packet.
+        let packets = drop_packets(&stream.build(), &[3, 5]);
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .latency(core::time::Duration::from_millis(50))
+            .resilient(true)
+            .build()
+            .unwrap();
+        for bytes in &packets {
+            assert_eq!(r.push_packet(&RtpPacket::parse(bytes).unwrap()), None);
+        }
+        let salvaged = core::iter::from_fn(|| r.pop_frame())
+            .find(|f| f.timestamp == 3000)
+            .unwrap();
+        assert_eq!(salvaged.lost_packets, 2);
+        assert_eq!(salvaged.missing_ranges, [0..=0, 2..=2]);
+        assert!(salvaged.corrupted);
+    }
+
+    #[test]
+    fn resilient_jitter_buffer_waits_for_reordered_packets() {
+        let mut packets = StreamBuilder::new(1)
+            .frame(&avc::fu_a(&[0x41, 0x9A, 1, 2, 3, 4], 2))
+            .build();
+        // The middle fragment arrives after the marker.
+        packets.swap(1, 2);
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .latency(core::time::Duration::from_millis(50))
+            .resilient(true)
+            .build()
+            .unwrap();
+        for bytes in &packets {
+            r.push_packet(&RtpPacket::parse(bytes).unwrap());
+        }
+        let frame = r.pop_frame().unwrap();
+        assert_eq!(frame.data, [0, 0, 0, 1, 0x41, 0x9A, 1, 2, 3, 4]);
+        assert!(!frame.corrupted);
+        assert_eq!(r.pop_frame(), None);
+    }
+
+    #[test]
+    fn nal_unit_delivery_rejects_output_shaping() {
+        let build = |codec, output| {
+            FrameReassembler::builder()
+                .codec(codec)
+                .output(output)
+                .delivery(Delivery::NalUnit)
+                .build()
+                .map(drop)
+        };
+        assert_eq!(
+            build(Codec::Hevc, OutputFormat::LengthPrefixed),
+            Err(ConfigError::ShapedNalUnits("output"))
+        );
+        assert_eq!(
+            build(Codec::Vp9, OutputFormat::AnnexB),
+            Err(ConfigError::UnsupportedDelivery(Codec::Vp9))
+        );
+    }
+}
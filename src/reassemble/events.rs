@@ -0,0 +1,159 @@
+use super::{DropReason, FrameDefect, MalformedReason};
+use crate::codecs::Codec;
+use alloc::collections::VecDeque;
+
+/// Significant decision recorded in the event log; see
+/// [`ReassemblerConfig::event_log`](super::ReassemblerConfig::event_log).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReassemblerEvent {
+    /// Number of packets pushed before the one that caused the event.
+    pub packet_index: u64,
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum EventKind {
+    /// A frame was dropped instead of being returned.
+    FrameDropped { timestamp: u32, reason: DropReason },
+    /// A pending frame was discarded to stay within a limit.
+    FrameEvicted {
+        timestamp: u32,
+        cause: EvictionCause,
+    },
+    /// `missing` sequence numbers were skipped before `seq`; they may still
+    /// arrive out of order.
+    SequenceGap { seq: u16, missing: u16 },
+    /// A packet arrived further behind than `max_reorder_window`.
+    PacketTooOld { seq: u16 },
+    /// A frame reached `max_buffered_packets_per_frame`.
+    FrameOverflow { timestamp: u32 },
+    /// The SSRC changed and all pending state was reset.
+    SsrcChanged { old: u32, new: u32 },
+    /// The codec was guessed from the stream.
+    CodecGuessed(Codec),
+    /// The payload of `seq` could not be depacketized and was quarantined.
+    MalformedPayload { seq: u16, reason: MalformedReason },
+    /// An output frame failed `validate_output`; it was still returned.
+    InvalidFrame { timestamp: u32, defect: FrameDefect },
+    /// The codec configuration became known with the frame at `timestamp`,
+    /// which is returned after it; see
+    /// [`FrameReassembler::take_codec_config`](super::FrameReassembler::take_codec_config).
+    CodecConfigured { timestamp: u32 },
+    /// `lost` frames were lost since the last keyframe, reaching the
+    /// [`Resync`](super::Resync) threshold.
+    ResyncStarted { lost: u32 },
+    /// The keyframe or recovery point at `timestamp` ended resyncing.
+    Resynced { timestamp: u32 },
+    /// A comfort noise packet at `timestamp` started or continued a
+    /// silence period; `level` is the noise level in -dBov, if valid.
+    ComfortNoise { timestamp: u32, level: Option<u8> },
+    /// Under [`SframeMode::Detect`](crate::sframe::SframeMode::Detect), the
+    /// frame at `timestamp` showed the stream to be SFrame encrypted; the
+    /// pending frames were discarded.
+    SframeDetected { timestamp: u32 },
+}
+
+/// Limit or stream change that caused a [`EventKind::FrameEvicted`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EvictionCause {
+    MaxPendingFrames,
+    Latency,
+    MaxBufferedBytes,
+    /// An AV1 frame started a new coded video sequence.
+    NewSequence,
+}
+
+// Ring of the most recent events; records nothing at capacity 0.
+#[derive(Debug, Default)]
+pub(crate) struct EventLog {
+    events: VecDeque<ReassemblerEvent>,
+    capacity: usize,
+    // Packets pushed so far, including the current one.
+    packets: u64,
+}
+
+impl EventLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+            packets: 0,
+        }
+    }
+
+    pub(crate) fn start_packet(&mut self) {
+        self.packets += 1;
+    }
+
+    pub(crate) fn record(&mut self, kind: EventKind) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(ReassemblerEvent {
+            packet_index: self.packets.saturating_sub(1),
+            kind,
+        });
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &ReassemblerEvent> {
+        self.events.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rtp::RtpPacket, test_utils::RtpPacketBuilder, FrameReassembler};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn keeps_the_most_recent_events() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .event_log(2)
+            .build()
+            .unwrap();
+        // FU start, a lost middle packet, then the end; then another SSRC.
+        for (payload, seq, ssrc) in [
+            (&[0x7C, 0x85, 0xAA][..], 1u16, 1u32),
+            (&[0x7C, 0x45, 0xCC], 3, 1),
+            (&[0x65, 0x01], 9, 2),
+        ] {
+            let bytes = RtpPacketBuilder::new()
+                .marker(seq != 1)
+                .sequence_number(seq)
+                .ssrc(ssrc)
+                .payload(payload)
+                .build();
+            r.push_packet(&RtpPacket::parse(&bytes).unwrap());
+        }
+        let kinds: Vec<_> = r
+            .recent_events()
+            .map(|e| (e.packet_index, e.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (
+                    1,
+                    EventKind::FrameDropped {
+                        timestamp: 0,
+                        reason: DropReason::SequenceGap
+                    }
+                ),
+                (2, EventKind::SsrcChanged { old: 1, new: 2 }),
+            ]
+        );
+
+        let mut log = EventLog::new(0);
+        log.record(EventKind::PacketTooOld { seq: 1 });
+        assert_eq!(log.iter().count(), 0);
+    }
+}
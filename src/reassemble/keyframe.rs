@@ -0,0 +1,223 @@
+use super::param_sets::{nal_role, NalRole};
+use crate::{
+    bitstream::{split_annex_b, BitError, BitReader},
+    codecs::{av1::read_leb128, sei::RecoveryPoint, Codec},
+};
+
+const AV1_OBU_SEQUENCE_HEADER: u8 = 1;
+
+/// A frame's place in the prediction structure, as far as one of its
+/// packets tells, ordered by how much depends on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum FrameRole {
+    /// No other frame predicts from it.
+    Disposable,
+    /// Later frames may predict from it.
+    Reference,
+    /// Decodable without earlier frames; parameter sets count too, as they
+    /// precede keyframes.
+    Keyframe,
+}
+
+/// Role of the frame `payload` belongs to; `None` when the payload carries
+/// no slice data to tell (e.g. an SEI or delimiter on its own).
+pub(super) fn packet_frame_role(codec: Codec, payload: &[u8]) -> Option<FrameRole> {
+    match codec {
+        Codec::Avc | Codec::Hevc => {
+            let mut role = None;
+            for_each_nal_header(codec, payload, |header| {
+                role = role.max(nal_frame_role(codec, header));
+            });
+            role
+        }
+        // Not inter-picture predicted.
+        Codec::Vp9 => payload.first().map(|&d| match d & 0x40 {
+            0 => FrameRole::Keyframe,
+            _ => FrameRole::Reference,
+        }),
+        // First packet of a new coded video sequence.
+        Codec::Av1 => payload.first().map(|&h| match h & 0x08 {
+            0 => FrameRole::Reference,
+            _ => FrameRole::Keyframe,
+        }),
+        Codec::Unknown | Codec::Custom(_) => None,
+    }
+}
+
+fn nal_frame_role(codec: Codec, header: u8) -> Option<FrameRole> {
+    match nal_role(codec, header) {
+        NalRole::Keyframe | NalRole::Vps | NalRole::Sps | NalRole::Pps => {
+            return Some(FrameRole::Keyframe)
+        }
+        NalRole::Delimiter => return None,
+        NalRole::Other => {}
+    }
+    let disposable = match codec {
+        Codec::Avc if (1..=5).contains(&(header & 0x1F)) => header & 0x60 == 0,
+        // Sub-layer non-reference pictures have even VCL types below 16.
+        Codec::Hevc if (header >> 1) & 0x3F < 32 => (header >> 1) & 0x11 == 0,
+        _ => return None,
+    };
+    Some(if disposable {
+        FrameRole::Disposable
+    } else {
+        FrameRole::Reference
+    })
+}
+
+// Calls `f` with the header of each NAL unit in an AVC/HEVC payload; for a
+// fragment, the header of the fragmented NAL unit.
+fn for_each_nal_header(codec: Codec, payload: &[u8], mut f: impl FnMut(u8)) {
+    let (aggregate, header_len) = match (codec, payload) {
+        (Codec::Avc, [indicator, ..]) => match indicator & 0x1F {
+            24 => (true, 1),
+            28 | 29 => {
+                if let Some(fu) = payload.get(1) {
+                    f((indicator & 0xE0) | (fu & 0x1F));
+                }
+                return;
+            }
+            _ => (false, 0),
+        },
+        (Codec::Hevc, [header, ..]) => match (header >> 1) & 0x3F {
+            48 => (true, 2),
+            49 => {
+                if let Some(fu) = payload.get(2) {
+                    f((header & 0x81) | ((fu & 0x3F) << 1));
+                }
+                return;
+            }
+            _ => (false, 0),
+        },
+        _ => return,
+    };
+    if !aggregate {
+        f(payload[0]);
+        return;
+    }
+    let mut rest = &payload[header_len..];
+    while let [hi, lo, nal @ ..] = rest {
+        let len = u16::from_be_bytes([*hi, *lo]) as usize;
+        if let Some(&header) = nal.first().filter(|_| len > 0) {
+            f(header);
+        }
+        rest = nal.get(len..).unwrap_or_default();
+    }
+}
+
+/// Whether an assembled frame (Annex-B for AVC/HEVC) can be decoded
+/// without earlier frames.
+pub(crate) fn is_keyframe(codec: Codec, frame: &[u8]) -> bool {
+    match codec {
+        Codec::Avc | Codec::Hevc => split_annex_b(frame)
+            .iter()
+            .any(|nal| nal.first().map(|&h| nal_role(codec, h)) == Some(NalRole::Keyframe)),
+        Codec::Vp9 => vp9_is_keyframe(frame),
+        // A sequence header starts a new coded video sequence.
+        Codec::Av1 => av1_obu_types(frame).any(|t| t == AV1_OBU_SEQUENCE_HEADER),
+        // Custom codecs answer through their `Depacketizer`.
+        Codec::Unknown | Codec::Custom(_) => false,
+    }
+}
+
+/// Pictures until decoding recovers, from the first recovery point SEI
+/// among an AVC/HEVC frame's NAL units.
+pub(super) fn recovery_after<'a>(
+    codec: Codec,
+    nals: impl IntoIterator<Item = &'a [u8]>,
+) -> Option<u32> {
+    nals.into_iter()
+        .find_map(|nal| RecoveryPoint::find(codec, nal))
+        .map(|rp| rp.recovery_frames)
+}
+
+// frame_type of the VP9 uncompressed header (0 is KEY_FRAME).
+fn vp9_is_keyframe(frame: &[u8]) -> bool {
+    let mut r = BitReader::new(frame);
+    let mut header = || -> Result<bool, BitError> {
+        if r.read_bits(2)? != 2 {
+            return Ok(false);
+        }
+        let profile = r.read_bits(1)? | r.read_bits(1)? << 1;
+        if profile == 3 {
+            r.skip(1)?;
+        }
+        let show_existing_frame = r.read_bit()?;
+        Ok(!show_existing_frame && !r.read_bit()?)
+    };
+    header().unwrap_or(false)
+}
+
+// Types of the size-delimited OBUs in `frame`, stopping at the first
+// malformed one.
+pub(super) fn av1_obu_types(frame: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    let mut rest = frame;
+    core::iter::from_fn(move || {
+        let (&header, after) = rest.split_first()?;
+        let ext = usize::from(header & 0x04 != 0);
+        if header & 0x02 == 0 {
+            rest = &[];
+            return Some((header >> 3) & 0x0F);
+        }
+        let (size, n) = read_leb128(after.get(ext..)?)?;
+        let end = (ext + n).checked_add(usize::try_from(size).ok()?)?;
+        rest = after.get(end..)?;
+        Some((header >> 3) & 0x0F)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::fixtures::{annex_b, AVC_IDR, AVC_NON_IDR, AVC_SPS};
+
+    #[test]
+    fn detects_keyframes_per_codec() {
+        assert!(is_keyframe(Codec::Avc, &annex_b(&[AVC_SPS, AVC_IDR])));
+        assert!(!is_keyframe(Codec::Avc, &annex_b(&[AVC_NON_IDR])));
+        // IDR_W_RADL (19) vs TRAIL_R (1).
+        assert!(is_keyframe(Codec::Hevc, &[0, 0, 0, 1, 0x26, 0x01, 0xAF]));
+        assert!(!is_keyframe(Codec::Hevc, &[0, 0, 0, 1, 0x02, 0x01, 0xAF]));
+        // frame_marker 2, profile 0, show_existing_frame 0, frame_type 0/1.
+        assert!(is_keyframe(Codec::Vp9, &[0x80, 0x49]));
+        assert!(!is_keyframe(Codec::Vp9, &[0x84, 0x00]));
+        // Temporal delimiter, then a sequence header or a frame OBU.
+        assert!(is_keyframe(Codec::Av1, &[0x12, 0x00, 0x0A, 0x01, 0xFF]));
+        assert!(!is_keyframe(Codec::Av1, &[0x12, 0x00, 0x32, 0x01, 0xFF]));
+    }
+
+    #[test]
+    fn tells_frame_roles_from_single_packets() {
+        use crate::test_utils::{
+            avc,
+            fixtures::{AVC_PPS, AVC_SPS},
+        };
+        let role = |payload: &[u8]| packet_frame_role(Codec::Avc, payload);
+        assert_eq!(
+            role(&avc::stap_a(&[AVC_SPS, AVC_PPS])),
+            Some(FrameRole::Keyframe)
+        );
+        assert_eq!(role(&avc::fu_a(AVC_IDR, 3)[1]), Some(FrameRole::Keyframe));
+        assert_eq!(role(AVC_NON_IDR), Some(FrameRole::Reference));
+        // nal_ref_idc 0.
+        assert_eq!(role(&[0x01, 0x88]), Some(FrameRole::Disposable));
+        // An SEI alone says nothing.
+        assert_eq!(role(&[0x06, 0x05, 0x01]), None);
+        // TRAIL_N and TRAIL_R.
+        assert_eq!(
+            packet_frame_role(Codec::Hevc, &[0x00, 0x01, 0xAF]),
+            Some(FrameRole::Disposable)
+        );
+        assert_eq!(
+            packet_frame_role(Codec::Hevc, &[0x02, 0x01, 0xAF]),
+            Some(FrameRole::Reference)
+        );
+    }
+
+    #[test]
+    fn recovery_point_sei_marks_gradual_refresh() {
+        let sei: &[u8] = &[0x06, 0x06, 0x02, 0x24, 0x40, 0x80];
+        assert_eq!(recovery_after(Codec::Avc, [sei, AVC_NON_IDR]), Some(3));
+        assert_eq!(recovery_after(Codec::Avc, [AVC_NON_IDR]), None);
+    }
+}
@@ -0,0 +1,2658 @@
+use crate::{
+    analyze::FrameAnalyzer,
+    bitstream::{annex_b_to_length_prefixed, split_annex_b},
+    clock::MediaTime,
+    cn::{is_comfort_noise, ComfortNoise},
+    codecs::{
+        av1::{parse_av1_obu_elements, parse_av1_payload_header, write_obu_with_size},
+        avc::{mvc_views, parse_avc_payload_header, AvcNalKind, AvcSliceSummary},
+        custom::{CodecRegistry, Depacketizer},
+        hevc::{parse_hevc_payload_header, HevcNalKind},
+        vp9::Vp9PayloadDesc,
+        Codec,
+    },
+    extensions::{StreamTag, StreamTagger},
+    metrics::{Metrics, MetricsSink},
+    rtp::{RtpError, RtpPacket},
+    sframe::{FrameDecryptor, SframeDetector, SframeHeader, SframeMode},
+};
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    vec::Vec,
+};
+use core::ops::RangeInclusive;
+
+mod codec_config;
+mod config;
+mod decimate;
+mod delivery;
+mod events;
+mod keyframe;
+mod nack;
+mod output;
+mod param_sets;
+mod quarantine;
+mod trace;
+mod typed;
+mod validate;
+
+pub use codec_config::CodecConfig;
+use codec_config::CodecConfigTracker;
+pub use config::{
+    ConfigError, FrameReassemblerBuilder, OutputFormat, OverflowPolicy, ParameterSetMode,
+    ReassemblerConfig, ReorderConfig, Resync, SsrcPolicy, StartCodes,
+};
+pub use decimate::Decimation;
+use decimate::{Decimator, Sample};
+use delivery::NalQueue;
+pub use delivery::{AssembledFrame, Delivery, NalUnit};
+use events::EventLog;
+pub use events::{EventKind, EvictionCause, ReassemblerEvent};
+pub(crate) use keyframe::is_keyframe;
+use keyframe::recovery_after;
+use nack::MissingSequences;
+use param_sets::ParameterSetCache;
+pub(crate) use param_sets::{nal_role, NalRole};
+use quarantine::Quarantine;
+pub use quarantine::{MalformedReason, QuarantinedPayload};
+#[cfg(feature = "std")]
+pub(crate) use trace::fnv1a;
+use trace::TraceRecorder;
+pub use trace::{Divergence, Trace, TraceError, TraceOutcome, TraceRecord, TraceReplay};
+pub use typed::{
+    Av1Frame, Av1Reassembler, AvcConfig, AvcFrame, AvcReassembler, HevcConfig, HevcFrame,
+    HevcReassembler, Vp9Frame, Vp9Reassembler,
+};
+pub use validate::FrameDefect;
+
+// Pending frames keyed by RTP timestamp. `no_std` builds fall back to an
+// ordered map since `HashMap` needs a std-provided hasher.
+#[cfg(feature = "std")]
+type FrameMap = std::collections::HashMap<u32, FrameCollector>;
+#[cfg(not(feature = "std"))]
+type FrameMap = BTreeMap<u32, FrameCollector>;
+
+#[derive(Debug, Default)]
+pub struct FrameReassembler {
+    analyzer: FrameAnalyzer,
+    current_ssrc: Option<u32>,
+    // Payload type of the current stream's last packet.
+    current_pt: Option<u8>,
+    codec: Option<Codec>,
+    frames: FrameMap,
+    // Frame being assembled straight from in-order packets, by timestamp.
+    in_order: Option<(u32, InOrderRun)>,
+    // Cleared buffer handed back by `push_packet_into`, reused for the next frame.
+    spare: Vec<u8>,
+    // Newest sequence number seen, for `max_reorder_window`.
+    highest_seq: Option<u16>,
+    // Sequence spans of the frames completed or salvaged last, bounding the
+    // losses of a frame missing its first or last packet.
+    finished_spans: VecDeque<(u16, u16)>,
+    missing: MissingSequences,
+    // Frame dropped for exceeding `max_frame_bytes`; its remaining packets
+    // are ignored.
+    oversized_ts: Option<u32>,
+    decimator: Decimator,
+    // A frame was lost since the last keyframe.
+    needs_keyframe: bool,
+    // `needs_keyframe` became set and `push_packet_result` has not said so yet.
+    keyframe_request: bool,
+    // Frames lost since the last keyframe, and whether that reached the
+    // `resync` threshold.
+    lost_since_keyframe: u32,
+    resyncing: bool,
+    // The last frame completed while resyncing under `Resync::FlagAfter`.
+    last_unsynced: bool,
+    notes: PushNotes,
+    events: EventLog,
+    quarantine: Quarantine,
+    param_sets: ParameterSetCache,
+    // Slices of the last AVC frame assembled.
+    last_slices: Option<AvcSliceSummary>,
+    // MVC view ids of the last AVC frame assembled.
+    last_views: Vec<u16>,
+    // The last AV1 frame assembled started a new coded video sequence.
+    last_new_sequence: bool,
+    // Defect `validate_output` found in the last frame.
+    last_defect: Option<FrameDefect>,
+    invalid_frames: u64,
+    codec_config: CodecConfigTracker,
+    nal_units: NalQueue,
+    // Frames queued under `resilient`.
+    assembled: VecDeque<AssembledFrame>,
+    // Loss information of the frame `finish` completed last under
+    // `resilient`, without its data.
+    unqueued: Option<AssembledFrame>,
+    metrics: Metrics,
+    registry: Option<Arc<CodecRegistry>>,
+    sframe: SframeDetector,
+    decryptor: Option<Arc<dyn FrameDecryptor>>,
+    tagger: StreamTagger,
+    trace: Option<TraceRecorder>,
+    config: ReassemblerConfig,
+}
+
+/// Outcome of [`FrameReassembler::push_packet_into`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameStatus {
+    /// The packet was buffered; no frame completed.
+    Pending,
+    /// A frame completed and was written to the output buffer.
+    Complete,
+    /// A frame completed with missing data (`drop_incomplete_frames`) or
+    /// grew too large, and was dropped.
+    Dropped(DropReason),
+}
+
+/// Why a frame was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DropReason {
+    /// Sequence numbers between the frame's packets are missing.
+    SequenceGap,
+    /// A fragmented NAL unit or OBU lacks its first or last part.
+    MissingFragment,
+    /// The frame grew past `max_frame_bytes`.
+    FrameTooLarge,
+    /// An AVC frame's slices do not start at the first macroblock or are
+    /// out of order, so a slice packet was lost.
+    MissingSlice,
+    /// A payload could not be depacketized; see
+    /// [`FrameReassembler::quarantine`].
+    MalformedPayload,
+    /// Frames were lost and [`Resync::SuppressAfter`] holds frames back
+    /// until a keyframe.
+    AwaitingKeyframe,
+    /// The [`FrameDecryptor`] rejected an SFrame encrypted frame.
+    DecryptionFailed,
+}
+
+impl DropReason {
+    /// Short description, also the `reason` label of dropped frame metrics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DropReason::SequenceGap => "sequence gap",
+            DropReason::MissingFragment => "missing fragment",
+            DropReason::FrameTooLarge => "frame too large",
+            DropReason::MissingSlice => "missing slice",
+            DropReason::MalformedPayload => "malformed payload",
+            DropReason::AwaitingKeyframe => "awaiting keyframe",
+            DropReason::DecryptionFailed => "decryption failed",
+        }
+    }
+}
+
+impl core::fmt::Display for DropReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a packet passed to [`FrameReassembler::push_packet_result`] led to.
+///
+/// When one packet causes several of these, the first applicable variant in
+/// declaration order is reported; `NeedsKeyframe` is held back until a
+/// packet has nothing else to report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PushResult {
+    /// The packet completed a frame.
+    FrameCompleted(Vec<u8>),
+    /// The packet completed a frame that was dropped.
+    FrameDropped(DropReason),
+    /// The packet is further behind the newest one than `max_reorder_window`
+    /// and was discarded.
+    TooOld,
+    /// The packet belongs to a frame already dropped or skipped by
+    /// [`Decimation`], or to another SSRC under [`SsrcPolicy::LockFirst`],
+    /// and was discarded.
+    Ignored,
+    /// The packet was already buffered.
+    Duplicate,
+    /// The packet was buffered; the pending frame with this timestamp was
+    /// dropped to stay within the configured limits.
+    EvictedFrame(u32),
+    /// The packet was buffered and let the reassembler guess the codec.
+    CodecGuessChanged(Codec),
+    /// The packet was buffered; frames were lost since the last keyframe, so
+    /// the sender should be asked for one (e.g. with an RTCP PLI).
+    NeedsKeyframe,
+    /// The packet was buffered.
+    Buffered,
+    /// The packet was comfort noise (RFC 3389) and was skipped.
+    ComfortNoise,
+}
+
+// Side effects of the packet being pushed, for `push_packet_result`.
+#[derive(Debug, Default)]
+struct PushNotes {
+    too_old: bool,
+    comfort_noise: bool,
+    ignored: bool,
+    duplicate: bool,
+    evicted: Option<u32>,
+    codec_guessed: Option<Codec>,
+}
+
+// A frame dropped by `drop_incomplete_frames`, with its buffer for reuse.
+#[derive(Debug)]
+struct Dropped {
+    buf: Vec<u8>,
+    reason: DropReason,
+}
+
+#[derive(Debug, Default)]
+struct FrameCollector {
+    packets: PacketStore,
+    // Payload bytes in `packets`.
+    packet_bytes: usize,
+    // Leading packets already assembled on the in-order path before
+    // reordering or loss was detected.
+    run: Option<InOrderRun>,
+    seen_marker: bool,
+}
+
+impl FrameCollector {
+    fn bytes(&self) -> usize {
+        self.packet_bytes + self.run.as_ref().map_or(0, |r| r.assembly.out.len())
+    }
+
+    // Whether sequence numbers between the first and last held are missing.
+    fn has_gap(&self) -> bool {
+        let Some((first, last)) = self.span() else {
+            return false;
+        };
+        let held = self.packets.len() + self.run.as_ref().map_or(0, |r| r.packets);
+        held <= last.wrapping_sub(first) as usize
+    }
+
+    // First and last sequence number held, across wraparound.
+    fn span(&self) -> Option<(u16, u16)> {
+        let run = self.run.iter().flat_map(|r| [r.first_seq, r.last_seq]);
+        let mut seqs = run.chain(self.packets.iter().map(|p| p.seq));
+        let reference = seqs.next()?;
+        let (first, last) = seqs.fold((0, 0), |(first, last), seq| {
+            let offset = seq.wrapping_sub(reference) as i16;
+            (offset.min(first), offset.max(last))
+        });
+        Some((
+            reference.wrapping_add(first as u16),
+            reference.wrapping_add(last as u16),
+        ))
+    }
+}
+
+// Consecutive packets `first_seq..=last_seq`, starting with a frame start,
+// assembled without buffering.
+#[derive(Debug)]
+struct InOrderRun {
+    first_seq: u16,
+    last_seq: u16,
+    packets: usize,
+    assembly: Assembly,
+}
+
+impl InOrderRun {
+    fn contains(&self, seq: u16) -> bool {
+        seq.wrapping_sub(self.first_seq) <= self.last_seq.wrapping_sub(self.first_seq)
+    }
+}
+
+// Output of a frame under construction plus the depacketization state
+// carried between packets.
+#[derive(Debug, Default)]
+struct Assembly {
+    out: Vec<u8>,
+    fu_open: bool,
+    av1_pending: Vec<u8>,
+    // HEVC payloads carry DONL/DOND fields.
+    hevc_donl: bool,
+    // Items allowed in one aggregation packet.
+    max_items: usize,
+    // An AV1 aggregation header had the N bit set: the frame starts a new
+    // coded video sequence.
+    new_sequence: bool,
+    // Offset of the in-order run's output in `out`, if the frame has one.
+    run_at: Option<usize>,
+    // Depacketizer of a custom codec.
+    custom: Option<Arc<dyn Depacketizer>>,
+    // First problem found, if any.
+    missing: Option<DropReason>,
+    // Sequence numbers lost between the packets assembled.
+    gaps: Vec<RangeInclusive<u16>>,
+}
+
+impl Assembly {
+    // Assembles into `out`, which must be empty.
+    fn with_buffer(mut out: Vec<u8>, capacity: usize) -> Self {
+        out.reserve(capacity);
+        Self {
+            out,
+            max_items: usize::MAX,
+            ..Default::default()
+        }
+    }
+
+    fn mark(&mut self, reason: DropReason) {
+        self.missing.get_or_insert(reason);
+    }
+
+    // Records the packets lost between `after` and `before`.
+    fn gap(&mut self, after: u16, before: u16) {
+        self.mark(DropReason::SequenceGap);
+        let (first, last) = (after.wrapping_add(1), before.wrapping_sub(1));
+        if before.wrapping_sub(after) > 0x8000 {
+            // Not in sequence order, e.g. from a map across wraparound.
+        } else if first <= last {
+            self.gaps.push(first..=last);
+        } else {
+            self.gaps.extend([first..=u16::MAX, 0..=last]);
+        }
+    }
+
+    // Malformed payloads add nothing and mark the frame.
+    fn append(&mut self, codec: Codec, payload: &[u8]) -> Result<(), MalformedReason> {
+        let out = &mut self.out;
+        let mut incomplete = false;
+        let appended = match codec {
+            Codec::Avc => FrameReassembler::append_avc_payload(
+                payload,
+                self.max_items,
+                out,
+                &mut self.fu_open,
+                &mut incomplete,
+            ),
+            Codec::Hevc => FrameReassembler::append_hevc_payload(
+                payload,
+                self.hevc_donl,
+                self.max_items,
+                out,
+                &mut self.fu_open,
+                &mut incomplete,
+            ),
+            Codec::Vp9 => FrameReassembler::append_vp9_payload(payload, out),
+            Codec::Av1 => {
+                self.new_sequence |= payload.first().is_some_and(|h| h & 0x08 != 0);
+                FrameReassembler::append_av1_payload(
+                    payload,
+                    self.max_items,
+                    out,
+                    &mut self.av1_pending,
+                    &mut incomplete,
+                )
+            }
+            Codec::Custom(_) if self.custom.is_some() => {
+                let custom = self.custom.as_ref().expect("checked above");
+                let len = out.len();
+                let appended = custom.depacketize(payload, out);
+                if appended.is_err() {
+                    out.truncate(len);
+                }
+                appended
+            }
+            Codec::Unknown | Codec::Custom(_) => {
+                out.extend_from_slice(payload);
+                Ok(())
+            }
+        };
+        if incomplete {
+            self.mark(DropReason::MissingFragment);
+        }
+        if appended.is_err() {
+            self.mark(DropReason::MalformedPayload);
+        }
+        appended
+    }
+
+    // Continues with a run assembled separately; the run begins with a frame
+    // start, so only an unfinished AV1 OBU before it is lost.
+    fn append_run(&mut self, run: InOrderRun) {
+        if !self.av1_pending.is_empty() {
+            self.mark(DropReason::MissingFragment);
+        }
+        self.run_at = Some(self.out.len());
+        self.out.extend_from_slice(&run.assembly.out);
+        self.fu_open = run.assembly.fu_open;
+        self.av1_pending = run.assembly.av1_pending;
+        self.new_sequence |= run.assembly.new_sequence;
+        if let Some(reason) = run.assembly.missing {
+            self.mark(reason);
+        }
+    }
+
+    // Output bytes, or the unused buffer when incomplete and `drop_incomplete`
+    // is set.
+    fn finish(mut self, drop_incomplete: bool) -> Result<Vec<u8>, Dropped> {
+        if !self.av1_pending.is_empty() {
+            // Last OBU fragment never arrived.
+            self.mark(DropReason::MissingFragment);
+        }
+        match self.missing {
+            Some(reason) if drop_incomplete => Err(Dropped {
+                buf: self.out,
+                reason,
+            }),
+            _ => Ok(self.out),
+        }
+    }
+}
+
+// Below this many slots a sparse window is still cheaper than a map.
+const MIN_WINDOW_SLOTS: usize = 64;
+
+// Packets of one frame. Normally a window of slots indexed by offset from
+// the lowest sequence number seen, which is allocation-light and keeps
+// sequence order across wraparound; falls back to a map when packets are
+// scattered so widely that the window would be mostly empty.
+#[derive(Debug)]
+enum PacketStore {
+    Window {
+        base: u16,
+        slots: VecDeque<Option<OwnedPkt>>,
+        len: usize,
+    },
+    Map(BTreeMap<u16, OwnedPkt>),
+}
+
+impl Default for PacketStore {
+    fn default() -> Self {
+        PacketStore::Window {
+            base: 0,
+            slots: VecDeque::new(),
+            len: 0,
+        }
+    }
+}
+
+impl PacketStore {
+    fn len(&self) -> usize {
+        match self {
+            PacketStore::Window { len, .. } => *len,
+            PacketStore::Map(map) => map.len(),
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = PacketStore::default();
+    }
+
+    // Returns the packet previously stored under the same sequence number.
+    fn insert(&mut self, pkt: OwnedPkt) -> Option<OwnedPkt> {
+        let (base, slots, len) = match self {
+            PacketStore::Map(map) => return map.insert(pkt.seq, pkt),
+            PacketStore::Window { base, slots, len } => (base, slots, len),
+        };
+        if slots.is_empty() {
+            *base = pkt.seq;
+        }
+        let offset = pkt.seq.wrapping_sub(*base) as i16;
+        let span = if offset < 0 {
+            slots.len() + offset.unsigned_abs() as usize
+        } else {
+            slots.len().max(offset as usize + 1)
+        };
+        if span > MIN_WINDOW_SLOTS.max(4 * (*len + 1)) {
+            let map = slots.drain(..).flatten().map(|p| (p.seq, p)).collect();
+            *self = PacketStore::Map(map);
+            return self.insert(pkt);
+        }
+        let index = if offset < 0 {
+            for _ in 0..offset.unsigned_abs() {
+                slots.push_front(None);
+            }
+            *base = pkt.seq;
+            0
+        } else {
+            let index = offset as usize;
+            if index >= slots.len() {
+                slots.resize_with(index + 1, || None);
+            }
+            index
+        };
+        let previous = slots[index].replace(pkt);
+        if previous.is_none() {
+            *len += 1;
+        }
+        previous
+    }
+
+    // Removes the packet first in sequence order.
+    fn pop_first(&mut self) -> Option<OwnedPkt> {
+        let (base, slots, len) = match self {
+            PacketStore::Map(map) => return map.pop_first().map(|(_, pkt)| pkt),
+            PacketStore::Window { base, slots, len } => (base, slots, len),
+        };
+        let pkt = slots.iter_mut().find_map(Option::take)?;
+        *len -= 1;
+        while let Some(None) = slots.front() {
+            slots.pop_front();
+            *base = base.wrapping_add(1);
+        }
+        Some(pkt)
+    }
+
+    // Packets in sequence order.
+    fn iter(&self) -> impl Iterator<Item = &OwnedPkt> {
+        let (window, map) = match self {
+            PacketStore::Window { slots, .. } => (Some(slots.iter().flatten()), None),
+            PacketStore::Map(map) => (None, Some(map.values())),
+        };
+        window
+            .into_iter()
+            .flatten()
+            .chain(map.into_iter().flatten())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OwnedPkt {
+    seq: u16,
+    payload: Vec<u8>,
+}
+
+impl FrameReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a builder for a fully configured reassembler.
+    pub fn builder() -> FrameReassemblerBuilder {
+        FrameReassemblerBuilder::new()
+    }
+
+    pub fn from_config(config: ReassemblerConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        let mut r = Self::default();
+        if let Some(codec) = config.codec {
+            r.change_codec(codec);
+        }
+        r.events = EventLog::new(config.event_log);
+        r.quarantine = Quarantine::new(config.quarantine);
+        r.config = config;
+        if config.trace {
+            r.start_trace();
+        }
+        Ok(r)
+    }
+
+    /// Creates a reassembler that buffers at most `max_pending_frames`
+    /// incomplete frames, reserving room for them up front.
+    pub fn with_capacity(max_pending_frames: usize) -> Self {
+        let mut r = Self::default();
+        r.config.reorder.max_pending_frames = max_pending_frames;
+        #[cfg(feature = "std")]
+        r.frames.reserve(max_pending_frames);
+        r
+    }
+
+    pub fn config(&self) -> &ReassemblerConfig {
+        &self.config
+    }
+
+    /// Slice accounting of the last AVC frame, including one dropped for
+    /// [`DropReason::MissingSlice`].
+    pub fn last_slice_summary(&self) -> Option<AvcSliceSummary> {
+        self.last_slices
+    }
+
+    /// Whether the last AV1 frame completed starts a new coded video
+    /// sequence (aggregation header N bit), making it a random access point
+    /// regardless of its OBUs.
+    pub fn last_frame_new_sequence(&self) -> bool {
+        self.last_new_sequence
+    }
+
+    /// View ids present in the last AVC frame, base view first, as listed
+    /// by [`mvc_views`]; empty unless the stream is MVC. Reported before
+    /// [`ReassemblerConfig::avc_base_view_only`] strips the other views.
+    pub fn last_mvc_views(&self) -> &[u16] {
+        &self.last_views
+    }
+
+    /// Reports packet and frame counters, frame sizes and the number of
+    /// pending frames to `sink`, also for the analyzer's metrics.
+    pub fn set_metrics(&mut self, sink: Arc<dyn MetricsSink>) {
+        self.metrics = Metrics::new(sink.clone());
+        self.analyzer.set_metrics(sink);
+    }
+
+    /// Makes the custom codecs of `registry` available for guessing (also
+    /// to the analyzer) and reassembly.
+    pub fn set_codec_registry(&mut self, registry: Arc<CodecRegistry>) {
+        self.analyzer.set_codec_registry(registry.clone());
+        self.registry = Some(registry);
+    }
+
+    /// Decrypts SFrame encrypted frames (see [`ReassemblerConfig::sframe`])
+    /// before the codec's post-processing; without one they are returned
+    /// as ciphertext.
+    pub fn set_frame_decryptor(&mut self, decryptor: Arc<dyn FrameDecryptor>) {
+        self.decryptor = Some(decryptor);
+    }
+
+    /// Whether frames are SFrame encrypted, as configured or detected.
+    pub fn encrypted(&self) -> bool {
+        match self.config.sframe {
+            SframeMode::Off => false,
+            SframeMode::Encrypted => true,
+            SframeMode::Detect => self.sframe.detected(),
+        }
+    }
+
+    /// Shorthand for changing the codec after construction, e.g. on SDP
+    /// renegotiation.
+    #[deprecated(note = "use FrameReassembler::builder()/from_config")]
+    pub fn set_codec(&mut self, codec: Codec) {
+        self.change_codec(codec);
+    }
+
+    // Switches codec, also for the analyzer and an active trace.
+    fn change_codec(&mut self, codec: Codec) {
+        self.codec = Some(codec);
+        self.config.codec = Some(codec);
+        self.analyzer.set_codec(codec);
+        if let Some(trace) = &mut self.trace {
+            trace.set_codec(codec);
+        }
+    }
+
+    pub fn codec(&self) -> Option<Codec> {
+        self.codec
+    }
+
+    /// Shorthand for replacing `config().reorder`; invalid settings are
+    /// rejected and leave the current ones in place.
+    #[deprecated(note = "use FrameReassembler::builder()/from_config")]
+    pub fn set_reorder_config(&mut self, cfg: ReorderConfig) -> Result<(), ConfigError> {
+        self.change_reorder_config(cfg)
+    }
+
+    // Replaces the reorder settings, also in an active trace.
+    fn change_reorder_config(&mut self, cfg: ReorderConfig) -> Result<(), ConfigError> {
+        cfg.validate()?;
+        self.config.reorder = cfg;
+        if let Some(trace) = &mut self.trace {
+            trace.set_reorder(&cfg);
+        }
+        Ok(())
+    }
+
+    /// Starts recording a [`Trace`] of the current configuration and all
+    /// later inputs, unless one is being recorded already.
+    pub fn start_trace(&mut self) {
+        if self.trace.is_none() {
+            self.trace = Some(TraceRecorder::new(&self.config));
+        }
+    }
+
+    /// The trace recorded since the last call; appended to each other, the
+    /// chunks form the complete trace. Empty when not recording.
+    pub fn take_trace(&mut self) -> Vec<u8> {
+        self.trace
+            .as_mut()
+            .map(TraceRecorder::take)
+            .unwrap_or_default()
+    }
+
+    /// Stops recording and returns the rest of the trace.
+    pub fn stop_trace(&mut self) -> Vec<u8> {
+        let trace = self.take_trace();
+        self.trace = None;
+        trace
+    }
+
+    // Push a parsed RTP packet. Returns Some(frame_bytes) when a full frame is completed.
+    pub fn push_packet<'a>(&mut self, pkt: &RtpPacket<'a>) -> Option<Vec<u8>> {
+        match self.push(pkt)? {
+            Ok(frame) => Some(frame),
+            Err(dropped) => {
+                self.recycle(dropped.buf);
+                None
+            }
+        }
+    }
+
+    /// Like `push_packet` for a serialized packet, also decoding the MID
+    /// and RID extensions into the [`stream_tag`](Self::stream_tag).
+    pub fn push_bytes(&mut self, packet: &[u8]) -> Result<Option<Vec<u8>>, RtpError> {
+        let pkt = RtpPacket::parse(packet)?;
+        let frame = self.push_packet(&pkt);
+        // Not for packets of another SSRC that `ssrc_policy` ignored.
+        if self.current_ssrc == Some(pkt.header.ssrc) {
+            self.tagger.observe(&pkt, packet);
+        }
+        Ok(frame)
+    }
+
+    /// Like `push_packet`, but a completed frame replaces the contents of
+    /// `out` instead of being returned in a new `Vec`.
+    ///
+    /// The previous allocation of `out` is kept and reused for the next
+    /// frame, so passing the same buffer for every packet avoids per-frame
+    /// allocations once buffers have grown to the typical frame size.
+    pub fn push_packet_into(&mut self, pkt: &RtpPacket<'_>, out: &mut Vec<u8>) -> FrameStatus {
+        match self.push(pkt) {
+            None => FrameStatus::Pending,
+            Some(Ok(frame)) => {
+                let previous = core::mem::replace(out, frame);
+                self.recycle(previous);
+                FrameStatus::Complete
+            }
+            Some(Err(dropped)) => {
+                self.recycle(dropped.buf);
+                FrameStatus::Dropped(dropped.reason)
+            }
+        }
+    }
+
+    /// Like `push_packet`, but reports what the packet led to even when no
+    /// frame completes.
+    pub fn push_packet_result(&mut self, pkt: &RtpPacket<'_>) -> PushResult {
+        let result = self.push(pkt);
+        let notes = core::mem::take(&mut self.notes);
+        match result {
+            Some(Ok(frame)) => return PushResult::FrameCompleted(frame),
+            Some(Err(dropped)) => {
+                self.recycle(dropped.buf);
+                return PushResult::FrameDropped(dropped.reason);
+            }
+            None => {}
+        }
+        if notes.too_old {
+            PushResult::TooOld
+        } else if notes.comfort_noise {
+            PushResult::ComfortNoise
+        } else if notes.ignored {
+            PushResult::Ignored
+        } else if notes.duplicate {
+            PushResult::Duplicate
+        } else if let Some(ts) = notes.evicted {
+            PushResult::EvictedFrame(ts)
+        } else if let Some(codec) = notes.codec_guessed {
+            PushResult::CodecGuessChanged(codec)
+        } else if core::mem::take(&mut self.keyframe_request) {
+            PushResult::NeedsKeyframe
+        } else {
+            PushResult::Buffered
+        }
+    }
+
+    /// Extmap id of the `sdes:mid` extension, read by
+    /// [`push_bytes`](Self::push_bytes).
+    pub fn set_mid_extension(&mut self, id: u8) {
+        self.tagger.mid_extension = Some(id);
+    }
+
+    /// Extmap id of the `sdes:rtp-stream-id` extension, read by
+    /// [`push_bytes`](Self::push_bytes) to tell simulcast encodings apart.
+    pub fn set_rid_extension(&mut self, id: u8) {
+        self.tagger.rid_extension = Some(id);
+    }
+
+    /// MID and RID of the current stream, as last seen by `push_bytes`.
+    pub fn stream_tag(&self) -> &StreamTag {
+        &self.tagger.tag
+    }
+
+    /// Sequence numbers of `ssrc` still worth a NACK, as runs oldest first;
+    /// empty unless `reorder.nack_window` is set, or for any SSRC but the
+    /// current one. A sequence number is reported until its packet arrives,
+    /// `nack_window` newer ones have, or the frame around it is completed
+    /// or dropped without it.
+    pub fn missing_sequences(&self, ssrc: u32) -> Vec<RangeInclusive<u16>> {
+        match self.highest_seq {
+            Some(newest) if self.current_ssrc == Some(ssrc) => {
+                self.missing.ranges(newest, self.nack_window())
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn nack_window(&self) -> u16 {
+        let reorder = &self.config.reorder;
+        reorder
+            .max_reorder_window
+            .map_or(reorder.nack_window, |w| w.min(reorder.nack_window))
+    }
+
+    /// The most recent significant events, oldest first; empty unless
+    /// `event_log` is configured.
+    pub fn recent_events(&self) -> impl Iterator<Item = &ReassemblerEvent> {
+        self.events.iter()
+    }
+
+    /// The most recent payloads that could not be depacketized, oldest
+    /// first; empty unless `quarantine` is configured.
+    pub fn quarantine(&self) -> impl Iterator<Item = &QuarantinedPayload> {
+        self.quarantine.iter()
+    }
+
+    /// Number of malformed payloads seen, whether kept or not.
+    pub fn malformed_payloads(&self) -> u64 {
+        self.quarantine.total()
+    }
+
+    fn quarantine_payload(
+        &mut self,
+        seq: u16,
+        timestamp: u32,
+        reason: MalformedReason,
+        payload: &[u8],
+    ) {
+        trace_event!(
+            DEBUG,
+            seq,
+            timestamp,
+            reason = reason.as_str(),
+            "malformed payload"
+        );
+        self.quarantine.record(seq, timestamp, reason, payload);
+        self.events
+            .record(EventKind::MalformedPayload { seq, reason });
+        let labels = [("reason", reason.as_str())];
+        self.metrics
+            .counter("rtpar_malformed_payloads_total", &labels, 1);
+    }
+
+    /// Defect found in the last frame completed; always `None` unless
+    /// `validate_output` is configured. The frame is still returned.
+    pub fn last_frame_defect(&self) -> Option<FrameDefect> {
+        self.last_defect
+    }
+
+    /// Number of output frames `validate_output` flagged.
+    pub fn invalid_frames(&self) -> u64 {
+        self.invalid_frames
+    }
+
+    fn flag_invalid(&mut self, timestamp: u32, defect: FrameDefect) {
+        trace_event!(
+            WARN,
+            timestamp,
+            defect = defect.as_str(),
+            "assembled frame failed validation"
+        );
+        self.last_defect = Some(defect);
+        self.invalid_frames += 1;
+        self.events
+            .record(EventKind::InvalidFrame { timestamp, defect });
+        let labels = [("reason", defect.as_str())];
+        self.metrics
+            .counter("rtpar_invalid_frames_total", &labels, 1);
+    }
+
+    /// The stream's codec configuration, once a completed frame (or the
+    /// frames before it) carried enough to build it. Returns it once, as
+    /// [`EventKind::CodecConfigured`] is recorded; see
+    /// [`codec_config`](Self::codec_config) to look again.
+    pub fn take_codec_config(&mut self) -> Option<CodecConfig> {
+        self.codec_config.take()
+    }
+
+    /// The stream's codec configuration, if known yet. AVC and HEVC need
+    /// every parameter set type, VP9 a keyframe and AV1 a sequence header.
+    pub fn codec_config(&self) -> Option<&CodecConfig> {
+        self.codec_config.get()
+    }
+
+    /// Next NAL unit under [`Delivery::NalUnit`], in arrival order.
+    pub fn pop_nal_unit(&mut self) -> Option<NalUnit> {
+        self.nal_units.pop()
+    }
+
+    /// Next frame under [`ReassemblerConfig::resilient`], in the order they
+    /// were completed or salvaged.
+    pub fn pop_frame(&mut self) -> Option<AssembledFrame> {
+        self.assembled.pop_front()
+    }
+
+    fn queue_assembled(&mut self, data: Vec<u8>) {
+        if let Some(frame) = self.unqueued.take() {
+            self.assembled.push_back(AssembledFrame { data, ..frame });
+        }
+    }
+
+    fn delivers_nal_units(&self) -> bool {
+        self.config.delivery == Delivery::NalUnit
+            && !self.encrypted()
+            && matches!(self.codec, Some(Codec::Avc | Codec::Hevc))
+    }
+
+    /// Whether a frame was lost since the last keyframe was completed. For
+    /// AVC/HEVC a frame with a recovery point SEI counts as a keyframe.
+    pub fn needs_keyframe(&self) -> bool {
+        self.needs_keyframe
+    }
+
+    /// Whether a loss burst reached the [`Resync`] threshold and no keyframe
+    /// followed yet.
+    pub fn resyncing(&self) -> bool {
+        self.resyncing
+    }
+
+    /// Whether the last frame completed was returned while resyncing under
+    /// [`Resync::FlagAfter`], so it likely references lost frames.
+    pub fn last_frame_unsynced(&self) -> bool {
+        self.last_unsynced
+    }
+
+    fn frame_lost(&mut self) {
+        if !self.needs_keyframe {
+            self.needs_keyframe = true;
+            self.keyframe_request = true;
+        }
+        self.lost_since_keyframe = self.lost_since_keyframe.saturating_add(1);
+        let lost = self.lost_since_keyframe;
+        if !self.resyncing && self.config.resync.threshold().is_some_and(|n| lost >= n) {
+            trace_event!(WARN, lost, "loss burst, waiting for a keyframe");
+            self.resyncing = true;
+            self.events.record(EventKind::ResyncStarted { lost });
+            self.metrics.counter("rtpar_resyncs_total", &[], 1);
+        }
+    }
+
+    fn keyframe_completed(&mut self, timestamp: u32) {
+        self.needs_keyframe = false;
+        self.keyframe_request = false;
+        self.lost_since_keyframe = 0;
+        if core::mem::take(&mut self.resyncing) {
+            trace_event!(INFO, timestamp, "resynchronized on keyframe");
+            self.events.record(EventKind::Resynced { timestamp });
+        }
+    }
+
+    fn recycle(&mut self, mut buf: Vec<u8>) {
+        if buf.capacity() > self.spare.capacity() {
+            buf.clear();
+            self.spare = buf;
+        }
+    }
+
+    fn new_assembly(&mut self, capacity: usize) -> Assembly {
+        let mut assembly = Assembly::with_buffer(core::mem::take(&mut self.spare), capacity);
+        assembly.hevc_donl = self.config.hevc_donl;
+        if let Some(max) = self.config.max_aggregation_items {
+            assembly.max_items = max;
+        }
+        if !self.encrypted() {
+            assembly.custom = self.custom_codec().cloned();
+        }
+        assembly
+    }
+
+    // Payload format to depacketize with: encrypted frames are opaque.
+    fn payload_codec(&self) -> Codec {
+        match self.codec {
+            Some(codec) if !self.encrypted() => codec,
+            _ => Codec::Unknown,
+        }
+    }
+
+    // Depacketizer of the current codec, if it is a registered custom one.
+    fn custom_codec(&self) -> Option<&Arc<dyn Depacketizer>> {
+        self.registry.as_ref()?.get(self.codec?)
+    }
+
+    /// Whether an assembled frame of the current codec can be decoded on
+    /// its own.
+    pub(crate) fn frame_is_keyframe(&self, frame: &[u8]) -> bool {
+        match (self.codec, self.custom_codec()) {
+            (_, Some(custom)) => custom.is_keyframe(frame),
+            (Some(codec), None) => is_keyframe(codec, frame),
+            (None, None) => false,
+        }
+    }
+
+    fn push(&mut self, pkt: &RtpPacket<'_>) -> Option<Result<Vec<u8>, Dropped>> {
+        let result = self.assemble(pkt);
+        if let Some(trace) = &mut self.trace {
+            trace.packet(pkt, &result);
+        }
+        let metrics = &self.metrics;
+        metrics.counter("rtpar_packets_total", &[], 1);
+        match &result {
+            Some(Ok(frame)) => {
+                metrics.counter("rtpar_frames_completed_total", &[], 1);
+                metrics.histogram("rtpar_frame_bytes", &[], frame.len() as u64);
+            }
+            Some(Err(dropped)) => {
+                let labels = [("reason", dropped.reason.as_str())];
+                metrics.counter("rtpar_frames_dropped_total", &labels, 1);
+            }
+            None => {}
+        }
+        if self.notes.too_old {
+            metrics.counter("rtpar_packets_too_old_total", &[], 1);
+        }
+        if self.notes.duplicate {
+            metrics.counter("rtpar_packets_duplicate_total", &[], 1);
+        }
+        if self.notes.evicted.is_some() {
+            metrics.counter("rtpar_frames_evicted_total", &[], 1);
+        }
+        let pending = self.frames.len() + self.in_order.is_some() as usize;
+        metrics.gauge("rtpar_pending_frames", &[], pending as i64);
+        match result {
+            // Its NAL units were queued by `finish`.
+            Some(Ok(frame)) if self.delivers_nal_units() => {
+                self.recycle(frame);
+                None
+            }
+            Some(Ok(frame)) if self.config.resilient => {
+                self.queue_assembled(frame);
+                None
+            }
+            result => result,
+        }
+    }
+
+    // Returns the completed frame, or Err with its buffer when it was dropped.
+    fn assemble(&mut self, pkt: &RtpPacket<'_>) -> Option<Result<Vec<u8>, Dropped>> {
+        self.notes = PushNotes::default();
+        self.events.start_packet();
+        // Silence periods of an audio stream sharing the capture or SSRC.
+        let cn_pt = self.config.comfort_noise_payload_type;
+        if is_comfort_noise(pkt.header.payload_type, cn_pt) {
+            let level = ComfortNoise::parse(pkt.payload).ok().map(|cn| cn.level);
+            trace_event!(DEBUG, timestamp = pkt.header.timestamp, "comfort noise");
+            self.events.record(EventKind::ComfortNoise {
+                timestamp: pkt.header.timestamp,
+                level,
+            });
+            self.metrics.counter("rtpar_comfort_noise_total", &[], 1);
+            self.notes.comfort_noise = true;
+            return None;
+        }
+        // Reset on SSRC change
+        if let Some(ssrc) = self.current_ssrc {
+            if ssrc != pkt.header.ssrc && self.config.ssrc_policy == SsrcPolicy::LockFirst {
+                trace_event!(
+                    DEBUG,
+                    ssrc = pkt.header.ssrc,
+                    locked_ssrc = ssrc,
+                    "ignoring packet from other ssrc"
+                );
+                self.notes.ignored = true;
+                return None;
+            }
+            if ssrc != pkt.header.ssrc {
+                trace_event!(
+                    INFO,
+                    old_ssrc = ssrc,
+                    new_ssrc = pkt.header.ssrc,
+                    dropped_frames = self.frames.len() + self.in_order.is_some() as usize,
+                    "ssrc changed, resetting reassembler"
+                );
+                self.events.record(EventKind::SsrcChanged {
+                    old: ssrc,
+                    new: pkt.header.ssrc,
+                });
+                // The new stream needs a keyframe of its own.
+                self.needs_keyframe = false;
+                self.lost_since_keyframe = 0;
+                self.frame_lost();
+                self.frames.clear();
+                self.in_order = None;
+                self.highest_seq = None;
+                self.finished_spans.clear();
+                self.missing.clear();
+                self.oversized_ts = None;
+                self.decimator = Decimator::default();
+                self.sframe = SframeDetector::default();
+                self.nal_units.reset();
+                let keep = self.config.ssrc_policy == SsrcPolicy::KeepParameterSets;
+                if keep && self.current_pt == Some(pkt.header.payload_type) {
+                    trace_event!(DEBUG, "keeping parameter sets across ssrc change");
+                } else {
+                    self.param_sets = ParameterSetCache::default();
+                    self.codec_config = CodecConfigTracker::default();
+                    if keep {
+                        self.codec = self.config.codec;
+                    }
+                }
+                self.analyzer = FrameAnalyzer::new();
+                self.analyzer.metrics = self.metrics.clone();
+                if let Some(registry) = &self.registry {
+                    self.analyzer.set_codec_registry(registry.clone());
+                }
+                if let Some(c) = self.codec {
+                    self.analyzer.set_codec(c);
+                }
+            }
+        }
+        self.current_ssrc = Some(pkt.header.ssrc);
+        self.current_pt = Some(pkt.header.payload_type);
+
+        if self.config.sframe == SframeMode::Detect
+            && !self.sframe.detected()
+            && self.sframe.push(pkt)
+        {
+            trace_event!(INFO, timestamp = pkt.header.timestamp, "sframe detected");
+            self.events.record(EventKind::SframeDetected {
+                timestamp: pkt.header.timestamp,
+            });
+            self.metrics.counter("rtpar_sframe_detected_total", &[], 1);
+            // Frames buffered so far were depacketized as the codec's.
+            self.frames.clear();
+            self.in_order = None;
+            self.nal_units.reset();
+            self.codec = self.config.codec;
+        }
+
+        // Update analyzer for codec guess tracking; ciphertext would only
+        // mislead it.
+        if !self.encrypted() {
+            let _ = self.analyzer.analyze(pkt);
+        }
+        if self.codec.is_none() && !self.encrypted() {
+            self.codec = self.analyzer.codec();
+            self.notes.codec_guessed = self.codec;
+            if let Some(codec) = self.codec {
+                self.events.record(EventKind::CodecGuessed(codec));
+            }
+        }
+
+        let ts = pkt.header.timestamp;
+        let seq = pkt.header.sequence_number;
+        let codec = self.payload_codec();
+        let nal_delivery = self.delivers_nal_units();
+
+        match self.highest_seq {
+            Some(highest) if (seq.wrapping_sub(highest) as i16) <= 0 => {
+                let behind = highest.wrapping_sub(seq);
+                if self
+                    .config
+                    .reorder
+                    .max_reorder_window
+                    .is_some_and(|w| behind > w)
+                {
+                    trace_event!(
+                        DEBUG,
+                        seq,
+                        highest_seq = highest,
+                        "packet outside reorder window"
+                    );
+                    self.notes.too_old = true;
+                    self.events.record(EventKind::PacketTooOld { seq });
+                    return None;
+                }
+                self.missing.received(seq);
+            }
+            previous => {
+                let missing = previous.map_or(0, |h| seq.wrapping_sub(h).wrapping_sub(1));
+                if missing > 0 {
+                    self.events.record(EventKind::SequenceGap { seq, missing });
+                }
+                if let Some(highest) = previous {
+                    let window = self.nack_window();
+                    self.missing.advance(highest, seq, window);
+                }
+                self.highest_seq = Some(seq);
+            }
+        }
+        if self.oversized_ts == Some(ts) {
+            self.notes.ignored = true;
+            return None;
+        }
+        if self.config.decimation != Decimation::Off && !self.sample(codec, ts, pkt.payload) {
+            self.notes.ignored = true;
+            return None;
+        }
+
+        // Fast path: the next packet of the in-order frame is appended
+        // directly to its output.
+        if let Some((run_ts, run)) = &mut self.in_order {
+            if *run_ts == ts && run.contains(seq) {
+                self.notes.duplicate = true;
+                return None;
+            }
+            if *run_ts == ts
+                && seq == run.last_seq.wrapping_add(1)
+                && run.packets < self.config.reorder.max_buffered_packets_per_frame
+            {
+                let before = run.assembly.out.len();
+                let appended = run.assembly.append(codec, pkt.payload);
+                if nal_delivery && !pkt.header.marker {
+                    let assembly = &run.assembly;
+                    self.nal_units
+                        .deliver_complete(ts, &assembly.out, before, assembly.fu_open);
+                }
+                run.last_seq = seq;
+                run.packets += 1;
+                let size = run.assembly.out.len();
+                if let Err(reason) = appended {
+                    self.quarantine_payload(seq, ts, reason, pkt.payload);
+                }
+                if self.config.reorder.exceeds_max_frame_bytes(size) {
+                    let (_, run) = self.in_order.take()?;
+                    return self.drop_oversized(ts, run.assembly.out);
+                }
+                if pkt.header.marker {
+                    return self.finish_in_order();
+                }
+                return None;
+            }
+            // Reordering or loss: hand the run over to the buffered path.
+            self.spill_in_order();
+        }
+        if !self.frames.is_empty() {
+            self.expire_stale(ts);
+        }
+        if !self.frames.contains_key(&ts) && self.starts_frame(codec, pkt.payload) {
+            let capacity = (pkt.payload.len() + 4).max(self.config.reorder.expected_frame_size);
+            let mut assembly = self.new_assembly(capacity);
+            assembly.run_at = Some(0);
+            if let Err(reason) = assembly.append(codec, pkt.payload) {
+                self.quarantine_payload(seq, ts, reason, pkt.payload);
+            }
+            if nal_delivery && !pkt.header.marker {
+                self.nal_units
+                    .deliver_complete(ts, &assembly.out, 0, assembly.fu_open);
+            }
+            let run = InOrderRun {
+                first_seq: seq,
+                last_seq: seq,
+                packets: 1,
+                assembly,
+            };
+            if self
+                .config
+                .reorder
+                .exceeds_max_frame_bytes(run.assembly.out.len())
+            {
+                return self.drop_oversized(ts, run.assembly.out);
+            }
+            self.in_order = Some((ts, run));
+            if pkt.header.marker {
+                return self.finish_in_order();
+            }
+            return None;
+        }
+
+        // Insert packet into frame map keyed by RTP timestamp
+        if !self.frames.contains_key(&ts) {
+            self.evict_to_capacity(ts);
+        }
+        let entry = self.frames.entry(ts).or_default();
+        if entry.run.as_ref().is_some_and(|run| run.contains(seq)) {
+            self.notes.duplicate = true;
+            return None;
+        }
+        if entry.packets.len() >= self.config.reorder.max_buffered_packets_per_frame {
+            self.events
+                .record(EventKind::FrameOverflow { timestamp: ts });
+            match self.config.reorder.overflow {
+                OverflowPolicy::DropFrame => {
+                    trace_event!(
+                        WARN,
+                        timestamp = ts,
+                        packets = entry.packets.len(),
+                        "frame exceeded max_buffered_packets_per_frame, discarding its packets"
+                    );
+                    entry.packets.clear();
+                    entry.packet_bytes = 0;
+                }
+                OverflowPolicy::DropOldestPacket => {
+                    if let Some(oldest) = entry.packets.pop_first() {
+                        entry.packet_bytes -= oldest.payload.len();
+                    }
+                }
+            }
+        }
+        entry.packet_bytes += pkt.payload.len();
+        let replaced = entry.packets.insert(OwnedPkt {
+            seq,
+            payload: pkt.payload.to_vec(),
+        });
+        if let Some(old) = replaced {
+            entry.packet_bytes -= old.payload.len();
+            self.notes.duplicate = true;
+        }
+        if pkt.header.marker {
+            entry.seen_marker = true;
+        }
+        if self.config.reorder.exceeds_max_frame_bytes(entry.bytes()) {
+            let buf = self
+                .frames
+                .remove(&ts)
+                .and_then(|entry| {
+                    self.missing.forget(&entry);
+                    entry.run
+                })
+                .map(|run| run.assembly.out)
+                .unwrap_or_default();
+            return self.drop_oversized(ts, buf);
+        }
+        if self.config.max_buffered_bytes.is_some() {
+            self.enforce_memory_limit(ts);
+        }
+
+        // If marker received for this frame, attempt to assemble and flush only when start conditions are present (for reordering)
+        if self.frames.get(&ts).is_some_and(|e| e.seen_marker)
+            && self.frame_ready_to_flush(ts, codec)
+            && !self.awaits_missing_packets(ts)
+        {
+            return self.assemble_frame(ts, false);
+        }
+        None
+    }
+
+    // Whether decimation keeps the frame of a packet; forgets what was
+    // buffered of a frame it skips.
+    fn sample(&mut self, codec: Codec, ts: u32, payload: &[u8]) -> bool {
+        let in_order = self.in_order.as_ref().is_some_and(|(t, _)| *t == ts);
+        let pending = in_order || self.frames.contains_key(&ts);
+        match self
+            .decimator
+            .sample(self.config.decimation, codec, ts, payload, pending)
+        {
+            Sample::Keep => true,
+            Sample::SkipFrame => {
+                trace_event!(TRACE, timestamp = ts, "frame skipped by decimation");
+                if in_order {
+                    self.in_order = None;
+                }
+                if let Some(frame) = self.frames.remove(&ts) {
+                    self.missing.forget(&frame);
+                }
+                self.metrics.counter("rtpar_frames_decimated_total", &[], 1);
+                false
+            }
+            Sample::SkipPacket => false,
+        }
+    }
+
+    fn finish_in_order(&mut self) -> Option<Result<Vec<u8>, Dropped>> {
+        let (ts, run) = self.in_order.take()?;
+        self.frame_finished((run.first_seq, run.last_seq));
+        Some(self.finish(ts, run.assembly))
+    }
+
+    fn finish(&mut self, timestamp: u32, mut assembly: Assembly) -> Result<Vec<u8>, Dropped> {
+        let mut damaged = assembly.missing.is_some() || !assembly.av1_pending.is_empty();
+        let new_sequence = assembly.new_sequence;
+        let run_at = assembly.run_at;
+        let gaps = core::mem::take(&mut assembly.gaps);
+        let drop_incomplete = self.config.reorder.drop_incomplete_frames && !self.config.resilient;
+        let mut result = assembly.finish(drop_incomplete);
+        // Without a decryptor encrypted frames skip codec post-processing.
+        let mut codec = self.codec;
+        if self.encrypted() {
+            match (&mut result, self.decryptor.clone()) {
+                (Ok(frame), Some(decryptor)) => match self.decrypt(decryptor.as_ref(), frame) {
+                    Some(plain) => self.recycle(core::mem::replace(frame, plain)),
+                    None => {
+                        result = Err(Dropped {
+                            buf: core::mem::take(frame),
+                            reason: DropReason::DecryptionFailed,
+                        });
+                    }
+                },
+                _ => codec = None,
+            }
+        }
+        self.last_slices = None;
+        self.last_views.clear();
+        self.last_new_sequence = false;
+        self.last_defect = None;
+        if let (Ok(frame), Some(Codec::Avc)) = (&mut result, codec) {
+            let nals = split_annex_b(frame);
+            self.last_views = mvc_views(nals.iter().copied());
+            let slices = self.analyzer.avc_slices(nals);
+            self.last_slices = Some(slices);
+            if !slices.complete {
+                damaged = true;
+                if drop_incomplete {
+                    result = Err(Dropped {
+                        buf: core::mem::take(frame),
+                        reason: DropReason::MissingSlice,
+                    });
+                }
+            }
+        }
+        match (&result, codec) {
+            _ if damaged => self.frame_lost(),
+            (Ok(_), Some(Codec::Av1)) if new_sequence => {
+                self.last_new_sequence = true;
+                self.keyframe_completed(timestamp);
+                self.discard_previous_sequence(timestamp);
+            }
+            (Ok(frame), Some(codec))
+                if self.needs_keyframe
+                    && (self.frame_is_keyframe(frame)
+                        || recovery_after(codec, split_annex_b(frame)).is_some()) =>
+            {
+                self.keyframe_completed(timestamp);
+            }
+            _ => {}
+        }
+        self.last_unsynced = false;
+        if let (Ok(frame), true) = (&mut result, self.resyncing) {
+            match self.config.resync {
+                Resync::SuppressAfter(_) => {
+                    result = Err(Dropped {
+                        buf: core::mem::take(frame),
+                        reason: DropReason::AwaitingKeyframe,
+                    });
+                }
+                Resync::FlagAfter(_) => self.last_unsynced = true,
+                Resync::Off => {}
+            }
+        }
+        if let (Ok(frame), Some(codec)) = (&result, codec) {
+            if self.codec_config.observe(codec, frame) {
+                trace_event!(DEBUG, timestamp, "codec configuration known");
+                self.events.record(EventKind::CodecConfigured { timestamp });
+            }
+        }
+        if let (Ok(frame), true) = (&result, self.delivers_nal_units()) {
+            self.nal_units.deliver_rest(timestamp, frame, run_at);
+        }
+        if let (Ok(_), true) = (&result, self.config.resilient) {
+            self.unqueued = Some(AssembledFrame {
+                timestamp,
+                data: Vec::new(),
+                lost_packets: gaps.iter().map(|r| r.len() as u32).sum(),
+                missing_ranges: gaps,
+                corrupted: damaged,
+            });
+        }
+        if let (Ok(frame), Some(codec @ (Codec::Avc | Codec::Hevc))) = (&mut result, codec) {
+            if self.config.parameter_sets == ParameterSetMode::InjectOnKeyframe {
+                self.param_sets.process(codec, frame);
+            }
+            if self.config.avc_base_view_only {
+                output::strip_non_base_views(frame);
+            }
+            if self.config.access_unit_delimiters {
+                output::prepend_aud(codec, frame);
+            }
+            match (self.config.output, self.config.start_codes) {
+                (OutputFormat::LengthPrefixed, _) => annex_b_to_length_prefixed(frame),
+                (_, StartCodes::FourByte) => {}
+                (_, StartCodes::FourByteFirst) => output::shorten_start_codes(frame, false),
+                (_, StartCodes::ThreeByte) => output::shorten_start_codes(frame, true),
+            }
+        }
+        if let (Ok(frame), Some(codec), true) = (&result, codec, self.config.validate_output) {
+            if let Err(defect) = validate::validate(codec, self.config.output, frame) {
+                self.flag_invalid(timestamp, defect);
+            }
+        }
+        match &result {
+            #[cfg(feature = "tracing")]
+            Ok(frame) => trace_event!(TRACE, timestamp, bytes = frame.len(), "frame completed"),
+            #[cfg(not(feature = "tracing"))]
+            Ok(_) => {}
+            Err(dropped) => {
+                trace_event!(
+                    DEBUG,
+                    timestamp,
+                    reason = %dropped.reason,
+                    "incomplete frame dropped"
+                );
+                self.events.record(EventKind::FrameDropped {
+                    timestamp,
+                    reason: dropped.reason,
+                });
+            }
+        }
+        result
+    }
+
+    fn decrypt(&self, decryptor: &dyn FrameDecryptor, frame: &[u8]) -> Option<Vec<u8>> {
+        let (header, _) = SframeHeader::parse(frame).ok()?;
+        match decryptor.decrypt(&header, frame) {
+            Ok(plain) => Some(plain),
+            Err(_e) => {
+                trace_event!(DEBUG, kid = header.kid, error = %_e, "sframe decryption failed");
+                None
+            }
+        }
+    }
+
+    // Moves the in-order run into a buffered frame so later packets can
+    // still complete it.
+    fn spill_in_order(&mut self) {
+        if let Some((ts, run)) = self.in_order.take() {
+            self.evict_to_capacity(ts);
+            self.frames.entry(ts).or_default().run = Some(run);
+        }
+    }
+
+    fn drop_oversized(&mut self, ts: u32, buf: Vec<u8>) -> Option<Result<Vec<u8>, Dropped>> {
+        trace_event!(
+            DEBUG,
+            timestamp = ts,
+            "frame exceeded max_frame_bytes, dropping it"
+        );
+        self.oversized_ts = Some(ts);
+        self.frame_lost();
+        self.events.record(EventKind::FrameDropped {
+            timestamp: ts,
+            reason: DropReason::FrameTooLarge,
+        });
+        Some(Err(Dropped {
+            buf,
+            reason: DropReason::FrameTooLarge,
+        }))
+    }
+
+    // Drops pending frames more than the configured latency behind `ts`.
+    fn expire_stale(&mut self, ts: u32) {
+        let Some(ticks) = self.config.latency_ticks() else {
+            return;
+        };
+        if self.config.resilient {
+            let stale: Vec<u32> = self
+                .frames
+                .keys()
+                .copied()
+                .filter(|&t| MediaTime::ticks_between(ts, t) > ticks as i32)
+                .collect();
+            for t in stale {
+                self.salvage(t);
+            }
+            return;
+        }
+        let mut expired = None;
+        let events = &mut self.events;
+        let missing = &mut self.missing;
+        self.frames.retain(|&frame_ts, frame| {
+            let keep = MediaTime::ticks_between(ts, frame_ts) <= ticks as i32;
+            if !keep {
+                trace_event!(
+                    DEBUG,
+                    timestamp = frame_ts,
+                    newest_timestamp = ts,
+                    "dropping frame older than latency"
+                );
+                expired = Some(frame_ts);
+                events.record(EventKind::FrameEvicted {
+                    timestamp: frame_ts,
+                    cause: EvictionCause::Latency,
+                });
+                missing.forget(frame);
+            }
+            keep
+        });
+        if expired.is_some() {
+            self.notes.evicted = expired;
+            self.frame_lost();
+        }
+    }
+
+    // Drops pending frames older than `ts`, whose coded video sequence
+    // ended; nothing after `ts` can depend on them.
+    fn discard_previous_sequence(&mut self, ts: u32) {
+        let mut discarded = None;
+        let events = &mut self.events;
+        let missing = &mut self.missing;
+        self.frames.retain(|&frame_ts, frame| {
+            let keep = MediaTime::ticks_between(ts, frame_ts) <= 0;
+            if !keep {
+                trace_event!(
+                    DEBUG,
+                    timestamp = frame_ts,
+                    sequence_timestamp = ts,
+                    "dropping frame of previous coded video sequence"
+                );
+                discarded = Some(frame_ts);
+                events.record(EventKind::FrameEvicted {
+                    timestamp: frame_ts,
+                    cause: EvictionCause::NewSequence,
+                });
+                missing.forget(frame);
+            }
+            keep
+        });
+        if discarded.is_some() {
+            self.notes.evicted = discarded;
+        }
+    }
+
+    // Drops the oldest frames (the one at `ts` only as a last resort) until the
+    // buffered payload fits `max_buffered_bytes`.
+    fn enforce_memory_limit(&mut self, ts: u32) {
+        let Some(limit) = self.config.max_buffered_bytes else {
+            return;
+        };
+        loop {
+            let total: usize = self.frames.values().map(FrameCollector::bytes).sum();
+            if total <= limit {
+                return;
+            }
+            let oldest = self
+                .frames
+                .keys()
+                .copied()
+                .filter(|&t| t != ts)
+                .max_by_key(|&t| ts.wrapping_sub(t))
+                .unwrap_or(ts);
+            trace_event!(
+                DEBUG,
+                timestamp = oldest,
+                buffered_bytes = total,
+                "dropping frame over max_buffered_bytes"
+            );
+            if let Some(frame) = self.frames.remove(&oldest) {
+                self.missing.forget(&frame);
+            }
+            self.notes.evicted = Some(oldest);
+            self.events.record(EventKind::FrameEvicted {
+                timestamp: oldest,
+                cause: EvictionCause::MaxBufferedBytes,
+            });
+            self.frame_lost();
+        }
+    }
+
+    // Drops the frames furthest behind `incoming_ts` until a new frame fits.
+    fn evict_to_capacity(&mut self, incoming_ts: u32) {
+        let capacity = self.config.reorder.max_pending_frames.max(1);
+        while self.frames.len() >= capacity {
+            let oldest = self
+                .frames
+                .keys()
+                .copied()
+                .max_by_key(|&ts| incoming_ts.wrapping_sub(ts));
+            match oldest {
+                Some(ts) if self.config.resilient => self.salvage(ts),
+                Some(ts) => {
+                    trace_event!(
+                        DEBUG,
+                        timestamp = ts,
+                        incoming_timestamp = incoming_ts,
+                        pending = self.frames.len(),
+                        "evicting oldest pending frame"
+                    );
+                    if let Some(frame) = self.frames.remove(&ts) {
+                        self.missing.forget(&frame);
+                    }
+                    self.notes.evicted = Some(ts);
+                    self.events.record(EventKind::FrameEvicted {
+                        timestamp: ts,
+                        cause: EvictionCause::MaxPendingFrames,
+                    });
+                    self.frame_lost();
+                }
+                None => break,
+            }
+        }
+    }
+
+    // Emits a pending frame that would be evicted under `resilient`.
+    fn salvage(&mut self, ts: u32) {
+        let codec = self.payload_codec();
+        let truncated = !self.frame_ready_to_flush(ts, codec);
+        trace_event!(DEBUG, timestamp = ts, truncated, "salvaging pending frame");
+        match self.assemble_frame(ts, truncated) {
+            Some(Ok(frame)) => {
+                self.metrics.counter("rtpar_frames_salvaged_total", &[], 1);
+                self.queue_assembled(frame);
+            }
+            Some(Err(dropped)) => self.recycle(dropped.buf),
+            None => {}
+        }
+    }
+
+    // Whether `payload` carries the first bytes of a frame.
+    fn starts_frame(&self, codec: Codec, payload: &[u8]) -> bool {
+        match codec {
+            Codec::Avc => match parse_avc_payload_header(payload) {
+                Ok((
+                    AvcNalKind::Single(_)
+                    | AvcNalKind::StapA
+                    | AvcNalKind::StapB
+                    | AvcNalKind::Mtap16
+                    | AvcNalKind::Mtap24,
+                    _,
+                )) => true,
+                Ok((AvcNalKind::FuA { start, .. } | AvcNalKind::FuB { start, .. }, _)) => start,
+                Ok((AvcNalKind::Unknown(_), _)) | Err(_) => false,
+            },
+            Codec::Hevc => match parse_hevc_payload_header(payload) {
+                Ok((HevcNalKind::Single { .. } | HevcNalKind::Ap | HevcNalKind::Pacsi, _)) => true,
+                Ok((HevcNalKind::Fu { start, .. }, _)) => start,
+                Ok((HevcNalKind::Unknown(_), _)) | Err(_) => false,
+            },
+            Codec::Vp9 => matches!(Vp9PayloadDesc::parse(payload), Ok((desc, _)) if desc.b_bit),
+            Codec::Av1 => !matches!(parse_av1_payload_header(payload), Ok((hdr, _)) if hdr.z_bit),
+            Codec::Custom(_) => self
+                .custom_codec()
+                .is_none_or(|custom| custom.starts_frame(payload)),
+            Codec::Unknown => true,
+        }
+    }
+
+    // A resilient jitter buffer holds a frame with gaps until they fill or
+    // it falls behind `latency` and is salvaged.
+    fn awaits_missing_packets(&self, timestamp: u32) -> bool {
+        self.config.resilient
+            && self.config.latency.is_some()
+            && self
+                .frames
+                .get(&timestamp)
+                .is_some_and(FrameCollector::has_gap)
+    }
+
+    fn frame_ready_to_flush(&self, timestamp: u32, codec: Codec) -> bool {
+        let entry = match self.frames.get(&timestamp) {
+            Some(e) => e,
+            None => return false,
+        };
+        if !entry.seen_marker {
+            return false;
+        }
+        match codec {
+            Codec::Av1 | Codec::Unknown => true,
+            _ => {
+                entry.run.is_some()
+                    || entry
+                        .packets
+                        .iter()
+                        .any(|pkt| self.starts_frame(codec, &pkt.payload))
+            }
+        }
+    }
+
+    // Removes the frame at `timestamp` and depacketizes it in sequence order;
+    // a `truncated` frame lacks its first or last packet.
+    fn assemble_frame(
+        &mut self,
+        timestamp: u32,
+        truncated: bool,
+    ) -> Option<Result<Vec<u8>, Dropped>> {
+        let codec = self.payload_codec();
+        let (prev_seq, next_seq) = self.neighbour_seqs(timestamp);
+        let entry = self.frames.remove(&timestamp)?;
+        self.missing.forget(&entry);
+        let span = entry.span();
+        let has_start = entry.run.is_some()
+            || entry
+                .packets
+                .iter()
+                .any(|pkt| self.starts_frame(codec, &pkt.payload));
+        let has_end = entry.seen_marker;
+        let capacity =
+            Self::assembled_size(codec, &entry).max(self.config.reorder.expected_frame_size);
+        let mut assembly = self.new_assembly(capacity);
+        let mut run = entry.run;
+
+        // Detect sequence gaps; the in-order run counts as one contiguous span.
+        // Without its first packet the frame's losses start after the
+        // previous frame.
+        let mut last_seq = prev_seq.filter(|_| truncated && !has_start);
+        let mut check_gap = |first: u16, last: u16, assembly: &mut Assembly| {
+            if let Some(prev) = last_seq {
+                if first.wrapping_sub(prev) != 1 {
+                    assembly.gap(prev, first);
+                }
+            }
+            last_seq = Some(last);
+        };
+        for pkt in entry.packets.iter() {
+            if let Some(r) = &run {
+                let offset = pkt.seq.wrapping_sub(r.first_seq) as i16;
+                if offset >= 0 {
+                    if offset as usize <= r.last_seq.wrapping_sub(r.first_seq) as usize {
+                        // Duplicate of a packet already in the run.
+                        continue;
+                    }
+                    let r = run.take().expect("checked above");
+                    check_gap(r.first_seq, r.last_seq, &mut assembly);
+                    assembly.append_run(r);
+                }
+            }
+            check_gap(pkt.seq, pkt.seq, &mut assembly);
+            if let Err(reason) = assembly.append(codec, &pkt.payload) {
+                self.quarantine_payload(pkt.seq, timestamp, reason, &pkt.payload);
+            }
+        }
+        if let Some(r) = run {
+            check_gap(r.first_seq, r.last_seq, &mut assembly);
+            assembly.append_run(r);
+        }
+        // Without its last packet they run up to the next frame.
+        if let Some(next) = next_seq.filter(|_| truncated && !has_end) {
+            check_gap(next, next, &mut assembly);
+        }
+        if truncated {
+            assembly.mark(DropReason::MissingFragment);
+        }
+        if let Some(span) = span {
+            self.frame_finished(span);
+        }
+
+        Some(self.finish(timestamp, assembly))
+    }
+
+    fn frame_finished(&mut self, span: (u16, u16)) {
+        if self.finished_spans.len() >= self.config.reorder.max_pending_frames.max(1) {
+            self.finished_spans.pop_front();
+        }
+        self.finished_spans.push_back(span);
+    }
+
+    // Closest sequence numbers received before and after the frame at
+    // `timestamp`, from the frames around it or the newest packet.
+    fn neighbour_seqs(&self, timestamp: u32) -> (Option<u16>, Option<u16>) {
+        let Some((first, last)) = self.frames.get(&timestamp).and_then(FrameCollector::span) else {
+            return (None, None);
+        };
+        let pending = self
+            .frames
+            .iter()
+            .filter(|(&ts, _)| ts != timestamp)
+            .filter_map(|(_, frame)| frame.span());
+        let run = self
+            .in_order
+            .as_ref()
+            .map(|(_, run)| (run.first_seq, run.last_seq));
+        let spans = || {
+            pending
+                .clone()
+                .chain(run)
+                .chain(self.finished_spans.iter().copied())
+        };
+        let prev = spans()
+            .map(|(_, l)| l)
+            .filter(|&l| (first.wrapping_sub(l) as i16) > 0)
+            .min_by_key(|&l| first.wrapping_sub(l));
+        let next = spans()
+            .map(|(f, _)| f)
+            .chain(self.highest_seq)
+            .filter(|&f| (f.wrapping_sub(last) as i16) > 0)
+            .min_by_key(|&f| f.wrapping_sub(last));
+        (prev, next)
+    }
+
+    // Output size estimate: payload bytes plus one start code (AVC/HEVC) or
+    // OBU size field (AV1) per packet. Aggregates of many tiny NAL units can
+    // exceed it, costing one extra reallocation.
+    fn assembled_size(codec: Codec, entry: &FrameCollector) -> usize {
+        let payload: usize = entry.packets.iter().map(|p| p.payload.len()).sum();
+        let run = entry.run.as_ref().map_or(0, |r| r.assembly.out.len());
+        match codec {
+            Codec::Avc | Codec::Hevc | Codec::Av1 => run + payload + 4 * entry.packets.len(),
+            Codec::Vp9 | Codec::Unknown | Codec::Custom(_) => run + payload,
+        }
+    }
+
+    fn write_start_code(buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&[0, 0, 0, 1]);
+    }
+
+    // The append functions write what they can depacketize; a malformed
+    // payload is reported instead of being copied through.
+    pub(crate) fn append_avc_payload(
+        payload: &[u8],
+        max_items: usize,
+        out: &mut Vec<u8>,
+        fu_open: &mut bool,
+        incomplete: &mut bool,
+    ) -> Result<(), MalformedReason> {
+        let (kind, off) =
+            parse_avc_payload_header(payload).map_err(|_| MalformedReason::InvalidHeader)?;
+        match kind {
+            AvcNalKind::Single(_) => {
+                Self::write_start_code(out);
+                out.extend_from_slice(&payload[0..]);
+            }
+            AvcNalKind::StapA => {
+                // STAP-A: 1-byte indicator then series of (16-bit size, nalu)
+                let mut i = 1usize; // skip indicator
+                let mut items = 0;
+                while i < payload.len() {
+                    items += 1;
+                    if items > max_items {
+                        return Err(MalformedReason::TooManyItems);
+                    }
+                    let size = match payload.get(i..i + 2) {
+                        Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]) as usize,
+                        _ => return Err(MalformedReason::TruncatedAggregate),
+                    };
+                    i += 2;
+                    let nal = payload
+                        .get(i..i + size)
+                        .ok_or(MalformedReason::TruncatedAggregate)?;
+                    Self::write_start_code(out);
+                    out.extend_from_slice(nal);
+                    i += size;
+                }
+            }
+            AvcNalKind::FuA {
+                start,
+                end: _,
+                nal_type,
+            }
+            | AvcNalKind::FuB {
+                start,
+                end: _,
+                nal_type,
+            } => {
+                if start {
+                    // Reconstruct NAL header: take F and NRI from FU indicator, payload type from FU header
+                    let fu_indicator = payload[0];
+                    let nal_hdr = (fu_indicator & 0xE0) | (nal_type & 0x1F);
+                    Self::write_start_code(out);
+                    out.push(nal_hdr);
+                    *fu_open = true;
+                } else if !*fu_open {
+                    *incomplete = true;
+                    return Ok(());
+                }
+                out.extend_from_slice(&payload[off..]);
+            }
+            AvcNalKind::StapB
+            | AvcNalKind::Mtap16
+            | AvcNalKind::Mtap24
+            | AvcNalKind::Unknown(_) => {
+                return Err(MalformedReason::UnsupportedPacketization);
+            }
+        }
+        Ok(())
+    }
+
+    // With `donl`, the decoding order fields (RFC 7798 section 4.4) are
+    // stripped; the output keeps transmission order.
+    pub(crate) fn append_hevc_payload(
+        payload: &[u8],
+        donl: bool,
+        max_items: usize,
+        out: &mut Vec<u8>,
+        fu_open: &mut bool,
+        incomplete: &mut bool,
+    ) -> Result<(), MalformedReason> {
+        let don_len = if donl { 2 } else { 0 };
+        let (kind, off) =
+            parse_hevc_payload_header(payload).map_err(|_| MalformedReason::InvalidHeader)?;
+        match kind {
+            HevcNalKind::Single { .. } if donl => {
+                if payload.len() < 4 {
+                    *incomplete = true;
+                    return Ok(());
+                }
+                Self::write_start_code(out);
+                out.extend_from_slice(&payload[..2]);
+                out.extend_from_slice(&payload[4..]);
+            }
+            HevcNalKind::Single { .. } | HevcNalKind::Pacsi => {
+                Self::write_start_code(out);
+                out.extend_from_slice(&payload[0..]);
+            }
+            HevcNalKind::Unknown(_) => return Err(MalformedReason::UnsupportedPacketization),
+            HevcNalKind::Ap => {
+                // AP: after 2-byte header, sequence of 16-bit length + NALU,
+                // each preceded by DONL (first) or DOND (rest) when enabled
+                let mut i = 2usize; // skip AP header (nal header with type=48)
+                let mut don_field = don_len;
+                let mut items = 0;
+                while i < payload.len() {
+                    items += 1;
+                    if items > max_items {
+                        return Err(MalformedReason::TooManyItems);
+                    }
+                    i += don_field;
+                    don_field = don_len / 2;
+                    let size = match payload.get(i..i + 2) {
+                        Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]) as usize,
+                        _ => return Err(MalformedReason::TruncatedAggregate),
+                    };
+                    i += 2;
+                    let nal = payload
+                        .get(i..i + size)
+                        .ok_or(MalformedReason::TruncatedAggregate)?;
+                    Self::write_start_code(out);
+                    out.extend_from_slice(nal);
+                    i += size;
+                }
+            }
+            HevcNalKind::Fu {
+                start,
+                end: _,
+                nal_type,
+            } => {
+                if start {
+                    // Reconstruct 2-byte NAL header by replacing type bits
+                    let b0 = payload[0];
+                    let b1 = payload[1];
+                    let new_b0 = (b0 & !0x7E) | ((nal_type << 1) & 0x7E);
+                    Self::write_start_code(out);
+                    out.push(new_b0);
+                    out.push(b1);
+                    *fu_open = true;
+                    // Only the first fragment carries DONL.
+                    let data = payload.get(off + don_len..).unwrap_or_default();
+                    out.extend_from_slice(data);
+                } else if !*fu_open {
+                    *incomplete = true;
+                } else {
+                    out.extend_from_slice(&payload[off..]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn append_vp9_payload(
+        payload: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), MalformedReason> {
+        let (_desc, off) =
+            Vp9PayloadDesc::parse(payload).map_err(|_| MalformedReason::InvalidHeader)?;
+        out.extend_from_slice(&payload[off..]);
+        Ok(())
+    }
+
+    // Writes complete OBUs with size fields; a fragment continued by the
+    // next packet (Y) is kept in `pending` until its last part arrives.
+    pub(crate) fn append_av1_payload(
+        payload: &[u8],
+        max_items: usize,
+        out: &mut Vec<u8>,
+        pending: &mut Vec<u8>,
+        incomplete: &mut bool,
+    ) -> Result<(), MalformedReason> {
+        let (hdr, elements) = parse_av1_obu_elements(payload).map_err(|_| {
+            match parse_av1_payload_header(payload) {
+                Ok(_) => MalformedReason::TruncatedAggregate,
+                Err(_) => MalformedReason::InvalidHeader,
+            }
+        })?;
+        let count = elements.len();
+        if count > max_items {
+            return Err(MalformedReason::TooManyItems);
+        }
+        for (i, element) in elements.into_iter().enumerate() {
+            if i == 0 && hdr.z_bit {
+                if pending.is_empty() {
+                    // Continuation without its first fragment.
+                    *incomplete = true;
+                    continue;
+                }
+            } else if !pending.is_empty() {
+                *incomplete = true;
+                pending.clear();
+            }
+            pending.extend_from_slice(element);
+            if i + 1 == count && hdr.y_bit {
+                break;
+            }
+            write_obu_with_size(pending, out);
+            pending.clear();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+// FU headers spell out their cleared S/E bits.
+#[allow(clippy::identity_op)]
+mod tests {
+    use super::*;
+    use crate::rtp::RtpPacket;
+    use crate::test_utils::{rtp_packet, RtpPacketBuilder};
+
+    #[test]
+    fn reassemble_h264_fu_annexb() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        // FU-A S=1, type=5 (IDR), indicator with NRI=0x60
+        let p1 = rtp_packet(
+            &[0x7C /*28 + NRI=0x60*/, 0x80 | 0x05, 0xAA, 0xBB],
+            false,
+            100,
+        );
+        let p2 = rtp_packet(&[0x7C, 0x00 | 0x05, 0xCC], true, 101);
+        let pkt1 = RtpPacket::parse(&p1).unwrap();
+        let pkt2 = RtpPacket::parse(&p2).unwrap();
+        assert!(r.push_packet(&pkt1).is_none());
+        let out = r.push_packet(&pkt2).expect("frame");
+        // Expect start code + [reconstructed header] + payload fragments
+        assert!(out.starts_with(&[0, 0, 0, 1]));
+        // reconstructed nal header should be 0xE0 (F/NRI) from indicator + 0x05 type -> 0x65 typical
+        assert_eq!(out[4], 0x65);
+        assert_eq!(&out[5..], &[0xAA, 0xBB, 0xCC]);
+    }
+
+    #[test]
+    fn push_result_reports_what_happened() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .reorder(ReorderConfig {
+                max_pending_frames: 1,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let push = |r: &mut FrameReassembler, payload: &[u8], marker: bool, seq: u16, ts: u32| {
+            let bytes = RtpPacketBuilder::new()
+                .marker(marker)
+                .sequence_number(seq)
+                .timestamp(ts)
+                .payload(payload)
+                .build();
+            r.push_packet_result(&RtpPacket::parse(&bytes).unwrap())
+        };
+        assert_eq!(
+            push(&mut r, &[0x7C, 0x85, 0xAA], false, 1, 0),
+            PushResult::Buffered
+        );
+        assert_eq!(
+            push(&mut r, &[0x7C, 0x85, 0xAA], false, 1, 0),
+            PushResult::Duplicate
+        );
+        assert_eq!(
+            push(&mut r, &[0x7C, 0x45, 0xCC], true, 3, 0),
+            PushResult::FrameDropped(DropReason::SequenceGap)
+        );
+        // The loss is reported on the next packet with nothing else to say.
+        assert_eq!(
+            push(&mut r, &[0x7C, 0x01, 0xAA], false, 4, 3000),
+            PushResult::NeedsKeyframe
+        );
+        assert_eq!(
+            push(&mut r, &[0x7C, 0x01, 0xBB], false, 6, 6000),
+            PushResult::EvictedFrame(3000)
+        );
+        assert!(r.needs_keyframe());
+        assert_eq!(
+            push(&mut r, &[0x65, 0x01], true, 7, 9000),
+            PushResult::FrameCompleted(alloc::vec![0, 0, 0, 1, 0x65, 0x01])
+        );
+        assert!(!r.needs_keyframe());
+    }
+
+    #[test]
+    fn suppresses_frames_after_a_loss_burst_until_a_keyframe() {
+        use crate::test_utils::{
+            avc,
+            fixtures::{AVC_IDR, AVC_NON_IDR},
+        };
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .resync(Resync::SuppressAfter(2))
+            .event_log(16)
+            .build()
+            .unwrap();
+        let mut seq = 0;
+        let mut push =
+            |r: &mut FrameReassembler, payload: &[u8], marker: bool, ts: u32, lost: u16| {
+                seq += 1 + lost;
+                let bytes = RtpPacketBuilder::new()
+                    .marker(marker)
+                    .sequence_number(seq)
+                    .timestamp(ts)
+                    .payload(payload)
+                    .build();
+                r.push_packet_result(&RtpPacket::parse(&bytes).unwrap())
+            };
+        assert!(matches!(
+            push(&mut r, &avc::single(AVC_IDR), true, 0, 0),
+            PushResult::FrameCompleted(_)
+        ));
+        // Two frames lose their middle fragment.
+        let fragments = avc::fu_a(AVC_NON_IDR, 4);
+        for ts in [3000, 6000] {
+            push(&mut r, &fragments[0], false, ts, 0);
+            let end = push(&mut r, fragments.last().unwrap(), true, ts, 1);
+            assert!(matches!(end, PushResult::FrameDropped(_)));
+        }
+        assert_eq!(
+            push(&mut r, &avc::single(AVC_NON_IDR), true, 9000, 0),
+            PushResult::FrameDropped(DropReason::AwaitingKeyframe)
+        );
+        assert!(r.resyncing());
+        assert!(matches!(
+            push(&mut r, &avc::single(AVC_IDR), true, 12000, 0),
+            PushResult::FrameCompleted(_)
+        ));
+        assert!(!r.resyncing());
+        let resync: Vec<_> = r
+            .recent_events()
+            .filter(|e| {
+                matches!(
+                    e.kind,
+                    EventKind::ResyncStarted { .. } | EventKind::Resynced { .. }
+                )
+            })
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            resync,
+            [
+                EventKind::ResyncStarted { lost: 2 },
+                EventKind::Resynced { timestamp: 12000 }
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_avc_frame_missing_its_first_slice() {
+        use crate::test_utils::{
+            avc,
+            fixtures::{AVC_IDR, AVC_PPS, AVC_SPS},
+            rbsp,
+        };
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let mut push = |payload: &[u8], seq: u16, ts: u32| {
+            let bytes = RtpPacketBuilder::new()
+                .marker(true)
+                .sequence_number(seq)
+                .timestamp(ts)
+                .payload(payload)
+                .build();
+            r.push_packet_result(&RtpPacket::parse(&bytes).unwrap())
+        };
+        let first = push(&avc::stap_a(&[AVC_SPS, AVC_PPS, AVC_IDR]), 1, 0);
+        assert!(matches!(first, PushResult::FrameCompleted(_)));
+        // The packet with the slice at macroblock 0 is lost; the frame that
+        // is left starts at macroblock 40.
+        let slice = [&[0x41][..], &rbsp("00000101001 00110 1 0011")].concat();
+        assert_eq!(
+            push(&slice, 3, 3000),
+            PushResult::FrameDropped(DropReason::MissingSlice)
+        );
+        assert!(r.needs_keyframe());
+        let summary = r.last_slice_summary().unwrap();
+        assert_eq!((summary.slice_count, summary.complete), (1, false));
+    }
+
+    #[test]
+    fn reassemble_h264_stap_a() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        // STAP-A: indicator 24, then len1=2, nalu1(0x61,0x01), len2=3, nalu2(0x65,0x02,0x03)
+        let payload = [0x18, 0x00, 0x02, 0x61, 0x01, 0x00, 0x03, 0x65, 0x02, 0x03];
+        let p = rtp_packet(&payload, true, 200);
+        let pkt = RtpPacket::parse(&p).unwrap();
+        let out = r.push_packet(&pkt).expect("frame");
+        // Two start codes
+        let sc = [0, 0, 0, 1];
+        assert!(out.starts_with(&sc));
+        let mut idx = 4;
+        assert_eq!(&out[idx..idx + 2], &[0x61, 0x01]);
+        idx += 2;
+        assert_eq!(&out[idx..idx + 4], &sc);
+        idx += 4;
+        assert_eq!(&out[idx..idx + 3], &[0x65, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn reassemble_h265_fu_annexb() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Hevc)
+            .build()
+            .unwrap();
+        // HEVC FU: header bytes with type=49, then FU header S=1 type=19 (IDR_W_RADL typically)
+        let b0 = (49u8 << 1) & 0x7E;
+        let b1 = 0x01; // simple header
+        let fu_s = 0x80 | 19u8; // S=1, type=19
+        let fu_m = 0x00 | 19u8; // middle
+        let p1 = rtp_packet(&[b0, b1, fu_s, 0xDE], false, 300);
+        let p2 = rtp_packet(&[b0, b1, fu_m, 0xAD, 0xBE], true, 301);
+        let pkt1 = RtpPacket::parse(&p1).unwrap();
+        let pkt2 = RtpPacket::parse(&p2).unwrap();
+        assert!(r.push_packet(&pkt1).is_none());
+        let out = r.push_packet(&pkt2).expect("frame");
+        assert!(out.starts_with(&[0, 0, 0, 1]));
+        // reconstructed header first byte should have type=19
+        let new_b0 = out[4];
+        assert_eq!((new_b0 & 0x7E) >> 1, 19);
+        assert_eq!(&out[6..], &[0xDE, 0xAD, 0xBE]);
+    }
+
+    #[test]
+    fn reassemble_vp9_concat() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Vp9)
+            .build()
+            .unwrap();
+        // VP9: two fragments: first with B=1 I=1 and PictureID 7-bit=1, second with E=1
+        let p1 = rtp_packet(&[0x80 | 0x08, 0x01, 0xAA], false, 400); // desc + payload 0xAA
+        let p2 = rtp_packet(&[0x04, 0xBB, 0xCC], true, 401); // E=1
+        let pkt1 = RtpPacket::parse(&p1).unwrap();
+        let pkt2 = RtpPacket::parse(&p2).unwrap();
+        assert!(r.push_packet(&pkt1).is_none());
+        let out = r.push_packet(&pkt2).expect("frame");
+        assert_eq!(&out, &[0xAA, 0xBB, 0xCC]);
+    }
+
+    #[test]
+    fn quarantines_malformed_av1_payloads() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Av1)
+            .quarantine(1)
+            .event_log(4)
+            .build()
+            .unwrap();
+        // W=0 with unterminated element lengths, so neither OBU is copied.
+        let p1 = rtp_packet(&[0x04, 0xAA], false, 500);
+        let p2 = rtp_packet(&[0x04, 0xBB, 0xCC], true, 501);
+        r.push_packet(&RtpPacket::parse(&p1).unwrap());
+        assert_eq!(
+            r.push_packet_result(&RtpPacket::parse(&p2).unwrap()),
+            PushResult::FrameDropped(DropReason::MalformedPayload)
+        );
+        assert_eq!(r.malformed_payloads(), 2);
+        let kept: Vec<_> = r.quarantine().cloned().collect();
+        assert_eq!(
+            kept,
+            [QuarantinedPayload {
+                sequence_number: 501,
+                timestamp: 2,
+                reason: MalformedReason::TruncatedAggregate,
+                payload: vec![0x04, 0xBB, 0xCC],
+            }]
+        );
+        assert!(r.recent_events().any(|e| e.kind
+            == EventKind::MalformedPayload {
+                seq: 500,
+                reason: MalformedReason::TruncatedAggregate
+            }));
+    }
+
+    #[test]
+    fn quarantines_truncated_and_unsupported_avc_payloads() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .quarantine(4)
+            .build()
+            .unwrap();
+        // A STAP-A whose second NAL unit is cut short, then a STAP-B.
+        let stap_a = rtp_packet(&[0x78, 0x00, 0x01, 0x09, 0x00, 0x05, 0x65], false, 10);
+        let stap_b = rtp_packet(&[0x79, 0x00, 0x01], true, 11);
+        r.push_packet(&RtpPacket::parse(&stap_a).unwrap());
+        assert_eq!(
+            r.push_packet_result(&RtpPacket::parse(&stap_b).unwrap()),
+            PushResult::FrameDropped(DropReason::MalformedPayload)
+        );
+        let reasons: Vec<_> = r.quarantine().map(|q| q.reason).collect();
+        assert_eq!(
+            reasons,
+            [
+                MalformedReason::TruncatedAggregate,
+                MalformedReason::UnsupportedPacketization
+            ]
+        );
+    }
+
+    #[test]
+    fn reassemble_av1_obus_with_size_fields() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Av1)
+            .build()
+            .unwrap();
+        // W=2: sequence header OBU, then the first part of a frame OBU (Y=1)
+        let p1 = rtp_packet(&[0x60, 0x02, 0x08, 0x01, 0x30, 0xAA], false, 500);
+        // Z=1, W=1: rest of the frame OBU
+        let p2 = rtp_packet(&[0x90, 0xBB], true, 501);
+        assert!(r.push_packet(&RtpPacket::parse(&p1).unwrap()).is_none());
+        let out = r
+            .push_packet(&RtpPacket::parse(&p2).unwrap())
+            .expect("frame");
+        assert_eq!(&out, &[0x0A, 0x01, 0x01, 0x32, 0x02, 0xAA, 0xBB]);
+
+        // A continuation whose first fragment was lost drops the frame.
+        let p3 = rtp_packet(&[0x90, 0xCC], true, 502);
+        let mut pkt3 = RtpPacket::parse(&p3).unwrap();
+        pkt3.header.timestamp = 3;
+        assert!(r.push_packet(&pkt3).is_none());
+    }
+
+    #[test]
+    fn av1_new_sequence_is_a_random_access_point() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Av1)
+            .event_log(8)
+            .build()
+            .unwrap();
+        let packet = |payload: &[u8], seq: u16, timestamp: u32| {
+            RtpPacketBuilder::new()
+                .marker(timestamp != 0)
+                .sequence_number(seq)
+                .timestamp(timestamp)
+                .payload(payload)
+                .build()
+        };
+        // A frame whose middle packet is lost stays pending.
+        for (payload, seq) in [(&[0x50, 0x30, 0xAA][..], 1), (&[0x90, 0xBB], 3)] {
+            let p = packet(payload, seq, 0);
+            assert!(r.push_packet(&RtpPacket::parse(&p).unwrap()).is_none());
+        }
+        assert!(!r.last_frame_new_sequence());
+
+        // N=1, W=1: a frame OBU starting a new coded video sequence.
+        let p = packet(&[0x18, 0x30, 0xCC], 4, 3000);
+        let out = r.push_packet(&RtpPacket::parse(&p).unwrap()).unwrap();
+        assert_eq!(out, [0x32, 0x01, 0xCC]);
+        assert!(r.last_frame_new_sequence());
+        assert!(r.frames.is_empty());
+        assert!(r.recent_events().any(|e| e.kind
+            == EventKind::FrameEvicted {
+                timestamp: 0,
+                cause: EvictionCause::NewSequence
+            }));
+    }
+
+    #[test]
+    fn reorder_out_of_order_h264_fu() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        // Send middle FU first with marker, then start FU
+        let fu_mid = rtp_packet(&[0x7C, 0x00 | 0x05, 0x11], true, 610);
+        let fu_start = rtp_packet(&[0x7C /*28+NRI*/, 0x80 | 0x05, 0x22, 0x33], false, 609);
+        let pkt_mid = RtpPacket::parse(&fu_mid).unwrap();
+        let pkt_start = RtpPacket::parse(&fu_start).unwrap();
+        // First push (marker, but no start yet) should not flush due to reordering
+        assert!(r.push_packet(&pkt_mid).is_none());
+        // Now push start; should flush assembled, ordered by seq
+        let out = r.push_packet(&pkt_start).expect("frame");
+        assert!(out.starts_with(&[0, 0, 0, 1]));
+        assert_eq!(out[4] & 0x1F, 0x05);
+        assert_eq!(&out[5..], &[0x22, 0x33, 0x11]);
+    }
+
+    #[test]
+    fn drop_incomplete_on_gap() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        // Missing middle packet (gap between seq)
+        let fu_start = rtp_packet(&[0x7C /*28+NRI*/, 0x80 | 0x01, 0xAA], false, 700);
+        let fu_end = rtp_packet(&[0x7C, 0x40 | 0x01, 0xBB], true, 702);
+        let pkt_s = RtpPacket::parse(&fu_start).unwrap();
+        let pkt_e = RtpPacket::parse(&fu_end).unwrap();
+        assert!(r.push_packet(&pkt_s).is_none());
+        // End arrives, gap exists -> dropped (None)
+        assert!(r.push_packet(&pkt_e).is_none());
+    }
+
+    #[test]
+    fn reorder_out_of_order_vp9() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Vp9)
+            .build()
+            .unwrap();
+        // E=1 packet arrives first, then B=1 start
+        let end_pkt = rtp_packet(&[0x04, 0xBB], true, 801);
+        let start_pkt = rtp_packet(&[0x80 | 0x08, 0x01, 0xAA], false, 800);
+        let pe = RtpPacket::parse(&end_pkt).unwrap();
+        let ps = RtpPacket::parse(&start_pkt).unwrap();
+        assert!(r.push_packet(&pe).is_none());
+        let out = r.push_packet(&ps).expect("frame");
+        assert_eq!(&out, &[0xAA, 0xBB]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn frame_buffer_reserves_expected_size() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let p1 = rtp_packet(&[0x7C, 0x85, 0xAA, 0xBB], false, 900);
+        let p2 = rtp_packet(&[0x7C, 0x45, 0xCC], true, 901);
+        assert!(r.push_packet(&RtpPacket::parse(&p1).unwrap()).is_none());
+        let out = r.push_packet(&RtpPacket::parse(&p2).unwrap()).unwrap();
+        assert!(out.capacity() <= 4 + 3 + 2 * 4, "sized from packets");
+
+        r.set_reorder_config(ReorderConfig {
+            expected_frame_size: 4096,
+            ..Default::default()
+        })
+        .unwrap();
+        let p3 = rtp_packet(&[0x65, 0x01], true, 902);
+        let mut pkt = RtpPacket::parse(&p3).unwrap();
+        pkt.header.timestamp = 3000;
+        let out = r.push_packet(&pkt).unwrap();
+        assert_eq!(out, [0, 0, 0, 1, 0x65, 0x01]);
+        assert!(out.capacity() >= 4096);
+    }
+
+    #[test]
+    fn packet_store_orders_across_wraparound_and_falls_back_to_map() {
+        let mut store = PacketStore::default();
+        for seq in [1u16, 65535, 0, 2] {
+            store.insert(OwnedPkt {
+                seq,
+                payload: alloc::vec![seq as u8],
+            });
+        }
+        assert!(matches!(store, PacketStore::Window { .. }));
+        assert_eq!(store.len(), 4);
+        let order: Vec<u16> = store.iter().map(|p| p.seq).collect();
+        assert_eq!(order, [65535, 0, 1, 2]);
+        assert_eq!(store.pop_first().map(|p| p.seq), Some(65535));
+        assert_eq!(store.iter().next().map(|p| p.seq), Some(0));
+        store.insert(OwnedPkt {
+            seq: 65535,
+            payload: alloc::vec![0xFF],
+        });
+
+        store.insert(OwnedPkt {
+            seq: 10_000,
+            payload: Vec::new(),
+        });
+        assert!(matches!(store, PacketStore::Map(_)));
+        assert_eq!(store.len(), 5);
+        assert_eq!(store.iter().last().map(|p| p.seq), Some(65535));
+    }
+
+    #[test]
+    fn reassembles_fu_a_across_sequence_wraparound() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let p1 = rtp_packet(&[0x7C, 0x85, 0xAA], false, 65535);
+        let p2 = rtp_packet(&[0x7C, 0x45, 0xBB], true, 0);
+        assert!(r.push_packet(&RtpPacket::parse(&p2).unwrap()).is_none());
+        let out = r.push_packet(&RtpPacket::parse(&p1).unwrap()).unwrap();
+        assert_eq!(out, [0, 0, 0, 1, 0x65, 0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn in_order_frames_bypass_buffering() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let p1 = rtp_packet(&[0x7C, 0x85, 0xAA], false, 10);
+        let p2 = rtp_packet(&[0x7C, 0x05, 0xBB], false, 11);
+        let p3 = rtp_packet(&[0x7C, 0x45, 0xCC], true, 12);
+        assert!(r.push_packet(&RtpPacket::parse(&p1).unwrap()).is_none());
+        assert!(r.push_packet(&RtpPacket::parse(&p2).unwrap()).is_none());
+        assert!(r.frames.is_empty() && r.in_order.is_some());
+        let out = r.push_packet(&RtpPacket::parse(&p3).unwrap()).unwrap();
+        assert_eq!(out, [0, 0, 0, 1, 0x65, 0xAA, 0xBB, 0xCC]);
+        assert!(r.in_order.is_none());
+    }
+
+    #[test]
+    fn reordering_after_in_order_start_falls_back_to_buffering() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let sps = rtp_packet(&[0x67, 0x42], false, 20);
+        let start = rtp_packet(&[0x7C, 0x85, 0xAA], false, 21);
+        let mid = rtp_packet(&[0x7C, 0x05, 0xBB], false, 22);
+        let end = rtp_packet(&[0x7C, 0x45, 0xCC], true, 23);
+        for p in [&sps, &mid, &start] {
+            assert!(r.push_packet(&RtpPacket::parse(p).unwrap()).is_none());
+        }
+        assert!(r.in_order.is_none() && r.frames.len() == 1);
+        let out = r.push_packet(&RtpPacket::parse(&end).unwrap()).unwrap();
+        assert_eq!(
+            out,
+            [0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x65, 0xAA, 0xBB, 0xCC]
+        );
+    }
+
+    #[test]
+    fn push_packet_into_reuses_caller_buffer() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let mut out = Vec::with_capacity(256);
+        let original = out.as_ptr();
+        let mut ptrs = Vec::new();
+        for (i, ts) in [1u32, 2, 3].into_iter().enumerate() {
+            let p = rtp_packet(&[0x65, i as u8], true, i as u16);
+            let mut pkt = RtpPacket::parse(&p).unwrap();
+            pkt.header.timestamp = ts;
+            assert_eq!(r.push_packet_into(&pkt, &mut out), FrameStatus::Complete);
+            assert_eq!(out, [0, 0, 0, 1, 0x65, i as u8]);
+            ptrs.push(out.as_ptr());
+        }
+        // The caller's buffer comes back for the second frame, then the two
+        // allocations alternate.
+        assert_eq!(ptrs[1], original);
+        assert_eq!(ptrs[0], ptrs[2]);
+
+        let fu_start = rtp_packet(&[0x7C, 0x85, 0xAA], false, 10);
+        let fu_end = rtp_packet(&[0x7C, 0x45, 0xBB], true, 12);
+        let status = r.push_packet_into(&RtpPacket::parse(&fu_start).unwrap(), &mut out);
+        assert_eq!(status, FrameStatus::Pending);
+        let status = r.push_packet_into(&RtpPacket::parse(&fu_end).unwrap(), &mut out);
+        assert_eq!(status, FrameStatus::Dropped(DropReason::SequenceGap));
+        assert_eq!(out, [0, 0, 0, 1, 0x65, 2]);
+    }
+
+    #[test]
+    fn evicts_oldest_pending_frame_at_capacity() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .reorder(ReorderConfig {
+                max_pending_frames: 2,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        // Three frames that each only received a non-start FU fragment.
+        for ts in [10u32, 20, 30] {
+            let mut p = rtp_packet(&[0x7C, 0x05, 0x01], true, ts as u16);
+            p[4..8].copy_from_slice(&ts.to_be_bytes());
+            assert!(r.push_packet(&RtpPacket::parse(&p).unwrap()).is_none());
+        }
+        assert_eq!(r.frames.len(), 2);
+        assert!(!r.frames.contains_key(&10));
+        assert!(r.frames.contains_key(&20) && r.frames.contains_key(&30));
+    }
+}
@@ -0,0 +1,125 @@
+use super::FrameCollector;
+use alloc::{collections::VecDeque, vec::Vec};
+use core::ops::RangeInclusive;
+
+// Sequence numbers missing from the current stream, oldest first. They are
+// added as the newest sequence number advances past them, and leave when
+// their packet arrives, when they fall out of the window or when a frame
+// around them is finished.
+#[derive(Debug, Default)]
+pub(super) struct MissingSequences {
+    missing: VecDeque<u16>,
+}
+
+impl MissingSequences {
+    pub(super) fn clear(&mut self) {
+        self.missing.clear();
+    }
+
+    // The newest sequence number advanced from `previous` to `seq`.
+    pub(super) fn advance(&mut self, previous: u16, seq: u16, window: u16) {
+        let lost = seq.wrapping_sub(previous).wrapping_sub(1).min(window);
+        self.missing
+            .extend((1..=lost).rev().map(|back| seq.wrapping_sub(back)));
+        while self
+            .missing
+            .front()
+            .is_some_and(|&s| seq.wrapping_sub(s) > window)
+        {
+            self.missing.pop_front();
+        }
+    }
+
+    pub(super) fn received(&mut self, seq: u16) {
+        if let Some(i) = self.missing.iter().position(|&s| s == seq) {
+            self.missing.remove(i);
+        }
+    }
+
+    // Gives up on the sequence numbers between the first and last packet of
+    // a frame that was completed or dropped.
+    pub(super) fn forget(&mut self, frame: &FrameCollector) {
+        if let Some((first, last)) = frame.span() {
+            let len = last.wrapping_sub(first);
+            self.missing.retain(|&s| s.wrapping_sub(first) > len);
+        }
+    }
+
+    // Runs of consecutive sequence numbers at most `window` behind `newest`;
+    // a run crossing wraparound is split in two.
+    pub(super) fn ranges(&self, newest: u16, window: u16) -> Vec<RangeInclusive<u16>> {
+        let mut ranges: Vec<RangeInclusive<u16>> = Vec::new();
+        let recent = self
+            .missing
+            .iter()
+            .copied()
+            .filter(|&s| newest.wrapping_sub(s) <= window);
+        for seq in recent {
+            match ranges.last_mut() {
+                Some(run) if run.end().checked_add(1) == Some(seq) => *run = *run.start()..=seq,
+                _ => ranges.push(seq..=seq),
+            }
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        codecs::Codec,
+        reassemble::{FrameReassembler, ReorderConfig},
+        rtp::RtpPacket,
+        test_utils::{avc, StreamBuilder},
+    };
+    use alloc::vec::Vec;
+
+    #[test]
+    fn reports_missing_sequences_until_recovered_given_up_or_aged_out() {
+        // Three packets per frame: 65530..=65532, 65533..=65535, 0..=2, ...
+        let mut builder = StreamBuilder::new(9).start_sequence(65530);
+        for i in 0..8u8 {
+            let nal = if i == 0 { 0x65 } else { 0x41 };
+            builder = builder.frame(&avc::fu_a(&[nal, 0x88, i, 0xAB, 0xCD, i], 2));
+        }
+        let packets = builder.build();
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .reorder(ReorderConfig {
+                nack_window: 10,
+                ..ReorderConfig::default()
+            })
+            .build()
+            .unwrap();
+        let push = |r: &mut FrameReassembler, i: usize| {
+            r.push_packet(&RtpPacket::parse(&packets[i]).unwrap());
+        };
+        // Frame 1 arrives without its last two packets, frame 2 not at all.
+        for i in [0, 1, 2, 3, 9] {
+            push(&mut r, i);
+        }
+        assert_eq!(r.missing_sequences(9), [65534..=65535, 0..=2]);
+        assert!(r.missing_sequences(10).is_empty());
+
+        // A retransmission brings 0. With its marker, frame 1 is dropped
+        // for the gap, so 65534 is no longer worth asking for.
+        push(&mut r, 6);
+        push(&mut r, 5);
+        assert_eq!(r.missing_sequences(9), [1..=2]);
+
+        // 1 and 2 fall out of the window.
+        for i in 10..packets.len() {
+            push(&mut r, i);
+        }
+        assert!(r.missing_sequences(9).is_empty());
+
+        let mut untracked = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        for i in [0, 1, 2, 9] {
+            untracked.push_packet(&RtpPacket::parse(&packets[i]).unwrap());
+        }
+        assert_eq!(untracked.missing_sequences(9), Vec::new());
+    }
+}
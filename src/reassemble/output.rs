@@ -0,0 +1,186 @@
+use crate::{
+    bitstream::{find_start_code, split_annex_b, start_codes},
+    codecs::{
+        avc::{AVC_NAL_PREFIX, AVC_NAL_SLICE_EXTENSION, AVC_NAL_SUBSET_SPS},
+        Codec,
+    },
+};
+use alloc::vec::Vec;
+
+// Start code and H.264 access unit delimiter; primary_pic_type 7 allows
+// every slice type, so it is valid for any frame.
+const AVC_AUD: [u8; 6] = [0, 0, 0, 1, 0x09, 0xF0];
+// H.265 AUD_NUT (35) with pic_type 2 (I, P and B slices); the second
+// header byte takes the access unit's TemporalId.
+const HEVC_AUD: [u8; 7] = [0, 0, 0, 1, 0x46, 0x01, 0x50];
+
+/// Prepends an access unit delimiter to an Annex-B frame that does not
+/// already start with one.
+pub(super) fn prepend_aud(codec: Codec, frame: &mut Vec<u8>) {
+    let first = find_start_code(frame, 0).and_then(|sc| frame.get(sc.payload_offset()));
+    match codec {
+        Codec::Avc if first.map(|h| h & 0x1F) != Some(9) => {
+            frame.splice(0..0, AVC_AUD);
+        }
+        Codec::Hevc if first.map(|h| (h >> 1) & 0x3F) != Some(35) => {
+            let mut aud = HEVC_AUD;
+            // nuh_temporal_id_plus1 of the first VCL NAL unit.
+            if let Some(nal) = split_annex_b(frame)
+                .into_iter()
+                .find(|nal| nal.len() >= 2 && (nal[0] >> 1) & 0x3F < 32)
+            {
+                aud[5] = nal[1] & 0x07;
+            }
+            frame.splice(0..0, aud);
+        }
+        _ => {}
+    }
+}
+
+/// Removes the MVC prefix, subset SPS and slice extension NAL units of an
+/// Annex-B frame, leaving the base view as a plain H.264 stream.
+pub(super) fn strip_non_base_views(frame: &mut Vec<u8>) {
+    let is_mvc = |nal: &[u8]| {
+        nal.first().is_some_and(|h| {
+            matches!(
+                h & 0x1F,
+                AVC_NAL_PREFIX | AVC_NAL_SUBSET_SPS | AVC_NAL_SLICE_EXTENSION
+            )
+        })
+    };
+    let nals = split_annex_b(frame);
+    if !nals.iter().any(|nal| is_mvc(nal)) {
+        return;
+    }
+    let mut base = Vec::with_capacity(frame.len());
+    for nal in nals.into_iter().filter(|nal| !is_mvc(nal)) {
+        base.extend_from_slice(&[0, 0, 0, 1]);
+        base.extend_from_slice(nal);
+    }
+    *frame = base;
+}
+
+/// Turns the 4-byte start codes of an Annex-B frame into 3-byte ones,
+/// except the first unless `first` is set.
+pub(super) fn shorten_start_codes(frame: &mut Vec<u8>, first: bool) {
+    let leading_zeros: Vec<usize> = start_codes(frame)
+        .filter(|sc| sc.len == 4 && (first || sc.offset > 0))
+        .map(|sc| sc.offset)
+        .collect();
+    let mut remove = leading_zeros.into_iter().peekable();
+    let mut at = 0;
+    frame.retain(|_| {
+        let keep = remove.next_if_eq(&at).is_none();
+        at += 1;
+        keep
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        reassemble::{ConfigError, FrameReassembler, OutputFormat, StartCodes},
+        rtp::RtpPacket,
+        test_utils::{
+            avc,
+            fixtures::{annex_b, AVC_IDR, AVC_NON_IDR, AVC_PPS, AVC_SPS, HEVC_PPS},
+            StreamBuilder,
+        },
+    };
+
+    #[test]
+    fn avc_frames_start_with_a_delimiter() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .access_unit_delimiters(true)
+            .output(OutputFormat::LengthPrefixed)
+            .build()
+            .unwrap();
+        let stream = StreamBuilder::new(1)
+            .frame(&[avc::single(AVC_NON_IDR)])
+            .frame(&[avc::stap_a(&[&[0x09, 0xF0], AVC_NON_IDR])])
+            .build();
+        let frames: Vec<_> = stream
+            .iter()
+            .filter_map(|p| r.push_packet(&RtpPacket::parse(p).unwrap()))
+            .collect();
+        let expected = [&[0, 0, 0, 2, 0x09, 0xF0][..], &[0, 0, 0, 9], AVC_NON_IDR].concat();
+        assert_eq!(frames, [expected.clone(), expected]);
+
+        let mut frame = annex_b(&[AVC_NON_IDR]);
+        prepend_aud(Codec::Vp9, &mut frame);
+        assert_eq!(frame, annex_b(&[AVC_NON_IDR]));
+    }
+
+    #[test]
+    fn start_codes_follow_the_configured_style() {
+        let stap_a = avc::stap_a(&[AVC_SPS, AVC_PPS, AVC_IDR]);
+        let packet = StreamBuilder::new(1).frame(&[stap_a]).build().remove(0);
+        let frame = |style| {
+            let mut r = FrameReassembler::builder()
+                .codec(Codec::Avc)
+                .start_codes(style)
+                .build()
+                .unwrap();
+            r.push_packet(&RtpPacket::parse(&packet).unwrap()).unwrap()
+        };
+        let short = |nal: &[u8]| [&[0, 0, 1][..], nal].concat();
+        assert_eq!(
+            frame(StartCodes::FourByteFirst),
+            [annex_b(&[AVC_SPS]), short(AVC_PPS), short(AVC_IDR)].concat()
+        );
+        assert_eq!(
+            frame(StartCodes::ThreeByte),
+            [short(AVC_SPS), short(AVC_PPS), short(AVC_IDR)].concat()
+        );
+        let err = FrameReassembler::builder()
+            .codec(Codec::Vp9)
+            .start_codes(StartCodes::ThreeByte)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, ConfigError::UnsupportedStartCodes(Codec::Vp9));
+    }
+
+    #[test]
+    fn base_view_only_strips_mvc_nal_units() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .avc_base_view_only(true)
+            .build()
+            .unwrap();
+        let subset_sps = [0x6F, 0x76, 0x00, 0x1E];
+        let prefix = [0x6E, 0x00, 0x00, 0x07];
+        let extension = [0x74, 0x00, 0x00, 0x47, 0x88];
+        let stream = StreamBuilder::new(1)
+            .frame(&[
+                avc::stap_a(&[&subset_sps, &prefix, AVC_IDR]),
+                avc::single(&extension),
+            ])
+            .build();
+        let frame = stream
+            .iter()
+            .find_map(|p| r.push_packet(&RtpPacket::parse(p).unwrap()))
+            .unwrap();
+        assert_eq!(frame, annex_b(&[AVC_IDR]));
+        assert_eq!(r.last_mvc_views(), [0, 1]);
+
+        assert!(FrameReassembler::builder()
+            .codec(Codec::Hevc)
+            .avc_base_view_only(true)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn hevc_delimiter_takes_the_temporal_id() {
+        // TRAIL_R in temporal layer 2 behind a PPS.
+        let trail = [0x02, 0x03, 0xAF];
+        let mut frame = annex_b(&[HEVC_PPS, &trail]);
+        prepend_aud(Codec::Hevc, &mut frame);
+        assert_eq!(frame, annex_b(&[&[0x46, 0x03, 0x50], HEVC_PPS, &trail]));
+        let before = frame.clone();
+        prepend_aud(Codec::Hevc, &mut frame);
+        assert_eq!(frame, before);
+    }
+}
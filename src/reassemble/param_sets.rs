@@ -0,0 +1,119 @@
+use crate::{bitstream::start_codes, codecs::Codec};
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NalRole {
+    Vps,
+    Sps,
+    Pps,
+    Keyframe,
+    Delimiter,
+    Other,
+}
+
+pub(crate) fn nal_role(codec: Codec, header: u8) -> NalRole {
+    match codec {
+        Codec::Avc => match header & 0x1F {
+            5 => NalRole::Keyframe,
+            7 => NalRole::Sps,
+            8 => NalRole::Pps,
+            9 => NalRole::Delimiter,
+            _ => NalRole::Other,
+        },
+        Codec::Hevc => match (header >> 1) & 0x3F {
+            16..=21 => NalRole::Keyframe,
+            32 => NalRole::Vps,
+            33 => NalRole::Sps,
+            34 => NalRole::Pps,
+            35 => NalRole::Delimiter,
+            _ => NalRole::Other,
+        },
+        _ => NalRole::Other,
+    }
+}
+
+/// Latest VPS/SPS/PPS seen on a stream, for re-inserting into keyframes.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParameterSetCache {
+    vps: Option<Vec<u8>>,
+    sps: Option<Vec<u8>>,
+    pps: Option<Vec<u8>>,
+}
+
+impl ParameterSetCache {
+    /// Records the parameter sets of an Annex-B `frame` (4-byte start
+    /// codes), and prepends cached ones a keyframe is missing, after any
+    /// access unit delimiter.
+    pub(crate) fn process(&mut self, codec: Codec, frame: &mut Vec<u8>) {
+        let codes: Vec<_> = start_codes(frame).collect();
+        let mut present = [false; 3];
+        let mut keyframe = false;
+        let mut insert_at = None;
+        for (i, code) in codes.iter().enumerate() {
+            let start = code.payload_offset();
+            let end = codes.get(i + 1).map_or(frame.len(), |next| next.offset);
+            let Some(&header) = frame.get(start) else {
+                continue;
+            };
+            let role = nal_role(codec, header);
+            let slot = match role {
+                NalRole::Vps => Some(0),
+                NalRole::Sps => Some(1),
+                NalRole::Pps => Some(2),
+                NalRole::Keyframe => {
+                    keyframe = true;
+                    None
+                }
+                NalRole::Delimiter | NalRole::Other => None,
+            };
+            if role != NalRole::Delimiter && insert_at.is_none() {
+                insert_at = Some(code.offset);
+            }
+            if let Some(slot) = slot {
+                present[slot] = true;
+                let nal = &frame[start..end];
+                let cached = [&mut self.vps, &mut self.sps, &mut self.pps];
+                if cached[slot].as_deref() != Some(nal) {
+                    *cached[slot] = Some(nal.to_vec());
+                }
+            }
+        }
+        if !keyframe {
+            return;
+        }
+        let mut prefix = Vec::new();
+        for (slot, cached) in [&self.vps, &self.sps, &self.pps].into_iter().enumerate() {
+            if let (false, Some(nal)) = (present[slot], cached) {
+                prefix.extend_from_slice(&[0, 0, 0, 1]);
+                prefix.extend_from_slice(nal);
+            }
+        }
+        if !prefix.is_empty() {
+            let at = insert_at.unwrap_or(0);
+            frame.splice(at..at, prefix);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::fixtures::{annex_b, AVC_IDR, AVC_NON_IDR, AVC_PPS, AVC_SPS};
+
+    #[test]
+    fn injects_cached_parameter_sets_into_bare_keyframes() {
+        let mut cache = ParameterSetCache::default();
+        let mut first = annex_b(&[AVC_SPS, AVC_PPS, AVC_IDR]);
+        cache.process(Codec::Avc, &mut first);
+        assert_eq!(first, annex_b(&[AVC_SPS, AVC_PPS, AVC_IDR]));
+
+        let mut inter = annex_b(&[AVC_NON_IDR]);
+        cache.process(Codec::Avc, &mut inter);
+        assert_eq!(inter, annex_b(&[AVC_NON_IDR]));
+
+        let aud: &[u8] = &[0x09, 0xF0];
+        let mut bare = annex_b(&[aud, AVC_PPS, AVC_IDR]);
+        cache.process(Codec::Avc, &mut bare);
+        assert_eq!(bare, annex_b(&[aud, AVC_SPS, AVC_PPS, AVC_IDR]));
+    }
+}
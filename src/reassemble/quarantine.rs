@@ -0,0 +1,97 @@
+use alloc::{collections::VecDeque, vec::Vec};
+
+/// Why a payload could not be depacketized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MalformedReason {
+    /// The payload header or descriptor does not parse.
+    InvalidHeader,
+    /// A packetization the reassembler does not handle, e.g. H.264 STAP-B
+    /// or MTAP, or a reserved NAL unit type.
+    UnsupportedPacketization,
+    /// A length field of an aggregation packet or AV1 OBU element runs past
+    /// the end of the payload.
+    TruncatedAggregate,
+    /// An aggregation packet holds more items than
+    /// [`ReassemblerConfig::max_aggregation_items`](super::ReassemblerConfig::max_aggregation_items).
+    TooManyItems,
+}
+
+impl MalformedReason {
+    /// Short description, also the `reason` label of malformed payload
+    /// metrics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MalformedReason::InvalidHeader => "invalid header",
+            MalformedReason::UnsupportedPacketization => "unsupported packetization",
+            MalformedReason::TruncatedAggregate => "truncated aggregate",
+            MalformedReason::TooManyItems => "too many aggregation items",
+        }
+    }
+}
+
+impl core::fmt::Display for MalformedReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A payload kept by the quarantine; see
+/// [`ReassemblerConfig::quarantine`](super::ReassemblerConfig::quarantine).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuarantinedPayload {
+    pub sequence_number: u16,
+    pub timestamp: u32,
+    pub reason: MalformedReason,
+    pub payload: Vec<u8>,
+}
+
+// Ring of the most recent malformed payloads; keeps none at capacity 0 but
+// still counts them.
+#[derive(Debug, Default)]
+pub(crate) struct Quarantine {
+    payloads: VecDeque<QuarantinedPayload>,
+    capacity: usize,
+    total: u64,
+}
+
+impl Quarantine {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            payloads: VecDeque::new(),
+            capacity,
+            total: 0,
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        sequence_number: u16,
+        timestamp: u32,
+        reason: MalformedReason,
+        payload: &[u8],
+    ) {
+        self.total += 1;
+        if self.capacity == 0 {
+            return;
+        }
+        if self.payloads.len() == self.capacity {
+            self.payloads.pop_front();
+        }
+        self.payloads.push_back(QuarantinedPayload {
+            sequence_number,
+            timestamp,
+            reason,
+            payload: payload.to_vec(),
+        });
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &QuarantinedPayload> {
+        self.payloads.iter()
+    }
+
+    pub(crate) fn total(&self) -> u64 {
+        self.total
+    }
+}
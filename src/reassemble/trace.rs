@@ -0,0 +1,657 @@
+//! Record-and-replay traces of a reassembler.
+//!
+//! With [`ReassemblerConfig::trace`] set, or after
+//! [`FrameReassembler::start_trace`], a reassembler records its
+//! configuration, later calls to `set_codec` and `set_reorder_config`, and
+//! every packet pushed along with what it led to. The reassembler reads no
+//! clock, so feeding the same inputs to a fresh one reproduces every
+//! decision: [`Trace::replay`] does, and reports the first packet whose
+//! outcome differs from the recorded one. A trace captured where frames
+//! were reported corrupt can so be stepped through offline.
+//!
+//! Codec registries, frame decryptors and metrics sinks are not recorded;
+//! set them on [`Trace::reassembler`]'s result before replaying.
+//!
+//! The format is the magic `RTPT` and a version byte, then records of a
+//! tag byte and a body: the configuration, a codec or reorder config
+//! change, or a packet (a LEB128 length, the RTP header without extension
+//! and the payload) and its outcome. Completed frames are recorded as their
+//! length and FNV-1a hash, not their contents.
+
+use super::{ConfigError, Dropped, FrameReassembler, ReassemblerConfig, ReorderConfig};
+use super::{Decimation, Delivery, DropReason, OutputFormat, OverflowPolicy, ParameterSetMode};
+use super::{Resync, SsrcPolicy, StartCodes};
+use crate::{
+    codecs::{
+        av1::{read_leb128, write_leb128},
+        Codec,
+    },
+    rtp::RtpPacket,
+    sframe::SframeMode,
+};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+const MAGIC: &[u8; 4] = b"RTPT";
+const VERSION: u8 = 1;
+
+const CONFIG: u8 = 0;
+const SET_CODEC: u8 = 1;
+const SET_REORDER: u8 = 2;
+const PACKET: u8 = 3;
+
+/// 64-bit FNV-1a, the hash of recorded frames and of golden outputs;
+/// enough to notice an output change, not collision resistant.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceError {
+    /// The data does not start with the trace magic.
+    NotATrace,
+    UnsupportedVersion(u8),
+    /// The first record is not the configuration.
+    MissingConfig,
+    /// The record at this offset is truncated or invalid.
+    InvalidRecord(usize),
+}
+
+impl core::fmt::Display for TraceError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TraceError::NotATrace => f.write_str("not a reassembler trace"),
+            TraceError::UnsupportedVersion(v) => write!(f, "unsupported trace version {}", v),
+            TraceError::MissingConfig => f.write_str("trace does not start with a configuration"),
+            TraceError::InvalidRecord(offset) => {
+                write!(f, "invalid trace record at offset {}", offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TraceError {}
+
+/// What a traced packet led to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceOutcome {
+    /// No frame completed.
+    Pending,
+    /// A frame of `len` bytes with FNV-1a hash `hash` completed.
+    Completed {
+        len: usize,
+        hash: u64,
+    },
+    Dropped(DropReason),
+}
+
+impl TraceOutcome {
+    fn of(result: &Option<Result<Vec<u8>, Dropped>>) -> Self {
+        match result {
+            None => TraceOutcome::Pending,
+            Some(Ok(frame)) => TraceOutcome::Completed {
+                len: frame.len(),
+                hash: fnv1a(frame),
+            },
+            Some(Err(dropped)) => TraceOutcome::Dropped(dropped.reason),
+        }
+    }
+}
+
+/// A traced input after the initial configuration.
+#[derive(Debug, Clone)]
+pub enum TraceRecord {
+    SetCodec(Codec),
+    SetReorder(ReorderConfig),
+    /// A serialized RTP packet and its outcome.
+    Packet {
+        data: Vec<u8>,
+        outcome: TraceOutcome,
+    },
+}
+
+/// A parsed trace; see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Trace {
+    config: ReassemblerConfig,
+    records: Vec<TraceRecord>,
+}
+
+/// Result of [`Trace::replay`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceReplay {
+    pub packets: usize,
+    /// Frames returned while replaying; none under NAL unit delivery or
+    /// resilient output.
+    pub frames: Vec<Vec<u8>>,
+    /// The first packet whose outcome differed from the recorded one.
+    pub divergence: Option<Divergence>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the packet among the traced packets.
+    pub packet: usize,
+    pub recorded: TraceOutcome,
+    pub replayed: TraceOutcome,
+}
+
+impl Trace {
+    pub fn parse(data: &[u8]) -> Result<Self, TraceError> {
+        let body = data.strip_prefix(MAGIC).ok_or(TraceError::NotATrace)?;
+        let (&version, _) = body.split_first().ok_or(TraceError::NotATrace)?;
+        if version != VERSION {
+            return Err(TraceError::UnsupportedVersion(version));
+        }
+        let mut reader = Reader {
+            data,
+            pos: MAGIC.len() + 1,
+        };
+        let start = reader.pos;
+        if reader.u8() != Some(CONFIG) {
+            return Err(TraceError::MissingConfig);
+        }
+        let config = reader.config().ok_or(TraceError::InvalidRecord(start))?;
+        let mut records = Vec::new();
+        while reader.pos < data.len() {
+            let start = reader.pos;
+            let record = reader.record().ok_or(TraceError::InvalidRecord(start))?;
+            records.push(record);
+        }
+        Ok(Self { config, records })
+    }
+
+    /// The configuration the trace started with.
+    pub fn config(&self) -> &ReassemblerConfig {
+        &self.config
+    }
+
+    pub fn records(&self) -> &[TraceRecord] {
+        &self.records
+    }
+
+    /// A reassembler as configured when the trace started.
+    pub fn reassembler(&self) -> Result<FrameReassembler, ConfigError> {
+        FrameReassembler::from_config(self.config)
+    }
+
+    /// Feeds the recorded inputs to `reassembler`, usually from
+    /// [`reassembler`](Self::reassembler), comparing each packet's outcome
+    /// to the recorded one. The reassembler records a trace of its own
+    /// while replaying.
+    pub fn replay(&self, reassembler: &mut FrameReassembler) -> TraceReplay {
+        reassembler.start_trace();
+        let mut replay = TraceReplay::default();
+        for record in &self.records {
+            match record {
+                TraceRecord::SetCodec(codec) => reassembler.change_codec(*codec),
+                TraceRecord::SetReorder(cfg) => {
+                    // Only accepted changes are recorded.
+                    let _ = reassembler.change_reorder_config(*cfg);
+                }
+                TraceRecord::Packet { data, outcome } => {
+                    let Ok(pkt) = RtpPacket::parse(data) else {
+                        continue;
+                    };
+                    replay.frames.extend(reassembler.push_packet(&pkt));
+                    let replayed = reassembler
+                        .trace
+                        .as_ref()
+                        .and_then(|t| t.last)
+                        .unwrap_or(TraceOutcome::Pending);
+                    if replayed != *outcome && replay.divergence.is_none() {
+                        replay.divergence = Some(Divergence {
+                            packet: replay.packets,
+                            recorded: *outcome,
+                            replayed,
+                        });
+                    }
+                    replay.packets += 1;
+                }
+            }
+        }
+        replay
+    }
+}
+
+// The trace being recorded by a reassembler.
+#[derive(Debug)]
+pub(super) struct TraceRecorder {
+    buf: Vec<u8>,
+    // Outcome of the last packet recorded.
+    last: Option<TraceOutcome>,
+}
+
+impl TraceRecorder {
+    pub(super) fn new(config: &ReassemblerConfig) -> Self {
+        let mut buf = Vec::from(&MAGIC[..]);
+        buf.extend([VERSION, CONFIG]);
+        write_config(&mut buf, config);
+        Self { buf, last: None }
+    }
+
+    pub(super) fn set_codec(&mut self, codec: Codec) {
+        self.buf.push(SET_CODEC);
+        write_codec(&mut self.buf, codec);
+    }
+
+    pub(super) fn set_reorder(&mut self, cfg: &ReorderConfig) {
+        self.buf.push(SET_REORDER);
+        write_reorder(&mut self.buf, cfg);
+    }
+
+    pub(super) fn packet(
+        &mut self,
+        pkt: &RtpPacket<'_>,
+        result: &Option<Result<Vec<u8>, Dropped>>,
+    ) {
+        let mut data = Vec::with_capacity(12 + 4 * pkt.header.csrcs.len() + pkt.payload.len());
+        pkt.header.write(&mut data);
+        data.extend_from_slice(pkt.payload);
+        self.buf.push(PACKET);
+        write_leb128(data.len() as u64, &mut self.buf);
+        self.buf.extend(data);
+        let outcome = TraceOutcome::of(result);
+        match outcome {
+            TraceOutcome::Pending => self.buf.push(0),
+            TraceOutcome::Completed { len, hash } => {
+                self.buf.push(1);
+                write_leb128(len as u64, &mut self.buf);
+                self.buf.extend(hash.to_be_bytes());
+            }
+            TraceOutcome::Dropped(reason) => self.buf.extend([2, reason as u8]),
+        }
+        self.last = Some(outcome);
+    }
+
+    pub(super) fn take(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.buf)
+    }
+}
+
+// Destructured so that a new field cannot be left out of traces.
+fn write_config(out: &mut Vec<u8>, config: &ReassemblerConfig) {
+    let ReassemblerConfig {
+        codec,
+        output,
+        start_codes,
+        reorder,
+        latency,
+        max_buffered_bytes,
+        parameter_sets,
+        hevc_donl,
+        access_unit_delimiters,
+        avc_base_view_only,
+        ssrc_policy,
+        event_log,
+        quarantine,
+        decimation,
+        validate_output,
+        delivery,
+        resync,
+        max_aggregation_items,
+        comfort_noise_payload_type,
+        sframe,
+        resilient,
+        trace,
+    } = *config;
+    write_option(out, codec, write_codec);
+    out.extend([output as u8, start_codes as u8]);
+    write_reorder(out, &reorder);
+    write_option(out, latency, |out, latency| {
+        write_int(out, latency.as_secs());
+        write_int(out, latency.subsec_nanos().into());
+    });
+    write_option(out, max_buffered_bytes, |out, n| write_int(out, n as u64));
+    out.extend([
+        parameter_sets as u8,
+        hevc_donl as u8,
+        access_unit_delimiters as u8,
+        avc_base_view_only as u8,
+        ssrc_policy as u8,
+    ]);
+    write_int(out, event_log as u64);
+    write_int(out, quarantine as u64);
+    let (kind, n) = match decimation {
+        Decimation::Off => (0, 0),
+        Decimation::EveryNth(n) => (1, n),
+        Decimation::MaxFps(n) => (2, n),
+    };
+    out.push(kind);
+    write_int(out, n.into());
+    out.extend([validate_output as u8, delivery as u8]);
+    let (kind, n) = match resync {
+        Resync::Off => (0, 0),
+        Resync::SuppressAfter(n) => (1, n),
+        Resync::FlagAfter(n) => (2, n),
+    };
+    out.push(kind);
+    write_int(out, n.into());
+    write_option(out, max_aggregation_items, |out, n| {
+        write_int(out, n as u64)
+    });
+    write_option(out, comfort_noise_payload_type, |out, pt| out.push(pt));
+    out.extend([sframe as u8, resilient as u8, trace as u8]);
+}
+
+fn write_reorder(out: &mut Vec<u8>, cfg: &ReorderConfig) {
+    let ReorderConfig {
+        enable_reordering,
+        drop_incomplete_frames,
+        max_buffered_packets_per_frame,
+        max_pending_frames,
+        expected_frame_size,
+        max_frame_bytes,
+        max_reorder_window,
+        overflow,
+        nack_window,
+    } = *cfg;
+    out.extend([enable_reordering as u8, drop_incomplete_frames as u8]);
+    write_int(out, max_buffered_packets_per_frame as u64);
+    write_int(out, max_pending_frames as u64);
+    write_int(out, expected_frame_size as u64);
+    write_option(out, max_frame_bytes, |out, n| write_int(out, n as u64));
+    write_option(out, max_reorder_window, |out, n| write_int(out, n.into()));
+    out.push(overflow as u8);
+    write_int(out, nack_window.into());
+}
+
+fn write_codec(out: &mut Vec<u8>, codec: Codec) {
+    let kind = match codec {
+        Codec::Unknown => 0,
+        Codec::Avc => 1,
+        Codec::Hevc => 2,
+        Codec::Vp9 => 3,
+        Codec::Av1 => 4,
+        Codec::Custom(id) => {
+            out.push(5);
+            return write_int(out, id.into());
+        }
+    };
+    out.push(kind);
+}
+
+fn write_option<T>(out: &mut Vec<u8>, value: Option<T>, write: impl FnOnce(&mut Vec<u8>, T)) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            write(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+// Fixed width: limits are often set to `usize::MAX`, past the LEB128
+// helpers' range.
+fn write_int(out: &mut Vec<u8>, value: u64) {
+    out.extend(value.to_be_bytes());
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Option<&[u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        self.pick(&[false, true])
+    }
+
+    // The variant at the index read, in declaration order.
+    fn pick<T: Copy>(&mut self, values: &[T]) -> Option<T> {
+        values.get(self.u8()? as usize).copied()
+    }
+
+    fn int<T: TryFrom<u64>>(&mut self) -> Option<T> {
+        let bytes = self.bytes(8)?.try_into().ok()?;
+        u64::from_be_bytes(bytes).try_into().ok()
+    }
+
+    fn leb128(&mut self) -> Option<usize> {
+        let (value, len) = read_leb128(self.data.get(self.pos..)?)?;
+        self.pos += len;
+        value.try_into().ok()
+    }
+
+    fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        match self.flag()? {
+            true => read(self).map(Some),
+            false => Some(None),
+        }
+    }
+
+    fn record(&mut self) -> Option<TraceRecord> {
+        match self.u8()? {
+            SET_CODEC => self.codec().map(TraceRecord::SetCodec),
+            SET_REORDER => self.reorder().map(TraceRecord::SetReorder),
+            PACKET => {
+                let len = self.leb128()?;
+                let data = self.bytes(len)?.to_vec();
+                let outcome = match self.u8()? {
+                    0 => TraceOutcome::Pending,
+                    1 => TraceOutcome::Completed {
+                        len: self.leb128()?,
+                        hash: u64::from_be_bytes(self.bytes(8)?.try_into().ok()?),
+                    },
+                    2 => TraceOutcome::Dropped(self.pick(&[
+                        DropReason::SequenceGap,
+                        DropReason::MissingFragment,
+                        DropReason::FrameTooLarge,
+                        DropReason::MissingSlice,
+                        DropReason::MalformedPayload,
+                        DropReason::AwaitingKeyframe,
+                        DropReason::DecryptionFailed,
+                    ])?),
+                    _ => return None,
+                };
+                Some(TraceRecord::Packet { data, outcome })
+            }
+            _ => None,
+        }
+    }
+
+    fn config(&mut self) -> Option<ReassemblerConfig> {
+        Some(ReassemblerConfig {
+            codec: self.option(Self::codec)?,
+            output: self.pick(&[OutputFormat::AnnexB, OutputFormat::LengthPrefixed])?,
+            start_codes: self.pick(&[
+                StartCodes::FourByte,
+                StartCodes::FourByteFirst,
+                StartCodes::ThreeByte,
+            ])?,
+            reorder: self.reorder()?,
+            latency: self.option(|r| Some(Duration::new(r.int()?, r.int()?)))?,
+            max_buffered_bytes: self.option(Self::int)?,
+            parameter_sets: self.pick(&[
+                ParameterSetMode::PassThrough,
+                ParameterSetMode::InjectOnKeyframe,
+            ])?,
+            hevc_donl: self.flag()?,
+            access_unit_delimiters: self.flag()?,
+            avc_base_view_only: self.flag()?,
+            ssrc_policy: self.pick(&[
+                SsrcPolicy::ResetOnChange,
+                SsrcPolicy::LockFirst,
+                SsrcPolicy::KeepParameterSets,
+            ])?,
+            event_log: self.int()?,
+            quarantine: self.int()?,
+            decimation: match (self.u8()?, self.int()?) {
+                (0, _) => Decimation::Off,
+                (1, n) => Decimation::EveryNth(n),
+                (2, n) => Decimation::MaxFps(n),
+                _ => return None,
+            },
+            validate_output: self.flag()?,
+            delivery: self.pick(&[Delivery::AccessUnit, Delivery::NalUnit])?,
+            resync: match (self.u8()?, self.int()?) {
+                (0, _) => Resync::Off,
+                (1, n) => Resync::SuppressAfter(n),
+                (2, n) => Resync::FlagAfter(n),
+                _ => return None,
+            },
+            max_aggregation_items: self.option(Self::int)?,
+            comfort_noise_payload_type: self.option(Self::u8)?,
+            sframe: self.pick(&[SframeMode::Off, SframeMode::Encrypted, SframeMode::Detect])?,
+            resilient: self.flag()?,
+            trace: self.flag()?,
+        })
+    }
+
+    fn reorder(&mut self) -> Option<ReorderConfig> {
+        Some(ReorderConfig {
+            enable_reordering: self.flag()?,
+            drop_incomplete_frames: self.flag()?,
+            max_buffered_packets_per_frame: self.int()?,
+            max_pending_frames: self.int()?,
+            expected_frame_size: self.int()?,
+            max_frame_bytes: self.option(Self::int)?,
+            max_reorder_window: self.option(Self::int)?,
+            overflow: self.pick(&[OverflowPolicy::DropFrame, OverflowPolicy::DropOldestPacket])?,
+            nack_window: self.int()?,
+        })
+    }
+
+    fn codec(&mut self) -> Option<Codec> {
+        match self.u8()? {
+            5 => self.int().map(Codec::Custom),
+            kind => [
+                Codec::Unknown,
+                Codec::Avc,
+                Codec::Hevc,
+                Codec::Vp9,
+                Codec::Av1,
+            ]
+            .get(kind as usize)
+            .copied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{avc, drop_packets, reorder_within, StreamBuilder};
+    use alloc::format;
+
+    fn stream() -> Vec<Vec<u8>> {
+        let mut builder = StreamBuilder::new(7).start_sequence(65530);
+        for i in 0..8u8 {
+            let nal = if i % 4 == 0 { 0x65 } else { 0x41 };
+            builder = builder.frame(&avc::fu_a(&[nal, 0x88, i, 0xAB, 0xCD, i], 2));
+        }
+        // Frame 1 loses its middle fragment.
+        reorder_within(&drop_packets(&builder.build(), &[4]), 3)
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn replays_a_trace_to_the_same_decisions() {
+        let config = ReassemblerConfig {
+            output: OutputFormat::LengthPrefixed,
+            latency: Some(Duration::from_millis(250)),
+            resync: Resync::FlagAfter(3),
+            max_aggregation_items: Some(usize::MAX),
+            event_log: 16,
+            trace: true,
+            ..ReassemblerConfig::default()
+        };
+        let mut r = FrameReassembler::from_config(config).unwrap();
+        r.set_codec(Codec::Avc);
+        let packets = stream();
+        let mut frames = Vec::new();
+        let mut trace = Vec::new();
+        for (i, bytes) in packets.iter().enumerate() {
+            frames.extend(r.push_packet(&RtpPacket::parse(bytes).unwrap()));
+            if i == 6 {
+                let reorder = ReorderConfig {
+                    max_reorder_window: Some(100),
+                    ..config.reorder
+                };
+                r.set_reorder_config(reorder).unwrap();
+                // Chunks taken along the way form one trace.
+                trace.extend(r.take_trace());
+            }
+        }
+        trace.extend(r.stop_trace());
+        assert!(r.take_trace().is_empty());
+
+        let parsed = Trace::parse(&trace).unwrap();
+        assert_eq!(format!("{:?}", parsed.config()), format!("{:?}", config));
+        assert!(matches!(
+            parsed.records()[0],
+            TraceRecord::SetCodec(Codec::Avc)
+        ));
+        let mut replayer = parsed.reassembler().unwrap();
+        let replay = parsed.replay(&mut replayer);
+        assert_eq!(replay.divergence, None);
+        assert_eq!(replay.packets, packets.len());
+        assert_eq!(replay.frames, frames);
+        assert_eq!(replayer.stop_trace(), trace);
+        assert!(parsed.records().iter().any(|r| matches!(
+            r,
+            TraceRecord::Packet {
+                outcome: TraceOutcome::Dropped(DropReason::SequenceGap),
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn reports_divergence_and_invalid_traces() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        r.start_trace();
+        for bytes in stream() {
+            r.push_packet(&RtpPacket::parse(&bytes).unwrap());
+        }
+        let trace = r.stop_trace();
+        let parsed = Trace::parse(&trace).unwrap();
+
+        // Keeping incomplete frames turns the recorded drop into a frame.
+        let lenient = ReassemblerConfig {
+            codec: Some(Codec::Avc),
+            reorder: ReorderConfig {
+                drop_incomplete_frames: false,
+                ..ReorderConfig::default()
+            },
+            ..ReassemblerConfig::default()
+        };
+        let mut replayer = FrameReassembler::from_config(lenient).unwrap();
+        let divergence = parsed.replay(&mut replayer).divergence.unwrap();
+        assert_eq!(
+            divergence.recorded,
+            TraceOutcome::Dropped(DropReason::SequenceGap)
+        );
+        assert!(matches!(
+            divergence.replayed,
+            TraceOutcome::Completed { .. }
+        ));
+
+        assert_eq!(Trace::parse(b"RIFF").err(), Some(TraceError::NotATrace));
+        assert_eq!(
+            Trace::parse(b"RTPT\x09").err(),
+            Some(TraceError::UnsupportedVersion(9))
+        );
+        let cut = trace.len() - 3;
+        assert!(matches!(
+            Trace::parse(&trace[..cut]),
+            Err(TraceError::InvalidRecord(offset)) if offset < cut
+        ));
+    }
+}
@@ -0,0 +1,547 @@
+use super::{
+    keyframe::{av1_obu_types, is_keyframe, recovery_after},
+    param_sets::{nal_role, NalRole},
+    ConfigError, FrameReassembler, OutputFormat, ParameterSetMode, ReassemblerConfig,
+};
+use crate::{
+    bitstream::split_annex_b,
+    captions::FrameCaptions,
+    codecs::{
+        hevc::HevcSps,
+        sei::{
+            parse_time_code, sei_messages, sei_rbsp, ClockTimestamp, HevcPicTiming, SEI_PIC_TIMING,
+            SEI_TIME_CODE,
+        },
+        vp9::Vp9StreamParams,
+        Codec,
+    },
+    hdr::{DolbyVisionRpu, HdrMetadata},
+    rtp::RtpPacket,
+};
+use alloc::vec::Vec;
+
+// Reassembler for one codec known up front, wrapping a `FrameReassembler`
+// whose frames are returned as `$frame`.
+macro_rules! typed_reassembler {
+    ($(#[$doc:meta])* $name:ident, $frame:ident) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $name {
+            inner: FrameReassembler,
+            stream: StreamState,
+        }
+
+        impl $name {
+            /// Pushes a parsed RTP packet; returns the frame it completes, if any.
+            pub fn push_packet(&mut self, pkt: &RtpPacket<'_>) -> Option<$frame> {
+                let data = self.inner.push_packet(pkt)?;
+                Some($frame::new(pkt.header.timestamp, data, &self.inner, &mut self.stream))
+            }
+
+            /// The wrapped reassembler, e.g. for its event log.
+            pub fn inner(&self) -> &FrameReassembler {
+                &self.inner
+            }
+
+            pub fn into_inner(self) -> FrameReassembler {
+                self.inner
+            }
+        }
+    };
+}
+
+// Parameters carried across the frames of a stream.
+#[derive(Debug, Default)]
+struct StreamState {
+    // Latest HEVC SPS, for picture timing SEI.
+    hevc_sps: Option<HevcSps>,
+    vp9_params: Option<Vp9StreamParams>,
+}
+
+// NAL units of an AVC/HEVC frame in either output format.
+fn nal_units(data: &[u8], output: OutputFormat) -> Vec<&[u8]> {
+    match output {
+        OutputFormat::AnnexB => split_annex_b(data),
+        OutputFormat::LengthPrefixed => {
+            let mut nals = Vec::new();
+            let mut rest = data;
+            while let Some((len, tail)) = rest.split_first_chunk::<4>() {
+                let len = u32::from_be_bytes(*len) as usize;
+                let Some(nal) = tail.get(..len) else {
+                    break;
+                };
+                nals.push(nal);
+                rest = &tail[len..];
+            }
+            nals
+        }
+    }
+}
+
+// Metadata shared by AVC and HEVC frames.
+struct NalMetadata<'a> {
+    nals: Vec<&'a [u8]>,
+    keyframe: bool,
+    nal_types: Vec<u8>,
+    recovery_after: Option<u32>,
+    captions: FrameCaptions,
+}
+
+fn nal_metadata(codec: Codec, data: &[u8], output: OutputFormat) -> NalMetadata<'_> {
+    let nals = nal_units(data, output);
+    let headers: Vec<u8> = nals.iter().filter_map(|nal| nal.first().copied()).collect();
+    let keyframe = headers
+        .iter()
+        .any(|&h| nal_role(codec, h) == NalRole::Keyframe);
+    let nal_types = match codec {
+        Codec::Hevc => headers.iter().map(|h| (h >> 1) & 0x3F).collect(),
+        _ => headers.iter().map(|h| h & 0x1F).collect(),
+    };
+    NalMetadata {
+        keyframe,
+        nal_types,
+        recovery_after: recovery_after(codec, nals.iter().copied()),
+        captions: FrameCaptions::from_nals(codec, nals.iter().copied()),
+        nals,
+    }
+}
+
+/// Settings specific to [`AvcReassembler`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AvcConfig {
+    pub output: OutputFormat,
+    pub parameter_sets: ParameterSetMode,
+    /// Start every frame with an access unit delimiter.
+    pub access_unit_delimiters: bool,
+    /// Strip the non-base views of MVC (stereo) streams.
+    pub base_view_only: bool,
+}
+
+/// Access unit completed by an [`AvcReassembler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvcFrame {
+    pub timestamp: u32,
+    /// In the configured [`OutputFormat`].
+    pub data: Vec<u8>,
+    /// Contains an IDR slice.
+    pub keyframe: bool,
+    /// `nal_unit_type` of each NAL unit, in order.
+    pub nal_types: Vec<u8>,
+    /// Carries a recovery point SEI: decoding started here is correct after
+    /// this many more pictures, as with gradual decoder refresh.
+    pub recovery_after: Option<u32>,
+    /// CEA-608/708 caption data from SEI user data.
+    pub captions: FrameCaptions,
+    /// Coded slices in the frame.
+    pub slice_count: usize,
+    /// The slices start at the first macroblock and are in order; see
+    /// [`AvcSliceSummary::complete`](crate::codecs::avc::AvcSliceSummary::complete).
+    pub slices_complete: bool,
+    /// MVC view ids present in the stream's access unit, base view first;
+    /// empty for single-view streams. Lists all views even when
+    /// [`AvcConfig::base_view_only`] removed them from `data`.
+    pub views: Vec<u16>,
+}
+
+impl AvcFrame {
+    fn new(
+        timestamp: u32,
+        data: Vec<u8>,
+        inner: &FrameReassembler,
+        _stream: &mut StreamState,
+    ) -> Self {
+        let meta = nal_metadata(Codec::Avc, &data, inner.config().output);
+        let slices = inner.last_slice_summary();
+        Self {
+            timestamp,
+            keyframe: meta.keyframe,
+            nal_types: meta.nal_types,
+            recovery_after: meta.recovery_after,
+            captions: meta.captions,
+            slice_count: slices.map_or(0, |s| s.slice_count),
+            slices_complete: slices.is_none_or(|s| s.complete),
+            views: inner.last_mvc_views().to_vec(),
+            data,
+        }
+    }
+
+    /// Carries both an SPS and a PPS.
+    pub fn has_parameter_sets(&self) -> bool {
+        self.nal_types.contains(&7) && self.nal_types.contains(&8)
+    }
+}
+
+typed_reassembler!(
+    /// H.264 reassembler (RFC 6184).
+    AvcReassembler,
+    AvcFrame
+);
+
+impl AvcReassembler {
+    pub fn new(config: AvcConfig) -> Result<Self, ConfigError> {
+        Self::with_config(config, ReassemblerConfig::default())
+    }
+
+    /// Takes codec-independent settings (reordering, limits) from `base`.
+    pub fn with_config(config: AvcConfig, base: ReassemblerConfig) -> Result<Self, ConfigError> {
+        let inner = FrameReassembler::from_config(ReassemblerConfig {
+            codec: Some(Codec::Avc),
+            output: config.output,
+            parameter_sets: config.parameter_sets,
+            hevc_donl: false,
+            access_unit_delimiters: config.access_unit_delimiters,
+            avc_base_view_only: config.base_view_only,
+            ..base
+        })?;
+        Ok(Self {
+            inner,
+            stream: StreamState::default(),
+        })
+    }
+}
+
+/// Settings specific to [`HevcReassembler`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HevcConfig {
+    pub output: OutputFormat,
+    pub parameter_sets: ParameterSetMode,
+    /// Payloads carry DONL/DOND fields (`sprop-max-don-diff` > 0).
+    pub donl: bool,
+    /// Start every access unit with an access unit delimiter.
+    pub access_unit_delimiters: bool,
+}
+
+/// Access unit completed by an [`HevcReassembler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HevcFrame {
+    pub timestamp: u32,
+    /// In the configured [`OutputFormat`].
+    pub data: Vec<u8>,
+    /// Contains an IRAP picture (BLA, IDR or CRA).
+    pub keyframe: bool,
+    /// `nal_unit_type` of each NAL unit, in order.
+    pub nal_types: Vec<u8>,
+    /// Carries a recovery point SEI: decoding started here is correct after
+    /// this many more pictures, as with gradual decoder refresh.
+    pub recovery_after: Option<u32>,
+    /// CEA-608/708 caption data from SEI user data.
+    pub captions: FrameCaptions,
+    /// Picture timing SEI, once an SPS describing it has been seen.
+    pub pic_timing: Option<HevcPicTiming>,
+    /// Clock timestamps of a time code SEI.
+    pub time_code: Vec<ClockTimestamp>,
+    /// HDR10+ and other ST 2094 dynamic metadata.
+    pub hdr_metadata: Vec<HdrMetadata>,
+    /// Dolby Vision RPU; the NAL unit is also left in `data`.
+    pub dolby_vision_rpu: Option<DolbyVisionRpu>,
+}
+
+impl HevcFrame {
+    fn new(
+        timestamp: u32,
+        data: Vec<u8>,
+        inner: &FrameReassembler,
+        stream: &mut StreamState,
+    ) -> Self {
+        let meta = nal_metadata(Codec::Hevc, &data, inner.config().output);
+        let mut pic_timing = None;
+        let mut time_code = Vec::new();
+        for nal in &meta.nals {
+            if nal.first().map(|h| (h >> 1) & 0x3F) == Some(33) {
+                if let Ok(sps) = HevcSps::parse(nal) {
+                    stream.hevc_sps = Some(sps);
+                }
+            }
+            let Some(rbsp) = sei_rbsp(Codec::Hevc, nal) else {
+                continue;
+            };
+            for message in sei_messages(&rbsp) {
+                match (message.payload_type, &stream.hevc_sps) {
+                    (SEI_PIC_TIMING, Some(sps)) => {
+                        pic_timing = HevcPicTiming::parse(message.payload, sps).ok();
+                    }
+                    (SEI_TIME_CODE, _) => {
+                        time_code = parse_time_code(message.payload).unwrap_or_default();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Self {
+            pic_timing,
+            time_code,
+            hdr_metadata: HdrMetadata::from_nals(Codec::Hevc, meta.nals.iter().copied()),
+            dolby_vision_rpu: DolbyVisionRpu::from_nals(Codec::Hevc, meta.nals.iter().copied()),
+            timestamp,
+            keyframe: meta.keyframe,
+            nal_types: meta.nal_types,
+            recovery_after: meta.recovery_after,
+            captions: meta.captions,
+            data,
+        }
+    }
+
+    /// Carries a VPS, an SPS and a PPS.
+    pub fn has_parameter_sets(&self) -> bool {
+        [32, 33, 34].iter().all(|t| self.nal_types.contains(t))
+    }
+}
+
+typed_reassembler!(
+    /// H.265 reassembler (RFC 7798).
+    HevcReassembler,
+    HevcFrame
+);
+
+impl HevcReassembler {
+    pub fn new(config: HevcConfig) -> Result<Self, ConfigError> {
+        Self::with_config(config, ReassemblerConfig::default())
+    }
+
+    /// Takes codec-independent settings (reordering, limits) from `base`.
+    pub fn with_config(config: HevcConfig, base: ReassemblerConfig) -> Result<Self, ConfigError> {
+        let inner = FrameReassembler::from_config(ReassemblerConfig {
+            codec: Some(Codec::Hevc),
+            output: config.output,
+            parameter_sets: config.parameter_sets,
+            hevc_donl: config.donl,
+            access_unit_delimiters: config.access_unit_delimiters,
+            ..base
+        })?;
+        Ok(Self {
+            inner,
+            stream: StreamState::default(),
+        })
+    }
+}
+
+/// Frame completed by a [`Vp9Reassembler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vp9Frame {
+    pub timestamp: u32,
+    pub data: Vec<u8>,
+    /// `frame_type` is KEY_FRAME.
+    pub keyframe: bool,
+    /// Profile, color config and size from the latest key or intra-only
+    /// frame of the stream.
+    pub params: Option<Vp9StreamParams>,
+}
+
+impl Vp9Frame {
+    fn new(
+        timestamp: u32,
+        data: Vec<u8>,
+        _inner: &FrameReassembler,
+        stream: &mut StreamState,
+    ) -> Self {
+        let keyframe = is_keyframe(Codec::Vp9, &data);
+        if let Ok(Some(params)) = Vp9StreamParams::parse(&data) {
+            stream.vp9_params = Some(params);
+        }
+        Self {
+            timestamp,
+            data,
+            keyframe,
+            params: stream.vp9_params,
+        }
+    }
+}
+
+typed_reassembler!(
+    /// VP9 reassembler (RFC 9628).
+    Vp9Reassembler,
+    Vp9Frame
+);
+
+/// Temporal unit completed by an [`Av1Reassembler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Av1Frame {
+    pub timestamp: u32,
+    /// OBUs, each with its size field.
+    pub data: Vec<u8>,
+    /// Contains a sequence header or starts a new coded video sequence.
+    pub keyframe: bool,
+    /// The aggregation header N bit was set.
+    pub new_sequence: bool,
+    /// `obu_type` of each OBU, in order.
+    pub obu_types: Vec<u8>,
+    /// HDR10+ and other ST 2094 dynamic metadata from metadata OBUs.
+    pub hdr_metadata: Vec<HdrMetadata>,
+}
+
+impl Av1Frame {
+    fn new(
+        timestamp: u32,
+        data: Vec<u8>,
+        inner: &FrameReassembler,
+        _stream: &mut StreamState,
+    ) -> Self {
+        let obu_types: Vec<u8> = av1_obu_types(&data).collect();
+        let new_sequence = inner.last_frame_new_sequence();
+        Self {
+            timestamp,
+            keyframe: new_sequence || obu_types.contains(&1),
+            new_sequence,
+            hdr_metadata: HdrMetadata::from_obus(&data),
+            data,
+            obu_types,
+        }
+    }
+}
+
+typed_reassembler!(
+    /// AV1 reassembler (AV1 RTP payload specification).
+    Av1Reassembler,
+    Av1Frame
+);
+
+// VP9 and AV1 have no codec-specific settings.
+macro_rules! plain_constructors {
+    ($name:ident, $codec:expr) => {
+        impl $name {
+            pub fn new() -> Self {
+                Self::with_config(ReassemblerConfig::default()).expect("default config is valid")
+            }
+
+            /// Takes all settings but the codec from `base`; NAL-specific
+            /// ones must be left at their defaults.
+            pub fn with_config(base: ReassemblerConfig) -> Result<Self, ConfigError> {
+                let inner = FrameReassembler::from_config(ReassemblerConfig {
+                    codec: Some($codec),
+                    ..base
+                })?;
+                Ok(Self {
+                    inner,
+                    stream: StreamState::default(),
+                })
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+plain_constructors!(Vp9Reassembler, Codec::Vp9);
+plain_constructors!(Av1Reassembler, Codec::Av1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        packetize::{HevcPacketizer, Packetizer},
+        test_utils::{
+            avc,
+            fixtures::{
+                annex_b, AVC_IDR, AVC_NON_IDR, AVC_PPS, AVC_SPS, HEVC_IDR, HEVC_PPS, HEVC_SPS,
+                HEVC_VPS,
+            },
+            RtpPacketBuilder, StreamBuilder,
+        },
+    };
+
+    #[test]
+    fn hevc_reassembler_strips_donl() {
+        let idr = [HEVC_IDR, &[0x11; 26]].concat();
+        let frame = annex_b(&[HEVC_VPS, HEVC_SPS, HEVC_PPS, &idr]);
+        // VPS and SPS share an AP, the PPS goes alone, the IDR is fragmented.
+        let payloads = HevcPacketizer::with_donl(7).packetize(&frame, 30);
+        let mut r = HevcReassembler::new(HevcConfig {
+            donl: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut frames = Vec::new();
+        for p in StreamBuilder::new(1).frame(&payloads).build() {
+            frames.extend(r.push_packet(&RtpPacket::parse(&p).unwrap()));
+        }
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data, frame);
+        assert!(frames[0].keyframe && frames[0].has_parameter_sets());
+        assert_eq!(frames[0].nal_types, [32, 33, 34, 19]);
+    }
+
+    #[test]
+    fn dolby_vision_rpus_stay_with_their_frames() {
+        // rpu_type 2, rpu_format 18, long enough to be fragmented.
+        let rpu = [&[0x7C, 0x01, 0x19, 0x08, 0x09, 0x00][..], &[0x42; 40]].concat();
+        let idr = [HEVC_IDR, &[0x11; 26]].concat();
+        let first = annex_b(&[HEVC_VPS, HEVC_SPS, HEVC_PPS, &idr, &rpu]);
+        let second = annex_b(&[&idr]);
+        let mut packetizer = HevcPacketizer::new();
+        let packets = StreamBuilder::new(1)
+            .frame(&packetizer.packetize(&first, 30))
+            .frame(&packetizer.packetize(&second, 30))
+            .build();
+        let mut r = HevcReassembler::new(HevcConfig::default()).unwrap();
+        let mut frames = Vec::new();
+        for p in &packets {
+            frames.extend(r.push_packet(&RtpPacket::parse(p).unwrap()));
+        }
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data, first);
+        let found = frames[0].dolby_vision_rpu.as_ref().unwrap();
+        assert_eq!(
+            (found.rpu_type, found.rpu_format, &found.nal),
+            (2, 18, &rpu)
+        );
+        assert_eq!(frames[1].dolby_vision_rpu, None);
+        assert!(crate::hdr::is_dolby_vision_nal(Codec::Hevc, &rpu));
+    }
+
+    #[test]
+    fn frame_metadata_follows_output_format() {
+        let mut r = AvcReassembler::new(AvcConfig {
+            output: OutputFormat::LengthPrefixed,
+            ..Default::default()
+        })
+        .unwrap();
+        let stream = StreamBuilder::new(1)
+            .frame(&[avc::stap_a(&[AVC_SPS, AVC_PPS]), avc::single(AVC_IDR)])
+            .build();
+        let frame = stream
+            .iter()
+            .find_map(|p| r.push_packet(&RtpPacket::parse(p).unwrap()))
+            .unwrap();
+        assert!(frame.keyframe && frame.has_parameter_sets());
+        assert_eq!(frame.nal_types, [7, 8, 5]);
+        assert_eq!(frame.data[..4], (AVC_SPS.len() as u32).to_be_bytes());
+
+        let mut vp9 = Vp9Reassembler::new();
+        let p = StreamBuilder::new(1)
+            .frame(&[alloc::vec![0x8C, 0x80, 0x01, 0x80, 0x49, 0x83]])
+            .build();
+        let frame = vp9.push_packet(&RtpPacket::parse(&p[0]).unwrap()).unwrap();
+        assert!(frame.keyframe);
+        assert!(Av1Reassembler::with_config(ReassemblerConfig {
+            hevc_donl: true,
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn recovery_point_frame_ends_keyframe_wait() {
+        let mut r = AvcReassembler::new(AvcConfig::default()).unwrap();
+        let mut push = |payload: &[u8], marker: bool, seq: u16, ts: u32| {
+            let bytes = RtpPacketBuilder::new()
+                .marker(marker)
+                .sequence_number(seq)
+                .timestamp(ts)
+                .payload(payload)
+                .build();
+            r.push_packet(&RtpPacket::parse(&bytes).unwrap())
+        };
+        // FU-A start and end with a lost middle fragment.
+        assert!(push(&[0x7C, 0x81, 0xAA], false, 1, 0).is_none());
+        assert!(push(&[0x7C, 0x41, 0xCC], true, 3, 0).is_none());
+        let sei: &[u8] = &[0x06, 0x06, 0x02, 0x24, 0x40, 0x80];
+        let frame = push(&avc::stap_a(&[sei, AVC_NON_IDR]), true, 4, 3000).unwrap();
+        assert!(!frame.keyframe);
+        assert_eq!(frame.recovery_after, Some(3));
+        assert!(!r.inner().needs_keyframe());
+    }
+}
@@ -0,0 +1,188 @@
+use super::OutputFormat;
+use crate::{
+    bitstream::split_annex_b,
+    codecs::{
+        av1::{read_leb128, ObuHeader},
+        Codec,
+    },
+};
+
+/// Structural problem in an assembled frame that is likely to upset a
+/// decoder; see
+/// [`ReassemblerConfig::validate_output`](super::ReassemblerConfig::validate_output).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrameDefect {
+    /// An Annex-B frame does not begin with a start code.
+    MissingStartCode,
+    /// A NAL unit is empty or shorter than its header.
+    TruncatedNalUnit,
+    /// A NAL unit or OBU header has its forbidden bit set.
+    ForbiddenBit,
+    /// An HEVC NAL unit header has `nuh_temporal_id_plus1` 0.
+    InvalidTemporalId,
+    /// A length prefix or `obu_size` runs past the end of the frame.
+    SizeMismatch,
+    /// An OBU lacks its size field or has a reserved type.
+    InvalidObuHeader,
+}
+
+impl FrameDefect {
+    /// Short description, also the `reason` label of invalid frame metrics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FrameDefect::MissingStartCode => "missing start code",
+            FrameDefect::TruncatedNalUnit => "truncated NAL unit",
+            FrameDefect::ForbiddenBit => "forbidden bit set",
+            FrameDefect::InvalidTemporalId => "invalid temporal id",
+            FrameDefect::SizeMismatch => "size mismatch",
+            FrameDefect::InvalidObuHeader => "invalid OBU header",
+        }
+    }
+}
+
+impl core::fmt::Display for FrameDefect {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// First defect found in an output frame; VP9 and custom codec frames are
+/// not checked.
+pub(super) fn validate(
+    codec: Codec,
+    output: OutputFormat,
+    frame: &[u8],
+) -> Result<(), FrameDefect> {
+    match codec {
+        Codec::Avc | Codec::Hevc => match output {
+            OutputFormat::AnnexB => {
+                if !(frame.starts_with(&[0, 0, 1]) || frame.starts_with(&[0, 0, 0, 1])) {
+                    return Err(FrameDefect::MissingStartCode);
+                }
+                split_annex_b(frame)
+                    .into_iter()
+                    .try_for_each(|nal| validate_nal(codec, nal))
+            }
+            OutputFormat::LengthPrefixed => {
+                let mut rest = frame;
+                while !rest.is_empty() {
+                    let (len, tail) = rest
+                        .split_first_chunk::<4>()
+                        .ok_or(FrameDefect::SizeMismatch)?;
+                    let len = u32::from_be_bytes(*len) as usize;
+                    let nal = tail.get(..len).ok_or(FrameDefect::SizeMismatch)?;
+                    validate_nal(codec, nal)?;
+                    rest = &tail[len..];
+                }
+                Ok(())
+            }
+        },
+        Codec::Av1 => validate_obus(frame),
+        Codec::Vp9 | Codec::Unknown | Codec::Custom(_) => Ok(()),
+    }
+}
+
+fn validate_nal(codec: Codec, nal: &[u8]) -> Result<(), FrameDefect> {
+    let header_len = if codec == Codec::Hevc { 2 } else { 1 };
+    let header = nal.get(..header_len).ok_or(FrameDefect::TruncatedNalUnit)?;
+    if header[0] & 0x80 != 0 {
+        return Err(FrameDefect::ForbiddenBit);
+    }
+    if codec == Codec::Hevc && header[1] & 0x07 == 0 {
+        return Err(FrameDefect::InvalidTemporalId);
+    }
+    Ok(())
+}
+
+// Every OBU of the output carries a size field.
+fn validate_obus(mut frame: &[u8]) -> Result<(), FrameDefect> {
+    while let Some(&first) = frame.first() {
+        let header = ObuHeader::parse(first);
+        if first & 0x80 != 0 {
+            return Err(FrameDefect::ForbiddenBit);
+        }
+        // Types 0 and 9 to 14 are reserved.
+        if !header.has_size_field || matches!(header.obu_type, 0 | 9..=14) {
+            return Err(FrameDefect::InvalidObuHeader);
+        }
+        let size_at = header.header_len();
+        let (size, n) = frame
+            .get(size_at..)
+            .and_then(read_leb128)
+            .ok_or(FrameDefect::SizeMismatch)?;
+        let end = usize::try_from(size)
+            .ok()
+            .and_then(|size| (size_at + n).checked_add(size))
+            .filter(|&end| end <= frame.len())
+            .ok_or(FrameDefect::SizeMismatch)?;
+        frame = &frame[end..];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        reassemble::FrameReassembler,
+        rtp::RtpPacket,
+        test_utils::{
+            fixtures::{annex_b, AVC_IDR, AVC_SPS},
+            rtp_packet, RtpPacketBuilder,
+        },
+    };
+
+    #[test]
+    fn flags_structural_defects() {
+        let avc = |frame: &[u8]| validate(Codec::Avc, OutputFormat::AnnexB, frame);
+        assert_eq!(avc(&annex_b(&[AVC_SPS, AVC_IDR])), Ok(()));
+        assert_eq!(avc(AVC_IDR), Err(FrameDefect::MissingStartCode));
+        assert_eq!(
+            avc(&annex_b(&[&[0xE5, 0x01]])),
+            Err(FrameDefect::ForbiddenBit)
+        );
+        let hevc = validate(
+            Codec::Hevc,
+            OutputFormat::AnnexB,
+            &annex_b(&[&[0x26, 0x00]]),
+        );
+        assert_eq!(hevc, Err(FrameDefect::InvalidTemporalId));
+        let prefixed = validate(
+            Codec::Avc,
+            OutputFormat::LengthPrefixed,
+            &[0, 0, 0, 3, 0x65],
+        );
+        assert_eq!(prefixed, Err(FrameDefect::SizeMismatch));
+
+        // Temporal delimiter, then a frame OBU claiming 2 bytes but holding 1.
+        let av1 = |frame: &[u8]| validate(Codec::Av1, OutputFormat::AnnexB, frame);
+        assert_eq!(av1(&[0x12, 0x00, 0x32, 0x01, 0xFF]), Ok(()));
+        assert_eq!(
+            av1(&[0x12, 0x00, 0x32, 0x02, 0xFF]),
+            Err(FrameDefect::SizeMismatch)
+        );
+        assert_eq!(av1(&[0x30, 0xFF]), Err(FrameDefect::InvalidObuHeader));
+    }
+
+    #[test]
+    fn reassembler_flags_but_returns_invalid_frames() {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .validate_output(true)
+            .build()
+            .unwrap();
+        let bad = rtp_packet(&[0xE5, 0x01], true, 1);
+        assert!(r.push_packet(&RtpPacket::parse(&bad).unwrap()).is_some());
+        assert_eq!(r.last_frame_defect(), Some(FrameDefect::ForbiddenBit));
+        let good = RtpPacketBuilder::new()
+            .marker(true)
+            .sequence_number(2)
+            .timestamp(3000)
+            .payload(AVC_IDR)
+            .build();
+        assert!(r.push_packet(&RtpPacket::parse(&good).unwrap()).is_some());
+        assert_eq!(r.last_frame_defect(), None);
+        assert_eq!(r.invalid_frames(), 1);
+    }
+}
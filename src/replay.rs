@@ -0,0 +1,206 @@
+//! Recording packets as they arrive and replaying them later.
+//!
+//! A recording is the magic `RTPR`, a version byte, then one record per
+//! packet: the arrival time in microseconds since the previous packet and
+//! the packet length, both LEB128, followed by the packet bytes. Replaying
+//! into a [`PacketSink`] reproduces a field capture deterministically,
+//! either with its original pacing or as fast as possible.
+
+use crate::{
+    codecs::av1::{read_leb128, write_leb128},
+    net::PacketSink,
+};
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+    vec::Vec,
+};
+
+const MAGIC: &[u8; 4] = b"RTPR";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    /// The record starting at `offset` is cut off.
+    Truncated {
+        offset: usize,
+    },
+}
+
+impl core::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ReplayError::BadMagic => write!(f, "not a packet recording"),
+            ReplayError::UnsupportedVersion(v) => {
+                write!(f, "unsupported recording version {}", v)
+            }
+            ReplayError::Truncated { offset } => {
+                write!(f, "recording truncated in the record at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Writes packets to a recording as they arrive.
+#[derive(Debug)]
+pub struct Recorder<W> {
+    out: W,
+    start: Instant,
+    last: Duration,
+    record: Vec<u8>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Writes the file header; arrival times count from now.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        Ok(Self {
+            out,
+            start: Instant::now(),
+            last: Duration::ZERO,
+            record: Vec::new(),
+        })
+    }
+
+    /// Records `packet` as arriving now.
+    pub fn record(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.record_at(self.start.elapsed(), packet)
+    }
+
+    /// Records `packet` with an arrival time since the start of the
+    /// recording; times earlier than the previous packet's are raised to it.
+    pub fn record_at(&mut self, arrival: Duration, packet: &[u8]) -> io::Result<()> {
+        let delta = arrival.saturating_sub(self.last);
+        self.last = self.last.max(arrival);
+        self.record.clear();
+        write_leb128(delta.as_micros() as u64, &mut self.record);
+        write_leb128(packet.len() as u64, &mut self.record);
+        self.out.write_all(&self.record)?;
+        self.out.write_all(packet)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// One recorded packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedPacket {
+    /// Since the start of the recording.
+    pub arrival: Duration,
+    pub data: Vec<u8>,
+}
+
+/// How [`Recording::replay`] spaces packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pacing {
+    /// Sleeps so packets are delivered at their recorded arrival times.
+    Original,
+    #[default]
+    AsFastAsPossible,
+}
+
+/// A recording read back into memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub packets: Vec<RecordedPacket>,
+}
+
+impl Recording {
+    pub fn parse(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let rest = bytes.strip_prefix(MAGIC).ok_or(ReplayError::BadMagic)?;
+        let (&version, mut rest) = rest.split_first().ok_or(ReplayError::BadMagic)?;
+        if version != VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let mut packets = Vec::new();
+        let mut arrival = Duration::ZERO;
+        while !rest.is_empty() {
+            let truncated = ReplayError::Truncated {
+                offset: bytes.len() - rest.len(),
+            };
+            let (delta, n) = read_leb128(rest).ok_or(truncated.clone())?;
+            let (len, m) = read_leb128(&rest[n..]).ok_or(truncated.clone())?;
+            let data = usize::try_from(len)
+                .ok()
+                .and_then(|len| rest[n + m..].get(..len))
+                .ok_or(truncated)?;
+            arrival += Duration::from_micros(delta);
+            packets.push(RecordedPacket {
+                arrival,
+                data: data.to_vec(),
+            });
+            rest = &rest[n + m + data.len()..];
+        }
+        Ok(Self { packets })
+    }
+
+    /// Feeds every packet to `sink` and returns the frames it completed.
+    /// Packets the sink rejects are skipped, as a receiver would.
+    pub fn replay<S: PacketSink>(&self, sink: &mut S, pacing: Pacing) -> Vec<S::Frame> {
+        let start = Instant::now();
+        let mut frames = Vec::new();
+        for packet in &self.packets {
+            if pacing == Pacing::Original {
+                std::thread::sleep(packet.arrival.saturating_sub(start.elapsed()));
+            }
+            if let Ok(Some(frame)) = sink.push_datagram(&packet.data) {
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::Codec,
+        reassemble::FrameReassembler,
+        test_utils::{avc, fixtures::AVC_NON_IDR, StreamBuilder},
+    };
+
+    #[test]
+    fn replays_a_recording_with_its_pacing() {
+        let packets = StreamBuilder::new(3)
+            .frame(&avc::fu_a(AVC_NON_IDR, 4))
+            .build();
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        for (i, packet) in packets.iter().enumerate() {
+            recorder
+                .record_at(Duration::from_millis(2 * i as u64), packet)
+                .unwrap();
+        }
+        let bytes = recorder.into_inner();
+        let recording = Recording::parse(&bytes).unwrap();
+        assert_eq!(recording.packets.len(), packets.len());
+        assert_eq!(recording.packets[1].arrival, Duration::from_millis(2));
+
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        let start = Instant::now();
+        let frames = recording.replay(&mut r, Pacing::Original);
+        assert!(start.elapsed() >= recording.packets.last().unwrap().arrival);
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].ends_with(AVC_NON_IDR));
+
+        let cut = &bytes[..bytes.len() - 1];
+        assert!(matches!(
+            Recording::parse(cut),
+            Err(ReplayError::Truncated { .. })
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+//! RTP over QUIC (RoQ) framing.
+//!
+//! RoQ tags every packet with a flow identifier so one QUIC connection can
+//! carry several RTP sessions. A QUIC datagram holds the flow identifier
+//! and one packet; a QUIC stream starts with the flow identifier and then
+//! carries length-prefixed packets. Both fields are QUIC variable-length
+//! integers (RFC 9000, section 16). The unwrapped packets parse with
+//! [`RtpPacket::parse`](crate::rtp::RtpPacket::parse) as if they came from
+//! UDP.
+
+use alloc::vec::Vec;
+
+/// Largest value a QUIC variable-length integer can hold.
+pub const MAX_VARINT: u64 = (1 << 62) - 1;
+
+/// Packets longer than this on a stream are rejected rather than buffered.
+pub const MAX_STREAM_PACKET: usize = 65_536;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoqError {
+    /// The input ends inside `field`.
+    Truncated { field: &'static str },
+    /// A stream announced a packet longer than [`MAX_STREAM_PACKET`].
+    PacketTooLarge(u64),
+}
+
+impl core::fmt::Display for RoqError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RoqError::Truncated { field } => write!(f, "RoQ frame ends inside {}", field),
+            RoqError::PacketTooLarge(len) => {
+                write!(f, "RoQ packet of {} bytes exceeds the limit", len)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RoqError {}
+
+/// Reads a QUIC variable-length integer; returns it and its length.
+pub fn read_varint(buf: &[u8]) -> Option<(u64, usize)> {
+    let first = *buf.first()?;
+    let len = 1 << (first >> 6);
+    let bytes = buf.get(..len)?;
+    let value = bytes[1..]
+        .iter()
+        .fold((first & 0x3F) as u64, |acc, &b| (acc << 8) | b as u64);
+    Some((value, len))
+}
+
+/// Appends `value` in the shortest encoding; values above [`MAX_VARINT`]
+/// are clamped.
+pub fn write_varint(value: u64, out: &mut Vec<u8>) {
+    let value = value.min(MAX_VARINT);
+    let (len, prefix) = match value {
+        0..=0x3F => (1, 0x00),
+        0x40..=0x3FFF => (2, 0x40),
+        0x4000..=0x3FFF_FFFF => (4, 0x80),
+        _ => (8, 0xC0),
+    };
+    let bytes = value.to_be_bytes();
+    let start = out.len();
+    out.extend_from_slice(&bytes[8 - len..]);
+    out[start] |= prefix;
+}
+
+/// Splits a QUIC datagram into its flow identifier and RTP/RTCP packet.
+pub fn parse_datagram(datagram: &[u8]) -> Result<(u64, &[u8]), RoqError> {
+    let (flow_id, n) = read_varint(datagram).ok_or(RoqError::Truncated { field: "flow ID" })?;
+    Ok((flow_id, &datagram[n..]))
+}
+
+/// Appends the QUIC datagram payload carrying `packet` on `flow_id`.
+pub fn write_datagram(flow_id: u64, packet: &[u8], out: &mut Vec<u8>) {
+    write_varint(flow_id, out);
+    out.extend_from_slice(packet);
+}
+
+/// Appends the flow identifier that opens a RoQ stream.
+pub fn write_stream_header(flow_id: u64, out: &mut Vec<u8>) {
+    write_varint(flow_id, out);
+}
+
+/// Appends one length-prefixed packet of a RoQ stream.
+pub fn write_stream_packet(packet: &[u8], out: &mut Vec<u8>) {
+    write_varint(packet.len() as u64, out);
+    out.extend_from_slice(packet);
+}
+
+/// Incremental decoder for the receiving side of one RoQ stream; QUIC
+/// stream reads may split or merge packets arbitrarily.
+#[derive(Debug, Default)]
+pub struct RoqStreamDecoder {
+    buf: Vec<u8>,
+    pos: usize,
+    flow_id: Option<u64>,
+}
+
+impl RoqStreamDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flow identifier, once the stream header has arrived.
+    pub fn flow_id(&self) -> Option<u64> {
+        self.flow_id
+    }
+
+    /// Appends bytes read from the stream.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Next complete packet, `Ok(None)` until more bytes arrive. After an
+    /// error the stream cannot be resynchronized and should be reset.
+    pub fn next_packet(&mut self) -> Result<Option<&[u8]>, RoqError> {
+        if self.flow_id.is_none() {
+            let Some((flow_id, n)) = read_varint(&self.buf[self.pos..]) else {
+                return Ok(None);
+            };
+            self.flow_id = Some(flow_id);
+            self.pos += n;
+        }
+        let Some((len, n)) = read_varint(&self.buf[self.pos..]) else {
+            return Ok(None);
+        };
+        if len > MAX_STREAM_PACKET as u64 {
+            return Err(RoqError::PacketTooLarge(len));
+        }
+        let start = self.pos + n;
+        let end = start + len as usize;
+        if end > self.buf.len() {
+            return Ok(None);
+        }
+        self.pos = end;
+        Ok(Some(&self.buf[start..end]))
+    }
+
+    /// Bytes received but not yet returned as packets. Non-zero when the
+    /// stream ends means the last packet was cut off.
+    pub fn pending(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rtp::RtpPacket, test_utils::RtpPacketBuilder};
+
+    #[test]
+    fn varints_round_trip_in_shortest_form() {
+        for (value, len) in [
+            (0, 1),
+            (63, 1),
+            (64, 2),
+            (16_383, 2),
+            (16_384, 4),
+            (1 << 30, 8),
+        ] {
+            let mut out = Vec::new();
+            write_varint(value, &mut out);
+            assert_eq!(out.len(), len);
+            assert_eq!(read_varint(&out), Some((value, len)));
+        }
+        // RFC 9000 appendix A.1 example.
+        assert_eq!(read_varint(&[0x7B, 0xBD]), Some((15_293, 2)));
+        assert_eq!(read_varint(&[0x80, 0x00]), None);
+    }
+
+    #[test]
+    fn unwraps_datagrams_and_split_stream_reads() {
+        let packet = RtpPacketBuilder::new()
+            .sequence_number(9)
+            .payload(&[1, 2, 3])
+            .build();
+        let mut datagram = Vec::new();
+        write_datagram(300, &packet, &mut datagram);
+        assert_eq!(parse_datagram(&datagram), Ok((300, &packet[..])));
+
+        let mut stream = Vec::new();
+        write_stream_header(4, &mut stream);
+        write_stream_packet(&packet, &mut stream);
+        write_stream_packet(&packet, &mut stream);
+        let mut decoder = RoqStreamDecoder::new();
+        let mut packets = Vec::new();
+        for chunk in stream.chunks(5) {
+            decoder.push(chunk);
+            while let Some(p) = decoder.next_packet().unwrap() {
+                packets.push(RtpPacket::parse(p).unwrap().header.sequence_number);
+            }
+        }
+        assert_eq!((decoder.flow_id(), packets), (Some(4), alloc::vec![9, 9]));
+        assert_eq!(decoder.pending(), 0);
+
+        decoder.push(&[0x80, 0x01, 0x00, 0x01]);
+        assert_eq!(decoder.next_packet(), Err(RoqError::PacketTooLarge(65_537)));
+    }
+}
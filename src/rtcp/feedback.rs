@@ -0,0 +1,288 @@
+use super::{word, RtcpError};
+use alloc::vec::Vec;
+
+/// RTCP packet type of transport layer feedback (RFC 4585).
+pub const RTCP_RTPFB: u8 = 205;
+/// RTCP packet type of payload-specific feedback (RFC 4585).
+pub const RTCP_PSFB: u8 = 206;
+
+// Feedback message types (FMT) in the count field.
+pub(super) const FMT_NACK: u8 = 1;
+pub(super) const FMT_PLI: u8 = 1;
+pub(super) const FMT_FIR: u8 = 4;
+pub(super) const FMT_TWCC: u8 = 15;
+// Application layer feedback, e.g. REMB.
+pub(super) const FMT_AFB: u8 = 15;
+
+pub(super) fn is_remb(body: &[u8]) -> bool {
+    body.get(8..12) == Some(b"REMB")
+}
+
+// Sender and media SSRC at the start of every feedback body.
+fn feedback_header(body: &[u8]) -> Result<(u32, u32), RtcpError> {
+    if body.len() < 8 {
+        return Err(RtcpError::too_short(body, "feedback header", 0, 8).shifted(4));
+    }
+    Ok((word(body, 0), word(body, 4)))
+}
+
+// Feedback control information made of `entry`-byte entries.
+fn fci<'a>(body: &'a [u8], field: &'static str, entry: usize) -> Result<&'a [u8], RtcpError> {
+    let fci = &body[8..];
+    let extra = fci.len() % entry;
+    if fci.is_empty() || extra != 0 {
+        let at = 8 + fci.len() - extra;
+        return Err(RtcpError::too_short(body, field, at, entry).shifted(4));
+    }
+    Ok(fci)
+}
+
+fn write_header(out: &mut Vec<u8>, fmt: u8, packet_type: u8, len: usize) {
+    out.extend_from_slice(&[0x80 | fmt, packet_type]);
+    out.extend_from_slice(&((len / 4 - 1) as u16).to_be_bytes());
+}
+
+/// Generic NACK (RFC 4585 section 6.2.1): sequence numbers of `media_ssrc`
+/// to retransmit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenericNack<'a> {
+    pub sender_ssrc: u32,
+    pub media_ssrc: u32,
+    fci: &'a [u8],
+}
+
+impl<'a> GenericNack<'a> {
+    pub(super) fn parse(body: &'a [u8]) -> Result<Self, RtcpError> {
+        let (sender_ssrc, media_ssrc) = feedback_header(body)?;
+        Ok(Self {
+            sender_ssrc,
+            media_ssrc,
+            fci: fci(body, "NACK FCI", 4)?,
+        })
+    }
+
+    /// Packet id and bitmask of following lost packets (BLP) of each entry.
+    pub fn entries(&self) -> impl Iterator<Item = (u16, u16)> + 'a {
+        self.fci.chunks_exact(4).map(|e| {
+            (
+                u16::from_be_bytes([e[0], e[1]]),
+                u16::from_be_bytes([e[2], e[3]]),
+            )
+        })
+    }
+
+    /// The lost sequence numbers, each packet id followed by those its BLP
+    /// flags.
+    pub fn sequence_numbers(&self) -> impl Iterator<Item = u16> + 'a {
+        self.entries().flat_map(|(pid, blp)| {
+            core::iter::once(pid).chain(
+                (0..16u16)
+                    .filter(move |bit| blp & (1 << bit) != 0)
+                    .map(move |bit| pid.wrapping_add(bit + 1)),
+            )
+        })
+    }
+}
+
+/// Picture loss indication (RFC 4585 section 6.3.1): `media_ssrc` should
+/// send a keyframe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pli {
+    pub sender_ssrc: u32,
+    pub media_ssrc: u32,
+}
+
+impl Pli {
+    pub(super) fn parse(body: &[u8]) -> Result<Self, RtcpError> {
+        let (sender_ssrc, media_ssrc) = feedback_header(body)?;
+        Ok(Self {
+            sender_ssrc,
+            media_ssrc,
+        })
+    }
+}
+
+/// Full intra request (RFC 5104 section 4.3.1), asking one or more
+/// senders for a decoder refresh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirPacket<'a> {
+    pub sender_ssrc: u32,
+    fci: &'a [u8],
+}
+
+/// One entry of a [`FirPacket`]. The command sequence number grows with
+/// every new request, so repeated packets can be recognized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirRequest {
+    pub ssrc: u32,
+    pub seq_nr: u8,
+}
+
+impl<'a> FirPacket<'a> {
+    pub(super) fn parse(body: &'a [u8]) -> Result<Self, RtcpError> {
+        let (sender_ssrc, _) = feedback_header(body)?;
+        Ok(Self {
+            sender_ssrc,
+            fci: fci(body, "FIR FCI", 8)?,
+        })
+    }
+
+    pub fn requests(&self) -> impl Iterator<Item = FirRequest> + 'a {
+        self.fci.chunks_exact(8).map(|e| FirRequest {
+            ssrc: word(e, 0),
+            seq_nr: e[4],
+        })
+    }
+}
+
+/// Receiver estimated maximum bitrate (draft-alvestrand-rmcat-remb) of
+/// the streams it lists; built by [`remb`](crate::bwe::remb).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remb<'a> {
+    pub sender_ssrc: u32,
+    /// Bits per second, saturating.
+    pub bitrate: u64,
+    ssrcs: &'a [u8],
+}
+
+impl<'a> Remb<'a> {
+    pub(super) fn parse(body: &'a [u8]) -> Result<Self, RtcpError> {
+        let (sender_ssrc, _) = feedback_header(body)?;
+        if body.len() < 16 {
+            return Err(RtcpError::too_short(body, "REMB", 8, 8).shifted(4));
+        }
+        let brexp = word(body, 12);
+        let count = (brexp >> 24) as usize;
+        let exponent = (brexp >> 18) & 0x3F;
+        let mantissa = (brexp & 0x3_FFFF) as u64;
+        let ssrcs = body
+            .get(16..16 + 4 * count)
+            .ok_or_else(|| RtcpError::too_short(body, "REMB SSRCs", 16, 4 * count).shifted(4))?;
+        Ok(Self {
+            sender_ssrc,
+            bitrate: mantissa
+                .checked_shl(exponent)
+                .filter(|bitrate| bitrate >> exponent == mantissa)
+                .unwrap_or(u64::MAX),
+            ssrcs,
+        })
+    }
+
+    pub fn ssrcs(&self) -> impl Iterator<Item = u32> + 'a {
+        self.ssrcs.chunks_exact(4).map(|s| word(s, 0))
+    }
+}
+
+/// Serializes a generic NACK from `sender_ssrc` for the `lost` sequence
+/// numbers of `media_ssrc`. Runs within 16 of an entry's packet id share
+/// it; `lost` is best sorted. Empty if nothing is lost.
+pub fn generic_nack(sender_ssrc: u32, media_ssrc: u32, lost: &[u16]) -> Vec<u8> {
+    let mut entries: Vec<(u16, u16)> = Vec::new();
+    for &seq in lost {
+        match entries.last_mut() {
+            Some((pid, blp)) if (1..=16).contains(&seq.wrapping_sub(*pid)) => {
+                *blp |= 1 << (seq.wrapping_sub(*pid) - 1);
+            }
+            Some((pid, _)) if *pid == seq => {}
+            _ => entries.push((seq, 0)),
+        }
+    }
+    if entries.is_empty() {
+        return Vec::new();
+    }
+    let len = 12 + 4 * entries.len();
+    let mut out = Vec::with_capacity(len);
+    write_header(&mut out, FMT_NACK, RTCP_RTPFB, len);
+    out.extend_from_slice(&sender_ssrc.to_be_bytes());
+    out.extend_from_slice(&media_ssrc.to_be_bytes());
+    for (pid, blp) in entries {
+        out.extend_from_slice(&pid.to_be_bytes());
+        out.extend_from_slice(&blp.to_be_bytes());
+    }
+    out
+}
+
+/// Serializes a PLI from `sender_ssrc` asking `media_ssrc` for a keyframe.
+pub fn pli(sender_ssrc: u32, media_ssrc: u32) -> Vec<u8> {
+    let mut out = Vec::with_capacity(12);
+    write_header(&mut out, FMT_PLI, RTCP_PSFB, 12);
+    out.extend_from_slice(&sender_ssrc.to_be_bytes());
+    out.extend_from_slice(&media_ssrc.to_be_bytes());
+    out
+}
+
+/// Serializes a FIR from `sender_ssrc` with one entry per request.
+pub fn fir(sender_ssrc: u32, requests: &[FirRequest]) -> Vec<u8> {
+    let len = 12 + 8 * requests.len();
+    let mut out = Vec::with_capacity(len);
+    write_header(&mut out, FMT_FIR, RTCP_PSFB, len);
+    out.extend_from_slice(&sender_ssrc.to_be_bytes());
+    // The media SSRC is unused; the entries name the senders.
+    out.extend_from_slice(&0u32.to_be_bytes());
+    for request in requests {
+        out.extend_from_slice(&request.ssrc.to_be_bytes());
+        out.extend_from_slice(&[request.seq_nr, 0, 0, 0]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rtcp::RtcpPacket, session::Feedback};
+
+    #[test]
+    fn builds_and_parses_feedback_messages() {
+        // 65535 to 15 share the first entry across wraparound; 20 and 21
+        // are too far and start a second one.
+        let lost = [65535, 0, 4, 15, 20, 21];
+        let feedback = Feedback {
+            nacks: alloc::vec![(2, lost.to_vec()), (3, Vec::new())],
+            keyframe_requests: alloc::vec![2],
+        };
+        let mut compound = feedback.to_rtcp(1);
+        compound.extend(fir(1, &[FirRequest { ssrc: 2, seq_nr: 7 }]));
+        let packets: Vec<_> = RtcpPacket::parse_compound(&compound)
+            .map(Result::unwrap)
+            .collect();
+        let [RtcpPacket::Nack(nack), RtcpPacket::Pli(pli), RtcpPacket::Fir(fir)] = &packets[..]
+        else {
+            panic!("{:?}", packets);
+        };
+        assert_eq!((nack.sender_ssrc, nack.media_ssrc), (1, 2));
+        assert_eq!(
+            nack.entries().collect::<Vec<_>>(),
+            [(65535, 0b1000_0000_0001_0001), (20, 0b1)]
+        );
+        assert_eq!(nack.sequence_numbers().collect::<Vec<_>>(), lost);
+        assert_eq!(
+            *pli,
+            Pli {
+                sender_ssrc: 1,
+                media_ssrc: 2
+            }
+        );
+        assert_eq!(
+            fir.requests().collect::<Vec<_>>(),
+            [FirRequest { ssrc: 2, seq_nr: 7 }]
+        );
+        assert_eq!(packets[2].packet_type(), RTCP_PSFB);
+        assert!(generic_nack(1, 2, &[]).is_empty());
+
+        let remb = crate::bwe::remb(1, 1_000_000, &[5, 6]);
+        let Ok(RtcpPacket::Remb(remb)) = RtcpPacket::parse(&remb) else {
+            panic!();
+        };
+        assert_eq!(remb.bitrate, 1_000_000);
+        assert_eq!(remb.ssrcs().collect::<Vec<_>>(), [5, 6]);
+
+        // A NACK without entries.
+        let mut empty = generic_nack(1, 2, &[5]);
+        empty.truncate(12);
+        empty[3] = 2;
+        assert_eq!(
+            RtcpPacket::parse(&empty).unwrap_err().to_string(),
+            "NACK FCI needs 4 bytes at offset 12, 0 available"
+        );
+    }
+}
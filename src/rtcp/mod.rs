@@ -0,0 +1,869 @@
+//! RTCP packet parsing (RFC 3550 section 6).
+//!
+//! [`RtcpPacket::parse`] reads one packet without copying, as
+//! [`RtpPacket::parse`](crate::rtp::RtpPacket::parse) does for RTP, and
+//! [`RtcpPacket::parse_compound`] the packets of a compound one, which
+//! [`CompoundRtcp::parse`] also checks for RFC 3550 compliance. Sender and
+//! receiver reports, SDES, BYE, APP, and the generic NACK, PLI, FIR, REMB
+//! and transport-wide congestion control feedback messages, and XR blocks
+//! (RFC 3611) are decoded; other packets keep their raw body. The parsers
+//! check the whole packet up front, so the iterators over report blocks,
+//! SDES chunks and BYE sources cannot fail.
+
+use alloc::vec::Vec;
+
+mod feedback;
+mod twcc;
+mod xr;
+
+pub use feedback::{
+    fir, generic_nack, pli, FirPacket, FirRequest, GenericNack, Pli, Remb, RTCP_PSFB, RTCP_RTPFB,
+};
+use feedback::{is_remb, FMT_AFB, FMT_FIR, FMT_NACK, FMT_PLI, FMT_TWCC};
+pub use twcc::{PacketReport, PacketStatus, TransportFeedback};
+pub use xr::{
+    receiver_reference_time, Dlrr, DlrrItem, LossRle, VoipMetrics, XrBlock, XrPacket, RTCP_XR,
+};
+
+/// RTCP packet type of a sender report.
+pub const RTCP_SR: u8 = 200;
+/// RTCP packet type of a receiver report.
+pub const RTCP_RR: u8 = 201;
+/// RTCP packet type of source descriptions.
+pub const RTCP_SDES: u8 = 202;
+/// RTCP packet type of a goodbye.
+pub const RTCP_BYE: u8 = 203;
+/// RTCP packet type of application-defined packets.
+pub const RTCP_APP: u8 = 204;
+
+/// Reception report block of an RTCP SR or RR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportBlock {
+    pub ssrc: u32,
+    /// Fraction of packets lost since the previous report, in 1/256.
+    pub fraction_lost: u8,
+    /// 24-bit signed count of packets lost since reception started.
+    pub cumulative_lost: i32,
+    pub extended_highest_seq: u32,
+    pub jitter: u32,
+    /// Middle 32 bits of the last SR's NTP timestamp (LSR), 0 without one.
+    pub last_sr: u32,
+    /// Time since that SR in 1/65536 s (DLSR).
+    pub delay_since_last_sr: u32,
+}
+
+impl ReportBlock {
+    /// Serialized size in bytes.
+    pub const LEN: usize = 24;
+
+    /// Reads the block at the start of `data`, which holds at least
+    /// [`LEN`](Self::LEN) bytes.
+    fn read(data: &[u8]) -> Self {
+        Self {
+            ssrc: word(data, 0),
+            fraction_lost: data[4],
+            // Sign-extend the 24-bit count.
+            cumulative_lost: ((word(data, 4) << 8) as i32) >> 8,
+            extended_highest_seq: word(data, 8),
+            jitter: word(data, 12),
+            last_sr: word(data, 16),
+            delay_since_last_sr: word(data, 20),
+        }
+    }
+
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.ssrc.to_be_bytes());
+        let lost = (self.cumulative_lost as u32) & 0x00FF_FFFF;
+        out.extend_from_slice(&((self.fraction_lost as u32) << 24 | lost).to_be_bytes());
+        out.extend_from_slice(&self.extended_highest_seq.to_be_bytes());
+        out.extend_from_slice(&self.jitter.to_be_bytes());
+        out.extend_from_slice(&self.last_sr.to_be_bytes());
+        out.extend_from_slice(&self.delay_since_last_sr.to_be_bytes());
+    }
+}
+
+/// Sender info of an RTCP SR; [`SrPacket`] has its report blocks too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SenderReport {
+    pub ssrc: u32,
+    /// Wall-clock time of the report, 32.32 fixed point since 1900.
+    pub ntp_timestamp: u64,
+    /// The same instant on the stream's RTP clock.
+    pub rtp_timestamp: u32,
+    pub packet_count: u32,
+    pub octet_count: u32,
+}
+
+/// Whether a datagram on a port shared by RTP and RTCP is RTCP (RFC 5761):
+/// RTCP packet types 192 to 223 take the place of RTP payload types 64 to
+/// 95 with the marker bit set.
+pub fn is_rtcp(datagram: &[u8]) -> bool {
+    datagram.get(1).is_some_and(|pt| (192..=223).contains(pt))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcpError {
+    /// `field` needs `needed` bytes at `offset`, but only `available` remain.
+    BufferTooShort {
+        field: &'static str,
+        offset: usize,
+        needed: usize,
+        available: usize,
+    },
+    InvalidVersion(u8),
+    /// The length field at `offset` claims `words` 32-bit words after the
+    /// header, but only `remaining` bytes follow it.
+    InvalidLength {
+        words: u16,
+        offset: usize,
+        remaining: usize,
+    },
+    /// The padding count in the last byte is zero or exceeds the `remaining`
+    /// bytes of the body, which starts at `offset`.
+    InvalidPadding {
+        length: u8,
+        offset: usize,
+        remaining: usize,
+    },
+    /// `field` at `offset` holds a reserved or impossible value.
+    Invalid {
+        field: &'static str,
+        offset: usize,
+    },
+}
+
+impl RtcpError {
+    /// Byte offset into the (compound) packet where parsing failed.
+    pub fn offset(&self) -> usize {
+        match self {
+            RtcpError::BufferTooShort { offset, .. }
+            | RtcpError::InvalidLength { offset, .. }
+            | RtcpError::InvalidPadding { offset, .. }
+            | RtcpError::Invalid { offset, .. } => *offset,
+            RtcpError::InvalidVersion(_) => 0,
+        }
+    }
+
+    fn too_short(buf: &[u8], field: &'static str, offset: usize, needed: usize) -> Self {
+        RtcpError::BufferTooShort {
+            field,
+            offset,
+            needed,
+            available: buf.len().saturating_sub(offset),
+        }
+    }
+
+    // The same error for a packet starting `by` bytes into a compound one.
+    fn shifted(mut self, by: usize) -> Self {
+        match &mut self {
+            RtcpError::BufferTooShort { offset, .. }
+            | RtcpError::InvalidLength { offset, .. }
+            | RtcpError::InvalidPadding { offset, .. }
+            | RtcpError::Invalid { offset, .. } => *offset += by,
+            RtcpError::InvalidVersion(_) => {}
+        }
+        self
+    }
+}
+
+impl core::fmt::Display for RtcpError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RtcpError::BufferTooShort {
+                field,
+                offset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "{} needs {} bytes at offset {}, {} available",
+                field, needed, offset, available
+            ),
+            RtcpError::InvalidVersion(v) => write!(f, "invalid rtcp version {}", v),
+            RtcpError::InvalidLength {
+                words,
+                offset,
+                remaining,
+            } => write!(
+                f,
+                "rtcp length {} words exceeds remaining {} bytes at offset {}",
+                words, remaining, offset
+            ),
+            RtcpError::InvalidPadding {
+                length,
+                offset,
+                remaining,
+            } => write!(
+                f,
+                "padding length {} exceeds remaining {} bytes at offset {}",
+                length, remaining, offset
+            ),
+            RtcpError::Invalid { field, offset } => {
+                write!(f, "invalid {} at offset {}", field, offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RtcpError {}
+
+fn word(data: &[u8], i: usize) -> u32 {
+    u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]])
+}
+
+/// One RTCP packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtcpPacket<'a> {
+    SenderReport(SrPacket<'a>),
+    ReceiverReport(RrPacket<'a>),
+    Sdes(SdesPacket<'a>),
+    Bye(ByePacket<'a>),
+    App(AppPacket<'a>),
+    Nack(GenericNack<'a>),
+    Pli(Pli),
+    Fir(FirPacket<'a>),
+    Remb(Remb<'a>),
+    TransportFeedback(TransportFeedback<'a>),
+    ExtendedReport(XrPacket<'a>),
+    /// A packet of another type, with the 5-bit count field (the feedback
+    /// message type for RFC 4585 packets) and the body after the header.
+    Other {
+        packet_type: u8,
+        count: u8,
+        body: &'a [u8],
+    },
+}
+
+impl<'a> RtcpPacket<'a> {
+    /// Parses the packet at the start of `buf`; bytes after it, e.g. the
+    /// rest of a compound packet, are ignored.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, RtcpError> {
+        Self::parse_first(buf).map(|(packet, _)| packet)
+    }
+
+    /// The packets of a compound packet, stopping after the first error.
+    pub fn parse_compound(buf: &'a [u8]) -> RtcpPackets<'a> {
+        RtcpPackets {
+            buf,
+            offset: 0,
+            failed: false,
+        }
+    }
+
+    pub fn packet_type(&self) -> u8 {
+        match self {
+            RtcpPacket::SenderReport(_) => RTCP_SR,
+            RtcpPacket::ReceiverReport(_) => RTCP_RR,
+            RtcpPacket::Sdes(_) => RTCP_SDES,
+            RtcpPacket::Bye(_) => RTCP_BYE,
+            RtcpPacket::App(_) => RTCP_APP,
+            RtcpPacket::Nack(_) | RtcpPacket::TransportFeedback(_) => RTCP_RTPFB,
+            RtcpPacket::Pli(_) | RtcpPacket::Fir(_) | RtcpPacket::Remb(_) => RTCP_PSFB,
+            RtcpPacket::ExtendedReport(_) => RTCP_XR,
+            RtcpPacket::Other { packet_type, .. } => *packet_type,
+        }
+    }
+
+    // Returns the packet and its length.
+    fn parse_first(buf: &'a [u8]) -> Result<(Self, usize), RtcpError> {
+        let header = buf
+            .get(..4)
+            .ok_or_else(|| RtcpError::too_short(buf, "RTCP header", 0, 4))?;
+        let version = header[0] >> 6;
+        if version != 2 {
+            return Err(RtcpError::InvalidVersion(version));
+        }
+        let padding = header[0] & 0x20 != 0;
+        let count = header[0] & 0x1F;
+        let packet_type = header[1];
+        let words = u16::from_be_bytes([header[2], header[3]]);
+        let len = 4 + words as usize * 4;
+        let mut body = buf.get(4..len).ok_or(RtcpError::InvalidLength {
+            words,
+            offset: 2,
+            remaining: buf.len() - 4,
+        })?;
+        if padding {
+            let length = body.last().copied().unwrap_or(0);
+            if length == 0 || length as usize > body.len() {
+                return Err(RtcpError::InvalidPadding {
+                    length,
+                    offset: 4,
+                    remaining: body.len(),
+                });
+            }
+            body = &body[..body.len() - length as usize];
+        }
+        let packet = match (packet_type, count) {
+            (RTCP_SR, _) => RtcpPacket::SenderReport(SrPacket::parse(body, count)?),
+            (RTCP_RR, _) => RtcpPacket::ReceiverReport(RrPacket::parse(body, count)?),
+            (RTCP_SDES, _) => RtcpPacket::Sdes(SdesPacket::parse(body, count)?),
+            (RTCP_BYE, _) => RtcpPacket::Bye(ByePacket::parse(body, count)?),
+            (RTCP_APP, _) => RtcpPacket::App(AppPacket::parse(body, count)?),
+            (RTCP_RTPFB, FMT_NACK) => RtcpPacket::Nack(GenericNack::parse(body)?),
+            (RTCP_PSFB, FMT_PLI) => RtcpPacket::Pli(Pli::parse(body)?),
+            (RTCP_PSFB, FMT_FIR) => RtcpPacket::Fir(FirPacket::parse(body)?),
+            (RTCP_PSFB, FMT_AFB) if is_remb(body) => RtcpPacket::Remb(Remb::parse(body)?),
+            (RTCP_XR, _) => RtcpPacket::ExtendedReport(XrPacket::parse(body)?),
+            (RTCP_RTPFB, FMT_TWCC) => {
+                RtcpPacket::TransportFeedback(TransportFeedback::parse(body)?)
+            }
+            _ => RtcpPacket::Other {
+                packet_type,
+                count,
+                body,
+            },
+        };
+        Ok((packet, len))
+    }
+}
+
+/// Iterator over the packets of a compound RTCP packet.
+#[derive(Debug, Clone)]
+pub struct RtcpPackets<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    failed: bool,
+}
+
+impl<'a> Iterator for RtcpPackets<'a> {
+    type Item = Result<RtcpPacket<'a>, RtcpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.buf[self.offset..];
+        if rest.is_empty() || self.failed {
+            return None;
+        }
+        match RtcpPacket::parse_first(rest) {
+            Ok((packet, len)) => {
+                self.offset += len;
+                Some(Ok(packet))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e.shifted(self.offset)))
+            }
+        }
+    }
+}
+
+/// A compound RTCP datagram checked against RFC 3550 section 6.1: every
+/// packet parses, they fill the datagram exactly, only the last one is
+/// padded and, unless reduced-size RTCP (RFC 5506) is allowed, the first
+/// one is an SR or RR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompoundRtcp<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> CompoundRtcp<'a> {
+    pub fn parse(buf: &'a [u8]) -> Result<Self, RtcpError> {
+        Self::validate(buf, false)
+    }
+
+    /// Like [`parse`](Self::parse), but also accepts datagrams that do not
+    /// start with a report, such as a lone feedback message.
+    pub fn parse_reduced_size(buf: &'a [u8]) -> Result<Self, RtcpError> {
+        Self::validate(buf, true)
+    }
+
+    fn validate(buf: &'a [u8], reduced_size: bool) -> Result<Self, RtcpError> {
+        let mut offset = 0;
+        loop {
+            let rest = &buf[offset..];
+            let (packet, len) = RtcpPacket::parse_first(rest).map_err(|e| e.shifted(offset))?;
+            let report = matches!(
+                packet,
+                RtcpPacket::SenderReport(_) | RtcpPacket::ReceiverReport(_)
+            );
+            if offset == 0 && !report && !reduced_size {
+                return Err(RtcpError::Invalid {
+                    field: "first packet type",
+                    offset: 1,
+                });
+            }
+            offset += len;
+            if offset == buf.len() {
+                return Ok(Self { buf });
+            }
+            if rest[0] & 0x20 != 0 {
+                return Err(RtcpError::Invalid {
+                    field: "padding bit before the last packet",
+                    offset: offset - len,
+                });
+            }
+        }
+    }
+
+    pub fn packets(&self) -> impl Iterator<Item = RtcpPacket<'a>> + 'a {
+        RtcpPacket::parse_compound(self.buf).filter_map(Result::ok)
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+// Report blocks and profile-specific extensions after the first `fixed`
+// bytes of an SR or RR body. Offsets are within the packet, whose body
+// starts at 4.
+fn split_reports<'a>(
+    body: &'a [u8],
+    fixed: usize,
+    count: u8,
+) -> Result<(ReportBlocks<'a>, &'a [u8]), RtcpError> {
+    let len = count as usize * ReportBlock::LEN;
+    let blocks = body
+        .get(fixed..fixed + len)
+        .ok_or_else(|| RtcpError::too_short(body, "report blocks", fixed, len).shifted(4))?;
+    Ok((ReportBlocks { data: blocks }, &body[fixed + len..]))
+}
+
+/// Sender report: sender info and reception report blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrPacket<'a> {
+    pub sender: SenderReport,
+    pub reports: ReportBlocks<'a>,
+    /// Profile-specific extension after the report blocks.
+    pub extension: &'a [u8],
+}
+
+impl<'a> SrPacket<'a> {
+    fn parse(body: &'a [u8], count: u8) -> Result<Self, RtcpError> {
+        if body.len() < 24 {
+            return Err(RtcpError::too_short(body, "sender info", 0, 24).shifted(4));
+        }
+        let sender = SenderReport {
+            ssrc: word(body, 0),
+            ntp_timestamp: (word(body, 4) as u64) << 32 | word(body, 8) as u64,
+            rtp_timestamp: word(body, 12),
+            packet_count: word(body, 16),
+            octet_count: word(body, 20),
+        };
+        let (reports, extension) = split_reports(body, 24, count)?;
+        Ok(Self {
+            sender,
+            reports,
+            extension,
+        })
+    }
+}
+
+/// Receiver report: the reporter and its reception report blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RrPacket<'a> {
+    pub ssrc: u32,
+    pub reports: ReportBlocks<'a>,
+    /// Profile-specific extension after the report blocks.
+    pub extension: &'a [u8],
+}
+
+impl<'a> RrPacket<'a> {
+    fn parse(body: &'a [u8], count: u8) -> Result<Self, RtcpError> {
+        if body.len() < 4 {
+            return Err(RtcpError::too_short(body, "reporter SSRC", 0, 4).shifted(4));
+        }
+        let (reports, extension) = split_reports(body, 4, count)?;
+        Ok(Self {
+            ssrc: word(body, 0),
+            reports,
+            extension,
+        })
+    }
+}
+
+/// Iterator over the report blocks of an SR or RR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportBlocks<'a> {
+    data: &'a [u8],
+}
+
+impl ReportBlocks<'_> {
+    pub fn len(&self) -> usize {
+        self.data.len() / ReportBlock::LEN
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+impl Iterator for ReportBlocks<'_> {
+    type Item = ReportBlock;
+
+    fn next(&mut self) -> Option<ReportBlock> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let block = ReportBlock::read(self.data);
+        self.data = &self.data[ReportBlock::LEN..];
+        Some(block)
+    }
+}
+
+/// Source descriptions, one chunk per source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdesPacket<'a> {
+    count: u8,
+    data: &'a [u8],
+}
+
+impl<'a> SdesPacket<'a> {
+    fn parse(body: &'a [u8], count: u8) -> Result<Self, RtcpError> {
+        let mut offset = 0;
+        for _ in 0..count {
+            offset += sdes_chunk_len(body, offset)?;
+        }
+        Ok(Self {
+            count,
+            data: &body[..offset],
+        })
+    }
+
+    pub fn chunks(&self) -> SdesChunks<'a> {
+        SdesChunks {
+            remaining: self.count,
+            rest: self.data,
+        }
+    }
+}
+
+// Length of the chunk at `offset` of an SDES body: SSRC, items, the end
+// item and padding to the next 32-bit boundary.
+fn sdes_chunk_len(body: &[u8], offset: usize) -> Result<usize, RtcpError> {
+    let too_short = |field, at, needed| RtcpError::too_short(body, field, at, needed).shifted(4);
+    let mut at = offset + 4;
+    if body.len() < at {
+        return Err(too_short("SDES chunk", offset, 4));
+    }
+    loop {
+        match body.get(at) {
+            None => return Err(too_short("SDES item", at, 1)),
+            Some(0) => break,
+            Some(_) => {
+                let len = *body.get(at + 1).ok_or(too_short("SDES item", at, 2))? as usize;
+                if body.len() < at + 2 + len {
+                    return Err(too_short("SDES item", at, 2 + len));
+                }
+                at += 2 + len;
+            }
+        }
+    }
+    // The end item is followed by null padding up to a multiple of 4.
+    let end = offset + (at + 1 - offset).next_multiple_of(4);
+    if body.len() < end {
+        return Err(too_short("SDES chunk padding", at + 1, end - at - 1));
+    }
+    Ok(end - offset)
+}
+
+/// Iterator over the chunks of an SDES packet.
+#[derive(Debug, Clone)]
+pub struct SdesChunks<'a> {
+    remaining: u8,
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for SdesChunks<'a> {
+    type Item = SdesChunk<'a>;
+
+    fn next(&mut self) -> Option<SdesChunk<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let len = sdes_chunk_len(self.rest, 0).ok()?;
+        let (chunk, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(SdesChunk {
+            ssrc: word(chunk, 0),
+            items: &chunk[4..],
+        })
+    }
+}
+
+/// The items describing one source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdesChunk<'a> {
+    pub ssrc: u32,
+    items: &'a [u8],
+}
+
+impl<'a> SdesChunk<'a> {
+    pub fn items(&self) -> impl Iterator<Item = SdesItem<'a>> + 'a {
+        let mut rest = self.items;
+        core::iter::from_fn(move || {
+            let (&kind, tail) = rest.split_first().filter(|(&kind, _)| kind != 0)?;
+            let (&len, tail) = tail.split_first()?;
+            let (value, tail) = tail.split_at(len as usize);
+            rest = tail;
+            Some(SdesItem { kind, value })
+        })
+    }
+
+    /// The canonical name, which ties the streams of one participant
+    /// together.
+    pub fn cname(&self) -> Option<&'a str> {
+        self.items()
+            .find(|item| item.kind == SdesItem::CNAME)
+            .and_then(|item| item.text())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdesItem<'a> {
+    pub kind: u8,
+    pub value: &'a [u8],
+}
+
+impl<'a> SdesItem<'a> {
+    pub const CNAME: u8 = 1;
+    pub const NAME: u8 = 2;
+    pub const EMAIL: u8 = 3;
+    pub const PHONE: u8 = 4;
+    pub const LOC: u8 = 5;
+    pub const TOOL: u8 = 6;
+    pub const NOTE: u8 = 7;
+    pub const PRIV: u8 = 8;
+
+    /// The value as UTF-8, if it is.
+    pub fn text(&self) -> Option<&'a str> {
+        core::str::from_utf8(self.value).ok()
+    }
+}
+
+/// Goodbye: sources leaving, with an optional reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByePacket<'a> {
+    sources: &'a [u8],
+    reason: Option<&'a [u8]>,
+}
+
+impl<'a> ByePacket<'a> {
+    fn parse(body: &'a [u8], count: u8) -> Result<Self, RtcpError> {
+        let len = count as usize * 4;
+        let sources = body
+            .get(..len)
+            .ok_or_else(|| RtcpError::too_short(body, "BYE sources", 0, len).shifted(4))?;
+        let reason = match body.get(len) {
+            Some(&reason_len) => Some(
+                body.get(len + 1..len + 1 + reason_len as usize)
+                    .ok_or_else(|| {
+                        RtcpError::too_short(body, "BYE reason", len, 1 + reason_len as usize)
+                            .shifted(4)
+                    })?,
+            ),
+            None => None,
+        };
+        Ok(Self { sources, reason })
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = u32> + 'a {
+        self.sources.chunks_exact(4).map(|s| word(s, 0))
+    }
+
+    pub fn reason(&self) -> Option<&'a [u8]> {
+        self.reason
+    }
+}
+
+/// Application-defined packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppPacket<'a> {
+    pub subtype: u8,
+    pub ssrc: u32,
+    /// Four ASCII characters naming the application.
+    pub name: [u8; 4],
+    pub data: &'a [u8],
+}
+
+impl<'a> AppPacket<'a> {
+    fn parse(body: &'a [u8], subtype: u8) -> Result<Self, RtcpError> {
+        if body.len() < 8 {
+            return Err(RtcpError::too_short(body, "APP header", 0, 8).shifted(4));
+        }
+        Ok(Self {
+            subtype,
+            ssrc: word(body, 0),
+            name: [body[4], body[5], body[6], body[7]],
+            data: &body[8..],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn parses_sender_report_sdes_and_bye_of_a_compound_packet() {
+        let mut compound = words(&[
+            0x81C8_000C,
+            1,
+            1,
+            0x8000_0000,
+            3000,
+            10,
+            1000,
+            // Block about SSRC 2: 1/4 lost, -3 cumulative.
+            2,
+            0x40FF_FFFD,
+            0x1_0005,
+            7,
+            0x1234_5678,
+            0x1_0000,
+        ]);
+        // CNAME "ab" of SSRC 1, NOTE "x" of SSRC 2.
+        compound.extend(words(&[0x82CA_0005, 1]));
+        compound.extend([1, 2, b'a', b'b', 0, 0, 0, 0]);
+        compound.extend(words(&[2]));
+        compound.extend([7, 1, b'x', 0]);
+        compound.extend(words(&[0x81CB_0002, 1]));
+        compound.extend([3, b'b', b'y', b'e']);
+
+        let packets: Vec<_> = RtcpPacket::parse_compound(&compound)
+            .map(Result::unwrap)
+            .collect();
+        let [RtcpPacket::SenderReport(sr), RtcpPacket::Sdes(sdes), RtcpPacket::Bye(bye)] =
+            &packets[..]
+        else {
+            panic!("{:?}", packets);
+        };
+        assert_eq!(sr.sender.ntp_timestamp, 0x1_8000_0000);
+        let blocks: Vec<_> = sr.reports.clone().collect();
+        assert_eq!(
+            blocks,
+            [ReportBlock {
+                ssrc: 2,
+                fraction_lost: 64,
+                cumulative_lost: -3,
+                extended_highest_seq: 0x1_0005,
+                jitter: 7,
+                last_sr: 0x1234_5678,
+                delay_since_last_sr: 0x1_0000,
+            }]
+        );
+        let chunks: Vec<_> = sdes.chunks().collect();
+        assert_eq!((chunks[0].ssrc, chunks[0].cname()), (1, Some("ab")));
+        let note: Vec<_> = chunks[1].items().collect();
+        assert_eq!(
+            note,
+            [SdesItem {
+                kind: SdesItem::NOTE,
+                value: b"x"
+            }]
+        );
+        assert_eq!(bye.sources().collect::<Vec<_>>(), [1]);
+        assert_eq!(bye.reason(), Some(&b"bye"[..]));
+        assert_eq!(packets[2].packet_type(), RTCP_BYE);
+    }
+
+    #[test]
+    fn parses_padding_app_and_unknown_types_and_reports_errors() {
+        // Padded RR without blocks, APP "abcd", and an RTPFB of an
+        // unknown type.
+        let mut compound = words(&[0xA0C9_0002, 9, 4]);
+        compound.extend(words(&[0x83CC_0003, 9, u32::from_be_bytes(*b"abcd"), 5]));
+        compound.extend(words(&[0x8ACD_0002, 9, 10]));
+        let packets: Vec<_> = RtcpPacket::parse_compound(&compound)
+            .map(Result::unwrap)
+            .collect();
+        assert!(matches!(
+            &packets[0],
+            RtcpPacket::ReceiverReport(rr) if rr.ssrc == 9 && rr.reports.is_empty()
+        ));
+        assert_eq!(
+            packets[1],
+            RtcpPacket::App(AppPacket {
+                subtype: 3,
+                ssrc: 9,
+                name: *b"abcd",
+                data: &[0, 0, 0, 5],
+            })
+        );
+        assert_eq!(
+            packets[2],
+            RtcpPacket::Other {
+                packet_type: 205,
+                count: 10,
+                body: &compound[32..],
+            }
+        );
+
+        assert_eq!(
+            RtcpPacket::parse(&words(&[0x80C9_0005, 1])),
+            Err(RtcpError::InvalidLength {
+                words: 5,
+                offset: 2,
+                remaining: 4
+            })
+        );
+        let err = RtcpPacket::parse(&words(&[0x81C8_0006, 1, 0, 0, 0, 0, 0])).unwrap_err();
+        assert_eq!(err.offset(), 28);
+        assert_eq!(
+            err.to_string(),
+            "report blocks needs 24 bytes at offset 28, 0 available"
+        );
+        // A zero padding count in the second packet, and nothing after it.
+        let mut bad = words(&[0x80C9_0001, 9, 0xA0C9_0001, 0]);
+        bad.extend(words(&[0x80C9_0001, 9]));
+        let results: Vec<_> = RtcpPacket::parse_compound(&bad).collect();
+        assert_eq!(
+            results[1..],
+            [Err(RtcpError::InvalidPadding {
+                length: 0,
+                offset: 12,
+                remaining: 4
+            })]
+        );
+    }
+
+    #[test]
+    fn validates_compound_packets() {
+        let rr = words(&[0x80C9_0001, 9]);
+        let sdes = words(&[0x81CA_0002, 9, 0x0100_0000]);
+        let pli = words(&[0x81CE_0002, 9, 3]);
+        let compound = [&rr[..], &sdes, &pli].concat();
+        let parsed = CompoundRtcp::parse(&compound).unwrap();
+        let types: Vec<_> = parsed.packets().map(|p| p.packet_type()).collect();
+        assert_eq!(types, [RTCP_RR, RTCP_SDES, RTCP_PSFB]);
+
+        let feedback_first = [&pli[..], &rr].concat();
+        assert_eq!(
+            CompoundRtcp::parse(&feedback_first),
+            Err(RtcpError::Invalid {
+                field: "first packet type",
+                offset: 1
+            })
+        );
+        assert!(CompoundRtcp::parse_reduced_size(&feedback_first).is_ok());
+
+        // Padding is only allowed on the last packet.
+        let padded = words(&[0xA0C9_0002, 9, 4]);
+        assert!(CompoundRtcp::parse(&[&rr[..], &padded].concat()).is_ok());
+        assert_eq!(
+            CompoundRtcp::parse(&[&padded[..], &rr].concat())
+                .unwrap_err()
+                .offset(),
+            0
+        );
+        // A truncated trailing packet.
+        let err = CompoundRtcp::parse(&[&rr[..], &sdes[..8]].concat()).unwrap_err();
+        assert_eq!(
+            err,
+            RtcpError::InvalidLength {
+                words: 2,
+                offset: 10,
+                remaining: 4
+            }
+        );
+        assert!(CompoundRtcp::parse(&[]).is_err());
+    }
+}
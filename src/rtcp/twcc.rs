@@ -0,0 +1,194 @@
+use super::{word, RtcpError};
+
+// Reference time unit, and receive delta unit, in microseconds.
+const REFERENCE_TIME_US: i64 = 64_000;
+const DELTA_US: i64 = 250;
+
+/// Transport-wide congestion control feedback
+/// (draft-holmer-rmcat-transport-wide-cc-extensions-01 section 3.1): which
+/// packets, by transport-wide sequence number, arrived and when.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportFeedback<'a> {
+    pub sender_ssrc: u32,
+    pub media_ssrc: u32,
+    pub base_sequence: u16,
+    pub status_count: u16,
+    /// Arrival time base in multiples of 64 ms, 24-bit signed.
+    pub reference_time: i32,
+    /// Counts the feedback packets sent, to detect lost ones.
+    pub feedback_count: u8,
+    chunks: &'a [u8],
+    deltas: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PacketStatus {
+    NotReceived,
+    /// Received within 0 to 63.75 ms of the previous packet.
+    SmallDelta,
+    /// Received with a larger or negative delta.
+    LargeDelta,
+}
+
+/// Status of one packet of a [`TransportFeedback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PacketReport {
+    pub sequence: u16,
+    pub status: PacketStatus,
+    /// Arrival on the receiver's clock in microseconds, from the reference
+    /// time and the receive deltas; `None` if not received.
+    pub arrival_us: Option<i64>,
+}
+
+// Status symbols of a packet chunk: a run of one symbol, or a vector of
+// 14 one-bit or 7 two-bit symbols.
+fn chunk_symbols(chunk: u16) -> impl Iterator<Item = u8> {
+    let count = match chunk >> 14 {
+        0 | 1 => chunk & 0x1FFF,
+        2 => 14,
+        _ => 7,
+    };
+    (0..count).map(move |i| match chunk >> 14 {
+        0 | 1 => (chunk >> 13) as u8 & 0x03,
+        2 => (chunk >> (13 - i)) as u8 & 0x01,
+        _ => (chunk >> (12 - 2 * i)) as u8 & 0x03,
+    })
+}
+
+impl<'a> TransportFeedback<'a> {
+    pub(super) fn parse(body: &'a [u8]) -> Result<Self, RtcpError> {
+        let too_short =
+            |field, at, needed| RtcpError::too_short(body, field, at, needed).shifted(4);
+        if body.len() < 16 {
+            return Err(too_short("transport feedback header", 0, 16));
+        }
+        let status_count = u16::from_be_bytes([body[10], body[11]]);
+        // Walk the chunks for the length of the receive deltas.
+        let mut at = 16;
+        let mut covered = 0;
+        let mut deltas_len = 0;
+        while covered < status_count as usize {
+            let chunk = body
+                .get(at..at + 2)
+                .ok_or_else(|| too_short("packet chunk", at, 2))?;
+            let chunk = u16::from_be_bytes([chunk[0], chunk[1]]);
+            for symbol in chunk_symbols(chunk).take(status_count as usize - covered) {
+                deltas_len += match symbol {
+                    0 => 0,
+                    1 => 1,
+                    2 => 2,
+                    _ => {
+                        return Err(RtcpError::Invalid {
+                            field: "packet status",
+                            offset: 4 + at,
+                        })
+                    }
+                };
+                covered += 1;
+            }
+            at += 2;
+        }
+        let deltas = body
+            .get(at..at + deltas_len)
+            .ok_or_else(|| too_short("receive deltas", at, deltas_len))?;
+        Ok(Self {
+            sender_ssrc: word(body, 0),
+            media_ssrc: word(body, 4),
+            base_sequence: u16::from_be_bytes([body[8], body[9]]),
+            status_count,
+            // Sign-extend the 24-bit field.
+            reference_time: (word(body, 12) as i32) >> 8,
+            feedback_count: body[15],
+            chunks: &body[16..at],
+            deltas,
+        })
+    }
+
+    /// The reports of the `status_count` packets from `base_sequence` on.
+    pub fn packets(&self) -> impl Iterator<Item = PacketReport> + 'a {
+        let base = self.base_sequence;
+        let mut deltas = self.deltas;
+        let mut time = self.reference_time as i64 * REFERENCE_TIME_US;
+        self.chunks
+            .chunks_exact(2)
+            .flat_map(|c| chunk_symbols(u16::from_be_bytes([c[0], c[1]])))
+            .take(self.status_count as usize)
+            .enumerate()
+            .map(move |(i, symbol)| {
+                let (status, delta) = match symbol {
+                    1 => {
+                        let delta = deltas[0] as i64;
+                        deltas = &deltas[1..];
+                        (PacketStatus::SmallDelta, Some(delta))
+                    }
+                    2 => {
+                        let delta = i16::from_be_bytes([deltas[0], deltas[1]]) as i64;
+                        deltas = &deltas[2..];
+                        (PacketStatus::LargeDelta, Some(delta))
+                    }
+                    _ => (PacketStatus::NotReceived, None),
+                };
+                let arrival_us = delta.map(|delta| {
+                    time += delta * DELTA_US;
+                    time
+                });
+                PacketReport {
+                    sequence: base.wrapping_add(i as u16),
+                    status,
+                    arrival_us,
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtcp::RtcpPacket;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn expands_chunks_into_arrival_times() {
+        let mut packet = [0x8F, 205, 0, 7, 0, 0, 0, 1, 0, 0, 0, 2].to_vec();
+        // Ten packets from 65534, reference time -64 ms, feedback 3.
+        packet.extend([0xFF, 0xFE, 0, 10, 0xFF, 0xFF, 0xFF, 3]);
+        // A run of two small deltas; two-bit symbols large, missing, small,
+        // then four missing; one-bit symbols small, then unused.
+        packet.extend([0x20, 0x02, 0xE1, 0x00, 0xA0, 0x00]);
+        packet.extend([4, 8, 0xFF, 0xFC, 255, 20]);
+        let RtcpPacket::TransportFeedback(feedback) = RtcpPacket::parse(&packet).unwrap() else {
+            panic!();
+        };
+        assert_eq!((feedback.reference_time, feedback.feedback_count), (-1, 3));
+        let received: Vec<_> = feedback
+            .packets()
+            .filter_map(|p| Some((p.sequence, p.arrival_us?)))
+            .collect();
+        assert_eq!(
+            received,
+            [
+                (65534, -63_000),
+                (65535, -61_000),
+                (0, -62_000),
+                (2, 1_750),
+                (7, 6_750)
+            ]
+        );
+        let statuses: Vec<_> = feedback.packets().map(|p| p.status).collect();
+        assert_eq!(statuses.len(), 10);
+        assert_eq!(statuses[2], PacketStatus::LargeDelta);
+        assert_eq!(statuses[3], PacketStatus::NotReceived);
+
+        // The reserved status symbol 3.
+        packet[20] = 0x60;
+        assert_eq!(
+            RtcpPacket::parse(&packet),
+            Err(RtcpError::Invalid {
+                field: "packet status",
+                offset: 20
+            })
+        );
+    }
+}
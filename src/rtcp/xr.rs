@@ -0,0 +1,346 @@
+use super::{word, RtcpError};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+/// RTCP packet type of extended reports (RFC 3611).
+pub const RTCP_XR: u8 = 207;
+
+const BT_LOSS_RLE: u8 = 1;
+const BT_RRTR: u8 = 4;
+const BT_DLRR: u8 = 5;
+const BT_VOIP_METRICS: u8 = 7;
+
+/// Extended report: report blocks from `ssrc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XrPacket<'a> {
+    pub ssrc: u32,
+    blocks: &'a [u8],
+}
+
+impl<'a> XrPacket<'a> {
+    pub(super) fn parse(body: &'a [u8]) -> Result<Self, RtcpError> {
+        if body.len() < 4 {
+            return Err(RtcpError::too_short(body, "XR SSRC", 0, 4).shifted(4));
+        }
+        let mut at = 4;
+        while at < body.len() {
+            let (_, len) = XrBlock::parse(&body[at..]).map_err(|e| e.shifted(4 + at))?;
+            at += len;
+        }
+        Ok(Self {
+            ssrc: word(body, 0),
+            blocks: &body[4..],
+        })
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = XrBlock<'a>> + 'a {
+        let mut rest = self.blocks;
+        core::iter::from_fn(move || {
+            let (block, len) = XrBlock::parse(rest).ok()?;
+            rest = &rest[len..];
+            Some(block)
+        })
+    }
+}
+
+/// Report block of an [`XrPacket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XrBlock<'a> {
+    LossRle(LossRle<'a>),
+    /// Receiver reference time: when a receiver that sends no SR sent
+    /// this report, 32.32 fixed point NTP, echoed back in a [`Dlrr`].
+    ReceiverReferenceTime {
+        ntp_timestamp: u64,
+    },
+    Dlrr(Dlrr<'a>),
+    VoipMetrics(VoipMetrics),
+    /// A block of another type, with the type-specific byte and the data
+    /// after the block header.
+    Other {
+        block_type: u8,
+        type_specific: u8,
+        data: &'a [u8],
+    },
+}
+
+impl<'a> XrBlock<'a> {
+    // Returns the block at the start of `buf` and its length.
+    fn parse(buf: &'a [u8]) -> Result<(Self, usize), RtcpError> {
+        let header = buf
+            .get(..4)
+            .ok_or_else(|| RtcpError::too_short(buf, "XR block header", 0, 4))?;
+        let (block_type, type_specific) = (header[0], header[1]);
+        let len = 4 + u16::from_be_bytes([header[2], header[3]]) as usize * 4;
+        let data = buf
+            .get(4..len)
+            .ok_or_else(|| RtcpError::too_short(buf, "XR block", 4, len - 4))?;
+        let needs = |needed: usize| {
+            if data.len() < needed {
+                return Err(RtcpError::too_short(data, "XR block", 0, needed).shifted(4));
+            }
+            Ok(())
+        };
+        let block = match block_type {
+            BT_LOSS_RLE => {
+                needs(8)?;
+                XrBlock::LossRle(LossRle {
+                    ssrc: word(data, 0),
+                    thinning: type_specific & 0x0F,
+                    begin_seq: u16::from_be_bytes([data[4], data[5]]),
+                    end_seq: u16::from_be_bytes([data[6], data[7]]),
+                    chunks: &data[8..],
+                })
+            }
+            BT_RRTR => {
+                needs(8)?;
+                XrBlock::ReceiverReferenceTime {
+                    ntp_timestamp: (word(data, 0) as u64) << 32 | word(data, 4) as u64,
+                }
+            }
+            BT_DLRR => {
+                if data.len() % 12 != 0 {
+                    let at = data.len() - data.len() % 12;
+                    return Err(RtcpError::too_short(data, "DLRR sub-block", at, 12).shifted(4));
+                }
+                XrBlock::Dlrr(Dlrr { items: data })
+            }
+            BT_VOIP_METRICS => {
+                needs(32)?;
+                XrBlock::VoipMetrics(VoipMetrics::read(data))
+            }
+            _ => XrBlock::Other {
+                block_type,
+                type_specific,
+                data,
+            },
+        };
+        Ok((block, len))
+    }
+}
+
+/// Loss run-length encoding: which of the packets from `begin_seq` up to,
+/// but excluding, `end_seq` arrived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LossRle<'a> {
+    pub ssrc: u32,
+    /// Only every 2^`thinning`th sequence number is reported.
+    pub thinning: u8,
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    chunks: &'a [u8],
+}
+
+impl<'a> LossRle<'a> {
+    /// The reported sequence numbers and whether each arrived.
+    pub fn packets(&self) -> impl Iterator<Item = (u16, bool)> + 'a {
+        let step = 1u16 << self.thinning;
+        let span = self.end_seq.wrapping_sub(self.begin_seq) as usize;
+        let begin = self.begin_seq;
+        self.chunks
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            // A null chunk pads the block.
+            .take_while(|&chunk| chunk != 0)
+            .flat_map(|chunk| {
+                // A run of 14-bit length, or a vector of 15 bits.
+                let vector = chunk & 0x8000 != 0;
+                let len = if vector { 15 } else { chunk & 0x3FFF };
+                (0..len).map(move |i| match vector {
+                    true => chunk >> (14 - i) & 1 != 0,
+                    false => chunk & 0x4000 != 0,
+                })
+            })
+            .take(span.div_ceil(step as usize))
+            .enumerate()
+            .map(move |(i, received)| (begin.wrapping_add((i as u16).wrapping_mul(step)), received))
+    }
+}
+
+/// Delay since the last receiver reference time of each receiver, sent
+/// back so it can measure the round-trip time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dlrr<'a> {
+    items: &'a [u8],
+}
+
+impl<'a> Dlrr<'a> {
+    pub fn items(&self) -> impl Iterator<Item = DlrrItem> + 'a {
+        self.items.chunks_exact(12).map(|item| DlrrItem {
+            ssrc: word(item, 0),
+            last_rr: word(item, 4),
+            delay_since_last_rr: word(item, 8),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DlrrItem {
+    /// The receiver the item is for.
+    pub ssrc: u32,
+    /// Middle 32 bits of its last receiver reference time (LRR).
+    pub last_rr: u32,
+    /// Time since that report in 1/65536 s (DLRR).
+    pub delay_since_last_rr: u32,
+}
+
+impl DlrrItem {
+    /// Round-trip time to the sender of this item, given the NTP time the
+    /// packet arrived at (RFC 3611 section 4.5); `None` without a reference
+    /// time or if the clocks make it negative.
+    pub fn round_trip_time(&self, arrival_ntp: u64) -> Option<Duration> {
+        if self.last_rr == 0 {
+            return None;
+        }
+        let compact = (arrival_ntp >> 16) as u32;
+        let rtt = compact
+            .wrapping_sub(self.last_rr)
+            .wrapping_sub(self.delay_since_last_rr);
+        if rtt > i32::MAX as u32 {
+            return None;
+        }
+        Some(Duration::from_nanos((rtt as u64 * 1_000_000_000) >> 16))
+    }
+}
+
+/// VoIP call quality metrics (RFC 3611 section 4.7), mostly as sent:
+/// rates and densities in 1/256, durations and delays in milliseconds,
+/// levels in dB, and 127 for unavailable values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VoipMetrics {
+    pub ssrc: u32,
+    pub loss_rate: u8,
+    pub discard_rate: u8,
+    pub burst_density: u8,
+    pub gap_density: u8,
+    pub burst_duration: u16,
+    pub gap_duration: u16,
+    pub round_trip_delay: u16,
+    pub end_system_delay: u16,
+    /// Signal level in dBm.
+    pub signal_level: i8,
+    /// Noise level in dBm.
+    pub noise_level: i8,
+    /// Residual echo return loss.
+    pub rerl: u8,
+    pub gmin: u8,
+    pub r_factor: u8,
+    pub ext_r_factor: u8,
+    /// Listening and conversational quality MOS, times 10.
+    pub mos_lq: u8,
+    pub mos_cq: u8,
+    /// Packet loss concealment and jitter buffer configuration flags.
+    pub rx_config: u8,
+    pub jb_nominal: u16,
+    pub jb_maximum: u16,
+    pub jb_abs_max: u16,
+}
+
+impl VoipMetrics {
+    fn read(data: &[u8]) -> Self {
+        let u16_at = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+        Self {
+            ssrc: word(data, 0),
+            loss_rate: data[4],
+            discard_rate: data[5],
+            burst_density: data[6],
+            gap_density: data[7],
+            burst_duration: u16_at(8),
+            gap_duration: u16_at(10),
+            round_trip_delay: u16_at(12),
+            end_system_delay: u16_at(14),
+            signal_level: data[16] as i8,
+            noise_level: data[17] as i8,
+            rerl: data[18],
+            gmin: data[19],
+            r_factor: data[20],
+            ext_r_factor: data[21],
+            mos_lq: data[22],
+            mos_cq: data[23],
+            rx_config: data[24],
+            jb_nominal: u16_at(26),
+            jb_maximum: u16_at(28),
+            jb_abs_max: u16_at(30),
+        }
+    }
+}
+
+/// Serializes an XR from `ssrc` with a receiver reference time block, for
+/// receivers that send no SR to learn their round-trip time from the DLRR
+/// answer.
+pub fn receiver_reference_time(ssrc: u32, ntp_timestamp: u64) -> Vec<u8> {
+    let mut out = Vec::with_capacity(20);
+    out.extend_from_slice(&[0x80, RTCP_XR, 0, 4]);
+    out.extend_from_slice(&ssrc.to_be_bytes());
+    out.extend_from_slice(&[BT_RRTR, 0, 0, 2]);
+    out.extend_from_slice(&ntp_timestamp.to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtcp::RtcpPacket;
+
+    #[test]
+    fn parses_loss_rle_dlrr_and_voip_metrics() {
+        let mut packet = [0x80, RTCP_XR, 0, 18, 0, 0, 0, 1].to_vec();
+        // Every second packet from 100 to 110: a run of two received, then
+        // received, lost, received.
+        packet.extend([BT_LOSS_RLE, 1, 0, 3, 0, 0, 0, 2, 0, 100, 0, 110]);
+        packet.extend([0x40, 0x02, 0xD0, 0x00]);
+        // The RRTR of SSRC 7 at 1.5 s, answered after 0.5 s.
+        packet.extend([BT_DLRR, 0, 0, 3, 0, 0, 0, 7, 0, 1, 0x80, 0, 0, 0, 0x80, 0]);
+        packet.extend([BT_VOIP_METRICS, 0, 0, 8, 0, 0, 0, 2, 12, 0, 0, 0]);
+        packet.extend([0; 8]);
+        packet.extend([0xEC, 127, 0, 0, 0, 0, 41, 39, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let RtcpPacket::ExtendedReport(xr) = RtcpPacket::parse(&packet).unwrap() else {
+            panic!();
+        };
+        let blocks: Vec<_> = xr.blocks().collect();
+        let [XrBlock::LossRle(rle), XrBlock::Dlrr(dlrr), XrBlock::VoipMetrics(voip)] = &blocks[..]
+        else {
+            panic!("{:?}", blocks);
+        };
+        assert_eq!(
+            rle.packets().collect::<Vec<_>>(),
+            [
+                (100, true),
+                (102, true),
+                (104, true),
+                (106, false),
+                (108, true)
+            ]
+        );
+        let item = dlrr.items().next().unwrap();
+        assert_eq!(item.ssrc, 7);
+        let arrival = 0x0002_8000u64 << 16;
+        assert_eq!(
+            item.round_trip_time(arrival),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(item.round_trip_time(0x0001_0000 << 16), None);
+        assert_eq!((voip.loss_rate, voip.signal_level), (12, -20));
+        assert_eq!((voip.mos_lq, voip.mos_cq), (41, 39));
+
+        let rrtr = receiver_reference_time(3, 0x1_8000_0000);
+        let Ok(RtcpPacket::ExtendedReport(xr)) = RtcpPacket::parse(&rrtr) else {
+            panic!();
+        };
+        assert_eq!(
+            xr.blocks().collect::<Vec<_>>(),
+            [XrBlock::ReceiverReferenceTime {
+                ntp_timestamp: 0x1_8000_0000
+            }]
+        );
+
+        // The DLRR block grows by a third of a sub-block.
+        packet[27] = 4;
+        assert_eq!(
+            RtcpPacket::parse(&packet).unwrap_err().to_string(),
+            "DLRR sub-block needs 12 bytes at offset 40, 4 available"
+        );
+    }
+}
@@ -1,4 +1,13 @@
+use crate::limits::{Limit, LimitExceeded, Limits};
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+/// Maximum number of CSRCs an RTP header can carry (4-bit CC field).
+pub const MAX_CSRCS: usize = 15;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtpHeader {
     pub version: u8,
     pub padding: bool,
@@ -9,11 +18,13 @@ pub struct RtpHeader {
     pub sequence_number: u16,
     pub timestamp: u32,
     pub ssrc: u32,
-    pub csrcs: Vec<u32>,
+    pub csrcs: CsrcList,
     pub extension_header: Option<RtpExtension>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtpExtension {
     pub profile: u16,
     pub length_words: u16,
@@ -21,6 +32,113 @@ pub struct RtpExtension {
     pub data_len: usize,
 }
 
+impl RtpExtension {
+    /// Profile of the RFC 8285 one-byte header extension form.
+    pub const ONE_BYTE_PROFILE: u16 = 0xBEDE;
+    /// Profile of the RFC 8285 two-byte form, with the appbits cleared.
+    pub const TWO_BYTE_PROFILE: u16 = 0x1000;
+
+    /// Extension block within `packet`, the buffer this header was parsed from.
+    pub fn data<'a>(&self, packet: &'a [u8]) -> &'a [u8] {
+        packet
+            .get(self.data_offset..self.data_offset + self.data_len)
+            .unwrap_or(&[])
+    }
+
+    /// The 4 application-dependent bits of a two-byte form block.
+    pub fn appbits(&self) -> Option<u8> {
+        self.is_two_byte().then_some((self.profile & 0x0F) as u8)
+    }
+
+    fn is_two_byte(&self) -> bool {
+        self.profile & 0xFFF0 == Self::TWO_BYTE_PROFILE
+    }
+
+    /// RFC 8285 elements of the block; empty unless it uses the one-byte or
+    /// two-byte form.
+    pub fn elements<'a>(&self, packet: &'a [u8]) -> ExtensionElements<'a> {
+        let two_byte = self.is_two_byte();
+        let rest = if two_byte || self.profile == Self::ONE_BYTE_PROFILE {
+            self.data(packet)
+        } else {
+            &[]
+        };
+        ExtensionElements { rest, two_byte }
+    }
+}
+
+/// Header extension element, e.g. the MID of a bundled stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionElement<'a> {
+    /// Local identifier negotiated with `a=extmap`.
+    pub id: u8,
+    pub data: &'a [u8],
+}
+
+/// Iterator over the elements of a header extension block, stopping at the
+/// first malformed one.
+#[derive(Debug, Clone)]
+pub struct ExtensionElements<'a> {
+    rest: &'a [u8],
+    two_byte: bool,
+}
+
+impl<'a> ExtensionElements<'a> {
+    // Two-byte form: id byte (0 is padding), length byte, then 0 to 255
+    // bytes of data.
+    fn next_two_byte(&mut self) -> Option<ExtensionElement<'a>> {
+        loop {
+            let (&id, tail) = self.rest.split_first()?;
+            if id == 0 {
+                self.rest = tail;
+                continue;
+            }
+            let element = tail.split_first().and_then(|(&len, tail)| {
+                let data = tail.get(..len as usize)?;
+                Some((data, &tail[len as usize..]))
+            });
+            let Some((data, rest)) = element else {
+                self.rest = &[];
+                return None;
+            };
+            self.rest = rest;
+            return Some(ExtensionElement { id, data });
+        }
+    }
+}
+
+impl<'a> Iterator for ExtensionElements<'a> {
+    type Item = ExtensionElement<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.two_byte {
+            return self.next_two_byte();
+        }
+        loop {
+            let (&b, tail) = self.rest.split_first()?;
+            self.rest = tail;
+            match b >> 4 {
+                // Padding byte.
+                0 if b == 0 => continue,
+                // Reserved id 15 ends processing of the block.
+                0 | 15 => {
+                    self.rest = &[];
+                    return None;
+                }
+                id => {
+                    let len = (b & 0x0F) as usize + 1;
+                    let Some(data) = tail.get(..len) else {
+                        self.rest = &[];
+                        return None;
+                    };
+                    self.rest = &tail[len..];
+                    return Some(ExtensionElement { id, data });
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RtpPacket<'a> {
     pub header: RtpHeader,
@@ -28,29 +146,284 @@ pub struct RtpPacket<'a> {
     pub payload: &'a [u8],
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// CSRC list stored inline, so parsing a header never allocates.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<u32>", into = "Vec<u32>")
+)]
+pub struct CsrcList {
+    len: u8,
+    items: [u32; MAX_CSRCS],
+}
+
+/// More than [`MAX_CSRCS`] CSRCs were supplied; holds the offending count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManyCsrcs(pub usize);
+
+impl core::fmt::Display for TooManyCsrcs {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} csrcs exceed the limit of {}", self.0, MAX_CSRCS)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TooManyCsrcs {}
+
+impl CsrcList {
+    pub const fn new() -> Self {
+        Self {
+            len: 0,
+            items: [0; MAX_CSRCS],
+        }
+    }
+
+    /// Appends a CSRC; fails once the list holds [`MAX_CSRCS`] entries.
+    pub fn push(&mut self, csrc: u32) -> Result<(), TooManyCsrcs> {
+        let len = self.len as usize;
+        if len == MAX_CSRCS {
+            return Err(TooManyCsrcs(len + 1));
+        }
+        self.items[len] = csrc;
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn as_slice(&self) -> &[u32] {
+        &self.items[..self.len as usize]
+    }
+}
+
+impl Deref for CsrcList {
+    type Target = [u32];
+
+    fn deref(&self) -> &[u32] {
+        self.as_slice()
+    }
+}
+
+impl core::fmt::Debug for CsrcList {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+impl PartialEq for CsrcList {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Eq for CsrcList {}
+
+impl<const N: usize> PartialEq<[u32; N]> for CsrcList {
+    fn eq(&self, other: &[u32; N]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl TryFrom<&[u32]> for CsrcList {
+    type Error = TooManyCsrcs;
+
+    fn try_from(csrcs: &[u32]) -> Result<Self, Self::Error> {
+        if csrcs.len() > MAX_CSRCS {
+            return Err(TooManyCsrcs(csrcs.len()));
+        }
+        let mut list = Self::new();
+        list.items[..csrcs.len()].copy_from_slice(csrcs);
+        list.len = csrcs.len() as u8;
+        Ok(list)
+    }
+}
+
+impl TryFrom<Vec<u32>> for CsrcList {
+    type Error = TooManyCsrcs;
+
+    fn try_from(csrcs: Vec<u32>) -> Result<Self, Self::Error> {
+        Self::try_from(csrcs.as_slice())
+    }
+}
+
+impl From<CsrcList> for Vec<u32> {
+    fn from(list: CsrcList) -> Self {
+        list.as_slice().to_vec()
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for CsrcList {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let len = u.int_in_range(0..=MAX_CSRCS)?;
+        let mut list = Self::new();
+        for _ in 0..len {
+            // Cannot overflow: `len` is at most MAX_CSRCS.
+            let _ = list.push(u.arbitrary()?);
+        }
+        Ok(list)
+    }
+}
+
+/// Header parse failure, with the byte offset it was detected at.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RtpError {
-    BufferTooShort,
+    /// `field` needs `needed` bytes at `offset`, but only `available` remain.
+    BufferTooShort {
+        field: &'static str,
+        offset: usize,
+        needed: usize,
+        available: usize,
+    },
     InvalidVersion(u8),
-    InvalidExtensionLength,
+    /// The extension claims `words` 32-bit words starting at `offset`, but
+    /// only `remaining` bytes follow.
+    InvalidExtensionLength {
+        words: u16,
+        offset: usize,
+        remaining: usize,
+    },
+    /// The padding count in the last byte is zero or exceeds the `remaining`
+    /// bytes after the header, which ends at `offset`.
+    InvalidPadding {
+        length: u8,
+        offset: usize,
+        remaining: usize,
+    },
+    /// A field starting at `offset` went past the configured [`Limits`].
+    LimitExceeded {
+        offset: usize,
+        exceeded: LimitExceeded,
+    },
+}
+
+impl RtpError {
+    /// Byte offset into the packet where parsing failed.
+    pub fn offset(&self) -> usize {
+        match self {
+            RtpError::BufferTooShort { offset, .. }
+            | RtpError::InvalidExtensionLength { offset, .. }
+            | RtpError::InvalidPadding { offset, .. }
+            | RtpError::LimitExceeded { offset, .. } => *offset,
+            RtpError::InvalidVersion(_) => 0,
+        }
+    }
+
+    fn too_short(buf: &[u8], field: &'static str, offset: usize, needed: usize) -> Self {
+        RtpError::BufferTooShort {
+            field,
+            offset,
+            needed,
+            available: buf.len().saturating_sub(offset),
+        }
+    }
 }
 
 impl core::fmt::Display for RtpError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            RtpError::BufferTooShort => write!(f, "buffer too short"),
+            RtpError::BufferTooShort {
+                field,
+                offset,
+                needed,
+                available,
+            } => write!(
+                f,
+                "{} needs {} bytes at offset {}, {} available",
+                field, needed, offset, available
+            ),
             RtpError::InvalidVersion(v) => write!(f, "invalid rtp version {}", v),
-            RtpError::InvalidExtensionLength => write!(f, "invalid header extension length"),
+            RtpError::InvalidExtensionLength {
+                words,
+                offset,
+                remaining,
+            } => write!(
+                f,
+                "extension length {} words exceeds remaining {} bytes at offset {}",
+                words, remaining, offset
+            ),
+            RtpError::InvalidPadding {
+                length,
+                offset,
+                remaining,
+            } => write!(
+                f,
+                "padding length {} exceeds remaining {} bytes at offset {}",
+                length, remaining, offset
+            ),
+            RtpError::LimitExceeded { offset, exceeded } => {
+                write!(f, "{} at offset {}", exceeded, offset)
+            }
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for RtpError {}
 
+impl RtpHeader {
+    /// Length of the fixed header plus CSRC list as written by [`RtpHeader::write`].
+    pub fn serialized_len(&self) -> usize {
+        12 + 4 * self.csrcs.len()
+    }
+
+    /// Appends the fixed header and CSRC list to `out`.
+    ///
+    /// The header owns no extension or padding bytes, so the X and P bits are
+    /// written as zero and the CC field reflects `csrcs`.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        let b0 = ((self.version & 0x03) << 6) | (self.csrcs.len() as u8 & 0x0F);
+        let mut b1 = self.payload_type & 0x7F;
+        if self.marker {
+            b1 |= 0x80;
+        }
+        out.push(b0);
+        out.push(b1);
+        out.extend_from_slice(&self.sequence_number.to_be_bytes());
+        out.extend_from_slice(&self.timestamp.to_be_bytes());
+        out.extend_from_slice(&self.ssrc.to_be_bytes());
+        for csrc in self.csrcs.iter() {
+            out.extend_from_slice(&csrc.to_be_bytes());
+        }
+    }
+}
+
 impl<'a> RtpPacket<'a> {
     pub fn parse(buf: &'a [u8]) -> Result<RtpPacket<'a>, RtpError> {
+        let result = Self::parse_inner(buf);
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(pkt) => trace_event!(
+                TRACE,
+                ssrc = pkt.header.ssrc,
+                seq = pkt.header.sequence_number,
+                timestamp = pkt.header.timestamp,
+                payload_len = pkt.payload.len(),
+                "parsed rtp packet"
+            ),
+            Err(e) => trace_event!(DEBUG, len = buf.len(), error = %e, "rtp parse failed"),
+        }
+        result
+    }
+
+    /// Like [`parse`](Self::parse), but rejects a header with more CSRCs or
+    /// a longer extension block than `limits` allow.
+    pub fn parse_with_limits(buf: &'a [u8], limits: &Limits) -> Result<RtpPacket<'a>, RtpError> {
+        let pkt = Self::parse(buf)?;
+        let exceeded = |offset| move |exceeded| RtpError::LimitExceeded { offset, exceeded };
+        limits
+            .check(Limit::Csrcs, pkt.header.csrcs.len())
+            .map_err(exceeded(0))?;
+        if let Some(ext) = &pkt.header.extension_header {
+            limits
+                .check(Limit::ExtensionBytes, ext.data_len)
+                .map_err(exceeded(ext.data_offset - 4))?;
+        }
+        Ok(pkt)
+    }
+
+    fn parse_inner(buf: &'a [u8]) -> Result<RtpPacket<'a>, RtpError> {
         if buf.len() < 12 {
-            return Err(RtpError::BufferTooShort);
+            return Err(RtpError::too_short(buf, "fixed header", 0, 12));
         }
         let b0 = buf[0];
         let version = (b0 >> 6) & 0x03;
@@ -70,12 +443,13 @@ impl<'a> RtpPacket<'a> {
         let ssrc = u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]);
 
         let mut offset = 12usize;
-        let mut csrcs = Vec::new();
+        let mut csrcs = CsrcList::new();
         for _ in 0..csrc_count {
             if buf.len() < offset + 4 {
-                return Err(RtpError::BufferTooShort);
+                return Err(RtpError::too_short(buf, "csrc", offset, 4));
             }
-            csrcs.push(u32::from_be_bytes([
+            // CC is a 4-bit field, so the list cannot overflow.
+            let _ = csrcs.push(u32::from_be_bytes([
                 buf[offset],
                 buf[offset + 1],
                 buf[offset + 2],
@@ -87,14 +461,18 @@ impl<'a> RtpPacket<'a> {
         let mut extension_header = None;
         if extension {
             if buf.len() < offset + 4 {
-                return Err(RtpError::BufferTooShort);
+                return Err(RtpError::too_short(buf, "extension header", offset, 4));
             }
             let profile = u16::from_be_bytes([buf[offset], buf[offset + 1]]);
             let length_words = u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]);
             offset += 4;
             let ext_len_bytes = length_words as usize * 4;
             if buf.len() < offset + ext_len_bytes {
-                return Err(RtpError::InvalidExtensionLength);
+                return Err(RtpError::InvalidExtensionLength {
+                    words: length_words,
+                    offset,
+                    remaining: buf.len() - offset,
+                });
             }
             extension_header = Some(RtpExtension {
                 profile,
@@ -109,13 +487,17 @@ impl<'a> RtpPacket<'a> {
         let payload_end = if padding {
             // Last byte indicates number of padding octets including itself
             if buf.len() <= offset {
-                return Err(RtpError::BufferTooShort);
+                return Err(RtpError::too_short(buf, "padding count", offset, 1));
             }
-            let pad = *buf.last().unwrap() as usize;
-            if pad == 0 || pad > buf.len() - offset {
-                return Err(RtpError::BufferTooShort);
+            let pad = *buf.last().unwrap();
+            if pad == 0 || pad as usize > buf.len() - offset {
+                return Err(RtpError::InvalidPadding {
+                    length: pad,
+                    offset,
+                    remaining: buf.len() - offset,
+                });
             }
-            buf.len() - pad
+            buf.len() - pad as usize
         } else {
             buf.len()
         };
@@ -144,49 +526,53 @@ impl<'a> RtpPacket<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::RtpPacketBuilder;
 
-    fn build_basic_rtp(
-        v: u8,
-        p: bool,
-        x: bool,
-        cc: u8,
-        m: bool,
-        pt: u8,
-        seq: u16,
-        ts: u32,
-        ssrc: u32,
-        payload: &[u8],
-    ) -> Vec<u8> {
-        let mut b = Vec::new();
-        let mut b0 = (v & 0x03) << 6;
-        if p {
-            b0 |= 1 << 5;
-        }
-        if x {
-            b0 |= 1 << 4;
-        }
-        b0 |= cc & 0x0F;
-        b.push(b0);
-        let mut b1 = pt & 0x7F;
-        if m {
-            b1 |= 0x80;
-        }
-        b.push(b1);
-        b.extend_from_slice(&seq.to_be_bytes());
-        b.extend_from_slice(&ts.to_be_bytes());
-        b.extend_from_slice(&ssrc.to_be_bytes());
-        // No CSRCs
-        // No extension
-        b.extend_from_slice(payload);
-        b
+    #[test]
+    fn parse_with_limits_rejects_oversized_headers() {
+        let buf = RtpPacketBuilder::new()
+            .csrc(1)
+            .csrc(2)
+            .csrc(3)
+            .extension(0xBEDE, &[0; 16])
+            .payload(&[1])
+            .build();
+        let limits = Limits::default();
+        assert!(RtpPacket::parse_with_limits(&buf, &limits).is_ok());
+        let strict = Limits {
+            max_extension_bytes: 8,
+            ..limits
+        };
+        assert_eq!(
+            RtpPacket::parse_with_limits(&buf, &strict),
+            Err(RtpError::LimitExceeded {
+                offset: 24,
+                exceeded: LimitExceeded {
+                    limit: Limit::ExtensionBytes,
+                    value: 16,
+                    max: 8
+                }
+            })
+        );
+        let strict = Limits {
+            max_csrcs: 2,
+            ..limits
+        };
+        let err = RtpPacket::parse_with_limits(&buf, &strict).unwrap_err();
+        assert_eq!(err.to_string(), "3 csrcs exceed the limit of 2 at offset 0");
     }
 
     #[test]
     fn parse_basic_packet() {
         let payload = [1, 2, 3, 4, 5];
-        let buf = build_basic_rtp(
-            2, false, false, 0, true, 96, 1234, 0x11223344, 0x55667788, &payload,
-        );
+        let buf = RtpPacketBuilder::new()
+            .marker(true)
+            .payload_type(96)
+            .sequence_number(1234)
+            .timestamp(0x11223344)
+            .ssrc(0x55667788)
+            .payload(&payload)
+            .build();
         let pkt = RtpPacket::parse(&buf).unwrap();
         assert_eq!(pkt.header.version, 2);
         assert!(pkt.header.marker);
@@ -245,5 +631,143 @@ mod tests {
         assert_eq!(ext.profile, 0xBEDE);
         assert_eq!(ext.length_words, 2);
         assert_eq!(pkt.payload, &[9, 9, 9]);
+        // Element id 0 with a nonzero length is invalid and ends the block.
+        assert_eq!(ext.elements(&buf).count(), 0);
+    }
+
+    #[test]
+    fn iterates_one_byte_extension_elements() {
+        // id 1 "ab", padding, id 3 one byte, then a truncated id 2.
+        let buf = RtpPacketBuilder::new()
+            .extension(0xBEDE, &[0x11, b'a', b'b', 0, 0x30, 7, 0x25, 1])
+            .build();
+        let ext = RtpPacket::parse(&buf)
+            .unwrap()
+            .header
+            .extension_header
+            .unwrap();
+        let elements: Vec<_> = ext.elements(&buf).map(|e| (e.id, e.data)).collect();
+        assert_eq!(elements, [(1, &b"ab"[..]), (3, &[7][..])]);
+        assert_eq!(ext.data(&buf).len(), 8);
+        assert_eq!(ext.appbits(), None);
+    }
+
+    #[test]
+    fn iterates_two_byte_extension_elements() {
+        // Appbits 5. Id 1 "ab", padding, id 20 empty, id 3 with 16 bytes,
+        // then a truncated id 2.
+        let data = [
+            &[1, 2, b'a', b'b', 0, 0, 20, 0, 3, 16][..],
+            &[0xAA; 16],
+            &[2, 8, 9],
+        ]
+        .concat();
+        let buf = RtpPacketBuilder::new().extension(0x1005, &data).build();
+        let ext = RtpPacket::parse(&buf)
+            .unwrap()
+            .header
+            .extension_header
+            .unwrap();
+        assert_eq!(ext.appbits(), Some(5));
+        let elements: Vec<_> = ext.elements(&buf).map(|e| (e.id, e.data)).collect();
+        assert_eq!(
+            elements,
+            [(1, &b"ab"[..]), (20, &[][..]), (3, &[0xAA; 16][..])]
+        );
+    }
+
+    #[test]
+    fn errors_report_field_and_offset() {
+        let mut buf = RtpPacketBuilder::new()
+            .extension(0xBEDE, &[0x10, 0xFF, 0, 0])
+            .payload(&[0xAA; 16])
+            .build();
+        buf[14..16].copy_from_slice(&52u16.to_be_bytes());
+        let err = RtpPacket::parse(&buf).unwrap_err();
+        assert_eq!(
+            err,
+            RtpError::InvalidExtensionLength {
+                words: 52,
+                offset: 16,
+                remaining: 20
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "extension length 52 words exceeds remaining 20 bytes at offset 16"
+        );
+
+        let err = RtpPacket::parse(&buf[..14]).unwrap_err();
+        assert_eq!(err.offset(), 12);
+        assert_eq!(
+            err.to_string(),
+            "extension header needs 4 bytes at offset 12, 2 available"
+        );
+
+        let mut padded = RtpPacketBuilder::new().payload(&[1, 2]).build();
+        padded[0] |= 0x20;
+        padded[13] = 9;
+        assert!(matches!(
+            RtpPacket::parse(&padded),
+            Err(RtpError::InvalidPadding {
+                length: 9,
+                offset: 12,
+                remaining: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn csrc_list_holds_up_to_fifteen_entries() {
+        let all: Vec<u32> = (1..=15).collect();
+        let mut builder = RtpPacketBuilder::new();
+        for &csrc in &all {
+            builder = builder.csrc(csrc);
+        }
+        let buf = builder.build();
+        let pkt = RtpPacket::parse(&buf).unwrap();
+        assert_eq!(&*pkt.header.csrcs, all.as_slice());
+
+        let mut list = CsrcList::try_from(all.as_slice()).unwrap();
+        assert_eq!(list.push(16), Err(TooManyCsrcs(16)));
+        assert_eq!(
+            CsrcList::try_from(alloc::vec![0; 16]),
+            Err(TooManyCsrcs(16))
+        );
+        assert_eq!(list, pkt.header.csrcs);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn header_json_round_trip() {
+        let buf = RtpPacketBuilder::new()
+            .sequence_number(7)
+            .timestamp(90_000)
+            .ssrc(0xDEADBEEF)
+            .payload(&[1])
+            .build();
+        let pkt = RtpPacket::parse(&buf).unwrap();
+        let json = serde_json::to_string(&pkt.header).unwrap();
+        assert!(json.contains("\"sequence_number\":7"));
+        let back: RtpHeader = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, pkt.header);
+    }
+
+    #[test]
+    fn write_header_round_trips() {
+        let buf = RtpPacketBuilder::new()
+            .marker(true)
+            .payload_type(111)
+            .sequence_number(65535)
+            .timestamp(48_000)
+            .ssrc(9)
+            .csrc(1)
+            .csrc(2)
+            .build();
+        let header = RtpPacket::parse(&buf).unwrap().header;
+        let mut out = Vec::new();
+        header.write(&mut out);
+        assert_eq!(out.len(), header.serialized_len());
+        assert_eq!(out, buf);
     }
 }
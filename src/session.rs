@@ -0,0 +1,628 @@
+//! Handling many streams at once.
+//!
+//! [`Session`] takes the packets of a bundled transport (RFC 8843), where
+//! audio, video and screenshare share one socket, and routes them by the
+//! `sdes:mid` header extension to one [`StreamSet`] per m-line. Simulcast
+//! encodings are told apart by their RID (RFC 8852), and repair streams are
+//! tied to the encoding named by their repaired RID.
+//!
+//! Given the raw datagrams of an RTP/RTCP-multiplexed port through
+//! [`Session::push_datagram`], it also keeps per-SSRC receiver statistics,
+//! maps frames to the sender's wall clock from its sender reports and
+//! collects the NACKs and keyframe requests to send back.
+
+pub use crate::bwe::{remb, BandwidthUsage, ReceiveBandwidthEstimator};
+pub use crate::clock::{
+    duration_to_ntp, ntp_to_duration, MediaClock, MediaTime, TimestampUnwrapper,
+};
+#[cfg(feature = "std")]
+pub use crate::pipeline::{Pipeline, PipelineConfig, PipelineError, PipelineStats, ShardStats};
+pub use crate::quality::{QualityMonitor, QualityReport};
+pub use crate::rtcp::{ReportBlock, SenderReport};
+pub use crate::shard::{shard_index, StreamSet};
+#[cfg(feature = "std")]
+pub use crate::shard::{ShardedReassembler, StreamFrame};
+pub use crate::stats::{receiver_report, Histogram, NackList, Percentiles, ReceiverStats};
+pub use crate::sync::{PresentationTime, StreamSync};
+pub use crate::timeline::{Timeline, TimelineEntry, TimelineEvent, TimelineRecorder};
+
+use crate::{
+    codecs::Codec,
+    metrics::{Metrics, MetricsSink},
+    rtcp::{generic_nack, is_rtcp, pli, RtcpPacket},
+    rtp::{RtpError, RtpHeader, RtpPacket},
+};
+use alloc::{collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+use core::time::Duration;
+
+// Wall-clock mappings moving by more than this are flagged as drift.
+const DRIFT_THRESHOLD: Duration = Duration::from_millis(40);
+
+/// Demultiplexer for a bundled transport.
+///
+/// Packets carrying the MID extension are routed to the m-line they name,
+/// and their SSRC is learned so later packets without the extension follow.
+/// An SSRC that shows up with another MID moves there, dropping its old
+/// reassembler state.
+///
+/// A stream carrying `repaired-rtp-stream-id` repairs the encoding with that
+/// RID on the same m-line, whose SSRC need not be known yet. Its packets are
+/// unwrapped as RTX (RFC 4588) into the encoding's stream when their payload
+/// type was registered with [`add_rtx_payload_type`](Self::add_rtx_payload_type),
+/// and ignored otherwise (e.g. FEC).
+#[derive(Debug)]
+pub struct Session {
+    mid_extension: u8,
+    rid_extension: Option<u8>,
+    repaired_rid_extension: Option<u8>,
+    rtx_payload_types: Vec<u8>,
+    media: Vec<Media>,
+    ssrcs: BTreeMap<u32, Route>,
+    unrouted: u64,
+    // Per primary SSRC, for packets given to `push_datagram`.
+    stats: BTreeMap<u32, ReceiverStats>,
+    nacks: BTreeMap<u32, NackList>,
+    sync: StreamSync,
+    metrics: Metrics,
+}
+
+// Where the packets of a learned SSRC go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Route {
+    // Index into `Session::media`.
+    media: usize,
+    rid: Option<String>,
+    // `rid` is the repaired RID.
+    repair: bool,
+}
+
+// Identification extensions of one packet.
+#[derive(Default)]
+struct StreamIds<'a> {
+    mid: Option<&'a [u8]>,
+    rid: Option<&'a [u8]>,
+    repaired_rid: Option<&'a [u8]>,
+}
+
+#[derive(Debug)]
+struct Media {
+    mid: String,
+    streams: StreamSet,
+}
+
+/// Frame completed by one of the m-lines of a [`Session`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaFrame {
+    pub mid: String,
+    /// RID of the simulcast encoding, if the stream carried one.
+    pub rid: Option<String>,
+    pub ssrc: u32,
+    pub timestamp: u32,
+    /// Capture time on the sender's clock, once a sender report (from
+    /// [`Session::push_datagram`]) has mapped the stream.
+    pub presentation: Option<PresentationTime>,
+    pub data: Vec<u8>,
+}
+
+/// RTCP feedback a receiver should send, from [`Session::feedback`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Feedback {
+    /// Missing sequence numbers per media SSRC, for generic NACKs.
+    pub nacks: Vec<(u32, Vec<u16>)>,
+    /// SSRCs that need a keyframe, for PLIs.
+    pub keyframe_requests: Vec<u32>,
+}
+
+impl Feedback {
+    /// Serializes the feedback from `sender_ssrc` as generic NACKs and
+    /// PLIs, to follow a receiver report in a compound packet or go out on
+    /// their own with reduced-size RTCP (RFC 5506).
+    pub fn to_rtcp(&self, sender_ssrc: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (ssrc, lost) in &self.nacks {
+            out.extend(generic_nack(sender_ssrc, *ssrc, lost));
+        }
+        for &ssrc in &self.keyframe_requests {
+            out.extend(pli(sender_ssrc, ssrc));
+        }
+        out
+    }
+}
+
+impl Session {
+    /// `mid_extension` is the `a=extmap` id negotiated for
+    /// `urn:ietf:params:rtp-hdrext:sdes:mid`.
+    pub fn new(mid_extension: u8) -> Self {
+        Self {
+            mid_extension,
+            rid_extension: None,
+            repaired_rid_extension: None,
+            rtx_payload_types: Vec::new(),
+            media: Vec::new(),
+            ssrcs: BTreeMap::new(),
+            unrouted: 0,
+            stats: BTreeMap::new(),
+            nacks: BTreeMap::new(),
+            sync: StreamSync::new(DRIFT_THRESHOLD),
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Extmap id of `urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id`.
+    pub fn set_rid_extension(&mut self, id: u8) {
+        self.rid_extension = Some(id);
+    }
+
+    /// Extmap id of `urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id`.
+    pub fn set_repaired_rid_extension(&mut self, id: u8) {
+        self.repaired_rid_extension = Some(id);
+    }
+
+    /// Payload type of repair packets in RTX format.
+    pub fn add_rtx_payload_type(&mut self, payload_type: u8) {
+        if !self.rtx_payload_types.contains(&payload_type) {
+            self.rtx_payload_types.push(payload_type);
+        }
+    }
+
+    /// Reports unrouted, repair and RTCP packet counters to `sink`, and
+    /// hands it to the streams of every m-line, current and future.
+    pub fn set_metrics(&mut self, sink: Arc<dyn MetricsSink>) {
+        for media in &mut self.media {
+            media.streams.set_metrics(sink.clone());
+        }
+        self.metrics = Metrics::new(sink);
+    }
+
+    /// Adds the m-line `mid`, processed by `streams`; replaces the streams
+    /// of an existing one.
+    pub fn add_media(&mut self, mid: impl Into<String>, mut streams: StreamSet) {
+        if let Some(sink) = self.metrics.sink() {
+            streams.set_metrics(sink.clone());
+        }
+        let mid = mid.into();
+        match self.media.iter_mut().find(|m| m.mid == mid) {
+            Some(media) => media.streams = streams,
+            None => self.media.push(Media { mid, streams }),
+        }
+    }
+
+    pub fn media(&self, mid: &str) -> Option<&StreamSet> {
+        self.media.iter().find(|m| m.mid == mid).map(|m| &m.streams)
+    }
+
+    pub fn mids(&self) -> impl Iterator<Item = &str> + '_ {
+        self.media.iter().map(|m| m.mid.as_str())
+    }
+
+    /// m-line the packets of `ssrc` are routed to.
+    pub fn mid_of(&self, ssrc: u32) -> Option<&str> {
+        self.ssrcs
+            .get(&ssrc)
+            .map(|r| self.media[r.media].mid.as_str())
+    }
+
+    /// RID of the encoding `ssrc` carries, or repairs.
+    pub fn rid_of(&self, ssrc: u32) -> Option<&str> {
+        self.ssrcs.get(&ssrc)?.rid.as_deref()
+    }
+
+    /// SSRC of the encoding repaired by the stream `ssrc`, once both are known.
+    pub fn primary_of(&self, ssrc: u32) -> Option<u32> {
+        let route = self.ssrcs.get(&ssrc).filter(|r| r.repair)?;
+        self.ssrcs
+            .iter()
+            .find(|(_, r)| !r.repair && r.media == route.media && r.rid == route.rid)
+            .map(|(&primary, _)| primary)
+    }
+
+    /// Packets dropped because neither their MID nor their SSRC was known.
+    pub fn unrouted(&self) -> u64 {
+        self.unrouted
+    }
+
+    /// Forgets `ssrc`, e.g. after an RTCP BYE.
+    pub fn remove_ssrc(&mut self, ssrc: u32) {
+        if let Some(route) = self.ssrcs.remove(&ssrc) {
+            self.media[route.media].streams.remove(ssrc);
+        }
+        self.stats.remove(&ssrc);
+        self.nacks.remove(&ssrc);
+        self.sync.remove_stream(ssrc);
+    }
+
+    /// Routes a serialized RTP packet; returns the frame it completes.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<MediaFrame>, RtpError> {
+        self.push_at(packet, None)
+    }
+
+    /// Takes a datagram of an RTP/RTCP-multiplexed port that arrived at
+    /// `arrival` (any monotonic clock). RTP packets are routed as by
+    /// [`push`](Self::push) and counted in the statistics; RTCP sender
+    /// reports map streams to wall-clock time, and BYEs remove streams.
+    pub fn push_datagram(
+        &mut self,
+        datagram: &[u8],
+        arrival: Duration,
+    ) -> Result<Option<MediaFrame>, RtpError> {
+        if !is_rtcp(datagram) {
+            return self.push_at(datagram, Some(arrival));
+        }
+        for packet in RtcpPacket::parse_compound(datagram) {
+            let packet = match packet {
+                Ok(packet) => packet,
+                Err(_e) => {
+                    trace_event!(DEBUG, error = %_e, "malformed rtcp packet");
+                    break;
+                }
+            };
+            self.metrics
+                .counter("rtpar_session_rtcp_packets_total", &[], 1);
+            match packet {
+                RtcpPacket::SenderReport(sr) => {
+                    let report = sr.sender;
+                    if let Some(stats) = self.stats.get_mut(&report.ssrc) {
+                        stats.on_sender_report(report.ntp_timestamp, arrival);
+                    }
+                    self.sync.on_sender_report(&report, arrival);
+                }
+                RtcpPacket::Bye(bye) => {
+                    for ssrc in bye.sources() {
+                        self.remove_ssrc(ssrc);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Receiver statistics of `ssrc`, from packets given to
+    /// [`push_datagram`](Self::push_datagram), including the assembly time
+    /// and reorder depth of its frames.
+    pub fn stats(&self, ssrc: u32) -> Option<&ReceiverStats> {
+        self.stats.get(&ssrc)
+    }
+
+    /// Report blocks for every received stream, for an RTCP RR or SR.
+    pub fn report_blocks(&mut self, now: Duration) -> Vec<ReportBlock> {
+        self.stats
+            .iter_mut()
+            .map(|(&ssrc, stats)| stats.report_block(ssrc, now))
+            .collect()
+    }
+
+    /// Packets to NACK and streams to send a PLI for.
+    pub fn feedback(&self) -> Feedback {
+        let nacks = self
+            .nacks
+            .iter()
+            .filter(|(_, nacks)| !nacks.is_empty())
+            .map(|(&ssrc, nacks)| (ssrc, nacks.missing().collect()))
+            .collect();
+        let keyframe_requests = self
+            .ssrcs
+            .iter()
+            .filter(|(&ssrc, route)| {
+                let streams = &self.media[route.media].streams;
+                streams.get(ssrc).is_some_and(|r| r.needs_keyframe())
+            })
+            .map(|(&ssrc, _)| ssrc)
+            .collect();
+        Feedback {
+            nacks,
+            keyframe_requests,
+        }
+    }
+
+    pub fn sync(&self) -> &StreamSync {
+        &self.sync
+    }
+
+    fn push_at(
+        &mut self,
+        packet: &[u8],
+        arrival: Option<Duration>,
+    ) -> Result<Option<MediaFrame>, RtpError> {
+        let pkt = RtpPacket::parse(packet)?;
+        let ssrc = pkt.header.ssrc;
+        let ids = self.stream_ids(&pkt, packet);
+        let media = ids
+            .mid
+            .and_then(|mid| self.media.iter().position(|m| m.mid.as_bytes() == mid));
+        if let Some(media) = media {
+            let (rid, repair) = match ids.repaired_rid {
+                Some(rid) => (Some(rid), true),
+                None => (ids.rid, false),
+            };
+            self.learn(ssrc, media, rid, repair);
+        }
+        let Some(route) = self.ssrcs.get(&ssrc) else {
+            self.unrouted += 1;
+            self.metrics
+                .counter("rtpar_session_unrouted_packets_total", &[], 1);
+            return Ok(None);
+        };
+        if !route.repair {
+            let route = route.clone();
+            if let Some(arrival) = arrival {
+                self.observe(&route, &pkt.header, arrival);
+            }
+            let frame = self.push_to(&route, &pkt);
+            return Ok(self.timed(frame, arrival));
+        }
+        // RTX: the original sequence number, then the original payload.
+        let Some(primary) = self.primary_of(ssrc) else {
+            return Ok(None);
+        };
+        if !self.rtx_payload_types.contains(&pkt.header.payload_type) {
+            return Ok(None);
+        }
+        let Some((osn, payload)) = pkt.payload.split_first_chunk::<2>() else {
+            return Ok(None);
+        };
+        self.metrics
+            .counter("rtpar_session_rtx_packets_total", &[], 1);
+        let mut header = pkt.header.clone();
+        header.ssrc = primary;
+        header.sequence_number = u16::from_be_bytes(*osn);
+        let original = RtpPacket {
+            header,
+            payload_offset: pkt.payload_offset + 2,
+            payload,
+        };
+        let route = self.ssrcs[&primary].clone();
+        if arrival.is_some() {
+            // A repaired packet fills its gap but says nothing about jitter.
+            if let Some(nacks) = self.nacks.get_mut(&primary) {
+                nacks.on_packet(original.header.sequence_number);
+            }
+        }
+        let frame = self.push_to(&route, &original);
+        Ok(self.timed(frame, arrival))
+    }
+
+    // Adds a frame emitted at `arrival` to its stream's timing histograms.
+    fn timed(
+        &mut self,
+        frame: Option<MediaFrame>,
+        arrival: Option<Duration>,
+    ) -> Option<MediaFrame> {
+        if let (Some(frame), Some(arrival)) = (&frame, arrival) {
+            if let Some(stats) = self.stats.get_mut(&frame.ssrc) {
+                stats.on_frame(frame.timestamp, arrival);
+            }
+        }
+        frame
+    }
+
+    // Statistics, NACK and sync state of a primary stream's packet.
+    fn observe(&mut self, route: &Route, header: &RtpHeader, arrival: Duration) {
+        let ssrc = header.ssrc;
+        let clock_rate = self.media[route.media]
+            .streams
+            .get(ssrc)
+            .and_then(|r| r.codec())
+            .unwrap_or(Codec::Unknown)
+            .clock_rate();
+        self.stats
+            .entry(ssrc)
+            .or_insert_with(|| ReceiverStats::new(clock_rate))
+            .on_packet(header, arrival);
+        self.nacks
+            .entry(ssrc)
+            .or_default()
+            .on_packet(header.sequence_number);
+        if self.sync.clock(ssrc).is_none() {
+            self.sync.add_stream(ssrc, clock_rate);
+        }
+    }
+
+    fn push_to(&mut self, route: &Route, pkt: &RtpPacket<'_>) -> Option<MediaFrame> {
+        let media = &mut self.media[route.media];
+        let data = media.streams.push_packet(pkt)?;
+        Some(MediaFrame {
+            mid: media.mid.clone(),
+            rid: route.rid.clone(),
+            ssrc: pkt.header.ssrc,
+            timestamp: pkt.header.timestamp,
+            presentation: self
+                .sync
+                .presentation_time(pkt.header.ssrc, pkt.header.timestamp),
+            data,
+        })
+    }
+
+    // Records the route of `ssrc`, moving it away from another m-line.
+    fn learn(&mut self, ssrc: u32, media: usize, rid: Option<&[u8]>, repair: bool) {
+        if let Some(route) = self.ssrcs.get(&ssrc) {
+            if route.media == media
+                && route.repair == repair
+                && route.rid.as_deref().map(str::as_bytes) == rid
+            {
+                return;
+            }
+            if route.media != media {
+                let _mid = &self.media[media].mid;
+                trace_event!(DEBUG, ssrc, mid = %_mid, "ssrc moved to another mid");
+                self.media[route.media].streams.remove(ssrc);
+            }
+        }
+        let rid = rid.map(|rid| String::from_utf8_lossy(rid).into_owned());
+        self.ssrcs.insert(ssrc, Route { media, rid, repair });
+    }
+
+    fn stream_ids<'a>(&self, pkt: &RtpPacket<'_>, packet: &'a [u8]) -> StreamIds<'a> {
+        let mut ids = StreamIds::default();
+        let Some(ext) = &pkt.header.extension_header else {
+            return ids;
+        };
+        for element in ext.elements(packet) {
+            let id = Some(element.id);
+            if element.id == self.mid_extension {
+                ids.mid = Some(element.data);
+            } else if id == self.rid_extension {
+                ids.rid = Some(element.data);
+            } else if id == self.repaired_rid_extension {
+                ids.repaired_rid = Some(element.data);
+            }
+        }
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{codecs::Codec, test_utils::RtpPacketBuilder};
+
+    fn packet(ssrc: u32, seq: u16, mid: Option<&str>) -> Vec<u8> {
+        let ids: Vec<_> = mid.map(|mid| (1, mid)).into_iter().collect();
+        tagged(ssrc, seq, &ids, &[0x65, seq as u8], true)
+    }
+
+    // Packet with one-byte extension elements `(id, value)`.
+    fn tagged(ssrc: u32, seq: u16, ids: &[(u8, &str)], payload: &[u8], marker: bool) -> Vec<u8> {
+        let mut b = RtpPacketBuilder::new()
+            .ssrc(ssrc)
+            .sequence_number(seq)
+            .timestamp(seq as u32)
+            .marker(marker)
+            .payload(payload);
+        if !ids.is_empty() {
+            let mut elements = Vec::new();
+            for (id, value) in ids {
+                elements.push(id << 4 | (value.len() as u8 - 1));
+                elements.extend_from_slice(value.as_bytes());
+            }
+            b = b.extension(0xBEDE, &elements);
+        }
+        b.build()
+    }
+
+    fn avc_streams() -> StreamSet {
+        let mut set = StreamSet::new();
+        set.set_codec(Some(Codec::Avc));
+        set
+    }
+
+    #[test]
+    fn routes_bundled_streams_by_mid() {
+        let mut session = Session::new(1);
+        session.add_media("0", avc_streams());
+        session.add_media("screen", avc_streams());
+
+        let mut frames = Vec::new();
+        for p in [
+            packet(10, 1, Some("0")),
+            packet(20, 1, Some("screen")),
+            // Routed by the learned SSRC.
+            packet(10, 2, None),
+            // Neither MID nor SSRC known.
+            packet(30, 1, None),
+            packet(40, 1, Some("unknown")),
+        ] {
+            frames.extend(session.push(&p).unwrap());
+        }
+        let routed: Vec<_> = frames
+            .iter()
+            .map(|f| (f.mid.as_str(), f.ssrc, f.timestamp))
+            .collect();
+        assert_eq!(routed, [("0", 10, 1), ("screen", 20, 1), ("0", 10, 2)]);
+        assert_eq!(session.unrouted(), 2);
+
+        // SSRC 10 moves to the screenshare m-line.
+        let frame = session
+            .push(&packet(10, 3, Some("screen")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(frame.mid, "screen");
+        assert_eq!(session.mid_of(10), Some("screen"));
+        assert!(session.media("0").unwrap().is_empty());
+        assert_eq!(session.media("screen").unwrap().len(), 2);
+        assert!(session.push(&[0x80]).is_err());
+    }
+
+    #[test]
+    fn associates_repair_streams_by_rid() {
+        let mut session = Session::new(1);
+        session.set_rid_extension(2);
+        session.set_repaired_rid_extension(3);
+        session.add_rtx_payload_type(97);
+        session.add_media("v", avc_streams());
+        let fu = crate::test_utils::avc::fu_a(&[0x65, 1, 2, 3, 4, 5, 6], 2);
+
+        // The RTX stream for "h" shows up before the encoding itself.
+        let early = tagged(30, 7, &[(1, "v"), (3, "h")], &[0, 1, 0x7C, 0x05], true);
+        assert_eq!(session.push(&early).unwrap(), None);
+        assert_eq!(session.primary_of(30), None);
+
+        let mut frames = Vec::new();
+        let h = [(1, "v"), (2, "h")];
+        frames.extend(session.push(&tagged(10, 1, &h, &fu[0], false)).unwrap());
+        frames.extend(
+            session
+                .push(&tagged(20, 1, &[(1, "v"), (2, "l")], &[0x65, 9], true))
+                .unwrap(),
+        );
+        // Packet 2 of "h" is lost and retransmitted.
+        let mut rtx = RtpPacketBuilder::new()
+            .ssrc(30)
+            .sequence_number(8)
+            .timestamp(1)
+            .payload_type(97)
+            .payload(&[&[0, 2][..], &fu[1]].concat())
+            .build();
+        frames.extend(session.push(&rtx).unwrap());
+        let last = RtpPacketBuilder::new()
+            .ssrc(10)
+            .sequence_number(3)
+            .timestamp(1)
+            .marker(true)
+            .payload(&fu[2])
+            .build();
+        frames.extend(session.push(&last).unwrap());
+
+        let routed: Vec<_> = frames
+            .iter()
+            .map(|f| (f.rid.as_deref(), f.ssrc, f.data.len()))
+            .collect();
+        assert_eq!(routed, [(Some("l"), 20, 6), (Some("h"), 10, 11)]);
+        assert_eq!(session.primary_of(30), Some(10));
+        assert_eq!(
+            (session.rid_of(30), session.rid_of(20)),
+            (Some("h"), Some("l"))
+        );
+
+        // Repair packets of other payload types (e.g. FEC) are not unwrapped.
+        rtx[1] = 98;
+        assert_eq!(session.push(&rtx).unwrap(), None);
+    }
+
+    #[test]
+    fn datagrams_feed_stats_sync_and_feedback() {
+        let ms = Duration::from_millis;
+        let mut session = Session::new(1);
+        session.add_media("0", avc_streams());
+        let first = session.push_datagram(&packet(10, 1, Some("0")), ms(0));
+        assert_eq!(first.unwrap().unwrap().presentation, None);
+
+        // SR: RTP timestamp 2 was captured at 100 s; then BYE for SSRC 10.
+        let mut sr = alloc::vec![0x80, 200, 0, 6, 0, 0, 0, 10];
+        sr.extend_from_slice(&duration_to_ntp(ms(100_000)).to_be_bytes());
+        sr.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2]);
+        assert_eq!(session.push_datagram(&sr, ms(5)), Ok(None));
+
+        let frame = session.push_datagram(&packet(10, 2, None), ms(10)).unwrap();
+        let presentation = frame.unwrap().presentation.unwrap();
+        assert_eq!(presentation.wall_clock, ms(100_000));
+        session.push_datagram(&packet(10, 5, None), ms(20)).unwrap();
+        assert_eq!(session.stats(10).unwrap().received(), 3);
+        assert_eq!(session.feedback().nacks, [(10, alloc::vec![3, 4])]);
+        assert_eq!(session.report_blocks(ms(30))[0].last_sr, 100 << 16);
+
+        let bye = [0x81, 203, 0, 1, 0, 0, 0, 10];
+        session.push_datagram(&bye, ms(40)).unwrap();
+        assert!(session.mid_of(10).is_none() && session.stats(10).is_none());
+    }
+}
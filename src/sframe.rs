@@ -0,0 +1,300 @@
+//! SFrame end-to-end encrypted media (RFC 9605).
+//!
+//! With per-frame SFrame the sender encrypts each encoded frame before
+//! packetizing it, so payloads after the first packet of a frame are
+//! ciphertext, and the first starts with an SFrame header instead of a
+//! payload header. Codec parsing would misfire on them. The application
+//! usually knows from signaling, or from a header extension it negotiated,
+//! that a stream is encrypted; [`SframeDetector`] also recognizes it from
+//! the headers of consecutive frames. Encrypted streams are reassembled as
+//! opaque frames, handed to a [`FrameDecryptor`] if one is set; see
+//! [`ReassemblerConfig::sframe`](crate::reassemble::ReassemblerConfig::sframe).
+
+use crate::rtp::{RtpError, RtpPacket};
+use alloc::vec::Vec;
+
+/// Consecutive frames with plausible headers needed to detect SFrame.
+pub const DETECT_FRAMES: u32 = 4;
+
+// Shortest authentication tag of the RFC 9605 cipher suites
+// (AES_128_CTR_HMAC_SHA256_32).
+const MIN_TAG_BYTES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SframeError {
+    /// The frame ends inside its SFrame header.
+    Truncated,
+    /// A [`FrameDecryptor`] could not decrypt or authenticate a frame.
+    DecryptionFailed,
+}
+
+impl core::fmt::Display for SframeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SframeError::Truncated => f.write_str("frame ends inside its SFrame header"),
+            SframeError::DecryptionFailed => f.write_str("SFrame decryption failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SframeError {}
+
+/// Key ID and counter of an SFrame ciphertext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SframeHeader {
+    pub kid: u64,
+    pub ctr: u64,
+}
+
+impl SframeHeader {
+    /// Parses the header; returns it and its length.
+    pub fn parse(buf: &[u8]) -> Result<(Self, usize), SframeError> {
+        let (&config, rest) = buf.split_first().ok_or(SframeError::Truncated)?;
+        // X and Y flag values too large for the 3 bits K and C, which then
+        // hold their lengths minus one.
+        let field = |long: bool, bits: u8, rest: &[u8]| -> Result<(u64, usize), SframeError> {
+            if !long {
+                return Ok((bits as u64, 0));
+            }
+            let len = bits as usize + 1;
+            let bytes = rest.get(..len).ok_or(SframeError::Truncated)?;
+            let value = bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+            Ok((value, len))
+        };
+        let (kid, kid_len) = field(config & 0x80 != 0, (config >> 4) & 0x07, rest)?;
+        let (ctr, ctr_len) = field(config & 0x08 != 0, config & 0x07, &rest[kid_len..])?;
+        Ok((Self { kid, ctr }, 1 + kid_len + ctr_len))
+    }
+}
+
+/// Whether and how streams are SFrame encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SframeMode {
+    /// Payloads are parsed as the codec's.
+    #[default]
+    Off,
+    /// Every frame is encrypted, as signaled.
+    Encrypted,
+    /// Frames are treated as encrypted once an [`SframeDetector`] has
+    /// recognized the stream; the frames before are parsed as the codec's.
+    Detect,
+}
+
+/// Decrypts SFrame frames, e.g. with the keys of an MLS group.
+pub trait FrameDecryptor: Send + Sync {
+    /// Returns the encoded frame within `frame`, the complete ciphertext
+    /// whose header (also the associated data) is `header`. The frame is
+    /// expected in the form the reassembler outputs for the codec, e.g.
+    /// Annex-B for H.264.
+    fn decrypt(&self, header: &SframeHeader, frame: &[u8]) -> Result<Vec<u8>, SframeError>;
+}
+
+impl core::fmt::Debug for dyn FrameDecryptor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("FrameDecryptor")
+    }
+}
+
+/// Recognizes SFrame encrypted streams. Frames must start with a header
+/// whose key ID stays the same and whose counter grows; once
+/// [`DETECT_FRAMES`] in a row do, or a packet carries the configured header
+/// extension, the stream counts as encrypted for good.
+#[derive(Debug, Default)]
+pub struct SframeDetector {
+    extension: Option<u8>,
+    // Timestamp and header of the last frame start.
+    last: Option<(u32, Option<SframeHeader>)>,
+    streak: u32,
+    detected: bool,
+}
+
+impl SframeDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Extmap id of a header extension the application negotiated to flag
+    /// encrypted packets, checked by [`push_bytes`](Self::push_bytes).
+    pub fn set_extension(&mut self, id: u8) {
+        self.extension = Some(id);
+    }
+
+    pub fn detected(&self) -> bool {
+        self.detected
+    }
+
+    /// Looks at the first packet of every frame; returns whether the
+    /// stream is encrypted.
+    pub fn push(&mut self, pkt: &RtpPacket<'_>) -> bool {
+        let timestamp = pkt.header.timestamp;
+        if self.detected || self.last.is_some_and(|(ts, _)| ts == timestamp) {
+            return self.detected;
+        }
+        let header = SframeHeader::parse(pkt.payload)
+            .ok()
+            .filter(|(_, len)| pkt.payload.len() > len + MIN_TAG_BYTES)
+            .map(|(header, _)| header);
+        let previous = self.last.and_then(|(_, header)| header);
+        self.streak = match (previous, header) {
+            (Some(p), Some(h)) if p.kid == h.kid && h.ctr > p.ctr => self.streak + 1,
+            (_, Some(_)) => 1,
+            (_, None) => 0,
+        };
+        self.last = Some((timestamp, header));
+        self.detected = self.streak >= DETECT_FRAMES;
+        self.detected
+    }
+
+    /// Like [`push`](Self::push) for a serialized packet, also checking the
+    /// configured header extension.
+    pub fn push_bytes(&mut self, packet: &[u8]) -> Result<bool, RtpError> {
+        let pkt = RtpPacket::parse(packet)?;
+        if let (Some(id), Some(ext)) = (self.extension, &pkt.header.extension_header) {
+            self.detected |= ext.elements(packet).any(|e| e.id == id);
+        }
+        Ok(self.push(&pkt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::Codec,
+        reassemble::{DropReason, EventKind, FrameReassembler, PushResult},
+        rtp::RtpPacket,
+        test_utils::{fixtures::annex_b, RtpPacketBuilder, StreamBuilder},
+    };
+    use alloc::{sync::Arc, vec, vec::Vec};
+
+    // XORs with the key ID; the tag is the plaintext length.
+    struct Xor;
+
+    impl FrameDecryptor for Xor {
+        fn decrypt(&self, header: &SframeHeader, frame: &[u8]) -> Result<Vec<u8>, SframeError> {
+            let (body, tag) = frame[1..].split_at(frame.len() - 5);
+            if tag != (body.len() as u32).to_be_bytes() {
+                return Err(SframeError::DecryptionFailed);
+            }
+            Ok(body.iter().map(|b| b ^ header.kid as u8).collect())
+        }
+    }
+
+    fn encrypt(ctr: u8, plain: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x50 | ctr];
+        out.extend(plain.iter().map(|b| b ^ 5));
+        out.extend((plain.len() as u32).to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn parses_short_and_long_headers() {
+        // KID 3 and CTR 5 in the config byte.
+        assert_eq!(
+            SframeHeader::parse(&[0x35, 0xAA]),
+            Ok((SframeHeader { kid: 3, ctr: 5 }, 1))
+        );
+        // Two-byte KID, three-byte CTR.
+        assert_eq!(
+            SframeHeader::parse(&[0x9A, 0x01, 0x02, 0x00, 0x10, 0x00]),
+            Ok((
+                SframeHeader {
+                    kid: 0x0102,
+                    ctr: 0x1000
+                },
+                6
+            ))
+        );
+        assert_eq!(
+            SframeHeader::parse(&[0x9A, 0x01]),
+            Err(SframeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn detects_growing_counters_or_the_extension() {
+        let packet = |ts: u32, ctr: u8| {
+            RtpPacketBuilder::new()
+                .timestamp(ts)
+                .payload(&[&[0x18, ctr][..], &[0x5A; 20]].concat())
+                .build()
+        };
+        let mut detector = SframeDetector::new();
+        let detected: Vec<_> = (0..DETECT_FRAMES)
+            .map(|i| {
+                detector
+                    .push_bytes(&packet(3000 * i, 10 + i as u8))
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(detected, [false, false, false, true]);
+
+        // H.264 IDR slices repeat the same "counter".
+        let mut detector = SframeDetector::new();
+        let idr = RtpPacketBuilder::new().payload(&[0x65; 30]);
+        for i in 0..8 {
+            assert!(!detector
+                .push_bytes(&idr.clone().timestamp(i).build())
+                .unwrap());
+        }
+        detector.set_extension(3);
+        let flagged = idr.clone().extension(0xBEDE, &[0x30, 1, 0, 0]).build();
+        assert!(detector.push_bytes(&flagged).unwrap());
+    }
+
+    #[test]
+    fn encrypted_frames_are_reassembled_opaquely_or_decrypted() {
+        let plain = annex_b(&[&[0x65, 0x88, 0x84, 0x00, 0x33]]);
+        let frames: Vec<_> = (1..=5).map(|ctr| encrypt(ctr, &plain)).collect();
+        let mut stream = StreamBuilder::new(1);
+        for frame in &frames {
+            let (a, b) = frame.split_at(6);
+            stream = stream.frame(&[a.to_vec(), b.to_vec()]);
+        }
+        let mut tampered = frames[0].clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let tampered = StreamBuilder::new(1)
+            .start_sequence(10)
+            .start_timestamp(30000)
+            .frame(&[tampered])
+            .build();
+        let packets = stream.build();
+
+        let builder = FrameReassembler::builder().codec(Codec::Avc).event_log(64);
+        let encrypted = builder.clone().sframe(SframeMode::Encrypted);
+        let mut r = encrypted.clone().build().unwrap();
+        let mut out = Vec::new();
+        for bytes in &packets {
+            out.extend(r.push_packet(&RtpPacket::parse(bytes).unwrap()));
+        }
+        assert_eq!(out, frames);
+
+        let mut r = encrypted.build().unwrap();
+        r.set_frame_decryptor(Arc::new(Xor));
+        let mut out = Vec::new();
+        for bytes in &packets {
+            out.extend(r.push_packet(&RtpPacket::parse(bytes).unwrap()));
+        }
+        assert_eq!(out, vec![plain.clone(); 5]);
+        assert_eq!(
+            r.push_packet_result(&RtpPacket::parse(&tampered[0]).unwrap()),
+            PushResult::FrameDropped(DropReason::DecryptionFailed)
+        );
+
+        // Detected on the fourth frame; the ones before went through the
+        // AVC depacketizer.
+        let mut r = builder.sframe(SframeMode::Detect).build().unwrap();
+        let mut out = Vec::new();
+        for bytes in &packets {
+            out.extend(r.push_packet(&RtpPacket::parse(bytes).unwrap()));
+        }
+        assert!(r.encrypted());
+        assert_eq!(out[3..], frames[3..]);
+        assert!(r
+            .recent_events()
+            .any(|e| matches!(e.kind, EventKind::SframeDetected { timestamp: 9000 })));
+    }
+}
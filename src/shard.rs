@@ -0,0 +1,389 @@
+//! Multi-stream processing.
+//!
+//! Threading model: a `FrameReassembler` holds the state of one stream and
+//! is driven through `&mut self`; it is `Send` but not meant to be shared.
+//! To process many streams in parallel, partition them by SSRC so that every
+//! packet of a stream reaches the same reassembler, in arrival order:
+//!
+//! - [`StreamSet`] keeps one reassembler per SSRC for a single thread.
+//! - [`shard_index`] maps an SSRC to one of `n` shards, for callers that
+//!   drive their own threads (e.g. one `StreamSet` per rayon task).
+//! - [`ShardedReassembler`] (`std`) runs one `StreamSet` per worker thread
+//!   and returns frames over a channel.
+//! - [`Pipeline`](crate::pipeline::Pipeline) (`std`) adds bounded queues,
+//!   RTCP BYE handling and per-worker stats, for thousands of streams.
+
+use crate::{
+    codecs::{custom::CodecRegistry, Codec},
+    limits::{Limit, LimitExceeded, Limits},
+    metrics::{Metrics, MetricsSink},
+    reassemble::{ConfigError, FrameReassembler, ReassemblerConfig, ReorderConfig},
+    rtp::RtpPacket,
+};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+
+/// Shard (in `0..shards`) that owns `ssrc`. Stable for a given shard count;
+/// SSRCs are spread evenly even when they are sequential.
+pub fn shard_index(ssrc: u32, shards: usize) -> usize {
+    let hash = ssrc.wrapping_mul(0x9E37_79B1) as u64;
+    ((hash * shards.max(1) as u64) >> 32) as usize
+}
+
+/// One `FrameReassembler` per SSRC, created on the first packet of a stream.
+#[derive(Debug, Default)]
+pub struct StreamSet {
+    codec: Option<Codec>,
+    config: ReorderConfig,
+    limits: Option<Limits>,
+    streams: BTreeMap<u32, FrameReassembler>,
+    metrics: Metrics,
+    registry: Option<Arc<CodecRegistry>>,
+}
+
+impl StreamSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Codec for streams created from now on; `None` guesses per stream.
+    pub fn set_codec(&mut self, codec: Option<Codec>) {
+        self.codec = codec;
+    }
+
+    /// Reorder settings for streams created from now on.
+    pub fn set_reorder_config(&mut self, cfg: ReorderConfig) -> Result<(), ConfigError> {
+        cfg.validate()?;
+        self.config = cfg;
+        Ok(())
+    }
+
+    /// Applies `limits` to streams created from now on, and creates no
+    /// stream beyond `max_ssrcs`.
+    pub fn set_limits(&mut self, limits: Limits) -> Result<(), ConfigError> {
+        if limits.max_ssrcs == 0 {
+            return Err(ConfigError::ZeroLimit("max_ssrcs"));
+        }
+        self.stream_config(Some(&limits)).validate()?;
+        self.limits = Some(limits);
+        Ok(())
+    }
+
+    fn stream_config(&self, limits: Option<&Limits>) -> ReassemblerConfig {
+        let mut config = ReassemblerConfig {
+            codec: self.codec,
+            reorder: self.config,
+            ..Default::default()
+        };
+        if let Some(limits) = limits {
+            config.apply_limits(limits);
+        }
+        config
+    }
+
+    /// Reports the metrics of every stream, current and future, to `sink`
+    /// along with the number of streams.
+    pub fn set_metrics(&mut self, sink: Arc<dyn MetricsSink>) {
+        for r in self.streams.values_mut() {
+            r.set_metrics(sink.clone());
+        }
+        self.metrics = Metrics::new(sink);
+    }
+
+    /// Makes the custom codecs of `registry` available to every stream,
+    /// current and future.
+    pub fn set_codec_registry(&mut self, registry: Arc<CodecRegistry>) {
+        for r in self.streams.values_mut() {
+            r.set_codec_registry(registry.clone());
+        }
+        self.registry = Some(registry);
+    }
+
+    /// Routes `pkt` to the reassembler of its SSRC; returns a completed frame.
+    /// Packets of streams over the `max_ssrcs` limit are dropped.
+    pub fn push_packet(&mut self, pkt: &RtpPacket<'_>) -> Option<Vec<u8>> {
+        self.try_push_packet(pkt).ok().flatten()
+    }
+
+    /// Like `push_packet`, but fails when `pkt` would start a stream beyond
+    /// the [`Limits::max_ssrcs`] set with `set_limits`.
+    pub fn try_push_packet(
+        &mut self,
+        pkt: &RtpPacket<'_>,
+    ) -> Result<Option<Vec<u8>>, LimitExceeded> {
+        let ssrc = pkt.header.ssrc;
+        let streams = self.streams.len();
+        if let (Some(limits), false) = (&self.limits, self.streams.contains_key(&ssrc)) {
+            if let Err(exceeded) = limits.check(Limit::Ssrcs, streams + 1) {
+                trace_event!(
+                    DEBUG,
+                    ssrc,
+                    streams,
+                    "stream limit reached, dropping packet"
+                );
+                let labels = [("limit", exceeded.limit.as_str())];
+                self.metrics
+                    .counter("rtpar_limit_exceeded_total", &labels, 1);
+                return Err(exceeded);
+            }
+        }
+        let config = self.stream_config(self.limits.as_ref());
+        let metrics = &self.metrics;
+        let registry = &self.registry;
+        let r = self.streams.entry(ssrc).or_insert_with(|| {
+            // Validated by `set_reorder_config` and `set_limits`.
+            let mut r = FrameReassembler::from_config(config).unwrap_or_default();
+            if let Some(registry) = registry {
+                r.set_codec_registry(registry.clone());
+            }
+            if let Some(sink) = metrics.sink() {
+                r.set_metrics(sink.clone());
+                metrics.gauge("rtpar_streams", &[], streams as i64 + 1);
+            }
+            r
+        });
+        Ok(r.push_packet(pkt))
+    }
+
+    pub fn get(&self, ssrc: u32) -> Option<&FrameReassembler> {
+        self.streams.get(&ssrc)
+    }
+
+    /// Forgets a stream, e.g. after an RTCP BYE.
+    pub fn remove(&mut self, ssrc: u32) -> Option<FrameReassembler> {
+        let removed = self.streams.remove(&ssrc);
+        if removed.is_some() {
+            self.metrics
+                .gauge("rtpar_streams", &[], self.streams.len() as i64);
+        }
+        removed
+    }
+
+    pub fn ssrcs(&self) -> impl Iterator<Item = u32> + '_ {
+        self.streams.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+pub use threaded::{ShardedReassembler, StreamFrame};
+
+#[cfg(feature = "std")]
+mod threaded {
+    use super::{shard_index, StreamSet};
+    use crate::rtp::{RtpError, RtpPacket};
+    use std::{
+        sync::mpsc::{self, Receiver, Sender},
+        thread::{self, JoinHandle},
+        vec::Vec,
+    };
+
+    /// Frame completed by a worker of a `ShardedReassembler`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct StreamFrame {
+        pub ssrc: u32,
+        pub timestamp: u32,
+        pub data: Vec<u8>,
+    }
+
+    /// Reassembles many streams on a pool of worker threads.
+    ///
+    /// Each worker owns a `StreamSet` and receives the streams whose
+    /// `shard_index` matches it, so per-stream packet order is preserved.
+    /// Frames of different streams may be interleaved in any order.
+    /// Dropping the reassembler stops and joins the workers.
+    #[derive(Debug)]
+    pub struct ShardedReassembler {
+        inputs: Vec<Sender<Vec<u8>>>,
+        workers: Vec<JoinHandle<()>>,
+        frames: Receiver<StreamFrame>,
+    }
+
+    impl ShardedReassembler {
+        /// Starts `shards` workers (at least one), each with a `StreamSet`
+        /// built by `make`.
+        pub fn spawn(shards: usize, make: impl Fn() -> StreamSet) -> Self {
+            let (frame_tx, frames) = mpsc::channel();
+            let mut inputs = Vec::new();
+            let mut workers = Vec::new();
+            for _shard in 0..shards.max(1) {
+                let (tx, rx) = mpsc::channel::<Vec<u8>>();
+                let frame_tx = frame_tx.clone();
+                let mut set = make();
+                workers.push(thread::spawn(move || {
+                    #[cfg(feature = "tracing")]
+                    let _span = tracing::debug_span!("shard", index = _shard).entered();
+                    for bytes in rx {
+                        let Ok(pkt) = RtpPacket::parse(&bytes) else {
+                            continue;
+                        };
+                        if let Some(data) = set.push_packet(&pkt) {
+                            let frame = StreamFrame {
+                                ssrc: pkt.header.ssrc,
+                                timestamp: pkt.header.timestamp,
+                                data,
+                            };
+                            if frame_tx.send(frame).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }));
+                inputs.push(tx);
+            }
+            Self {
+                inputs,
+                workers,
+                frames,
+            }
+        }
+
+        pub fn shards(&self) -> usize {
+            self.inputs.len()
+        }
+
+        /// Queues a serialized RTP packet for the worker owning its SSRC.
+        pub fn push(&self, packet: Vec<u8>) -> Result<(), RtpError> {
+            let ssrc = RtpPacket::parse(&packet)?.header.ssrc;
+            let shard = shard_index(ssrc, self.inputs.len());
+            // A worker only exits once its input is closed.
+            let _ = self.inputs[shard].send(packet);
+            Ok(())
+        }
+
+        /// Completed frames, as workers produce them.
+        pub fn frames(&self) -> &Receiver<StreamFrame> {
+            &self.frames
+        }
+
+        /// Processes all queued packets, stops the workers and returns the
+        /// frames not yet received.
+        pub fn finish(mut self) -> Vec<StreamFrame> {
+            self.join();
+            self.frames.try_iter().collect()
+        }
+
+        fn join(&mut self) {
+            self.inputs.clear();
+            for worker in self.workers.drain(..) {
+                let _ = worker.join();
+            }
+        }
+    }
+
+    impl Drop for ShardedReassembler {
+        fn drop(&mut self) {
+            self.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{avc, StreamBuilder};
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn stream_state_is_send() {
+        assert_send::<FrameReassembler>();
+        assert_send::<crate::FrameAnalyzer>();
+        assert_send::<crate::FramePacketizer>();
+        assert_send::<StreamSet>();
+        assert_send::<crate::session::Session>();
+    }
+
+    #[test]
+    fn stream_set_keeps_interleaved_streams_apart() {
+        let a = StreamBuilder::new(1)
+            .frame(&avc::fu_a(&[0x65, 1, 2, 3, 4, 5], 3))
+            .build();
+        let b = StreamBuilder::new(2)
+            .frame(&avc::fu_a(&[0x65, 6, 7, 8, 9, 10], 3))
+            .build();
+        let mut set = StreamSet::new();
+        set.set_codec(Some(Codec::Avc));
+        let mut frames = Vec::new();
+        for (pa, pb) in a.iter().zip(&b) {
+            for p in [pa, pb] {
+                frames.extend(set.push_packet(&RtpPacket::parse(p).unwrap()));
+            }
+        }
+        assert_eq!(
+            frames,
+            [
+                alloc::vec![0, 0, 0, 1, 0x65, 1, 2, 3, 4, 5],
+                alloc::vec![0, 0, 0, 1, 0x65, 6, 7, 8, 9, 10]
+            ]
+        );
+        assert_eq!(set.ssrcs().collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn stream_set_enforces_limits() {
+        let mut set = StreamSet::new();
+        set.set_codec(Some(Codec::Avc));
+        let limits = Limits {
+            max_ssrcs: 1,
+            max_aggregation_items: 2,
+            ..Limits::default()
+        };
+        set.set_limits(limits).unwrap();
+        let nal: &[u8] = &[0x65, 1];
+        let a = StreamBuilder::new(1)
+            .frame(&[avc::stap_a(&[nal, nal])])
+            .frame(&[avc::stap_a(&[nal, nal, nal])])
+            .build();
+        let frames: Vec<_> = a
+            .iter()
+            .map(|p| set.try_push_packet(&RtpPacket::parse(p).unwrap()))
+            .collect();
+        assert!(matches!(frames[..], [Ok(Some(_)), Ok(None)]));
+        let b = StreamBuilder::new(2).frame(&[avc::single(nal)]).build();
+        assert_eq!(
+            set.try_push_packet(&RtpPacket::parse(&b[0]).unwrap()),
+            Err(LimitExceeded {
+                limit: Limit::Ssrcs,
+                value: 2,
+                max: 1
+            })
+        );
+        assert_eq!(set.len(), 1);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn sharded_workers_reassemble_every_stream() {
+        assert_send::<ShardedReassembler>();
+        let sharded = ShardedReassembler::spawn(4, || {
+            let mut set = StreamSet::new();
+            set.set_codec(Some(Codec::Avc));
+            set
+        });
+        for ssrc in 0..32u32 {
+            let mut builder = StreamBuilder::new(ssrc);
+            for i in 0..3u8 {
+                builder = builder.frame(&avc::fu_a(&[0x65, ssrc as u8, i, 0xAA, 0xBB], 2));
+            }
+            for p in builder.build() {
+                sharded.push(p).unwrap();
+            }
+        }
+        let mut frames = sharded.finish();
+        frames.sort_by_key(|f| (f.ssrc, f.timestamp));
+        assert_eq!(frames.len(), 32 * 3);
+        for (i, frame) in frames.iter().enumerate() {
+            let (ssrc, n) = (i / 3, (i % 3) as u8);
+            assert_eq!(frame.ssrc, ssrc as u32);
+            assert_eq!(frame.data, [0, 0, 0, 1, 0x65, ssrc as u8, n, 0xAA, 0xBB]);
+        }
+        assert!(shard_index(7, 1) == 0 && (0..100).all(|s| shard_index(s, 4) < 4));
+    }
+}
@@ -0,0 +1,227 @@
+//! Deterministic network impairment for testing reassembly settings.
+//!
+//! [`Impairments`] turns a clean packet sequence, e.g. from a
+//! [`FramePacketizer`](crate::packetize::FramePacketizer), into the arrival
+//! order a lossy, jittery network would produce. Every random choice comes
+//! from the seed, so a run can be repeated exactly. [`Impairments::run`]
+//! feeds the result to a [`FrameReassembler`] and tallies what became of
+//! the frames.
+
+use crate::{
+    reassemble::{DropReason, FrameReassembler, PushResult},
+    replay::{RecordedPacket, Recording},
+    rtp::RtpPacket,
+};
+use std::{collections::BTreeSet, time::Duration, vec::Vec};
+
+/// Seeded impairment model. Probabilities are per packet, in `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Impairments {
+    pub seed: u64,
+    pub loss: f64,
+    /// Chance a packet is delivered twice, one interval apart.
+    pub duplicate: f64,
+    /// Chance a packet is held back by 1 to `reorder_depth` intervals.
+    pub reorder: f64,
+    pub reorder_depth: u32,
+    /// Spacing of the packets when sent.
+    pub interval: Duration,
+    /// Upper bound of the uniform extra delay every packet gets.
+    pub jitter: Duration,
+}
+
+impl Default for Impairments {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            loss: 0.0,
+            duplicate: 0.0,
+            reorder: 0.0,
+            reorder_depth: 0,
+            interval: Duration::from_millis(1),
+            jitter: Duration::ZERO,
+        }
+    }
+}
+
+/// Outcome of [`Impairments::run`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimReport {
+    pub packets_sent: usize,
+    pub packets_lost: usize,
+    pub packets_duplicated: usize,
+    /// Packets that arrived after one sent later.
+    pub packets_reordered: usize,
+    /// Distinct RTP timestamps sent.
+    pub frames_sent: usize,
+    pub frames_completed: usize,
+    /// Frames completed but dropped as incomplete, in order.
+    pub frames_dropped: Vec<DropReason>,
+    /// Pending frames evicted to stay within the configured limits.
+    pub frames_evicted: usize,
+    pub too_old: usize,
+    pub duplicates: usize,
+}
+
+impl SimReport {
+    /// Frames that never came out complete, whatever the reason.
+    pub fn frames_lost(&self) -> usize {
+        self.frames_sent.saturating_sub(self.frames_completed)
+    }
+}
+
+impl Impairments {
+    /// Arrival times and order of `packets` through this network.
+    pub fn apply(&self, packets: &[Vec<u8>]) -> Recording {
+        self.apply_counting(packets).0
+    }
+
+    /// Impairs `packets` and pushes them into `reassembler` in arrival order.
+    pub fn run(&self, packets: &[Vec<u8>], reassembler: &mut FrameReassembler) -> SimReport {
+        let (recording, mut report) = self.apply_counting(packets);
+        report.frames_sent = packets
+            .iter()
+            .filter_map(|p| RtpPacket::parse(p).ok())
+            .map(|p| p.header.timestamp)
+            .collect::<BTreeSet<_>>()
+            .len();
+        for packet in &recording.packets {
+            let Ok(pkt) = RtpPacket::parse(&packet.data) else {
+                continue;
+            };
+            match reassembler.push_packet_result(&pkt) {
+                PushResult::FrameCompleted(_) => report.frames_completed += 1,
+                PushResult::FrameDropped(reason) => report.frames_dropped.push(reason),
+                PushResult::EvictedFrame(_) => report.frames_evicted += 1,
+                PushResult::TooOld => report.too_old += 1,
+                PushResult::Duplicate => report.duplicates += 1,
+                _ => {}
+            }
+        }
+        report
+    }
+
+    fn apply_counting(&self, packets: &[Vec<u8>]) -> (Recording, SimReport) {
+        let mut rng = SplitMix64(self.seed);
+        let mut report = SimReport {
+            packets_sent: packets.len(),
+            ..SimReport::default()
+        };
+        // (arrival, send index, data)
+        let mut arrivals = Vec::with_capacity(packets.len());
+        for (i, data) in packets.iter().enumerate() {
+            if rng.chance(self.loss) {
+                report.packets_lost += 1;
+                continue;
+            }
+            let mut arrival = self.interval * i as u32 + self.jitter.mul_f64(rng.unit());
+            if self.reorder_depth > 0 && rng.chance(self.reorder) {
+                arrival += self.interval * (1 + rng.below(self.reorder_depth));
+            }
+            if rng.chance(self.duplicate) {
+                report.packets_duplicated += 1;
+                arrivals.push((arrival + self.interval, i, data));
+            }
+            arrivals.push((arrival, i, data));
+        }
+        arrivals.sort_by_key(|&(arrival, i, _)| (arrival, i));
+        let mut latest = None;
+        let mut seen = BTreeSet::new();
+        for &(_, i, _) in &arrivals {
+            if seen.insert(i) && latest.is_some_and(|latest| i < latest) {
+                report.packets_reordered += 1;
+            }
+            latest = latest.max(Some(i));
+        }
+        let packets = arrivals
+            .into_iter()
+            .map(|(arrival, _, data)| RecordedPacket {
+                arrival,
+                data: data.clone(),
+            })
+            .collect();
+        (Recording { packets }, report)
+    }
+}
+
+// SplitMix64: tiny, seedable and good enough for test impairments.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1).
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.unit() < probability
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        (self.unit() * n as f64) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        codecs::Codec,
+        test_utils::{avc, fixtures::AVC_NON_IDR, StreamBuilder},
+    };
+
+    fn packets() -> Vec<Vec<u8>> {
+        (0..30)
+            .fold(StreamBuilder::new(5), |b, _| {
+                b.frame(&avc::fu_a(AVC_NON_IDR, 3))
+            })
+            .build()
+    }
+
+    fn run(impairments: Impairments) -> SimReport {
+        let mut r = FrameReassembler::builder()
+            .codec(Codec::Avc)
+            .build()
+            .unwrap();
+        impairments.run(&packets(), &mut r)
+    }
+
+    #[test]
+    fn duplicates_are_absorbed() {
+        let clean = run(Impairments::default());
+        assert_eq!((clean.frames_sent, clean.frames_completed), (30, 30));
+
+        let report = run(Impairments {
+            seed: 7,
+            duplicate: 0.1,
+            jitter: Duration::from_micros(500),
+            ..Impairments::default()
+        });
+        assert!(report.packets_duplicated > 0 && report.duplicates > 0);
+        assert_eq!(report.frames_lost(), 0);
+    }
+
+    #[test]
+    fn impairments_are_reproducible_from_the_seed() {
+        let lossy = Impairments {
+            seed: 42,
+            loss: 0.05,
+            reorder: 0.2,
+            reorder_depth: 3,
+            ..Impairments::default()
+        };
+        let report = run(lossy);
+        assert!(report.packets_lost > 0 && report.packets_reordered > 0);
+        assert!(report.frames_lost() > 0);
+        assert_eq!(run(lossy), report);
+        assert_eq!(lossy.apply(&packets()), lossy.apply(&packets()));
+    }
+}
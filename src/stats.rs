@@ -0,0 +1,631 @@
+//! Receiver statistics and RTCP receiver reports (RFC 3550 section 6.4).
+//!
+//! [`ReceiverStats`] follows one SSRC: sequence number extension, loss and
+//! interarrival jitter as in appendix A of RFC 3550, plus the drift of the
+//! sender's media clock against the arrival clock. Arrival times are
+//! supplied by the caller, so the tracker works without `std`. Told when
+//! a frame comes out, it also keeps [`Histogram`]s of frame assembly time
+//! and reorder depth for jitter buffer tuning.
+//! [`ReceiverStats::report_block`] turns the counters into a
+//! [`ReportBlock`], and [`receiver_report`] serializes blocks into a
+//! ready-to-send RR packet. [`SenderReport`] is the sender info of an
+//! incoming SR, and [`NackList`] keeps the sequence numbers to NACK.
+
+pub use crate::rtcp::{is_rtcp, ReportBlock, SenderReport, RTCP_BYE, RTCP_RR, RTCP_SR};
+use crate::{
+    clock::{MediaTime, TimestampUnwrapper},
+    rtp::RtpHeader,
+};
+use alloc::{collections::VecDeque, vec::Vec};
+use core::time::Duration;
+
+const MAX_DROPOUT: u16 = 3000;
+const MAX_MISORDER: u16 = 100;
+const RTP_SEQ_MOD: u32 = 1 << 16;
+const MAX_NACKS: usize = 256;
+// Frames whose first packet arrival is remembered until they complete.
+const MAX_TIMED_FRAMES: usize = 32;
+// Histogram buckets per power of two above the exact range.
+const SUB_BUCKETS: u64 = 8;
+// Span over which the least delayed packet is picked for drift estimation.
+const DRIFT_WINDOW: Duration = Duration::from_secs(10);
+// A transit change this large is a timestamp jump, not drift.
+const MAX_TRANSIT_JUMP_NANOS: i64 = 1_000_000_000;
+
+/// Loss and jitter counters of one received SSRC.
+#[derive(Debug, Clone)]
+pub struct ReceiverStats {
+    clock_rate: u32,
+    base_seq: u32,
+    max_seq: u16,
+    // Sequence number wraparounds, shifted left by 16.
+    cycles: u32,
+    // Sequence number after a large jump; a second packet continuing from
+    // it means the sender restarted.
+    bad_seq: Option<u16>,
+    received: u64,
+    expected_prior: u64,
+    received_prior: u64,
+    // Relative transit time of the previous packet, in timestamp units.
+    transit: Option<u32>,
+    // Interarrival jitter scaled by 16.
+    jitter: u32,
+    // Middle 32 bits of the last SR's NTP timestamp and when it arrived.
+    last_sr: Option<(u32, Duration)>,
+    drift: ClockDrift,
+    // Frames not yet completed, oldest first.
+    pending_frames: VecDeque<TimedFrame>,
+    assembly_time: Histogram,
+    reorder_depth: Histogram,
+    started: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TimedFrame {
+    timestamp: u32,
+    first_arrival: Duration,
+    // Most packets any of its packets arrived behind the newest.
+    reorder_depth: u16,
+}
+
+// Sender clock drift from the slope of the transit time (arrival minus
+// media time). Each window keeps its least delayed packet, so queuing
+// delay mostly drops out; the slope runs from the first window to the
+// latest one, growing more precise as the stream goes on.
+#[derive(Debug, Clone, Default)]
+struct ClockDrift {
+    unwrapper: TimestampUnwrapper,
+    // (arrival, transit nanos) of the minimum of the first finished window.
+    reference: Option<(Duration, i64)>,
+    window_start: Option<Duration>,
+    window_min: Option<(Duration, i64)>,
+    ppm: Option<f64>,
+}
+
+impl ClockDrift {
+    fn on_packet(&mut self, time: MediaTime, timestamp: u32, arrival: Duration) {
+        let mut transit =
+            arrival.as_nanos() as i64 - time.ticks_to_nanos(self.unwrapper.unwrap(timestamp));
+        let baseline = self.reference.or(self.window_min);
+        if baseline.is_some_and(|(_, t)| (transit - t).abs() > MAX_TRANSIT_JUMP_NANOS) {
+            *self = Self::default();
+            transit =
+                arrival.as_nanos() as i64 - time.ticks_to_nanos(self.unwrapper.unwrap(timestamp));
+        }
+        let start = *self.window_start.get_or_insert(arrival);
+        if self.window_min.is_none_or(|(_, min)| transit < min) {
+            self.window_min = Some((arrival, transit));
+        }
+        if arrival.saturating_sub(start) < DRIFT_WINDOW {
+            return;
+        }
+        let min = self.window_min.take();
+        self.window_start = Some(arrival);
+        match (self.reference, min) {
+            (None, min) => self.reference = min,
+            (Some((first_arrival, first)), Some((last_arrival, last))) => {
+                let elapsed = last_arrival.saturating_sub(first_arrival).as_nanos() as f64;
+                // Transit shrinks when the sender's clock runs fast.
+                self.ppm = (elapsed > 0.0).then(|| (first - last) as f64 * 1e6 / elapsed);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl ReceiverStats {
+    /// `clock_rate` is the RTP timestamp rate, e.g. `Codec::clock_rate()`.
+    pub fn new(clock_rate: u32) -> Self {
+        Self {
+            clock_rate,
+            base_seq: 0,
+            max_seq: 0,
+            cycles: 0,
+            bad_seq: None,
+            received: 0,
+            expected_prior: 0,
+            received_prior: 0,
+            transit: None,
+            jitter: 0,
+            last_sr: None,
+            drift: ClockDrift::default(),
+            pending_frames: VecDeque::new(),
+            assembly_time: Histogram::new(),
+            reorder_depth: Histogram::new(),
+            started: false,
+        }
+    }
+
+    /// Records a packet that arrived at `arrival` (any monotonic clock).
+    pub fn on_packet(&mut self, header: &RtpHeader, arrival: Duration) {
+        let seq = header.sequence_number;
+        let mut behind = 0;
+        if !self.started {
+            self.restart(seq);
+        } else {
+            let delta = seq.wrapping_sub(self.max_seq);
+            if delta < MAX_DROPOUT {
+                if seq < self.max_seq {
+                    self.cycles = self.cycles.wrapping_add(RTP_SEQ_MOD);
+                }
+                self.max_seq = seq;
+            } else if delta <= (RTP_SEQ_MOD - MAX_MISORDER as u32) as u16 {
+                // Large jump: accept it only once a second packet follows.
+                if self.bad_seq == Some(seq) {
+                    self.restart(seq);
+                } else {
+                    self.bad_seq = Some(seq.wrapping_add(1));
+                    return;
+                }
+            } else {
+                // A duplicate or reordered packet.
+                behind = self.max_seq.wrapping_sub(seq);
+            }
+        }
+        self.received += 1;
+        self.time_frame(header.timestamp, arrival, behind);
+        self.update_jitter(header.timestamp, arrival);
+        self.drift
+            .on_packet(MediaTime::new(self.clock_rate), header.timestamp, arrival);
+    }
+
+    fn time_frame(&mut self, timestamp: u32, arrival: Duration, behind: u16) {
+        match self
+            .pending_frames
+            .iter_mut()
+            .find(|f| f.timestamp == timestamp)
+        {
+            Some(frame) => frame.reorder_depth = frame.reorder_depth.max(behind),
+            None => {
+                if self.pending_frames.len() == MAX_TIMED_FRAMES {
+                    self.pending_frames.pop_front();
+                }
+                self.pending_frames.push_back(TimedFrame {
+                    timestamp,
+                    first_arrival: arrival,
+                    reorder_depth: behind,
+                });
+            }
+        }
+    }
+
+    /// Records that the frame at `timestamp` was emitted at `now`, adding
+    /// the time since its first packet arrived and its reorder depth to the
+    /// histograms.
+    pub fn on_frame(&mut self, timestamp: u32, now: Duration) {
+        let Some(i) = self
+            .pending_frames
+            .iter()
+            .position(|f| f.timestamp == timestamp)
+        else {
+            return;
+        };
+        let frame = self.pending_frames.remove(i).unwrap();
+        let assembly = now.saturating_sub(frame.first_arrival);
+        self.assembly_time.record(assembly.as_micros() as u64);
+        self.reorder_depth.record(frame.reorder_depth as u64);
+    }
+
+    /// Time from a frame's first packet arriving to its emission, in
+    /// microseconds, over the frames passed to [`on_frame`](Self::on_frame).
+    pub fn assembly_time(&self) -> &Histogram {
+        &self.assembly_time
+    }
+
+    /// Most packets one of a frame's packets arrived behind the newest
+    /// packet, over the frames passed to [`on_frame`](Self::on_frame).
+    pub fn reorder_depth(&self) -> &Histogram {
+        &self.reorder_depth
+    }
+
+    /// Records a sender report from this SSRC carrying `ntp_timestamp`.
+    pub fn on_sender_report(&mut self, ntp_timestamp: u64, arrival: Duration) {
+        self.last_sr = Some(((ntp_timestamp >> 16) as u32, arrival));
+    }
+
+    fn restart(&mut self, seq: u16) {
+        self.started = true;
+        self.base_seq = seq as u32;
+        self.max_seq = seq;
+        self.cycles = 0;
+        self.bad_seq = None;
+        self.received = 0;
+        self.expected_prior = 0;
+        self.received_prior = 0;
+        self.transit = None;
+        self.drift = ClockDrift::default();
+    }
+
+    fn update_jitter(&mut self, timestamp: u32, arrival: Duration) {
+        // Truncation is the intended modulo-2^32 wrap.
+        let arrival_ts = MediaTime::new(self.clock_rate).duration_to_ticks(arrival) as u32;
+        let transit = arrival_ts.wrapping_sub(timestamp);
+        if let Some(previous) = self.transit {
+            let d = MediaTime::ticks_between(transit, previous).unsigned_abs();
+            // J += (|D| - J) / 16, kept scaled by 16.
+            self.jitter = (self.jitter + d).saturating_sub((self.jitter + 8) >> 4);
+        }
+        self.transit = Some(transit);
+    }
+
+    /// Highest sequence number received, extended with the wraparound count.
+    pub fn extended_highest_seq(&self) -> u32 {
+        self.cycles | self.max_seq as u32
+    }
+
+    /// Packets received, duplicates included.
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    fn expected(&self) -> u64 {
+        if !self.started {
+            return 0;
+        }
+        (self.extended_highest_seq() as u64 + 1).saturating_sub(self.base_seq as u64)
+    }
+
+    /// Packets expected but not received; negative with duplicates.
+    pub fn cumulative_lost(&self) -> i64 {
+        self.expected() as i64 - self.received as i64
+    }
+
+    /// Interarrival jitter in timestamp units.
+    pub fn jitter(&self) -> u32 {
+        self.jitter >> 4
+    }
+
+    /// How fast the sender's media clock runs against the arrival clock, in
+    /// parts per million (positive: fast). Available after two 10 s windows.
+    pub fn clock_drift_ppm(&self) -> Option<f64> {
+        self.drift.ppm
+    }
+
+    /// Report block for this source as `ssrc`, as of `now` (same clock as
+    /// the arrival times). Starts a new interval for the fraction lost.
+    pub fn report_block(&mut self, ssrc: u32, now: Duration) -> ReportBlock {
+        let expected = self.expected();
+        let expected_interval = expected - self.expected_prior;
+        let received_interval = self.received - self.received_prior;
+        self.expected_prior = expected;
+        self.received_prior = self.received;
+        let lost_interval = expected_interval as i64 - received_interval as i64;
+        let fraction_lost = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / expected_interval as i64) as u8
+        };
+        let (last_sr, delay_since_last_sr) = match self.last_sr {
+            Some((lsr, arrival)) => {
+                let delay = now.saturating_sub(arrival);
+                let units = delay.as_nanos() * 65536 / 1_000_000_000;
+                (lsr, units.min(u32::MAX as u128) as u32)
+            }
+            None => (0, 0),
+        };
+        ReportBlock {
+            ssrc,
+            fraction_lost,
+            cumulative_lost: self.cumulative_lost().clamp(-0x80_0000, 0x7F_FFFF) as i32,
+            extended_highest_seq: self.extended_highest_seq(),
+            jitter: self.jitter(),
+            last_sr,
+            delay_since_last_sr,
+        }
+    }
+}
+
+/// Distribution of non-negative values in log-linear buckets: exact below
+/// 16, then eight per power of two, so quantiles are within 12.5 %.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    max: u64,
+}
+
+/// Quantiles of a [`Histogram`], each the upper bound of its bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Percentiles {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, value: u64) {
+        let bucket = Self::bucket(value);
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.max = self.max.max(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Smallest bucket bound at or above a `quantile` (`0.0..=1.0`) of the
+    /// values, capped at the largest value; `None` when empty.
+    pub fn quantile(&self, quantile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64) as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Some(Self::upper_bound(bucket).min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    pub fn percentiles(&self) -> Option<Percentiles> {
+        Some(Percentiles {
+            p50: self.quantile(0.5)?,
+            p95: self.quantile(0.95)?,
+            p99: self.quantile(0.99)?,
+        })
+    }
+
+    fn bucket(value: u64) -> usize {
+        if value < 2 * SUB_BUCKETS {
+            return value as usize;
+        }
+        let octave = 63 - value.leading_zeros() as u64;
+        let sub = (value >> (octave - 3)) & (SUB_BUCKETS - 1);
+        (2 * SUB_BUCKETS + (octave - 4) * SUB_BUCKETS + sub) as usize
+    }
+
+    fn upper_bound(bucket: usize) -> u64 {
+        let bucket = bucket as u64;
+        if bucket < 2 * SUB_BUCKETS {
+            return bucket;
+        }
+        let octave = (bucket - 2 * SUB_BUCKETS) / SUB_BUCKETS + 4;
+        let sub = bucket % SUB_BUCKETS;
+        let width = 1u64 << (octave - 3);
+        ((SUB_BUCKETS + sub) << (octave - 3)).saturating_add(width - 1)
+    }
+}
+
+/// Sequence numbers still missing from one SSRC, for generic NACKs
+/// (RFC 4585). Only the most recent 256 gaps are kept.
+#[derive(Debug, Clone, Default)]
+pub struct NackList {
+    highest: Option<u16>,
+    missing: VecDeque<u16>,
+}
+
+impl NackList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_packet(&mut self, seq: u16) {
+        let Some(highest) = self.highest else {
+            self.highest = Some(seq);
+            return;
+        };
+        let delta = seq.wrapping_sub(highest);
+        if delta == 0 {
+            return;
+        }
+        if delta < MAX_DROPOUT {
+            let first = (delta as usize).saturating_sub(MAX_NACKS).max(1) as u16;
+            self.missing
+                .extend((first..delta).map(|n| highest.wrapping_add(n)));
+            let excess = self.missing.len().saturating_sub(MAX_NACKS);
+            self.missing.drain(..excess);
+            self.highest = Some(seq);
+        } else if delta <= (RTP_SEQ_MOD - MAX_MISORDER as u32) as u16 {
+            // The sender restarted; earlier gaps no longer matter.
+            self.missing.clear();
+            self.highest = Some(seq);
+        } else {
+            self.missing.retain(|&missing| missing != seq);
+        }
+    }
+
+    /// Oldest first.
+    pub fn missing(&self) -> impl Iterator<Item = u16> + '_ {
+        self.missing.iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Serializes an RTCP receiver report from `reporter_ssrc`. At most 31
+/// blocks fit one report; the rest are left out.
+pub fn receiver_report(reporter_ssrc: u32, blocks: &[ReportBlock]) -> Vec<u8> {
+    let blocks = &blocks[..blocks.len().min(31)];
+    let len = 8 + blocks.len() * ReportBlock::LEN;
+    let mut out = Vec::with_capacity(len);
+    out.push(0x80 | blocks.len() as u8);
+    out.push(RTCP_RR);
+    out.extend_from_slice(&((len / 4 - 1) as u16).to_be_bytes());
+    out.extend_from_slice(&reporter_ssrc.to_be_bytes());
+    for block in blocks {
+        block.write_to(&mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rtcp::RtcpPacket, rtp::RtpPacket, test_utils::RtpPacketBuilder};
+
+    fn header(seq: u16, timestamp: u32) -> RtpHeader {
+        let bytes = RtpPacketBuilder::new()
+            .sequence_number(seq)
+            .timestamp(timestamp)
+            .build();
+        RtpPacket::parse(&bytes).unwrap().header
+    }
+
+    #[test]
+    fn tracks_loss_across_wraparound() {
+        let mut stats = ReceiverStats::new(90_000);
+        let ms = Duration::from_millis;
+        // 65534..=3 with 65535 and 2 lost.
+        for (i, seq) in [65534u16, 0, 1, 3].into_iter().enumerate() {
+            stats.on_packet(&header(seq, 0), ms(i as u64));
+        }
+        assert_eq!(stats.extended_highest_seq(), 0x1_0003);
+        assert_eq!(stats.cumulative_lost(), 2);
+        let block = stats.report_block(7, ms(10));
+        // 2 of 6 lost: 512 / 6.
+        assert_eq!(block.fraction_lost, 85);
+        assert_eq!((block.last_sr, block.delay_since_last_sr), (0, 0));
+
+        // A late duplicate makes the next interval lossless.
+        stats.on_packet(&header(1, 0), ms(11));
+        stats.on_packet(&header(4, 0), ms(12));
+        let block = stats.report_block(7, ms(20));
+        assert_eq!((block.fraction_lost, block.cumulative_lost), (0, 1));
+    }
+
+    #[test]
+    fn jitter_and_sender_report_delay() {
+        let mut stats = ReceiverStats::new(90_000);
+        // 3000 ticks (33.3 ms) per frame, every other one arriving 10 ms late.
+        for i in 0..64u32 {
+            let late = if i % 2 == 1 { 10 } else { 0 };
+            let arrival =
+                Duration::from_micros(i as u64 * 100_000 / 3) + Duration::from_millis(late);
+            stats.on_packet(&header(i as u16, i * 3000), arrival);
+        }
+        // Converges towards |D| = 900 ticks.
+        assert!((800..=900).contains(&stats.jitter()), "{}", stats.jitter());
+
+        stats.on_sender_report(0x1122_3344_5566_7788, Duration::from_secs(1));
+        let block = stats.report_block(9, Duration::from_millis(1500));
+        assert_eq!(block.last_sr, 0x3344_5566);
+        assert_eq!(block.delay_since_last_sr, 32768);
+    }
+
+    #[test]
+    fn estimates_sender_clock_drift() {
+        let mut stats = ReceiverStats::new(90_000);
+        // 30 fps from a camera clock 100 ppm fast, with up to 7 ms of
+        // queuing delay.
+        let mut ticks = 0.0f64;
+        for i in 0..1800u64 {
+            let queued = (i * 7919 % 8) * 1_000_000;
+            let arrival = Duration::from_nanos(i * 33_333_333 + queued);
+            stats.on_packet(&header(i as u16, ticks as u32), arrival);
+            ticks += 3000.0 * 1.0001;
+            if i == 300 {
+                assert_eq!(stats.clock_drift_ppm(), None);
+            }
+        }
+        let ppm = stats.clock_drift_ppm().unwrap();
+        assert!((ppm - 100.0).abs() < 5.0, "{}", ppm);
+    }
+
+    #[test]
+    fn frame_timing_histograms() {
+        let ms = Duration::from_millis;
+        let mut stats = ReceiverStats::new(90_000);
+        // Frame 0 in order over 4 ms; frame 1 with its first packet two
+        // behind, completing 6 ms after its first arrival.
+        for (seq, ts, at) in [
+            (0, 0, 0),
+            (1, 0, 4),
+            (3, 3000, 10),
+            (4, 3000, 11),
+            (2, 3000, 16),
+        ] {
+            stats.on_packet(&header(seq, ts), ms(at));
+        }
+        stats.on_frame(0, ms(4));
+        stats.on_frame(3000, ms(16));
+        stats.on_frame(6000, ms(20));
+        assert_eq!(stats.assembly_time().count(), 2);
+        // 4 ms lands in the 3840..=4095 µs bucket.
+        assert_eq!(stats.assembly_time().quantile(0.5), Some(4095));
+        assert_eq!(stats.assembly_time().max(), 6000);
+        assert_eq!(stats.reorder_depth().quantile(1.0), Some(2));
+
+        let mut histogram = Histogram::new();
+        assert_eq!(histogram.percentiles(), None);
+        for v in 1..=1000 {
+            histogram.record(v);
+        }
+        let p = histogram.percentiles().unwrap();
+        assert!((500..=575).contains(&p.p50), "{:?}", p);
+        assert!(
+            (950..=1000).contains(&p.p95) && (990..=1000).contains(&p.p99),
+            "{:?}",
+            p
+        );
+    }
+
+    #[test]
+    fn serializes_receiver_report() {
+        let block = ReportBlock {
+            ssrc: 0x0102_0304,
+            fraction_lost: 0x40,
+            cumulative_lost: -1,
+            extended_highest_seq: 0x0001_0005,
+            jitter: 12,
+            last_sr: 0xAABB_CCDD,
+            delay_since_last_sr: 0x10,
+        };
+        let rr = receiver_report(0xDEAD_BEEF, &[block]);
+        assert_eq!(rr.len(), 32);
+        assert_eq!(rr[..8], [0x81, 201, 0, 7, 0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(rr[8..16], [1, 2, 3, 4, 0x40, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(rr[28..], [0, 0, 0, 0x10]);
+
+        let mut sr = alloc::vec![0x80, RTCP_SR, 0, 6];
+        for word in [7u32, 0x1122_3344, 0x5566_7788, 9000, 10, 1200] {
+            sr.extend_from_slice(&word.to_be_bytes());
+        }
+        let mut compound = sr.clone();
+        compound.extend_from_slice(&[0x81, RTCP_BYE, 0, 1, 0, 0, 0, 7]);
+        assert!(is_rtcp(&compound));
+        let packets: Vec<_> = RtcpPacket::parse_compound(&compound)
+            .map(Result::unwrap)
+            .collect();
+        let [RtcpPacket::SenderReport(report), RtcpPacket::Bye(bye)] = &packets[..] else {
+            panic!("{:?}", packets);
+        };
+        assert_eq!(report.sender.ntp_timestamp, 0x1122_3344_5566_7788);
+        assert_eq!((report.sender.ssrc, report.sender.rtp_timestamp), (7, 9000));
+        assert_eq!(bye.sources().collect::<Vec<_>>(), [7]);
+        let Ok(RtcpPacket::ReceiverReport(parsed)) = RtcpPacket::parse(&rr) else {
+            panic!("not an RR");
+        };
+        assert_eq!(parsed.reports.collect::<Vec<_>>(), [block]);
+    }
+
+    #[test]
+    fn nack_list_follows_gaps_and_recoveries() {
+        let mut nacks = NackList::new();
+        for seq in [65533, 65534, 1, 4, 0, 65535] {
+            nacks.on_packet(seq);
+        }
+        assert_eq!(nacks.missing().collect::<Vec<_>>(), [2, 3]);
+        nacks.on_packet(2000);
+        assert_eq!(nacks.missing().count(), MAX_NACKS);
+        nacks.on_packet(40_000);
+        assert!(nacks.is_empty());
+    }
+}
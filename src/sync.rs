@@ -0,0 +1,170 @@
+//! Lip sync across the streams of one sender.
+//!
+//! A sender's streams share its wall clock: RTCP sender reports and the
+//! abs-capture-time extension map each stream's RTP timestamps onto it.
+//! [`StreamSync`] keeps a [`MediaClock`] per SSRC so audio packets and
+//! video frames get presentation times on that common clock. Each new
+//! mapping is compared with what the previous one extrapolates to; a
+//! difference beyond the threshold means the sender's RTP and wall clocks
+//! drift apart (or it restarted), and the stream's times are flagged until
+//! the mappings agree again.
+
+use crate::{clock::MediaClock, extensions::AbsCaptureTime, rtcp::SenderReport};
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+
+/// Wall-clock time of a frame or audio packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PresentationTime {
+    /// Capture time on the sender's clock, since the NTP epoch.
+    pub wall_clock: Duration,
+    /// The stream's last mapping moved by more than the drift threshold.
+    pub drifted: bool,
+}
+
+#[derive(Debug, Clone)]
+struct SyncedStream {
+    clock: MediaClock,
+    drift: Option<Duration>,
+}
+
+/// Presentation times for several SSRCs on their sender's wall clock.
+#[derive(Debug, Clone)]
+pub struct StreamSync {
+    streams: BTreeMap<u32, SyncedStream>,
+    drift_threshold: Duration,
+}
+
+impl StreamSync {
+    pub fn new(drift_threshold: Duration) -> Self {
+        Self {
+            streams: BTreeMap::new(),
+            drift_threshold,
+        }
+    }
+
+    /// Starts following `ssrc`, whose RTP clock runs at `clock_rate`;
+    /// forgets its mapping if it was already followed.
+    pub fn add_stream(&mut self, ssrc: u32, clock_rate: u32) {
+        let stream = SyncedStream {
+            clock: MediaClock::new(clock_rate),
+            drift: None,
+        };
+        self.streams.insert(ssrc, stream);
+    }
+
+    pub fn remove_stream(&mut self, ssrc: u32) {
+        self.streams.remove(&ssrc);
+    }
+
+    /// Maps the report's stream onto the wall clock; returns how far the
+    /// previous mapping was off, `None` for the first one or an unknown SSRC.
+    pub fn on_sender_report(
+        &mut self,
+        report: &SenderReport,
+        arrival: Duration,
+    ) -> Option<Duration> {
+        self.remap(report.ssrc, report.rtp_timestamp, |clock| {
+            clock.on_sender_report(report.rtp_timestamp, report.ntp_timestamp, arrival, None)
+        })
+    }
+
+    /// As [`on_sender_report`](Self::on_sender_report), from the
+    /// abs-capture-time extension of a packet of `ssrc`.
+    pub fn on_capture_time(
+        &mut self,
+        ssrc: u32,
+        rtp_timestamp: u32,
+        capture: &AbsCaptureTime,
+    ) -> Option<Duration> {
+        self.remap(ssrc, rtp_timestamp, |clock| {
+            clock.on_capture_time(rtp_timestamp, capture)
+        })
+    }
+
+    fn remap(
+        &mut self,
+        ssrc: u32,
+        rtp_timestamp: u32,
+        anchor: impl FnOnce(&mut MediaClock),
+    ) -> Option<Duration> {
+        let stream = self.streams.get_mut(&ssrc)?;
+        let predicted = stream.clock.capture_time(rtp_timestamp);
+        anchor(&mut stream.clock);
+        let actual = stream.clock.capture_time(rtp_timestamp);
+        let drift = predicted.zip(actual).map(|(p, a)| p.abs_diff(a));
+        if drift.is_some() {
+            stream.drift = drift;
+        }
+        drift
+    }
+
+    /// Last drift measured for `ssrc`.
+    pub fn drift(&self, ssrc: u32) -> Option<Duration> {
+        self.streams.get(&ssrc)?.drift
+    }
+
+    /// Wall-clock time of `rtp_timestamp` on `ssrc`, once the stream has
+    /// been mapped.
+    pub fn presentation_time(&self, ssrc: u32, rtp_timestamp: u32) -> Option<PresentationTime> {
+        let stream = self.streams.get(&ssrc)?;
+        Some(PresentationTime {
+            wall_clock: stream.clock.capture_time(rtp_timestamp)?,
+            drifted: stream.drift.is_some_and(|d| d > self.drift_threshold),
+        })
+    }
+
+    pub fn clock(&self, ssrc: u32) -> Option<&MediaClock> {
+        Some(&self.streams.get(&ssrc)?.clock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::duration_to_ntp;
+
+    fn report(ssrc: u32, ms: u64, rtp_timestamp: u32) -> SenderReport {
+        SenderReport {
+            ssrc,
+            ntp_timestamp: duration_to_ntp(Duration::from_millis(ms)),
+            rtp_timestamp,
+            packet_count: 0,
+            octet_count: 0,
+        }
+    }
+
+    #[test]
+    fn aligns_streams_and_flags_drift() {
+        let ms = Duration::from_millis;
+        let (audio, video) = (1, 2);
+        let mut sync = StreamSync::new(ms(5));
+        sync.add_stream(audio, 48_000);
+        sync.add_stream(video, 90_000);
+        assert_eq!(sync.presentation_time(video, 0), None);
+        assert_eq!(
+            sync.on_sender_report(&report(audio, 100_000, 0), ms(0)),
+            None
+        );
+        assert_eq!(
+            sync.on_sender_report(&report(video, 100_000, 500), ms(0)),
+            None
+        );
+        assert_eq!(sync.on_sender_report(&report(3, 100_000, 0), ms(0)), None);
+
+        // 100 ms in on both clocks.
+        let a = sync.presentation_time(audio, 4800).unwrap();
+        let v = sync.presentation_time(video, 9500).unwrap();
+        assert_eq!((a.wall_clock, a.drifted), (ms(100_100), false));
+        assert_eq!(v, a);
+
+        // One second later the video clock is 10 ms (900 ticks) behind.
+        let drift = sync.on_sender_report(&report(video, 101_000, 90_500 - 900), ms(1000));
+        assert_eq!(drift, Some(ms(10)));
+        assert!(sync.presentation_time(video, 90_500).unwrap().drifted);
+        let drift = sync.on_sender_report(&report(video, 102_000, 180_500 - 900), ms(2000));
+        assert_eq!(drift, Some(ms(0)));
+        assert!(!sync.presentation_time(video, 90_500).unwrap().drifted);
+    }
+}
@@ -0,0 +1,335 @@
+//! Per-stream timelines of what happened to a stream, for visualization.
+//!
+//! [`TimelineRecorder`] reassembles one stream and records each completed
+//! or dropped frame, sequence gap, codec guess, parameter set change and
+//! SSRC switch as a [`TimelineEntry`] stamped with its arrival time. The
+//! resulting [`Timeline`] can be queried by time range or event kind, and
+//! derives `serde` traits for handing to a rendering tool.
+
+use crate::{
+    bitstream::split_annex_b,
+    codecs::Codec,
+    reassemble::{nal_role, DropReason, EventKind, FrameReassembler, NalRole, PushResult},
+    rtp::RtpPacket,
+};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+// Enough for every event a single packet can cause.
+const EVENTS_PER_PACKET: usize = 16;
+
+/// Something that happened to the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimelineEvent {
+    Frame {
+        timestamp: u32,
+        size: usize,
+        keyframe: bool,
+    },
+    FrameDropped {
+        timestamp: u32,
+        reason: DropReason,
+    },
+    /// A pending frame was discarded to stay within the reassembler limits.
+    FrameEvicted {
+        timestamp: u32,
+    },
+    /// `count` sequence numbers from `first_seq` on were skipped; they may
+    /// still arrive out of order.
+    Loss {
+        first_seq: u16,
+        count: u16,
+    },
+    /// The codec was guessed from the stream.
+    CodecChanged(Codec),
+    /// A frame carried a VPS, SPS or PPS differing from the previous one.
+    ParameterSetsChanged {
+        timestamp: u32,
+    },
+    SsrcChanged {
+        old: u32,
+        new: u32,
+    },
+    /// Audio silence: a comfort noise packet with its noise level in
+    /// -dBov, if valid.
+    ComfortNoise {
+        timestamp: u32,
+        level: Option<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimelineEntry {
+    /// Arrival of the packet that caused the event.
+    pub arrival: Duration,
+    pub ssrc: u32,
+    pub event: TimelineEvent,
+}
+
+/// Events of one stream in the order their packets were pushed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timeline {
+    pub entries: Vec<TimelineEntry>,
+}
+
+impl Timeline {
+    /// Entries that arrived in `from..to`; assumes arrival times never went
+    /// backwards.
+    pub fn between(&self, from: Duration, to: Duration) -> &[TimelineEntry] {
+        let start = self.entries.partition_point(|e| e.arrival < from);
+        let end = self.entries.partition_point(|e| e.arrival < to);
+        &self.entries[start..end.max(start)]
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &TimelineEntry> {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.event, TimelineEvent::Frame { .. }))
+    }
+
+    pub fn keyframes(&self) -> impl Iterator<Item = &TimelineEntry> {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.event, TimelineEvent::Frame { keyframe: true, .. }))
+    }
+
+    /// Sequence gaps, dropped and evicted frames.
+    pub fn losses(&self) -> impl Iterator<Item = &TimelineEntry> {
+        self.entries.iter().filter(|e| {
+            matches!(
+                e.event,
+                TimelineEvent::Loss { .. }
+                    | TimelineEvent::FrameDropped { .. }
+                    | TimelineEvent::FrameEvicted { .. }
+            )
+        })
+    }
+
+    /// Time from the first entry to the last.
+    pub fn duration(&self) -> Duration {
+        match (self.entries.first(), self.entries.last()) {
+            (Some(first), Some(last)) => last.arrival.saturating_sub(first.arrival),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Builds a [`Timeline`] from the packets of one stream.
+#[derive(Debug)]
+pub struct TimelineRecorder {
+    reassembler: FrameReassembler,
+    packets: u64,
+    // Latest VPS, SPS and PPS.
+    param_sets: [Option<Vec<u8>>; 3],
+    timeline: Timeline,
+}
+
+impl TimelineRecorder {
+    /// `codec` is the stream's payload format; `None` guesses it.
+    pub fn new(codec: Option<Codec>) -> Self {
+        let mut builder = FrameReassembler::builder().event_log(EVENTS_PER_PACKET);
+        if let Some(codec) = codec {
+            builder = builder.codec(codec);
+        }
+        Self {
+            reassembler: builder.build().expect("default configuration is valid"),
+            packets: 0,
+            param_sets: Default::default(),
+            timeline: Timeline::default(),
+        }
+    }
+
+    /// Records what `pkt`, arriving at `arrival`, led to.
+    pub fn push(&mut self, pkt: &RtpPacket<'_>, arrival: Duration) {
+        let result = self.reassembler.push_packet_result(pkt);
+        let index = self.packets;
+        self.packets += 1;
+        let ssrc = pkt.header.ssrc;
+        let events: Vec<_> = self
+            .reassembler
+            .recent_events()
+            .filter(|e| e.packet_index == index)
+            .filter_map(|e| match e.kind {
+                EventKind::FrameDropped { timestamp, reason } => {
+                    Some(TimelineEvent::FrameDropped { timestamp, reason })
+                }
+                EventKind::FrameEvicted { timestamp, .. } => {
+                    Some(TimelineEvent::FrameEvicted { timestamp })
+                }
+                EventKind::SequenceGap { seq, missing } => Some(TimelineEvent::Loss {
+                    first_seq: seq.wrapping_sub(missing),
+                    count: missing,
+                }),
+                EventKind::CodecGuessed(codec) => Some(TimelineEvent::CodecChanged(codec)),
+                EventKind::SsrcChanged { old, new } => {
+                    Some(TimelineEvent::SsrcChanged { old, new })
+                }
+                EventKind::ComfortNoise { timestamp, level } => {
+                    Some(TimelineEvent::ComfortNoise { timestamp, level })
+                }
+                _ => None,
+            })
+            .collect();
+        for event in events {
+            self.record(arrival, ssrc, event);
+        }
+        if let PushResult::FrameCompleted(frame) = result {
+            let timestamp = pkt.header.timestamp;
+            let codec = self.reassembler.codec();
+            if codec.is_some_and(|c| self.param_sets_changed(c, &frame)) {
+                self.record(
+                    arrival,
+                    ssrc,
+                    TimelineEvent::ParameterSetsChanged { timestamp },
+                );
+            }
+            let keyframe = self.reassembler.frame_is_keyframe(&frame);
+            let size = frame.len();
+            self.record(
+                arrival,
+                ssrc,
+                TimelineEvent::Frame {
+                    timestamp,
+                    size,
+                    keyframe,
+                },
+            );
+        }
+    }
+
+    fn record(&mut self, arrival: Duration, ssrc: u32, event: TimelineEvent) {
+        self.timeline.entries.push(TimelineEntry {
+            arrival,
+            ssrc,
+            event,
+        });
+    }
+
+    // Updates the latest parameter sets; true when one replaced a
+    // different one.
+    fn param_sets_changed(&mut self, codec: Codec, frame: &[u8]) -> bool {
+        if !matches!(codec, Codec::Avc | Codec::Hevc) {
+            return false;
+        }
+        let mut changed = false;
+        for nal in split_annex_b(frame) {
+            let slot = match nal.first().map(|&h| nal_role(codec, h)) {
+                Some(NalRole::Vps) => 0,
+                Some(NalRole::Sps) => 1,
+                Some(NalRole::Pps) => 2,
+                _ => continue,
+            };
+            let cached = &mut self.param_sets[slot];
+            if cached.as_deref() != Some(nal) {
+                changed |= cached.is_some();
+                *cached = Some(nal.to_vec());
+            }
+        }
+        changed
+    }
+
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    pub fn into_timeline(self) -> Timeline {
+        self.timeline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        avc,
+        fixtures::{annex_b, AVC_IDR, AVC_NON_IDR, AVC_PPS, AVC_SPS},
+        RtpPacketBuilder, StreamBuilder,
+    };
+
+    #[test]
+    fn records_frames_losses_and_parameter_set_changes() {
+        let other_pps: &[u8] = &[0x68, 0xCE, 0x3C, 0x81];
+        let packets = StreamBuilder::new(1)
+            .frame(&[avc::stap_a(&[AVC_SPS, AVC_PPS]), avc::single(AVC_IDR)])
+            .frame(&avc::fu_a(AVC_NON_IDR, 2))
+            .frame(&[avc::stap_a(&[AVC_SPS, other_pps]), avc::single(AVC_IDR)])
+            .build();
+        let mut recorder = TimelineRecorder::new(Some(Codec::Avc));
+        for (i, bytes) in packets.iter().enumerate() {
+            // The second frame loses its second packet.
+            if i != 3 {
+                let pkt = RtpPacket::parse(bytes).unwrap();
+                recorder.push(&pkt, Duration::from_millis(10 * i as u64));
+            }
+        }
+        let timeline = recorder.into_timeline();
+        assert_eq!(timeline.frames().count(), 2);
+        assert_eq!(timeline.keyframes().count(), 2);
+        let losses: Vec<_> = timeline.losses().map(|e| &e.event).collect();
+        assert_eq!(
+            losses,
+            [
+                &TimelineEvent::Loss {
+                    first_seq: 3,
+                    count: 1
+                },
+                &TimelineEvent::FrameDropped {
+                    timestamp: 3000,
+                    reason: DropReason::SequenceGap
+                },
+            ]
+        );
+        let end = Duration::from_millis(10 * packets.len() as u64);
+        let last = timeline.between(end - Duration::from_millis(10), end);
+        assert_eq!(
+            last.iter().map(|e| &e.event).collect::<Vec<_>>(),
+            [
+                &TimelineEvent::ParameterSetsChanged { timestamp: 6000 },
+                &TimelineEvent::Frame {
+                    timestamp: 6000,
+                    size: annex_b(&[AVC_SPS, other_pps, AVC_IDR]).len(),
+                    keyframe: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn comfort_noise_is_not_guessed_as_video() {
+        let mut recorder = TimelineRecorder::new(None);
+        let cn = |pt, seq| {
+            RtpPacketBuilder::new()
+                .payload_type(pt)
+                .sequence_number(seq)
+                .timestamp(160 * seq as u32)
+                .payload(&[0x3F])
+                .build()
+        };
+        for (seq, pt) in [(1, 13), (2, 118)] {
+            let bytes = cn(pt, seq);
+            recorder.push(&RtpPacket::parse(&bytes).unwrap(), Duration::ZERO);
+        }
+        // Dynamic types need configuring.
+        let entries = &recorder.timeline().entries;
+        assert_eq!(
+            entries[0].event,
+            TimelineEvent::ComfortNoise {
+                timestamp: 160,
+                level: Some(0x3F)
+            }
+        );
+        assert!(matches!(entries[1].event, TimelineEvent::CodecChanged(_)));
+
+        let mut r = FrameReassembler::builder()
+            .comfort_noise_payload_type(118)
+            .build()
+            .unwrap();
+        let bytes = cn(118, 3);
+        let pkt = RtpPacket::parse(&bytes).unwrap();
+        assert_eq!(r.push_packet_result(&pkt), PushResult::ComfortNoise);
+        assert_eq!(r.codec(), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! `wasm-bindgen` wrapper around the parse/analyze/reassemble path.
+//!
+//! Enabled with the `wasm` feature. The wrapped core never touches
+//! `std::time`, sockets or threads, so it builds for `wasm32-unknown-unknown`
+//! as-is; this module only adapts the API to JavaScript-friendly types.
+
+use crate::{
+    analyze::{FrameAnalyzer, FrameBoundary},
+    codecs::Codec,
+    reassemble::FrameReassembler,
+    rtp::RtpPacket,
+};
+use wasm_bindgen::prelude::*;
+
+fn codec_name(codec: Option<Codec>) -> String {
+    codec
+        .unwrap_or(Codec::Unknown)
+        .to_string()
+        .to_ascii_lowercase()
+}
+
+/// Parsed RTP header fields of a single packet.
+#[wasm_bindgen]
+pub struct PacketInfo {
+    pub marker: bool,
+    pub payload_type: u8,
+    pub sequence_number: u16,
+    pub timestamp: u32,
+    pub ssrc: u32,
+    pub payload_offset: usize,
+    pub payload_len: usize,
+}
+
+/// Parses the RTP header of `packet`.
+#[wasm_bindgen(js_name = parsePacket)]
+pub fn parse_packet(packet: &[u8]) -> Result<PacketInfo, JsError> {
+    let pkt = RtpPacket::parse(packet).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(PacketInfo {
+        marker: pkt.header.marker,
+        payload_type: pkt.header.payload_type,
+        sequence_number: pkt.header.sequence_number,
+        timestamp: pkt.header.timestamp,
+        ssrc: pkt.header.ssrc,
+        payload_offset: pkt.payload_offset,
+        payload_len: pkt.payload.len(),
+    })
+}
+
+/// Frame boundary analyzer; `analyze` returns "none", "start", "end" or "start-end".
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Analyzer {
+    inner: FrameAnalyzer,
+}
+
+#[wasm_bindgen]
+impl Analyzer {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current codec name, either forced or guessed from the packets seen so far.
+    pub fn codec(&self) -> String {
+        codec_name(self.inner.codec())
+    }
+
+    pub fn analyze(&mut self, packet: &[u8]) -> Result<String, JsError> {
+        let pkt = RtpPacket::parse(packet).map_err(|e| JsError::new(&e.to_string()))?;
+        let boundary = match self.inner.analyze(&pkt) {
+            FrameBoundary::None => "none",
+            FrameBoundary::Start => "start",
+            FrameBoundary::End => "end",
+            FrameBoundary::StartEnd => "start-end",
+        };
+        Ok(boundary.to_string())
+    }
+}
+
+/// Frame reassembler producing codec bitstream bytes per completed frame.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Reassembler {
+    inner: FrameReassembler,
+}
+
+#[wasm_bindgen]
+impl Reassembler {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forces the codec ("h264", "h265", "vp9" or "av1") instead of guessing it.
+    /// Packets buffered so far are discarded.
+    #[wasm_bindgen(js_name = setCodec)]
+    pub fn set_codec(&mut self, name: &str) -> Result<(), JsError> {
+        let codec: Codec = name.parse()?;
+        self.inner = FrameReassembler::builder()
+            .codec(codec)
+            .build()
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(())
+    }
+
+    pub fn codec(&self) -> String {
+        codec_name(self.inner.codec())
+    }
+
+    /// Pushes one RTP datagram; returns the frame bytes once a frame completes.
+    pub fn push(&mut self, packet: &[u8]) -> Result<Option<Vec<u8>>, JsError> {
+        let pkt = RtpPacket::parse(packet).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(self.inner.push_packet(&pkt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::rtp_packet;
+
+    #[test]
+    fn reassembler_wrapper_emits_frames() {
+        let mut r = Reassembler::new();
+        r.set_codec("H264").ok().unwrap();
+        assert_eq!(r.codec(), "h264");
+        assert_eq!(
+            r.push(&rtp_packet(&[0x7C, 0x85, 0x01], false, 1))
+                .ok()
+                .unwrap(),
+            None
+        );
+        let frame = r
+            .push(&rtp_packet(&[0x7C, 0x45, 0x02], true, 2))
+            .ok()
+            .unwrap();
+        assert_eq!(frame, Some(vec![0, 0, 0, 1, 0x65, 0x01, 0x02]));
+    }
+
+    #[test]
+    fn analyzer_and_parse_wrappers() {
+        let packet = rtp_packet(&[0x65, 0x01], false, 9);
+        let info = parse_packet(&packet).ok().unwrap();
+        assert_eq!(info.sequence_number, 9);
+        assert_eq!(info.payload_len, 2);
+
+        let mut a = Analyzer::new();
+        assert_eq!(a.analyze(&packet).ok().unwrap(), "start");
+        assert_eq!(a.codec(), "h264");
+    }
+}
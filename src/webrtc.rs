@@ -0,0 +1,363 @@
+//! Interop with the webrtc-rs `rtp` crate.
+//!
+//! Enabled with the `webrtc` feature. Provides conversions between this
+//! crate's packet types and `rtp::packet::Packet` / `rtp::header::Header`, and
+//! [`ReassemblingDepacketizer`], an `rtp::packetizer::Depacketizer` that uses
+//! the same payload handling as [`FrameReassembler`] so webrtc-rs sample
+//! builders produce identical frame bytes.
+
+use crate::{
+    codecs::{
+        av1::parse_av1_payload_header,
+        avc::{parse_avc_payload_header, AvcNalKind},
+        custom::CodecRegistry,
+        hevc::{parse_hevc_payload_header, HevcNalKind},
+        vp9::Vp9PayloadDesc,
+        Codec,
+    },
+    reassemble::{FrameReassembler, MalformedReason},
+    rtp::{CsrcList, RtpExtension, RtpHeader, RtpPacket},
+};
+use alloc::sync::Arc;
+use bytes::Bytes;
+use rtp::packetizer::Depacketizer;
+
+/// Reasons a webrtc-rs header cannot be represented as an [`RtpHeader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    InvalidVersion(u8),
+    TooManyCsrcs(usize),
+}
+
+impl core::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConversionError::InvalidVersion(v) => write!(f, "invalid rtp version {}", v),
+            ConversionError::TooManyCsrcs(n) => write!(f, "{} csrcs exceed the limit of 15", n),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+// Size of the header extension block (profile + length + padded data) once marshaled.
+fn extension_block_len(header: &rtp::header::Header) -> usize {
+    let data = header.get_extension_payload_len() + header.extensions_padding;
+    data.div_ceil(4) * 4
+}
+
+/// Header extension elements are not carried over: `RtpHeader` only records
+/// where the extension block sits in the original buffer, not its contents.
+impl From<&RtpHeader> for rtp::header::Header {
+    fn from(h: &RtpHeader) -> Self {
+        rtp::header::Header {
+            version: h.version,
+            padding: h.padding,
+            extension: false,
+            marker: h.marker,
+            payload_type: h.payload_type,
+            sequence_number: h.sequence_number,
+            timestamp: h.timestamp,
+            ssrc: h.ssrc,
+            csrc: h.csrcs.to_vec(),
+            extension_profile: 0,
+            extensions: Vec::new(),
+            extensions_padding: 0,
+        }
+    }
+}
+
+/// The extension location is computed as it would appear in the marshaled packet.
+impl TryFrom<&rtp::header::Header> for RtpHeader {
+    type Error = ConversionError;
+
+    fn try_from(h: &rtp::header::Header) -> Result<Self, Self::Error> {
+        if h.version != 2 {
+            return Err(ConversionError::InvalidVersion(h.version));
+        }
+        let csrcs = CsrcList::try_from(h.csrc.as_slice())
+            .map_err(|e| ConversionError::TooManyCsrcs(e.0))?;
+        let extension_header = h.extension.then(|| {
+            let data_len = extension_block_len(h);
+            RtpExtension {
+                profile: h.extension_profile,
+                length_words: (data_len / 4) as u16,
+                data_offset: 12 + 4 * h.csrc.len() + 4,
+                data_len,
+            }
+        });
+        Ok(RtpHeader {
+            version: h.version,
+            padding: h.padding,
+            extension: h.extension,
+            csrc_count: h.csrc.len() as u8,
+            marker: h.marker,
+            payload_type: h.payload_type,
+            sequence_number: h.sequence_number,
+            timestamp: h.timestamp,
+            ssrc: h.ssrc,
+            csrcs,
+            extension_header,
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a rtp::packet::Packet> for RtpPacket<'a> {
+    type Error = ConversionError;
+
+    fn try_from(p: &'a rtp::packet::Packet) -> Result<Self, Self::Error> {
+        let header = RtpHeader::try_from(&p.header)?;
+        let payload_offset = match &header.extension_header {
+            Some(ext) => ext.data_offset + ext.data_len,
+            None => 12 + 4 * header.csrcs.len(),
+        };
+        Ok(RtpPacket {
+            header,
+            payload_offset,
+            payload: &p.payload,
+        })
+    }
+}
+
+impl From<&RtpPacket<'_>> for rtp::packet::Packet {
+    fn from(p: &RtpPacket<'_>) -> Self {
+        rtp::packet::Packet {
+            header: (&p.header).into(),
+            payload: Bytes::copy_from_slice(p.payload),
+        }
+    }
+}
+
+/// `Depacketizer` for webrtc-rs sample builders backed by this crate's
+/// payload handling. Each call returns the bytes the payload contributes to
+/// the assembled frame (start codes and reconstructed NAL headers included).
+/// Custom codecs use their registered depacketizer (see
+/// [`set_codec_registry`](Self::set_codec_registry)) and are otherwise
+/// passed through as they are, like unknown ones.
+#[derive(Debug, Clone)]
+pub struct ReassemblingDepacketizer {
+    codec: Codec,
+    // Depacketizer of a custom codec.
+    custom: Option<Arc<dyn crate::codecs::custom::Depacketizer>>,
+    fu_open: bool,
+    // AV1 OBU fragment waiting for its continuation
+    obu_pending: Vec<u8>,
+}
+
+impl ReassemblingDepacketizer {
+    pub fn new(codec: Codec) -> Self {
+        Self {
+            codec,
+            custom: None,
+            fu_open: false,
+            obu_pending: Vec::new(),
+        }
+    }
+
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Looks up the depacketizer of a custom codec in `registry`.
+    pub fn set_codec_registry(&mut self, registry: &CodecRegistry) {
+        self.custom = registry.get(self.codec).cloned();
+    }
+}
+
+impl Depacketizer for ReassemblingDepacketizer {
+    fn depacketize(&mut self, b: &Bytes) -> Result<Bytes, rtp::Error> {
+        if b.is_empty() {
+            return Err(rtp::Error::ErrShortPacket);
+        }
+        let mut out = Vec::with_capacity(b.len() + 4);
+        let mut incomplete = false;
+        let appended = match self.codec {
+            Codec::Avc => {
+                let appended = FrameReassembler::append_avc_payload(
+                    b,
+                    usize::MAX,
+                    &mut out,
+                    &mut self.fu_open,
+                    &mut incomplete,
+                );
+                if let Ok((
+                    AvcNalKind::FuA { end: true, .. } | AvcNalKind::FuB { end: true, .. },
+                    _,
+                )) = parse_avc_payload_header(b)
+                {
+                    self.fu_open = false;
+                }
+                appended
+            }
+            Codec::Hevc => {
+                let appended = FrameReassembler::append_hevc_payload(
+                    b,
+                    false,
+                    usize::MAX,
+                    &mut out,
+                    &mut self.fu_open,
+                    &mut incomplete,
+                );
+                if let Ok((HevcNalKind::Fu { end: true, .. }, _)) = parse_hevc_payload_header(b) {
+                    self.fu_open = false;
+                }
+                appended
+            }
+            Codec::Vp9 => FrameReassembler::append_vp9_payload(b, &mut out),
+            Codec::Av1 => FrameReassembler::append_av1_payload(
+                b,
+                usize::MAX,
+                &mut out,
+                &mut self.obu_pending,
+                &mut incomplete,
+            ),
+            Codec::Custom(_) if self.custom.is_some() => {
+                let custom = self.custom.as_ref().expect("checked above");
+                custom.depacketize(b, &mut out)
+            }
+            Codec::Unknown | Codec::Custom(_) => {
+                out.extend_from_slice(b);
+                Ok(())
+            }
+        };
+        match appended {
+            Err(MalformedReason::UnsupportedPacketization) => {
+                return Err(rtp::Error::ErrUnhandledNaluType)
+            }
+            Err(_) => return Err(rtp::Error::ErrShortPacket),
+            Ok(()) => {}
+        }
+        if incomplete {
+            // A continuation fragment without its start cannot be decoded.
+            return Err(rtp::Error::ErrShortPacket);
+        }
+        Ok(Bytes::from(out))
+    }
+
+    fn is_partition_head(&self, payload: &Bytes) -> bool {
+        match self.codec {
+            Codec::Avc => match parse_avc_payload_header(payload) {
+                Ok((AvcNalKind::FuA { start, .. } | AvcNalKind::FuB { start, .. }, _)) => start,
+                Ok(_) => true,
+                Err(_) => false,
+            },
+            Codec::Hevc => match parse_hevc_payload_header(payload) {
+                Ok((HevcNalKind::Fu { start, .. }, _)) => start,
+                Ok(_) => true,
+                Err(_) => false,
+            },
+            Codec::Vp9 => Vp9PayloadDesc::parse(payload)
+                .map(|(desc, _)| desc.b_bit)
+                .unwrap_or(false),
+            Codec::Av1 => parse_av1_payload_header(payload)
+                .map(|(hdr, _)| !hdr.z_bit)
+                .unwrap_or(false),
+            // Without a way to tell, every packet may start a sample and the
+            // marker ends it.
+            Codec::Custom(_) => self
+                .custom
+                .as_ref()
+                .is_none_or(|custom| custom.starts_frame(payload)),
+            Codec::Unknown => true,
+        }
+    }
+
+    fn is_partition_tail(&self, marker: bool, _payload: &Bytes) -> bool {
+        marker
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{avc, fixtures, RtpPacketBuilder, StreamBuilder};
+
+    #[test]
+    fn header_and_packet_conversions_round_trip() {
+        let buf = RtpPacketBuilder::new()
+            .marker(true)
+            .sequence_number(10)
+            .timestamp(900)
+            .ssrc(5)
+            .csrc(6)
+            .payload(&[1, 2, 3])
+            .build();
+        let ours = RtpPacket::parse(&buf).unwrap();
+        let theirs = rtp::packet::Packet::from(&ours);
+        assert_eq!(theirs.header.csrc, vec![6]);
+        assert_eq!(&theirs.payload[..], &[1, 2, 3]);
+
+        let back = RtpPacket::try_from(&theirs).unwrap();
+        assert_eq!(back, ours);
+    }
+
+    #[test]
+    fn extension_location_matches_marshaled_layout() {
+        let mut header = rtp::header::Header {
+            version: 2,
+            extension: true,
+            extension_profile: 0xBEDE,
+            ..Default::default()
+        };
+        header
+            .set_extension(1, Bytes::from_static(&[0xAA, 0xBB]))
+            .unwrap();
+        let ours = RtpHeader::try_from(&header).unwrap();
+        let ext = ours.extension_header.unwrap();
+        assert_eq!(ext.profile, 0xBEDE);
+        assert_eq!((ext.data_offset, ext.data_len), (16, 4));
+
+        header.version = 1;
+        assert_eq!(
+            RtpHeader::try_from(&header),
+            Err(ConversionError::InvalidVersion(1))
+        );
+    }
+
+    #[test]
+    fn depacketizer_matches_frame_reassembler() {
+        let packets = StreamBuilder::new(1)
+            .frame(&avc::fu_a(fixtures::AVC_IDR, 5))
+            .build();
+        let mut d = ReassemblingDepacketizer::new(Codec::Avc);
+        let mut frame = Vec::new();
+        for (i, buf) in packets.iter().enumerate() {
+            let pkt = RtpPacket::parse(buf).unwrap();
+            let payload = Bytes::copy_from_slice(pkt.payload);
+            assert_eq!(d.is_partition_head(&payload), i == 0);
+            assert_eq!(
+                d.is_partition_tail(pkt.header.marker, &payload),
+                i + 1 == packets.len()
+            );
+            frame.extend_from_slice(&d.depacketize(&payload).unwrap());
+        }
+        assert_eq!(frame, fixtures::annex_b(&[fixtures::AVC_IDR]));
+
+        // A continuation fragment without a preceding start is rejected.
+        let orphan = RtpPacket::parse(&packets[1]).unwrap();
+        assert!(d
+            .depacketize(&Bytes::copy_from_slice(orphan.payload))
+            .is_err());
+    }
+
+    #[test]
+    fn unknown_codec_samples_end_at_the_marker() {
+        let packets = StreamBuilder::new(1)
+            .frame(&[vec![1, 2], vec![3]])
+            .frame(&[vec![4, 5, 6]])
+            .build();
+        let mut d = ReassemblingDepacketizer::new(Codec::Unknown);
+        let mut samples = Vec::new();
+        let mut sample = Vec::new();
+        for buf in &packets {
+            let pkt = RtpPacket::parse(buf).unwrap();
+            let payload = Bytes::copy_from_slice(pkt.payload);
+            assert!(d.is_partition_head(&payload));
+            sample.extend_from_slice(&d.depacketize(&payload).unwrap());
+            if d.is_partition_tail(pkt.header.marker, &payload) {
+                samples.push(core::mem::take(&mut sample));
+            }
+        }
+        assert_eq!(samples, [vec![1, 2, 3], vec![4, 5, 6]]);
+    }
+}
@@ -0,0 +1,9 @@
+//! AV1 (RTP payload format v1.0): aggregation headers and OBU fragments.
+
+use super::check;
+use rtpar::{codecs::Codec, reassemble::ReassemblerConfig};
+
+#[test]
+fn corpus() {
+    check("av1", Codec::Av1, ReassemblerConfig::default());
+}
@@ -0,0 +1,9 @@
+//! H.264 (RFC 6184): single NAL units, STAP-A and FU-A.
+
+use super::check;
+use rtpar::{codecs::Codec, reassemble::ReassemblerConfig};
+
+#[test]
+fn corpus() {
+    check("avc", Codec::Avc, ReassemblerConfig::default());
+}
@@ -0,0 +1,176 @@
+//! Synthesized seed captures, `synth-*` in each corpus. Recordings of real
+//! traffic sit next to them under other names and are left alone. After a
+//! change here, rewrite them with
+//! `cargo test --features capture --test it -- --ignored generate`.
+
+use super::corpus_dir;
+use rtpar::{
+    codecs::Codec,
+    conformance::Corpus,
+    packetize::{FramePacketizer, HevcPacketizer, PacketizerConfig},
+    reassemble::ReassemblerConfig,
+    sim::Impairments,
+    test_utils::{
+        capture,
+        fixtures::{annex_b, AVC_PPS, AVC_SPS, HEVC_PPS, HEVC_SPS, HEVC_VPS},
+    },
+};
+use std::{fs, time::Duration};
+
+const FRAMES: usize = 12;
+const KEYFRAME_INTERVAL: usize = 6;
+const TIMESTAMP_STEP: u32 = 3000;
+
+// Deterministic frame bodies without zero bytes, so they never form start
+// codes or need emulation prevention.
+struct Bytes(u64);
+
+impl Bytes {
+    fn take(&mut self, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1);
+                ((self.0 >> 33) % 255) as u8 + 1
+            })
+            .collect()
+    }
+}
+
+fn leb128(mut value: usize, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn obu(header: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![header | 0x02];
+    leb128(payload.len(), &mut out);
+    out.extend_from_slice(payload);
+    out
+}
+
+// Frame `i` of a stream: keyframes of about 700 bytes, inter frames of
+// about 150, all fragmented at the 300 byte MTU below.
+fn frame(codec: Codec, i: usize, bytes: &mut Bytes) -> Vec<u8> {
+    let key = i.is_multiple_of(KEYFRAME_INTERVAL);
+    let body = bytes.take(if key { 700 } else { 150 + i * 7 });
+    match (codec, key) {
+        // Slice headers of the fixtures, starting at the first macroblock.
+        (Codec::Avc, true) => {
+            annex_b(&[AVC_SPS, AVC_PPS, &[&[0x65, 0x88, 0x84][..], &body].concat()])
+        }
+        (Codec::Avc, false) => annex_b(&[&[&[0x41, 0x9A][..], &body].concat()]),
+        (Codec::Hevc, true) => annex_b(&[
+            HEVC_VPS,
+            HEVC_SPS,
+            HEVC_PPS,
+            &[&[0x26, 0x01][..], &body].concat(),
+        ]),
+        (Codec::Hevc, false) => annex_b(&[&[&[0x02, 0x01][..], &body].concat()]),
+        (Codec::Vp9, true) => [&[0x82, 0x49, 0x83, 0x42][..], &body].concat(),
+        (Codec::Vp9, false) => [&[0x86][..], &body].concat(),
+        (Codec::Av1, true) => {
+            let sequence_header = [0x00, 0x00, 0x00, 0x02, 0xAF, 0xFF, 0x9F, 0xFE];
+            [obu(0x08, &sequence_header), obu(0x30, &body)].concat()
+        }
+        (Codec::Av1, false) => obu(0x30, &body),
+        (codec, _) => unreachable!("no seed captures for {}", codec),
+    }
+}
+
+fn packetize(codec: Codec, donl: bool, first_seq: u16, first_ts: u32) -> Vec<Vec<u8>> {
+    let config = PacketizerConfig {
+        mtu: 300,
+        ssrc: 0x5EED,
+        initial_sequence_number: first_seq,
+        ..PacketizerConfig::default()
+    };
+    let mut packetizer = if donl {
+        FramePacketizer::with_packetizer(codec, Box::new(HevcPacketizer::with_donl(0)), config)
+    } else {
+        FramePacketizer::new(codec, config)
+    };
+    let mut bytes = Bytes(0x5EED);
+    (0..FRAMES)
+        .flat_map(|i| {
+            let ts = first_ts.wrapping_add(i as u32 * TIMESTAMP_STEP);
+            packetizer.packetize(&frame(codec, i, &mut bytes), ts)
+        })
+        .collect()
+}
+
+fn write_corpus(name: &str, codec: Codec, donl: bool, golden: &str, config: ReassemblerConfig) {
+    let dir = corpus_dir(name);
+    fs::create_dir_all(&dir).unwrap();
+    let clean = packetize(codec, donl, 0, 0);
+    let cases = [
+        ("clean", Impairments::default()),
+        (
+            "loss",
+            Impairments {
+                seed: 1,
+                loss: 0.05,
+                ..Impairments::default()
+            },
+        ),
+        (
+            "reorder",
+            Impairments {
+                seed: 2,
+                reorder: 0.2,
+                reorder_depth: 3,
+                duplicate: 0.02,
+                jitter: Duration::from_micros(500),
+                ..Impairments::default()
+            },
+        ),
+    ];
+    for (case, impairments) in cases {
+        let arrivals: Vec<_> = impairments
+            .apply(&clean)
+            .packets
+            .into_iter()
+            .map(|p| (p.arrival, p.data))
+            .collect();
+        write_case(&dir, &format!("synth-{}", case), &arrivals, golden);
+    }
+    // Sequence numbers and timestamps wrap in the middle of the stream.
+    let wrap = packetize(codec, donl, 65530, u32::MAX - 5 * TIMESTAMP_STEP);
+    let arrivals: Vec<_> = wrap
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| (Duration::from_millis(i as u64), p))
+        .collect();
+    write_case(&dir, "synth-wrap", &arrivals, golden);
+    Corpus::load(&dir).unwrap().bless_with(config).unwrap();
+}
+
+fn write_case(dir: &std::path::Path, stem: &str, arrivals: &[(Duration, Vec<u8>)], golden: &str) {
+    fs::write(
+        dir.join(stem).with_extension("pcap"),
+        capture::pcap(arrivals),
+    )
+    .unwrap();
+    fs::write(dir.join(stem).with_extension(golden), b"").unwrap();
+}
+
+#[test]
+#[ignore = "rewrites tests/captures"]
+fn generate() {
+    let default = ReassemblerConfig::default();
+    write_corpus("avc", Codec::Avc, false, "h264", default);
+    write_corpus("hevc", Codec::Hevc, false, "h265", default);
+    write_corpus("vp9", Codec::Vp9, false, "vp9", default);
+    write_corpus("av1", Codec::Av1, false, "obu", default);
+    let donl = ReassemblerConfig {
+        hevc_donl: true,
+        ..default
+    };
+    write_corpus("hevc-donl", Codec::Hevc, true, "h265", donl);
+}
@@ -0,0 +1,19 @@
+//! H.265 (RFC 7798): aggregation and fragmentation units, with and without
+//! DONL fields.
+
+use super::check;
+use rtpar::{codecs::Codec, reassemble::ReassemblerConfig};
+
+#[test]
+fn corpus() {
+    check("hevc", Codec::Hevc, ReassemblerConfig::default());
+}
+
+#[test]
+fn donl_corpus() {
+    let config = ReassemblerConfig {
+        hevc_donl: true,
+        ..ReassemblerConfig::default()
+    };
+    check("hevc-donl", Codec::Hevc, config);
+}
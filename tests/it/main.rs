@@ -0,0 +1,38 @@
+//! Golden-capture integration tests: `cargo test --features capture`.
+//!
+//! Every directory under `tests/captures` is a conformance corpus (see
+//! `rtpar::conformance`) of one codec and reassembler configuration, with
+//! clean, lossy, reordered and wrapping streams. Set `RTPAR_BLESS=1` to
+//! rewrite the golden files after an intended output change, then review
+//! their diff like code.
+
+mod av1;
+mod avc;
+mod generate;
+mod hevc;
+mod vp9;
+
+use rtpar::{codecs::Codec, conformance::Corpus, reassemble::ReassemblerConfig};
+use std::path::{Path, PathBuf};
+
+fn corpus_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/captures")
+        .join(name)
+}
+
+/// Runs the corpus `name` with `config`, blessing it first under
+/// `RTPAR_BLESS`; every case must be of `codec`.
+fn check(name: &str, codec: Codec, config: ReassemblerConfig) {
+    let dir = corpus_dir(name);
+    let corpus = Corpus::load(&dir).unwrap_or_else(|e| panic!("{}", e));
+    assert!(!corpus.cases.is_empty(), "no captures in {}", dir.display());
+    for case in &corpus.cases {
+        assert_eq!(case.codec, codec, "{}/{}", name, case.name);
+    }
+    if std::env::var_os("RTPAR_BLESS").is_some() {
+        corpus.bless_with(config).unwrap();
+    }
+    let report = corpus.run_with(config);
+    assert!(report.passed(), "{}:\n{}", name, report);
+}
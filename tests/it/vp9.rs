@@ -0,0 +1,9 @@
+//! VP9 (RFC 9628) in non-flexible mode.
+
+use super::check;
+use rtpar::{codecs::Codec, reassemble::ReassemblerConfig};
+
+#[test]
+fn corpus() {
+    check("vp9", Codec::Vp9, ReassemblerConfig::default());
+}